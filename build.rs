@@ -1,5 +1,102 @@
+use std::path::PathBuf;
+
 fn main() {
     cfg_aliases::cfg_aliases! {
         wasm: { target_arch = "wasm32" }
     }
+
+    set_build_info();
+
+    if std::env::var_os("CARGO_FEATURE_EMBEDDED_ASSETS").is_some() {
+        pack_embedded_assets();
+    }
+}
+
+/// Set the `SKIPNGO_GIT_HASH` and `SKIPNGO_BUILD_DATE` environment variables used by the engine's
+/// `BuildInfo`
+fn set_build_info() {
+    // Rebuild when the checked out commit changes
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let git_head = manifest_dir.join(".git/HEAD");
+    if git_head.exists() {
+        println!("cargo:rerun-if-changed={}", git_head.display());
+        if let Some(head_ref) = std::fs::read_to_string(&git_head)
+            .ok()
+            .and_then(|x| x.strip_prefix("ref: ").map(|x| x.trim().to_owned()))
+        {
+            println!(
+                "cargo:rerun-if-changed={}",
+                manifest_dir.join(".git").join(head_ref).display()
+            );
+        }
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let git_hash = std::process::Command::new("git")
+        .args(&["rev-parse", "--short", "HEAD"])
+        .current_dir(&manifest_dir)
+        .output()
+        .ok()
+        .filter(|x| x.status.success())
+        .and_then(|x| String::from_utf8(x.stdout).ok())
+        .map(|x| x.trim().to_owned())
+        .unwrap_or_else(|| "unknown".into());
+    println!("cargo:rustc-env=SKIPNGO_GIT_HASH={}", git_hash);
+
+    // Use the date from `SOURCE_DATE_EPOCH` for reproducible builds
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|x| x.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        });
+    let (year, month, day) = civil_date((timestamp / 86400) as i64);
+    println!(
+        "cargo:rustc-env=SKIPNGO_BUILD_DATE={:04}-{:02}-{:02}",
+        year, month, day
+    );
+}
+
+/// Convert a number of days since the Unix epoch to a year, month, and day
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// Pack the asset directory into an asset pack in the build output so it can be included in the
+/// executable
+fn pack_embedded_assets() {
+    println!("cargo:rerun-if-env-changed=SKIPNGO_EMBEDDED_ASSETS");
+
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let asset_dir = manifest_dir
+        .join(std::env::var("SKIPNGO_EMBEDDED_ASSETS").unwrap_or_else(|_| "assets".into()));
+    println!("cargo:rerun-if-changed={}", asset_dir.display());
+
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let file = std::fs::File::create(out_dir.join("embedded-assets.skipngo")).unwrap();
+    let mut builder = tar::Builder::new(file);
+    builder
+        .append_dir_all(".", &asset_dir)
+        .unwrap_or_else(|e| panic!("Could not embed `{}`: {}", asset_dir.display(), e));
+    builder.finish().unwrap();
 }
@@ -0,0 +1,285 @@
+//! Asset IO used to download assets on web, with retries, progress tracking, and cache busting
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, RwLock,
+};
+
+use bevy::{asset::AssetIo, prelude::*};
+
+/// Resource tracking the assets being downloaded, shown on the loading screen
+///
+/// This is only added on web, where assets are downloaded over HTTP.
+#[derive(Default, Clone)]
+pub struct AssetDownloadProgress(Arc<DownloadCounts>);
+
+#[derive(Default)]
+struct DownloadCounts {
+    requested: AtomicUsize,
+    finished: AtomicUsize,
+    retries: AtomicUsize,
+}
+
+impl AssetDownloadProgress {
+    /// The number of assets that have been requested
+    pub fn requested(&self) -> usize {
+        self.0.requested.load(Ordering::Relaxed)
+    }
+
+    /// The number of requested assets that have finished downloading or failed
+    pub fn finished(&self) -> usize {
+        self.0.finished.load(Ordering::Relaxed)
+    }
+
+    /// The number of times a download has been retried
+    pub fn retries(&self) -> usize {
+        self.0.retries.load(Ordering::Relaxed)
+    }
+}
+
+/// Resource containing the version of the game's assets, which is added to asset URLs on web so
+/// that browsers don't use old cached assets after the game is updated
+///
+/// Set from the `asset-version` in the game info once it loads.
+#[derive(Default, Clone)]
+pub struct AssetVersion(pub Arc<RwLock<Option<String>>>);
+
+/// Create the asset IO for the platform, which is the Bevy default on desktop and the
+/// [`HttpAssetIo`] on web
+pub fn platform_asset_io(app: &mut AppBuilder) -> Box<dyn AssetIo> {
+    #[cfg(wasm)]
+    {
+        let root = app
+            .world()
+            .get_resource::<bevy::asset::AssetServerSettings>()
+            .map(|x| x.asset_folder.clone())
+            .unwrap_or_else(|| "assets".into());
+        let progress = AssetDownloadProgress::default();
+        let version = AssetVersion::default();
+        app.insert_resource(progress.clone())
+            .insert_resource(version.clone());
+
+        Box::new(http::HttpAssetIo {
+            root,
+            progress,
+            version,
+        })
+    }
+
+    #[cfg(not(wasm))]
+    bevy::asset::create_platform_default_asset_io(app)
+}
+
+/// Plugin that replaces the asset server's IO with the [`platform_asset_io`]. Must be added before
+/// the Bevy `AssetPlugin`.
+pub struct PlatformAssetIoPlugin;
+
+impl Plugin for PlatformAssetIoPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let task_pool = app
+            .world()
+            .get_resource::<bevy::tasks::IoTaskPool>()
+            .expect("IoTaskPool resource not found")
+            .0
+            .clone();
+        let asset_io = platform_asset_io(app);
+
+        app.insert_resource(AssetServer::with_boxed_io(asset_io, task_pool));
+    }
+}
+
+#[cfg(wasm)]
+pub use http::HttpAssetIo;
+
+#[cfg(wasm)]
+mod http {
+    use std::path::{Path, PathBuf};
+
+    use bevy::{
+        asset::{AssetIo, AssetIoError},
+        prelude::*,
+        utils::BoxedFuture,
+    };
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{AbortController, RequestCache, RequestInit, Response};
+
+    use super::{AssetDownloadProgress, AssetVersion, Ordering};
+
+    /// The number of times to retry a failed download before giving up
+    const MAX_RETRIES: u32 = 4;
+    /// The delay before the first retry in milliseconds, which doubles with each retry
+    const RETRY_DELAY_MS: i32 = 500;
+    /// How long to wait for the server to respond to a download before trying again, in
+    /// milliseconds
+    const RESPONSE_TIMEOUT_MS: i32 = 30_000;
+    /// How long to wait for the body of a download before trying again, in milliseconds, which is
+    /// long enough for an asset pack on a slow connection
+    const BODY_TIMEOUT_MS: i32 = 300_000;
+
+    /// Asset IO that downloads assets over HTTP, retrying failed downloads
+    pub struct HttpAssetIo {
+        pub(super) root: String,
+        pub(super) progress: AssetDownloadProgress,
+        pub(super) version: AssetVersion,
+    }
+
+    /// An error downloading a file
+    enum FetchError {
+        /// The file doesn't exist, so there's no point in trying again
+        NotFound,
+        /// The download failed in a way that might work if we try again
+        Failed(String),
+    }
+
+    impl AssetIo for HttpAssetIo {
+        fn load_path<'a>(
+            &'a self,
+            path: &'a Path,
+        ) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+            Box::pin(async move {
+                let counts = &self.progress.0;
+                counts.requested.fetch_add(1, Ordering::Relaxed);
+
+                // Always check for a new game info, and bust the cache for everything else
+                let is_game_info = path.to_string_lossy().ends_with(".game.yaml");
+                let mut url = format!("{}/{}", self.root, path.to_string_lossy());
+                if !is_game_info {
+                    if let Some(version) = self.version.0.read().unwrap().as_ref() {
+                        url.push_str(&format!("?v={}", version));
+                    }
+                }
+
+                let mut attempt = 0;
+                let result = loop {
+                    match fetch(&url, is_game_info).await {
+                        Ok(bytes) => break Ok(bytes),
+                        Err(FetchError::NotFound) => {
+                            break Err(AssetIoError::NotFound(path.to_owned()))
+                        }
+                        Err(FetchError::Failed(error)) if attempt < MAX_RETRIES => {
+                            warn!(%url, %error, "Asset download failed, retrying");
+                            counts.retries.fetch_add(1, Ordering::Relaxed);
+                            sleep(RETRY_DELAY_MS << attempt).await;
+                            attempt += 1;
+                        }
+                        Err(FetchError::Failed(error)) => {
+                            break Err(AssetIoError::Io(std::io::Error::new(
+                                std::io::ErrorKind::Other,
+                                error,
+                            )))
+                        }
+                    }
+                };
+
+                counts.finished.fetch_add(1, Ordering::Relaxed);
+                result
+            })
+        }
+
+        fn read_directory(
+            &self,
+            _path: &Path,
+        ) -> Result<Box<dyn Iterator<Item = PathBuf>>, AssetIoError> {
+            Ok(Box::new(std::iter::empty::<PathBuf>()))
+        }
+
+        fn is_directory(&self, _path: &Path) -> bool {
+            false
+        }
+
+        fn watch_path_for_changes(&self, _path: &Path) -> Result<(), AssetIoError> {
+            Ok(())
+        }
+
+        fn watch_for_changes(&self) -> Result<(), AssetIoError> {
+            warn!("Watching for changes is not supported on web");
+            Ok(())
+        }
+    }
+
+    /// Download a file, giving up if it takes too long
+    async fn fetch(url: &str, no_cache: bool) -> Result<Vec<u8>, FetchError> {
+        let window = web_sys::window().unwrap();
+        let controller =
+            AbortController::new().map_err(|e| FetchError::Failed(format!("{:?}", e)))?;
+        let mut init = RequestInit::new();
+        init.signal(Some(&controller.signal()));
+        if no_cache {
+            // Make the browser check with the server for a newer version
+            init.cache(RequestCache::NoCache);
+        }
+        let failed = |e| {
+            if controller.signal().aborted() {
+                FetchError::Failed("Timed out".into())
+            } else {
+                FetchError::Failed(format!("{:?}", e))
+            }
+        };
+
+        let timeout = Timeout::new(&controller, RESPONSE_TIMEOUT_MS);
+        let response: Response = JsFuture::from(window.fetch_with_str_and_init(url, &init))
+            .await
+            .map_err(failed)?
+            .dyn_into()
+            .unwrap();
+        drop(timeout);
+
+        if response.status() == 404 {
+            return Err(FetchError::NotFound);
+        } else if !response.ok() {
+            return Err(FetchError::Failed(format!("HTTP {}", response.status())));
+        }
+
+        let _timeout = Timeout::new(&controller, BODY_TIMEOUT_MS);
+        let data = JsFuture::from(response.array_buffer().map_err(failed)?)
+            .await
+            .map_err(failed)?;
+
+        Ok(js_sys::Uint8Array::new(&data).to_vec())
+    }
+
+    /// Aborts a download after a delay unless it is dropped first
+    struct Timeout {
+        handle: i32,
+        _abort: Closure<dyn FnMut()>,
+    }
+
+    impl Timeout {
+        fn new(controller: &AbortController, ms: i32) -> Self {
+            let controller = controller.clone();
+            let abort = Closure::wrap(Box::new(move || controller.abort()) as Box<dyn FnMut()>);
+            let handle = web_sys::window()
+                .unwrap()
+                .set_timeout_with_callback_and_timeout_and_arguments_0(
+                    abort.as_ref().unchecked_ref(),
+                    ms,
+                )
+                .unwrap();
+
+            Self {
+                handle,
+                _abort: abort,
+            }
+        }
+    }
+
+    impl Drop for Timeout {
+        fn drop(&mut self) {
+            web_sys::window()
+                .unwrap()
+                .clear_timeout_with_handle(self.handle);
+        }
+    }
+
+    /// Wait for the given number of milliseconds
+    async fn sleep(ms: i32) {
+        let promise = js_sys::Promise::new(&mut |resolve, _| {
+            web_sys::window()
+                .unwrap()
+                .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms)
+                .unwrap();
+        });
+        JsFuture::from(promise).await.ok();
+    }
+}
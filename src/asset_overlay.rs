@@ -0,0 +1,249 @@
+//! Support for mods, which are asset directories laid over the game's assets so that they can
+//! replace individual files without changing the base game
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use bevy::{
+    asset::{AssetIo, AssetIoError, AssetServer},
+    prelude::*,
+    tasks::IoTaskPool,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::asset_io::platform_asset_io;
+
+/// The directory that mods are automatically loaded from, relative to the current directory
+pub const MODS_DIR: &str = "mods";
+
+/// The name of the file in a mod directory that describes the mod
+pub const MANIFEST_FILE: &str = "pack.yaml";
+
+/// The description of a mod, loaded from the [`MANIFEST_FILE`] in its directory
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct ContentPackManifest {
+    /// The name of the mod, which defaults to the name of its directory
+    pub name: String,
+    pub version: String,
+    pub author: String,
+    pub description: String,
+}
+
+/// A mod that was found when the game started
+#[derive(Debug, Clone)]
+pub struct ContentPack {
+    /// The name of the mod's directory, which is used to identify it in the user settings
+    pub id: String,
+    /// The mod's directory
+    pub dir: PathBuf,
+    pub manifest: ContentPackManifest,
+}
+
+/// Resource containing the mods that were found when the game started, and which of them are
+/// disabled
+///
+/// Disabling a mod only affects assets that are loaded afterward, so the game has to be restarted
+/// for it to fully take effect. The user settings are loaded after the game info, so a mod's game
+/// info is used even if the mod is disabled.
+#[derive(Default, Clone)]
+pub struct ContentPacks {
+    pub packs: Vec<ContentPack>,
+    disabled: Arc<RwLock<HashSet<String>>>,
+}
+
+impl ContentPacks {
+    /// Set the IDs of the mods that shouldn't be loaded
+    pub fn set_disabled<'a>(&self, ids: impl IntoIterator<Item = &'a String>) {
+        *self.disabled.write().unwrap() = ids.into_iter().cloned().collect();
+    }
+
+    /// Get the directories of the enabled mods, from lowest to highest priority
+    fn enabled_dirs(&self) -> Vec<&PathBuf> {
+        let disabled = self.disabled.read().unwrap();
+        self.packs
+            .iter()
+            .filter(|pack| !disabled.contains(&pack.id))
+            .map(|pack| &pack.dir)
+            .collect()
+    }
+
+    /// Get the path to an asset in the highest priority enabled mod that has it, if any mod
+    /// replaces it
+    pub fn overlay_path(&self, path: &Path) -> Option<PathBuf> {
+        self.enabled_dirs()
+            .into_iter()
+            .rev()
+            .map(|dir| dir.join(path))
+            .find(|x| x.is_file())
+    }
+}
+
+/// Plugin that replaces the asset server's IO with an [`OverlayAssetIo`]. Must be added before the
+/// Bevy `AssetPlugin`.
+pub struct OverlayAssetIoPlugin {
+    /// The overlay directories, from lowest to highest priority
+    pub overlays: Vec<PathBuf>,
+}
+
+impl Plugin for OverlayAssetIoPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let task_pool = app
+            .world()
+            .get_resource::<IoTaskPool>()
+            .expect("IoTaskPool resource not found")
+            .0
+            .clone();
+        let inner = platform_asset_io(app);
+        let asset_io = overlay_asset_io(app, inner, &self.overlays);
+
+        app.insert_resource(AssetServer::with_boxed_io(asset_io, task_pool));
+    }
+}
+
+/// Lay the given overlay directories over an asset IO, from lowest to highest priority, and add
+/// the [`ContentPacks`] resource for them
+///
+/// Returns the inner asset IO unchanged if there are no overlays.
+pub fn overlay_asset_io(
+    app: &mut AppBuilder,
+    inner: Box<dyn AssetIo>,
+    overlays: &[PathBuf],
+) -> Box<dyn AssetIo> {
+    if overlays.is_empty() {
+        return inner;
+    }
+
+    let mut content_packs = ContentPacks::default();
+    for overlay in overlays {
+        info!(path = %overlay.display(), "Loading mod");
+        let id = overlay
+            .file_name()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut manifest = load_manifest(overlay);
+        if manifest.name.is_empty() {
+            manifest.name = id.clone();
+        }
+        content_packs.packs.push(ContentPack {
+            id,
+            dir: overlay.clone(),
+            manifest,
+        });
+    }
+    app.insert_resource(content_packs.clone());
+
+    Box::new(OverlayAssetIo {
+        inner,
+        content_packs,
+    })
+}
+
+/// Get the mod directories to load, from lowest to highest priority
+///
+/// These are the directories in the [`MODS_DIR`], in alphabetical order, followed by the
+/// directories given on the command line in the order they were given.
+pub fn mod_dirs(extra_dirs: &[String]) -> Vec<PathBuf> {
+    let mut dirs = std::fs::read_dir(MODS_DIR)
+        .map(|entries| {
+            entries
+                .filter_map(|x| x.ok())
+                .map(|x| x.path())
+                .filter(|x| x.is_dir())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs.extend(extra_dirs.iter().map(PathBuf::from));
+
+    dirs
+}
+
+/// Load the manifest of the mod in the given directory, if it has one
+fn load_manifest(dir: &Path) -> ContentPackManifest {
+    let path = dir.join(MANIFEST_FILE);
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(_) => return Default::default(),
+    };
+
+    match serde_yaml::from_slice(&bytes) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            warn!(path = %path.display(), %e, "Could not load mod manifest");
+            Default::default()
+        }
+    }
+}
+
+/// Asset IO that loads assets from the first of a list of overlay directories that has them,
+/// falling back to an inner asset IO for the base game's assets
+///
+/// The overlays are the directories of the mods in the [`ContentPacks`], and disabled mods are
+/// skipped.
+pub struct OverlayAssetIo {
+    inner: Box<dyn AssetIo>,
+    content_packs: ContentPacks,
+}
+
+impl AssetIo for OverlayAssetIo {
+    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            match self.content_packs.overlay_path(path) {
+                Some(overlay_path) => Ok(std::fs::read(overlay_path)?),
+                None => self.inner.load_path(path).await,
+            }
+        })
+    }
+
+    fn read_directory(
+        &self,
+        path: &Path,
+    ) -> Result<Box<dyn Iterator<Item = PathBuf>>, AssetIoError> {
+        // Mods may add files to a directory as well as replacing them
+        let mut children = self
+            .inner
+            .read_directory(path)
+            .map(|x| x.collect::<HashSet<_>>())
+            .unwrap_or_default();
+        for overlay in self.content_packs.enabled_dirs() {
+            if let Ok(entries) = std::fs::read_dir(overlay.join(path)) {
+                children.extend(
+                    entries
+                        .filter_map(|x| x.ok())
+                        .map(|x| path.join(x.file_name())),
+                );
+            }
+        }
+
+        if children.is_empty() && !self.is_directory(path) {
+            return Err(AssetIoError::NotFound(path.to_owned()));
+        }
+
+        Ok(Box::new(children.into_iter()))
+    }
+
+    fn is_directory(&self, path: &Path) -> bool {
+        self.inner.is_directory(path)
+            || self
+                .content_packs
+                .enabled_dirs()
+                .into_iter()
+                .any(|x| x.join(path).is_dir())
+    }
+
+    fn watch_path_for_changes(&self, path: &Path) -> Result<(), AssetIoError> {
+        // Only the base game's assets are hot reloaded
+        self.inner.watch_path_for_changes(path)
+    }
+
+    fn watch_for_changes(&self) -> Result<(), AssetIoError> {
+        self.inner.watch_for_changes()
+    }
+}
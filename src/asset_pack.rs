@@ -0,0 +1,281 @@
+//! Support for loading the game assets from a single archive file instead of a directory
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::Read,
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use bevy::{
+    asset::{AssetIo, AssetIoError, AssetServer},
+    prelude::*,
+    tasks::IoTaskPool,
+    utils::BoxedFuture,
+};
+
+use crate::{asset_io::platform_asset_io, asset_overlay::overlay_asset_io};
+
+/// Plugin that replaces the asset server's IO with a [`PackAssetIo`]. Must be added before the
+/// Bevy `AssetPlugin`.
+pub struct AssetPackPlugin {
+    /// The path to the asset pack, relative to the asset directory or URL
+    pub path: PathBuf,
+    /// The mod directories to lay over the asset pack, from lowest to highest priority
+    pub overlays: Vec<PathBuf>,
+}
+
+impl Plugin for AssetPackPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let task_pool = app
+            .world()
+            .get_resource::<IoTaskPool>()
+            .expect("IoTaskPool resource not found")
+            .0
+            .clone();
+        let asset_io = PackAssetIo {
+            inner: platform_asset_io(app),
+            path: self.path.clone(),
+            state: Mutex::new(PackState::Unloaded),
+        };
+        let asset_io = overlay_asset_io(app, Box::new(asset_io), &self.overlays);
+
+        app.insert_resource(AssetServer::with_boxed_io(asset_io, task_pool));
+    }
+}
+
+/// Plugin that replaces the asset server's IO with a [`PackAssetIo`] that loads the assets embedded
+/// in the executable by the `embedded-assets` feature. Must be added before the Bevy `AssetPlugin`.
+pub struct EmbeddedAssetsPlugin {
+    /// The mod directories to lay over the embedded assets, from lowest to highest priority
+    pub overlays: Vec<PathBuf>,
+}
+
+impl Plugin for EmbeddedAssetsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let task_pool = app
+            .world()
+            .get_resource::<IoTaskPool>()
+            .expect("IoTaskPool resource not found")
+            .0
+            .clone();
+        let bytes = embedded_assets().expect("The game was not built with embedded assets");
+        let files = read_pack(bytes).expect("Could not read embedded assets");
+        let asset_io = PackAssetIo {
+            inner: platform_asset_io(app),
+            path: PathBuf::new(),
+            // The pack is already loaded so the inner IO is never used
+            state: Mutex::new(PackState::Loaded(Arc::new(files))),
+        };
+        let asset_io = overlay_asset_io(app, Box::new(asset_io), &self.overlays);
+
+        app.insert_resource(AssetServer::with_boxed_io(asset_io, task_pool));
+    }
+}
+
+/// Get the asset pack embedded in the executable, if it was built with the `embedded-assets`
+/// feature
+#[cfg(feature = "embedded-assets")]
+pub fn embedded_assets() -> Option<&'static [u8]> {
+    Some(include_bytes!(concat!(
+        env!("OUT_DIR"),
+        "/embedded-assets.skipngo"
+    )))
+}
+
+/// Get the asset pack embedded in the executable, if it was built with the `embedded-assets`
+/// feature
+#[cfg(not(feature = "embedded-assets"))]
+pub fn embedded_assets() -> Option<&'static [u8]> {
+    None
+}
+
+/// The files in an asset pack, by their path in the pack
+type PackFiles = HashMap<PathBuf, Vec<u8>>;
+
+/// Whether an asset pack has been loaded yet
+enum PackState {
+    Unloaded,
+    /// The pack is being loaded, with the loads that are waiting for it to finish
+    Loading(Vec<Waker>),
+    Loaded(Arc<PackFiles>),
+}
+
+/// Asset IO that loads assets from a tar archive
+///
+/// The archive itself is loaded with the [`platform_asset_io`] the first time an asset is loaded,
+/// so it works on web as well as desktop. Assets that are loaded while the archive is still loading
+/// wait for it instead of loading it again.
+pub struct PackAssetIo {
+    inner: Box<dyn AssetIo>,
+    path: PathBuf,
+    state: Mutex<PackState>,
+}
+
+impl PackAssetIo {
+    /// Get the files in the pack, loading the pack if it hasn't been loaded yet
+    async fn files(&self) -> Result<Arc<PackFiles>, AssetIoError> {
+        if let Some(files) = WaitForPack(&self.state).await {
+            return Ok(files);
+        }
+
+        let result = match self.inner.load_path(&self.path).await {
+            Ok(bytes) => read_pack(&bytes).map(Arc::new),
+            Err(e) => Err(e),
+        };
+
+        // If the pack couldn't be loaded, the next load that was waiting for it tries again
+        let mut state = self.state.lock().unwrap();
+        let next_state = match &result {
+            Ok(files) => PackState::Loaded(files.clone()),
+            Err(_) => PackState::Unloaded,
+        };
+        if let PackState::Loading(waiters) = std::mem::replace(&mut *state, next_state) {
+            for waiter in waiters {
+                waiter.wake();
+            }
+        }
+
+        result
+    }
+
+    /// Get the files in the pack if it has already been loaded
+    fn loaded_files(&self) -> Option<Arc<PackFiles>> {
+        match &*self.state.lock().unwrap() {
+            PackState::Loaded(files) => Some(files.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Future that waits for an asset pack that is being loaded by another asset load
+///
+/// Resolves to the files in the pack, or to `None` if the pack isn't loaded, in which case the
+/// pack is marked as loading and the caller must load it.
+struct WaitForPack<'a>(&'a Mutex<PackState>);
+
+impl<'a> Future for WaitForPack<'a> {
+    type Output = Option<Arc<PackFiles>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.0.lock().unwrap();
+        match &mut *state {
+            PackState::Loaded(files) => Poll::Ready(Some(files.clone())),
+            PackState::Loading(waiters) => {
+                if !waiters.iter().any(|x| x.will_wake(cx.waker())) {
+                    waiters.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+            PackState::Unloaded => {
+                *state = PackState::Loading(Vec::new());
+                Poll::Ready(None)
+            }
+        }
+    }
+}
+
+impl AssetIo for PackAssetIo {
+    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            self.files()
+                .await?
+                .get(&normalize_path(path))
+                .cloned()
+                .ok_or_else(|| AssetIoError::NotFound(path.to_owned()))
+        })
+    }
+
+    fn read_directory(
+        &self,
+        path: &Path,
+    ) -> Result<Box<dyn Iterator<Item = PathBuf>>, AssetIoError> {
+        let files = self
+            .loaded_files()
+            .ok_or_else(|| AssetIoError::NotFound(path.to_owned()))?;
+        let path = normalize_path(path);
+
+        let children = files
+            .keys()
+            .filter(|x| x.parent() == Some(&path))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        Ok(Box::new(children.into_iter()))
+    }
+
+    fn is_directory(&self, path: &Path) -> bool {
+        let path = normalize_path(path);
+        self.loaded_files()
+            .map(|files| files.keys().any(|x| x != &path && x.starts_with(&path)))
+            .unwrap_or(false)
+    }
+
+    fn watch_path_for_changes(&self, _path: &Path) -> Result<(), AssetIoError> {
+        // Asset packs are not hot reloaded
+        Ok(())
+    }
+
+    fn watch_for_changes(&self) -> Result<(), AssetIoError> {
+        Ok(())
+    }
+}
+
+/// Remove `.` components from a path so that paths from the pack and the asset server match
+fn normalize_path(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|x| !matches!(x, Component::CurDir | Component::RootDir))
+        .collect()
+}
+
+/// Read all of the files in an asset pack
+fn read_pack(bytes: &[u8]) -> Result<PackFiles, AssetIoError> {
+    let mut files = HashMap::default();
+    let mut archive = tar::Archive::new(bytes);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = normalize_path(&entry.path()?);
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        files.insert(path, data);
+    }
+
+    Ok(files)
+}
+
+/// Read a single file from an asset pack without loading the rest of it
+#[cfg(not(wasm))]
+pub fn read_pack_file(pack: impl Read, path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+    let path = normalize_path(path);
+    let mut archive = tar::Archive::new(pack);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type().is_file() && normalize_path(&entry.path()?) == path {
+            let mut data = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut data)?;
+            return Ok(Some(data));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Pack the files in an asset directory into an asset pack
+#[cfg(not(wasm))]
+pub fn pack_assets(asset_dir: &Path, output: &Path) -> anyhow::Result<()> {
+    let file = std::fs::File::create(output)?;
+    let mut builder = tar::Builder::new(file);
+    builder.append_dir_all(".", asset_dir)?;
+    builder.finish()?;
+
+    Ok(())
+}
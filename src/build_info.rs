@@ -0,0 +1,42 @@
+//! Information about the engine build, so that bug reports can be matched to engine versions
+
+use std::fmt;
+
+/// Resource with the version of the engine and when it was built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// The version of the engine crate
+    pub version: &'static str,
+    /// The short hash of the git commit the engine was built from, or `unknown` if it wasn't built
+    /// from a git checkout
+    pub git_hash: &'static str,
+    /// The day the engine was built, as `YYYY-MM-DD`
+    pub build_date: &'static str,
+}
+
+impl BuildInfo {
+    /// Get the info for the running build
+    pub const fn get() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: env!("SKIPNGO_GIT_HASH"),
+            build_date: env!("SKIPNGO_BUILD_DATE"),
+        }
+    }
+}
+
+impl Default for BuildInfo {
+    fn default() -> Self {
+        Self::get()
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Skip'n Go v{} ({} {})",
+            self.version, self.git_hash, self.build_date
+        )
+    }
+}
@@ -0,0 +1,222 @@
+//! Crash reports written when the game panics, so that players can send them with bug reports
+
+use std::{collections::VecDeque, fmt::Display, sync::Mutex};
+
+use crate::BuildInfo;
+
+/// The number of recent log lines kept for crash reports
+const RECENT_LOG_LINES: usize = 100;
+
+/// What the game was doing, for including in crash reports
+struct CrashContext {
+    /// A summary of the game info, if it has loaded
+    game: String,
+    /// The level the player was in, if any
+    level: String,
+    /// The directory to write crash reports to
+    #[cfg(not(wasm))]
+    report_dir: Option<std::path::PathBuf>,
+    /// The most recent log lines, oldest first
+    recent_logs: VecDeque<String>,
+}
+
+lazy_static::lazy_static! {
+    static ref CRASH_CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext {
+        game: String::new(),
+        level: String::new(),
+        #[cfg(not(wasm))]
+        report_dir: None,
+        recent_logs: VecDeque::new(),
+    });
+}
+
+/// Run a function with the crash context, even if a panic while it was locked poisoned it
+fn with_context<R>(f: impl FnOnce(&mut CrashContext) -> R) -> R {
+    let mut context = CRASH_CONTEXT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut context)
+}
+
+/// Set the summary of the game being played for crash reports
+pub fn set_game_summary(summary: String) {
+    with_context(|context| context.game = summary);
+}
+
+/// Set the level the player is in for crash reports
+pub fn set_current_level(level: Option<&str>) {
+    with_context(|context| context.level = level.unwrap_or_default().to_owned());
+}
+
+/// Set the directory that crash reports are written to. The current directory is used until
+/// this is set.
+#[cfg(not(wasm))]
+pub fn set_report_dir(dir: std::path::PathBuf) {
+    with_context(|context| context.report_dir = Some(dir));
+}
+
+/// Add a log line to the lines included in crash reports
+pub fn push_log_line(line: String) {
+    with_context(|context| {
+        if context.recent_logs.len() >= RECENT_LOG_LINES {
+            context.recent_logs.pop_front();
+        }
+        context.recent_logs.push_back(line);
+    });
+}
+
+/// Install a panic hook that writes a crash report before running the default hook
+///
+/// On desktop the report is written to a `crash-<timestamp>.txt` file, and on web it is shown in
+/// an overlay on the page.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let report = crash_report(info);
+
+        #[cfg(not(wasm))]
+        write_report(&report);
+        #[cfg(wasm)]
+        show_report_overlay(&report);
+
+        default_hook(info);
+    }));
+}
+
+/// Get the text of the crash report for a panic, given the panic's message and location
+fn crash_report(info: &dyn Display) -> String {
+    with_context(|context| {
+        let mut report = format!(
+            "The game crashed!\n\n\
+            Build: {}\n\
+            Game: {}\n\
+            Level: {}\n\n\
+            {}\n",
+            BuildInfo::get(),
+            if context.game.is_empty() {
+                "not loaded"
+            } else {
+                &context.game
+            },
+            if context.level.is_empty() {
+                "none"
+            } else {
+                &context.level
+            },
+            info,
+        );
+
+        report.push_str("\nRecent log:\n");
+        if context.recent_logs.is_empty() {
+            report.push_str("No log lines were recorded. On web, see the browser console.\n");
+        }
+        for line in &context.recent_logs {
+            report.push_str(line);
+            report.push('\n');
+        }
+
+        report
+    })
+}
+
+/// Write a crash report to a file in the report directory
+#[cfg(not(wasm))]
+fn write_report(report: &str) {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default();
+    let dir = with_context(|context| context.report_dir.clone())
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    let path = dir.join(format!("crash-{}.txt", timestamp));
+
+    let result = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, report));
+    match result {
+        Ok(()) => eprintln!("Wrote crash report to `{}`", path.display()),
+        Err(e) => eprintln!(
+            "Could not write crash report to `{}`: {}",
+            path.display(),
+            e
+        ),
+    }
+}
+
+/// Show a crash report in an overlay on the page, with a button to copy it
+#[cfg(wasm)]
+fn show_report_overlay(report: &str) {
+    let document = match web_sys::window().and_then(|x| x.document()) {
+        Some(document) => document,
+        None => return,
+    };
+    let body = match document.body() {
+        Some(body) => body,
+        None => return,
+    };
+
+    let show = || -> Result<(), wasm_bindgen::JsValue> {
+        let overlay = document.create_element("div")?;
+        overlay.set_attribute(
+            "style",
+            "position: fixed; inset: 0; z-index: 1000; overflow: auto; padding: 1em; \
+            background: rgba(0, 0, 0, 0.85); color: white; font-family: monospace;",
+        )?;
+
+        let copy_button = document.create_element("button")?;
+        copy_button.set_text_content(Some("Copy crash report"));
+        copy_button.set_attribute(
+            "onclick",
+            "navigator.clipboard.writeText(\
+            document.getElementById('skipngo-crash-report').textContent)",
+        )?;
+
+        let text = document.create_element("pre")?;
+        text.set_id("skipngo-crash-report");
+        text.set_attribute("style", "white-space: pre-wrap;")?;
+        text.set_text_content(Some(report));
+
+        overlay.append_child(&copy_button)?;
+        overlay.append_child(&text)?;
+        body.append_child(&overlay)?;
+
+        Ok(())
+    };
+    // There's nothing else to do if the page can't show the report, it is still in the console
+    let _ = show();
+}
+
+/// A tracing layer that keeps the most recent log lines for crash reports
+#[cfg(not(wasm))]
+pub struct RecentLogLayer;
+
+#[cfg(not(wasm))]
+impl<S: bevy::utils::tracing::Subscriber> tracing_subscriber::Layer<S> for RecentLogLayer {
+    fn on_event(
+        &self,
+        event: &bevy::utils::tracing::Event<'_>,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let metadata = event.metadata();
+        let mut line = format!("{} {}:", metadata.level(), metadata.target());
+        event.record(&mut LogLineVisitor(&mut line));
+        push_log_line(line);
+    }
+}
+
+/// Visitor that adds the fields of a log event to a log line
+#[cfg(not(wasm))]
+struct LogLineVisitor<'a>(&'a mut String);
+
+#[cfg(not(wasm))]
+impl<'a> bevy::utils::tracing::field::Visit for LogLineVisitor<'a> {
+    fn record_debug(
+        &mut self,
+        field: &bevy::utils::tracing::field::Field,
+        value: &dyn std::fmt::Debug,
+    ) {
+        if field.name() == "message" {
+            self.0.push_str(&format!(" {:?}", value));
+        } else {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+}
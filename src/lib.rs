@@ -12,18 +12,61 @@ use bevy_retrograde::prelude::*;
 #[cfg(not(wasm))]
 use structopt::StructOpt;
 
+pub mod asset_io;
+pub mod asset_overlay;
+pub mod asset_pack;
+pub mod build_info;
+pub mod crash_report;
+#[cfg(not(wasm))]
+pub mod log_file;
 pub mod plugins;
+#[cfg(not(wasm))]
+pub mod scaffold;
 pub mod utils;
 
 #[cfg(wasm)]
 pub mod wasm_utils;
+#[cfg(wasm)]
+pub mod web_api;
+#[cfg(wasm)]
+pub use wasm_utils::Storage;
+
+#[cfg(not(wasm))]
+pub mod native_utils;
+#[cfg(not(wasm))]
+pub use native_utils::Storage;
+
+pub use build_info::BuildInfo;
 
 pub fn run() {
-    // Get logging config
-    let log_config = get_log_config();
+    // Write a crash report if the game panics
+    crash_report::install_panic_hook();
+
     // Get the engine config
     let engine_config = EngineConfig::get_config();
 
+    // Run a command instead of the game if one was given
+    #[cfg(not(wasm))]
+    match &engine_config.command {
+        // Running the game is the same as not giving a command
+        None | Some(EngineCommand::Run) => (),
+        Some(command) => {
+            if let Err(error) = command.run() {
+                eprintln!("Error: {:?}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+    }
+
+    // Get logging config
+    #[cfg(not(wasm))]
+    let startup_game_info = read_startup_game_info(&engine_config);
+    #[cfg(not(wasm))]
+    let log_config = get_log_config(startup_game_info.as_ref());
+    #[cfg(wasm)]
+    let log_config = get_log_config();
+
     // Create an app builder
     let mut builder = App::build();
 
@@ -43,10 +86,56 @@ pub fn run() {
         .insert_resource(engine_config.clone())
         // Add the logging config
         .insert_resource(log_config)
-        // Install Bevy Retrograde
-        .add_plugins(RetroPlugins)
-        // Add our SkipnGo plugins
-        .add_plugins(plugins::SkipnGoPlugins);
+        // Add the build info, for showing to players and including in bug reports
+        .init_resource::<BuildInfo>();
+
+    // Set up logging, writing a trace file if enabled
+    #[cfg(not(wasm))]
+    start_logging(&mut builder, &engine_config, startup_game_info.as_ref());
+
+    // Install Bevy Retrograde
+    let asset_pack = engine_config.asset_pack.clone();
+    // Mods are laid over the assets wherever they are loaded from, but only on desktop
+    #[cfg(not(wasm))]
+    let mod_dirs = asset_overlay::mod_dirs(&engine_config.mod_dirs);
+    #[cfg(wasm)]
+    let mod_dirs = Vec::new();
+    builder.add_plugins_with(RetroPlugins, |group| {
+        // Replace the logger with our own on desktop, which keeps recent log lines for crash
+        // reports
+        #[cfg(not(wasm))]
+        group.disable::<bevy::log::LogPlugin>();
+
+        // Load the assets from the asset pack if one was given
+        if let Some(path) = asset_pack {
+            group.add_before::<bevy::asset::AssetPlugin, _>(asset_pack::AssetPackPlugin {
+                path: path.into(),
+                overlays: mod_dirs,
+            });
+        // Or from the assets embedded in the executable
+        } else if asset_pack::embedded_assets().is_some() {
+            group.add_before::<bevy::asset::AssetPlugin, _>(asset_pack::EmbeddedAssetsPlugin {
+                overlays: mod_dirs,
+            });
+        } else {
+            // Otherwise lay any mods over the asset directory on desktop
+            #[cfg(not(wasm))]
+            if !mod_dirs.is_empty() {
+                group.add_before::<bevy::asset::AssetPlugin, _>(
+                    asset_overlay::OverlayAssetIoPlugin { overlays: mod_dirs },
+                );
+            }
+
+            // Or download assets with retries on web
+            #[cfg(wasm)]
+            group.add_before::<bevy::asset::AssetPlugin, _>(asset_io::PlatformAssetIoPlugin);
+        }
+
+        group
+    });
+
+    // Add our SkipnGo plugins
+    builder.add_plugins(plugins::SkipnGoPlugins);
 
     // Enable diagnostics
     if engine_config.frame_time_diagnostics {
@@ -68,11 +157,111 @@ pub fn run() {
 
 #[cfg(not(wasm))]
 use bevy::log::LogSettings;
-/// Get logging config for desktop
 #[cfg(not(wasm))]
-fn get_log_config() -> LogSettings {
-    // Default settings are fine, just use RUST_LOG env to tweak
-    Default::default()
+use plugins::game::StartupGameInfo;
+
+/// Read the parts of the game info needed before the game starts, from the same place the assets
+/// will be loaded from
+#[cfg(not(wasm))]
+fn read_startup_game_info(engine_config: &EngineConfig) -> Option<StartupGameInfo> {
+    let path = std::path::Path::new("default.game.yaml");
+    let bytes = if let Some(pack_path) = &engine_config.asset_pack {
+        asset_pack::read_pack_file(std::fs::File::open(pack_path).ok()?, path).ok()??
+    } else if let Some(embedded_assets) = asset_pack::embedded_assets() {
+        asset_pack::read_pack_file(embedded_assets, path).ok()??
+    } else {
+        std::fs::read(std::path::Path::new(&engine_config.asset_path).join(path)).ok()?
+    };
+
+    // Errors are reported when the game info is loaded as an asset
+    serde_yaml::from_slice(&bytes).ok()
+}
+
+/// Get logging config for desktop from the game info's log settings
+#[cfg(not(wasm))]
+fn get_log_config(game_info: Option<&StartupGameInfo>) -> LogSettings {
+    let mut log_config = LogSettings::default();
+    let logging = if let Some(game_info) = game_info {
+        &game_info.logging
+    } else {
+        return log_config;
+    };
+
+    match logging.level.parse() {
+        Ok(level) => log_config.level = level,
+        Err(_) => eprintln!("Invalid log level in the game info: `{}`", logging.level),
+    }
+    for (module, level) in &logging.filters {
+        log_config
+            .filter
+            .push_str(&format!(",{}={}", module, level));
+    }
+
+    log_config
+}
+
+/// Set up logging that keeps the recent log lines for crash reports, writes the log file from the
+/// game info's log settings if there is one, and writes a chrome tracing file if enabled
+#[cfg(not(wasm))]
+fn start_logging(
+    builder: &mut AppBuilder,
+    engine_config: &EngineConfig,
+    game_info: Option<&StartupGameInfo>,
+) {
+    use tracing_subscriber::{fmt, prelude::*, EnvFilter, Registry};
+
+    // Use the same filter as the default logger
+    let log_config = builder.world().get_resource::<LogSettings>().unwrap();
+    let default_filter = format!("{},{}", log_config.level, log_config.filter);
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&default_filter))
+        .unwrap();
+
+    // Log files are kept next to the save data, unless they have an absolute path
+    let file_layer = game_info.and_then(|game_info| {
+        let logging = &game_info.logging;
+        let storage = if engine_config.portable {
+            Storage::portable()
+        } else {
+            Storage::new(&game_info.title)
+        };
+        let path = std::path::PathBuf::from(storage.location()).join(logging.file.as_ref()?);
+
+        match log_file::RotatingLogFile::open(
+            path.clone(),
+            logging.max_file_size,
+            logging.max_files,
+        ) {
+            Ok(file) => Some(fmt::Layer::default().with_ansi(false).with_writer(file)),
+            Err(e) => {
+                eprintln!("Could not open log file `{}`: {}", path.display(), e);
+                None
+            }
+        }
+    });
+
+    let (chrome_layer, guard) = match &engine_config.trace {
+        Some(path) => {
+            let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new()
+                .file(std::path::PathBuf::from(path))
+                .build();
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+    Registry::default()
+        .with(filter)
+        .with(fmt::Layer::default())
+        .with(crash_report::RecentLogLayer)
+        .with(file_layer)
+        .with(chrome_layer)
+        .try_init()
+        .expect("Could not set up logging");
+
+    // The trace file is finished writing when the guard is dropped as the app exits
+    if let Some(guard) = guard {
+        builder.insert_non_send_resource(guard);
+    }
 }
 
 #[cfg(wasm)]
@@ -98,12 +287,153 @@ pub struct EngineConfig {
         structopt(short = "a", long = "asset-dir", default_value = "assets", parse(from_str = parse_asset_path))
     )]
     asset_path: String,
+    /// Load the game assets from an asset pack built with the `pack` command instead of the asset
+    /// directory
+    #[cfg_attr(
+        not(wasm),
+        structopt(short = "p", long = "asset-pack", parse(from_str = parse_asset_path))
+    )]
+    asset_pack: Option<String>,
+    /// A mod directory to lay over the game assets, replacing the assets it has. Can be given more
+    /// than once, with later mods taking priority. Mods in the `mods` directory are loaded too.
+    #[cfg_attr(
+        not(wasm),
+        structopt(short = "m", long = "mod-dir", parse(from_str = parse_asset_path))
+    )]
+    mod_dirs: Vec<String>,
     /// Enable frame time diagnostics to the console
     #[cfg_attr(not(wasm), structopt(short = "d", long = "frame-time-diagnostics"))]
     frame_time_diagnostics: bool,
     /// Enable hot reloading game assets
     #[cfg_attr(not(wasm), structopt(short = "R", long = "hot-reload"))]
     hot_reload: bool,
+    /// Record the player's controls to the given `.replay.yaml` file, i.e. for attract mode
+    #[cfg_attr(not(wasm), structopt(long = "record-replay"))]
+    record_replay: Option<String>,
+    /// Seed the gameplay RNG so that runs are reproducible. A random seed is used if not set.
+    #[cfg_attr(not(wasm), structopt(long = "seed"))]
+    seed: Option<u64>,
+    /// Write a chrome tracing file to the given path to debug the game's performance. Build with
+    /// the `trace` feature to include a span for every system.
+    #[cfg_attr(not(wasm), structopt(long = "trace"))]
+    trace: Option<String>,
+    /// Start the game in the given level instead of the game's start level or the saved level
+    #[cfg_attr(not(wasm), structopt(long = "start-level"))]
+    start_level: Option<String>,
+    /// Show the navmesh and the performance HUD, and enable the debug keys: F4 for the free-fly
+    /// camera, F5 for the entity inspector, F6 for the physics and movement tweak panel, and F7 to
+    /// open the LDtk map in the editor
+    #[cfg_attr(not(wasm), structopt(long = "debug"))]
+    debug: bool,
+    /// The command used to launch the LDtk editor when opening the map with F7 in debug mode
+    #[cfg(not(wasm))]
+    #[structopt(long = "ldtk-editor", default_value = "ldtk")]
+    ldtk_editor: String,
+    /// Mute all sounds and music
+    #[cfg_attr(not(wasm), structopt(long = "mute"))]
+    mute: bool,
+    /// Run the game faster or slower, i.e. `0.5` for half speed, to debug fast moments
+    #[cfg_attr(not(wasm), structopt(long = "game-speed", default_value = "1"))]
+    game_speed: f32,
+    /// The save slot to save and load progress from, so that more than one playthrough can be
+    /// kept
+    #[cfg_attr(not(wasm), structopt(long = "save-slot"))]
+    save_slot: Option<String>,
+    /// Keep saves and settings in the current directory instead of the user's data directory, i.e.
+    /// when running the game from a USB drive
+    #[cfg_attr(not(wasm), structopt(long = "portable"))]
+    portable: bool,
+    /// A command to run instead of the game
+    #[cfg(not(wasm))]
+    #[structopt(subcommand)]
+    command: Option<EngineCommand>,
+}
+
+/// Commands that can be run instead of the game
+#[cfg(not(wasm))]
+#[derive(Debug, Clone, StructOpt)]
+pub enum EngineCommand {
+    /// Run the game. This is the default if no command is given.
+    Run,
+    /// Create a new game with a template to start from
+    New {
+        /// The asset directory to create the game in
+        asset_dir: std::path::PathBuf,
+    },
+    /// Check the game's data files for errors without running the game
+    Validate {
+        /// The asset directory to validate
+        #[structopt(default_value = "assets")]
+        asset_dir: std::path::PathBuf,
+    },
+    /// Pack an asset directory into a single asset pack file that can be loaded with
+    /// `--asset-pack`
+    Pack {
+        /// The asset directory to pack
+        #[structopt(default_value = "assets")]
+        asset_dir: std::path::PathBuf,
+        /// The asset pack file to write
+        #[structopt(short = "o", long = "output", default_value = "game.skipngo")]
+        output: std::path::PathBuf,
+    },
+    /// Add the high scores and best times from a leaderboard exported by the game, i.e. on
+    /// another device, to the player's leaderboard
+    ImportLeaderboard {
+        /// The exported leaderboard JSON file
+        file: std::path::PathBuf,
+        /// The asset directory of the game the leaderboard is for
+        #[structopt(short = "a", long = "asset-dir", default_value = "assets")]
+        asset_dir: std::path::PathBuf,
+        /// Import into the portable storage in the current directory, like `--portable`
+        #[structopt(long = "portable")]
+        portable: bool,
+    },
+}
+
+#[cfg(not(wasm))]
+impl EngineCommand {
+    /// Run the command
+    pub fn run(&self) -> anyhow::Result<()> {
+        match self {
+            EngineCommand::Run => (),
+            EngineCommand::New { asset_dir } => {
+                scaffold::new_game(asset_dir)?;
+                println!(
+                    "Created a new game in `{}`. Run it with `skipngo -a {}`.",
+                    asset_dir.display(),
+                    asset_dir.display()
+                );
+            }
+            EngineCommand::Validate { asset_dir } => {
+                let problems = plugins::game::validate_assets(asset_dir)?;
+                for problem in &problems {
+                    println!("{}", problem);
+                }
+                if !problems.is_empty() {
+                    anyhow::bail!("found {} problem(s) in the game assets", problems.len());
+                }
+                println!("No problems found in `{}`", asset_dir.display());
+            }
+            EngineCommand::Pack { asset_dir, output } => {
+                asset_pack::pack_assets(asset_dir, output)?;
+                println!(
+                    "Packed `{}` into `{}`",
+                    asset_dir.display(),
+                    output.display()
+                );
+            }
+            EngineCommand::ImportLeaderboard {
+                file,
+                asset_dir,
+                portable,
+            } => {
+                plugins::game::import_leaderboard_file(file, asset_dir, *portable)?;
+                println!("Imported the leaderboard from `{}`", file.display());
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(not(wasm))]
@@ -123,23 +453,75 @@ impl EngineConfig {
     }
 }
 
+impl EngineConfig {
+    /// Change one of the options that can be changed while the game is running: `start_level`,
+    /// `debug`, `mute`, or `game_speed`
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let parse_bool = |value: &str| {
+            value
+                .parse::<bool>()
+                .map_err(|_| format!("`{}` must be `true` or `false`", name))
+        };
+
+        match name {
+            "start_level" => {
+                self.start_level = Some(value.to_owned()).filter(|x| !x.is_empty());
+            }
+            "debug" => self.debug = parse_bool(value)?,
+            "mute" => self.mute = parse_bool(value)?,
+            "game_speed" => {
+                self.game_speed = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|x| *x > 0.)
+                    .ok_or_else(|| format!("`{}` must be a number above zero", name))?
+            }
+            _ => {
+                return Err(format!(
+                    "`{}` can't be changed while the game is running",
+                    name
+                ))
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(wasm)]
 impl EngineConfig {
     pub fn get_config() -> Self {
         use web_sys::*;
 
         // Get the query string
-        let asset_url: String = window().unwrap().location().search().unwrap();
+        let query: String = window().unwrap().location().search().unwrap();
+        let string = |key: &str| parse_url_query_string(&query, key).map(String::from);
+        let flag = |key: &str| parse_url_query_string(&query, key) == Some("true");
 
         Self {
-            asset_path: parse_url_query_string(&asset_url, "asset_url")
-                .map(String::from)
-                .unwrap_or("/assets".into()),
-            frame_time_diagnostics: parse_url_query_string(&asset_url, "frame_time_diagnostics")
-                .map(|x| x == "true")
-                .unwrap_or(false),
-            // Hot reload is not supported on web yet
-            hot_reload: false,
+            asset_path: string("asset_url").unwrap_or_else(|| "/assets".into()),
+            // The asset pack path is relative to the asset URL
+            asset_pack: string("asset_pack"),
+            // There's no file system to load mods from on web
+            mod_dirs: Vec::new(),
+            frame_time_diagnostics: flag("frame_time_diagnostics"),
+            // Hot reload can be turned on, but web assets can't be watched for changes yet
+            hot_reload: flag("hot_reload"),
+            // There's no file system to record replays to on web
+            record_replay: None,
+            seed: parse_url_query_string(&query, "seed").and_then(|x| x.parse().ok()),
+            // There's no file system to write traces to on web
+            trace: None,
+            start_level: string("start_level"),
+            debug: flag("debug"),
+            mute: flag("mute"),
+            game_speed: parse_url_query_string(&query, "game_speed")
+                .and_then(|x| x.parse().ok())
+                .filter(|x: &f32| *x > 0.)
+                .unwrap_or(1.),
+            save_slot: string("save_slot"),
+            // Saves are always kept in the browser's local storage on web
+            portable: false,
         }
     }
 }
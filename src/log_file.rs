@@ -0,0 +1,113 @@
+//! Log files that are rotated when they get too big, so that shipped games can collect logs from
+//! players without filling up their disks
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+/// A log file that is moved to `<path>.1` when it grows past its maximum size, with older files
+/// moved to `<path>.2` and so on, up to the maximum number of old files
+///
+/// Every clone writes to the same file, so it can be used as a writer for a tracing layer.
+#[derive(Clone)]
+pub struct RotatingLogFile(Arc<Mutex<LogFileState>>);
+
+struct LogFileState {
+    path: PathBuf,
+    /// The current file, which is only closed while it is being rotated
+    file: Option<File>,
+    /// The number of bytes in the current file
+    size: u64,
+    max_size: u64,
+    max_files: u32,
+}
+
+impl RotatingLogFile {
+    /// Open the log file, appending to it if it already exists
+    pub fn open(path: PathBuf, max_size: u64, max_files: u32) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self(Arc::new(Mutex::new(LogFileState {
+            path,
+            file: Some(file),
+            size,
+            max_size,
+            max_files,
+        }))))
+    }
+}
+
+impl LogFileState {
+    /// Get the path of an old log file
+    fn old_path(&self, number: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", number));
+        path.into()
+    }
+
+    /// Move the current file to the old files and start a new one
+    fn rotate(&mut self) -> io::Result<()> {
+        // Close the file first, since open files can't be moved on Windows
+        self.file = None;
+
+        // Move the old files along, replacing the oldest
+        for number in (1..self.max_files).rev() {
+            let old_path = self.old_path(number);
+            if old_path.exists() {
+                std::fs::rename(old_path, self.old_path(number + 1))?;
+            }
+        }
+        if self.max_files > 0 {
+            std::fs::rename(&self.path, self.old_path(1))?;
+        }
+
+        self.file = Some(
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&self.path)?,
+        );
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingLogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.0.lock().unwrap_or_else(|x| x.into_inner());
+        if state.size > 0 && state.size + buf.len() as u64 > state.max_size {
+            state.rotate()?;
+        }
+
+        let written = match &mut state.file {
+            Some(file) => file.write(buf)?,
+            None => return Err(io::Error::new(io::ErrorKind::Other, "log file is closed")),
+        };
+        state.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.0.lock().unwrap_or_else(|x| x.into_inner()).file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl tracing_subscriber::fmt::MakeWriter for RotatingLogFile {
+    type Writer = Self;
+
+    fn make_writer(&self) -> Self::Writer {
+        self.clone()
+    }
+}
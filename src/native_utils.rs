@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+/// Persistent storage for save data and settings, kept as files in the user's data directory
+///
+/// This is the desktop counterpart to the web storage in `wasm_utils`, with the same API.
+#[derive(Clone, Debug)]
+pub struct Storage {
+    dir: PathBuf,
+}
+
+impl Storage {
+    /// Open the storage for the given namespace, usually the game title, in the platform's user
+    /// data directory
+    pub fn new(namespace: &str) -> Self {
+        // Keep the namespace from escaping the data directory or using invalid file names
+        let namespace = namespace
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+
+        // Fall back to portable storage if the platform doesn't have a data directory for us
+        match directories::ProjectDirs::from("", "", &namespace) {
+            Some(dirs) => Self {
+                dir: dirs.data_dir().to_owned(),
+            },
+            None => Self::portable(),
+        }
+    }
+
+    /// Open the storage in the current directory, for running the game from a portable drive
+    pub fn portable() -> Self {
+        Self {
+            dir: std::env::current_dir().unwrap_or_default(),
+        }
+    }
+
+    /// Describe where the storage is kept, i.e. for showing in the performance HUD
+    pub fn location(&self) -> String {
+        self.dir.display().to_string()
+    }
+
+    /// Get the value stored under a key, if there is one
+    pub async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match std::fs::read(self.dir.join(key)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Store a value under a key, replacing any existing value
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        // Write to a temporary file first so that the old value isn't lost if we crash while
+        // writing
+        let tmp_path = self.dir.join(format!("{}.tmp", key));
+        std::fs::write(&tmp_path, data)?;
+        std::fs::rename(tmp_path, self.dir.join(key))?;
+
+        Ok(())
+    }
+
+    /// Remove the value stored under a key, if there is one
+    pub async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match std::fs::remove_file(self.dir.join(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
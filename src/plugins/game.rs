@@ -2,6 +2,8 @@ use bevy::prelude::*;
 
 use assets::*;
 mod assets;
+#[cfg(not(wasm))]
+pub use assets::{validate_assets, AssetProblem, LoggingConfig, StartupGameInfo};
 
 mod systems;
 use systems::*;
@@ -12,6 +14,53 @@ use components::*;
 mod events;
 use events::*;
 
+mod map;
+use map::*;
+
+mod settings;
+use settings::*;
+
+mod level_state;
+use level_state::*;
+
+mod inventory;
+use inventory::*;
+
+mod clock;
+use clock::*;
+
+mod affinity;
+use affinity::*;
+
+mod dungeon;
+use dungeon::*;
+
+mod meta;
+use meta::*;
+
+mod score;
+use score::*;
+
+mod leaderboard;
+use leaderboard::*;
+
+#[cfg(any(feature = "online-leaderboard", feature = "online-analytics"))]
+mod http;
+
+mod rng;
+use rng::*;
+
+mod collision_router;
+use collision_router::*;
+
+mod entity_pool;
+use entity_pool::*;
+
+mod persistence;
+#[cfg(not(wasm))]
+pub use persistence::import_leaderboard_file;
+use persistence::*;
+
 /// Plugin responsible for booting and handling core game stuff
 pub struct GamePlugin;
 
@@ -23,6 +72,42 @@ impl Plugin for GamePlugin {
         // Add events
         add_events(app);
 
+        // Add user settings
+        add_settings(app);
+
+        // Add the persistent level state
+        add_level_state(app);
+
+        // Add the items the player is carrying
+        add_inventory(app);
+
+        // Add the in-game clock
+        add_clock(app);
+
+        // Add how much NPCs like the player
+        add_affinity(app);
+
+        // Add the current dungeon run
+        add_dungeon(app);
+
+        // Add the progress kept between dungeon runs
+        add_meta_progress(app);
+
+        // Add the player's score and the high score table
+        add_score(app);
+
+        // Add the best level times and the online leaderboard
+        add_leaderboard(app);
+
+        // Add the seeded gameplay RNG
+        add_rng(app);
+
+        // Add the saving and loading of settings and progress
+        add_persistence(app);
+
+        // Add the collision events for pairs of components
+        add_collision_routes(app);
+
         // Add systems
         add_systems(app);
     }
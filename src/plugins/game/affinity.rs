@@ -0,0 +1,28 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub fn add_affinity(app: &mut AppBuilder) {
+    app.init_resource::<Affinities>();
+}
+
+/// How much each NPC likes the player, keyed by the path to the NPC's `.npc.yml` file relative to
+/// the asset directory
+///
+/// Affinity starts at `0` and is changed by dialogue choices and gifts.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Affinities(pub BTreeMap<String, i32>);
+
+impl Affinities {
+    /// Get how much an NPC likes the player
+    pub fn get(&self, npc: &str) -> i32 {
+        self.0.get(npc).copied().unwrap_or(0)
+    }
+
+    /// Change how much an NPC likes the player
+    pub fn change(&mut self, npc: &str, amount: i32) {
+        *self.0.entry(npc.into()).or_default() += amount;
+    }
+}
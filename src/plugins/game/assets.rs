@@ -1,3 +1,8 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path},
+};
+
 use serde::{Deserialize, Serialize};
 
 use bevy::{
@@ -5,25 +10,88 @@ use bevy::{
     prelude::*,
     reflect::TypeUuid,
 };
-use bevy_retrograde::prelude::{
-    ui::raui::prelude::{Prefab, PropsData},
-    *,
+use bevy_retrograde::{
+    physics::heron::rapier_plugin::rapier2d::prelude::IntegrationParameters,
+    prelude::{
+        ui::raui::prelude::{Prefab, PropsData},
+        *,
+    },
 };
 
 use super::*;
 
+mod tiled;
+pub use tiled::*;
+
+#[cfg(not(wasm))]
+mod validate;
+#[cfg(not(wasm))]
+pub use validate::*;
+
 /// Add all assets and their loaders to the Bevy app
 pub fn add_assets(app: &mut AppBuilder) {
     app.add_asset::<GameInfo>()
         .add_asset_loader(GameInfoLoader::default())
         .add_asset::<Character>()
-        .add_asset_loader(CharacterLoader::default());
+        .add_asset_loader(CharacterLoader::default())
+        .add_asset::<SpriteAnimations>()
+        .add_asset_loader(SpriteAnimationsLoader::default())
+        .add_asset::<TiledMap>()
+        .add_asset_loader(TiledMapLoader::default())
+        .add_asset::<LdtkExternalLevel>()
+        .add_asset_loader(LdtkExternalLevelLoader::default())
+        .add_asset::<ShaderSource>()
+        .add_asset_loader(ShaderSourceLoader::default())
+        .add_asset::<Palette>()
+        .add_asset_loader(PaletteLoader::default())
+        .add_asset::<InputReplay>()
+        .add_asset_loader(InputReplayLoader::default())
+        .add_asset::<Ability>()
+        .add_asset_loader(AbilityLoader::default())
+        .add_asset::<Item>()
+        .add_asset_loader(ItemLoader::default())
+        .add_asset::<Recipe>()
+        .add_asset_loader(RecipeLoader::default())
+        .add_asset::<ResourceNode>()
+        .add_asset_loader(ResourceNodeLoader::default())
+        .add_asset::<Npc>()
+        .add_asset_loader(NpcLoader::default())
+        .add_asset::<Crop>()
+        .add_asset_loader(CropLoader::default())
+        .add_asset::<Cutscene>()
+        .add_asset_loader(CutsceneLoader::default())
+        .add_asset::<FontMetrics>()
+        .add_asset_loader(BitmapFontLoader::default());
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum AssetLoaderError {
     #[error("Could not parse game info: {0}")]
     DeserializationError(#[from] serde_yaml::Error),
+    #[error("Shader source is not valid UTF-8: {0}")]
+    InvalidShaderSource(#[from] std::str::Utf8Error),
+    #[error("Invalid color `{0}`, expected a hex color like `#ff00ff`")]
+    InvalidColor(String),
+    #[error("Could not read asset: {0}")]
+    AssetIo(#[from] bevy::asset::AssetIoError),
+    #[error("Could not load image: {0}")]
+    Image(#[from] bevy_retrograde::core::image::ImageError),
+    #[error("Invalid bitmap font: {0}")]
+    InvalidFont(String),
+    #[error("Invalid character: {0}")]
+    InvalidCharacter(String),
+    #[error("Invalid sprite animations: {0}")]
+    InvalidSpriteAnimations(String),
+    #[error("Invalid Tiled map: {0}")]
+    InvalidTiledMap(String),
+    #[error("Could not parse XML: {0}")]
+    Xml(#[from] xml::reader::Error),
+    #[error("Invalid Aseprite sheet: {0}")]
+    InvalidAseprite(String),
+    #[error("Invalid ability: {0}")]
+    InvalidAbility(String),
+    #[error("Invalid crop: {0}")]
+    InvalidCrop(String),
 }
 
 /// The core info about the game provided by the .game.yaml file
@@ -40,15 +108,201 @@ pub struct GameInfo {
     pub game_start_level: String,
     /// The path to the character that you will play as
     pub player_character: String,
+    /// The path to the image to use as the window icon on desktop and the page icon on web
+    #[serde(default)]
+    pub window_icon: Option<String>,
     /// The camera size
     #[serde(with = "CameraSizeDef")]
     pub camera_size: CameraSize,
+    /// How long the camera takes to catch up with the player in seconds, slowing down as it gets
+    /// closer. `0` keeps the camera on the player.
+    #[serde(default)]
+    pub camera_smoothing: f32,
     /// Splash screen configuration
     pub splash_screen: SplashScreen,
+    /// The splash images shown one after the other before the start menu, such as the studio and
+    /// engine logos
+    #[serde(default)]
+    pub intro: Vec<IntroSlide>,
     /// UI theme configuration
     pub ui_theme: UiTheme,
     /// Debug rendering options
     pub debug_rendering: DebugRenderingOptions,
+    /// Damage vignette and low-health warning settings
+    #[serde(default)]
+    pub health_feedback: HealthFeedbackConfig,
+    /// Post-processing shader settings
+    #[serde(default)]
+    pub post_processing: PostProcessingConfig,
+    /// Screen transition settings
+    #[serde(default)]
+    pub transitions: TransitionConfig,
+    /// Mouse control settings
+    #[serde(default)]
+    pub mouse: MouseConfig,
+    /// Mouse cursor settings
+    #[serde(default)]
+    pub cursor: CursorConfig,
+    /// Settings for pausing the game when it loses focus
+    #[serde(default)]
+    pub auto_pause: AutoPauseConfig,
+    /// UI scale and safe area settings
+    #[serde(default)]
+    pub ui_layout: UiLayoutConfig,
+    /// Sound caption settings
+    #[serde(default)]
+    pub captions: CaptionsConfig,
+    /// The localization table, mapping text keys to the text shown for `{text:key}` in rich text.
+    /// Mods can replace the entries to translate the game.
+    #[serde(default)]
+    pub localization: HashMap<String, String>,
+    /// Y-sorting settings
+    #[serde(default)]
+    pub y_sort: YSortConfig,
+    /// Settings for roof layers that fade out when the player walks underneath them
+    #[serde(default)]
+    pub roofs: RoofConfig,
+    /// Settings for smoothing the movement of characters, enemies, and projectiles between
+    /// simulation steps
+    #[serde(default)]
+    pub physics_smoothing: PhysicsSmoothingConfig,
+    /// Physics simulation settings
+    #[serde(default)]
+    pub physics: PhysicsConfig,
+    /// Enemy movement settings
+    #[serde(default)]
+    pub enemies: EnemyConfig,
+    /// Named collision groups and which groups they collide with
+    #[serde(default)]
+    pub collision_layers: CollisionLayersConfig,
+    /// The metadata for tiles tagged with each LDtk tileset enum value ( or Tiled tile class ), so
+    /// that tiles can be given collisions, damage, etc. without writing YAML in their custom data
+    #[serde(default)]
+    pub tile_tags: HashMap<String, TilesetTileMetadata>,
+    /// The paths to the `.recipe.yml` files of the recipes the player can craft
+    #[serde(default)]
+    pub recipes: Vec<String>,
+    /// The paths to the `.crop.yml` files of the crops the player can plant
+    #[serde(default)]
+    pub crops: Vec<String>,
+    /// In-game clock settings
+    #[serde(default)]
+    pub clock: ClockConfig,
+    /// Procedurally generated dungeon settings, for games with roguelite runs
+    #[serde(default)]
+    pub dungeon: Option<DungeonConfig>,
+    /// Turn-based battle settings, for games where walking into an enemy starts a battle instead
+    /// of fighting in real time
+    #[serde(default)]
+    pub battle: Option<BattleConfig>,
+    /// Stealth settings for enemies with vision cones
+    #[serde(default)]
+    pub stealth: StealthConfig,
+    /// Settings for freezing enemies that are far away from the player
+    #[serde(default)]
+    pub enemy_culling: EnemyCullingConfig,
+    /// Settings for the secret wall tiles that break when hit hard enough
+    #[serde(default)]
+    pub secret_walls: SecretWallsConfig,
+    /// Settings for the props that the player can lift and throw
+    #[serde(default)]
+    pub lifting: LiftingConfig,
+    /// Blob shadows drawn under characters and enemies, which are off if not set
+    #[serde(default)]
+    pub shadows: Option<ShadowConfig>,
+    /// Reflections of characters and enemies shown on reflective tiles, such as water, which are
+    /// off if not set
+    #[serde(default)]
+    pub reflections: Option<ReflectionConfig>,
+    /// Settings for the footprints left behind on tiles with a `footprint` image
+    #[serde(default)]
+    pub footprints: FootprintConfig,
+    /// Settings for the decorative critters spawned in levels with a `critters` field
+    #[serde(default)]
+    pub critters: CritterConfig,
+    /// Settings for the emotes shown over characters, enemies, and NPCs
+    #[serde(default)]
+    pub emotes: EmoteConfig,
+    /// Settings for cutscenes
+    #[serde(default)]
+    pub cutscenes: CutsceneConfig,
+    /// Settings for the results screen shown when the player leaves a level through one of its
+    /// `goal` entrances. If not set, the results screen isn't shown.
+    #[serde(default)]
+    pub results: Option<ResultsConfig>,
+    /// Settings for scoring points, for arcade-style games. If not set, the score isn't kept.
+    #[serde(default)]
+    pub score: Option<ScoreConfig>,
+    /// Settings for the online leaderboard. If not set, scores are only kept on the player's
+    /// device.
+    #[serde(default)]
+    pub leaderboard: Option<LeaderboardConfig>,
+    /// Settings for recording gameplay analytics, i.e. where players die, to learn where the game
+    /// is too hard. If not set, nothing is recorded.
+    #[serde(default)]
+    pub analytics: Option<AnalyticsConfig>,
+    /// Settings for how the map is drawn
+    #[serde(default)]
+    pub rendering: RenderingConfig,
+    /// The version of the game's assets. On web this is added to asset URLs so that players
+    /// download the new assets instead of using old cached ones when it changes.
+    #[serde(default)]
+    pub asset_version: Option<String>,
+    /// Log settings, which are read when the game starts on desktop
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Photo mode settings
+    #[serde(default)]
+    pub photo_mode: PhotoModeConfig,
+}
+
+/// The parts of the game info that are needed before the game starts, which are read straight
+/// from the game info file instead of being loaded as an asset
+#[cfg(not(wasm))]
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct StartupGameInfo {
+    /// The title of the game
+    pub title: String,
+    /// Log settings
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Score settings, for the number of high scores kept when importing a leaderboard
+    #[serde(default)]
+    pub score: Option<ScoreConfig>,
+}
+
+/// Log settings
+///
+/// The `RUST_LOG` environment variable overrides the level and filters when it is set.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// The most detailed log messages to record: `error`, `warn`, `info`, `debug`, or `trace`
+    pub level: String,
+    /// The log level for specific modules, overriding `level`, i.e. `skipngo: debug`
+    pub filters: HashMap<String, String>,
+    /// A file to also write the log to. Relative paths are in the game's data directory, next to
+    /// the save files.
+    pub file: Option<String>,
+    /// The size in bytes that the log file can grow to before it is moved to `<file>.1`
+    pub max_file_size: u64,
+    /// The number of old log files to keep
+    pub max_files: u32,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".into(),
+            filters: HashMap::new(),
+            file: None,
+            max_file_size: 1024 * 1024,
+            max_files: 3,
+        }
+    }
 }
 
 /// Splash screen settings
@@ -60,6 +314,57 @@ pub struct SplashScreen {
     pub background_level: String,
     pub music: String,
     pub copyright: CopyrightInfo,
+    /// The speed in pixels per second to pan the camera back and forth across the background
+    /// level
+    #[serde(default)]
+    pub background_pan_speed: f32,
+    /// Attract mode settings. If not set, attract mode is disabled.
+    #[serde(default)]
+    pub attract_mode: Option<AttractModeConfig>,
+}
+
+/// A splash image shown in the intro before the start menu, centered on a black screen
+#[derive(Deserialize, Clone, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct IntroSlide {
+    pub image: SplashImage,
+    /// How long the image is shown for, in seconds, including fading in and out
+    #[serde(default = "default_intro_slide_duration")]
+    pub duration: f32,
+    /// A sound played when the image is shown
+    #[serde(default)]
+    pub sound: Option<String>,
+    /// Whether pressing a key or button skips to the next image
+    #[serde(default = "default_intro_slide_skippable")]
+    pub skippable: bool,
+}
+
+fn default_intro_slide_duration() -> f32 {
+    2.5
+}
+
+fn default_intro_slide_skippable() -> bool {
+    true
+}
+
+/// Settings for the attract mode that plays a demo of the game when the start menu is left idle
+#[derive(Deserialize, Clone, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct AttractModeConfig {
+    /// The path to the `.replay.yaml` input replay to play
+    pub replay: String,
+    /// The level to play the replay in. Defaults to the game start level.
+    #[serde(default)]
+    pub level: Option<String>,
+    /// How long the start menu must be idle before attract mode starts, in seconds
+    #[serde(default = "default_attract_mode_idle_time")]
+    pub idle_time: f32,
+}
+
+fn default_attract_mode_idle_time() -> f32 {
+    15.
 }
 
 #[derive(Deserialize, Clone, Serialize, Debug)]
@@ -90,6 +395,29 @@ pub struct UiTheme {
     pub button_down: UiBoxImage,
     pub checkbox: UiCheckboxImages,
     pub hud: UiHud,
+    /// Map of item keys to the icon images shown for `{item-icon:key}` in rich text
+    #[serde(default)]
+    pub item_icons: HashMap<String, String>,
+    /// Map of emote icon names to the images shown for them in [`Emote`] bubbles
+    #[serde(default)]
+    pub emotes: HashMap<String, String>,
+    /// The bubble drawn behind emotes. The `panel` is used if this isn't set.
+    #[serde(default)]
+    pub emote_bubble: Option<UiBoxImage>,
+    /// The sound played as each character of dialogue and sign text is revealed
+    #[serde(default)]
+    pub text_sound: Option<String>,
+    /// An alternative theme used when the player turns on the high-contrast accessibility option
+    #[serde(default)]
+    pub high_contrast: Option<Box<UiTheme>>,
+    /// Named alternative themes, i.e. `spooky` for a haunted area, used by levels with a matching
+    /// `ui_theme` field and by the screens in `screens`
+    #[serde(default)]
+    pub variants: HashMap<String, UiTheme>,
+    /// The variant used for each screen when the level doesn't set one: `menu`, `pause`, `battle`,
+    /// or `hud`
+    #[serde(default)]
+    pub screens: HashMap<String, String>,
 }
 
 /// In-game HUD theme
@@ -97,6 +425,8 @@ pub struct UiTheme {
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct UiHud {
+    /// The background of the hearts. Unless it is stretched, it grows to fit all of the player's
+    /// heart containers, with `size` as its smallest size.
     pub health_background: SizedImage,
     pub full_heart: SizedImage,
     pub half_heart: SizedImage,
@@ -108,6 +438,33 @@ pub struct UiHud {
 pub struct SizedImage {
     pub image: String,
     pub size: (u32, u32),
+    /// How the image fills the space it is drawn in
+    #[serde(default)]
+    pub scaling: UiImageScaling,
+}
+
+/// How a [`SizedImage`] fills the space it is drawn in when that isn't the image's size
+#[derive(Deserialize, Clone, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub enum UiImageScaling {
+    /// Stretch the whole image
+    Stretch,
+    /// Draw the image as a 9-patch, like panels and buttons, with the corners kept at their size
+    #[serde(rename_all = "kebab-case")]
+    Frame {
+        border_size: u32,
+        #[serde(default)]
+        only_frame: bool,
+    },
+    /// Repeat the image at its size
+    Tile,
+}
+
+impl Default for UiImageScaling {
+    fn default() -> Self {
+        Self::Stretch
+    }
 }
 
 /// The theme for a checkbox
@@ -140,120 +497,2713 @@ pub enum CameraSizeDef {
     LetterBoxed { width: u32, height: u32 },
 }
 
-#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+/// Settings for the screen-space feedback shown when the player is hurt or low on health
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 #[serde(default)]
-pub struct DebugRenderingOptions {
-    /// Enable navmesh rendering
-    pub navmesh: bool,
+pub struct HealthFeedbackConfig {
+    /// Optional image stretched over the screen as the vignette. If not set, a border of the
+    /// vignette color that fades in from the edges of the screen is drawn instead.
+    pub vignette_image: Option<String>,
+    /// The RGB color of the vignette
+    pub vignette_color: (f32, f32, f32),
+    /// How far the vignette border reaches in from the edges of the screen, in pixels
+    pub vignette_width: f32,
+    /// The opacity of the vignette right after the player is damaged
+    pub flash_opacity: f32,
+    /// How long the damage flash takes to fade out in seconds
+    pub flash_duration: f32,
+    /// The player health at or below which the low-health warning is shown
+    pub low_health_threshold: u32,
+    /// The maximum opacity of the pulsing low-health vignette
+    pub pulse_opacity: f32,
+    /// How many times per second the low-health vignette pulses
+    pub pulse_rate: f32,
+    /// Sound looped while the player is at low health
+    pub heartbeat_sound: Option<String>,
+    /// How strong the rumble is when the player is damaged, from `0.0` to `1.0`. Rumble is only
+    /// played on the web, which vibrates the device at full strength for any intensity above `0.0`.
+    pub damage_rumble_intensity: f32,
+    /// How long the device vibrates when the player is damaged in seconds
+    pub damage_rumble_duration: f32,
+    /// How much the camera shakes when the player is damaged, from `0.0` to `1.0`
+    pub damage_shake: f32,
 }
 
-//
-// Game info loader
-//
+impl Default for HealthFeedbackConfig {
+    fn default() -> Self {
+        Self {
+            vignette_image: None,
+            vignette_color: (1., 0., 0.),
+            vignette_width: 24.,
+            flash_opacity: 0.5,
+            flash_duration: 0.3,
+            low_health_threshold: 2,
+            pulse_opacity: 0.3,
+            pulse_rate: 1.,
+            heartbeat_sound: None,
+            damage_rumble_intensity: 0.6,
+            damage_rumble_duration: 0.25,
+            damage_shake: 0.,
+        }
+    }
+}
 
-#[derive(Default)]
-pub struct GameInfoLoader;
+/// Post-processing shader settings
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct PostProcessingConfig {
+    /// Named shaders that levels and shader regions can refer to, mapped to the path of their
+    /// `.glsl` source file. The `crt` shader is always available.
+    pub shaders: HashMap<String, String>,
+    /// How long it takes to blend from one shader to another in seconds
+    pub blend_duration: f32,
+}
 
-impl AssetLoader for GameInfoLoader {
-    fn load<'a>(
-        &'a self,
-        bytes: &'a [u8],
-        load_context: &'a mut bevy::asset::LoadContext,
-    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
-        Box::pin(async move { Ok(load_game_info(bytes, load_context).await?) })
+impl Default for PostProcessingConfig {
+    fn default() -> Self {
+        Self {
+            shaders: Default::default(),
+            blend_duration: 0.5,
+        }
     }
+}
 
-    fn extensions(&self) -> &[&str] {
-        &["game.yml", "game.yaml"]
+/// Screen transition settings
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct TransitionConfig {
+    /// How long it takes to fade the screen to or from black in seconds
+    pub fade_duration: f32,
+    /// The minimum amount of time to show the loading screen in seconds
+    pub min_loading_duration: f32,
+    /// The text shown on the loading screen
+    pub loading_text: String,
+}
+
+impl Default for TransitionConfig {
+    fn default() -> Self {
+        Self {
+            fade_duration: 0.5,
+            min_loading_duration: 0.5,
+            loading_text: "Loading...".into(),
+        }
     }
 }
 
-async fn load_game_info<'a, 'b>(
-    bytes: &'a [u8],
-    load_context: &'a mut bevy::asset::LoadContext<'b>,
-) -> Result<(), AssetLoaderError> {
-    let game_info: GameInfo = serde_yaml::from_slice(bytes)?;
-    load_context.set_default_asset(LoadedAsset::new(game_info));
-    Ok(())
+/// Mouse control settings
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct MouseConfig {
+    /// Make the player face the mouse cursor, attack with the left mouse button, and interact with
+    /// the right mouse button
+    pub enabled: bool,
 }
 
-//
-// Character loader
-//
+/// Settings for the captions shown for important sounds when the player turns captions on
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct CaptionsConfig {
+    /// Map of sound asset paths to the caption shown when the sound plays
+    pub sounds: HashMap<String, String>,
+    /// How long each caption is shown in seconds
+    pub duration: f32,
+    /// The most captions to show at once
+    pub max_visible: usize,
+}
 
-#[derive(Default)]
-pub struct CharacterLoader;
+impl Default for CaptionsConfig {
+    fn default() -> Self {
+        Self {
+            sounds: Default::default(),
+            duration: 3.,
+            max_visible: 3,
+        }
+    }
+}
 
-impl AssetLoader for CharacterLoader {
-    fn load<'a>(
-        &'a self,
-        bytes: &'a [u8],
-        load_context: &'a mut bevy::asset::LoadContext,
-    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
-        Box::pin(async move { Ok(load_character(bytes, load_context).await?) })
+/// Settings for sorting characters, enemies, and props by their y position
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct YSortConfig {
+    /// The range of z positions above their layer that y-sorted entities are spread across. This
+    /// should be less than `2`, the distance between map layers, so that y-sorted entities stay
+    /// below the next layer up.
+    pub band: f32,
+}
+
+impl Default for YSortConfig {
+    fn default() -> Self {
+        Self { band: 1.0 }
     }
+}
 
-    fn extensions(&self) -> &[&str] {
-        &["character.yml", "character.yaml"]
+/// Settings for map roof layers, which are layers with names starting with `Roof`
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct RoofConfig {
+    /// The opacity of a roof layer while the player is underneath it, from `0` to `1`
+    pub opacity: f32,
+    /// How long it takes the roof to fade in or out in seconds
+    pub fade_duration: f32,
+}
+
+impl Default for RoofConfig {
+    fn default() -> Self {
+        Self {
+            opacity: 0.25,
+            fade_duration: 0.25,
+        }
     }
 }
 
-async fn load_character<'a, 'b>(
-    bytes: &'a [u8],
-    load_context: &'a mut bevy::asset::LoadContext<'b>,
-) -> Result<(), AssetLoaderError> {
-    // Load the character
-    let character: CharacterYmlData = serde_yaml::from_slice(bytes)?;
+/// Settings for the in-game clock
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct ClockConfig {
+    /// How long an in-game day lasts while playing, in seconds
+    pub day_length: f32,
+    /// The hour of the day that a new game starts at
+    pub start_hour: f32,
+    /// The palettes that recolor every level and character through the day, i.e. for dusk and
+    /// night palettes. Each one is used from its hour until the next one's hour.
+    pub palettes: Vec<ClockPalette>,
+}
 
-    // Get the path to the tileset image asset
-    let atlas_file_path = load_context
-        .path()
-        .parent()
-        .unwrap()
-        .join(&character.sprite_sheet.path);
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            day_length: 720.,
+            start_hour: 6.,
+            palettes: Vec::new(),
+        }
+    }
+}
 
-    // Get the path to the tileset image asset
-    let collision_file_path = load_context
-        .path()
-        .parent()
-        .unwrap()
-        .join(&character.collision_shape);
+/// A palette used from an hour of the in-game day
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ClockPalette {
+    /// The hour of the day that the palette starts being used at
+    pub hour: f32,
+    /// The path to the palette, or `none` to go back to the levels' own palettes
+    pub palette: String,
+}
 
-    // Convert that to an asset path for the texture
-    let sprite_image_path = AssetPath::new(atlas_file_path, None);
-    // Get the texture handle
-    let sprite_image_handle: Handle<Image> = load_context.get_handle(sprite_image_path.clone());
-    // Add it as a labled asset
-    let sprite_sheet_handle = load_context.set_labeled_asset(
-        "SpriteSheet",
-        LoadedAsset::new(SpriteSheet {
-            grid_size: UVec2::splat(character.sprite_sheet.grid_size.0),
-            tile_index: 0,
-        }),
-    );
+/// Settings for dungeons that are assembled from room levels at the start of each run
+///
+/// Rooms are linked by their entrances: entrances with their `to` field set to `@next` or
+/// `@previous` lead to the next or previous room in the run, and entrances in other levels with
+/// their `to` field set to `@dungeon` lead into the first room. The last room's `@next` entrances
+/// lead back out to the first `@dungeon` entrance.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct DungeonConfig {
+    /// The identifiers of the levels that rooms are picked from. Each room is used at most once
+    /// per run.
+    pub rooms: Vec<String>,
+    /// How many rooms to pick for each run, not counting the first and last rooms
+    pub length: usize,
+    /// The level to always start the run in, if any
+    pub first_room: Option<String>,
+    /// The level to always end the run in, such as a boss room, if any
+    pub last_room: Option<String>,
+    /// The level the player goes back to when they die during a run, keeping their
+    /// meta-currency and unlocks. If not set, dying ends the game as usual.
+    pub hub: Option<String>,
+    /// The name of the meta-currency shown in the unlocks menu
+    pub currency_name: String,
+    /// The meta-currency given the first time the player enters each room of a run
+    pub room_reward: u32,
+    /// The unlocks that the player can buy with meta-currency between runs
+    pub unlocks: Vec<DungeonUnlock>,
+}
 
-    // Convert that to an asset path for the texture
-    let collision_image_path = AssetPath::new(collision_file_path, None);
-    // Get the texture handle
-    let collision_image_handle: Handle<Image> =
-        load_context.get_handle(collision_image_path.clone());
+impl Default for DungeonConfig {
+    fn default() -> Self {
+        Self {
+            rooms: Vec::new(),
+            length: 5,
+            first_room: None,
+            last_room: None,
+            hub: None,
+            currency_name: "Gems".into(),
+            room_reward: 1,
+            unlocks: Vec::new(),
+        }
+    }
+}
 
-    // Set the character asset
-    load_context.set_default_asset(
-        LoadedAsset::new(Character {
-            name: character.name,
-            max_health: character.max_health,
-            sprite_sheet_info: character.sprite_sheet,
-            collision_shape: collision_image_handle,
-            actions: character.actions,
-            walk_speed: character.walk_speed,
-            sprite_image: sprite_image_handle,
-            sprite_sheet: sprite_sheet_handle,
-        })
-        .with_dependency(collision_image_path)
-        .with_dependency(sprite_image_path),
-    );
+impl DungeonConfig {
+    /// Get every level that can be a room in a run, including the rooms added by unlocks
+    pub fn all_rooms(&self) -> impl Iterator<Item = &String> {
+        self.rooms
+            .iter()
+            .chain(self.unlocks.iter().flat_map(|x| &x.rooms))
+            .chain(self.first_room.iter())
+            .chain(self.last_room.iter())
+    }
+}
 
-    Ok(())
+/// An upgrade bought with meta-currency that lasts between dungeon runs
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct DungeonUnlock {
+    /// The unique ID that the unlock is saved as
+    pub id: String,
+    /// The name shown in the unlocks menu
+    pub name: String,
+    /// How much meta-currency the unlock costs
+    pub cost: u32,
+    /// The identifiers of levels added to the pool of rooms that runs are made from
+    pub rooms: Vec<String>,
+    /// The paths to items put in the player's inventory when the unlock is bought
+    pub items: Vec<String>,
+}
+
+/// Settings for turn-based battles
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct BattleConfig {
+    /// The damage done by the player's basic attack, before their attack stat is added
+    pub attack_damage: u32,
+    /// The damage done by enemies' basic attack, before the player's defense is taken away
+    pub enemy_attack_damage: u32,
+    /// The chance that the player gets away when they flee, from `0` to `1`
+    pub flee_chance: f32,
+}
+
+impl Default for BattleConfig {
+    fn default() -> Self {
+        Self {
+            attack_damage: 1,
+            enemy_attack_damage: 1,
+            flee_chance: 0.5,
+        }
+    }
+}
+
+/// Settings for enemies that have to see or hear the player before chasing them, which are enemies
+/// with a `vision_range` field in the map
+///
+/// Enemies can set their own `vision_angle`, `suspicion_time`, and `search_time` fields to
+/// override these defaults.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct StealthConfig {
+    /// How wide enemies' vision cones are, in degrees
+    pub vision_angle: f32,
+    /// How long an enemy has to see the player for before it is alerted, in seconds
+    pub suspicion_time: f32,
+    /// How long an alerted enemy searches for the player after losing sight of them, in seconds
+    pub search_time: f32,
+    /// How far away the player's footsteps can be heard, in pixels
+    pub footstep_noise: f32,
+    /// How far away the player using an ability can be heard, in pixels
+    pub ability_noise: f32,
+    /// How much of a noise's radius is left after it goes through a wall, from `0` to `1`
+    pub wall_muffling: f32,
+    /// Whether enemies go to look where they heard a noise when they can't see the player. Can
+    /// be overridden with an enemy's `investigates_noise` field.
+    pub investigate_noise: bool,
+}
+
+impl Default for StealthConfig {
+    fn default() -> Self {
+        Self {
+            vision_angle: 90.,
+            suspicion_time: 1.,
+            search_time: 5.,
+            footstep_noise: 24.,
+            ability_noise: 96.,
+            wall_muffling: 0.5,
+            investigate_noise: true,
+        }
+    }
+}
+
+/// Settings for secret wall tiles, which look like any other wall but break when they are hit by
+/// a player's projectile or explosion that does enough damage
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct SecretWallsConfig {
+    /// The least damage a hit has to do to break a secret wall
+    pub min_damage: u32,
+    /// Sound played when a secret wall breaks
+    pub break_sound: Option<String>,
+    /// Sound played when a secret wall is hit by something too weak to break it, to hint to the
+    /// player that there is something behind it
+    pub hint_sound: Option<String>,
+}
+
+impl Default for SecretWallsConfig {
+    fn default() -> Self {
+        Self {
+            min_damage: 2,
+            break_sound: None,
+            hint_sound: None,
+        }
+    }
+}
+
+/// Settings for the props that the player can lift and throw, which are props with the `lift`
+/// interaction
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct LiftingConfig {
+    /// How far above the player's position a lifted prop is carried, in pixels
+    pub carry_height: f32,
+    /// How fast a thrown prop flies, in pixels per second
+    pub throw_speed: f32,
+    /// How far a thrown prop flies before it breaks on the ground, in pixels
+    pub throw_range: f32,
+    /// The damage done by a thrown prop to the enemy it hits. Props can set their own
+    /// `throw_damage` field to override this.
+    pub throw_damage: u32,
+}
+
+impl Default for LiftingConfig {
+    fn default() -> Self {
+        Self {
+            carry_height: 12.,
+            throw_speed: 160.,
+            throw_range: 64.,
+            throw_damage: 1,
+        }
+    }
+}
+
+/// Settings for the blob shadows drawn under characters and enemies
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ShadowConfig {
+    /// The image of the shadow, which is centered on the character
+    pub sprite: String,
+    /// How far the shadow is moved from the center of the character, in pixels, i.e. to put it
+    /// under the character's feet
+    #[serde(default)]
+    pub offset: (f32, f32),
+}
+
+/// Settings for the reflections shown under characters and enemies standing on tiles with
+/// `reflective` metadata
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct ReflectionConfig {
+    /// The opacity of the reflections, from `0` to `1`
+    pub opacity: f32,
+    /// How far the reflection is moved from where it would touch the character's feet, in pixels
+    pub offset: (f32, f32),
+}
+
+impl Default for ReflectionConfig {
+    fn default() -> Self {
+        Self {
+            opacity: 0.4,
+            offset: (0., 0.),
+        }
+    }
+}
+
+/// Settings for the footprints that characters and enemies leave behind when walking on tiles with
+/// a `footprint` image, such as sand or snow
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct FootprintConfig {
+    /// How far apart footprints are left, in pixels
+    pub spacing: f32,
+    /// How long footprints stay on the ground, in seconds
+    pub lifetime: f32,
+    /// The most footprints that can be on the ground at once. No more are left until the oldest
+    /// ones disappear.
+    pub max_footprints: usize,
+}
+
+impl Default for FootprintConfig {
+    fn default() -> Self {
+        Self {
+            spacing: 8.,
+            lifetime: 3.,
+            max_footprints: 128,
+        }
+    }
+}
+
+/// Settings for decorative critters, such as birds and butterflies, that wander around levels and
+/// flee from the player
+///
+/// Levels get critters by setting their `critters` field to an image or `.animations.yml` file,
+/// and can set a `critter_count` field to override the default count. Animated critters play
+/// their `idle` clip while wandering and their `flee` clip while fleeing.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct CritterConfig {
+    /// How many critters are spawned in a level that doesn't set its `critter_count`
+    pub count: u32,
+    /// How fast critters wander around, in pixels per second
+    pub wander_speed: f32,
+    /// How fast critters flee from the player, in pixels per second
+    pub flee_speed: f32,
+    /// How close the player can get to a critter before it flees, in pixels
+    pub flee_distance: f32,
+}
+
+impl Default for CritterConfig {
+    fn default() -> Self {
+        Self {
+            count: 6,
+            wander_speed: 12.,
+            flee_speed: 96.,
+            flee_distance: 32.,
+        }
+    }
+}
+
+/// Settings for the emotes shown in bubbles over characters, enemies, and NPCs
+///
+/// The bubbles are drawn with the UI theme's `emote-bubble`, or its `panel` if it doesn't have
+/// one, and emote icons are looked up in the theme's `emotes`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct EmoteConfig {
+    /// Where the bubble is drawn relative to the entity it is over, in pixels
+    pub offset: (f32, f32),
+    /// The emote shown over an enemy when it becomes suspicious of the player
+    pub suspicious: Option<Emote>,
+    /// The emote shown over an enemy when it is alerted to the player
+    pub alert: Option<Emote>,
+    /// The emote shown over an enemy when it starts searching for the player
+    pub searching: Option<Emote>,
+}
+
+impl Default for EmoteConfig {
+    fn default() -> Self {
+        Self {
+            offset: (0., -20.),
+            suspicious: Some(Emote {
+                icon: None,
+                text: Some("?".into()),
+                duration: 1.,
+            }),
+            alert: Some(Emote {
+                icon: None,
+                text: Some("!".into()),
+                duration: 1.,
+            }),
+            searching: None,
+        }
+    }
+}
+
+/// An icon or short text shown in a bubble over an entity for a while
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct Emote {
+    /// The name of the icon to show from the UI theme's `emotes`
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// The text to show, after the icon if there is one
+    #[serde(default)]
+    pub text: Option<String>,
+    /// How long the emote is shown for, in seconds
+    #[serde(default = "default_emote_duration")]
+    pub duration: f32,
+}
+
+/// How long emotes are shown for when they don't set a duration, in seconds
+pub fn default_emote_duration() -> f32 {
+    2.
+}
+
+/// Settings for how [`Cutscene`]s are shown
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct CutsceneConfig {
+    /// How tall the letterbox bars at the top and bottom of the screen are, in pixels
+    pub letterbox_height: f32,
+    /// How long the letterbox bars take to slide in or out, in seconds
+    pub letterbox_time: f32,
+}
+
+impl Default for CutsceneConfig {
+    fn default() -> Self {
+        Self {
+            letterbox_height: 20.,
+            letterbox_time: 0.5,
+        }
+    }
+}
+
+/// Settings for the results screen shown when the player completes a level
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct ResultsConfig {
+    /// The title of the results screen
+    pub title: String,
+    /// How long the results screen is shown for, in seconds
+    pub duration: f32,
+    /// Whether the time spent in the level is shown
+    pub show_time: bool,
+    /// Whether the number of enemies defeated in the level is shown
+    pub show_enemies: bool,
+    /// Whether the number of secrets found in the level is shown
+    pub show_secrets: bool,
+}
+
+impl Default for ResultsConfig {
+    fn default() -> Self {
+        Self {
+            title: "Level Complete!".into(),
+            duration: 4.,
+            show_time: true,
+            show_enemies: true,
+            show_secrets: true,
+        }
+    }
+}
+
+/// Settings for the player's score, which is shown in the HUD, and the high score table, which is
+/// shown on the start menu
+///
+/// Points are multiplied by the player's combo, which grows each time they score and runs out if
+/// they don't score again soon enough. Props with a `score:` interaction followed by a number of
+/// points, i.e. `score:50`, are picked up for those points.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct ScoreConfig {
+    /// The points for defeating an enemy
+    pub enemy_points: u32,
+    /// The points for finding a secret
+    pub secret_points: u32,
+    /// How long the player has to score again to continue their combo, in seconds
+    pub combo_time: f32,
+    /// How much the multiplier grows for each score in a combo
+    pub combo_step: f32,
+    /// The highest the multiplier can get
+    pub max_multiplier: f32,
+    /// How many scores are kept in the high score table
+    pub high_scores: usize,
+}
+
+impl Default for ScoreConfig {
+    fn default() -> Self {
+        Self {
+            enemy_points: 100,
+            secret_points: 500,
+            combo_time: 2.,
+            combo_step: 0.5,
+            max_multiplier: 4.,
+            high_scores: 5,
+        }
+    }
+}
+
+/// Settings for the online leaderboard, which the player's score and best level times are sent to
+/// when a game is over, and which the best scores of all players are fetched from to show on the
+/// start menu
+///
+/// The engine must be built with the `online-leaderboard` feature for this to be used. Scores are
+/// sent with a `POST` of JSON to the `url`, and the best scores are fetched with a `GET` of the
+/// `url`, which should return a JSON list of entries like `{ "name": "Bob", "score": 1200 }`. On
+/// web, scores are sent as plain text so that the browser doesn't need to check with the server
+/// first, but the server must still allow the game's site with CORS headers.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct LeaderboardConfig {
+    /// The URL of the leaderboard server
+    pub url: String,
+    /// How many of the best scores to show
+    pub top: usize,
+}
+
+impl Default for LeaderboardConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            top: 10,
+        }
+    }
+}
+
+/// Settings for recording gameplay analytics
+///
+/// Players must turn on sharing play data in the settings menu before anything is recorded. Each
+/// play session records when levels are started and completed, where the player dies, when they
+/// quit, and how long they have played, and is flushed to the sink as a single JSON document that
+/// replaces the one flushed before it.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct AnalyticsConfig {
+    /// Where the sessions are sent
+    pub sink: AnalyticsSink,
+    /// How often new events are flushed, in seconds
+    pub flush_interval: f32,
+    /// The most sessions to keep with the `file` sink, after which the oldest are removed
+    pub max_stored_sessions: usize,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            sink: Default::default(),
+            flush_interval: 60.,
+            max_stored_sessions: 20,
+        }
+    }
+}
+
+/// Where gameplay analytics are sent
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnalyticsSink {
+    /// Keep each session in an `analytics-<session>.json` file next to the player's save data, or
+    /// in local storage on web, up to the `max-stored-sessions`
+    File,
+    /// `POST` each session to a URL, identified by its `session` field. The engine must be built
+    /// with the `online-analytics` feature for this to be used.
+    Http(String),
+}
+
+impl Default for AnalyticsSink {
+    fn default() -> Self {
+        Self::File
+    }
+}
+
+/// Settings for how the map is drawn
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct RenderingConfig {
+    /// Merge the map layers that nothing is drawn between into one sprite when a level is
+    /// entered, so that levels with a lot of decorative layers take fewer draws. This helps slow
+    /// devices and web browsers keep up, at the cost of a short pause the first time each level is
+    /// entered.
+    pub merge_static_layers: bool,
+    /// Pack small sprites that are drawn on their own, like props, plants, and projectiles, into
+    /// shared atlas textures as they load, so that they can be drawn without switching textures.
+    /// This matters most in web browsers, where every texture switch is expensive.
+    pub pack_sprite_atlases: bool,
+    /// The largest width or height, in pixels, of a sprite that is packed into an atlas
+    pub atlas_max_sprite_size: u32,
+    /// The width and height, in pixels, of each atlas texture
+    pub atlas_size: u32,
+}
+
+impl Default for RenderingConfig {
+    fn default() -> Self {
+        Self {
+            merge_static_layers: false,
+            pack_sprite_atlases: false,
+            atlas_max_sprite_size: 64,
+            atlas_size: 1024,
+        }
+    }
+}
+
+/// Settings for freezing the AI, animation, and movement of enemies that are far away from the
+/// player, to save work on levels with a lot of enemies
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct EnemyCullingConfig {
+    /// How close the player has to get to a frozen enemy to wake it up, in pixels
+    pub activation_radius: f32,
+    /// How much further than the activation radius the player has to get from an enemy for it to
+    /// freeze again, so that enemies at the edge don't keep freezing and waking up, in pixels
+    pub margin: f32,
+    /// The most enemies that can be awake at once. When more are in range, the ones closest to
+    /// the player are woken up.
+    pub max_active: usize,
+}
+
+impl Default for EnemyCullingConfig {
+    fn default() -> Self {
+        Self {
+            activation_radius: 256.,
+            margin: 32.,
+            max_active: 64,
+        }
+    }
+}
+
+/// Enemy movement settings
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct EnemyConfig {
+    /// How fast enemies chase the player, in pixels per second
+    pub speed: f32,
+    /// How enemies knock the player back when they touch them
+    pub knock_back: DamageRegionKnockBack,
+}
+
+impl Default for EnemyConfig {
+    fn default() -> Self {
+        Self {
+            speed: 40.,
+            knock_back: DamageRegionKnockBack {
+                speed: 800.,
+                force_duration: 0.04,
+                freeze_duration: 0.18,
+            },
+        }
+    }
+}
+
+/// Settings for the physics simulation's rapier integration parameters, which are tuned for a
+/// "16 pixels equals 1 meter" scale by default
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct PhysicsConfig {
+    /// The error reduction parameter, from `0.0` to `1.0`: how much of the overlap between bodies
+    /// is corrected each step
+    pub erp: f32,
+    /// The amount of overlap between bodies that is allowed without being corrected
+    pub allowed_linear_error: f32,
+    /// How close bodies have to be for contacts between them to be computed
+    pub prediction_distance: f32,
+    /// The most that overlap between bodies is corrected by in one step
+    pub max_linear_correction: f32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            erp: 0.1,
+            allowed_linear_error: 0.05,
+            prediction_distance: 1.,
+            max_linear_correction: 3.,
+        }
+    }
+}
+
+impl PhysicsConfig {
+    /// Set the physics simulation's integration parameters to these settings
+    pub fn apply(&self, params: &mut IntegrationParameters) {
+        params.erp = self.erp;
+        params.allowed_linear_error = self.allowed_linear_error;
+        params.prediction_distance = self.prediction_distance;
+        params.max_linear_correction = self.max_linear_correction;
+    }
+}
+
+/// Settings for smoothing the rendered movement of characters, enemies, and projectiles by
+/// interpolating between simulation steps
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct PhysicsSmoothingConfig {
+    /// The distance in pixels that something has to move in one step to jump straight to its new
+    /// position instead of being smoothed, i.e. when it is teleported. `0` disables smoothing.
+    pub snap_distance: f32,
+}
+
+impl Default for PhysicsSmoothingConfig {
+    fn default() -> Self {
+        Self { snap_distance: 32. }
+    }
+}
+
+/// The names of the built-in collision groups, in the same order as [`PhysicsGroup`]
+const BUILTIN_COLLISION_GROUPS: [(&str, PhysicsGroup); 5] = [
+    ("terrain", PhysicsGroup::Terrain),
+    ("entrance", PhysicsGroup::Entrance),
+    ("player", PhysicsGroup::Player),
+    ("enemy", PhysicsGroup::Enemy),
+    ("region", PhysicsGroup::Region),
+];
+
+/// Collision layer settings
+///
+/// Every collision shape is in one or more groups, and has a list of groups that it collides with.
+/// Two shapes will only collide if each of them is in a group that the other collides with. The
+/// built-in groups are `terrain`, `entrance`, `player`, `enemy`, and `region`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct CollisionLayersConfig {
+    /// Extra collision groups to add to the built-in ones, i.e. `projectile` or `pickup`. There
+    /// can be at most 27 extra groups.
+    pub groups: Vec<String>,
+    /// The groups that shapes in each group collide with. Groups that are not listed collide with
+    /// all groups. By default only `region` is listed, and it only collides with `player`.
+    pub matrix: HashMap<String, Vec<String>>,
+}
+
+impl Default for CollisionLayersConfig {
+    fn default() -> Self {
+        let mut matrix = HashMap::default();
+        matrix.insert("region".into(), vec!["player".into()]);
+
+        Self {
+            groups: Default::default(),
+            matrix,
+        }
+    }
+}
+
+impl CollisionLayersConfig {
+    /// Get the bit for a collision group, if it exists
+    pub fn group_bits(&self, group: &str) -> Option<u32> {
+        if let Some((_, builtin)) = BUILTIN_COLLISION_GROUPS.iter().find(|(x, _)| *x == group) {
+            return Some(builtin.to_bits());
+        }
+
+        self.groups
+            .iter()
+            .position(|x| x == group)
+            .map(|i| i + BUILTIN_COLLISION_GROUPS.len())
+            .filter(|&i| i < 32)
+            .map(|i| 1 << i)
+    }
+
+    /// Get the combined bits for a list of collision groups, warning about unknown groups
+    pub fn groups_bits<S: AsRef<str>>(&self, groups: &[S]) -> u32 {
+        groups.iter().fold(0, |bits, group| {
+            let group = group.as_ref();
+            if let Some(group_bits) = self.group_bits(group) {
+                bits | group_bits
+            } else {
+                warn!(%group, "Unknown collision group");
+                bits
+            }
+        })
+    }
+
+    /// Get the collision layers for a shape in the given groups, colliding with the groups set in
+    /// the collision matrix
+    pub fn collision_layers<S: AsRef<str>>(&self, groups: &[S]) -> CollisionLayers {
+        let masks = groups.iter().fold(0, |masks, group| {
+            masks
+                | self
+                    .matrix
+                    .get(group.as_ref())
+                    .map(|x| self.groups_bits(x))
+                    .unwrap_or(u32::MAX)
+        });
+
+        CollisionLayers::from_bits(self.groups_bits(groups), masks)
+    }
+
+    /// Get the collision layers for a shape in the given groups that collides with the given groups
+    /// instead of the ones in the collision matrix
+    pub fn collision_layers_with<S: AsRef<str>, T: AsRef<str>>(
+        &self,
+        groups: &[S],
+        collides_with: &[T],
+    ) -> CollisionLayers {
+        CollisionLayers::from_bits(self.groups_bits(groups), self.groups_bits(collides_with))
+    }
+}
+
+/// Mouse cursor settings
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct CursorConfig {
+    /// The path to an image to draw in place of the system cursor
+    pub image: Option<String>,
+    /// The pixel in the cursor image that points at the cursor position, from the top-left corner
+    pub hotspot: (u32, u32),
+    /// Hide the cursor while playing the game, showing it only in menus
+    pub hide_in_gameplay: bool,
+}
+
+/// Settings for pausing the game when its window loses focus, or when its page is hidden on web
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct AutoPauseConfig {
+    /// Pause the game when it loses focus during gameplay
+    pub enabled: bool,
+    /// Pause the music while the game doesn't have focus, and resume it when focus comes back
+    pub duck_music: bool,
+}
+
+impl Default for AutoPauseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            duck_music: true,
+        }
+    }
+}
+
+/// Settings for the size of the HUD and menus, and for keeping them out from under phone notches
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct UiLayoutConfig {
+    /// The amount to scale the UI by
+    pub scale: f32,
+    /// The amount to scale the UI by, in place of `scale`, once the player has used a touch screen
+    pub touch_scale: f32,
+    /// Keep the UI inside of the screen's safe area on web, so that it isn't drawn under notches
+    /// and rounded corners
+    pub safe_area: bool,
+    /// The camera size, in game pixels, that the UI was designed for
+    ///
+    /// When set, the UI is scaled along with the camera so that it covers the same part of the
+    /// screen when the camera size changes or letterboxing shrinks the camera's view.
+    pub reference_size: Option<UiReferenceSize>,
+    /// Round the scale from the `reference_size` down to a whole number when it is bigger than one,
+    /// so that the UI's pixels stay square
+    pub whole_number_scale: bool,
+}
+
+impl Default for UiLayoutConfig {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            touch_scale: 1.5,
+            safe_area: true,
+            reference_size: None,
+            whole_number_scale: true,
+        }
+    }
+}
+
+/// The camera size that the UI was designed for
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct UiReferenceSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Settings for photo mode, which hides the UI and lets the player move the camera freely to take
+/// screenshots
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct PhotoModeConfig {
+    /// Show the photo mode button in the pause menu
+    pub enabled: bool,
+    /// How fast the camera moves in pixels per second
+    pub pan_speed: f32,
+    /// The furthest the camera can zoom out, as a fraction of the normal camera size
+    pub min_zoom: f32,
+    /// The furthest the camera can zoom in, as a multiple of the normal camera size
+    pub max_zoom: f32,
+    /// The post-processing shaders that the player can cycle through as filters, by their name in
+    /// the `post-processing` shaders or `crt`
+    pub filters: Vec<String>,
+}
+
+impl Default for PhotoModeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            pan_speed: 120.,
+            min_zoom: 0.5,
+            max_zoom: 4.,
+            filters: vec!["crt".into()],
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct DebugRenderingOptions {
+    /// Enable navmesh rendering
+    pub navmesh: bool,
+    /// Color the paths of enemies by their cost, from green to red, to find the enemies that take
+    /// long detours or get stuck. The cost is the path's length weighted by the navigation cost of
+    /// the tiles it crosses. Paths are drawn when this or `navmesh` is enabled.
+    pub path_costs: bool,
+    /// Enable rendering the vision cones of enemies that can't see through walls
+    pub vision_cones: bool,
+    /// Log how many sprites and textures each level draws when the player enters it
+    pub sprite_audit: bool,
+}
+
+//
+// Game info loader
+//
+
+#[derive(Default)]
+pub struct GameInfoLoader;
+
+impl AssetLoader for GameInfoLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_game_info(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["game.yml", "game.yaml"]
+    }
+}
+
+async fn load_game_info<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let game_info: GameInfo = serde_yaml::from_slice(bytes)?;
+    load_context.set_default_asset(LoadedAsset::new(game_info));
+    Ok(())
+}
+
+//
+// Character loader
+//
+
+#[derive(Default)]
+pub struct CharacterLoader;
+
+impl AssetLoader for CharacterLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_character(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["character.yml", "character.yaml"]
+    }
+}
+
+async fn load_character<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    // Load the character
+    let mut character: CharacterYmlData = serde_yaml::from_slice(bytes)?;
+
+    // Get the sprite sheet, either from the Aseprite export or from the character file
+    let sprite_sheet = if let Some(aseprite) = &character.aseprite {
+        let sheet = load_aseprite(aseprite, load_context).await?;
+
+        // Use the tagged animations for any that are in the sheet
+        for (action_name, action) in [
+            ("walk", &mut character.actions.walk),
+            ("idle", &mut character.actions.idle),
+        ]
+        .iter_mut()
+        {
+            sheet.apply_tags(action_name, &mut action.animations);
+        }
+
+        sheet.info
+    } else if let Some(sprite_sheet) = character.sprite_sheet {
+        sprite_sheet
+    } else {
+        return Err(AssetLoaderError::InvalidCharacter(
+            "either `sprite-sheet` or `aseprite` must be set".into(),
+        ));
+    };
+
+    // Get the path to the tileset image asset
+    let collision_file_path = load_context
+        .path()
+        .parent()
+        .unwrap()
+        .join(&character.collision_shape);
+
+    let (sprite_image_path, sprite_image_handle, sprite_sheet_handle) =
+        set_sprite_sheet(&sprite_sheet, load_context);
+
+    // Add the character's animations, named after their action and direction
+    let mut clips = HashMap::default();
+    for &(action_name, action) in [
+        ("walk", &character.actions.walk),
+        ("idle", &character.actions.idle),
+    ]
+    .iter()
+    {
+        for &(direction, animation) in [
+            ("up", &action.animations.up),
+            ("down", &action.animations.down),
+            ("left", &action.animations.left),
+            ("right", &action.animations.right),
+        ]
+        .iter()
+        {
+            // Make sure that every animation has frames
+            if animation.frames.is_empty() {
+                return Err(AssetLoaderError::InvalidCharacter(format!(
+                    "the `{}` action has no frames for the `{}` direction",
+                    action_name, direction
+                )));
+            }
+
+            clips.insert(format!("{}_{}", action_name, direction), animation.clone());
+        }
+    }
+    let animations_handle = load_context.set_labeled_asset(
+        "Animations",
+        LoadedAsset::new(SpriteAnimations {
+            sprite_image: sprite_image_handle.clone(),
+            sprite_sheet: sprite_sheet_handle.clone(),
+            clips,
+        }),
+    );
+
+    // Convert that to an asset path for the texture
+    let collision_image_path = AssetPath::new(collision_file_path, None);
+    // Get the texture handle
+    let collision_image_handle: Handle<Image> =
+        load_context.get_handle(collision_image_path.clone());
+
+    // Get the handles to the character's abilities and starting equipment
+    let relative_paths = |paths: &[String]| {
+        paths
+            .iter()
+            .map(|path| AssetPath::new(load_context.path().parent().unwrap().join(path), None))
+            .collect::<Vec<_>>()
+    };
+    let ability_paths = relative_paths(&character.abilities);
+    let equipment_paths = relative_paths(&character.equipment);
+    let abilities = ability_paths
+        .iter()
+        .map(|path| load_context.get_handle(path.clone()))
+        .collect();
+    let equipment = equipment_paths
+        .iter()
+        .map(|path| load_context.get_handle(path.clone()))
+        .collect();
+
+    // Set the character asset
+    let mut character_asset = LoadedAsset::new(Character {
+        name: character.name,
+        max_health: character.max_health,
+        sprite_sheet_info: sprite_sheet,
+        collision_shape: collision_image_handle,
+        actions: character.actions,
+        walk_speed: character.walk_speed,
+        sprite_image: sprite_image_handle,
+        sprite_sheet: sprite_sheet_handle,
+        animations: animations_handle,
+        abilities,
+        equipment,
+        max_energy: character.max_energy,
+        energy_regen: character.energy_regen,
+    })
+    .with_dependency(collision_image_path)
+    .with_dependency(sprite_image_path);
+    for path in ability_paths.into_iter().chain(equipment_paths) {
+        character_asset = character_asset.with_dependency(path);
+    }
+    load_context.set_default_asset(character_asset);
+
+    Ok(())
+}
+
+/// Add the sprite sheet described by the sprite sheet info as a labeled asset, returning the
+/// path to the sprite image and the handles to the image and sprite sheet
+fn set_sprite_sheet(
+    info: &SpriteSheetInfo,
+    load_context: &mut bevy::asset::LoadContext,
+) -> (AssetPath<'static>, Handle<Image>, Handle<SpriteSheet>) {
+    // Get the path to the tileset image asset
+    let atlas_file_path = load_context.path().parent().unwrap().join(&info.path);
+
+    // Convert that to an asset path for the texture
+    let sprite_image_path = AssetPath::new(atlas_file_path, None);
+    // Get the texture handle
+    let sprite_image_handle: Handle<Image> = load_context.get_handle(sprite_image_path.clone());
+    // Add it as a labled asset
+    let sprite_sheet_handle = load_context.set_labeled_asset(
+        "SpriteSheet",
+        LoadedAsset::new(SpriteSheet {
+            grid_size: UVec2::new(info.grid_size.0, info.grid_size.1),
+            tile_index: 0,
+        }),
+    );
+
+    (sprite_image_path, sprite_image_handle, sprite_sheet_handle)
+}
+
+//
+// Sprite animations loader
+//
+
+/// The data in an `.animations.yml` file
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+pub struct SpriteAnimationsYmlData {
+    /// The sprite sheet, which is required unless `aseprite` is set
+    #[serde(default)]
+    pub sprite_sheet: Option<SpriteSheetInfo>,
+    /// The path to an Aseprite JSON export to take the sprite sheet and clips from. Every tag in
+    /// the sheet is added as a clip.
+    #[serde(default)]
+    pub aseprite: Option<String>,
+    /// The animation clips by name, which are added to or replace any Aseprite tags
+    #[serde(default)]
+    pub clips: HashMap<String, SpriteAnimationClip>,
+}
+
+#[derive(Default)]
+pub struct SpriteAnimationsLoader;
+
+impl AssetLoader for SpriteAnimationsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_sprite_animations(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["animations.yml", "animations.yaml"]
+    }
+}
+
+async fn load_sprite_animations<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let data: SpriteAnimationsYmlData = serde_yaml::from_slice(bytes)?;
+
+    let (sprite_sheet, mut clips) = if let Some(aseprite) = &data.aseprite {
+        let sheet = load_aseprite(aseprite, load_context).await?;
+        (sheet.info, sheet.tags)
+    } else if let Some(sprite_sheet) = data.sprite_sheet {
+        (sprite_sheet, HashMap::default())
+    } else {
+        return Err(AssetLoaderError::InvalidSpriteAnimations(
+            "either `sprite-sheet` or `aseprite` must be set".into(),
+        ));
+    };
+    clips.extend(data.clips);
+
+    if let Some((name, _)) = clips.iter().find(|(_, clip)| clip.frames.is_empty()) {
+        return Err(AssetLoaderError::InvalidSpriteAnimations(format!(
+            "the `{}` clip has no frames",
+            name
+        )));
+    }
+
+    let (sprite_image_path, sprite_image, sprite_sheet) =
+        set_sprite_sheet(&sprite_sheet, load_context);
+
+    load_context.set_default_asset(
+        LoadedAsset::new(SpriteAnimations {
+            sprite_image,
+            sprite_sheet,
+            clips,
+        })
+        .with_dependency(sprite_image_path),
+    );
+
+    Ok(())
+}
+
+//
+// Aseprite sheets
+//
+
+/// Load the Aseprite JSON export at the given path, relative to the asset being loaded
+async fn load_aseprite<'a, 'b>(
+    path: &str,
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<AsepriteSheet, AssetLoaderError> {
+    let aseprite_path = load_context.path().parent().unwrap().join(path);
+    let aseprite_data: AsepriteData =
+        serde_yaml::from_slice(&load_context.read_asset_bytes(&aseprite_path).await?)?;
+    let mut sheet = aseprite_data.into_sheet()?;
+
+    // The image path is relative to the Aseprite export
+    sheet.info.path = Path::new(path)
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(&sheet.info.path)
+        .to_string_lossy()
+        .into();
+
+    Ok(sheet)
+}
+
+/// The data in a JSON sprite sheet exported from Aseprite, in either the array or hash format
+///
+/// The sheet must be exported without trimming so that the frames line up on a grid.
+#[derive(Deserialize)]
+struct AsepriteData {
+    frames: AsepriteFrames,
+    meta: AsepriteMeta,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AsepriteFrames {
+    Array(Vec<AsepriteFrame>),
+    /// Frames keyed by their file names, in frame order
+    Hash(serde_yaml::Mapping),
+}
+
+#[derive(Deserialize)]
+struct AsepriteFrame {
+    frame: AsepriteRect,
+    duration: u32,
+}
+
+#[derive(Deserialize)]
+struct AsepriteRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteMeta {
+    image: String,
+    size: AsepriteSize,
+    #[serde(default)]
+    frame_tags: Vec<AsepriteTag>,
+}
+
+#[derive(Deserialize)]
+struct AsepriteSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct AsepriteTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: AsepriteDirection,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum AsepriteDirection {
+    Forward,
+    Reverse,
+    Pingpong,
+}
+
+impl Default for AsepriteDirection {
+    fn default() -> Self {
+        AsepriteDirection::Forward
+    }
+}
+
+/// The sprite sheet and animations derived from an Aseprite export
+struct AsepriteSheet {
+    info: SpriteSheetInfo,
+    /// The animations by tag name
+    tags: HashMap<String, SpriteAnimationClip>,
+}
+
+impl AsepriteData {
+    fn into_sheet(self) -> Result<AsepriteSheet, AssetLoaderError> {
+        let frames = match self.frames {
+            AsepriteFrames::Array(frames) => frames,
+            AsepriteFrames::Hash(frames) => frames
+                .into_iter()
+                .map(|(_, frame)| serde_yaml::from_value(frame))
+                .collect::<Result<_, _>>()?,
+        };
+
+        let (frame_width, frame_height) = match frames.first() {
+            Some(frame) => (frame.frame.w, frame.frame.h),
+            None => {
+                return Err(AssetLoaderError::InvalidAseprite(
+                    "sheet has no frames".into(),
+                ))
+            }
+        };
+        let columns = self.meta.size.w / frame_width.max(1);
+
+        // Get the sprite sheet tile index of each frame
+        let tile_indexes = frames
+            .iter()
+            .map(|frame| {
+                let rect = &frame.frame;
+                if rect.w != frame_width
+                    || rect.h != frame_height
+                    || rect.x % frame_width != 0
+                    || rect.y % frame_height != 0
+                {
+                    return Err(AssetLoaderError::InvalidAseprite(
+                        "frames must all be the same size and lie on a grid, make sure the sheet \
+                        is exported without trimming or padding"
+                            .into(),
+                    ));
+                }
+
+                Ok(rect.y / frame_height * columns + rect.x / frame_width)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut tags = HashMap::default();
+        for tag in self.meta.frame_tags {
+            if tag.from > tag.to || tag.to >= frames.len() {
+                return Err(AssetLoaderError::InvalidAseprite(format!(
+                    "tag `{}` is out of range",
+                    tag.name
+                )));
+            }
+
+            let mut order: Vec<usize> = (tag.from..=tag.to).collect();
+            match tag.direction {
+                AsepriteDirection::Forward => (),
+                AsepriteDirection::Reverse => order.reverse(),
+                // Go back down without repeating the first and last frames
+                AsepriteDirection::Pingpong => {
+                    order.extend((tag.from + 1..tag.to).rev());
+                }
+            }
+
+            tags.insert(
+                tag.name,
+                SpriteAnimationClip {
+                    frames: order.iter().map(|&i| tile_indexes[i]).collect(),
+                    durations: order.iter().map(|&i| frames[i].duration).collect(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        Ok(AsepriteSheet {
+            info: SpriteSheetInfo {
+                path: self.meta.image,
+                grid_size: (frame_width, frame_height),
+                tiles: (columns, self.meta.size.h / frame_height.max(1)),
+            },
+            tags,
+        })
+    }
+}
+
+impl AsepriteSheet {
+    /// Set the animations for an action from the tags named `{action}_{direction}`, such as
+    /// `walk_up`. If there is no left tag or left animation in the character file, the right tag is
+    /// used flipped.
+    fn apply_tags(&self, action_name: &str, animations: &mut CharacterAnimations) {
+        let tag = |direction: &str| self.tags.get(&format!("{}_{}", action_name, direction));
+
+        if let Some(animation) = tag("up") {
+            animations.up = animation.clone();
+        }
+        if let Some(animation) = tag("down") {
+            animations.down = animation.clone();
+        }
+        if let Some(animation) = tag("right") {
+            animations.right = animation.clone();
+        }
+        if let Some(animation) = tag("left") {
+            animations.left = animation.clone();
+        } else if let (true, Some(animation)) = (animations.left.frames.is_empty(), tag("right")) {
+            animations.left = SpriteAnimationClip {
+                flip: true,
+                ..animation.clone()
+            };
+        }
+    }
+}
+
+//
+// Shader source loader
+//
+
+/// The GLSL source of a custom post-processing shader
+#[derive(TypeUuid, Clone, Debug)]
+#[uuid = "4e5e1c3b-8f0c-4a8e-9d3a-6c1f5b2e7a90"]
+pub struct ShaderSource(pub String);
+
+#[derive(Default)]
+pub struct ShaderSourceLoader;
+
+impl AssetLoader for ShaderSourceLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_shader_source(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["glsl"]
+    }
+}
+
+async fn load_shader_source<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let source = std::str::from_utf8(bytes)?;
+    load_context.set_default_asset(LoadedAsset::new(ShaderSource(source.into())));
+    Ok(())
+}
+
+//
+// LDtk external level loader
+//
+
+/// A level of an LDtk project saved with the "separate level files" option
+///
+/// The level is kept as raw data and merged into its [`LdtkMap`] once it loads.
+#[derive(TypeUuid, Clone, Debug)]
+#[uuid = "9a4e1c27-63d8-4b0f-b5a2-7e81f0c3d946"]
+pub struct LdtkExternalLevel {
+    /// The level data, in the same format as a level in the LDtk project file
+    pub data: serde_yaml::Value,
+}
+
+#[derive(Default)]
+pub struct LdtkExternalLevelLoader;
+
+impl AssetLoader for LdtkExternalLevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            // LDtk level files are JSON, which can be parsed as YAML
+            let data: serde_yaml::Value = serde_yaml::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(LdtkExternalLevel { data }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ldtkl"]
+    }
+}
+
+//
+// Palette loader
+//
+
+/// A color palette swap that can be applied to a whole level
+#[derive(TypeUuid, Clone, Debug)]
+#[uuid = "2b0c7d3e-5a61-4f3a-8f7e-0d9c4b1a6e52"]
+pub struct Palette {
+    /// Map of original RGB colors to the colors they should be replaced with
+    pub swaps: HashMap<[u8; 3], [u8; 3]>,
+}
+
+/// The format of the `.palette.yaml` file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+struct PaletteYmlData {
+    /// Map of original hex colors to the hex colors they should be replaced with
+    colors: HashMap<String, String>,
+}
+
+#[derive(Default)]
+pub struct PaletteLoader;
+
+impl AssetLoader for PaletteLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_palette(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["palette.yml", "palette.yaml"]
+    }
+}
+
+async fn load_palette<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let palette: PaletteYmlData = serde_yaml::from_slice(bytes)?;
+
+    let swaps = palette
+        .colors
+        .iter()
+        .map(|(from, to)| Ok((parse_hex_color(from)?, parse_hex_color(to)?)))
+        .collect::<Result<_, AssetLoaderError>>()?;
+
+    load_context.set_default_asset(LoadedAsset::new(Palette { swaps }));
+    Ok(())
+}
+
+/// Parse a hex color in the `#rrggbb` format
+fn parse_hex_color(color: &str) -> Result<[u8; 3], AssetLoaderError> {
+    let invalid = || AssetLoaderError::InvalidColor(color.into());
+    let decoded =
+        hex::decode(color.strip_prefix('#').ok_or_else(invalid)?).map_err(|_| invalid())?;
+
+    if decoded.len() != 3 {
+        return Err(invalid());
+    }
+
+    Ok([decoded[0], decoded[1], decoded[2]])
+}
+
+//
+// Input replay loader
+//
+
+/// A recording of player controls that can be played back, i.e. for attract mode
+#[derive(TypeUuid, Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[uuid = "7d0f7a34-1c2b-4f5e-a9b8-3e6d2c1f0a47"]
+pub struct InputReplay {
+    /// The RNG seed the replay was recorded with, so that playback happens the same way
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// The segments of the replay, played in order
+    pub segments: Vec<InputReplaySegment>,
+}
+
+/// A span of time in an [`InputReplay`] where the same controls are held
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct InputReplaySegment {
+    /// How long the controls are held in seconds
+    pub duration: f32,
+    /// The controls that are held
+    #[serde(default)]
+    pub controls: Vec<ControlEvent>,
+}
+
+#[derive(Default)]
+pub struct InputReplayLoader;
+
+impl AssetLoader for InputReplayLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_input_replay(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["replay.yml", "replay.yaml"]
+    }
+}
+
+async fn load_input_replay<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let replay: InputReplay = serde_yaml::from_slice(bytes)?;
+    load_context.set_default_asset(LoadedAsset::new(replay));
+    Ok(())
+}
+
+//
+// Ability loader
+//
+
+/// An ability that the player and enemies can use, such as a fireball or a dash, loaded from an
+/// `.ability.yml` file
+///
+/// The paths in the ability file are relative to it, and are made relative to the asset
+/// directory when it is loaded.
+#[derive(TypeUuid, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[uuid = "e3b4a9d2-6f1c-4c8e-b5a7-0d92f7c31e68"]
+pub struct Ability {
+    /// The name of the ability
+    pub name: String,
+    /// The image shown for the ability in the HUD
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// How long the ability takes to be ready again after it is used, in seconds
+    #[serde(default)]
+    pub cooldown: f32,
+    /// The amount of energy it takes to use the ability. Enemies don't use energy.
+    #[serde(default)]
+    pub cost: u32,
+    /// How close the player has to be for an enemy to use the ability, in pixels
+    #[serde(default = "default_ability_ai_range")]
+    pub ai_range: f32,
+    /// The clip that the user plays once when using the ability, followed by the direction it is
+    /// facing, i.e. `attack` plays `attack_down`
+    #[serde(default)]
+    pub animation: Option<String>,
+    /// What the ability does when it is used
+    pub effects: Vec<AbilityEffect>,
+}
+
+fn default_ability_ai_range() -> f32 {
+    64.
+}
+
+/// Something that happens when an [`Ability`] is used
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub enum AbilityEffect {
+    /// Shoot a projectile in the direction the user is facing, or towards the player for enemies
+    #[serde(rename_all = "kebab-case")]
+    Projectile {
+        /// The speed of the projectile in pixels per second
+        speed: f32,
+        /// The damage done to whatever the projectile hits
+        damage: u32,
+        /// How far the projectile goes before disappearing, in pixels
+        range: f32,
+        /// How close the projectile has to get to something to hit it, in pixels
+        #[serde(default = "default_projectile_radius")]
+        radius: f32,
+        /// The image to show for the projectile
+        sprite: String,
+    },
+    /// Damage everything around the user
+    Area {
+        /// The radius of the area in pixels
+        radius: f32,
+        /// The damage done to everything in the area
+        damage: u32,
+    },
+    /// Move the user quickly in the direction it is facing
+    Dash {
+        /// The speed of the dash in pixels per second
+        speed: f32,
+        /// How long the dash lasts in seconds
+        duration: f32,
+    },
+    /// Shoot a grappling hook in the direction the user is facing and pull the user to where it
+    /// catches, which is either a wall or a `HookPoint` entity placed in the map
+    Grapple {
+        /// How far the hook reaches, in pixels
+        range: f32,
+        /// How fast the user is pulled, on average, in pixels per second
+        speed: f32,
+    },
+    /// Restore the user's health
+    Heal {
+        /// The amount of health to restore
+        amount: u32,
+    },
+    /// Place a bomb at the user's feet. Players use up one of the bomb item from their inventory
+    /// and can't use the ability without one, while enemies don't need to carry it.
+    Bomb {
+        /// The path to the `.item.yml` file of the bomb, which must have `bomb` settings
+        item: String,
+    },
+    /// Spawn enemies around the user. Only enemies can summon.
+    Summon {
+        /// The number of enemies to spawn
+        count: u32,
+        /// The `.animations.yml` file for the summoned enemies, which play their `idle` clip
+        #[serde(default)]
+        animations: Option<String>,
+    },
+}
+
+fn default_projectile_radius() -> f32 {
+    4.
+}
+
+#[derive(Default)]
+pub struct AbilityLoader;
+
+impl AssetLoader for AbilityLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_ability(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ability.yml", "ability.yaml"]
+    }
+}
+
+async fn load_ability<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let mut ability: Ability = serde_yaml::from_slice(bytes)?;
+
+    if ability.effects.is_empty() {
+        return Err(AssetLoaderError::InvalidAbility(format!(
+            "the `{}` ability has no effects",
+            ability.name
+        )));
+    }
+
+    // Make the paths relative to the asset directory
+    let resolve = |path: &mut String| *path = resolve_asset_path(load_context, path);
+    if let Some(icon) = &mut ability.icon {
+        resolve(icon);
+    }
+    for effect in &mut ability.effects {
+        match effect {
+            AbilityEffect::Projectile { sprite, .. } => resolve(sprite),
+            AbilityEffect::Bomb { item } => resolve(item),
+            AbilityEffect::Summon {
+                animations: Some(animations),
+                ..
+            } => resolve(animations),
+            _ => (),
+        }
+    }
+
+    load_context.set_default_asset(LoadedAsset::new(ability));
+    Ok(())
+}
+
+//
+// Item loader
+//
+
+/// An item that the player can carry, which may be a piece of equipment that changes the stats
+/// of whoever wears it, loaded from an `.item.yml` file
+///
+/// The paths in the item file are relative to it, and are made relative to the asset directory
+/// when it is loaded.
+#[derive(TypeUuid, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[uuid = "4a8c2e71-93d5-4b0f-8e16-c7a35d9b2f04"]
+pub struct Item {
+    /// The name of the item
+    pub name: String,
+    /// The image shown for the item in menus
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// The equipment slot the item is worn in. Equipping an item replaces the one in its slot.
+    /// Items without a slot can't be worn, i.e. crafting materials.
+    #[serde(default)]
+    pub slot: Option<EquipmentSlot>,
+    /// The changes the item makes to the wearer's stats
+    #[serde(default)]
+    pub stats: StatModifiers,
+    /// A sprite sheet to draw over the wearer's sprite, with the same layout as the wearer's
+    /// sprite sheet, i.e. to show a sword in the character's hand
+    #[serde(default)]
+    pub sprite_sheet: Option<String>,
+    /// The health restored by using the item in a battle, which uses it up. Items that don't heal
+    /// can't be used in battles.
+    #[serde(default)]
+    pub heal: u32,
+    /// Makes the item a bomb that can be placed with an ability that has a `bomb` effect
+    #[serde(default)]
+    pub bomb: Option<BombItem>,
+}
+
+/// The settings for an [`Item`] that is a bomb
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct BombItem {
+    /// How long after the bomb is placed that it explodes, in seconds
+    #[serde(default = "default_bomb_fuse")]
+    pub fuse: f32,
+    /// The radius of the explosion in pixels
+    pub radius: f32,
+    /// The damage done to everything in the explosion, including the player. Bombs that do at
+    /// least the `min-damage` of the game's `secret-walls` break secret walls.
+    pub damage: u32,
+    /// The image shown for the placed bomb, which defaults to the item's `icon`
+    #[serde(default)]
+    pub sprite: Option<String>,
+    /// The image shown where the bomb exploded
+    #[serde(default)]
+    pub explosion_sprite: Option<String>,
+    /// How long the explosion image is shown for, in seconds
+    #[serde(default = "default_explosion_duration")]
+    pub explosion_duration: f32,
+    /// Sound played when the bomb explodes
+    #[serde(default)]
+    pub explosion_sound: Option<String>,
+}
+
+fn default_bomb_fuse() -> f32 {
+    2.
+}
+
+fn default_explosion_duration() -> f32 {
+    0.5
+}
+
+/// The slot that an [`Item`] is worn in. The sprites of items are drawn over the wearer in this
+/// order.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum EquipmentSlot {
+    Armor,
+    Accessory,
+    Weapon,
+}
+
+/// The amounts that an [`Item`] adds to its wearer's stats, which may be negative
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct StatModifiers {
+    pub max_health: i32,
+    pub max_energy: i32,
+    pub walk_speed: f32,
+    /// Extra damage done by the wearer's abilities
+    pub attack: i32,
+    /// Reduces the damage the wearer takes
+    pub defense: i32,
+}
+
+#[derive(Default)]
+pub struct ItemLoader;
+
+impl AssetLoader for ItemLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_item(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["item.yml", "item.yaml"]
+    }
+}
+
+async fn load_item<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let mut item: Item = serde_yaml::from_slice(bytes)?;
+
+    // Make the paths relative to the asset directory
+    let bomb_paths = item.bomb.iter_mut().flat_map(|x| {
+        x.sprite
+            .iter_mut()
+            .chain(x.explosion_sprite.iter_mut())
+            .chain(x.explosion_sound.iter_mut())
+    });
+    for path in item
+        .icon
+        .iter_mut()
+        .chain(item.sprite_sheet.iter_mut())
+        .chain(bomb_paths)
+    {
+        *path = resolve_asset_path(load_context, path);
+    }
+
+    load_context.set_default_asset(LoadedAsset::new(item));
+    Ok(())
+}
+
+//
+// Recipe loader
+//
+
+/// A recipe for crafting an item out of other items, loaded from a `.recipe.yml` file
+///
+/// Items are referred to by the paths to their `.item.yml` files, relative to the recipe file. The
+/// paths are made relative to the asset directory when the recipe is loaded, the same way that
+/// items are identified in the player's inventory.
+#[derive(TypeUuid, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[uuid = "b7e0d5c3-28a4-4f9b-91c6-5e3f8a0d7b12"]
+pub struct Recipe {
+    /// The name of the recipe, which defaults to the name of the item it makes
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The crafting station the recipe has to be made at. Recipes without a station can be made
+    /// anywhere.
+    #[serde(default)]
+    pub station: Option<String>,
+    /// The items used up by the recipe, and how many of each
+    pub inputs: HashMap<String, u32>,
+    /// The item the recipe makes
+    pub output: String,
+    /// How many of the item the recipe makes
+    #[serde(default = "default_recipe_count")]
+    pub count: u32,
+}
+
+fn default_recipe_count() -> u32 {
+    1
+}
+
+#[derive(Default)]
+pub struct RecipeLoader;
+
+impl AssetLoader for RecipeLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_recipe(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["recipe.yml", "recipe.yaml"]
+    }
+}
+
+async fn load_recipe<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let mut recipe: Recipe = serde_yaml::from_slice(bytes)?;
+
+    // Make the paths relative to the asset directory
+    recipe.inputs = recipe
+        .inputs
+        .into_iter()
+        .map(|(path, count)| (resolve_asset_path(load_context, &path), count))
+        .collect();
+    recipe.output = resolve_asset_path(load_context, &recipe.output);
+
+    load_context.set_default_asset(LoadedAsset::new(recipe));
+    Ok(())
+}
+
+//
+// NPC loader
+//
+
+/// A character that the player can talk to and give gifts, loaded from an `.npc.yml` file
+///
+/// How much the NPC likes the player is tracked in the [`Affinities`][super::Affinities], keyed by
+/// the path to this file.
+#[derive(TypeUuid, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[uuid = "3a6e9b14-7d2c-4f85-b0e1-96c4d8a2f057"]
+pub struct Npc {
+    pub name: String,
+    /// What the NPC says when the player talks to them. The first branch with a condition that is
+    /// met is used.
+    #[serde(default)]
+    pub dialogue: Vec<DialogueBranch>,
+    /// How much the NPC's affinity changes when given each item, by the path to the item relative
+    /// to the file
+    #[serde(default)]
+    pub gifts: HashMap<String, i32>,
+    /// How much the NPC's affinity changes when given an item that isn't in the `gifts`
+    #[serde(default = "default_npc_gift_affinity")]
+    pub default_gift_affinity: i32,
+}
+
+fn default_npc_gift_affinity() -> i32 {
+    1
+}
+
+impl Npc {
+    /// Get the dialogue branch to use at the given affinity
+    pub fn dialogue_at(&self, affinity: i32) -> Option<&DialogueBranch> {
+        self.dialogue.iter().find(|x| x.condition.is_met(affinity))
+    }
+}
+
+/// Something an [`Npc`] can say, with the choices the player can answer with
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct DialogueBranch {
+    /// When the NPC says this. Branches without a condition are always used.
+    #[serde(default)]
+    pub condition: DialogueCondition,
+    pub text: String,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+}
+
+/// The condition for using a [`DialogueBranch`], based on how much the NPC likes the player
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct DialogueCondition {
+    /// The lowest affinity the NPC can have for the branch to be used
+    pub min_affinity: Option<i32>,
+    /// The highest affinity the NPC can have for the branch to be used
+    pub max_affinity: Option<i32>,
+}
+
+impl DialogueCondition {
+    /// Get whether the condition is met at the given affinity
+    pub fn is_met(&self, affinity: i32) -> bool {
+        self.min_affinity.map(|x| affinity >= x).unwrap_or(true)
+            && self.max_affinity.map(|x| affinity <= x).unwrap_or(true)
+    }
+}
+
+/// An answer the player can give in a [`DialogueBranch`]
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct DialogueChoice {
+    pub text: String,
+    /// How much the NPC's affinity changes when the player picks this choice
+    #[serde(default)]
+    pub affinity: i32,
+    /// What the NPC says back
+    #[serde(default)]
+    pub reply: Option<String>,
+    /// The emote the NPC shows over their head when the player picks this choice, which is seen
+    /// when the conversation is over
+    #[serde(default)]
+    pub emote: Option<Emote>,
+}
+
+#[derive(Default)]
+pub struct NpcLoader;
+
+impl AssetLoader for NpcLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_npc(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["npc.yml", "npc.yaml"]
+    }
+}
+
+async fn load_npc<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let mut npc: Npc = serde_yaml::from_slice(bytes)?;
+
+    // Make the gift paths relative to the asset directory, like the inventory
+    npc.gifts = npc
+        .gifts
+        .into_iter()
+        .map(|(path, affinity)| (resolve_asset_path(load_context, &path), affinity))
+        .collect();
+
+    load_context.set_default_asset(LoadedAsset::new(npc));
+    Ok(())
+}
+
+//
+// Crop loader
+//
+
+/// A crop that can be planted in a farming plot, loaded from a `.crop.yml` file
+///
+/// The crop grows through its stages as the in-game clock advances, and can be harvested once it
+/// reaches the last one. The paths are made relative to the asset directory when the crop is
+/// loaded.
+#[derive(TypeUuid, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[uuid = "8f1c3e6a-94b2-4d07-a5e8-2c7b0d9f4163"]
+pub struct Crop {
+    /// The path to the item that is used up to plant the crop
+    pub seed: String,
+    /// The growth stages of the crop
+    pub stages: Vec<CropStage>,
+    /// The path to the item the player gets when harvesting the crop
+    pub harvest: String,
+    /// How many of the item the player gets
+    #[serde(default = "default_crop_harvest_count")]
+    pub harvest_count: u32,
+}
+
+fn default_crop_harvest_count() -> u32 {
+    1
+}
+
+impl Crop {
+    /// Get the index of the stage the crop is at after growing for some number of hours
+    pub fn stage_at(&self, hours: f64) -> usize {
+        let mut grown = 0.;
+        for (i, stage) in self.stages.iter().enumerate() {
+            grown += stage.hours as f64;
+            if hours < grown {
+                return i;
+            }
+        }
+
+        self.stages.len().saturating_sub(1)
+    }
+
+    /// Get whether the crop is ready to harvest after growing for some number of hours
+    pub fn is_ready(&self, hours: f64) -> bool {
+        self.stage_at(hours) + 1 >= self.stages.len()
+    }
+}
+
+/// A growth stage of a [`Crop`]
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct CropStage {
+    /// The path to the image of the crop at this stage
+    pub sprite: String,
+    /// How many in-game hours the crop stays at this stage. Ignored for the last stage.
+    #[serde(default)]
+    pub hours: f32,
+}
+
+#[derive(Default)]
+pub struct CropLoader;
+
+impl AssetLoader for CropLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_crop(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["crop.yml", "crop.yaml"]
+    }
+}
+
+async fn load_crop<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let mut crop: Crop = serde_yaml::from_slice(bytes)?;
+    if crop.stages.is_empty() {
+        return Err(AssetLoaderError::InvalidCrop(
+            "crops must have at least one stage".into(),
+        ));
+    }
+
+    // Make the paths relative to the asset directory
+    crop.seed = resolve_asset_path(load_context, &crop.seed);
+    crop.harvest = resolve_asset_path(load_context, &crop.harvest);
+    for stage in &mut crop.stages {
+        stage.sprite = resolve_asset_path(load_context, &stage.sprite);
+    }
+
+    load_context.set_default_asset(LoadedAsset::new(crop));
+    Ok(())
+}
+
+//
+// Resource node loader
+//
+
+/// A place where the player can gather items by playing a timing mini-game, such as a fishing spot
+/// or an ore vein, loaded from a `.gather.yml` file
+///
+/// A marker sweeps back and forth across a bar, and the player gets the item if they stop it in
+/// the target area.
+#[derive(TypeUuid, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[uuid = "5d2f7a90-c16e-4b38-8e4a-0f93b6d1c724"]
+pub struct ResourceNode {
+    pub name: String,
+    /// The path to the item the player gets, relative to the file
+    pub item: String,
+    /// How many of the item the player gets
+    #[serde(default = "default_resource_node_count")]
+    pub count: u32,
+    /// How many times per second the marker crosses the bar
+    #[serde(default = "default_resource_node_speed")]
+    pub speed: f32,
+    /// The size of the target area, as a fraction of the bar
+    #[serde(default = "default_resource_node_target_size")]
+    pub target_size: f32,
+    /// Whether the node is used up once the player gathers from it, like an ore vein, instead of
+    /// staying around, like a fishing spot
+    #[serde(default)]
+    pub depletes: bool,
+}
+
+fn default_resource_node_count() -> u32 {
+    1
+}
+
+fn default_resource_node_speed() -> f32 {
+    1.
+}
+
+fn default_resource_node_target_size() -> f32 {
+    0.2
+}
+
+#[derive(Default)]
+pub struct ResourceNodeLoader;
+
+impl AssetLoader for ResourceNodeLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_resource_node(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gather.yml", "gather.yaml"]
+    }
+}
+
+async fn load_resource_node<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let mut node: ResourceNode = serde_yaml::from_slice(bytes)?;
+
+    // Make the item path relative to the asset directory
+    node.item = resolve_asset_path(load_context, &node.item);
+    node.target_size = node.target_size.clamp(0., 1.);
+
+    load_context.set_default_asset(LoadedAsset::new(node));
+    Ok(())
+}
+
+//
+// Cutscene loader
+//
+
+/// A cutscene played when the player interacts with a prop with a `cutscene:` interaction
+/// followed by the path to the cutscene, i.e. `cutscene:cutscenes/bridge.cutscene.yml`, loaded
+/// from a `.cutscene.yml` file
+#[derive(TypeUuid, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+#[uuid = "da46fc06-4da6-49cc-bb03-2c324f4bf1e3"]
+pub struct Cutscene {
+    /// The steps of the cutscene, played one after the other
+    pub steps: Vec<CutsceneStep>,
+    /// Whether the player's controls are locked when the cutscene starts. They can be locked and
+    /// unlocked by the steps, and are always unlocked when the cutscene is over.
+    #[serde(default = "default_cutscene_lock_input")]
+    pub lock_input: bool,
+}
+
+fn default_cutscene_lock_input() -> bool {
+    true
+}
+
+/// A step of a [`Cutscene`]
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub enum CutsceneStep {
+    /// Slide the letterbox bars in or out
+    Letterbox(bool),
+    /// Pan the camera to a position relative to the top-left corner of the current level over
+    /// `duration` seconds, then hold it there for `hold` seconds
+    #[serde(rename_all = "kebab-case")]
+    Pan {
+        to: (f32, f32),
+        #[serde(default = "default_cutscene_pan_duration")]
+        duration: f32,
+        #[serde(default)]
+        hold: f32,
+    },
+    /// Pan the camera back to the player over the given number of seconds, after which it follows
+    /// the player again
+    ReturnCamera(f32),
+    /// Wait for the given number of seconds
+    Wait(f32),
+    /// Lock or unlock the player's controls
+    LockInput(bool),
+}
+
+fn default_cutscene_pan_duration() -> f32 {
+    1.
+}
+
+#[derive(Default)]
+pub struct CutsceneLoader;
+
+impl AssetLoader for CutsceneLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_cutscene(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cutscene.yml", "cutscene.yaml"]
+    }
+}
+
+async fn load_cutscene<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let cutscene: Cutscene = serde_yaml::from_slice(bytes)?;
+    load_context.set_default_asset(LoadedAsset::new(cutscene));
+    Ok(())
+}
+
+/// Get the path to an asset referred to by the asset being loaded, relative to the asset directory
+/// and with `/` separators, i.e. `items/sword.item.yml` for `../items/sword.item.yml` in
+/// `recipes/sword.recipe.yml`
+pub fn resolve_asset_path(load_context: &bevy::asset::LoadContext, path: &str) -> String {
+    let mut components = Vec::new();
+    for component in load_context
+        .path()
+        .parent()
+        .unwrap()
+        .join(path)
+        .components()
+    {
+        match component {
+            Component::ParentDir => {
+                components.pop();
+            }
+            Component::Normal(name) => components.push(name.to_string_lossy().into_owned()),
+            _ => (),
+        }
+    }
+
+    components.join("/")
+}
+
+//
+// Bitmap font loader
+//
+
+/// The format of the `.font.yaml` file, describing a pixel font drawn on a grid in a glyph sheet
+/// image
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+struct BitmapFontYmlData {
+    /// The path to the glyph sheet image, relative to the font file
+    glyph_sheet: String,
+    /// The size of each glyph cell in the sheet
+    grid_size: (u32, u32),
+    /// The characters in the sheet, in order from left to right and top to bottom
+    charset: String,
+    /// The row of the glyph cell, from the top, that the text sits on. Defaults to the bottom of
+    /// the cell.
+    #[serde(default)]
+    baseline: Option<u32>,
+    /// The number of pixels between each character
+    #[serde(default = "default_font_spacing")]
+    spacing: i32,
+    /// Adjustments, in pixels, to the space after specific characters, i.e. to tighten up narrow
+    /// letters like `i`
+    #[serde(default)]
+    kerning: HashMap<char, i32>,
+}
+
+fn default_font_spacing() -> i32 {
+    1
+}
+
+/// The size of the characters in a bitmap font, used to lay out rich text. Loaded as the
+/// `FontMetrics` labeled asset of a `.font.yaml` file.
+#[derive(TypeUuid, Clone, Debug)]
+#[uuid = "c3e1b4a2-8d5f-4e7a-9b6c-1f2a3d4e5b60"]
+pub struct FontMetrics {
+    /// The height of a line of text
+    pub line_height: u32,
+    /// The space taken up by each character
+    pub advances: HashMap<char, u32>,
+    /// The space taken up by characters that aren't in the font
+    pub default_advance: u32,
+}
+
+impl FontMetrics {
+    /// Get the width of a string of text
+    pub fn text_width(&self, text: &str) -> u32 {
+        text.chars()
+            .map(|c| *self.advances.get(&c).unwrap_or(&self.default_advance))
+            .sum()
+    }
+}
+
+/// Loads `.font.yaml` bitmap fonts into Bevy Retrograde [`Font`]s, so they can be used anywhere a
+/// font name can be used, such as the UI theme
+#[derive(Default)]
+pub struct BitmapFontLoader;
+
+impl AssetLoader for BitmapFontLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let bdf = load_bitmap_font(bytes, load_context).await?;
+
+            // Let Bevy Retrograde load the font from the generated BDF
+            bevy_retrograde::text::FontLoader::default()
+                .load(bdf.as_bytes(), load_context)
+                .await
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["font.yml", "font.yaml"]
+    }
+}
+
+/// Load a bitmap font and convert it to the BDF font format
+async fn load_bitmap_font<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<String, AssetLoaderError> {
+    let font: BitmapFontYmlData = serde_yaml::from_slice(bytes)?;
+
+    // Load the glyph sheet
+    let glyph_sheet_path = load_context
+        .path()
+        .parent()
+        .unwrap()
+        .join(&font.glyph_sheet);
+    let glyph_sheet_bytes = load_context.read_asset_bytes(&glyph_sheet_path).await?;
+    let glyph_sheet =
+        bevy_retrograde::core::image::load_from_memory(&glyph_sheet_bytes)?.to_rgba8();
+
+    let (cell_width, cell_height) = font.grid_size;
+    if cell_width == 0 || cell_height == 0 {
+        return Err(AssetLoaderError::InvalidFont(
+            "grid size must not be zero".into(),
+        ));
+    }
+    let columns = glyph_sheet.width() / cell_width;
+    let rows = glyph_sheet.height() / cell_height;
+    let char_count = font.charset.chars().count() as u32;
+    if char_count > columns * rows {
+        return Err(AssetLoaderError::InvalidFont(format!(
+            "charset has {} characters but the glyph sheet only fits {}",
+            char_count,
+            columns * rows
+        )));
+    }
+
+    let ascent = font.baseline.unwrap_or(cell_height).min(cell_height);
+    let descent = cell_height - ascent;
+    let font_name = load_context.path().to_string_lossy().replace(' ', "_");
+
+    // Write the BDF header
+    let mut bdf = String::new();
+    bdf.push_str("STARTFONT 2.1\n");
+    bdf.push_str(&format!("FONT {}\n", font_name));
+    bdf.push_str(&format!("SIZE {} 75 75\n", cell_height));
+    bdf.push_str(&format!(
+        "FONTBOUNDINGBOX {} {} 0 -{}\n",
+        cell_width, cell_height, descent
+    ));
+    bdf.push_str("STARTPROPERTIES 2\n");
+    bdf.push_str(&format!("FONT_ASCENT {}\n", ascent));
+    bdf.push_str(&format!("FONT_DESCENT {}\n", descent));
+    bdf.push_str("ENDPROPERTIES\n");
+    bdf.push_str(&format!("CHARS {}\n", char_count));
+
+    let mut metrics = FontMetrics {
+        line_height: cell_height,
+        advances: HashMap::default(),
+        default_advance: (cell_width as i32 + font.spacing).max(0) as u32,
+    };
+
+    // Write each glyph, counting any non-transparent pixel as set
+    for (i, c) in font.charset.chars().enumerate() {
+        let cell_x = (i as u32 % columns) * cell_width;
+        let cell_y = (i as u32 / columns) * cell_height;
+        let advance =
+            (cell_width as i32 + font.spacing + font.kerning.get(&c).copied().unwrap_or(0)).max(0);
+        metrics.advances.insert(c, advance as u32);
+
+        bdf.push_str(&format!("STARTCHAR U+{:04X}\n", c as u32));
+        bdf.push_str(&format!("ENCODING {}\n", c as u32));
+        bdf.push_str(&format!("DWIDTH {} 0\n", advance));
+        bdf.push_str(&format!(
+            "BBX {} {} 0 -{}\n",
+            cell_width, cell_height, descent
+        ));
+        bdf.push_str("BITMAP\n");
+        for y in 0..cell_height {
+            // Each row is padded out to a whole number of bytes
+            let mut row = vec![0u8; ((cell_width + 7) / 8) as usize];
+            for x in 0..cell_width {
+                if glyph_sheet.get_pixel(cell_x + x, cell_y + y)[3] > 127 {
+                    row[(x / 8) as usize] |= 0x80 >> (x % 8);
+                }
+            }
+            bdf.push_str(&hex::encode_upper(row));
+            bdf.push('\n');
+        }
+        bdf.push_str("ENDCHAR\n");
+    }
+    bdf.push_str("ENDFONT\n");
+
+    load_context.set_labeled_asset("FontMetrics", LoadedAsset::new(metrics));
+
+    Ok(bdf)
 }
@@ -0,0 +1,738 @@
+//! Loader for maps made in the [Tiled](https://www.mapeditor.org/) map editor
+//!
+//! A single `.tmx` map is loaded as a map with one level, and a `.world` file is loaded as a map
+//! with a level for every map in the world. Tile layers are rendered to images when the map loads.
+
+use bevy_retrograde::core::image::{
+    load_from_memory, DynamicImage, GenericImageView, Pixel, RgbaImage,
+};
+
+use super::*;
+
+/// The tile flip flags stored in the top bits of Tiled tile GIDs
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+
+/// The amount to offset the tileset IDs of each map in a world by, so that they don't collide
+const WORLD_MAP_TILESET_ID_STRIDE: i32 = 1 << 20;
+
+/// A map loaded from a Tiled `.tmx` or `.world` file
+#[derive(TypeUuid)]
+#[uuid = "0d6c2f8e-7b41-4a39-8e5f-2a9c4b7d1e63"]
+pub struct TiledMap {
+    /// The map data
+    pub map: GameMap,
+    /// The rendered tile layers
+    pub layers: Vec<TiledMapLayerImage>,
+}
+
+/// A rendered tile layer in a [`TiledMap`]
+pub struct TiledMapLayerImage {
+    /// The identifier of the level the layer is in
+    pub level: String,
+    /// The identifier of the layer
+    pub layer: String,
+    /// The position of the top-left corner of the layer in the world
+    pub position: Vec3,
+    pub image: Handle<Image>,
+}
+
+#[derive(Default)]
+pub struct TiledMapLoader;
+
+impl AssetLoader for TiledMapLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { Ok(load_tiled_map(bytes, load_context).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmx", "world"]
+    }
+}
+
+/// A Tiled world file
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TiledWorld {
+    maps: Vec<TiledWorldMap>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TiledWorldMap {
+    file_name: String,
+    x: i32,
+    y: i32,
+}
+
+async fn load_tiled_map<'a, 'b>(
+    bytes: &'a [u8],
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+) -> Result<(), AssetLoaderError> {
+    let map_path = load_context.path().to_owned();
+    let map_dir = map_path
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .to_owned();
+
+    // Get the maps to load and their positions
+    let is_world = map_path.extension().map(|x| x == "world").unwrap_or(false);
+    let maps = if is_world {
+        let world: TiledWorld = serde_yaml::from_slice(bytes)?;
+        let mut maps = Vec::new();
+        for world_map in world.maps {
+            let path = map_dir.join(&world_map.file_name);
+            let bytes = load_context.read_asset_bytes(&path).await?;
+            maps.push((
+                path,
+                bytes,
+                Vec2::new(world_map.x as f32, world_map.y as f32),
+            ));
+        }
+        maps
+    } else {
+        vec![(map_path.clone(), bytes.to_vec(), Vec2::ZERO)]
+    };
+
+    let mut game_map = GameMap::default();
+    let mut layers = Vec::new();
+    let mut dependencies = Vec::new();
+    for (i, (path, bytes, position)) in maps.into_iter().enumerate() {
+        let tileset_id_offset = i as i32 * WORLD_MAP_TILESET_ID_STRIDE;
+        let level = load_tmx(
+            &path,
+            &bytes,
+            position,
+            tileset_id_offset,
+            load_context,
+            &mut game_map,
+            &mut layers,
+            &mut dependencies,
+        )
+        .await?;
+        game_map.levels.push(level);
+    }
+
+    // Move the instanced levels, and the layers with them
+    game_map.place_instanced_levels();
+    for layer in &mut layers {
+        if let Some(level) = game_map.level(&layer.level) {
+            layer.position += level.instance_offset.extend(0.);
+        }
+    }
+
+    let mut asset = LoadedAsset::new(TiledMap {
+        map: game_map,
+        layers,
+    });
+    for dependency in dependencies {
+        asset = asset.with_dependency(dependency);
+    }
+    load_context.set_default_asset(asset);
+
+    Ok(())
+}
+
+/// A tileset in a map being loaded
+struct LoadingTileset {
+    /// The GID of the first tile in the tileset
+    first_gid: u32,
+    /// The ID of the tileset in the [`GameMap`]
+    id: i32,
+    tile_width: u32,
+    tile_height: u32,
+    columns: u32,
+    image: DynamicImage,
+}
+
+/// Load a `.tmx` map as a level, adding its tilesets to the game map and rendering its layers
+#[allow(clippy::too_many_arguments)]
+async fn load_tmx<'a, 'b>(
+    path: &Path,
+    bytes: &[u8],
+    position: Vec2,
+    tileset_id_offset: i32,
+    load_context: &'a mut bevy::asset::LoadContext<'b>,
+    game_map: &mut GameMap,
+    layer_images: &mut Vec<TiledMapLayerImage>,
+    dependencies: &mut Vec<AssetPath<'static>>,
+) -> Result<GameLevel, AssetLoaderError> {
+    let map = parse_xml(bytes)?;
+    if map.name != "map" {
+        return Err(AssetLoaderError::InvalidTiledMap(format!(
+            "{}: expected a `map` element",
+            path.display()
+        )));
+    }
+    if map.attr("orientation").unwrap_or("orthogonal") != "orthogonal" {
+        return Err(AssetLoaderError::InvalidTiledMap(format!(
+            "{}: only orthogonal maps are supported",
+            path.display()
+        )));
+    }
+    if map.attr("infinite") == Some("1") {
+        return Err(AssetLoaderError::InvalidTiledMap(format!(
+            "{}: infinite maps are not supported",
+            path.display()
+        )));
+    }
+
+    let map_dir = path.parent().unwrap_or_else(|| Path::new("")).to_owned();
+    let width: u32 = map.parse_attr("width")?;
+    let height: u32 = map.parse_attr("height")?;
+    let tile_width: u32 = map.parse_attr("tilewidth")?;
+    let tile_height: u32 = map.parse_attr("tileheight")?;
+
+    // Load the tilesets
+    let mut tilesets = Vec::new();
+    for tileset_ref in map.children_named("tileset") {
+        let first_gid: u32 = tileset_ref.parse_attr("firstgid")?;
+
+        // Load external tilesets from their `.tsx` file
+        let (tileset, tileset_dir) = if let Some(source) = tileset_ref.attr("source") {
+            let tileset_path = map_dir.join(source);
+            let tileset = parse_xml(&load_context.read_asset_bytes(&tileset_path).await?)?;
+            let tileset_dir = tileset_path
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .to_owned();
+            (tileset, tileset_dir)
+        } else {
+            (tileset_ref.clone(), map_dir.clone())
+        };
+
+        let image_element = tileset.child("image").ok_or_else(|| {
+            AssetLoaderError::InvalidTiledMap(format!(
+                "tileset `{}` has no image, image collection tilesets are not supported",
+                tileset.attr("name").unwrap_or_default()
+            ))
+        })?;
+        let image_path = tileset_dir.join(image_element.attr("source").unwrap_or_default());
+        let image = load_from_memory(&load_context.read_asset_bytes(&image_path).await?)?;
+
+        let id = tileset_id_offset + first_gid as i32;
+        let tile_width: u32 = tileset.parse_attr("tilewidth")?;
+        let tile_height: u32 = tileset.parse_attr("tileheight")?;
+        let columns: u32 = tileset.parse_attr("columns")?;
+
+        // Get the tile metadata from the tiles' `data` properties, and the tile tags from their
+        // classes
+        let mut tiles = HashMap::default();
+        let mut tags = HashMap::default();
+        for tile in tileset.children_named("tile") {
+            let tile_id: i32 = tile.parse_attr("id")?;
+            if let Some(class) = tile.attr("class").or_else(|| tile.attr("type")) {
+                tags.insert(tile_id, vec![class.to_owned()]);
+            }
+
+            let data = if let Some(data) = properties(tile).remove("data") {
+                data
+            } else {
+                continue;
+            };
+            match data
+                .as_str()
+                .map(serde_yaml::from_str::<TilesetTileMetadata>)
+            {
+                Some(Ok(metadata)) => {
+                    tiles.insert(tile_id, metadata);
+                }
+                _ => warn!(
+                    %tile_id,
+                    tileset_id=%tileset.attr("name").unwrap_or_default(),
+                    "Could not parse tileset tile metadata, ignoring"
+                ),
+            }
+        }
+
+        let image_asset_path = AssetPath::new(image_path, None);
+        game_map.tilesets.insert(
+            id,
+            GameTileset {
+                identifier: tileset.attr("name").unwrap_or_default().into(),
+                image: load_context.get_handle(image_asset_path.clone()),
+                tile_size: tile_width,
+                columns,
+                tiles,
+                tags,
+            },
+        );
+        dependencies.push(image_asset_path);
+
+        tilesets.push(LoadingTileset {
+            first_gid,
+            id,
+            tile_width,
+            tile_height,
+            columns,
+            image,
+        });
+    }
+    // Sort the tilesets so we can find the tileset for a GID
+    tilesets.sort_by_key(|x| x.first_gid);
+
+    let mut fields = properties(&map);
+    let identifier = fields
+        .remove("identifier")
+        .and_then(|x| x.as_str().map(String::from))
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|x| x.to_string_lossy().into())
+                .unwrap_or_default()
+        });
+
+    let mut level = GameLevel {
+        identifier,
+        position,
+        instance_offset: Vec2::ZERO,
+        size: UVec2::new(width * tile_width, height * tile_height),
+        grid_size: tile_width,
+        background_color: map.attr("backgroundcolor").and_then(parse_map_color),
+        fields,
+        layers: Vec::new(),
+        entities: Vec::new(),
+    };
+
+    // Load the layers, including the layers in groups
+    let mut elements = Vec::new();
+    flatten_layers(&map, Vec2::ZERO, true, &mut elements);
+    for (element, offset, visible) in elements {
+        match element.name.as_str() {
+            "layer" => {
+                let layer_width: u32 = element.parse_attr("width")?;
+                let gids = layer_gids(element)?;
+
+                // Render the layer
+                let mut image = RgbaImage::new(level.size.x, level.size.y);
+                let mut tiles = Vec::new();
+                for (i, &gid) in gids.iter().enumerate() {
+                    let flags =
+                        gid & (FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY);
+                    let gid = gid & !flags;
+                    if gid == 0 {
+                        continue;
+                    }
+
+                    let tileset =
+                        if let Some(tileset) = tilesets.iter().rev().find(|x| x.first_gid <= gid) {
+                            tileset
+                        } else {
+                            continue;
+                        };
+                    let tile_id = gid - tileset.first_gid;
+
+                    // Tiles are drawn from the bottom-left corner of their cell
+                    let cell_x = (i as u32 % layer_width) * tile_width;
+                    let cell_y = (i as u32 / layer_width) * tile_height;
+                    let tile_y = cell_y as i32 + tile_height as i32 - tileset.tile_height as i32;
+                    tiles.push(GameTile {
+                        position: IVec2::new(cell_x as i32, tile_y),
+                        tileset: tileset.id,
+                        id: tile_id as i32,
+                    });
+
+                    if visible {
+                        draw_tile(&mut image, tileset, tile_id, flags, cell_x as i32, tile_y);
+                    }
+                }
+
+                let identifier = element.attr("name").unwrap_or_default().to_owned();
+                if visible {
+                    let image_handle = load_context.set_labeled_asset(
+                        &format!("{}/{}", level.identifier, identifier),
+                        LoadedAsset::new(Image(DynamicImage::ImageRgba8(image))),
+                    );
+                    let z = level.layers.len() as f32 * LAYER_DEPTH;
+                    layer_images.push(TiledMapLayerImage {
+                        level: level.identifier.clone(),
+                        layer: identifier.clone(),
+                        position: (position + offset).extend(z),
+                        image: image_handle,
+                    });
+                }
+
+                // Roof layers can also be marked with a `roof` property
+                let roof = is_roof_layer(&identifier)
+                    || properties(element)
+                        .get("roof")
+                        .and_then(|x| x.as_bool())
+                        .unwrap_or(false);
+
+                level.layers.push(GameLayer {
+                    identifier,
+                    offset,
+                    grid_size: tile_width,
+                    columns: layer_width,
+                    tiles,
+                    cells: gids.iter().map(|&x| (x != 0) as i32).collect(),
+                    roof,
+                });
+            }
+            "objectgroup" => {
+                for object in element.children_named("object") {
+                    let mut fields = properties(object);
+                    if let Some(name) = object.attr("name") {
+                        fields
+                            .entry("name".into())
+                            .or_insert_with(|| serde_yaml::Value::String(name.into()));
+                    }
+
+                    let z = entity_z(level.layers.len(), &fields);
+                    level.entities.push(GameEntity {
+                        // Tiled 1.9 renamed the object `type` to `class`
+                        identifier: object
+                            .attr("class")
+                            .or_else(|| object.attr("type"))
+                            .or_else(|| object.attr("name"))
+                            .unwrap_or_default()
+                            .into(),
+                        position: offset
+                            + Vec2::new(
+                                object.parse_attr_or("x", 0.)?,
+                                object.parse_attr_or("y", 0.)?,
+                            ),
+                        size: Vec2::new(
+                            object.parse_attr_or("width", 0.)?,
+                            object.parse_attr_or("height", 0.)?,
+                        ),
+                        z,
+                        fields,
+                    });
+                }
+
+                // Add the object layer so that the layers above it get the right depth
+                level.layers.push(GameLayer {
+                    identifier: element.attr("name").unwrap_or_default().to_owned(),
+                    offset,
+                    grid_size: tile_width,
+                    ..Default::default()
+                });
+            }
+            _ => (),
+        }
+    }
+
+    Ok(level)
+}
+
+/// Draw a tile from a tileset onto a layer image, applying the Tiled flip flags
+fn draw_tile(
+    image: &mut RgbaImage,
+    tileset: &LoadingTileset,
+    tile_id: u32,
+    flags: u32,
+    x: i32,
+    y: i32,
+) {
+    let (tile_width, tile_height) = (tileset.tile_width, tileset.tile_height);
+    let source_x = (tile_id % tileset.columns.max(1)) * tile_width;
+    let source_y = (tile_id / tileset.columns.max(1)) * tile_height;
+
+    for dy in 0..tile_height {
+        for dx in 0..tile_width {
+            // Undo the flips to find the source pixel, the diagonal flip being applied first
+            let mut sx = dx;
+            let mut sy = dy;
+            if flags & FLIPPED_HORIZONTALLY != 0 {
+                sx = tile_width - 1 - sx;
+            }
+            if flags & FLIPPED_VERTICALLY != 0 {
+                sy = tile_height - 1 - sy;
+            }
+            if flags & FLIPPED_DIAGONALLY != 0 {
+                std::mem::swap(&mut sx, &mut sy);
+            }
+
+            let (to_x, to_y) = (x + dx as i32, y + dy as i32);
+            if to_x < 0
+                || to_y < 0
+                || to_x as u32 >= image.width()
+                || to_y as u32 >= image.height()
+                || !tileset.image.in_bounds(source_x + sx, source_y + sy)
+            {
+                continue;
+            }
+
+            let pixel = tileset.image.get_pixel(source_x + sx, source_y + sy);
+            image.get_pixel_mut(to_x as u32, to_y as u32).blend(&pixel);
+        }
+    }
+}
+
+/// Collect the layers in a map or group, with their offsets and visibility
+fn flatten_layers<'a>(
+    element: &'a XmlElement,
+    offset: Vec2,
+    visible: bool,
+    layers: &mut Vec<(&'a XmlElement, Vec2, bool)>,
+) {
+    for child in &element.children {
+        let offset = offset
+            + Vec2::new(
+                child.parse_attr_or("offsetx", 0.).unwrap_or(0.),
+                child.parse_attr_or("offsety", 0.).unwrap_or(0.),
+            );
+        let visible = visible && child.attr("visible") != Some("0");
+
+        match child.name.as_str() {
+            "layer" | "objectgroup" => layers.push((child, offset, visible)),
+            "group" => flatten_layers(child, offset, visible, layers),
+            _ => (),
+        }
+    }
+}
+
+/// Get the tile GIDs in a tile layer
+fn layer_gids(layer: &XmlElement) -> Result<Vec<u32>, AssetLoaderError> {
+    let invalid = |message: &str| {
+        AssetLoaderError::InvalidTiledMap(format!(
+            "layer `{}`: {}",
+            layer.attr("name").unwrap_or_default(),
+            message
+        ))
+    };
+    let data = layer.child("data").ok_or_else(|| invalid("missing data"))?;
+
+    match data.attr("encoding") {
+        Some("csv") => data
+            .text
+            .split(',')
+            .map(|x| {
+                x.trim()
+                    .parse::<u32>()
+                    .map_err(|_| invalid("invalid CSV data"))
+            })
+            .collect(),
+        Some("base64") => {
+            let bytes = base64::decode(data.text.trim()).map_err(|_| invalid("invalid base64"))?;
+            let bytes = match data.attr("compression") {
+                None => bytes,
+                Some("zlib") => miniz_oxide::inflate::decompress_to_vec_zlib(&bytes)
+                    .map_err(|_| invalid("invalid zlib data"))?,
+                Some(compression) => {
+                    return Err(invalid(&format!(
+                        "`{}` compression is not supported, use zlib or no compression",
+                        compression
+                    )))
+                }
+            };
+
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|x| u32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+                .collect())
+        }
+        _ => Err(invalid("only CSV and base64 layer data is supported")),
+    }
+}
+
+/// Get the custom properties of an element
+fn properties(element: &XmlElement) -> HashMap<String, serde_yaml::Value> {
+    element
+        .child("properties")
+        .map(|x| x.children_named("property").collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|property| {
+            let name = property.attr("name")?.to_owned();
+            // Multi-line strings are stored in the element text
+            let value = property.attr("value").unwrap_or(&property.text);
+            let value: serde_yaml::Value = match property.attr("type").unwrap_or("string") {
+                "int" => value.parse::<i64>().ok()?.into(),
+                "float" => value.parse::<f64>().ok()?.into(),
+                "bool" => (value == "true").into(),
+                _ => value.into(),
+            };
+            Some((name, value))
+        })
+        .collect()
+}
+
+//
+// XML
+//
+
+/// A parsed XML element
+#[derive(Clone, Debug, Default)]
+struct XmlElement {
+    name: String,
+    attributes: HashMap<String, String>,
+    children: Vec<XmlElement>,
+    text: String,
+}
+
+impl XmlElement {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    fn parse_attr<T: std::str::FromStr>(&self, name: &str) -> Result<T, AssetLoaderError> {
+        self.attr(name).and_then(|x| x.parse().ok()).ok_or_else(|| {
+            AssetLoaderError::InvalidTiledMap(format!(
+                "`{}` element has a missing or invalid `{}` attribute",
+                self.name, name
+            ))
+        })
+    }
+
+    fn parse_attr_or<T: std::str::FromStr>(
+        &self,
+        name: &str,
+        default: T,
+    ) -> Result<T, AssetLoaderError> {
+        if self.attr(name).is_some() {
+            self.parse_attr(name)
+        } else {
+            Ok(default)
+        }
+    }
+
+    fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|x| x.name == name)
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.children.iter().filter(move |x| x.name == name)
+    }
+}
+
+/// Parse an XML document, returning the root element
+fn parse_xml(bytes: &[u8]) -> Result<XmlElement, AssetLoaderError> {
+    use xml::reader::{EventReader, XmlEvent};
+
+    let mut stack = vec![XmlElement::default()];
+    for event in EventReader::new(bytes) {
+        match event? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => stack.push(XmlElement {
+                name: name.local_name,
+                attributes: attributes
+                    .into_iter()
+                    .map(|x| (x.name.local_name, x.value))
+                    .collect(),
+                ..Default::default()
+            }),
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().unwrap();
+                stack.last_mut().unwrap().children.push(element);
+            }
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                stack.last_mut().unwrap().text.push_str(&text);
+            }
+            _ => (),
+        }
+    }
+
+    stack
+        .pop()
+        .and_then(|x| x.children.into_iter().next())
+        .ok_or_else(|| AssetLoaderError::InvalidTiledMap("empty XML document".into()))
+}
+
+#[cfg(test)]
+mod test {
+    use bevy_retrograde::core::image::Rgba;
+
+    use super::*;
+
+    /// Make a `layer` element with the given `data` element attributes and text
+    fn layer(attributes: &[(&str, &str)], text: &str) -> XmlElement {
+        XmlElement {
+            name: "layer".into(),
+            children: vec![XmlElement {
+                name: "data".into(),
+                attributes: attributes
+                    .iter()
+                    .map(|&(name, value)| (name.into(), value.into()))
+                    .collect(),
+                text: text.into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Make a tileset with a single 2x2 tile, with a different color in every pixel
+    fn tileset() -> LoadingTileset {
+        let mut image = RgbaImage::new(2, 2);
+        image.put_pixel(0, 0, Rgba([1, 0, 0, 255]));
+        image.put_pixel(1, 0, Rgba([2, 0, 0, 255]));
+        image.put_pixel(0, 1, Rgba([3, 0, 0, 255]));
+        image.put_pixel(1, 1, Rgba([4, 0, 0, 255]));
+
+        LoadingTileset {
+            first_gid: 1,
+            id: 0,
+            tile_width: 2,
+            tile_height: 2,
+            columns: 1,
+            image: DynamicImage::ImageRgba8(image),
+        }
+    }
+
+    /// Draw the tile with the given flip flags and get the red value of each pixel, row by row
+    fn draw_flipped(flags: u32) -> [u8; 4] {
+        let mut image = RgbaImage::new(2, 2);
+        draw_tile(&mut image, &tileset(), 0, flags, 0, 0);
+        [
+            image.get_pixel(0, 0)[0],
+            image.get_pixel(1, 0)[0],
+            image.get_pixel(0, 1)[0],
+            image.get_pixel(1, 1)[0],
+        ]
+    }
+
+    #[test]
+    fn test_layer_gids() {
+        let gids = vec![1, 2, FLIPPED_HORIZONTALLY | 3, 0];
+        let bytes = gids
+            .iter()
+            .flat_map(|x| x.to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+
+        let csv = format!("\n1,2,{},\n0\n", FLIPPED_HORIZONTALLY | 3);
+        assert_eq!(
+            gids,
+            layer_gids(&layer(&[("encoding", "csv")], &csv)).unwrap()
+        );
+        assert_eq!(
+            gids,
+            layer_gids(&layer(&[("encoding", "base64")], &base64::encode(&bytes))).unwrap()
+        );
+        assert_eq!(
+            gids,
+            layer_gids(&layer(
+                &[("encoding", "base64"), ("compression", "zlib")],
+                &base64::encode(miniz_oxide::deflate::compress_to_vec_zlib(&bytes, 6)),
+            ))
+            .unwrap()
+        );
+
+        assert!(layer_gids(&layer(&[("encoding", "csv")], "1,a")).is_err());
+        assert!(layer_gids(&layer(
+            &[("encoding", "base64"), ("compression", "gzip")],
+            &base64::encode(&bytes),
+        ))
+        .is_err());
+        assert!(layer_gids(&layer(&[], "<tile gid=\"1\"/>")).is_err());
+    }
+
+    #[test]
+    fn test_draw_tile_flips() {
+        assert_eq!([1, 2, 3, 4], draw_flipped(0));
+        assert_eq!([2, 1, 4, 3], draw_flipped(FLIPPED_HORIZONTALLY));
+        assert_eq!([3, 4, 1, 2], draw_flipped(FLIPPED_VERTICALLY));
+        assert_eq!([1, 3, 2, 4], draw_flipped(FLIPPED_DIAGONALLY));
+        // Rotated 90 degrees clockwise
+        assert_eq!(
+            [3, 1, 4, 2],
+            draw_flipped(FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY)
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Validation of a game's asset directory, used by the `validate` command to catch problems
+//! before they show up as errors in the game
+
+use std::path::PathBuf;
+
+use super::*;
+
+/// A problem found in a game's assets
+#[derive(Debug, Clone)]
+pub struct AssetProblem {
+    /// The path of the asset with the problem, relative to the asset directory
+    pub path: PathBuf,
+    /// A description of the problem
+    pub message: String,
+}
+
+impl std::fmt::Display for AssetProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+/// Check every data file in an asset directory, returning the problems that were found
+///
+/// Each YAML asset is parsed the same way that its loader parses it, and the files that the game
+/// info, characters, animations, abilities, items, recipes, NPCs, crops, resource nodes and fonts
+/// refer to are checked to make sure they exist.
+pub fn validate_assets(asset_dir: &Path) -> std::io::Result<Vec<AssetProblem>> {
+    let mut validator = Validator {
+        asset_dir,
+        problems: Vec::new(),
+    };
+
+    // The game can't start without the game info
+    if !asset_dir.join("default.game.yaml").is_file() {
+        validator.problem("default.game.yaml", "the game info file does not exist");
+    }
+
+    let mut dirs = vec![asset_dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                validator.validate_file(&path)?;
+            }
+        }
+    }
+
+    let mut problems = validator.problems;
+    problems.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(problems)
+}
+
+struct Validator<'a> {
+    asset_dir: &'a Path,
+    problems: Vec<AssetProblem>,
+}
+
+impl<'a> Validator<'a> {
+    fn problem<P: Into<PathBuf>, M: ToString>(&mut self, path: P, message: M) {
+        self.problems.push(AssetProblem {
+            path: path.into(),
+            message: message.to_string(),
+        });
+    }
+
+    /// Validate a single file, if it is a kind of file that we know how to check
+    fn validate_file(&mut self, path: &Path) -> std::io::Result<()> {
+        let relative_path = path.strip_prefix(self.asset_dir).unwrap().to_owned();
+        let file_name = path
+            .file_name()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let has_ext = |exts: &[&str]| exts.iter().any(|ext| file_name.ends_with(ext));
+
+        if has_ext(&[".game.yml", ".game.yaml"]) {
+            if let Some(game_info) = self.parse::<GameInfo>(&relative_path)? {
+                self.validate_game_info(&game_info);
+            }
+        } else if has_ext(&[".character.yml", ".character.yaml"]) {
+            if let Some(character) = self.parse::<CharacterYmlData>(&relative_path)? {
+                if character.sprite_sheet.is_none() && character.aseprite.is_none() {
+                    self.problem(
+                        &relative_path,
+                        "either `sprite-sheet` or `aseprite` must be set",
+                    );
+                }
+                let sprite_sheet = character.sprite_sheet.as_ref().map(|x| x.path.as_str());
+                self.check_relative_exists(&relative_path, sprite_sheet);
+                self.check_relative_exists(&relative_path, character.aseprite.as_deref());
+                self.check_relative_exists(&relative_path, Some(&character.collision_shape));
+                for path in character.abilities.iter().chain(&character.equipment) {
+                    self.check_relative_exists(&relative_path, Some(path.as_str()));
+                }
+            }
+        } else if has_ext(&[".animations.yml", ".animations.yaml"]) {
+            if let Some(animations) = self.parse::<SpriteAnimationsYmlData>(&relative_path)? {
+                if animations.sprite_sheet.is_none() && animations.aseprite.is_none() {
+                    self.problem(
+                        &relative_path,
+                        "either `sprite-sheet` or `aseprite` must be set",
+                    );
+                }
+                let sprite_sheet = animations.sprite_sheet.as_ref().map(|x| x.path.as_str());
+                self.check_relative_exists(&relative_path, sprite_sheet);
+                self.check_relative_exists(&relative_path, animations.aseprite.as_deref());
+            }
+        } else if has_ext(&[".palette.yml", ".palette.yaml"]) {
+            if let Some(palette) = self.parse::<PaletteYmlData>(&relative_path)? {
+                for color in palette.colors.keys().chain(palette.colors.values()) {
+                    if let Err(e) = parse_hex_color(color) {
+                        self.problem(&relative_path, e);
+                    }
+                }
+            }
+        } else if has_ext(&[".ability.yml", ".ability.yaml"]) {
+            if let Some(ability) = self.parse::<Ability>(&relative_path)? {
+                if ability.effects.is_empty() {
+                    self.problem(&relative_path, "the ability has no effects");
+                }
+                self.check_relative_exists(&relative_path, ability.icon.as_deref());
+                for effect in &ability.effects {
+                    match effect {
+                        AbilityEffect::Projectile { sprite, .. } => {
+                            self.check_relative_exists(&relative_path, Some(sprite.as_str()))
+                        }
+                        AbilityEffect::Bomb { item } => {
+                            self.check_relative_exists(&relative_path, Some(item.as_str()))
+                        }
+                        AbilityEffect::Summon { animations, .. } => {
+                            self.check_relative_exists(&relative_path, animations.as_deref())
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        } else if has_ext(&[".item.yml", ".item.yaml"]) {
+            if let Some(item) = self.parse::<Item>(&relative_path)? {
+                self.check_relative_exists(&relative_path, item.icon.as_deref());
+                self.check_relative_exists(&relative_path, item.sprite_sheet.as_deref());
+                if let Some(bomb) = &item.bomb {
+                    self.check_relative_exists(&relative_path, bomb.sprite.as_deref());
+                    self.check_relative_exists(&relative_path, bomb.explosion_sprite.as_deref());
+                    self.check_relative_exists(&relative_path, bomb.explosion_sound.as_deref());
+                }
+            }
+        } else if has_ext(&[".recipe.yml", ".recipe.yaml"]) {
+            if let Some(recipe) = self.parse::<Recipe>(&relative_path)? {
+                for path in recipe.inputs.keys().chain(Some(&recipe.output)) {
+                    self.check_relative_exists(&relative_path, Some(path.as_str()));
+                }
+            }
+        } else if has_ext(&[".npc.yml", ".npc.yaml"]) {
+            if let Some(npc) = self.parse::<Npc>(&relative_path)? {
+                for path in npc.gifts.keys() {
+                    self.check_relative_exists(&relative_path, Some(path.as_str()));
+                }
+            }
+        } else if has_ext(&[".crop.yml", ".crop.yaml"]) {
+            if let Some(crop) = self.parse::<Crop>(&relative_path)? {
+                let stages = crop.stages.iter().map(|x| &x.sprite);
+                for path in stages.chain(Some(&crop.seed)).chain(Some(&crop.harvest)) {
+                    self.check_relative_exists(&relative_path, Some(path.as_str()));
+                }
+            }
+        } else if has_ext(&[".gather.yml", ".gather.yaml"]) {
+            if let Some(node) = self.parse::<ResourceNode>(&relative_path)? {
+                self.check_relative_exists(&relative_path, Some(node.item.as_str()));
+            }
+        } else if has_ext(&[".replay.yml", ".replay.yaml"]) {
+            self.parse::<InputReplay>(&relative_path)?;
+        } else if has_ext(&[".font.yml", ".font.yaml"]) {
+            if let Some(font) = self.parse::<BitmapFontYmlData>(&relative_path)? {
+                self.check_relative_exists(&relative_path, Some(&font.glyph_sheet));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parse a YAML asset, recording a problem and returning `None` if it is invalid
+    fn parse<T: serde::de::DeserializeOwned>(
+        &mut self,
+        relative_path: &Path,
+    ) -> std::io::Result<Option<T>> {
+        let bytes = std::fs::read(self.asset_dir.join(relative_path))?;
+        Ok(match serde_yaml::from_slice(&bytes) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                self.problem(relative_path, e);
+                None
+            }
+        })
+    }
+
+    /// Check that the assets referred to by the game info exist
+    fn validate_game_info(&mut self, game_info: &GameInfo) {
+        let splash_screen = &game_info.splash_screen;
+        let ui_theme = &game_info.ui_theme;
+        let mut paths = vec![
+            game_info.map.as_str(),
+            game_info.player_character.as_str(),
+            splash_screen.splash_image.path.as_str(),
+            splash_screen.music.as_str(),
+            splash_screen.copyright.font.as_str(),
+        ];
+        if let Some(attract_mode) = &splash_screen.attract_mode {
+            paths.push(&attract_mode.replay);
+        }
+        for slide in &game_info.intro {
+            paths.push(&slide.image.path);
+            paths.extend(slide.sound.as_deref());
+        }
+        paths.extend(game_info.window_icon.as_deref());
+        paths.extend(game_info.health_feedback.vignette_image.as_deref());
+        paths.extend(game_info.health_feedback.heartbeat_sound.as_deref());
+        paths.extend(
+            game_info
+                .post_processing
+                .shaders
+                .values()
+                .map(String::as_str),
+        );
+        paths.extend(game_info.cursor.image.as_deref());
+        paths.extend(game_info.shadows.as_ref().map(|x| x.sprite.as_str()));
+        paths.extend(game_info.recipes.iter().map(String::as_str));
+        paths.extend(game_info.crops.iter().map(String::as_str));
+        paths.extend(
+            game_info
+                .clock
+                .palettes
+                .iter()
+                .map(|x| x.palette.as_str())
+                .filter(|x| *x != "none"),
+        );
+        if let Some(dungeon) = &game_info.dungeon {
+            paths.extend(
+                dungeon
+                    .unlocks
+                    .iter()
+                    .flat_map(|x| &x.items)
+                    .map(String::as_str),
+            );
+        }
+
+        // Check the variants and high contrast themes along with the default theme
+        let mut themes = vec![ui_theme];
+        themes.extend(ui_theme.variants.values());
+        let high_contrast_themes = themes
+            .iter()
+            .filter_map(|x| x.high_contrast.as_deref())
+            .collect::<Vec<_>>();
+        themes.extend(high_contrast_themes);
+        for theme in themes {
+            paths.extend([
+                theme.default_font.as_str(),
+                theme.panel.image.as_str(),
+                theme.button_up.image.as_str(),
+                theme.button_down.image.as_str(),
+                theme.checkbox.checked.as_str(),
+                theme.checkbox.unchecked.as_str(),
+                theme.hud.health_background.image.as_str(),
+                theme.hud.full_heart.image.as_str(),
+                theme.hud.half_heart.image.as_str(),
+            ]);
+            paths.extend(theme.item_icons.values().map(String::as_str));
+            paths.extend(theme.emotes.values().map(String::as_str));
+            paths.extend(theme.emote_bubble.as_ref().map(|x| x.image.as_str()));
+            paths.extend(theme.text_sound.as_deref());
+        }
+        for (screen, variant) in &ui_theme.screens {
+            if !ui_theme.variants.contains_key(variant) {
+                self.problem(
+                    "default.game.yaml",
+                    format!(
+                        "the `{}` screen uses the missing `{}` UI theme",
+                        screen, variant
+                    ),
+                );
+            }
+        }
+
+        // The `crt` filter is built into the engine
+        for filter in &game_info.photo_mode.filters {
+            if filter != "crt" && !game_info.post_processing.shaders.contains_key(filter) {
+                self.problem(
+                    "default.game.yaml",
+                    format!("the `{}` photo mode filter is not a known shader", filter),
+                );
+            }
+        }
+
+        for path in paths {
+            if !self.asset_dir.join(path).is_file() {
+                self.problem(path, "referenced by the game info but does not exist");
+            }
+        }
+    }
+
+    /// Check that a path relative to the given asset exists
+    fn check_relative_exists(&mut self, asset: &Path, path: Option<&str>) {
+        let path = if let Some(path) = path {
+            path
+        } else {
+            return;
+        };
+
+        let full_path = asset.parent().unwrap_or_else(|| Path::new("")).join(path);
+        if !self.asset_dir.join(&full_path).is_file() {
+            self.problem(asset, format!("`{}` does not exist", full_path.display()));
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub fn add_clock(app: &mut AppBuilder) {
+    app.init_resource::<GameClock>();
+}
+
+/// The in-game calendar, which advances while the game is playing and drives things like crop
+/// growth
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct GameClock {
+    /// The number of days that have passed since the game started
+    pub day: u32,
+    /// The time of day, in hours since midnight, from `0` up to `24`
+    pub hour: f32,
+}
+
+impl GameClock {
+    /// Create a clock on the first day at the given hour
+    pub fn starting_at(hour: f32) -> Self {
+        let mut clock = Self::default();
+        clock.advance(hour);
+        clock
+    }
+
+    /// Get the time since the game started, in hours
+    pub fn total_hours(&self) -> f64 {
+        self.day as f64 * 24. + self.hour as f64
+    }
+
+    /// Move the clock forward to the next time it is the given hour, i.e. to the next morning
+    pub fn skip_to(&mut self, hour: f32) {
+        let mut hours = (hour - self.hour).rem_euclid(24.);
+        if hours == 0. {
+            hours = 24.;
+        }
+        self.advance(hours);
+    }
+
+    /// Move the clock forward by some number of hours
+    pub fn advance(&mut self, hours: f32) {
+        self.hour += hours.max(0.);
+        while self.hour >= 24. {
+            self.hour -= 24.;
+            self.day += 1;
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    ecs::component::Component,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_retrograde::prelude::*;
+
+use super::*;
+
+pub fn add_collision_routes(app: &mut AppBuilder) {
+    app.add_collision_route::<Handle<Character>, Entrance>()
+        .add_collision_route::<Handle<Character>, DamageRegion>()
+        .add_collision_route::<Handle<Character>, ShaderRegion>();
+}
+
+/// The label of the systems that send [`Collision`] events, which systems that read the events in
+/// the `PostUpdate` stage should run after
+#[derive(Clone, Debug, PartialEq, Eq, Hash, SystemLabel)]
+pub struct RouteCollisions;
+
+/// Sent when an entity with the `A` component starts or stops colliding with an entity with the
+/// `B` component
+///
+/// The component can be on the collision shape or on the rigid body the shape belongs to. Events
+/// are only sent once per pair of entities, when their first shapes start touching and when their
+/// last shapes stop touching, so entities made of several shapes don't send duplicate events.
+///
+/// Collision routes are registered with
+/// [`add_collision_route`][CollisionRouteAppExt::add_collision_route].
+pub struct Collision<A, B> {
+    /// The entity with the `A` component
+    pub a: Entity,
+    /// The entity with the `B` component
+    pub b: Entity,
+    /// Whether the entities started colliding, or stopped colliding
+    pub started: bool,
+    _phantom: PhantomData<fn() -> (A, B)>,
+}
+
+impl<A, B> Collision<A, B> {
+    /// Whether the entities started colliding
+    pub fn is_started(&self) -> bool {
+        self.started
+    }
+
+    /// Whether the entities stopped colliding
+    pub fn is_stopped(&self) -> bool {
+        !self.started
+    }
+}
+
+impl<A, B> Clone for Collision<A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            a: self.a,
+            b: self.b,
+            started: self.started,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A, B> std::fmt::Debug for Collision<A, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Collision")
+            .field("a", &self.a)
+            .field("b", &self.b)
+            .field("started", &self.started)
+            .finish()
+    }
+}
+
+/// Resource with the `A` and `B` entities that are currently touching, for the collision route
+/// between them
+pub struct Contacts<A, B> {
+    /// The pairs of collision shapes that are touching, for each pair of `A` and `B` entities
+    pairs: HashMap<(Entity, Entity), HashSet<(Entity, Entity)>>,
+    _phantom: PhantomData<fn() -> (A, B)>,
+}
+
+impl<A, B> Default for Contacts<A, B> {
+    fn default() -> Self {
+        Self {
+            pairs: Default::default(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A, B> Contacts<A, B> {
+    /// Whether or not the `a` entity is touching the `b` entity
+    pub fn is_touching(&self, a: Entity, b: Entity) -> bool {
+        self.pairs.contains_key(&(a, b))
+    }
+
+    /// Record that two shapes of the entities started touching, returning whether they are the
+    /// first shapes of the entities to touch
+    fn start(&mut self, a: Entity, b: Entity, shapes: (Entity, Entity)) -> bool {
+        let shapes_touching = self.pairs.entry((a, b)).or_default();
+        shapes_touching.insert(shapes) && shapes_touching.len() == 1
+    }
+
+    /// Record that two shapes of the entities stopped touching, returning whether they were the
+    /// last shapes of the entities that were touching
+    fn stop(&mut self, a: Entity, b: Entity, shapes: (Entity, Entity)) -> bool {
+        let shapes_touching = if let Some(shapes_touching) = self.pairs.get_mut(&(a, b)) {
+            shapes_touching
+        } else {
+            return false;
+        };
+        if !shapes_touching.remove(&shapes) || !shapes_touching.is_empty() {
+            return false;
+        }
+
+        self.pairs.remove(&(a, b));
+        true
+    }
+}
+
+/// Extension trait for registering collision routes on the app
+pub trait CollisionRouteAppExt {
+    /// Send a [`Collision<A, B>`] event whenever an entity with the `A` component starts or stops
+    /// colliding with an entity with the `B` component, and keep track of the ones touching in
+    /// the [`Contacts<A, B>`] resource
+    fn add_collision_route<A: Component, B: Component>(&mut self) -> &mut Self;
+}
+
+impl CollisionRouteAppExt for AppBuilder {
+    fn add_collision_route<A: Component, B: Component>(&mut self) -> &mut Self {
+        self.add_event::<Collision<A, B>>()
+            .init_resource::<Contacts<A, B>>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                route_collisions::<A, B>
+                    .system()
+                    .label(RouteCollisions)
+                    .after(PhysicsSystem::Events),
+            )
+    }
+}
+
+/// Send [`Collision<A, B>`] events for the physics collision events between `A` and `B` entities
+fn route_collisions<A: Component, B: Component>(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut routed_events: EventWriter<Collision<A, B>>,
+    mut contacts: ResMut<Contacts<A, B>>,
+    a_entities: Query<(), With<A>>,
+    b_entities: Query<(), With<B>>,
+) {
+    // Stop the contacts of entities that were despawned or lost their component, which the
+    // physics won't send events for
+    let stale_pairs = contacts
+        .pairs
+        .keys()
+        .filter(|(a, b)| a_entities.get(*a).is_err() || b_entities.get(*b).is_err())
+        .copied()
+        .collect::<Vec<_>>();
+    for (a, b) in stale_pairs {
+        contacts.pairs.remove(&(a, b));
+        routed_events.send(Collision {
+            a,
+            b,
+            started: false,
+            _phantom: PhantomData,
+        });
+    }
+
+    for event in collision_events.iter() {
+        let (shape1, shape2) = event.collision_shape_entities();
+        let (body1, body2) = event.rigid_body_entities();
+
+        // The components can be on the collision shape, or on the rigid body it belongs to
+        let with_a = |shape: Entity, body: Entity| {
+            [shape, body]
+                .iter()
+                .copied()
+                .find(|x| a_entities.get(*x).is_ok())
+        };
+        let with_b = |shape: Entity, body: Entity| {
+            [shape, body]
+                .iter()
+                .copied()
+                .find(|x| b_entities.get(*x).is_ok())
+        };
+
+        // Put the entities in the order of the route, or skip the event if it doesn't match
+        let (a, b, shapes) = match (with_a(shape1, body1), with_b(shape2, body2)) {
+            (Some(a), Some(b)) => (a, b, (shape1, shape2)),
+            _ => match (with_a(shape2, body2), with_b(shape1, body1)) {
+                (Some(a), Some(b)) => (a, b, (shape2, shape1)),
+                _ => continue,
+            },
+        };
+
+        // Only send events for the first shapes to start touching and the last to stop
+        let started = event.is_started();
+        let changed = if started {
+            contacts.start(a, b, shapes)
+        } else {
+            contacts.stop(a, b, shapes)
+        };
+        if !changed {
+            continue;
+        }
+
+        routed_events.send(Collision {
+            a,
+            b,
+            started,
+            _phantom: PhantomData,
+        });
+    }
+}
@@ -1,8 +1,12 @@
-use serde::Deserialize;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
 
 use bevy::{prelude::*, reflect::TypeUuid};
 use bevy_retrograde::prelude::*;
 
+use super::{Ability, Item};
+
 //
 // Game and level components
 //
@@ -12,6 +16,15 @@ use bevy_retrograde::prelude::*;
 pub struct CurrentLevel(pub String);
 impl_deref!(CurrentLevel, String);
 
+/// The ID of a map entity in the [`LevelStates`], used to persist its state
+#[derive(Clone, Debug)]
+pub struct PersistentId {
+    /// The identifier of the level the entity is in
+    pub level: String,
+    /// The ID of the entity, unique within its level
+    pub id: String,
+}
+
 #[derive(Clone)]
 pub struct CurrentLevelMusic {
     pub sound_data: Handle<SoundData>,
@@ -27,12 +40,20 @@ pub struct CurrentLevelMusic {
 pub struct Character {
     pub name: String,
     pub max_health: u32,
-    pub sprite_sheet_info: CharacterSpriteSheet,
+    pub sprite_sheet_info: SpriteSheetInfo,
     pub actions: CharacterActions,
     pub walk_speed: f32,
     pub sprite_image: Handle<Image>,
     pub sprite_sheet: Handle<SpriteSheet>,
+    /// The character's animations, with clips named `{action}_{direction}`, such as `walk_up`
+    pub animations: Handle<SpriteAnimations>,
     pub collision_shape: Handle<Image>,
+    /// The abilities the character can use, by slot
+    pub abilities: Vec<Handle<Ability>>,
+    /// The items the character starts out wearing
+    pub equipment: Vec<Handle<Item>>,
+    pub max_energy: u32,
+    pub energy_regen: f32,
 }
 
 #[derive(Deserialize)]
@@ -41,15 +62,40 @@ pub struct Character {
 pub struct CharacterYmlData {
     pub name: String,
     pub max_health: u32,
-    pub sprite_sheet: CharacterSpriteSheet,
+    /// The sprite sheet, which is required unless `aseprite` is set
+    #[serde(default)]
+    pub sprite_sheet: Option<SpriteSheetInfo>,
+    /// The path to an Aseprite JSON export to take the sprite sheet and animations from
+    #[serde(default)]
+    pub aseprite: Option<String>,
     pub actions: CharacterActions,
     pub walk_speed: f32,
     pub collision_shape: String,
+    /// The paths to the `.ability.yml` files of the abilities the character can use, by slot
+    #[serde(default)]
+    pub abilities: Vec<String>,
+    /// The paths to the `.item.yml` files of the items the character starts out wearing
+    #[serde(default)]
+    pub equipment: Vec<String>,
+    /// The most energy the character can have for using abilities
+    #[serde(default = "default_max_energy")]
+    pub max_energy: u32,
+    /// How much energy the character gets back per second
+    #[serde(default = "default_energy_regen")]
+    pub energy_regen: f32,
+}
+
+fn default_max_energy() -> u32 {
+    10
+}
+
+fn default_energy_regen() -> f32 {
+    2.
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "kebab-case")]
-pub struct CharacterSpriteSheet {
+pub struct SpriteSheetInfo {
     pub path: String,
     pub grid_size: (u32, u32),
     pub tiles: (u32, u32),
@@ -64,22 +110,17 @@ pub struct CharacterActions {
 #[derive(Deserialize)]
 pub struct CharacterAction {
     pub sound: Option<String>,
+    /// The animations, which may be left out if they are taken from Aseprite tags
+    #[serde(default)]
     pub animations: CharacterAnimations,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 pub struct CharacterAnimations {
-    pub up: CharacterAnimation,
-    pub down: CharacterAnimation,
-    pub right: CharacterAnimation,
-    pub left: CharacterAnimation,
-}
-
-#[derive(Deserialize)]
-pub struct CharacterAnimation {
-    #[serde(default)]
-    pub flip: bool,
-    pub frames: Vec<u32>,
+    pub up: SpriteAnimationClip,
+    pub down: SpriteAnimationClip,
+    pub right: SpriteAnimationClip,
+    pub left: SpriteAnimationClip,
 }
 
 #[derive(Clone)]
@@ -118,7 +159,6 @@ pub enum CharacterStateDirection {
 pub struct CharacterState {
     pub action: CharacterStateAction,
     pub direction: CharacterStateDirection,
-    pub anim_frame_idx: u32,
 }
 
 impl Default for CharacterState {
@@ -126,11 +166,21 @@ impl Default for CharacterState {
         Self {
             action: CharacterStateAction::Idle,
             direction: CharacterStateDirection::Down,
-            anim_frame_idx: 0,
         }
     }
 }
 
+/// Component that sets an entity's z position from its y position, so that entities lower on
+/// the screen are drawn in front of the ones above them
+///
+/// Used for characters, enemies, and props.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct YSort {
+    /// The z position of the map layer the entity was spawned in. The entity is sorted within
+    /// the y-sort band above it.
+    pub base_z: f32,
+}
+
 /// A bundle for spawning a character
 // Copied mostly from the SpriteSheetBundle bundle
 #[derive(Bundle, Default)]
@@ -142,6 +192,127 @@ pub struct CharacterBundle {
     pub sprite_sheet: Handle<SpriteSheet>,
 }
 
+//
+// Sprite animation
+//
+
+/// A set of animation clips for a sprite sheet, loaded from an `.animations.yml` file or from a
+/// character
+#[derive(TypeUuid)]
+#[uuid = "5b0f7a62-3c1d-4e8b-9a27-d41c8e6f0b93"]
+pub struct SpriteAnimations {
+    pub sprite_image: Handle<Image>,
+    pub sprite_sheet: Handle<SpriteSheet>,
+    /// The animation clips by name
+    pub clips: HashMap<String, SpriteAnimationClip>,
+}
+
+/// A single animation in a sprite sheet
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct SpriteAnimationClip {
+    /// Whether or not to flip the sprite horizontally
+    pub flip: bool,
+    /// The sprite sheet tile index of each frame
+    pub frames: Vec<u32>,
+    /// The duration of each frame in milliseconds. Frames without a duration are shown for 100 ms.
+    pub durations: Vec<u32>,
+    /// Events to send when a frame is shown, such as `footstep`, by the frame's position in the
+    /// animation
+    pub events: HashMap<u32, Vec<String>>,
+    /// Whether to start over after the last frame or to stop and send a
+    /// [`SpriteAnimationFinishedEvent`][super::SpriteAnimationFinishedEvent]
+    pub looping: bool,
+}
+
+impl Default for SpriteAnimationClip {
+    fn default() -> Self {
+        Self {
+            flip: false,
+            frames: Vec::new(),
+            durations: Vec::new(),
+            events: HashMap::default(),
+            looping: true,
+        }
+    }
+}
+
+/// Component that plays a clip from a [`SpriteAnimations`] asset on an entity's sprite sheet
+pub struct SpriteAnimation {
+    /// The animations to play clips from
+    pub animations: Handle<SpriteAnimations>,
+    /// The name of the clip being played
+    pub clip: String,
+    /// The playback speed, where `1.0` is normal speed
+    pub speed: f32,
+    /// Play the clip once even if it loops
+    pub one_shot: bool,
+    /// Whether or not the clip has reached its end
+    pub finished: bool,
+    /// The position of the current frame in the clip
+    pub frame: usize,
+    /// The timer for the current frame
+    pub timer: Timer,
+    /// Whether the current frame has yet to be shown
+    pub(crate) frame_pending: bool,
+    /// The animations that the entity's own copy of their sprite sheet was made from
+    pub(crate) sheet_source: Option<Handle<SpriteAnimations>>,
+}
+
+impl SpriteAnimation {
+    /// Start playing a looping clip
+    pub fn new(animations: Handle<SpriteAnimations>, clip: &str) -> Self {
+        Self {
+            animations,
+            clip: clip.into(),
+            speed: 1.,
+            one_shot: false,
+            finished: false,
+            frame: 0,
+            timer: Timer::default(),
+            frame_pending: true,
+            sheet_source: None,
+        }
+    }
+
+    /// Switch to the given clip, doing nothing if it is already playing
+    pub fn play(&mut self, clip: &str) {
+        if self.clip != clip || self.finished {
+            self.restart(clip);
+            self.one_shot = false;
+        }
+    }
+
+    /// Play the given clip from the start and stop when it is done, even if it loops
+    pub fn play_once(&mut self, clip: &str) {
+        self.restart(clip);
+        self.one_shot = true;
+    }
+
+    fn restart(&mut self, clip: &str) {
+        self.clip = clip.into();
+        self.frame = 0;
+        self.finished = false;
+        self.frame_pending = true;
+    }
+}
+
+impl Default for SpriteAnimation {
+    fn default() -> Self {
+        Self::new(Default::default(), "")
+    }
+}
+
+/// A bundle for spawning an animated sprite
+#[derive(Bundle, Default)]
+pub struct SpriteAnimationBundle {
+    pub animation: SpriteAnimation,
+    #[bundle]
+    pub sprite_bundle: SpriteBundle,
+    pub sprite_sheet: Handle<SpriteSheet>,
+}
+
 //
 // Physics
 //
@@ -152,6 +323,7 @@ pub enum PhysicsGroup {
     Entrance,
     Player,
     Enemy,
+    Region,
 }
 
 //
@@ -161,8 +333,8 @@ pub enum PhysicsGroup {
 /// An entrance on the map to another part of the map
 #[derive(Debug, Clone)]
 pub struct Entrance {
-    /// A handle to the map that this entrance is for
-    pub map_handle: Handle<LdtkMap>,
+    /// The map entity that this entrance is for
+    pub map: Entity,
     /// A unique identifier for the entrance
     pub id: String,
     /// The level that this entrance is found in
@@ -171,9 +343,24 @@ pub struct Entrance {
     pub to_level: String,
     /// The entrance in the `to` level that the entrance leads to
     pub spawn_at: String,
+    /// Whether leaving through the entrance completes the level, showing the results screen
+    pub goal: bool,
+}
+
+/// An area on the map that applies a post-processing shader while the player is inside of it
+#[derive(Debug, Clone)]
+pub struct ShaderRegion {
+    /// The map entity that this region is in
+    pub map: Entity,
+    /// The level that this region is found in
+    pub level: String,
+    /// The name of the shader to apply
+    pub shader: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+/// Metadata for a tileset tile, set in the tile's custom data or for an LDtk tile enum tag in the
+/// game info
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct TilesetTileMetadata {
@@ -181,9 +368,45 @@ pub struct TilesetTileMetadata {
     pub collision: TilesetTileCollisionMode,
     #[serde(default)]
     pub damage_region: Option<DamageRegion>,
+    /// Cycle the tile through other tiles in the tileset, i.e. for water or torches
+    #[serde(default)]
+    pub animation: Option<TileAnimation>,
+    /// Multiply the speed of characters and enemies walking on the tile, i.e. `0.5` for mud
+    #[serde(default)]
+    pub speed: Option<f32>,
+    /// Multiply the cost for enemies to path across the tile, i.e. `4` for lava that enemies
+    /// should walk around unless going around is much longer
+    #[serde(default)]
+    pub nav_cost: Option<f32>,
+    /// Keep enemies from pathing across the tile at all, i.e. for spikes
+    #[serde(default)]
+    pub nav_forbidden: bool,
+    /// Make the tile a secret wall that is removed when it is hit hard enough, i.e. for a cracked
+    /// wall hiding a room. The tile still needs a `collision` to block the way until then.
+    #[serde(default)]
+    pub secret: bool,
+    /// Show reflections of the characters and enemies standing on the tile, i.e. for water. Only
+    /// used if the game has `reflections` settings.
+    #[serde(default)]
+    pub reflective: bool,
+    /// The image, relative to the asset directory, of the footprints left behind by characters
+    /// and enemies walking across the tile, i.e. for sand or snow
+    #[serde(default)]
+    pub footprint: Option<String>,
+}
+
+/// An animation for a map tile
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct TileAnimation {
+    /// The IDs of the tiles in the same tileset to show for each frame
+    pub frames: Vec<i32>,
+    /// The number of frames to show per second
+    pub fps: f32,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "kebab-case")]
 pub enum TilesetTileCollisionMode {
     /// No collision for this tile
@@ -208,7 +431,7 @@ impl Default for TilesetTileCollisionMode {
 
 /// A damage region component that can be combined with a [`CollisionShape`] to hurt players or
 /// other entities.
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
 pub struct DamageRegion {
@@ -219,7 +442,7 @@ pub struct DamageRegion {
 }
 
 /// The knockback attributes of a damage region
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct DamageRegionKnockBack {
@@ -231,11 +454,179 @@ pub struct DamageRegionKnockBack {
     pub freeze_duration: f32,
 }
 
+/// A decorative object on the map, such as a tree, statue, or fence, spawned from a `Prop` map
+/// entity
+#[derive(Clone, Debug)]
+pub struct Prop {
+    /// The identifier of the map level that the prop is in
+    pub level: String,
+    /// The map entity this prop is in
+    pub map: Entity,
+    /// The interaction to send in a [`PropInteractionEvent`] when the player interacts with the
+    /// prop
+    pub interaction: Option<String>,
+}
+
+/// Component for a prop that makes a noise when the player interacts with it, i.e. breaking a pot
+#[derive(Clone, Copy, Debug)]
+pub struct PropNoise {
+    /// How far away the noise can be heard, in pixels
+    pub radius: f32,
+}
+
+/// Component for a prop that the player can lift with the `lift` interaction and then throw, i.e.
+/// a pot or a rock
+#[derive(Clone, Copy, Debug)]
+pub struct Liftable {
+    /// The damage done to the enemy that the prop is thrown at
+    pub damage: u32,
+}
+
+/// Component for a prop that walks back and forth along a path, i.e. an NPC wandering around town,
+/// set with the prop's `path` field
+#[derive(Clone, Debug)]
+pub struct PathFollower {
+    /// The points of the path in the world, starting at the prop's position
+    pub points: Vec<Vec2>,
+    /// The speed to move along the path in pixels per second
+    pub speed: f32,
+    /// Whether to go straight from the last point back to the first, instead of turning around
+    pub looping: bool,
+    /// The index of the point being walked to
+    pub target: usize,
+    /// Whether the prop is walking the path backwards
+    pub reversed: bool,
+}
+
+/// Marker for map enemies that come back when a day passes after they are killed
+#[derive(Clone, Copy, Debug)]
+pub struct Respawns;
+
+/// Component for an enemy that moves with a group of other enemies
+///
+/// The member of the pack with the lowest `order` leads it and pathfinds to the player, while the
+/// rest of the pack keeps its place in the formation around the leader.
+#[derive(Clone, Debug)]
+pub struct Pack {
+    /// The ID shared by the enemies in the pack
+    pub id: String,
+    /// Which member leads the pack, the lowest first
+    pub order: u32,
+    /// The enemy's place in the formation. The enemy keeps the difference between its offset and
+    /// the leader's offset away from the leader.
+    pub offset: Vec2,
+}
+
+/// Component for an enemy that has to see or hear the player before it chases them
+#[derive(Clone, Debug)]
+pub struct Vision {
+    /// How far the enemy can see, in pixels
+    pub range: f32,
+    /// How wide the enemy's vision cone is, in degrees
+    pub angle: f32,
+    /// The direction the enemy is looking in
+    pub facing: Vec2,
+}
+
+impl Vision {
+    /// Whether or not a point is inside of the vision cone, ignoring walls
+    pub fn covers(&self, offset: Vec2) -> bool {
+        let distance = offset.length();
+        if distance > self.range {
+            return false;
+        }
+        if distance == 0. {
+            return true;
+        }
+
+        self.facing.angle_between(offset).abs().to_degrees() <= self.angle / 2.
+    }
+}
+
+/// How aware an enemy with [`Vision`] is of the player
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlertState {
+    /// The enemy hasn't noticed anything
+    Unaware,
+    /// The enemy has seen or heard something and is looking towards it
+    Suspicious,
+    /// The enemy has seen the player and is chasing them
+    Alert,
+    /// The enemy has lost sight of the player and is searching where they were last seen
+    Searching,
+}
+
+/// Component for the alert state of an enemy with [`Vision`]
+#[derive(Clone, Debug)]
+pub struct Alertness {
+    pub state: AlertState,
+    /// How close the enemy is to being alerted, from `0` to `1`
+    pub suspicion: f32,
+    /// Where the enemy last saw or heard the player
+    pub last_known: Option<Vec2>,
+    /// How long the enemy has to see the player for before it is alerted, in seconds
+    pub suspicion_time: f32,
+    /// How long the enemy searches for the player after losing sight of them, in seconds
+    pub search_time: f32,
+    /// How much longer the enemy will keep searching, in seconds
+    pub search_remaining: f32,
+    /// How far the enemy can hear, as a multiple of the radius of noises. `0` makes it deaf.
+    pub hearing: f32,
+    /// Whether the enemy goes to look where it heard a noise instead of just looking towards it
+    pub investigates: bool,
+}
+
+impl Alertness {
+    pub fn new(suspicion_time: f32, search_time: f32) -> Self {
+        Self {
+            state: AlertState::Unaware,
+            suspicion: 0.,
+            last_known: None,
+            suspicion_time,
+            search_time,
+            search_remaining: 0.,
+            hearing: 1.,
+            investigates: true,
+        }
+    }
+}
+
+/// Component for an enemy that is frozen because it is far away from the player, with the
+/// velocity it had when it was frozen
+#[derive(Clone, Debug)]
+pub struct Dormant {
+    pub velocity: Vec3,
+}
+
+/// A farming plot where the player can plant crops, spawned from a `Plot` map entity along with a
+/// [`Prop`] for interacting with it
+#[derive(Clone, Debug)]
+pub struct Plot;
+
+/// The collision for a [`Prop`]
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PropCollisionMode {
+    /// The prop can be walked through
+    None,
+    /// The whole prop is solid
+    Full,
+    /// Only the bottom third of the prop is solid, so characters can walk behind the top of it,
+    /// i.e. for trees
+    Base,
+}
+
+impl Default for PropCollisionMode {
+    fn default() -> Self {
+        Self::Full
+    }
+}
+
 /// An enemy on the map
 #[derive(Clone, Debug)]
 pub struct Enemy {
     /// The identifier of the map level that the enemy is in
     pub level: String,
-    /// The handle to the map this enemy is in
-    pub map_handle: Handle<LdtkMap>,
+    /// The map entity this enemy is in
+    pub map: Entity,
 }
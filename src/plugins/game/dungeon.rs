@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use super::{DungeonConfig, MetaProgress};
+
+pub fn add_dungeon(app: &mut AppBuilder) {
+    app.init_resource::<DungeonRun>();
+}
+
+/// The player's current run through the procedurally generated dungeon
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct DungeonRun {
+    /// The seed that the run's rooms are picked with, or `None` if a run hasn't started
+    pub seed: Option<u64>,
+    /// The rooms that the player has entered during the run, which have given their reward
+    pub visited: Vec<String>,
+}
+
+impl DungeonRun {
+    /// Pick the rooms for the run, in the order they are linked
+    ///
+    /// The rooms only depend on the seed and the unlocked rooms, so a saved run is laid out the
+    /// same way when it is continued.
+    pub fn rooms(&self, config: &DungeonConfig, meta: &MetaProgress) -> Vec<String> {
+        let seed = if let Some(seed) = self.seed {
+            seed
+        } else {
+            return Vec::new();
+        };
+        let mut rng = Pcg32::seed_from_u64(seed);
+
+        let fixed_rooms = [&config.first_room, &config.last_room];
+        let unlocked_rooms = config
+            .unlocks
+            .iter()
+            .filter(|x| meta.is_unlocked(&x.id))
+            .flat_map(|x| &x.rooms);
+        let mut pool = config
+            .rooms
+            .iter()
+            .chain(unlocked_rooms)
+            .filter(|x| !fixed_rooms.iter().any(|fixed| fixed.as_ref() == Some(*x)))
+            .collect::<Vec<_>>();
+        pool.shuffle(&mut rng);
+        if pool.len() < config.length {
+            warn!(
+                rooms = pool.len(),
+                length = config.length,
+                "Not enough dungeon rooms for the configured length"
+            );
+        }
+
+        config
+            .first_room
+            .iter()
+            .chain(pool.into_iter().take(config.length))
+            .chain(config.last_room.iter())
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,66 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use bevy_retrograde::prelude::*;
+
+/// A pool of entities that are hidden and re-used instead of being despawned, to avoid the cost of
+/// spawning and despawning lots of short-lived entities such as projectiles and particles
+///
+/// The `T` type is used to tell different pools apart, and is usually the marker component of the
+/// pooled entities. Pools are added to the app as resources with
+/// `app.init_resource::<EntityPool<T>>()`.
+pub struct EntityPool<T> {
+    /// The entities that are waiting to be re-used
+    free: Vec<Entity>,
+    /// The most entities to keep waiting in the pool. Entities released while the pool is full are
+    /// despawned instead.
+    pub max_size: usize,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EntityPool<T> {
+    fn default() -> Self {
+        Self {
+            free: Default::default(),
+            max_size: 1024,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Marker component for entities that are waiting in an [`EntityPool`], which systems should
+/// ignore
+pub struct Pooled;
+
+impl<T> EntityPool<T> {
+    /// Get an entity from the pool, or spawn a new one if the pool is empty
+    ///
+    /// Re-used entities keep the components they had when they were released, so the caller should
+    /// insert all of the components it needs again.
+    pub fn take(&mut self, commands: &mut Commands) -> Entity {
+        if let Some(entity) = self.free.pop() {
+            commands.entity(entity).remove::<Pooled>();
+            entity
+        } else {
+            commands.spawn().id()
+        }
+    }
+
+    /// Put an entity back in the pool, hiding it and stopping it from moving or colliding
+    ///
+    /// The entity must not already be in the pool.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        if self.free.len() >= self.max_size {
+            commands.entity(entity).despawn_recursive();
+            return;
+        }
+
+        commands
+            .entity(entity)
+            .insert(Pooled)
+            .insert(Visible(false))
+            .remove::<Velocity>()
+            .remove::<CollisionShape>();
+        self.free.push(entity);
+    }
+}
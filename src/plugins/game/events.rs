@@ -1,15 +1,172 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::assets::Emote;
+use super::systems::LevelStats;
 
 pub fn add_events(app: &mut AppBuilder) {
-    app.add_event::<ControlEvent>();
+    app.add_event::<ControlEvent>()
+        .add_event::<CharacterDamagedEvent>()
+        .add_event::<RumbleEvent>()
+        .add_event::<SoundPlayedEvent>()
+        .add_event::<AnimationEvent>()
+        .add_event::<SpriteAnimationFinishedEvent>()
+        .add_event::<PropInteractionEvent>()
+        .add_event::<FatalErrorEvent>()
+        .add_event::<LoadLevelEvent>()
+        .add_event::<DayPassedEvent>()
+        .add_event::<NoiseEvent>()
+        .add_event::<WallImpactEvent>()
+        .add_event::<EmoteEvent>()
+        .add_event::<EnemyDefeatedEvent>()
+        .add_event::<SecretFoundEvent>()
+        .add_event::<LevelCompletedEvent>();
 }
 
 /// A user control event, used to control the character
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, Serialize, Deserialize)]
 #[allow(clippy::enum_variant_names)]
 pub enum ControlEvent {
     MoveUp,
     MoveDown,
     MoveLeft,
     MoveRight,
+    /// Attack in the direction the character is facing with the ability in the first slot, or
+    /// throw what the character is carrying
+    Attack,
+    /// Interact with whatever the character is facing
+    Interact,
+    /// Use the ability in the given slot of the character
+    Ability(u8),
+}
+
+/// Sent when a character takes damage
+#[derive(Clone, Copy, Debug)]
+pub struct CharacterDamagedEvent {
+    /// The character entity that was damaged
+    pub character: Entity,
+    /// The amount of health the character lost
+    pub damage: u32,
+}
+
+/// Sent when a sound starts playing, used to show captions for important sounds
+#[derive(Clone, Debug)]
+pub struct SoundPlayedEvent {
+    /// The asset path of the sound
+    pub sound: String,
+}
+
+/// Sent to request rumble, i.e. when the player is hit or something heavy lands nearby. Rumble is
+/// only played on the web, by vibrating the device.
+#[derive(Clone, Copy, Debug)]
+pub struct RumbleEvent {
+    /// How strong the rumble is, from `0.0` to `1.0`
+    pub intensity: f32,
+    /// How long the rumble lasts in seconds
+    pub duration: f32,
+}
+
+/// Sent when a sprite animation shows a frame that has events, so that sounds, hitboxes, etc. can
+/// be synced to the animation
+#[derive(Clone, Debug)]
+pub struct AnimationEvent {
+    /// The entity being animated
+    pub entity: Entity,
+    /// The name of the event, such as `footstep`
+    pub event: String,
+}
+
+/// Sent when a sprite animation that doesn't loop reaches the end of its clip
+#[derive(Clone, Debug)]
+pub struct SpriteAnimationFinishedEvent {
+    /// The entity being animated
+    pub entity: Entity,
+    /// The name of the clip that finished
+    pub clip: String,
+}
+
+/// Sent when the player interacts with a prop that has an `interaction`
+#[derive(Clone, Debug)]
+pub struct PropInteractionEvent {
+    /// The prop entity
+    pub prop: Entity,
+    /// The prop's `interaction`
+    pub interaction: String,
+}
+
+/// Sent when the [`GameClock`][super::GameClock] reaches a new day, either by playing through the
+/// night or by sleeping
+#[derive(Clone, Copy, Debug)]
+pub struct DayPassedEvent {
+    /// The number of days that have passed since the game started
+    pub day: u32,
+}
+
+/// Sent to move the player to the `PlayerStart` spawn point of a level during gameplay, i.e. when
+/// the page hosting the game loads a level on web
+#[derive(Clone, Debug)]
+pub struct LoadLevelEvent {
+    /// The identifier of the level
+    pub level: String,
+}
+
+/// Sent when something goes wrong that the game can't recover from, such as a missing map or
+/// character, to stop the game and show the error on screen
+#[derive(Clone, Debug)]
+pub struct FatalErrorEvent {
+    /// The error message
+    pub message: String,
+    /// The path of the asset that caused the error, if any
+    pub asset: Option<String>,
+}
+
+/// Sent when the player makes a noise that enemies with vision can hear, i.e. footsteps, using an
+/// ability, or breaking a pot
+#[derive(Clone, Copy, Debug)]
+pub struct NoiseEvent {
+    /// Where the noise was made
+    pub position: Vec2,
+    /// How far away the noise can be heard, in pixels
+    pub radius: f32,
+}
+
+/// Sent when something the player did hits the walls of the map hard, i.e. a projectile hitting a
+/// wall or an explosion, so that secret walls near it can break
+#[derive(Clone, Copy, Debug)]
+pub struct WallImpactEvent {
+    /// Where the hit was
+    pub position: Vec2,
+    /// How far from the position walls are hit, in pixels
+    pub radius: f32,
+    /// How much damage the hit does
+    pub damage: u32,
+}
+
+/// Sent to show an [`Emote`] in a bubble over an entity, replacing any emote it is already showing
+#[derive(Clone, Debug)]
+pub struct EmoteEvent {
+    /// The entity to show the emote over
+    pub entity: Entity,
+    pub emote: Emote,
+}
+
+/// Sent when an enemy is defeated
+#[derive(Clone, Copy, Debug)]
+pub struct EnemyDefeatedEvent {
+    /// Where the enemy was defeated
+    pub position: Vec2,
+}
+
+/// Sent when the player finds a secret, i.e. by breaking a secret wall
+#[derive(Clone, Copy, Debug)]
+pub struct SecretFoundEvent {
+    /// Where the secret was found
+    pub position: Vec2,
+}
+
+/// Sent when the player leaves a level through one of its `goal` entrances
+#[derive(Clone, Debug)]
+pub struct LevelCompletedEvent {
+    /// The player's stats for the level
+    pub stats: LevelStats,
 }
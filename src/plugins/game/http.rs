@@ -0,0 +1,87 @@
+//! Simple HTTP requests for the online features, i.e. the online leaderboard
+//!
+//! This is only compiled in when one of the online features is turned on, so that games don't
+//! make requests to other sites unless they opt in.
+
+#[cfg(not(wasm))]
+pub use native::*;
+#[cfg(wasm)]
+pub use web::*;
+
+#[cfg(not(wasm))]
+mod native {
+    use std::io::Read;
+
+    /// Send JSON to a URL
+    pub async fn post(url: &str, body: Vec<u8>) -> anyhow::Result<()> {
+        ureq::post(url)
+            .set("Content-Type", "application/json")
+            .send_bytes(&body)?;
+        Ok(())
+    }
+
+    /// Download the response from a URL
+    #[cfg_attr(not(feature = "online-leaderboard"), allow(dead_code))]
+    pub async fn get(url: &str) -> anyhow::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        ureq::get(url)
+            .call()?
+            .into_reader()
+            .read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
+
+#[cfg(wasm)]
+mod web {
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{RequestInit, RequestMode, Response};
+
+    /// Send JSON to a URL
+    ///
+    /// The JSON is sent as plain text, which browsers send without asking the server whether it
+    /// allows the request first, so that the leaderboard server only has to allow the game's site.
+    pub async fn post(url: &str, body: Vec<u8>) -> anyhow::Result<()> {
+        let mut init = RequestInit::new();
+        init.method("POST")
+            .mode(RequestMode::Cors)
+            .body(Some(&JsValue::from_str(&String::from_utf8(body)?)));
+        fetch(url, &init).await?;
+        Ok(())
+    }
+
+    /// Download the response from a URL
+    #[cfg_attr(not(feature = "online-leaderboard"), allow(dead_code))]
+    pub async fn get(url: &str) -> anyhow::Result<Vec<u8>> {
+        let mut init = RequestInit::new();
+        init.mode(RequestMode::Cors);
+        let response = fetch(url, &init).await?;
+        let data = JsFuture::from(
+            response
+                .array_buffer()
+                .map_err(|e| anyhow::format_err!("{:?}", e))?,
+        )
+        .await
+        .map_err(|e| anyhow::format_err!("{:?}", e))?;
+
+        Ok(js_sys::Uint8Array::new(&data).to_vec())
+    }
+
+    async fn fetch(url: &str, init: &RequestInit) -> anyhow::Result<Response> {
+        let response: Response = JsFuture::from(
+            web_sys::window()
+                .unwrap()
+                .fetch_with_str_and_init(url, init),
+        )
+        .await
+        .map_err(|e| anyhow::format_err!("{:?}", e))?
+        .dyn_into()
+        .unwrap();
+
+        if !response.ok() {
+            anyhow::bail!("HTTP {}", response.status());
+        }
+        Ok(response)
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Recipe;
+
+pub fn add_inventory(app: &mut AppBuilder) {
+    app.init_resource::<Inventory>();
+}
+
+/// The items the player is carrying, keyed by the path to their `.item.yml` file relative to the
+/// asset directory, with how many of each the player has
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Inventory(pub BTreeMap<String, u32>);
+
+impl Inventory {
+    /// Get how many of an item the player has
+    pub fn count(&self, item: &str) -> u32 {
+        self.0.get(item).copied().unwrap_or(0)
+    }
+
+    /// Add some of an item to the inventory
+    pub fn add(&mut self, item: &str, count: u32) {
+        *self.0.entry(item.into()).or_default() += count;
+    }
+
+    /// Take some of an item out of the inventory, returning `false` without changing anything if
+    /// there aren't enough of it
+    pub fn remove(&mut self, item: &str, count: u32) -> bool {
+        let current = self.count(item);
+        if current < count {
+            return false;
+        }
+
+        if current == count {
+            self.0.remove(item);
+        } else {
+            self.0.insert(item.into(), current - count);
+        }
+        true
+    }
+
+    /// Get whether the player has the items used up by a recipe
+    pub fn has_inputs(&self, recipe: &Recipe) -> bool {
+        recipe
+            .inputs
+            .iter()
+            .all(|(item, count)| self.count(item) >= *count)
+    }
+
+    /// Make a recipe, using up its inputs, returning `false` without changing anything if the
+    /// player doesn't have them
+    pub fn craft(&mut self, recipe: &Recipe) -> bool {
+        if !self.has_inputs(recipe) {
+            return false;
+        }
+
+        for (item, count) in &recipe.inputs {
+            self.remove(item, *count);
+        }
+        self.add(&recipe.output, recipe.count);
+        true
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{prelude::*, tasks::IoTaskPool};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "online-leaderboard")]
+use super::http;
+use super::{GameInfo, HighScores, LeaderboardConfig};
+
+pub fn add_leaderboard(app: &mut AppBuilder) {
+    app.init_resource::<BestTimes>()
+        .init_resource::<GlobalLeaderboard>()
+        .add_system(fetch_global_leaderboard.system())
+        .add_system(finish_global_leaderboard_fetch.system());
+}
+
+/// The fastest time the player has completed each level in, in seconds, which are saved between
+/// play sessions
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BestTimes(pub BTreeMap<String, f32>);
+
+impl BestTimes {
+    /// Record the time a level was completed in if it is faster than the best time, returning
+    /// whether it was
+    pub fn record(&mut self, level: &str, time: f32) -> bool {
+        match self.0.get(level) {
+            Some(&best) if best <= time => false,
+            _ => {
+                self.0.insert(level.into(), time);
+                true
+            }
+        }
+    }
+}
+
+/// The high scores and best times kept on the player's device, which are saved as JSON so that
+/// they can be copied to another device or imported from the page hosting the game
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct LocalLeaderboard {
+    pub high_scores: HighScores,
+    pub best_times: BestTimes,
+}
+
+impl LocalLeaderboard {
+    /// Add the scores and times of this leaderboard to the player's, keeping the best of both
+    pub fn merge_into(
+        self,
+        high_scores: &mut HighScores,
+        best_times: &mut BestTimes,
+        max_scores: usize,
+    ) {
+        for score in self.high_scores.0 {
+            high_scores.record(score, max_scores);
+        }
+        for (level, time) in self.best_times.0 {
+            best_times.record(&level, time);
+        }
+    }
+}
+
+/// A score on the online leaderboard
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub score: u32,
+}
+
+/// A game's score and the player's best times, as sent to the online leaderboard
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct LeaderboardSubmission<'a> {
+    game: &'a str,
+    score: u32,
+    best_times: &'a BestTimes,
+}
+
+/// Resource for the best scores of all players, fetched from the online leaderboard
+#[derive(Default)]
+pub struct GlobalLeaderboard {
+    /// The best scores, highest first
+    pub entries: Vec<LeaderboardEntry>,
+    /// The scores that have finished being fetched in the background
+    fetched: Arc<Mutex<Option<Vec<LeaderboardEntry>>>>,
+}
+
+impl GlobalLeaderboard {
+    /// Send the score of a finished game to the online leaderboard in the background, and fetch
+    /// the best scores again once it has been added
+    pub fn submit(
+        &self,
+        task_pool: &IoTaskPool,
+        game_info: &GameInfo,
+        score: u32,
+        best_times: &BestTimes,
+    ) {
+        let config = if let Some(config) = &game_info.leaderboard {
+            config
+        } else {
+            return;
+        };
+
+        let submission = LeaderboardSubmission {
+            game: &game_info.title,
+            score,
+            best_times,
+        };
+        match serde_json::to_vec(&submission) {
+            Ok(body) => sync(task_pool, config, Some(body), self.fetched.clone()),
+            Err(e) => warn!(%e, "Could not serialize the leaderboard submission"),
+        }
+    }
+}
+
+/// Fetch the best scores from the online leaderboard once the game info has loaded
+fn fetch_global_leaderboard(
+    game_info: Option<Res<GameInfo>>,
+    leaderboard: Res<GlobalLeaderboard>,
+    task_pool: Res<IoTaskPool>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+    if !game_info.is_added() {
+        return;
+    }
+
+    if let Some(config) = &game_info.leaderboard {
+        sync(&task_pool, config, None, leaderboard.fetched.clone());
+    }
+}
+
+/// Show the best scores that have finished being fetched
+fn finish_global_leaderboard_fetch(mut leaderboard: ResMut<GlobalLeaderboard>) {
+    let fetched = leaderboard.fetched.lock().unwrap().take();
+    if let Some(entries) = fetched {
+        leaderboard.entries = entries;
+    }
+}
+
+/// Send a submission to the online leaderboard in the background, if there is one, and then fetch
+/// the best scores into `output`
+#[cfg(feature = "online-leaderboard")]
+fn sync(
+    task_pool: &IoTaskPool,
+    config: &LeaderboardConfig,
+    submission: Option<Vec<u8>>,
+    output: Arc<Mutex<Option<Vec<LeaderboardEntry>>>>,
+) {
+    if config.url.is_empty() {
+        warn!("The leaderboard doesn't have a URL set");
+        return;
+    }
+
+    let url = config.url.clone();
+    let top = config.top;
+    task_pool
+        .spawn(async move {
+            if let Some(body) = submission {
+                if let Err(e) = http::post(&url, body).await {
+                    warn!(%url, %e, "Could not send score to the leaderboard");
+                }
+            }
+
+            let entries = http::get(&url)
+                .await
+                .and_then(|data| Ok(serde_json::from_slice::<Vec<LeaderboardEntry>>(&data)?));
+            match entries {
+                Ok(mut entries) => {
+                    entries.sort_by(|a, b| b.score.cmp(&a.score));
+                    entries.truncate(top);
+                    *output.lock().unwrap() = Some(entries);
+                }
+                Err(e) => warn!(%url, %e, "Could not fetch the leaderboard"),
+            }
+        })
+        .detach();
+}
+
+#[cfg(not(feature = "online-leaderboard"))]
+fn sync(
+    _task_pool: &IoTaskPool,
+    _config: &LeaderboardConfig,
+    _submission: Option<Vec<u8>>,
+    _output: Arc<Mutex<Option<Vec<LeaderboardEntry>>>>,
+) {
+    warn!(
+        "The game has an online leaderboard, but the engine was built without the \
+        `online-leaderboard` feature"
+    );
+}
@@ -0,0 +1,72 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub fn add_level_state(app: &mut AppBuilder) {
+    app.init_resource::<LevelStates>();
+}
+
+/// The persistent state of the world, such as opened chests and killed bosses, keyed by level
+/// identifier
+///
+/// Map entities with a [`PersistentId`][super::PersistentId] have their state re-applied when
+/// they are spawned, when the player enters their level, and when this resource is replaced, i.e.
+/// when loading a save.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LevelStates(pub HashMap<String, LevelState>);
+
+impl LevelStates {
+    /// Get the state of an entity, if it has changed
+    pub fn entity(&self, level: &str, id: &str) -> Option<&EntityState> {
+        self.0.get(level).and_then(|x| x.entities.get(id))
+    }
+
+    /// Get the state of an entity to change it
+    pub fn entity_mut(&mut self, level: &str, id: &str) -> &mut EntityState {
+        self.0
+            .entry(level.into())
+            .or_default()
+            .entities
+            .entry(id.into())
+            .or_default()
+    }
+}
+
+/// The persistent state of a level
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LevelState {
+    /// The state of the entities in the level that have changed, by their persistent ID
+    pub entities: HashMap<String, EntityState>,
+}
+
+/// The persistent state of a map entity
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct EntityState {
+    /// Whether or not the entity has been removed from the world, i.e. a killed boss or a
+    /// collected item
+    pub removed: bool,
+    /// The position the entity has been moved to, i.e. for a pushed block
+    pub position: Option<(f32, f32)>,
+    /// Custom flags set on the entity, i.e. `opened` for a chest
+    pub flags: HashSet<String>,
+    /// Whether the entity comes back when a day passes after being removed, i.e. a killed enemy
+    pub respawns: bool,
+    /// The crop planted in the entity, if it is a farming plot
+    pub crop: Option<PlantedCrop>,
+}
+
+/// A crop planted in a farming plot
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PlantedCrop {
+    /// The path to the `.crop.yml` file of the crop
+    pub crop: String,
+    /// The time on the [`GameClock`][super::GameClock] that the crop was planted, in hours since
+    /// the game started
+    pub planted_at: f64,
+}
@@ -0,0 +1,622 @@
+//! A format-independent representation of the game map
+//!
+//! Maps can be made in LDtk or Tiled, and are converted to a [`GameMap`] when they load so that
+//! the map loading and gameplay systems don't have to know which editor the map was made in.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_retrograde::prelude::*;
+use serde::de::DeserializeOwned;
+
+use super::*;
+
+/// The z distance between the layers of a level, which is how far apart the layers of LDtk maps
+/// are spawned. Tiled map layers are drawn the same way.
+pub const LAYER_DEPTH: f32 = 2.;
+
+/// How far characters are placed up and to the left of the center of the entity they are placed
+/// at, in pixels
+///
+/// A character placed exactly in the middle of an entrance can end up just far enough to the right
+/// to slip through the first tile of the doorpost.
+const CHARACTER_PLACEMENT_NUDGE: f32 = 0.1;
+
+/// Where to put something that is placed at a map entity, with [`GameLevel::place`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Placement {
+    /// At the entity's position, i.e. for enemies, which are centered on their spawn point
+    Position,
+    /// At the center of the entity's box, i.e. for props and sensors such as entrances
+    Center,
+    /// At the center of the entity's box, nudged slightly up and to the left, for the player
+    /// character when they spawn or are teleported
+    Character,
+}
+
+/// Component added to map entities containing the map data for the loaded LDtk or Tiled map
+#[derive(Clone, Debug, Default)]
+pub struct GameMap {
+    /// The levels in the map
+    pub levels: Vec<GameLevel>,
+    /// The tilesets used by the map, by their ID
+    pub tilesets: HashMap<i32, GameTileset>,
+}
+
+/// A level in a [`GameMap`]
+#[derive(Clone, Debug, Default)]
+pub struct GameLevel {
+    /// The unique name of the level
+    pub identifier: String,
+    /// The position of the top-left corner of the level in the world
+    pub position: Vec2,
+    /// How far the level was moved from its position in the map file. Only set for instanced
+    /// levels, which are moved away from the rest of the map.
+    pub instance_offset: Vec2,
+    /// The size of the level in pixels
+    pub size: UVec2,
+    /// The size of the level's tiles in pixels
+    pub grid_size: u32,
+    /// The background color of the level, if it has one
+    pub background_color: Option<Color>,
+    /// The custom fields set on the level
+    pub fields: HashMap<String, serde_yaml::Value>,
+    /// The layers in the level, from the bottom-most to the top-most
+    pub layers: Vec<GameLayer>,
+    /// The entities in the level
+    pub entities: Vec<GameEntity>,
+}
+
+impl GameLevel {
+    /// Get the value of a field, if it is set and has the right type
+    pub fn field<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        field(&self.fields, name)
+    }
+
+    /// Get the z position above all of the level's layers
+    pub fn top_z(&self) -> f32 {
+        self.layers.len() as f32 * LAYER_DEPTH
+    }
+
+    /// Get where in the world to put something placed at an entity in the level, at the entity's
+    /// z position
+    pub fn place(&self, entity: &GameEntity, placement: Placement) -> Vec3 {
+        let position = self.position + entity.position;
+        let position = match placement {
+            Placement::Position => position,
+            Placement::Center => position + entity.size / 2.,
+            Placement::Character => {
+                position + entity.size / 2. - Vec2::splat(CHARACTER_PLACEMENT_NUDGE)
+            }
+        };
+
+        position.extend(entity.z)
+    }
+
+    /// Get the position of the center of the level in the world
+    pub fn center(&self) -> Vec2 {
+        self.position + self.size.as_f32() / 2.
+    }
+
+    /// Find an entity with the given identifier that has a string field set to the given value
+    pub fn find_entity(&self, identifier: &str, field: &str, value: &str) -> Option<&GameEntity> {
+        self.entities
+            .iter()
+            .find(|x| x.identifier == identifier && x.field_str(field) == Some(value))
+    }
+}
+
+/// A layer in a [`GameLevel`]
+#[derive(Clone, Debug, Default)]
+pub struct GameLayer {
+    /// The name of the layer
+    pub identifier: String,
+    /// The position of the layer relative to its level
+    pub offset: Vec2,
+    /// The size of the layer's tiles in pixels
+    pub grid_size: u32,
+    /// The width of the layer in tiles
+    pub columns: u32,
+    /// The tiles in the layer
+    pub tiles: Vec<GameTile>,
+    /// The value of every cell in the layer, going left-to-right and then top-to-bottom, where `0`
+    /// is an empty cell. Only set for layers that mark cells, such as LDtk IntGrid layers.
+    pub cells: Vec<i32>,
+    /// Whether or not this is a roof layer, that fades out when the player walks underneath it
+    pub roof: bool,
+}
+
+impl GameLayer {
+    /// Get the cell value at the given pixel position in the layer, or `0` if the cell is empty
+    pub fn cell_at(&self, position: IVec2) -> i32 {
+        let grid_size = self.grid_size.max(1) as i32;
+        let index = position.x / grid_size + position.y / grid_size * self.columns as i32;
+        self.cells.get(index as usize).copied().unwrap_or(0)
+    }
+
+    /// Whether or not there is a tile covering the given pixel position in the layer
+    pub fn has_tile_at(&self, position: Vec2) -> bool {
+        let grid_size = self.grid_size as f32;
+        self.tiles.iter().any(|tile| {
+            let min = tile.position.as_f32();
+            let max = min + Vec2::splat(grid_size);
+            position.x >= min.x && position.y >= min.y && position.x < max.x && position.y < max.y
+        })
+    }
+}
+
+/// A tile in a [`GameLayer`]
+#[derive(Clone, Copy, Debug)]
+pub struct GameTile {
+    /// The position of the top-left corner of the tile relative to its layer
+    pub position: IVec2,
+    /// The ID of the tileset the tile is from
+    pub tileset: i32,
+    /// The ID of the tile in its tileset
+    pub id: i32,
+}
+
+/// An entity in a [`GameLevel`], such as an entrance or an enemy spawn point
+#[derive(Clone, Debug, Default)]
+pub struct GameEntity {
+    /// The kind of entity, such as `Entrance`
+    pub identifier: String,
+    /// The position of the entity relative to its level. For LDtk entities this is the pivot
+    /// point, and for Tiled objects it is the top-left corner.
+    pub position: Vec2,
+    /// The size of the entity in pixels
+    pub size: Vec2,
+    /// The z position to spawn the entity at: the z position of its layer, plus its `depth` field
+    /// if it has one
+    pub z: f32,
+    /// The custom fields set on the entity
+    pub fields: HashMap<String, serde_yaml::Value>,
+}
+
+impl GameEntity {
+    /// Get the value of a field, if it is set and has the right type
+    pub fn field<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        field(&self.fields, name)
+    }
+
+    /// Get the value of a string field, if it is set
+    pub fn field_str(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).and_then(|x| x.as_str())
+    }
+
+    /// Get the ID used to persist the entity's state: its `id` field if it has one, or else its
+    /// identifier and position in the level
+    pub fn persistent_id(&self) -> String {
+        self.field_str("id").map(String::from).unwrap_or_else(|| {
+            format!(
+                "{}@{},{}",
+                self.identifier, self.position.x, self.position.y
+            )
+        })
+    }
+}
+
+/// Deserialize a field value, treating `null` values as unset
+fn field<T: DeserializeOwned>(
+    fields: &HashMap<String, serde_yaml::Value>,
+    name: &str,
+) -> Option<T> {
+    let value = fields.get(name)?;
+    if value.is_null() {
+        return None;
+    }
+
+    match serde_yaml::from_value(value.clone()) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!(%error, field=%name, "Could not parse map field");
+            None
+        }
+    }
+}
+
+/// Get the z position for an entity in the layer at the given index, offset by its `depth` field
+pub(crate) fn entity_z(layer_index: usize, fields: &HashMap<String, serde_yaml::Value>) -> f32 {
+    layer_index as f32 * LAYER_DEPTH + field::<f32>(fields, "depth").unwrap_or(0.)
+}
+
+/// Whether or not a layer with the given identifier is a roof layer, which is any layer with a name
+/// starting with `Roof`, such as `Roof` or `RoofHouses`
+pub(crate) fn is_roof_layer(identifier: &str) -> bool {
+    identifier.starts_with("Roof")
+}
+
+/// Component added to every map layer sprite, for both LDtk and Tiled maps
+pub struct MapLayer {
+    /// The identifier of the level the layer is in
+    pub level_identifier: String,
+    /// The identifier of the layer
+    pub identifier: String,
+    /// Whether or not the layer should be visible when its level is shown
+    pub visible: bool,
+}
+
+/// Component added to LDtk map layers once they have been moved to their instanced level's
+/// position
+pub struct InstanceOffsetApplied;
+
+/// Parse a `#RRGGBB` or `#AARRGGBB` map background color. The alpha is ignored, since there is
+/// nothing behind the background to show through it.
+pub(crate) fn parse_map_color(color: &str) -> Option<Color> {
+    let bytes = hex::decode(color.strip_prefix('#')?).ok()?;
+    match bytes[..] {
+        [r, g, b] | [_, r, g, b] => Some(Color::from_rgba8(r, g, b, 255)),
+        _ => None,
+    }
+}
+
+/// A tileset used by a [`GameMap`]
+#[derive(Clone, Debug, Default)]
+pub struct GameTileset {
+    /// The name of the tileset
+    pub identifier: String,
+    /// The tileset image
+    pub image: Handle<Image>,
+    /// The size of the tiles in pixels
+    pub tile_size: u32,
+    /// The width of the tileset in tiles
+    pub columns: u32,
+    /// The metadata for the tiles that have it, by tile ID
+    pub tiles: HashMap<i32, TilesetTileMetadata>,
+    /// The tags set on tiles, such as LDtk tileset enum values, by tile ID
+    pub tags: HashMap<i32, Vec<String>>,
+}
+
+impl GameTileset {
+    /// Get the pixel position of the top-left corner of a tile in the tileset image
+    pub fn tile_position(&self, tile_id: i32) -> UVec2 {
+        let columns = self.columns.max(1);
+        let tile_id = tile_id.max(0) as u32;
+        UVec2::new(tile_id % columns, tile_id / columns) * self.tile_size
+    }
+
+    /// Get the metadata for a tile, filling in anything not set in its own metadata from the
+    /// metadata for its tags
+    ///
+    /// Returns `None` if the tile has no metadata and none of its tags have metadata.
+    pub fn tile_metadata(
+        &self,
+        tile_id: i32,
+        tag_metadata: &HashMap<String, TilesetTileMetadata>,
+    ) -> Option<TilesetTileMetadata> {
+        let mut metadata = self.tiles.get(&tile_id).cloned();
+
+        for tag in self.tags.get(&tile_id).into_iter().flatten() {
+            let tag_metadata = if let Some(tag_metadata) = tag_metadata.get(tag) {
+                tag_metadata
+            } else {
+                continue;
+            };
+
+            let metadata = metadata.get_or_insert_with(Default::default);
+            if let TilesetTileCollisionMode::None = metadata.collision {
+                metadata.collision = tag_metadata.collision.clone();
+            }
+            if metadata.damage_region.is_none() {
+                metadata.damage_region = tag_metadata.damage_region.clone();
+            }
+            if metadata.animation.is_none() {
+                metadata.animation = tag_metadata.animation.clone();
+            }
+            if metadata.speed.is_none() {
+                metadata.speed = tag_metadata.speed;
+            }
+            if metadata.nav_cost.is_none() {
+                metadata.nav_cost = tag_metadata.nav_cost;
+            }
+            metadata.nav_forbidden |= tag_metadata.nav_forbidden;
+            metadata.secret |= tag_metadata.secret;
+            metadata.reflective |= tag_metadata.reflective;
+            if metadata.footprint.is_none() {
+                metadata.footprint = tag_metadata.footprint.clone();
+            }
+        }
+
+        metadata
+    }
+}
+
+/// The empty space left around instanced levels, in pixels
+const INSTANCED_LEVEL_MARGIN: f32 = 2048.;
+
+/// The `to` value of entrances that lead into the first room of the dungeon
+const DUNGEON_ENTRANCE: &str = "@dungeon";
+/// The `to` value of entrances that lead to the next room of the dungeon
+const NEXT_ROOM: &str = "@next";
+/// The `to` value of entrances that lead to the previous room of the dungeon
+const PREVIOUS_ROOM: &str = "@previous";
+/// The field that the original `to` value of dungeon entrances is kept in, so that they can be
+/// linked again for a new run
+const DUNGEON_LINK_FIELD: &str = "dungeon_link";
+
+/// Whether or not an entrance's `to` value is a dungeon link that hasn't been linked to a level
+pub(crate) fn is_dungeon_link(to: &str) -> bool {
+    to.starts_with('@')
+}
+
+/// An entrance found while linking dungeon rooms
+struct DungeonEntrance {
+    level_index: usize,
+    entity_index: usize,
+    /// The identifier of the entrance's level
+    level: String,
+    /// The entrance's `id` field
+    id: String,
+    /// The entrance's dungeon link, such as `@next`
+    link: String,
+}
+
+impl GameMap {
+    /// Get a level by its identifier
+    pub fn level(&self, identifier: &str) -> Option<&GameLevel> {
+        self.levels.iter().find(|x| x.identifier == identifier)
+    }
+
+    /// Move levels with the `instanced` field set away from the rest of the map, into a row to the
+    /// right of it
+    ///
+    /// This lets interiors be laid out anywhere in the map file without the camera, collisions, or
+    /// navigation meshes of other levels leaking into them.
+    pub fn place_instanced_levels(&mut self) {
+        let is_instanced = |level: &GameLevel| level.field::<bool>("instanced").unwrap_or(false);
+
+        // Start the row to the right of all of the other levels
+        let mut next_x = self
+            .levels
+            .iter()
+            .filter(|x| !is_instanced(x))
+            .map(|x| x.position.x + x.size.x as f32)
+            .fold(0., f32::max)
+            + INSTANCED_LEVEL_MARGIN;
+
+        for level in &mut self.levels {
+            if !is_instanced(level) {
+                continue;
+            }
+
+            let position = Vec2::new(next_x, 0.);
+            level.instance_offset = position - level.position;
+            level.position = position;
+            next_x += level.size.x as f32 + INSTANCED_LEVEL_MARGIN;
+        }
+    }
+
+    /// Link the entrances of the dungeon rooms to each-other, in the given order
+    ///
+    /// Entrances with a dungeon link that doesn't lead anywhere in this run, such as the `@next`
+    /// entrances of rooms that weren't picked, are left unlinked and aren't spawned.
+    pub fn link_dungeon_rooms(&mut self, rooms: &[String]) {
+        // Find every dungeon entrance, remembering its link for the next time we link the rooms
+        let mut entrances = Vec::new();
+        for (level_index, level) in self.levels.iter_mut().enumerate() {
+            for (entity_index, entity) in level.entities.iter_mut().enumerate() {
+                if entity.identifier != "Entrance" {
+                    continue;
+                }
+                let link = match entity
+                    .field_str(DUNGEON_LINK_FIELD)
+                    .or_else(|| entity.field_str("to"))
+                {
+                    Some(link) if is_dungeon_link(link) => link.to_string(),
+                    _ => continue,
+                };
+                entity
+                    .fields
+                    .insert(DUNGEON_LINK_FIELD.into(), link.clone().into());
+                entrances.push(DungeonEntrance {
+                    level_index,
+                    entity_index,
+                    level: level.identifier.clone(),
+                    id: entity.field_str("id").unwrap_or_default().into(),
+                    link,
+                });
+            }
+        }
+
+        // Get the level and ID of the first entrance in a level with the given link
+        let find = |level: &str, link: &str| {
+            entrances
+                .iter()
+                .find(|x| x.level == level && x.link == link)
+                .map(|x| (x.level.clone(), x.id.clone()))
+        };
+        // The entrance that leads into the dungeon, which the player leaves the dungeon through
+        let dungeon_entrance = entrances
+            .iter()
+            .find(|x| x.link == DUNGEON_ENTRANCE && !rooms.contains(&x.level))
+            .map(|x| (x.level.clone(), x.id.clone()));
+
+        let mut targets = Vec::new();
+        for entrance in &entrances {
+            let room = rooms.iter().position(|x| x == &entrance.level);
+            let target = match (entrance.link.as_str(), room) {
+                (DUNGEON_ENTRANCE, None) => rooms.first().and_then(|x| find(x, PREVIOUS_ROOM)),
+                (PREVIOUS_ROOM, Some(0)) => dungeon_entrance.clone(),
+                (PREVIOUS_ROOM, Some(i)) => find(&rooms[i - 1], NEXT_ROOM),
+                (NEXT_ROOM, Some(i)) if i + 1 == rooms.len() => dungeon_entrance.clone(),
+                (NEXT_ROOM, Some(i)) => find(&rooms[i + 1], PREVIOUS_ROOM),
+                _ => None,
+            };
+            // Unlinked entrances keep their link as their destination
+            let (to, spawn_at) = target.unwrap_or_else(|| (entrance.link.clone(), String::new()));
+            targets.push((entrance.level_index, entrance.entity_index, to, spawn_at));
+        }
+
+        for (level_index, entity_index, to, spawn_at) in targets {
+            let fields = &mut self.levels[level_index].entities[entity_index].fields;
+            fields.insert("to".into(), to.into());
+            fields.insert("spawn_at".into(), spawn_at.into());
+        }
+    }
+
+    /// Convert an LDtk map
+    pub fn from_ldtk(map: &LdtkMap) -> Self {
+        let tilesets = map
+            .project
+            .defs
+            .tilesets
+            .iter()
+            .map(|tileset_def| {
+                // Parse the tile metadata from the custom tile data
+                let mut tiles = HashMap::default();
+                for tile_data in &tileset_def.custom_data {
+                    let tile_id = tile_data
+                        .get("tileId")
+                        .and_then(|x| x.as_i64())
+                        .map(|x| x as i32);
+                    let data = tile_data.get("data").and_then(|x| x.as_str());
+                    let (tile_id, data) = if let (Some(tile_id), Some(data)) = (tile_id, data) {
+                        (tile_id, data)
+                    } else {
+                        warn!(
+                            tileset_id=%tileset_def.identifier,
+                            "Tile data missing `tileId` or `data` field, ignoring"
+                        );
+                        continue;
+                    };
+
+                    match serde_yaml::from_str(data) {
+                        Ok(metadata) => {
+                            tiles.insert(tile_id, metadata);
+                        }
+                        Err(error) => warn!(
+                            %error,
+                            %tile_id,
+                            tileset_id=%tileset_def.identifier,
+                            "Could not parse tileset tile metadata, ignoring"
+                        ),
+                    }
+                }
+
+                // Get the enum values tagged on tiles
+                let mut tags = HashMap::<i32, Vec<String>>::default();
+                for enum_tag in &tileset_def.enum_tags {
+                    let enum_value = enum_tag.get("enumValueId").and_then(|x| x.as_str());
+                    let tile_ids = enum_tag.get("tileIds").and_then(|x| x.as_array());
+                    if let (Some(enum_value), Some(tile_ids)) = (enum_value, tile_ids) {
+                        for tile_id in tile_ids.iter().filter_map(|x| x.as_i64()) {
+                            tags.entry(tile_id as i32)
+                                .or_default()
+                                .push(enum_value.to_owned());
+                        }
+                    }
+                }
+
+                (
+                    tileset_def.uid as i32,
+                    GameTileset {
+                        identifier: tileset_def.identifier.clone(),
+                        image: map
+                            .tile_sets
+                            .get(&tileset_def.identifier)
+                            .cloned()
+                            .unwrap_or_default(),
+                        tile_size: tileset_def.tile_grid_size as u32,
+                        columns: tileset_def.__c_wid as u32,
+                        tiles,
+                        tags,
+                    },
+                )
+            })
+            .collect();
+
+        // Convert LDtk field instances to field values
+        macro_rules! fields {
+            ($field_instances:expr) => {
+                $field_instances
+                    .iter()
+                    .filter_map(|field| {
+                        serde_yaml::to_value(&field.__value)
+                            .ok()
+                            .map(|value| (field.__identifier.clone(), value))
+                    })
+                    .collect()
+            };
+        }
+
+        let levels = map
+            .project
+            .levels
+            .iter()
+            .map(|level| {
+                let layer_instances = level.layer_instances.as_deref().unwrap_or_default();
+
+                let mut layers = Vec::new();
+                let mut entities = Vec::new();
+                // LDtk lists layers from the top-most to the bottom-most
+                for layer in layer_instances.iter().rev() {
+                    let offset = Vec2::new(
+                        layer.__px_total_offset_x as f32,
+                        layer.__px_total_offset_y as f32,
+                    );
+
+                    for entity in &layer.entity_instances {
+                        let fields = fields!(entity.field_instances);
+                        entities.push(GameEntity {
+                            identifier: entity.__identifier.clone(),
+                            position: offset + Vec2::new(entity.px[0] as f32, entity.px[1] as f32),
+                            size: Vec2::new(entity.width as f32, entity.height as f32),
+                            z: entity_z(layers.len(), &fields),
+                            fields,
+                        });
+                    }
+
+                    let tiles = layer
+                        .__tileset_def_uid
+                        .map(|tileset| {
+                            layer
+                                .grid_tiles
+                                .iter()
+                                .chain(layer.auto_layer_tiles.iter())
+                                .map(|tile| GameTile {
+                                    position: IVec2::new(tile.px[0] as i32, tile.px[1] as i32),
+                                    tileset: tileset as i32,
+                                    id: tile.t as i32,
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+
+                    layers.push(GameLayer {
+                        identifier: layer.__identifier.clone(),
+                        offset,
+                        grid_size: layer.__grid_size as u32,
+                        columns: layer.__c_wid as u32,
+                        tiles,
+                        cells: layer.int_grid_csv.iter().map(|&x| x as i32).collect(),
+                        roof: is_roof_layer(&layer.__identifier),
+                    });
+                }
+
+                GameLevel {
+                    identifier: level.identifier.clone(),
+                    position: Vec2::new(level.world_x as f32, level.world_y as f32),
+                    instance_offset: Vec2::ZERO,
+                    size: UVec2::new(level.px_wid as u32, level.px_hei as u32),
+                    grid_size: layer_instances
+                        .get(0)
+                        .map(|x| x.__grid_size as u32)
+                        .unwrap_or(16),
+                    background_color: parse_map_color(
+                        level
+                            .bg_color
+                            .as_ref()
+                            .unwrap_or(&map.project.default_level_bg_color),
+                    ),
+                    fields: fields!(level.field_instances),
+                    layers,
+                    entities,
+                }
+            })
+            .collect();
+
+        let mut map = Self { levels, tilesets };
+        map.place_instanced_levels();
+        map
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::DungeonUnlock;
+
+pub fn add_meta_progress(app: &mut AppBuilder) {
+    app.init_resource::<MetaProgress>();
+}
+
+/// The progress that is kept between dungeon runs: the meta-currency and the unlocks bought with
+/// it
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct MetaProgress {
+    /// The meta-currency the player has to spend
+    pub currency: u32,
+    /// The IDs of the unlocks that have been bought
+    pub unlocked: BTreeSet<String>,
+}
+
+impl MetaProgress {
+    /// Whether or not an unlock has been bought
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+
+    /// Buy an unlock, returning `false` without changing anything if it has already been bought
+    /// or the player can't afford it
+    pub fn buy(&mut self, unlock: &DungeonUnlock) -> bool {
+        if self.is_unlocked(&unlock.id) || self.currency < unlock.cost {
+            return false;
+        }
+
+        self.currency -= unlock.cost;
+        self.unlocked.insert(unlock.id.clone());
+        true
+    }
+}
@@ -0,0 +1,332 @@
+use std::sync::{Arc, Mutex};
+#[cfg(not(wasm))]
+use std::{future::Future, path::Path};
+
+#[cfg(not(wasm))]
+use bevy::tasks::TaskPool;
+use bevy::{prelude::*, tasks::IoTaskPool};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{BuildInfo, EngineConfig, Storage};
+
+#[cfg(not(wasm))]
+use super::StartupGameInfo;
+
+use super::{
+    Affinities, BestTimes, CurrentLevel, DungeonRun, Equipment, GameClock, GameInfo, GameState,
+    HighScores, Inventory, LevelStates, LocalLeaderboard, MetaProgress, UserSettings,
+};
+
+/// The storage key of the user settings
+const SETTINGS_KEY: &str = "settings.yaml";
+
+/// The storage key of the high scores and best level times, which are kept separate from the save
+/// slots
+const LEADERBOARD_KEY: &str = "leaderboard.json";
+
+pub fn add_persistence(app: &mut AppBuilder) {
+    app.init_resource::<StorageLoads>()
+        .init_resource::<SaveRequest>()
+        .add_system(open_storage.system())
+        .add_system(finish_storage_loads.system())
+        .add_system(autosave.system())
+        .add_system(save_leaderboard.system());
+}
+
+/// The player's saved progress, which is loaded from storage when the game starts and is
+/// continued from when the player starts the game
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct SaveGame {
+    /// The level the player was last in
+    pub level: Option<String>,
+    /// The state of the world
+    pub level_states: LevelStates,
+    /// The paths of the items the player is wearing, if they have been saved
+    pub equipment: Option<Vec<String>>,
+    /// The items the player is carrying
+    pub inventory: Inventory,
+    /// The in-game time, if it has been saved
+    pub clock: Option<GameClock>,
+    /// How much each NPC likes the player
+    pub affinities: Affinities,
+    /// The player's current dungeon run
+    pub dungeon: DungeonRun,
+    /// The progress kept between dungeon runs
+    pub meta: MetaProgress,
+    /// The engine build the game was last saved with, to help track down problems with old saves
+    pub engine_build: Option<String>,
+}
+
+/// A request to save the game the next time it is playing, i.e. after the player sleeps, in
+/// addition to the autosave when entering a level
+#[derive(Default)]
+pub struct SaveRequest(pub bool);
+
+/// Values that are being loaded from storage in the background
+#[derive(Default)]
+struct StorageLoads {
+    settings: Arc<Mutex<Option<UserSettings>>>,
+    save: Arc<Mutex<Option<SaveGame>>>,
+    leaderboard: Arc<Mutex<Option<LocalLeaderboard>>>,
+}
+
+/// Open the game's storage once the game info has loaded, and start loading the user settings and
+/// save game from it
+fn open_storage(
+    mut commands: Commands,
+    game_info: Option<Res<GameInfo>>,
+    task_pool: Res<IoTaskPool>,
+    loads: Res<StorageLoads>,
+    engine_config: Res<EngineConfig>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+    if !game_info.is_added() {
+        return;
+    }
+
+    // Keep each game's data separate, unless we are running portable
+    #[cfg(not(wasm))]
+    let storage = if engine_config.portable {
+        Storage::portable()
+    } else {
+        Storage::new(&game_info.title)
+    };
+    #[cfg(wasm)]
+    let storage = Storage::new(&game_info.title);
+
+    load(&task_pool, &storage, SETTINGS_KEY, loads.settings.clone());
+    load(
+        &task_pool,
+        &storage,
+        save_key(&engine_config),
+        loads.save.clone(),
+    );
+    load(
+        &task_pool,
+        &storage,
+        LEADERBOARD_KEY,
+        loads.leaderboard.clone(),
+    );
+    commands.insert_resource(storage);
+}
+
+/// Apply the values that have finished loading from storage
+fn finish_storage_loads(
+    mut commands: Commands,
+    loads: Res<StorageLoads>,
+    mut settings: ResMut<UserSettings>,
+    mut high_scores: ResMut<HighScores>,
+    mut best_times: ResMut<BestTimes>,
+) {
+    if let Some(loaded_settings) = loads.settings.lock().unwrap().take() {
+        *settings = loaded_settings;
+    }
+
+    if let Some(save) = loads.save.lock().unwrap().take() {
+        commands.insert_resource(save);
+    }
+
+    if let Some(leaderboard) = loads.leaderboard.lock().unwrap().take() {
+        *high_scores = leaderboard.high_scores;
+        *best_times = leaderboard.best_times;
+    }
+}
+
+/// Save the game whenever the player enters a level, or when a save is requested
+fn autosave(
+    mut was_playing: Local<bool>,
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    current_level: Option<Res<CurrentLevel>>,
+    level_states: Res<LevelStates>,
+    inventory: Res<Inventory>,
+    clock: Res<GameClock>,
+    affinities: Res<Affinities>,
+    dungeon_run: Res<DungeonRun>,
+    meta: Res<MetaProgress>,
+    storage: Option<Res<Storage>>,
+    task_pool: Res<IoTaskPool>,
+    engine_config: Res<EngineConfig>,
+    equipment: Query<&Equipment>,
+    asset_server: Res<AssetServer>,
+    previous_save: Option<Res<SaveGame>>,
+    mut save_request: ResMut<SaveRequest>,
+) {
+    // Save when the game starts too, so the player continues from the first level
+    let playing = state.current() == &GameState::Playing;
+    let started_playing = playing && !*was_playing;
+    *was_playing = playing;
+
+    let (current_level, storage) = match (current_level, storage) {
+        (Some(current_level), Some(storage)) => (current_level, storage),
+        _ => return,
+    };
+    if !playing || !(current_level.is_changed() || started_playing || save_request.0) {
+        return;
+    }
+    save_request.0 = false;
+
+    let mut save = SaveGame {
+        level: Some(current_level.as_str().into()),
+        level_states: level_states.clone(),
+        equipment: equipment.single().ok().map(|equipment| {
+            equipment
+                .items
+                .iter()
+                .filter_map(|x| asset_server.get_handle_path(x))
+                .map(|x| x.path().to_string_lossy().into_owned())
+                .collect()
+        }),
+        inventory: inventory.clone(),
+        clock: Some(clock.clone()),
+        affinities: affinities.clone(),
+        dungeon: dungeon_run.clone(),
+        meta: meta.clone(),
+        engine_build: Some(BuildInfo::get().to_string()),
+    };
+    // Keep the saved equipment if the player hasn't finished spawning yet
+    if save.equipment.is_none() {
+        save.equipment = previous_save.and_then(|x| x.equipment.clone());
+    }
+    store(&task_pool, &storage, save_key(&engine_config), &save);
+    commands.insert_resource(save);
+}
+
+/// Save the high scores and best level times whenever one of them is added
+fn save_leaderboard(
+    high_scores: Res<HighScores>,
+    best_times: Res<BestTimes>,
+    storage: Option<Res<Storage>>,
+    task_pool: Res<IoTaskPool>,
+) {
+    if !(high_scores.is_changed() || best_times.is_changed()) {
+        return;
+    }
+    if let Some(storage) = storage {
+        let leaderboard = LocalLeaderboard {
+            high_scores: high_scores.clone(),
+            best_times: best_times.clone(),
+        };
+        store(&task_pool, &storage, LEADERBOARD_KEY, &leaderboard);
+    }
+}
+
+/// Add the high scores and best times of a leaderboard exported by the game, i.e. on another
+/// device, to the leaderboard kept in the storage of the game in `asset_dir`
+#[cfg(not(wasm))]
+pub fn import_leaderboard_file(
+    file: &Path,
+    asset_dir: &Path,
+    portable: bool,
+) -> anyhow::Result<()> {
+    let game_info: StartupGameInfo =
+        serde_yaml::from_slice(&std::fs::read(asset_dir.join("default.game.yaml"))?)?;
+    let storage = if portable {
+        Storage::portable()
+    } else {
+        Storage::new(&game_info.title)
+    };
+    let max_scores = game_info.score.unwrap_or_default().high_scores;
+
+    // JSON is also YAML, so the exported leaderboard is read the same way as stored values
+    let imported: LocalLeaderboard = serde_yaml::from_slice(&std::fs::read(file)?)?;
+
+    let task_pool = TaskPool::new();
+    let mut leaderboard = match block_on(&task_pool, storage.get(LEADERBOARD_KEY))? {
+        Some(data) => serde_yaml::from_slice(&data)?,
+        None => LocalLeaderboard::default(),
+    };
+    imported.merge_into(
+        &mut leaderboard.high_scores,
+        &mut leaderboard.best_times,
+        max_scores,
+    );
+    let data = serde_json::to_vec_pretty(&leaderboard)?;
+    block_on(&task_pool, storage.put(LEADERBOARD_KEY, data))
+}
+
+/// Run a storage future to completion outside of the game, i.e. in a command
+#[cfg(not(wasm))]
+fn block_on<T: Send + 'static>(task_pool: &TaskPool, future: impl Future<Output = T> + Send) -> T {
+    task_pool.scope(|scope| scope.spawn(future)).pop().unwrap()
+}
+
+/// Get the storage key of the save game in the configured save slot
+fn save_key(engine_config: &EngineConfig) -> String {
+    match &engine_config.save_slot {
+        Some(slot) => format!("save-{}.yaml", slot),
+        None => "save.yaml".into(),
+    }
+}
+
+/// Save the user settings to storage, i.e. when the player saves the settings menu
+pub fn save_user_settings(world: &World) {
+    if let Some(storage) = world.get_resource::<Storage>() {
+        let task_pool = world.get_resource::<IoTaskPool>().unwrap();
+        let settings = world.get_resource::<UserSettings>().unwrap();
+        store(task_pool, storage, SETTINGS_KEY, settings);
+    }
+}
+
+/// Load a value from storage in the background, putting it in `output` if it exists
+fn load<T: DeserializeOwned + Send + 'static>(
+    task_pool: &IoTaskPool,
+    storage: &Storage,
+    key: impl Into<String>,
+    output: Arc<Mutex<Option<T>>>,
+) {
+    let key = key.into();
+    let storage = storage.clone();
+    task_pool
+        .spawn(async move {
+            let value = match storage.get(&key).await {
+                Ok(Some(data)) => serde_yaml::from_slice(&data).map_err(anyhow::Error::from),
+                Ok(None) => return,
+                Err(e) => Err(e),
+            };
+
+            match value {
+                Ok(value) => *output.lock().unwrap() = Some(value),
+                Err(e) => warn!(%key, %e, "Could not load from storage"),
+            }
+        })
+        .detach();
+}
+
+/// Write a value to storage in the background
+fn store<T: Serialize>(
+    task_pool: &IoTaskPool,
+    storage: &Storage,
+    key: impl Into<String>,
+    value: &T,
+) {
+    let key = key.into();
+    // JSON is also YAML, so values stored as JSON are loaded the same way
+    let data = if key.ends_with(".json") {
+        serde_json::to_vec_pretty(value).map_err(anyhow::Error::from)
+    } else {
+        serde_yaml::to_vec(value).map_err(anyhow::Error::from)
+    };
+    let data = match data {
+        Ok(data) => data,
+        Err(e) => {
+            warn!(%key, %e, "Could not serialize value to store");
+            return;
+        }
+    };
+
+    let storage = storage.clone();
+    task_pool
+        .spawn(async move {
+            if let Err(e) = storage.put(&key, data).await {
+                warn!(%key, %e, "Could not write to storage");
+            }
+        })
+        .detach();
+}
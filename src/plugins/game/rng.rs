@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+
+pub fn add_rng(app: &mut AppBuilder) {
+    // Use the seed from the engine config if one was given
+    let seed = app
+        .world()
+        .get_resource::<crate::EngineConfig>()
+        .and_then(|x| x.seed)
+        .unwrap_or_else(random_seed);
+    debug!(%seed, "Seeding game RNG");
+
+    app.insert_resource(GameRng::new(seed));
+}
+
+/// The random number generator used for gameplay such as loot drops, spawners, and wandering AI
+///
+/// Gameplay systems should always use this instead of their own RNG so that a run can be
+/// reproduced by starting with the same seed, i.e. for testing and replay playback.
+pub struct GameRng {
+    seed: u64,
+    rng: Pcg32,
+}
+
+impl GameRng {
+    /// Create an RNG with the given seed
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Pcg32::seed_from_u64(seed),
+        }
+    }
+
+    /// The seed the RNG was last seeded with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restart the RNG from the given seed
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+}
+
+impl std::ops::Deref for GameRng {
+    type Target = Pcg32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.rng
+    }
+}
+
+impl std::ops::DerefMut for GameRng {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.rng
+    }
+}
+
+/// Get a seed for when one isn't given in the engine config
+#[cfg(not(wasm))]
+fn random_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+/// Get a seed for when one isn't given in the engine config
+#[cfg(wasm)]
+fn random_seed() -> u64 {
+    let performance = web_sys::window().unwrap().performance().unwrap();
+    ((performance.time_origin() + performance.now()) * 1000.) as u64
+}
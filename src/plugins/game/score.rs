@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ScoreConfig;
+
+pub fn add_score(app: &mut AppBuilder) {
+    app.init_resource::<Score>().init_resource::<HighScores>();
+}
+
+/// The player's score in the current game, and the combo that multiplies the points they get
+#[derive(Debug, Clone, Default)]
+pub struct Score {
+    pub points: u32,
+    /// How many times the player has scored without the combo running out
+    pub combo: u32,
+    /// The time left before the combo runs out, in seconds
+    pub combo_remaining: f32,
+}
+
+impl Score {
+    /// Get the amount the points the player gets are multiplied by at the current combo
+    pub fn multiplier(&self, config: &ScoreConfig) -> f32 {
+        (1. + self.combo as f32 * config.combo_step).min(config.max_multiplier.max(1.))
+    }
+
+    /// Give the player points, multiplied by their combo, and continue the combo
+    pub fn add(&mut self, points: u32, config: &ScoreConfig) {
+        self.points += (points as f32 * self.multiplier(config)).round() as u32;
+        self.combo += 1;
+        self.combo_remaining = config.combo_time;
+    }
+
+    /// Count down the time left in the combo, ending it when it runs out
+    pub fn tick(&mut self, delta: f32) {
+        if self.combo == 0 {
+            return;
+        }
+
+        self.combo_remaining -= delta;
+        if self.combo_remaining <= 0. {
+            self.combo = 0;
+            self.combo_remaining = 0.;
+        }
+    }
+}
+
+/// The best scores from past games, highest first, which are saved between play sessions
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct HighScores(pub Vec<u32>);
+
+impl HighScores {
+    /// Add a score to the table if it is one of the best `max` scores, returning whether it was
+    /// added
+    pub fn record(&mut self, score: u32, max: usize) -> bool {
+        if score == 0 {
+            return false;
+        }
+
+        let index = self
+            .0
+            .iter()
+            .position(|&x| score > x)
+            .unwrap_or(self.0.len());
+        if index >= max {
+            return false;
+        }
+        self.0.insert(index, score);
+        self.0.truncate(max);
+        true
+    }
+}
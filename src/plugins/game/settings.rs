@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::asset_overlay::ContentPacks;
+
+pub fn add_settings(app: &mut AppBuilder) {
+    app.init_resource::<UserSettings>()
+        .init_resource::<ContentPacks>()
+        .add_system(apply_disabled_content_packs.system());
+}
+
+/// Stop loading assets from the mods that the player has disabled
+fn apply_disabled_content_packs(settings: Res<UserSettings>, content_packs: Res<ContentPacks>) {
+    if settings.is_changed() {
+        content_packs.set_disabled(&settings.disabled_content_packs);
+    }
+}
+
+/// The player's own settings, as opposed to the game settings in the [`GameInfo`][super::GameInfo]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct UserSettings {
+    /// How fast dialogue and sign text is revealed
+    pub text_speed: TextSpeed,
+    /// Whether or not to rumble, which vibrates the device on the web
+    pub rumble: bool,
+    /// Accessibility options
+    pub accessibility: AccessibilitySettings,
+    /// The IDs of the mods that the player has turned off
+    pub disabled_content_packs: Vec<String>,
+    /// Whether the player agrees to share gameplay analytics, for games that record them
+    pub share_analytics: bool,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            text_speed: Default::default(),
+            rumble: true,
+            accessibility: Default::default(),
+            disabled_content_packs: Vec::new(),
+            share_analytics: false,
+        }
+    }
+}
+
+/// Accessibility options
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// Whether or not the camera shakes, i.e. when the player is hit
+    pub screen_shake: bool,
+    /// Replace sudden screen flashes with gentle fades
+    pub reduce_flashes: bool,
+    /// The size of dialogue and sign text
+    pub text_size: TextSize,
+    /// Use the game's high-contrast UI theme, if it has one
+    pub high_contrast: bool,
+    /// Show captions for important sounds
+    pub captions: bool,
+    /// How fast the game runs, from `0.0` to `1.0`, for players who need more time to react
+    pub game_speed: f32,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            screen_shake: true,
+            reduce_flashes: false,
+            text_size: Default::default(),
+            high_contrast: false,
+            captions: false,
+            game_speed: 1.,
+        }
+    }
+}
+
+impl AccessibilitySettings {
+    /// The game speeds that the settings menu cycles through
+    const GAME_SPEEDS: [f32; 4] = [1., 0.9, 0.75, 0.5];
+
+    /// Get the next slower game speed, wrapping around to full speed after the slowest
+    pub fn next_game_speed(&self) -> f32 {
+        Self::GAME_SPEEDS
+            .iter()
+            .copied()
+            .find(|x| *x < self.game_speed - f32::EPSILON)
+            .unwrap_or(Self::GAME_SPEEDS[0])
+    }
+}
+
+/// The size of dialogue and sign text
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextSize {
+    Normal,
+    Large,
+    Huge,
+}
+
+impl Default for TextSize {
+    fn default() -> Self {
+        TextSize::Normal
+    }
+}
+
+impl TextSize {
+    /// The amount to scale the text by
+    pub fn scale(&self) -> f32 {
+        match self {
+            TextSize::Normal => 1.,
+            TextSize::Large => 1.5,
+            TextSize::Huge => 2.,
+        }
+    }
+
+    /// Get the next size, wrapping around to the smallest size after the largest
+    pub fn next(&self) -> Self {
+        match self {
+            TextSize::Normal => TextSize::Large,
+            TextSize::Large => TextSize::Huge,
+            TextSize::Huge => TextSize::Normal,
+        }
+    }
+
+    /// The name of the size to show in the settings menu
+    pub fn label(&self) -> &'static str {
+        match self {
+            TextSize::Normal => "Normal",
+            TextSize::Large => "Large",
+            TextSize::Huge => "Huge",
+        }
+    }
+}
+
+/// How fast text is revealed by the typewriter effect
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextSpeed {
+    Slow,
+    Normal,
+    Fast,
+    Instant,
+}
+
+impl Default for TextSpeed {
+    fn default() -> Self {
+        TextSpeed::Normal
+    }
+}
+
+impl TextSpeed {
+    /// The number of characters revealed per second, or `None` if the text should be shown all at
+    /// once
+    pub fn characters_per_second(&self) -> Option<f32> {
+        match self {
+            TextSpeed::Slow => Some(15.),
+            TextSpeed::Normal => Some(30.),
+            TextSpeed::Fast => Some(60.),
+            TextSpeed::Instant => None,
+        }
+    }
+
+    /// Get the next speed, wrapping around to the slowest speed after the fastest
+    pub fn next(&self) -> Self {
+        match self {
+            TextSpeed::Slow => TextSpeed::Normal,
+            TextSpeed::Normal => TextSpeed::Fast,
+            TextSpeed::Fast => TextSpeed::Instant,
+            TextSpeed::Instant => TextSpeed::Slow,
+        }
+    }
+
+    /// The name of the speed to show in the settings menu
+    pub fn label(&self) -> &'static str {
+        match self {
+            TextSpeed::Slow => "Slow",
+            TextSpeed::Normal => "Normal",
+            TextSpeed::Fast => "Fast",
+            TextSpeed::Instant => "Instant",
+        }
+    }
+}
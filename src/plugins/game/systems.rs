@@ -1,4 +1,5 @@
 use bevy::{
+    core::FixedTimestep,
     ecs::{component::ComponentDescriptor, schedule::ShouldRun},
     prelude::*,
     transform::TransformSystem,
@@ -12,21 +13,150 @@ use super::*;
 mod game_init;
 mod map_loading;
 mod pause_menu;
+pub use pause_menu::PauseRequest;
 
 mod gameplay;
 use gameplay::{
     animate_sprites, camera_follow_system, change_level, check_for_game_over, control_character,
     damage_character, enemy_follow_player, finish_spawning_character, keyboard_control_input,
-    spawn_hud, touch_control_input,
+    mouse_control_input, play_footstep_sounds, resume_character_animations, spawn_hud,
+    touch_control_input, update_cursor_world_position, update_health_feedback,
+    CameraFollowPosition, CursorWorldPosition,
 };
 
 mod game_over;
 
+mod fixed_step;
+pub use fixed_step::{FixedStepInterpolation, GameStage};
+
+mod perf_hud;
+pub use perf_hud::SystemTimings;
+
+mod fatal_error;
+pub use fatal_error::CriticalAssets;
+
+mod animated_tiles;
+
+mod attract_mode;
+
+mod captions;
+
+mod cursor;
+
+mod rumble;
+
+mod y_sort;
+
+mod roofs;
+
+mod level_state;
+
+mod palette;
+pub use palette::PaletteOverride;
+
+mod post_processing;
+
+mod sprite_animation;
+
+mod transition;
+
+mod window_icon;
+
+mod engine_config;
+
+mod auto_pause;
+
+mod abilities;
+pub use abilities::{Abilities, Energy};
+
+mod equipment;
+pub use equipment::{Equipment, Stats};
+
+mod movement;
+pub use movement::SpeedModifiers;
+
+mod crafting;
+
+mod gathering;
+
+mod farming;
+
+mod clock;
+
+mod sleep;
+
+mod npcs;
+
+mod signs;
+
+mod dungeon;
+
+mod battle;
+
+mod stealth;
+
+mod enemy_culling;
+
+mod sprite_batching;
+
+mod sprite_atlas;
+
+mod crash_context;
+
+mod photo_mode;
+
+mod free_camera;
+
+mod game_speed;
+
+mod game_time;
+
+mod inspector;
+
+mod tweak_panel;
+
+#[cfg(not(wasm))]
+mod ldtk_editor;
+
+mod spatial_hash;
+
+mod secret_walls;
+
+mod bombs;
+
+mod lifting;
+
+mod shadows;
+
+mod reflections;
+
+mod footprints;
+
+mod critters;
+
+mod path_followers;
+
+mod emotes;
+
+mod cutscenes;
+
+mod results;
+pub use results::LevelStats;
+
+mod scoring;
+
+mod analytics;
+
+#[cfg(wasm)]
+mod web_api;
+
 /// The game states
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum GameState {
     /// The game is loading initial game data, spawning the map, and displaying the start menu
     Init,
+    /// The game is showing the intro splash images before the start menu
+    Intro,
     /// The game is showing the start menu
     StartMenu,
     /// The game is loading the map and spawning the player
@@ -35,15 +165,35 @@ pub enum GameState {
     Playing,
     /// The game is paused during the main game
     Paused,
+    /// The game is paused and the player is moving the camera around to take screenshots
+    PhotoMode,
+    /// The player is in a turn-based battle with an enemy
+    Battle,
     /// The game over screen is being shown
     GameOver,
+    /// A fatal error has stopped the game and is being shown
+    Error,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, SystemLabel)]
 pub enum GameSystemLabels {
     FinishSpawn,
     Input,
+    CollectControls,
     ControlCharacter,
+    MoveEnemies,
+    UseAbilities,
+    Simulate,
+    AnimateSprites,
+    UpdateSpriteAnimations,
+    UpdateLevelShader,
+    ApplyPalettes,
+    SmoothPhysics,
+    CameraFollow,
+    MoveFreeCamera,
+    UpdateSpatialHash,
+    UpdateCursorPosition,
+    ApplyEngineConfig,
 }
 
 pub fn add_systems(app: &mut AppBuilder) {
@@ -55,13 +205,159 @@ pub fn add_systems(app: &mut AppBuilder) {
         .register_component(ComponentDescriptor::new::<gameplay::CharacterLoaded>(
             bevy::ecs::component::StorageType::SparseSet,
         ))
+        .init_resource::<gameplay::HealthFeedback>()
+        .init_resource::<post_processing::PostProcessState>()
+        .init_resource::<PaletteOverride>()
+        .init_resource::<palette::SpritePalettes>()
+        .init_resource::<transition::ScreenFade>()
+        .init_resource::<attract_mode::AttractMode>()
+        .init_resource::<attract_mode::ReplayRecording>()
+        .init_resource::<CursorWorldPosition>()
+        .init_resource::<CameraFollowPosition>()
+        .init_resource::<ui_utils::Typewriters>()
+        .init_resource::<ui_utils::TextVariables>()
+        .init_resource::<ui_utils::UiLayout>()
+        .init_resource::<ui_utils::UiThemeVariant>()
+        .init_resource::<sprite_atlas::SpriteAtlases>()
+        .init_resource::<gameplay::CameraShake>()
+        .init_resource::<captions::Captions>()
+        .init_resource::<perf_hud::PerfHud>()
+        .init_resource::<SystemTimings>()
+        .init_resource::<CriticalAssets>()
+        .init_resource::<PauseRequest>()
+        .init_resource::<crafting::CraftingAssets>()
+        .init_resource::<crafting::CraftingStation>()
+        .init_resource::<gathering::Gathering>()
+        .init_resource::<farming::Crops>()
+        .init_resource::<sleep::Sleeping>()
+        .init_resource::<npcs::Conversation>()
+        .init_resource::<signs::Sign>()
+        .init_resource::<dungeon::UnlockStation>()
+        .init_resource::<battle::Battle>()
+        .init_resource::<photo_mode::PhotoMode>()
+        .init_resource::<free_camera::FreeCamera>()
+        .init_resource::<game_speed::GameSpeed>()
+        .init_resource::<game_time::GameTime>()
+        .init_resource::<inspector::Inspector>()
+        .init_resource::<tweak_panel::TweakPanel>()
+        .init_resource::<map_loading::MapReloadFocus>()
+        .init_resource::<perf_hud::PathfindingStats>()
+        .init_resource::<spatial_hash::EnemySpatialHash>()
+        .init_resource::<reflections::FadedImages>()
+        .init_resource::<cutscenes::CutscenePlayer>()
+        .init_resource::<game_init::Intro>()
+        .init_resource::<results::LevelStats>()
+        .init_resource::<results::LevelResults>()
+        .init_resource::<analytics::Analytics>()
+        .add_system(farming::load_crops.system())
+        .add_system(crafting::load_crafting_assets.system())
+        .add_system(fatal_error::check_critical_assets.system())
+        .add_system(fatal_error::handle_fatal_errors.system())
+        .add_system(perf_hud::update_perf_hud.exclusive_system())
+        .add_system(inspector::update_inspector.exclusive_system())
+        .add_system(tweak_panel::update_tweak_panel.exclusive_system())
+        .init_resource::<fixed_step::HeldControls>()
+        .init_resource::<EntityPool<gameplay::EnemyPathfindingDebugViz>>()
+        .init_resource::<EntityPool<stealth::VisionConeDebugViz>>()
+        .init_resource::<EntityPool<footprints::Footprint>>()
+        .init_resource::<EntityPool<abilities::Projectile>>()
+        .init_resource::<EntityPool<bombs::Explosion>>()
+        // Add the fixed timestep gameplay simulation stage
+        .add_stage_after(
+            CoreStage::Update,
+            GameStage::FixedUpdate,
+            SystemStage::parallel().with_run_criteria(
+                FixedTimestep::step(fixed_step::FIXED_TIMESTEP)
+                    .with_label(fixed_step::FIXED_TIMESTEP_LABEL),
+            ),
+        )
+        .add_system_to_stage(CoreStage::PreUpdate, game_time::update_game_time.system())
+        .add_system_to_stage(
+            CoreStage::PreUpdate,
+            fixed_step::restore_fixed_step_transforms.system(),
+        )
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            fixed_step::interpolate_fixed_step_transforms
+                .system()
+                .label(SmoothPhysics)
+                .after(PhysicsSystem::TransformUpdate)
+                .before(TransformSystem::TransformPropagate),
+        )
         .add_system(switch_fullscreen.system())
+        .add_system(window_icon::update_window_icon.system())
+        .add_system(game_init::hot_reload_game_info.system())
+        .add_system(crash_context::update_crash_context.system())
+        .add_system(analytics::record_analytics.system())
+        .add_system(analytics::flush_analytics.system())
+        .add_system(
+            engine_config::apply_engine_config
+                .system()
+                .label(ApplyEngineConfig),
+        )
+        .add_system(auto_pause::auto_pause.system())
+        .add_system(attract_mode::save_replay.system())
+        .add_system(gameplay::pause_heartbeat.system())
+        .add_system(ui_utils::update_typewriters.system())
+        .add_system(ui_utils::update_text_variables.system())
+        .add_system(ui_utils::update_ui_layout.system())
+        .add_system(ui_utils::update_ui_theme_variant.system())
+        .add_system(rumble::play_rumble.system())
+        .add_system(captions::update_captions.system())
+        .add_system(transition::update_screen_fade.system())
+        .add_system_to_stage(
+            CoreStage::PreUpdate,
+            map_loading::hot_reload_game_maps.system(),
+        )
+        .add_system(map_loading::despawn_stale_map_entities.system())
+        .add_system(map_loading::restore_map_reload_focus.system())
+        .add_system(map_loading::load_ldtk_external_levels.system())
+        .add_system(map_loading::build_ldtk_game_maps.system())
+        .add_system(map_loading::build_tiled_game_maps.system())
+        .add_system(map_loading::tag_ldtk_map_layers.system())
+        .add_system(map_loading::position_instanced_ldtk_layers.system())
         .add_system(map_loading::spawn_map_collisions.system())
-        .add_system(map_loading::hot_reload_map_collisions.system())
+        .add_system(dungeon::generate_dungeons.system())
         .add_system(map_loading::spawn_map_entrances.system())
-        .add_system(map_loading::hot_reload_map_entrances.system())
         .add_system(map_loading::spawn_map_enemies.system())
-        .add_system(map_loading::hot_reload_map_enemies.system())
+        .add_system(map_loading::spawn_map_props.system())
+        .add_system(map_loading::spawn_map_plots.system())
+        .add_system(level_state::apply_level_states.system())
+        .add_system(map_loading::spawn_map_shader_regions.system())
+        .add_system(movement::load_terrain_speeds.system())
+        .add_system(reflections::load_reflective_tiles.system())
+        .add_system(footprints::load_footprint_tiles.system())
+        .add_system(
+            sprite_batching::merge_static_layers
+                .system()
+                .before(ApplyPalettes),
+        )
+        .add_system(sprite_batching::audit_level_sprites.system())
+        .add_system(sprite_atlas::pack_sprite_atlases.system())
+        .add_system(sprite_atlas::unpack_changed_sprites.system())
+        .add_system(sprite_atlas::reload_atlas_sprites.system())
+        .add_system(palette::apply_level_palettes.system().label(ApplyPalettes))
+        .add_system(palette::apply_sprite_palettes.system())
+        .add_system(
+            animated_tiles::animate_map_tiles
+                .system()
+                .after(ApplyPalettes),
+        )
+        .add_system(roofs::fade_roofs.system().after(ApplyPalettes))
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            update_cursor_world_position
+                .system()
+                .label(UpdateCursorPosition)
+                .after(CameraFollow),
+        )
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            cursor::update_cursor
+                .system()
+                .after(UpdateCursorPosition)
+                .before(TransformSystem::TransformPropagate),
+        )
         .add_system_to_stage(
             CoreStage::PostUpdate,
             map_loading::generate_map_navigation_mesh
@@ -73,10 +369,27 @@ pub fn add_systems(app: &mut AppBuilder) {
         .add_system_set(
             SystemSet::on_update(GameState::Init).with_system(game_init::await_init.system()),
         )
+        // Game intro state
+        .add_system_set(
+            SystemSet::on_update(GameState::Intro).with_system(game_init::play_intro.system()),
+        )
         // Game start menu state
         .add_system_set(
             SystemSet::on_update(GameState::StartMenu)
-                .with_system(game_init::setup_start_menu.system()),
+                .with_system(game_init::setup_start_menu.system())
+                .with_system(attract_mode::animate_splash_background.system())
+                .with_system(attract_mode::start_attract_mode.system())
+                // Animate the NPCs walking around the background level
+                .with_system(
+                    path_followers::follow_paths
+                        .system()
+                        .before(UpdateSpriteAnimations),
+                )
+                .with_system(
+                    sprite_animation::update_sprite_animations
+                        .system()
+                        .label(UpdateSpriteAnimations),
+                ),
         )
         // Loading main game state
         .add_system_set(
@@ -89,7 +402,7 @@ pub fn add_systems(app: &mut AppBuilder) {
             SystemSet::on_update(GameState::Playing)
                 .with_system(spawn_hud.system())
                 .with_system(finish_spawning_character.system().label(FinishSpawn))
-                .with_system(check_for_game_over.system().before(ControlCharacter))
+                .with_system(check_for_game_over.system())
                 .with_system(touch_control_input.system().label(Input).after(FinishSpawn))
                 .with_system(
                     keyboard_control_input
@@ -97,39 +410,210 @@ pub fn add_systems(app: &mut AppBuilder) {
                         .label(Input)
                         .after(FinishSpawn),
                 )
+                .with_system(mouse_control_input.system().label(Input).after(FinishSpawn))
+                .with_system(
+                    attract_mode::replay_control_input
+                        .system()
+                        .label(Input)
+                        .after(FinishSpawn),
+                )
+                .with_system(attract_mode::end_attract_mode.system())
+                .with_system(attract_mode::record_replay.system().after(Input))
+                .with_system(
+                    fixed_step::collect_held_controls
+                        .system()
+                        .label(CollectControls)
+                        .after(Input),
+                )
+                .with_system(animate_sprites.system().label(AnimateSprites))
+                .with_system(
+                    sprite_animation::update_sprite_animations
+                        .system()
+                        .label(UpdateSpriteAnimations)
+                        .after(AnimateSprites),
+                )
+                .with_system(play_footstep_sounds.system().after(UpdateSpriteAnimations))
+                .with_system(
+                    resume_character_animations
+                        .system()
+                        .after(UpdateSpriteAnimations),
+                )
+                .with_system(equipment::update_equipment.system().after(FinishSpawn))
+                .with_system(
+                    equipment::animate_equipment_layers
+                        .system()
+                        .after(UpdateSpriteAnimations),
+                )
+                .with_system(shadows::add_shadows.system())
+                .with_system(footprints::leave_footprints.system())
+                .with_system(critters::spawn_critters.system())
+                .with_system(critters::move_critters.system())
+                .with_system(
+                    path_followers::follow_paths
+                        .system()
+                        .before(UpdateSpriteAnimations),
+                )
+                .with_system(emotes::show_emotes.system())
+                .with_system(emotes::emote_alert_states.system())
+                .with_system(emotes::emote_from_props.system())
+                .with_system(cutscenes::start_cutscenes.system())
+                .with_system(cutscenes::play_cutscenes.system())
+                .with_system(results::track_level_stats.system())
+                .with_system(results::update_level_results.system())
+                .with_system(scoring::score_points.system())
+                .with_system(scoring::record_best_times.system())
+                .with_system(
+                    reflections::update_reflections
+                        .system()
+                        .after(UpdateSpriteAnimations),
+                )
+                .with_system(equipment::equip_items_from_props.system())
+                .with_system(crafting::collect_items_from_props.system())
+                .with_system(crafting::use_crafting_stations.system())
+                .with_system(gathering::start_gathering.system())
+                .with_system(gathering::update_gathering.system().after(Input))
+                .with_system(farming::tend_plots.system())
+                .with_system(farming::update_plot_crops.system())
+                .with_system(sleep::use_beds.system())
+                .with_system(sleep::finish_sleeping.system())
+                .with_system(sleep::respawn_enemies.system())
+                .with_system(npcs::talk_to_npcs.system())
+                .with_system(signs::read_signs.system())
+                .with_system(level_state::set_flags_from_props.system())
+                .with_system(dungeon::reward_dungeon_rooms.system())
+                .with_system(dungeon::collect_currency_from_props.system())
+                .with_system(dungeon::use_unlock_stations.system())
+                .with_system(dungeon::return_to_hub.system())
+                .with_system(battle::start_battles.system())
+                .with_system(stealth::emit_player_noise.system())
+                .with_system(stealth::update_enemy_alertness.system())
+                .with_system(stealth::draw_vision_cones.system())
+                .with_system(movement::update_terrain_speeds.system())
+                .with_system(bombs::explode_bombs.system())
+                .with_system(secret_walls::break_secret_walls.system())
+                .with_system(enemy_culling::cull_distant_enemies.system())
+                .with_system(gameplay::interact_with_props.system())
+                .with_system(lifting::lift_props.system())
+                .with_system(lifting::carry_and_throw_props.system())
+                .with_system(change_level.system())
+                .with_system(free_camera::toggle_free_camera.system())
+                .with_system(game_speed::collect_slow_motion_pickups.system())
+                .with_system(game_speed::update_game_speed.system())
+                .with_system(update_health_feedback.system()),
+        )
+        // Fixed timestep gameplay simulation
+        .add_system_set_to_stage(
+            GameStage::FixedUpdate,
+            SystemSet::new()
+                .with_run_criteria(playing.system())
+                .with_system(
+                    spatial_hash::update_enemy_spatial_hash
+                        .system()
+                        .label(UpdateSpatialHash),
+                )
                 .with_system(
                     control_character
                         .system()
                         .label(ControlCharacter)
-                        .after(Input),
+                        .label(Simulate),
+                )
+                .with_system(
+                    enemy_follow_player
+                        .system()
+                        .label(MoveEnemies)
+                        .label(Simulate)
+                        .after(ControlCharacter)
+                        .after(UpdateSpatialHash),
+                )
+                .with_system(
+                    abilities::use_abilities
+                        .system()
+                        .label(UseAbilities)
+                        .label(Simulate)
+                        .after(ControlCharacter),
+                )
+                // Dashes override the movement from the character controls and enemy AI
+                .with_system(
+                    abilities::apply_dashes
+                        .system()
+                        .label(Simulate)
+                        .after(UseAbilities)
+                        .after(MoveEnemies),
                 )
-                .with_system(animate_sprites.system().after(ControlCharacter))
-                .with_system(enemy_follow_player.system().after(ControlCharacter))
-                .with_system(change_level.system().after(ControlCharacter)),
+                .with_system(
+                    abilities::apply_grapples
+                        .system()
+                        .label(Simulate)
+                        .after(UseAbilities)
+                        .after(MoveEnemies),
+                )
+                .with_system(
+                    abilities::move_projectiles
+                        .system()
+                        .label(Simulate)
+                        .after(UseAbilities)
+                        .after(UpdateSpatialHash),
+                )
+                .with_system(abilities::kill_enemies.system().after(Simulate))
+                .with_system(clock::advance_game_clock.system())
+                .with_system(palette::set_clock_palette.system())
+                .with_system(
+                    fixed_step::record_fixed_step_transforms
+                        .system()
+                        .after(Simulate),
+                )
+                .with_system(fixed_step::finish_held_controls.system().after(Simulate)),
         )
         .add_system_set_to_stage(
             CoreStage::PostUpdate,
             SystemSet::new()
-                .with_run_criteria(
-                    (|state: Res<State<GameState>>| {
-                        if state.current() == &GameState::Playing {
-                            ShouldRun::Yes
-                        } else {
-                            ShouldRun::No
-                        }
-                    })
-                    .system(),
-                )
+                .with_run_criteria(playing.system())
                 .with_system(
                     camera_follow_system
                         .system()
+                        .label(CameraFollow)
                         .before(TransformSystem::TransformPropagate)
-                        .after(PhysicsSystem::TransformUpdate),
+                        .after(SmoothPhysics),
                 )
                 .with_system(
-                    damage_character
+                    cutscenes::move_cutscene_camera
                         .system()
-                        .after(PhysicsSystem::TransformUpdate),
+                        .after(CameraFollow)
+                        .before(MoveFreeCamera),
+                )
+                .with_system(
+                    free_camera::move_free_camera
+                        .system()
+                        .label(MoveFreeCamera)
+                        .after(CameraFollow)
+                        .before(TransformSystem::TransformPropagate),
+                )
+                .with_system(
+                    gameplay::apply_camera_shake
+                        .system()
+                        .after(MoveFreeCamera)
+                        .before(TransformSystem::TransformPropagate),
+                )
+                .with_system(
+                    y_sort::y_sort
+                        .system()
+                        .after(PhysicsSystem::TransformUpdate)
+                        .before(TransformSystem::TransformPropagate),
+                )
+                // Damage is dealt by collision events, which only last for a couple of frames, so
+                // it is checked every frame instead of in the fixed update stage, which may not
+                // run every frame
+                .with_system(damage_character.system().after(RouteCollisions))
+                .with_system(
+                    post_processing::update_level_shader
+                        .system()
+                        .label(UpdateLevelShader)
+                        .after(RouteCollisions),
+                )
+                .with_system(
+                    post_processing::blend_post_process_shader
+                        .system()
+                        .after(UpdateLevelShader),
                 ),
         )
         // Pause menu state
@@ -137,12 +621,58 @@ pub fn add_systems(app: &mut AppBuilder) {
             SystemSet::on_update(GameState::Paused)
                 .with_system(pause_menu::handle_pause_menu.system()),
         )
+        // Photo mode state
+        .add_system_set(
+            SystemSet::on_enter(GameState::PhotoMode)
+                .with_system(photo_mode::enter_photo_mode.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::PhotoMode)
+                .with_system(photo_mode::update_photo_mode.system()),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::PhotoMode)
+                .with_system(photo_mode::exit_photo_mode.system()),
+        )
+        // Battle state
+        .add_system_set(
+            SystemSet::on_enter(GameState::Battle).with_system(battle::show_battle_screen.system()),
+        )
         // Game over menu state
+        .add_system_set(
+            SystemSet::on_enter(GameState::GameOver)
+                .with_system(scoring::record_high_score.system()),
+        )
         .add_system_set_to_stage(
             CoreStage::Update,
             SystemSet::on_update(GameState::GameOver)
                 .with_system(game_over::run_game_over_screen.system()),
         );
+
+    // Write crash reports next to the save data on desktop
+    #[cfg(not(wasm))]
+    app.add_system(crash_context::set_crash_report_dir.system());
+
+    // Open the map in the level editor while debugging on desktop
+    #[cfg(not(wasm))]
+    app.add_system(ldtk_editor::open_map_in_ldtk.system());
+
+    // Let the page hosting the game control it on web
+    #[cfg(wasm)]
+    app.init_resource::<web_api::PendingLevelLoad>()
+        .add_system(web_api::handle_web_commands.exclusive_system())
+        .add_system(web_api::send_web_game_events.system())
+        .add_system(web_api::send_web_leaderboard_events.system());
+}
+
+/// Run criteria that runs a system set only while the game is [`GameState::Playing`], for stages
+/// that don't drive the game state
+fn playing(state: Res<State<GameState>>) -> ShouldRun {
+    if state.current() == &GameState::Playing {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
 }
 
 fn switch_fullscreen(mut windows: ResMut<Windows>, keyboard_input: Res<Input<KeyCode>>) {
@@ -156,87 +686,4 @@ fn switch_fullscreen(mut windows: ResMut<Windows>, keyboard_input: Res<Input<Key
     }
 }
 
-mod ui_utils {
-    use crate::plugins::game::assets::GameInfo;
-    use bevy_retrograde::ui::raui::prelude::*;
-
-    pub fn get_ui_theme(game_info: &GameInfo) -> ThemeProps {
-        let mut theme = ThemeProps::default();
-
-        theme.content_backgrounds.insert(
-            String::from("panel"),
-            ThemedImageMaterial::Image(ImageBoxImage {
-                id: game_info.ui_theme.panel.image.clone(),
-                scaling: ImageBoxImageScaling::Frame(
-                    (
-                        game_info.ui_theme.panel.border_size as f32,
-                        game_info.ui_theme.panel.only_frame,
-                    )
-                        .into(),
-                ),
-                ..Default::default()
-            }),
-        );
-
-        theme.content_backgrounds.insert(
-            String::from("button-up"),
-            ThemedImageMaterial::Image(ImageBoxImage {
-                id: game_info.ui_theme.button_up.image.clone(),
-                scaling: ImageBoxImageScaling::Frame(
-                    (
-                        game_info.ui_theme.button_up.border_size as f32,
-                        game_info.ui_theme.button_up.only_frame,
-                    )
-                        .into(),
-                ),
-                ..Default::default()
-            }),
-        );
-
-        theme.content_backgrounds.insert(
-            String::from("button-down"),
-            ThemedImageMaterial::Image(ImageBoxImage {
-                id: game_info.ui_theme.button_down.image.clone(),
-                scaling: ImageBoxImageScaling::Frame(
-                    (
-                        game_info.ui_theme.button_down.border_size as f32,
-                        game_info.ui_theme.button_down.only_frame,
-                    )
-                        .into(),
-                ),
-                ..Default::default()
-            }),
-        );
-
-        theme.switch_variants.insert(
-            "checkbox".to_owned(),
-            ThemedSwitchMaterial {
-                on: ThemedImageMaterial::Image(ImageBoxImage {
-                    id: game_info.ui_theme.checkbox.checked.clone(),
-                    ..Default::default()
-                }),
-                off: ThemedImageMaterial::Image(ImageBoxImage {
-                    id: game_info.ui_theme.checkbox.unchecked.clone(),
-                    ..Default::default()
-                }),
-            },
-        );
-
-        theme.text_variants.insert(
-            String::new(),
-            ThemedTextMaterial {
-                font: TextBoxFont {
-                    name: game_info.ui_theme.default_font.clone(),
-                    // Font's in Bevy Retrograde don't really have sizes so we can just set this to
-                    // one
-                    size: 1.0,
-                },
-                ..Default::default()
-            },
-        );
-
-        theme.icons_level_sizes = vec![8., 12., 16.];
-
-        theme
-    }
-}
+mod ui_utils;
@@ -0,0 +1,698 @@
+use std::time::Duration;
+
+use bevy_retrograde::physics::heron::rapier_plugin::{PhysicsWorld, ShapeCastCollisionType};
+
+use super::{
+    bombs::Bomb,
+    fixed_step::HeldControls,
+    game_time::GameTime,
+    gameplay::{direction_clip_suffix, Health},
+    lifting::Carrying,
+    map_loading::{spawn_enemy, DEFAULT_ENEMY_HEALTH},
+    spatial_hash::EnemySpatialHash,
+    *,
+};
+
+/// How far from the user summoned enemies are spawned, in pixels
+const SUMMON_DISTANCE: f32 = 16.;
+
+/// The identifier of the map entities that grappling hooks can catch on
+const HOOK_POINT_ENTITY: &str = "HookPoint";
+
+/// How far to the side of the user's aim a hook point can be and still be caught, in degrees
+const HOOK_POINT_AIM_ANGLE: f32 = 30.;
+
+/// How far before the hook the user stops being pulled, so they don't end up inside a wall, in
+/// pixels
+const GRAPPLE_STOP_DISTANCE: f32 = 8.;
+
+/// Component for an entity that can use abilities, with the abilities in each of its slots
+pub struct Abilities {
+    pub slots: Vec<AbilitySlot>,
+}
+
+impl Abilities {
+    /// Put the given abilities in slots, ready to be used
+    pub fn new(abilities: impl IntoIterator<Item = Handle<Ability>>) -> Self {
+        Self {
+            slots: abilities
+                .into_iter()
+                .map(|ability| AbilitySlot {
+                    ability,
+                    cooldown: 0.,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// An ability that an entity can use
+pub struct AbilitySlot {
+    pub ability: Handle<Ability>,
+    /// The time left before the ability can be used again, in seconds
+    pub cooldown: f32,
+}
+
+/// The energy the player uses up when using abilities
+pub struct Energy {
+    pub current: f32,
+    pub max: f32,
+    /// The energy regained per second
+    pub regen: f32,
+}
+
+/// Something that an ability shot, or an area that it hit, which damages the first thing it
+/// touches
+///
+/// Projectiles stop at walls, but they fly over props and other obstacles that aren't part of the
+/// map collision. They are put back in their [`EntityPool`] when they hit something or run out of
+/// distance.
+pub struct Projectile {
+    /// The distance the projectile moves per second
+    pub velocity: Vec3,
+    /// How much further the projectile can go before disappearing
+    pub remaining_distance: f32,
+    /// How close the projectile has to be to something to hit it
+    pub radius: f32,
+    pub damage: u32,
+    /// Whether the projectile was shot by an enemy, in which case it hits the player, or by the
+    /// player, in which case it hits enemies
+    pub from_enemy: bool,
+    /// Whether the projectile is an area that hits everything in its radius instead of the first
+    /// thing it touches
+    pub area: bool,
+}
+
+/// Component that moves an entity quickly in one direction, added by a dash ability
+pub struct Dash {
+    pub velocity: Vec3,
+    pub timer: Timer,
+}
+
+/// Component that pulls an entity to where its grappling hook caught, overriding its normal
+/// movement, added by a grapple ability
+pub struct Grapple {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub timer: Timer,
+    /// The entity that the rope is drawn with
+    pub rope: Entity,
+}
+
+/// Get the direction that a character is facing, which is down if it has no state
+pub fn facing_direction(state: Option<&CharacterState>) -> Vec3 {
+    match state.map(|x| x.direction) {
+        Some(CharacterStateDirection::Up) => Vec3::new(0., -1., 0.),
+        Some(CharacterStateDirection::Left) => Vec3::new(-1., 0., 0.),
+        Some(CharacterStateDirection::Right) => Vec3::new(1., 0., 0.),
+        Some(CharacterStateDirection::Down) | None => Vec3::new(0., 1., 0.),
+    }
+}
+
+/// Use the abilities that the player is holding the controls for, and have enemies use their
+/// abilities when the player is in range
+#[allow(clippy::too_many_arguments)]
+pub fn use_abilities(
+    mut commands: Commands,
+    mut casters: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Abilities,
+            Option<&mut Energy>,
+            Option<&mut Health>,
+            Option<&CharacterState>,
+            Option<&Enemy>,
+            Option<&YSort>,
+            Option<&Stats>,
+            Option<&Alertness>,
+            Option<&Grapple>,
+            Option<&Pack>,
+            Option<&mut SpriteAnimation>,
+            Option<&Carrying>,
+        ),
+        Without<Dormant>,
+    >,
+    characters: Query<&Transform, With<Handle<Character>>>,
+    maps: Query<&GameMap>,
+    mut projectile_pool: ResMut<EntityPool<Projectile>>,
+    held_controls: Res<HeldControls>,
+    ability_assets: Res<Assets<Ability>>,
+    item_assets: Res<Assets<Item>>,
+    animation_assets: Res<Assets<SpriteAnimations>>,
+    mut inventory: ResMut<Inventory>,
+    asset_server: Res<AssetServer>,
+    game_info: Res<GameInfo>,
+    current_level: Option<Res<CurrentLevel>>,
+    physics_world: PhysicsWorld,
+    game_time: Res<GameTime>,
+) {
+    let delta = game_time.fixed_delta().as_secs_f32();
+    let character_pos = characters.single().ok().map(|x| x.translation);
+
+    for (
+        caster_ent,
+        transform,
+        mut abilities,
+        mut energy,
+        mut health,
+        state,
+        enemy,
+        y_sort,
+        stats,
+        alertness,
+        grapple,
+        pack,
+        mut sprite_animation,
+        carrying,
+    ) in casters.iter_mut()
+    {
+        let attack = stats.map(|x| x.attack).unwrap_or(0);
+
+        // Cool down the abilities and regain energy
+        for slot in &mut abilities.slots {
+            slot.cooldown = (slot.cooldown - delta).max(0.);
+        }
+        if let Some(energy) = &mut energy {
+            energy.current = (energy.current + energy.regen * delta).min(energy.max);
+        }
+
+        // Get the slots to use, and the direction to use them in
+        let (slots, direction): (Vec<usize>, Vec3) = if let Some(enemy) = enemy {
+            // Enemies only use abilities in the current level, when the player is in range
+            let character_pos = match (&current_level, character_pos) {
+                (Some(level), Some(pos)) if level.0 == enemy.level => pos,
+                _ => continue,
+            };
+            // Enemies with vision don't attack until they have noticed the player
+            if alertness.map_or(false, |x| x.state != AlertState::Alert) {
+                continue;
+            }
+            let to_character = (character_pos - transform.translation).truncate();
+            let distance = to_character.length();
+            let slots = abilities
+                .slots
+                .iter()
+                .enumerate()
+                .filter(|(_, slot)| {
+                    ability_assets
+                        .get(&slot.ability)
+                        .map(|x| distance <= x.ai_range)
+                        .unwrap_or(false)
+                })
+                .map(|(i, _)| i)
+                .collect();
+
+            (slots, to_character.normalize_or_zero().extend(0.))
+        } else {
+            let slots = held_controls
+                .controls
+                .iter()
+                .filter_map(|control| match control {
+                    ControlEvent::Ability(slot) => Some(*slot as usize),
+                    // Attacking uses the first ability, unless it throws what the player carries
+                    ControlEvent::Attack if carrying.is_none() => Some(0),
+                    _ => None,
+                })
+                .collect();
+            (slots, facing_direction(state))
+        };
+
+        for slot_index in slots {
+            let slot = if let Some(slot) = abilities.slots.get_mut(slot_index) {
+                slot
+            } else {
+                continue;
+            };
+            let ability = if let Some(ability) = ability_assets.get(&slot.ability) {
+                ability
+            } else {
+                continue;
+            };
+            if slot.cooldown > 0. {
+                continue;
+            }
+
+            // The player can only place the bombs they are carrying
+            let missing_bomb = ability.effects.iter().any(|effect| {
+                matches!(effect, AbilityEffect::Bomb { item } if inventory.count(item) == 0)
+            });
+            if enemy.is_none() && missing_bomb {
+                continue;
+            }
+
+            // Pay for the ability, if the caster uses energy
+            if let Some(energy) = &mut energy {
+                if energy.current < ability.cost as f32 {
+                    continue;
+                }
+                energy.current -= ability.cost as f32;
+            }
+            slot.cooldown = ability.cooldown;
+
+            // Play the ability's animation, if the user has the clip for it
+            if let (Some(clip), Some(sprite_animation), Some(state)) =
+                (&ability.animation, &mut sprite_animation, state)
+            {
+                let clip = format!("{}_{}", clip, direction_clip_suffix(state.direction));
+                let has_clip = animation_assets
+                    .get(&sprite_animation.animations)
+                    .map_or(false, |x| x.clips.contains_key(&clip));
+                if has_clip {
+                    sprite_animation.play_once(&clip);
+                }
+            }
+
+            for effect in &ability.effects {
+                match effect {
+                    AbilityEffect::Projectile {
+                        speed,
+                        damage,
+                        range,
+                        radius,
+                        sprite,
+                    } => {
+                        let projectile = projectile_pool.take(&mut commands);
+                        commands
+                            .entity(projectile)
+                            .insert_bundle(SpriteBundle {
+                                image: asset_server.load(sprite.as_str()),
+                                transform: Transform::from_translation(transform.translation),
+                                ..Default::default()
+                            })
+                            .remove::<Handle<SpriteSheet>>()
+                            .insert(Projectile {
+                                velocity: direction * *speed,
+                                remaining_distance: *range,
+                                radius: *radius,
+                                damage: *damage + attack,
+                                from_enemy: enemy.is_some(),
+                                area: false,
+                            })
+                            .insert(y_sort.copied().unwrap_or_default())
+                            .insert(FixedStepInterpolation::new(transform.translation));
+                    }
+                    AbilityEffect::Area { radius, damage } => {
+                        // Areas aren't drawn, so re-used projectiles are left hidden
+                        let projectile = projectile_pool.take(&mut commands);
+                        commands
+                            .entity(projectile)
+                            .insert(Transform::from_translation(transform.translation))
+                            .insert(Projectile {
+                                velocity: Vec3::ZERO,
+                                remaining_distance: 0.,
+                                radius: *radius,
+                                damage: *damage + attack,
+                                from_enemy: enemy.is_some(),
+                                area: true,
+                            })
+                            .remove::<FixedStepInterpolation>();
+                    }
+                    AbilityEffect::Bomb { item: path } => {
+                        let item_handle: Handle<Item> = asset_server.load_cached(path.as_str());
+                        let (item, bomb) = if let Some((item, Some(bomb))) =
+                            item_assets.get(item_handle).map(|x| (x, &x.bomb))
+                        {
+                            (item, bomb)
+                        } else {
+                            warn!(item=%path, "Bomb item isn't loaded or has no `bomb` settings");
+                            continue;
+                        };
+                        if enemy.is_none() {
+                            inventory.remove(path, 1);
+                        }
+
+                        let sprite = bomb.sprite.as_ref().or_else(|| item.icon.as_ref());
+                        commands
+                            .spawn_bundle(SpriteBundle {
+                                image: sprite
+                                    .map(|x| asset_server.load(x.as_str()))
+                                    .unwrap_or_default(),
+                                transform: Transform::from_translation(transform.translation),
+                                ..Default::default()
+                            })
+                            .insert(Bomb::new(bomb.clone()))
+                            .insert(y_sort.copied().unwrap_or_default());
+                    }
+                    AbilityEffect::Dash { speed, duration } => {
+                        commands.entity(caster_ent).insert(Dash {
+                            velocity: direction * *speed,
+                            timer: Timer::new(Duration::from_secs_f32(*duration), false),
+                        });
+                    }
+                    AbilityEffect::Grapple { range, speed } => {
+                        // Only one hook can be out at a time
+                        if grapple.is_some() {
+                            continue;
+                        }
+                        let start = transform.translation;
+                        let hook_points = match (maps.single(), &current_level) {
+                            (Ok(map), Some(level)) => hook_points(map, &level.0),
+                            _ => Vec::new(),
+                        };
+                        let hook = if let Some(hook) = find_grapple_hook(
+                            &physics_world,
+                            &hook_points,
+                            start.truncate(),
+                            direction.truncate(),
+                            *range,
+                        ) {
+                            hook
+                        } else {
+                            continue;
+                        };
+
+                        let to_hook = hook - start.truncate();
+                        let pull = (to_hook.length() - GRAPPLE_STOP_DISTANCE).max(0.);
+                        let end = start + (to_hook.normalize_or_zero() * pull).extend(0.);
+                        let rope = commands
+                            .spawn_bundle(ShapeBundle {
+                                shape: rope_shape(start, hook),
+                                transform: Transform::from_xyz(0., 0., start.z + 0.1),
+                                ..Default::default()
+                            })
+                            .id();
+                        commands.entity(caster_ent).insert(Grapple {
+                            start,
+                            end,
+                            timer: Timer::new(
+                                Duration::from_secs_f32((pull / speed).max(f32::EPSILON)),
+                                false,
+                            ),
+                            rope,
+                        });
+                    }
+                    AbilityEffect::Heal { amount } => {
+                        if let Some(health) = &mut health {
+                            health.current = (health.current + amount).min(health.max);
+                        }
+                    }
+                    AbilityEffect::Summon { count, animations } => {
+                        let enemy = if let Some(enemy) = enemy {
+                            enemy
+                        } else {
+                            warn!(ability=%ability.name, "Only enemies can summon");
+                            continue;
+                        };
+
+                        // The summoned enemies join the caster's pack, starting a new one led by
+                        // the caster if it isn't in one
+                        let pack = pack.cloned().unwrap_or_else(|| {
+                            let pack = Pack {
+                                id: format!(
+                                    "summon/{}v{}",
+                                    caster_ent.id(),
+                                    caster_ent.generation()
+                                ),
+                                order: 0,
+                                offset: Vec2::ZERO,
+                            };
+                            commands.entity(caster_ent).insert(pack.clone());
+                            pack
+                        });
+
+                        // Spread the summoned enemies out in a circle around the caster, in the
+                        // same map layer
+                        let base_z = y_sort.map(|x| x.base_z).unwrap_or(transform.translation.z);
+                        for i in 0..*count {
+                            let angle = std::f32::consts::TAU * i as f32 / *count as f32;
+                            let offset = Vec2::new(angle.cos(), angle.sin()) * SUMMON_DISTANCE;
+                            spawn_enemy(
+                                &mut commands,
+                                &asset_server,
+                                enemy.clone(),
+                                (transform.translation.truncate() + offset).extend(base_z),
+                                game_info.collision_layers.collision_layers(&["enemy"]),
+                                game_info.enemies.knock_back.clone(),
+                                animations.as_deref().map(|x| (x, "idle")),
+                            )
+                            .insert(Health {
+                                current: DEFAULT_ENEMY_HEALTH,
+                                max: DEFAULT_ENEMY_HEALTH,
+                            })
+                            .insert(Pack {
+                                id: pack.id.clone(),
+                                order: pack.order + 1 + i,
+                                offset: pack.offset + offset,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Move projectiles and damage whatever they hit
+#[allow(clippy::too_many_arguments)]
+pub fn move_projectiles(
+    mut commands: Commands,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform), Without<Pooled>>,
+    mut targets: Query<(Entity, &Transform, &mut Health, Option<&Enemy>), Without<Projectile>>,
+    enemy_spatial_hash: Res<EnemySpatialHash>,
+    mut projectile_pool: ResMut<EntityPool<Projectile>>,
+    mut damaged_events: EventWriter<CharacterDamagedEvent>,
+    mut wall_impacts: EventWriter<WallImpactEvent>,
+    physics_world: PhysicsWorld,
+    game_time: Res<GameTime>,
+) {
+    let delta = game_time.fixed_delta().as_secs_f32();
+
+    for (projectile_ent, mut projectile, mut transform) in projectiles.iter_mut() {
+        // Projectiles only hit the other side: the player's projectiles hit the enemies near them,
+        // and the enemies' projectiles hit the player
+        let position = transform.translation.truncate();
+        let target_ents = if projectile.from_enemy {
+            targets
+                .iter_mut()
+                .filter(|(.., enemy)| enemy.is_none())
+                .map(|(ent, ..)| ent)
+                .collect::<Vec<_>>()
+        } else if projectile.area {
+            enemy_spatial_hash
+                .within(position, projectile.radius)
+                .map(|(ent, _)| ent)
+                .collect()
+        } else {
+            enemy_spatial_hash
+                .nearest(position, projectile.radius)
+                .map(|(ent, _)| ent)
+                .into_iter()
+                .collect()
+        };
+
+        let mut hit = false;
+        for target_ent in target_ents {
+            let (_, target_transform, mut health, enemy) =
+                if let Ok(target) = targets.get_mut(target_ent) {
+                    target
+                } else {
+                    continue;
+                };
+
+            let distance = (target_transform.translation - transform.translation)
+                .truncate()
+                .length();
+            if distance > projectile.radius {
+                continue;
+            }
+
+            let damage = projectile.damage.min(health.current);
+            health.current -= damage;
+            if enemy.is_none() {
+                damaged_events.send(CharacterDamagedEvent {
+                    character: target_ent,
+                    damage,
+                });
+            }
+
+            hit = true;
+            if !projectile.area {
+                break;
+            }
+        }
+
+        // Move the projectile, stopping it at walls
+        let step = projectile.velocity * delta;
+        let start = transform.translation.truncate().extend(0.);
+        let hit_wall = step.length() > 0.
+            && physics_world
+                .shape_cast_with_filter(
+                    &CollisionShape::Sphere { radius: 1. },
+                    start,
+                    Quat::default(),
+                    step,
+                    CollisionLayers::new(PhysicsGroup::Terrain, PhysicsGroup::Terrain),
+                    |_| true,
+                )
+                .is_some();
+        transform.translation += step;
+        projectile.remaining_distance -= step.length();
+
+        if hit || hit_wall || projectile.remaining_distance <= 0. {
+            // The player's projectiles can break secret walls where they hit a wall or, for areas,
+            // anywhere in their radius
+            if !projectile.from_enemy && (hit_wall || projectile.area) {
+                wall_impacts.send(WallImpactEvent {
+                    position: transform.translation.truncate(),
+                    radius: projectile.radius,
+                    damage: projectile.damage,
+                });
+            }
+
+            projectile_pool.release(&mut commands, projectile_ent);
+        }
+    }
+}
+
+/// Move entities that are dashing, overriding their normal movement
+pub fn apply_dashes(
+    mut commands: Commands,
+    mut dashing: Query<(Entity, &mut Dash, &mut Velocity), Without<Dormant>>,
+    game_time: Res<GameTime>,
+) {
+    for (ent, mut dash, mut velocity) in dashing.iter_mut() {
+        dash.timer.tick(game_time.fixed_delta());
+        if dash.timer.finished() {
+            *velocity = Velocity::default();
+            commands.entity(ent).remove::<Dash>();
+        } else {
+            *velocity = Velocity::from_linear(dash.velocity);
+        }
+    }
+}
+
+/// Pull entities along to where their grappling hook caught, easing in and out, and overriding
+/// their normal movement
+pub fn apply_grapples(
+    mut commands: Commands,
+    mut grappling: Query<(Entity, &mut Grapple, &Transform, &mut Velocity)>,
+    game_time: Res<GameTime>,
+) {
+    let delta = game_time.fixed_delta();
+
+    for (ent, mut grapple, transform, mut velocity) in grappling.iter_mut() {
+        grapple.timer.tick(delta);
+        if grapple.timer.finished() {
+            *velocity = Velocity::default();
+            commands.entity(grapple.rope).despawn();
+            commands.entity(ent).remove::<Grapple>();
+            continue;
+        }
+
+        // Move towards where the tween says we should be at the end of this step, so that walls
+        // still stop the user
+        let t = grapple.timer.percent();
+        let eased = t * t * (3. - 2. * t);
+        let target = grapple.start.lerp(grapple.end, eased);
+        let step = (target - transform.translation).truncate();
+        *velocity = Velocity::from_linear((step / delta.as_secs_f32()).extend(0.));
+
+        let hook = grapple.end.truncate()
+            + (grapple.end - grapple.start).truncate().normalize_or_zero() * GRAPPLE_STOP_DISTANCE;
+        commands
+            .entity(grapple.rope)
+            .insert(rope_shape(transform.translation, hook));
+    }
+}
+
+/// Get the positions of the hook points in a level of the map
+fn hook_points(map: &GameMap, level: &str) -> Vec<Vec2> {
+    map.levels
+        .iter()
+        .filter(|x| x.identifier == level)
+        .flat_map(|level| {
+            level
+                .entities
+                .iter()
+                .filter(|x| x.identifier == HOOK_POINT_ENTITY)
+                .map(move |x| level.place(x, Placement::Position).truncate())
+        })
+        .collect()
+}
+
+/// Find where a grappling hook shot from `start` catches: the closest hook point near its aim that
+/// isn't behind a wall, or otherwise the first wall in its way
+fn find_grapple_hook(
+    physics_world: &PhysicsWorld,
+    hook_points: &[Vec2],
+    start: Vec2,
+    direction: Vec2,
+    range: f32,
+) -> Option<Vec2> {
+    let wall_distance = |to: Vec2| {
+        physics_world
+            .shape_cast_with_filter(
+                &CollisionShape::Sphere { radius: 1. },
+                start.extend(0.),
+                Quat::default(),
+                to.extend(0.),
+                CollisionLayers::new(PhysicsGroup::Terrain, PhysicsGroup::Terrain),
+                |_| true,
+            )
+            .map(|x| match x.collision_type {
+                ShapeCastCollisionType::Collided(info) => {
+                    (info.self_end_position.truncate() - start).length()
+                }
+                ShapeCastCollisionType::AlreadyPenetrating => 0.,
+            })
+    };
+
+    let hook_point = hook_points
+        .iter()
+        .map(|x| *x - start)
+        .filter(|x| {
+            x.length() <= range
+                && direction.angle_between(*x).abs().to_degrees() <= HOOK_POINT_AIM_ANGLE
+        })
+        .filter(|x| wall_distance(*x).map_or(true, |distance| distance >= x.length()))
+        .min_by(|a, b| a.length().partial_cmp(&b.length()).unwrap());
+    if let Some(hook_point) = hook_point {
+        return Some(start + hook_point);
+    }
+
+    let aim = direction.normalize_or_zero() * range;
+    wall_distance(aim).map(|distance| start + direction.normalize_or_zero() * distance)
+}
+
+/// The shape of a grappling hook's rope
+fn rope_shape(from: Vec3, to: Vec2) -> Shape {
+    Shape::line_segment(
+        [epaint::pos2(from.x, from.y), epaint::pos2(to.x, to.y)],
+        (1., epaint::Color32::from_rgb(120, 90, 60)),
+    )
+}
+
+/// Remove enemies that have run out of health, remembering that they were killed if they are
+/// from the map
+pub fn kill_enemies(
+    mut commands: Commands,
+    enemies: Query<
+        (
+            Entity,
+            &Health,
+            &Transform,
+            Option<&PersistentId>,
+            Option<&Respawns>,
+        ),
+        (With<Enemy>, Changed<Health>),
+    >,
+    mut level_states: ResMut<LevelStates>,
+    mut defeated_events: EventWriter<EnemyDefeatedEvent>,
+) {
+    for (ent, health, transform, persistent_id, respawns) in enemies.iter() {
+        if health.current > 0 {
+            continue;
+        }
+        defeated_events.send(EnemyDefeatedEvent {
+            position: transform.translation.truncate(),
+        });
+
+        if let Some(id) = persistent_id {
+            let state = level_states.entity_mut(&id.level, &id.id);
+            state.removed = true;
+            state.respawns = respawns.is_some();
+        }
+        commands.entity(ent).despawn_recursive();
+    }
+}
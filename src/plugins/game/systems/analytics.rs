@@ -0,0 +1,301 @@
+use bevy::{app::AppExit, tasks::IoTaskPool};
+use serde::Serialize;
+
+use crate::{BuildInfo, Storage};
+
+#[cfg(feature = "online-analytics")]
+use super::super::http;
+use super::{attract_mode::AttractMode, gameplay::Health, *};
+
+/// The storage key of the list of sessions kept by the `file` sink, oldest first
+const STORED_SESSIONS_KEY: &str = "analytics-sessions.json";
+
+/// Resource for the gameplay analytics recorded in this play session
+#[derive(Default)]
+pub struct Analytics {
+    /// The ID of the session, from the time it started
+    session: String,
+    /// How long the session has been running, in seconds
+    elapsed: f32,
+    /// How long the player has spent playing, not counting menus and pauses, in seconds
+    play_time: f32,
+    events: Vec<AnalyticsEvent>,
+    /// Whether there are events that haven't been flushed yet
+    unflushed: bool,
+    /// How long it has been since the session was last flushed, in seconds
+    since_flush: f32,
+}
+
+impl Analytics {
+    fn record(&mut self, kind: AnalyticsEventKind) {
+        debug!(?kind, "Analytics event");
+        self.events.push(AnalyticsEvent {
+            time: self.elapsed,
+            kind,
+        });
+        self.unflushed = true;
+    }
+}
+
+/// A gameplay event recorded for analytics
+#[derive(Serialize, Debug, Clone)]
+struct AnalyticsEvent {
+    /// When the event happened, in seconds since the session started
+    time: f32,
+    #[serde(flatten)]
+    kind: AnalyticsEventKind,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+enum AnalyticsEventKind {
+    /// The player entered a level
+    LevelStart { level: String },
+    /// The player completed a level through one of its `goal` entrances, in `time` seconds
+    LevelComplete { level: String, time: f32 },
+    /// The player died at a position relative to the top-left of the level
+    Death { level: String, x: i32, y: i32 },
+    /// The player closed the game
+    Quit { level: Option<String> },
+}
+
+/// A play session, as flushed to the analytics sink
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct AnalyticsSession<'a> {
+    session: &'a str,
+    game: &'a str,
+    engine_build: String,
+    play_time: f32,
+    events: &'a [AnalyticsEvent],
+}
+
+/// Get the analytics settings if the game records analytics and the player has agreed to share them
+fn analytics_config<'a>(
+    game_info: &'a Option<Res<GameInfo>>,
+    settings: &UserSettings,
+) -> Option<&'a AnalyticsConfig> {
+    if !settings.share_analytics {
+        return None;
+    }
+    game_info.as_ref().and_then(|x| x.analytics.as_ref())
+}
+
+/// Record level starts and completions, deaths, and play time
+#[allow(clippy::too_many_arguments)]
+pub fn record_analytics(
+    mut last_level: Local<Option<String>>,
+    mut was_dead: Local<bool>,
+    mut analytics: ResMut<Analytics>,
+    mut completed_events: EventReader<LevelCompletedEvent>,
+    characters: Query<(&Health, &Transform), With<Handle<Character>>>,
+    maps: Query<&GameMap>,
+    state: Res<State<GameState>>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+    settings: Res<UserSettings>,
+    attract_mode: Res<AttractMode>,
+    time: Res<Time>,
+) {
+    // The attract mode demo isn't a real player
+    if analytics_config(&game_info, &settings).is_none() || attract_mode.active {
+        return;
+    }
+    let analytics = &mut *analytics;
+    if analytics.session.is_empty() {
+        analytics.session = session_id();
+    }
+    analytics.elapsed += time.delta_seconds();
+
+    // Record the level again when the game is started over
+    let playing = state.current() == &GameState::Playing;
+    if state.current() == &GameState::StartMenu {
+        *last_level = None;
+    }
+    if !playing {
+        return;
+    }
+    analytics.play_time += time.delta_seconds();
+
+    let level = if let Some(level) = current_level {
+        level
+    } else {
+        return;
+    };
+    if last_level.as_deref() != Some(level.as_str()) {
+        *last_level = Some(level.0.clone());
+        analytics.record(AnalyticsEventKind::LevelStart {
+            level: level.0.clone(),
+        });
+    }
+
+    for event in completed_events.iter() {
+        analytics.record(AnalyticsEventKind::LevelComplete {
+            level: event.stats.level.clone(),
+            time: event.stats.time,
+        });
+    }
+
+    let (health, transform) = if let Ok(character) = characters.single() {
+        character
+    } else {
+        return;
+    };
+    let dead = health.current == 0;
+    if dead && !*was_dead {
+        let level_position = maps
+            .single()
+            .ok()
+            .and_then(|x| x.level(&level.0))
+            .map(|x| x.position)
+            .unwrap_or_default();
+        let position = transform.translation.truncate() - level_position;
+        analytics.record(AnalyticsEventKind::Death {
+            level: level.0.clone(),
+            x: position.x.round() as i32,
+            y: position.y.round() as i32,
+        });
+    }
+    *was_dead = dead;
+}
+
+/// Flush the session to the analytics sink every so often, and when the player closes the game
+#[allow(clippy::too_many_arguments)]
+pub fn flush_analytics(
+    mut analytics: ResMut<Analytics>,
+    mut exit_events: EventReader<AppExit>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+    settings: Res<UserSettings>,
+    storage: Option<Res<Storage>>,
+    task_pool: Res<IoTaskPool>,
+    time: Res<Time>,
+) {
+    let config = if let Some(config) = analytics_config(&game_info, &settings) {
+        config
+    } else {
+        return;
+    };
+    if analytics.session.is_empty() {
+        return;
+    }
+
+    let quitting = exit_events.iter().next().is_some();
+    if quitting {
+        analytics.record(AnalyticsEventKind::Quit {
+            level: current_level.map(|x| x.0.clone()),
+        });
+    }
+    analytics.since_flush += time.delta_seconds();
+    if !analytics.unflushed || !(quitting || analytics.since_flush >= config.flush_interval) {
+        return;
+    }
+    analytics.unflushed = false;
+    analytics.since_flush = 0.;
+
+    let session = AnalyticsSession {
+        session: &analytics.session,
+        game: &game_info.as_ref().unwrap().title,
+        engine_build: BuildInfo::get().to_string(),
+        play_time: analytics.play_time,
+        events: &analytics.events,
+    };
+    let data = match serde_json::to_vec(&session) {
+        Ok(data) => data,
+        Err(e) => {
+            warn!(%e, "Could not serialize analytics");
+            return;
+        }
+    };
+    let flush = send_session(
+        config.sink.clone(),
+        config.max_stored_sessions,
+        storage.map(|x| x.clone()),
+        format!("analytics-{}.json", analytics.session),
+        data,
+    );
+
+    // Wait for the last flush before the game closes on desktop, since the background tasks stop
+    // with it
+    #[cfg(not(wasm))]
+    if quitting {
+        task_pool.scope(|scope| scope.spawn(flush));
+        return;
+    }
+    task_pool.spawn(flush).detach();
+}
+
+/// Send a session to the analytics sink
+async fn send_session(
+    sink: AnalyticsSink,
+    max_stored_sessions: usize,
+    storage: Option<Storage>,
+    key: String,
+    data: Vec<u8>,
+) {
+    match sink {
+        AnalyticsSink::File => match storage {
+            Some(storage) => {
+                if let Err(e) = store_session(&storage, &key, data, max_stored_sessions).await {
+                    warn!(%key, %e, "Could not write analytics");
+                }
+            }
+            None => warn!("Could not write analytics before the storage is opened"),
+        },
+        #[cfg(feature = "online-analytics")]
+        AnalyticsSink::Http(url) => {
+            if let Err(e) = http::post(&url, data).await {
+                warn!(%url, %e, "Could not send analytics");
+            }
+        }
+        #[cfg(not(feature = "online-analytics"))]
+        AnalyticsSink::Http(_) => warn!(
+            "The game sends analytics to a URL, but the engine was built without the \
+            `online-analytics` feature"
+        ),
+    }
+}
+
+/// Store a session under a key, removing the oldest stored sessions so that there are no more
+/// than `max_sessions`, which keeps them from filling up local storage on web
+async fn store_session(
+    storage: &Storage,
+    key: &str,
+    data: Vec<u8>,
+    max_sessions: usize,
+) -> anyhow::Result<()> {
+    let mut keys: Vec<String> = match storage.get(STORED_SESSIONS_KEY).await? {
+        Some(keys) => serde_json::from_slice(&keys).unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    if !keys.iter().any(|x| x == key) {
+        keys.push(key.to_owned());
+        let removed = keys.len().saturating_sub(max_sessions.max(1));
+        for old_key in keys.drain(..removed) {
+            storage.delete(&old_key).await?;
+        }
+        storage
+            .put(STORED_SESSIONS_KEY, serde_json::to_vec(&keys)?)
+            .await?;
+    }
+
+    storage.put(key, data).await
+}
+
+/// Get an ID for a new session from the current time
+#[cfg(not(wasm))]
+fn session_id() -> String {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_millis())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Get an ID for a new session from the current time
+#[cfg(wasm)]
+fn session_id() -> String {
+    let performance = web_sys::window().unwrap().performance().unwrap();
+    ((performance.time_origin() + performance.now()) as u64).to_string()
+}
@@ -0,0 +1,207 @@
+use bevy_retrograde::core::image::{GenericImageView, RgbaImage};
+
+use super::*;
+use super::{game_time::GameTime, palette::LayerPalette};
+
+/// Component added to map layers containing the layer's animated tiles
+pub struct LayerAnimatedTiles {
+    tiles: Vec<AnimatedTile>,
+    /// The layer's tileset, without any palette applied, which is kept so that it doesn't have to
+    /// be copied every time a frame changes
+    tileset: Option<RgbaImage>,
+    /// The palette the current frames were drawn with
+    palette: Option<String>,
+}
+
+/// A tile in a map layer that has a tileset `animation`
+struct AnimatedTile {
+    /// The pixel position of the tile in the layer
+    px: (u32, u32),
+    /// The LDtk flip bits for the tile
+    flip: i32,
+    animation: TileAnimation,
+    /// The index of the frame currently drawn in the layer image
+    current_frame: Option<usize>,
+}
+
+/// Cycle the map tiles that have an `animation` in their tileset metadata by drawing the current
+/// frame's tile over them in the rendered layer image
+///
+/// Animated tiles should be the only tile in their cell of the layer, because the frames replace
+/// the layer pixels instead of being drawn over them.
+pub fn animate_map_tiles(
+    mut commands: Commands,
+    mut layers: Query<(
+        Entity,
+        &LdtkMapLayer,
+        &Handle<Image>,
+        Option<&mut LayerAnimatedTiles>,
+        Option<&LayerPalette>,
+    )>,
+    maps: Query<&GameMap>,
+    game_info: Option<Res<GameInfo>>,
+    palette_assets: Res<Assets<Palette>>,
+    asset_server: Res<AssetServer>,
+    mut image_assets: ResMut<Assets<Image>>,
+    game_time: Res<GameTime>,
+    timings: Res<SystemTimings>,
+) {
+    let _timer = timings.start("animate_map_tiles");
+    let map = if let Ok(map) = maps.single() {
+        map
+    } else {
+        return;
+    };
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+
+    let _span = info_span!("animate_map_tiles").entered();
+
+    for (layer_ent, layer, image_handle, animated_tiles, layer_palette) in layers.iter_mut() {
+        // Get the tileset for the layer
+        let tileset = if let Some(tileset) = layer
+            .layer_instance
+            .__tileset_def_uid
+            .and_then(|uid| map.tilesets.get(&(uid as i32)))
+        {
+            tileset
+        } else {
+            continue;
+        };
+
+        // Find the animated tiles in the layer the first time we see it
+        let mut animated_tiles = if let Some(animated_tiles) = animated_tiles {
+            animated_tiles
+        } else {
+            let layer = &layer.layer_instance;
+            let tiles = layer
+                .grid_tiles
+                .iter()
+                .chain(layer.auto_layer_tiles.iter())
+                .filter_map(|tile| {
+                    let animation = tileset
+                        .tile_metadata(tile.t as i32, &game_info.tile_tags)?
+                        .animation
+                        .filter(|x| !x.frames.is_empty() && x.fps > 0.)?;
+                    Some(AnimatedTile {
+                        px: (tile.px[0] as u32, tile.px[1] as u32),
+                        flip: tile.f as i32,
+                        animation,
+                        current_frame: None,
+                    })
+                })
+                .collect();
+
+            commands.entity(layer_ent).insert(LayerAnimatedTiles {
+                tiles,
+                tileset: None,
+                palette: None,
+            });
+            continue;
+        };
+        let animated_tiles = &mut *animated_tiles;
+
+        // Applying a palette replaces the whole layer image, so every tile has to be drawn again
+        // with the new palette
+        let layer_palette = layer_palette.and_then(|x| x.palette.as_ref());
+        if animated_tiles.palette.as_ref() != layer_palette {
+            animated_tiles.palette = layer_palette.cloned();
+            for tile in &mut animated_tiles.tiles {
+                tile.current_frame = None;
+            }
+        }
+
+        // Get the frame each tile should be showing
+        let seconds = game_time.seconds_since_startup();
+        let frame = |tile: &AnimatedTile| {
+            (seconds * tile.animation.fps as f64) as usize % tile.animation.frames.len()
+        };
+
+        // Skip layers where no tiles have changed frames
+        if animated_tiles
+            .tiles
+            .iter()
+            .all(|tile| tile.current_frame == Some(frame(tile)))
+        {
+            continue;
+        }
+
+        // Copy the tileset the first time its frames are drawn, so that we can borrow the layer
+        // image mutably
+        if animated_tiles.tileset.is_none() {
+            animated_tiles.tileset = image_assets.get(&tileset.image).map(|x| x.to_rgba8());
+        }
+        let tileset_image = if let Some(image) = &animated_tiles.tileset {
+            image
+        } else {
+            continue;
+        };
+
+        // Get the palette applied to the layer, and recolor the frames with it so that they match
+        // the rest of the layer
+        let palette =
+            layer_palette.and_then(|x| palette_assets.get(asset_server.load_cached(x.as_str())));
+
+        let layer_image = if let Some(image) = image_assets.get_mut(image_handle) {
+            image
+        } else {
+            continue;
+        };
+        let layer_image = if let Some(image) = layer_image.as_mut_rgba8() {
+            image
+        } else {
+            continue;
+        };
+
+        let tile_size = tileset.tile_size;
+        for tile in &mut animated_tiles.tiles {
+            let frame = frame(tile);
+
+            // Only draw tiles that have changed frames
+            if tile.current_frame == Some(frame) {
+                continue;
+            }
+            tile.current_frame = Some(frame);
+
+            // Get the position of the frame's tile in the tileset
+            let src = tileset.tile_position(tile.animation.frames[frame]);
+
+            for y in 0..tile_size {
+                for x in 0..tile_size {
+                    // Apply the tile flip bits
+                    let from_x = if tile.flip & 1 != 0 {
+                        tile_size - 1 - x
+                    } else {
+                        x
+                    };
+                    let from_y = if tile.flip & 2 != 0 {
+                        tile_size - 1 - y
+                    } else {
+                        y
+                    };
+
+                    let (to_x, to_y) = (tile.px.0 + x, tile.px.1 + y);
+                    if !tileset_image.in_bounds(src.x + from_x, src.y + from_y)
+                        || to_x >= layer_image.width()
+                        || to_y >= layer_image.height()
+                    {
+                        continue;
+                    }
+
+                    let mut pixel = *tileset_image.get_pixel(src.x + from_x, src.y + from_y);
+                    if let Some(palette) = palette {
+                        if let Some(to) = palette.swaps.get(&[pixel[0], pixel[1], pixel[2]]) {
+                            pixel[0] = to[0];
+                            pixel[1] = to[1];
+                            pixel[2] = to[2];
+                        }
+                    }
+                    layer_image.put_pixel(to_x, to_y, pixel);
+                }
+            }
+        }
+    }
+}
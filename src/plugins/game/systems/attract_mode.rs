@@ -0,0 +1,312 @@
+use bevy::app::AppExit;
+
+use super::{
+    game_over::restart_game,
+    gameplay::{stop_heartbeat, HealthFeedback},
+    *,
+};
+
+/// Resource tracking the attract mode demo playback
+#[derive(Default)]
+pub struct AttractMode {
+    /// Whether or not the attract mode demo is playing
+    pub active: bool,
+    /// The index of the replay segment being played
+    pub segment: usize,
+    /// How long we have been playing the current replay segment in seconds
+    pub segment_elapsed: f32,
+    /// Whether or not we have played the whole replay
+    pub finished: bool,
+}
+
+/// Pan the camera back and forth across the splash screen background level. The NPCs in the level
+/// walk along their paths while it pans, with
+/// [`follow_paths`](super::path_followers::follow_paths).
+pub fn animate_splash_background(
+    mut direction: Local<f32>,
+    mut cameras: Query<(&Camera, &mut Transform)>,
+    maps: Query<&GameMap>,
+    current_level: Res<CurrentLevel>,
+    game_info: Res<GameInfo>,
+    windows: Res<Windows>,
+    time: Res<Time>,
+) {
+    let pan_speed = game_info.splash_screen.background_pan_speed;
+    if pan_speed <= 0. {
+        return;
+    }
+
+    let (camera, mut camera_transform) = if let Ok(camera) = cameras.single_mut() {
+        camera
+    } else {
+        return;
+    };
+
+    let level = if let Some(level) = maps
+        .single()
+        .ok()
+        .and_then(|map| map.level(current_level.as_str()))
+    {
+        level
+    } else {
+        return;
+    };
+
+    // Get the range the camera can move in without showing outside of the level
+    let window = if let Some(window) = windows.get_primary() {
+        window
+    } else {
+        return;
+    };
+    let half_camera_width = camera.get_target_sizes(window).low.x as f32 / 2.;
+    let min_x = level.position.x + half_camera_width;
+    let max_x = level.position.x + level.size.x as f32 - half_camera_width;
+    if max_x <= min_x {
+        return;
+    }
+
+    // Start off panning to the right
+    if *direction == 0. {
+        *direction = 1.;
+    }
+
+    // Pan the camera, turning around at the edges of the level
+    let x = &mut camera_transform.translation.x;
+    *x += *direction * pan_speed * time.delta_seconds();
+    if *x >= max_x {
+        *x = max_x;
+        *direction = -1.;
+    } else if *x <= min_x {
+        *x = min_x;
+        *direction = 1.;
+    }
+}
+
+/// Start the attract mode demo when the start menu has been idle for long enough
+pub fn start_attract_mode(
+    mut idle_time: Local<f32>,
+    mut attract_mode: ResMut<AttractMode>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut state: ResMut<State<GameState>>,
+    mut rng: ResMut<GameRng>,
+    game_info: Res<GameInfo>,
+    asset_server: Res<AssetServer>,
+    replay_assets: Res<Assets<InputReplay>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    time: Res<Time>,
+) {
+    let config = if let Some(config) = &game_info.splash_screen.attract_mode {
+        config
+    } else {
+        return;
+    };
+
+    // Reset the idle time whenever the player does anything
+    if keyboard_input.get_pressed().len() > 0
+        || mouse_input.get_pressed().len() > 0
+        || touches.iter().next().is_some()
+    {
+        *idle_time = 0.;
+        return;
+    }
+
+    *idle_time += time.delta_seconds();
+    if *idle_time < config.idle_time {
+        return;
+    }
+
+    // Wait for the replay to load
+    let replay_handle: Handle<InputReplay> = asset_server.load_cached(config.replay.as_str());
+    let replay = if let Some(replay) = replay_assets.get(replay_handle) {
+        replay
+    } else {
+        return;
+    };
+
+    debug!("Start menu idle: starting attract mode");
+    *idle_time = 0.;
+    *attract_mode = AttractMode {
+        active: true,
+        ..Default::default()
+    };
+
+    // Use the same random numbers as when the replay was recorded
+    if let Some(seed) = replay.seed {
+        rng.reseed(seed);
+    }
+
+    // Start the game
+    *current_level = CurrentLevel(
+        config
+            .level
+            .clone()
+            .unwrap_or_else(|| game_info.game_start_level.clone()),
+    );
+    if state.current() != &GameState::LoadingGame {
+        state
+            .push(GameState::LoadingGame)
+            .expect("Could not transition to loading state");
+    }
+}
+
+/// Send control events from the attract mode replay
+pub fn replay_control_input(
+    mut attract_mode: ResMut<AttractMode>,
+    mut control_events: EventWriter<ControlEvent>,
+    game_info: Res<GameInfo>,
+    asset_server: Res<AssetServer>,
+    replay_assets: Res<Assets<InputReplay>>,
+    time: Res<Time>,
+) {
+    if !attract_mode.active || attract_mode.finished {
+        return;
+    }
+
+    let replay = if let Some(replay) = game_info
+        .splash_screen
+        .attract_mode
+        .as_ref()
+        .and_then(|config| replay_assets.get(asset_server.load_cached(config.replay.as_str())))
+    {
+        replay
+    } else {
+        return;
+    };
+
+    let segment = if let Some(segment) = replay.segments.get(attract_mode.segment) {
+        segment
+    } else {
+        attract_mode.finished = true;
+        return;
+    };
+
+    // Hold the segment's controls
+    for &control in &segment.controls {
+        control_events.send(control);
+    }
+
+    // Move on to the next segment when this one is done
+    attract_mode.segment_elapsed += time.delta_seconds();
+    if attract_mode.segment_elapsed >= segment.duration {
+        attract_mode.segment_elapsed -= segment.duration;
+        attract_mode.segment += 1;
+    }
+}
+
+/// Go back to the start menu when the player presses anything during attract mode, or the replay
+/// is finished
+pub fn end_attract_mode(
+    mut commands: Commands,
+    mut attract_mode: ResMut<AttractMode>,
+    all_entities: Query<Entity>,
+    mut state: ResMut<State<GameState>>,
+    mut ui_tree: ResMut<UiTree>,
+    current_level_music: Option<Res<CurrentLevelMusic>>,
+    mut sound_controller: SoundController,
+    mut health_feedback: ResMut<HealthFeedback>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+) {
+    if !attract_mode.active {
+        return;
+    }
+
+    if attract_mode.finished
+        || keyboard_input.get_just_pressed().len() > 0
+        || mouse_input.get_just_pressed().len() > 0
+        || touches.iter_just_pressed().next().is_some()
+    {
+        debug!("Ending attract mode");
+
+        // Stop the music
+        if let Some(current_level_music) = current_level_music {
+            sound_controller.stop_sound(current_level_music.sound);
+        }
+        commands.remove_resource::<CurrentLevelMusic>();
+        stop_heartbeat(&mut health_feedback, &mut sound_controller);
+
+        *attract_mode = AttractMode::default();
+        restart_game(&mut commands, &all_entities, &mut state, &mut ui_tree);
+    }
+}
+
+/// The in-progress recording of the player's controls
+#[derive(Default)]
+pub struct ReplayRecording {
+    replay: InputReplay,
+    /// Whether there are controls that haven't been written to the replay file yet
+    unsaved: bool,
+}
+
+/// Record the player's controls for the replay file set in the engine config
+pub fn record_replay(
+    mut recording: ResMut<ReplayRecording>,
+    mut control_events: EventReader<ControlEvent>,
+    engine_config: Res<crate::EngineConfig>,
+    attract_mode: Res<AttractMode>,
+    rng: Res<GameRng>,
+    time: Res<Time>,
+) {
+    // Don't record the demo
+    if engine_config.record_replay.is_none() || attract_mode.active {
+        return;
+    }
+
+    // Get the controls held this frame, in a consistent order
+    let mut controls = Vec::new();
+    for &control in control_events.iter() {
+        if !controls.contains(&control) {
+            controls.push(control);
+        }
+    }
+    controls.sort_by_key(|&x| x as u8);
+
+    // Record the seed so the replay plays back with the same random numbers
+    recording.replay.seed = Some(rng.seed());
+    recording.unsaved = true;
+
+    let segments = &mut recording.replay.segments;
+    match segments.last_mut() {
+        // Extend the last segment if the controls haven't changed
+        Some(segment) if segment.controls == controls => {
+            segment.duration += time.delta_seconds();
+        }
+        // Otherwise start a new segment
+        _ => {
+            segments.push(InputReplaySegment {
+                duration: time.delta_seconds(),
+                controls,
+            });
+        }
+    }
+}
+
+/// Write the recorded replay to its file when recording stops, i.e. when the game is paused or
+/// over, or the player closes the game
+pub fn save_replay(
+    mut recording: ResMut<ReplayRecording>,
+    mut exit_events: EventReader<AppExit>,
+    state: Res<State<GameState>>,
+    engine_config: Res<crate::EngineConfig>,
+) {
+    let quitting = exit_events.iter().next().is_some();
+    if !recording.unsaved || (state.current() == &GameState::Playing && !quitting) {
+        return;
+    }
+    let path = if let Some(path) = &engine_config.record_replay {
+        path
+    } else {
+        return;
+    };
+    recording.unsaved = false;
+
+    let result = std::fs::File::create(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|file| Ok(serde_yaml::to_writer(file, &recording.replay)?));
+    if let Err(error) = result {
+        warn!(%error, %path, "Could not write input replay");
+    }
+}
@@ -0,0 +1,56 @@
+use bevy::window::WindowFocused;
+
+use super::{attract_mode::AttractMode, game_init::StartMenuMusicHandle, *};
+use crate::EngineConfig;
+
+/// Pause the game and the music when the window loses focus, or when the page is hidden on web
+pub fn auto_pause(
+    mut music_ducked: Local<bool>,
+    mut focus_events: EventReader<WindowFocused>,
+    game_info: Option<Res<GameInfo>>,
+    state: Res<State<GameState>>,
+    attract_mode: Res<AttractMode>,
+    engine_config: Res<EngineConfig>,
+    mut pause_request: ResMut<PauseRequest>,
+    level_music: Option<Res<CurrentLevelMusic>>,
+    menu_music: Option<Res<StartMenuMusicHandle>>,
+    mut sound_controller: SoundController,
+) {
+    #[allow(unused_mut)]
+    let mut focus_changes = focus_events.iter().map(|x| x.focused).collect::<Vec<_>>();
+    #[cfg(wasm)]
+    focus_changes.extend(crate::wasm_utils::take_page_visibility_changes());
+
+    let focused = if let Some(focused) = focus_changes.last() {
+        *focused
+    } else {
+        return;
+    };
+    let config = game_info.map(|x| x.auto_pause.clone()).unwrap_or_default();
+
+    // Pause the music that is playing, or resume it if we paused it
+    let music = level_music
+        .map(|x| x.sound)
+        .into_iter()
+        .chain(menu_music.map(|x| x.0));
+    if !focused && config.duck_music && !engine_config.mute && !*music_ducked {
+        for sound in music {
+            sound_controller.pause_sound(sound);
+        }
+        *music_ducked = true;
+    } else if focused && *music_ducked {
+        if !engine_config.mute {
+            for sound in music {
+                sound_controller.resume_sound(sound);
+            }
+        }
+        *music_ducked = false;
+    }
+
+    // Pause the game, leaving it paused when focus comes back so the player can resume when ready
+    if !focused && config.enabled && state.current() == &GameState::Playing && !attract_mode.active
+    {
+        debug!("Pausing game because it lost focus");
+        pause_request.0 = Some(true);
+    }
+}
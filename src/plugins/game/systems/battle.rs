@@ -0,0 +1,516 @@
+use bevy_retrograde::prelude::raui::core::make_widget;
+use rand::Rng;
+
+use super::{crafting::CraftingAssets, gameplay::Health, spatial_hash::EnemySpatialHash, *};
+
+/// How close the player has to get to an enemy to start a battle with it, in pixels
+const ENCOUNTER_DISTANCE: f32 = 12.;
+
+/// How long after a battle ends before another one can start, so that the player can get away
+/// from an enemy they fled from, in seconds
+const ENCOUNTER_COOLDOWN: f32 = 2.;
+
+/// Resource for the turn-based battle the player is in
+#[derive(Default)]
+pub struct Battle {
+    /// The enemy the player is fighting
+    pub enemy: Option<Entity>,
+    /// What happened in the last turn
+    pub log: Vec<String>,
+    /// Whether the battle has been won, lost, or fled from, and is waiting for the player to
+    /// continue
+    pub finished: bool,
+    /// The time left before another battle can start, in seconds
+    cooldown: f32,
+}
+
+/// Something the player can do on their turn in a battle
+pub enum BattleAction {
+    Attack,
+    /// Use the ability in the player's ability slot with the given index
+    Ability(usize),
+    /// Use up the item with the given path to heal
+    Item(String),
+    Flee,
+}
+
+/// Start a battle when the player walks into an enemy, in games with turn-based battles
+#[allow(clippy::too_many_arguments)]
+pub fn start_battles(
+    characters: Query<&Transform, With<Handle<Character>>>,
+    enemies: Query<&Health, With<Enemy>>,
+    enemy_spatial_hash: Res<EnemySpatialHash>,
+    mut battle: ResMut<Battle>,
+    mut state: ResMut<State<GameState>>,
+    mut physics_time: ResMut<PhysicsTime>,
+    game_info: Res<GameInfo>,
+    time: Res<Time>,
+) {
+    if game_info.battle.is_none() {
+        return;
+    }
+    battle.cooldown = (battle.cooldown - time.delta_seconds()).max(0.);
+    if battle.cooldown > 0. {
+        return;
+    }
+    let character_pos = if let Ok(transform) = characters.single() {
+        transform.translation.truncate()
+    } else {
+        return;
+    };
+
+    // Fight the closest enemy in the current level that the player walked into
+    let enemy_ent = match enemy_spatial_hash.nearest(character_pos, ENCOUNTER_DISTANCE) {
+        Some((ent, _)) if enemies.get(ent).map_or(false, |x| x.current > 0) => ent,
+        _ => return,
+    };
+
+    // The game may already be pausing this frame
+    if state.push(GameState::Battle).is_ok() {
+        physics_time.pause();
+        *battle = Battle {
+            enemy: Some(enemy_ent),
+            log: vec!["An enemy attacks!".into()],
+            finished: false,
+            cooldown: 0.,
+        };
+    }
+}
+
+/// Show the battle menu when a battle starts
+pub fn show_battle_screen(mut ui: ResMut<UiTree>) {
+    *ui = UiTree(make_widget!(ui::battle_screen).into());
+}
+
+/// Take the player's turn, and then the enemy's turn if the battle isn't over
+pub fn take_battle_action(world: &mut World, action: BattleAction) {
+    let config = if let Some(config) = world.get_resource::<GameInfo>().unwrap().battle.clone() {
+        config
+    } else {
+        return;
+    };
+    let enemy = match world.get_resource::<Battle>().unwrap() {
+        Battle {
+            enemy: Some(enemy),
+            finished: false,
+            ..
+        } => *enemy,
+        _ => return,
+    };
+    let mut query = world.query_filtered::<Entity, With<Handle<Character>>>();
+    let player = if let Some(player) = query.iter(world).next() {
+        player
+    } else {
+        return;
+    };
+    let attack = world.get::<Stats>(player).map(|x| x.attack).unwrap_or(0);
+    let mut log = Vec::new();
+
+    // The player's turn
+    let (damage, heal) = match action {
+        BattleAction::Attack => {
+            log.push("You attack!".into());
+            (config.attack_damage + attack, 0)
+        }
+        BattleAction::Ability(index) => {
+            let ability = world
+                .get::<Abilities>(player)
+                .and_then(|x| x.slots.get(index))
+                .and_then(|x| {
+                    world
+                        .get_resource::<Assets<Ability>>()
+                        .unwrap()
+                        .get(&x.ability)
+                })
+                .cloned();
+            let ability = if let Some(ability) = ability {
+                ability
+            } else {
+                return;
+            };
+
+            let cost = ability.cost as f32;
+            if world
+                .get::<Energy>(player)
+                .map_or(false, |x| x.current < cost)
+            {
+                world.get_resource_mut::<Battle>().unwrap().log = vec!["Not enough energy!".into()];
+                return;
+            }
+            if let Some(mut energy) = world.get_mut::<Energy>(player) {
+                energy.current -= cost;
+            }
+
+            log.push(format!("You use {}!", ability.name));
+            let damage = ability_damage(&ability);
+            let damage = if damage > 0 { damage + attack } else { 0 };
+            (damage, ability_heal(&ability))
+        }
+        BattleAction::Item(path) => {
+            let crafting_assets = world.get_resource::<CraftingAssets>().unwrap();
+            let item_assets = world.get_resource::<Assets<Item>>().unwrap();
+            let name = crafting_assets.item_name(item_assets, &path);
+            let heal = crafting_assets
+                .items
+                .get(&path)
+                .and_then(|x| item_assets.get(x))
+                .map(|x| x.heal)
+                .unwrap_or(0);
+            if heal == 0
+                || !world
+                    .get_resource_mut::<Inventory>()
+                    .unwrap()
+                    .remove(&path, 1)
+            {
+                return;
+            }
+
+            log.push(format!("You use {}!", name));
+            (0, heal)
+        }
+        BattleAction::Flee => {
+            let roll: f32 = world.get_resource_mut::<GameRng>().unwrap().gen();
+            if roll < config.flee_chance {
+                let mut battle = world.get_resource_mut::<Battle>().unwrap();
+                battle.log = vec!["You got away!".into()];
+                battle.finished = true;
+                return;
+            }
+
+            log.push("You couldn't get away!".into());
+            (0, 0)
+        }
+    };
+
+    if heal > 0 {
+        if let Some(mut health) = world.get_mut::<Health>(player) {
+            let heal = heal.min(health.max - health.current);
+            health.current += heal;
+            log.push(format!("You recover {} health.", heal));
+        }
+    }
+    let mut won = false;
+    if damage > 0 {
+        if let Some(mut health) = world.get_mut::<Health>(enemy) {
+            let damage = damage.min(health.current);
+            health.current -= damage;
+            won = health.current == 0;
+            log.push(format!("The enemy takes {} damage.", damage));
+        }
+    }
+    if won {
+        log.push("You won!".into());
+        let mut battle = world.get_resource_mut::<Battle>().unwrap();
+        battle.log = log;
+        battle.finished = true;
+        return;
+    }
+
+    // The enemy's turn, using a random ability that does damage or its basic attack
+    let ability_assets = world.get_resource::<Assets<Ability>>().unwrap();
+    let mut attacks = world
+        .get::<Abilities>(enemy)
+        .map(|abilities| {
+            abilities
+                .slots
+                .iter()
+                .filter_map(|x| ability_assets.get(&x.ability))
+                .filter(|x| ability_damage(x) > 0)
+                .map(|x| (Some(x.name.clone()), ability_damage(x)))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    attacks.push((None, config.enemy_attack_damage));
+    let index = world
+        .get_resource_mut::<GameRng>()
+        .unwrap()
+        .gen_range(0..attacks.len());
+    let (name, damage) = attacks.swap_remove(index);
+    let damage = world
+        .get::<Stats>(player)
+        .map(|x| x.reduce_damage(damage))
+        .unwrap_or(damage);
+
+    match name {
+        Some(name) => log.push(format!("The enemy uses {}!", name)),
+        None => log.push("The enemy attacks!".into()),
+    }
+    let mut defeated = false;
+    let mut damage_taken = None;
+    if let Some(mut health) = world.get_mut::<Health>(player) {
+        let damage = damage.min(health.current);
+        health.current -= damage;
+        defeated = health.current == 0;
+        damage_taken = Some(damage);
+        log.push(format!("You take {} damage.", damage));
+    }
+    if let Some(damage) = damage_taken {
+        world
+            .get_resource_mut::<Events<CharacterDamagedEvent>>()
+            .unwrap()
+            .send(CharacterDamagedEvent {
+                character: player,
+                damage,
+            });
+    }
+    if defeated {
+        log.push("You were defeated...".into());
+    }
+
+    let mut battle = world.get_resource_mut::<Battle>().unwrap();
+    battle.log = log;
+    battle.finished = defeated;
+}
+
+/// Leave a finished battle and go back to the game
+pub fn end_battle(world: &mut World) {
+    let mut battle = world.get_resource_mut::<Battle>().unwrap();
+    if !battle.finished {
+        return;
+    }
+    *battle = Battle {
+        cooldown: ENCOUNTER_COOLDOWN,
+        ..Default::default()
+    };
+
+    world
+        .get_resource_mut::<State<GameState>>()
+        .unwrap()
+        .pop()
+        .expect("Could not transition game state");
+    world.get_resource_mut::<PhysicsTime>().unwrap().resume();
+}
+
+/// The damage an ability does to the enemy in a battle
+fn ability_damage(ability: &Ability) -> u32 {
+    ability
+        .effects
+        .iter()
+        .map(|effect| match effect {
+            AbilityEffect::Projectile { damage, .. } | AbilityEffect::Area { damage, .. } => {
+                *damage
+            }
+            _ => 0,
+        })
+        .sum()
+}
+
+/// The health an ability restores to the player in a battle
+fn ability_heal(ability: &Ability) -> u32 {
+    ability
+        .effects
+        .iter()
+        .map(|effect| match effect {
+            AbilityEffect::Heal { amount } => *amount,
+            _ => 0,
+        })
+        .sum()
+}
+
+mod ui {
+    use bevy::prelude::{Assets, Handle, With, World};
+    use bevy_retrograde::ui::raui::prelude::*;
+
+    use crate::plugins::game::{
+        assets::{Ability, Character, GameInfo, Item},
+        inventory::Inventory,
+        settings::UserSettings,
+        systems::{
+            crafting::CraftingAssets,
+            gameplay::Health,
+            ui_utils::{
+                active_ui_theme, get_ui_theme, menu_button, menu_label, menu_panel, menu_title,
+                ui_font, GameButtonMessage, UiLayout, UiThemeVariant,
+            },
+            Abilities, Energy,
+        },
+    };
+
+    use super::{end_battle, take_battle_action, Battle, BattleAction};
+
+    /// The pages of the battle menu
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    enum BattleMenuPage {
+        Actions,
+        Abilities,
+        Items,
+    }
+
+    impl Default for BattleMenuPage {
+        fn default() -> Self {
+            Self::Actions
+        }
+    }
+
+    #[derive(PropsData, Clone, Debug, serde::Serialize, serde::Deserialize, Default)]
+    struct BattleMenuState {
+        page: BattleMenuPage,
+    }
+
+    fn use_battle_screen(ctx: &mut WidgetContext) {
+        ctx.life_cycle.change(|ctx| {
+            let world: &mut World = ctx.process_context.get_mut().unwrap();
+            let BattleMenuState { mut page } = ctx.state.read_cloned_or_default();
+
+            let mut handled = false;
+            for msg in ctx.messenger.messages {
+                let msg = if let Some(msg) = msg.as_any().downcast_ref::<GameButtonMessage>() {
+                    msg
+                } else {
+                    continue;
+                };
+                handled = true;
+
+                match msg.0.as_str() {
+                    "attack" => take_battle_action(world, BattleAction::Attack),
+                    "flee" => take_battle_action(world, BattleAction::Flee),
+                    "continue" => end_battle(world),
+                    "show_actions" => page = BattleMenuPage::Actions,
+                    "show_abilities" => page = BattleMenuPage::Abilities,
+                    "show_items" => page = BattleMenuPage::Items,
+                    message => {
+                        if let Some(index) = message
+                            .strip_prefix("ability:")
+                            .and_then(|x| x.parse::<usize>().ok())
+                        {
+                            take_battle_action(world, BattleAction::Ability(index));
+                            page = BattleMenuPage::Actions;
+                        } else if let Some(item) = message.strip_prefix("item:") {
+                            take_battle_action(world, BattleAction::Item(item.into()));
+                            page = BattleMenuPage::Actions;
+                        }
+                    }
+                }
+            }
+
+            // Write the state even if the page hasn't changed, so that the menu is re-rendered
+            // after every turn
+            if handled {
+                ctx.state.write(BattleMenuState { page }).unwrap();
+            }
+        });
+    }
+
+    /// The battle menu, showing the health of the player and the enemy, what happened in the last
+    /// turn, and the actions the player can take
+    #[pre_hooks(use_battle_screen)]
+    pub fn battle_screen(mut ctx: WidgetContext) -> WidgetNode {
+        let BattleMenuState { page } = ctx.state.read_cloned_or_default();
+        let id = ctx.id.to_owned();
+
+        let world: &mut World = ctx.process_context.get_mut().unwrap();
+        let game_info = world.get_resource::<GameInfo>().unwrap();
+        let settings = world.get_resource::<UserSettings>().unwrap();
+        let ui_theme_variant = world.get_resource::<UiThemeVariant>().unwrap();
+        let ui_theme = active_ui_theme(game_info, settings, ui_theme_variant, "battle");
+        let theme = get_ui_theme(ui_theme);
+        let font = ui_font(&ui_theme.default_font, settings);
+        let ui_layout = world
+            .get_resource::<UiLayout>()
+            .cloned()
+            .unwrap_or_default();
+
+        let battle = world.get_resource::<Battle>().unwrap();
+        let finished = battle.finished;
+        let log = battle.log.clone();
+        let enemy_health = battle
+            .enemy
+            .and_then(|x| world.get::<Health>(x))
+            .map(|x| format!("Enemy: {}/{}", x.current, x.max));
+        let mut query =
+            world.query_filtered::<(&Health, Option<&Energy>), With<Handle<Character>>>();
+        let player_health = query.iter(world).next().map(|(health, energy)| {
+            let mut text = format!("You: {}/{}", health.current, health.max);
+            if let Some(energy) = energy {
+                text += &format!("  Energy: {}/{}", energy.current as u32, energy.max as u32);
+            }
+            text
+        });
+
+        let mut rows = vec![menu_title(&font, "Battle")];
+        rows.extend(enemy_health.map(|x| menu_label(&font, &x)));
+        rows.extend(player_health.map(|x| menu_label(&font, &x)));
+        rows.extend(log.iter().map(|x| menu_label(&font, x)));
+
+        if finished {
+            rows.push(menu_button("Continue", &id, "continue"));
+        } else {
+            match page {
+                BattleMenuPage::Actions => {
+                    rows.push(menu_button("Attack", &id, "attack"));
+                    rows.push(menu_button("Ability", &id, "show_abilities"));
+                    rows.push(menu_button("Item", &id, "show_items"));
+                    rows.push(menu_button("Flee", &id, "flee"));
+                }
+                BattleMenuPage::Abilities => {
+                    rows.extend(ability_buttons(world, &id));
+                    rows.push(menu_button("Back", &id, "show_actions"));
+                }
+                BattleMenuPage::Items => {
+                    rows.extend(item_buttons(world, &font, &id));
+                    rows.push(menu_button("Back", &id, "show_actions"));
+                }
+            }
+        }
+
+        menu_panel(theme, &ui_layout, rows)
+    }
+
+    /// A button for each of the player's abilities, with its energy cost
+    fn ability_buttons(world: &mut World, id: &WidgetId) -> Vec<WidgetNode> {
+        let mut query = world.query_filtered::<&Abilities, With<Handle<Character>>>();
+        let ability_assets = world.get_resource::<Assets<Ability>>().unwrap();
+        let abilities = if let Some(abilities) = query.iter(world).next() {
+            abilities
+        } else {
+            return Vec::new();
+        };
+
+        abilities
+            .slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| Some((i, ability_assets.get(&slot.ability)?)))
+            .map(|(i, ability)| {
+                let text = if ability.cost > 0 {
+                    format!("{} ({})", ability.name, ability.cost)
+                } else {
+                    ability.name.clone()
+                };
+                menu_button(&text, id, &format!("ability:{}", i))
+            })
+            .collect()
+    }
+
+    /// A button for each item in the player's inventory that heals
+    fn item_buttons(world: &mut World, font: &TextBoxFont, id: &WidgetId) -> Vec<WidgetNode> {
+        let inventory = world.get_resource::<Inventory>().unwrap();
+        let crafting_assets = world.get_resource::<CraftingAssets>().unwrap();
+        let item_assets = world.get_resource::<Assets<Item>>().unwrap();
+
+        let buttons = inventory
+            .0
+            .iter()
+            .filter(|(path, _)| {
+                crafting_assets
+                    .items
+                    .get(*path)
+                    .and_then(|x| item_assets.get(x))
+                    .map_or(false, |x| x.heal > 0)
+            })
+            .map(|(path, count)| {
+                let name = crafting_assets.item_name(item_assets, path);
+                menu_button(
+                    &format!("{} x{}", name, count),
+                    id,
+                    &format!("item:{}", path),
+                )
+            })
+            .collect::<Vec<_>>();
+        if buttons.is_empty() {
+            return vec![menu_label(font, "No items to use")];
+        }
+
+        buttons
+    }
+}
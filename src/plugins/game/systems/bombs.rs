@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use super::{game_time::GameTime, gameplay::Health, *};
+
+/// Component for a bomb that has been placed and explodes when its fuse runs out
+pub struct Bomb {
+    /// The settings from the bomb item
+    pub item: BombItem,
+    /// The time left until the bomb explodes
+    pub fuse: Timer,
+}
+
+impl Bomb {
+    /// Create a bomb with its fuse lit, from the settings of the bomb item
+    pub fn new(item: BombItem) -> Self {
+        Self {
+            fuse: Timer::new(Duration::from_secs_f32(item.fuse), false),
+            item,
+        }
+    }
+}
+
+/// Component for the image shown where a bomb exploded, which is put back in its [`EntityPool`]
+/// when its timer finishes
+pub struct Explosion {
+    timer: Timer,
+}
+
+/// Burn down the fuses of placed bombs and explode the ones that run out, damaging everything in
+/// their radius and breaking the secret walls near them
+#[allow(clippy::too_many_arguments)]
+pub fn explode_bombs(
+    mut commands: Commands,
+    mut bombs: Query<(Entity, &mut Bomb, &Transform, Option<&YSort>)>,
+    mut explosions: Query<(Entity, &mut Explosion), Without<Pooled>>,
+    mut explosion_pool: ResMut<EntityPool<Explosion>>,
+    mut targets: Query<
+        (Entity, &Transform, &mut Health, Option<&Enemy>),
+        (Without<Dormant>, Without<Bomb>),
+    >,
+    mut damaged_events: EventWriter<CharacterDamagedEvent>,
+    mut wall_impacts: EventWriter<WallImpactEvent>,
+    mut noise_events: EventWriter<NoiseEvent>,
+    asset_server: Res<AssetServer>,
+    mut sound_controller: SoundController,
+    mut sound_events: EventWriter<SoundPlayedEvent>,
+    engine_config: Res<crate::EngineConfig>,
+    game_info: Option<Res<GameInfo>>,
+    game_time: Res<GameTime>,
+) {
+    for (explosion_ent, mut explosion) in explosions.iter_mut() {
+        if explosion.timer.tick(game_time.delta()).finished() {
+            explosion_pool.release(&mut commands, explosion_ent);
+        }
+    }
+
+    for (bomb_ent, mut bomb, transform, y_sort) in bombs.iter_mut() {
+        if !bomb.fuse.tick(game_time.delta()).finished() {
+            continue;
+        }
+        commands.entity(bomb_ent).despawn();
+        let item = &bomb.item;
+        let position = transform.translation.truncate();
+
+        // Damage everything in the blast, whichever side it is on
+        for (target_ent, target_transform, mut health, enemy) in targets.iter_mut() {
+            if target_transform.translation.truncate().distance(position) > item.radius {
+                continue;
+            }
+
+            let damage = item.damage.min(health.current);
+            health.current -= damage;
+            if enemy.is_none() {
+                damaged_events.send(CharacterDamagedEvent {
+                    character: target_ent,
+                    damage,
+                });
+            }
+        }
+
+        wall_impacts.send(WallImpactEvent {
+            position,
+            radius: item.radius,
+            damage: item.damage,
+        });
+        if let Some(game_info) = &game_info {
+            noise_events.send(NoiseEvent {
+                position,
+                radius: game_info.stealth.ability_noise.max(item.radius),
+            });
+        }
+
+        if let Some(sprite) = &item.explosion_sprite {
+            let explosion = explosion_pool.take(&mut commands);
+            commands
+                .entity(explosion)
+                .insert_bundle(SpriteBundle {
+                    image: asset_server.load(sprite.as_str()),
+                    transform: *transform,
+                    ..Default::default()
+                })
+                .insert(y_sort.copied().unwrap_or_default())
+                .insert(Explosion {
+                    timer: Timer::new(Duration::from_secs_f32(item.explosion_duration), false),
+                });
+        }
+
+        if let (Some(sound), false) = (&item.explosion_sound, engine_config.mute) {
+            let sound_data = asset_server.load_cached(sound.as_str());
+            let sound_handle = sound_controller.create_sound(&sound_data);
+            sound_controller.play_sound(sound_handle);
+            sound_events.send(SoundPlayedEvent {
+                sound: sound.clone(),
+            });
+        }
+    }
+}
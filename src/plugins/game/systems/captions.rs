@@ -0,0 +1,129 @@
+use bevy::prelude::{EventReader, Res, ResMut, Time, Timer, World};
+use bevy_retrograde::ui::raui::prelude::*;
+
+use crate::plugins::game::{
+    assets::GameInfo,
+    events::SoundPlayedEvent,
+    settings::UserSettings,
+    systems::ui_utils::{rich_text, ui_font, RichTextProps},
+};
+
+/// Resource containing the sound captions currently on screen
+#[derive(Default)]
+pub struct Captions {
+    /// The caption text and the time left to show it, oldest first
+    pub active: Vec<(String, Timer)>,
+}
+
+/// Show captions for the sounds that have captions configured in the game info, if the player has
+/// turned captions on
+pub fn update_captions(
+    mut captions: ResMut<Captions>,
+    mut sound_events: EventReader<SoundPlayedEvent>,
+    game_info: Option<Res<GameInfo>>,
+    settings: Res<UserSettings>,
+    time: Res<Time>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+    let config = &game_info.captions;
+
+    // Avoid triggering change detection if there is nothing to caption
+    let events = sound_events.iter().collect::<Vec<_>>();
+    if captions.active.is_empty() && events.is_empty() {
+        return;
+    }
+
+    let captions = &mut *captions;
+    if settings.accessibility.captions {
+        for event in events {
+            let text = if let Some(text) = config.sounds.get(&event.sound) {
+                text
+            } else {
+                continue;
+            };
+
+            // Move repeated captions to the end instead of showing them twice
+            captions.active.retain(|(x, _)| x != text);
+            captions
+                .active
+                .push((text.clone(), Timer::from_seconds(config.duration, false)));
+        }
+    } else {
+        captions.active.clear();
+    }
+
+    // Remove old captions
+    for (_, timer) in &mut captions.active {
+        timer.tick(time.delta());
+    }
+    captions.active.retain(|(_, timer)| !timer.finished());
+    let overflow = captions.active.len().saturating_sub(config.max_visible);
+    captions.active.drain(..overflow);
+}
+
+/// The list of sound captions shown in the bottom-right corner of the HUD
+pub fn captions(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let captions = if let Some(captions) = world.get_resource::<Captions>() {
+        captions
+    } else {
+        return WidgetNode::None;
+    };
+    if captions.active.is_empty() {
+        return WidgetNode::None;
+    }
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+    let font = ui_font(
+        &game_info.ui_theme.default_font,
+        world.get_resource::<UserSettings>().unwrap(),
+    );
+
+    // Stack the captions from the bottom of the screen up, newest at the bottom
+    let mut list = make_widget!(vertical_box)
+        .with_props(VerticalBoxProps {
+            separation: 2.,
+            ..Default::default()
+        })
+        .with_props(ContentBoxItemLayout {
+            anchors: Rect {
+                left: 0.5,
+                right: 1.,
+                top: 0.,
+                bottom: 1.,
+            },
+            margin: 5.0.into(),
+            ..Default::default()
+        })
+        .listed_slot(make_widget!(space_box));
+
+    for (text, _) in &captions.active {
+        list = list.listed_slot(
+            make_widget!(rich_text)
+                .with_props(RichTextProps {
+                    text: format!("[{}]", text),
+                })
+                .with_props(TextBoxProps {
+                    font: font.clone(),
+                    color: Color {
+                        r: 1.,
+                        g: 1.,
+                        b: 1.,
+                        a: 1.,
+                    },
+                    horizontal_align: TextBoxHorizontalAlign::Right,
+                    ..Default::default()
+                })
+                .with_props(FlexBoxItemLayout {
+                    grow: 0.,
+                    basis: Some(10. * font.size),
+                    ..Default::default()
+                }),
+        );
+    }
+
+    list.into()
+}
@@ -0,0 +1,21 @@
+use super::{game_time::GameTime, *};
+
+/// Move the in-game clock forward while the game is playing, sending a [`DayPassedEvent`] at
+/// midnight
+pub fn advance_game_clock(
+    mut clock: ResMut<GameClock>,
+    mut day_passed_events: EventWriter<DayPassedEvent>,
+    game_info: Res<GameInfo>,
+    game_time: Res<GameTime>,
+) {
+    let day_length = game_info.clock.day_length;
+    if day_length <= 0. {
+        return;
+    }
+
+    let day = clock.day;
+    clock.advance(game_time.fixed_delta().as_secs_f32() * 24. / day_length);
+    if clock.day > day {
+        day_passed_events.send(DayPassedEvent { day: clock.day });
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use super::*;
+
+/// The prefix of prop interactions that put an item in the player's inventory, followed by the
+/// path to the item, i.e. `collect:items/wood.item.yml`
+const COLLECT_INTERACTION_PREFIX: &str = "collect:";
+
+/// The interaction of props that open the crafting screen, optionally followed by `:` and the name
+/// of the crafting station, i.e. `craft:anvil`
+const CRAFT_INTERACTION: &str = "craft";
+
+/// Resource holding the recipes from the game info, and the items in the inventory and the recipes
+/// by path, so that they stay loaded for the inventory and crafting screens
+#[derive(Default)]
+pub struct CraftingAssets {
+    pub recipes: Vec<Handle<Recipe>>,
+    pub items: HashMap<String, Handle<Item>>,
+}
+
+impl CraftingAssets {
+    /// Get the name of an item, falling back to its path if it hasn't loaded
+    pub fn item_name(&self, item_assets: &Assets<Item>, path: &str) -> String {
+        self.items
+            .get(path)
+            .and_then(|x| item_assets.get(x))
+            .map(|x| x.name.clone())
+            .unwrap_or_else(|| path.into())
+    }
+}
+
+/// Resource for the crafting station the player is using
+#[derive(Default)]
+pub struct CraftingStation {
+    /// Whether the pause menu should open on the crafting screen, which is set when the player
+    /// interacts with a crafting station
+    pub open_requested: bool,
+    /// The name of the station being used. Recipes that need a station can only be made there.
+    pub station: Option<String>,
+}
+
+/// Load the recipes listed in the game info, and the items that the inventory and recipes refer to
+pub fn load_crafting_assets(
+    game_info: Option<Res<GameInfo>>,
+    inventory: Res<Inventory>,
+    recipe_assets: Res<Assets<Recipe>>,
+    asset_server: Res<AssetServer>,
+    mut crafting_assets: ResMut<CraftingAssets>,
+) {
+    if let Some(game_info) = game_info {
+        if game_info.is_changed() {
+            crafting_assets.recipes = game_info
+                .recipes
+                .iter()
+                .map(|x| asset_server.load(x.as_str()))
+                .collect();
+        }
+    }
+
+    let recipe_items = crafting_assets
+        .recipes
+        .iter()
+        .filter_map(|x| recipe_assets.get(x))
+        .flat_map(|x| x.inputs.keys().chain(Some(&x.output)))
+        .cloned()
+        .collect::<Vec<_>>();
+    for path in inventory.0.keys().cloned().chain(recipe_items) {
+        if !crafting_assets.items.contains_key(&path) {
+            let handle = asset_server.load(path.as_str());
+            crafting_assets.items.insert(path, handle);
+        }
+    }
+}
+
+/// Put the items from props with a `collect:` interaction in the player's inventory, removing the
+/// props
+pub fn collect_items_from_props(
+    mut commands: Commands,
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    props: Query<&PersistentId, With<Prop>>,
+    mut level_states: ResMut<LevelStates>,
+    mut inventory: ResMut<Inventory>,
+) {
+    for event in interaction_events.iter() {
+        let item = if let Some(item) = event.interaction.strip_prefix(COLLECT_INTERACTION_PREFIX) {
+            item
+        } else {
+            continue;
+        };
+
+        inventory.add(item, 1);
+
+        // Collected items stay collected
+        if let Ok(id) = props.get(event.prop) {
+            level_states.entity_mut(&id.level, &id.id).removed = true;
+        }
+        commands.entity(event.prop).despawn_recursive();
+    }
+}
+
+/// Pause the game and open the crafting screen when the player interacts with a crafting station
+pub fn use_crafting_stations(
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    mut state: ResMut<State<GameState>>,
+    mut physics_time: ResMut<PhysicsTime>,
+    mut crafting_station: ResMut<CraftingStation>,
+) {
+    for event in interaction_events.iter() {
+        let station = if event.interaction == CRAFT_INTERACTION {
+            None
+        } else if let Some(station) = event
+            .interaction
+            .strip_prefix(CRAFT_INTERACTION)
+            .and_then(|x| x.strip_prefix(':'))
+        {
+            Some(station.to_owned())
+        } else {
+            continue;
+        };
+
+        // The game may already be pausing this frame
+        if state.push(GameState::Paused).is_ok() {
+            physics_time.pause();
+        }
+        crafting_station.open_requested = true;
+        crafting_station.station = station;
+    }
+}
+
+/// Make the recipe at the given index in the [`CraftingAssets`], if the player has its inputs
+pub fn craft_recipe(world: &mut World, index: usize) {
+    let recipe = world
+        .get_resource::<CraftingAssets>()
+        .unwrap()
+        .recipes
+        .get(index)
+        .and_then(|x| world.get_resource::<Assets<Recipe>>().unwrap().get(x))
+        .cloned();
+
+    if let Some(recipe) = recipe {
+        if !world
+            .get_resource_mut::<Inventory>()
+            .unwrap()
+            .craft(&recipe)
+        {
+            debug!(recipe = ?recipe.name, "Missing the inputs to craft recipe");
+        }
+    }
+}
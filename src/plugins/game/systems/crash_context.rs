@@ -0,0 +1,28 @@
+use crate::crash_report;
+
+use super::*;
+
+/// Keep the game and level included in crash reports up to date
+pub fn update_crash_context(
+    game_info: Option<Res<GameInfo>>,
+    current_level: Option<Res<CurrentLevel>>,
+) {
+    if let Some(game_info) = game_info.filter(|x| x.is_changed()) {
+        crash_report::set_game_summary(format!(
+            "{} (map `{}`, start level `{}`, character `{}`)",
+            game_info.title, game_info.map, game_info.game_start_level, game_info.player_character
+        ));
+    }
+
+    if let Some(level) = current_level.filter(|x| x.is_changed()) {
+        crash_report::set_current_level(Some(&level.0));
+    }
+}
+
+/// Write crash reports next to the save data once the storage is opened
+#[cfg(not(wasm))]
+pub fn set_crash_report_dir(storage: Option<Res<crate::Storage>>) {
+    if let Some(storage) = storage.filter(|x| x.is_added()) {
+        crash_report::set_report_dir(storage.location().into());
+    }
+}
@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use super::{game_time::GameTime, *};
+
+/// How long critters wander in one direction before picking another, in seconds
+const WANDER_TIME: std::ops::Range<f32> = 1.0..3.0;
+
+/// How many times farther than the flee distance the player has to be for a critter to stop
+/// fleeing
+const CALM_DISTANCE_FACTOR: f32 = 3.;
+
+/// Component for a decorative critter that wanders around its level and flees from the player
+pub struct Critter {
+    velocity: Vec2,
+    /// The time left before the critter picks a new direction to wander in
+    wander_timer: Timer,
+    fleeing: bool,
+}
+
+/// Spawn the critters for the current level when the player enters it, removing the critters of
+/// the previous level
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_critters(
+    mut commands: Commands,
+    mut spawned_level: Local<Option<String>>,
+    critters: Query<Entity, With<Critter>>,
+    maps: Query<&GameMap>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+    asset_server: Res<AssetServer>,
+    mut rng: ResMut<GameRng>,
+) {
+    let current_level = if let Some(current_level) = current_level {
+        current_level
+    } else {
+        // Spawn the critters again when the game is restarted
+        *spawned_level = None;
+        return;
+    };
+    let (map, game_info) = if let (Ok(map), Some(game_info)) = (maps.single(), game_info) {
+        (map, game_info)
+    } else {
+        return;
+    };
+    if spawned_level.as_deref() == Some(current_level.0.as_str()) {
+        return;
+    }
+    *spawned_level = Some(current_level.0.clone());
+
+    // Only the current level has critters, to keep them cheap
+    for critter in critters.iter() {
+        commands.entity(critter).despawn();
+    }
+
+    let level = if let Some(level) = map.level(&current_level.0) {
+        level
+    } else {
+        return;
+    };
+    let sprite = if let Some(sprite) = level.field::<String>("critters") {
+        sprite
+    } else {
+        return;
+    };
+    let count = level
+        .field::<u32>("critter_count")
+        .unwrap_or(game_info.critters.count);
+    let animated = sprite.ends_with(".animations.yml") || sprite.ends_with(".animations.yaml");
+
+    for _ in 0..count {
+        // Scatter the critters around the level, drawn over everything in it
+        let position = level.position
+            + Vec2::new(
+                rng.gen_range(0.0..level.size.x.max(1) as f32),
+                rng.gen_range(0.0..level.size.y.max(1) as f32),
+            );
+        let sprite_bundle = SpriteBundle {
+            transform: Transform::from_translation(position.extend(level.top_z())),
+            ..Default::default()
+        };
+        let mut critter_commands = if animated {
+            commands.spawn_bundle(SpriteAnimationBundle {
+                animation: SpriteAnimation::new(asset_server.load(sprite.as_str()), "idle"),
+                sprite_bundle,
+                ..Default::default()
+            })
+        } else {
+            commands.spawn_bundle(SpriteBundle {
+                image: asset_server.load(sprite.as_str()),
+                ..sprite_bundle
+            })
+        };
+        critter_commands.insert(Critter {
+            velocity: Vec2::ZERO,
+            wander_timer: Timer::default(),
+            fleeing: false,
+        });
+    }
+}
+
+/// Move critters around their level, fleeing from the player when they get too close
+#[allow(clippy::too_many_arguments)]
+pub fn move_critters(
+    mut critters: Query<(
+        &mut Critter,
+        &mut Transform,
+        &mut Sprite,
+        Option<&mut SpriteAnimation>,
+    )>,
+    characters: Query<&Transform, (With<Handle<Character>>, Without<Critter>)>,
+    maps: Query<&GameMap>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+    animation_assets: Res<Assets<SpriteAnimations>>,
+    mut rng: ResMut<GameRng>,
+    game_time: Res<GameTime>,
+) {
+    let (level, game_info) = match (maps.single(), current_level, game_info) {
+        (Ok(map), Some(current_level), Some(game_info)) => match map.level(&current_level.0) {
+            Some(level) => (level, game_info),
+            None => return,
+        },
+        _ => return,
+    };
+    let config = &game_info.critters;
+    let player_position = characters.iter().next().map(|x| x.translation.truncate());
+    let delta = game_time.delta_seconds();
+
+    for (mut critter, mut transform, mut sprite, animation) in critters.iter_mut() {
+        let position = transform.translation.truncate();
+        let away_from_player = player_position.map(|x| position - x);
+        let player_distance = away_from_player.map_or(f32::MAX, |x| x.length());
+
+        if player_distance <= config.flee_distance {
+            critter.fleeing = true;
+        } else if critter.fleeing && player_distance > config.flee_distance * CALM_DISTANCE_FACTOR {
+            critter.fleeing = false;
+            critter.velocity = Vec2::ZERO;
+        }
+
+        if critter.fleeing {
+            let away = away_from_player.unwrap_or_default();
+            if away.length_squared() > 0. {
+                critter.velocity = away.normalize() * config.flee_speed;
+            }
+        } else if critter.wander_timer.tick(game_time.delta()).finished() {
+            // Either rest or pick a new direction to wander in
+            critter.velocity = if rng.gen_bool(0.5) {
+                Vec2::ZERO
+            } else {
+                let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+                Vec2::new(angle.cos(), angle.sin()) * config.wander_speed
+            };
+            critter.wander_timer =
+                Timer::new(Duration::from_secs_f32(rng.gen_range(WANDER_TIME)), false);
+        }
+
+        // Keep the critters inside the level, turning them around at the edges
+        let min = level.position;
+        let max = level.position + level.size.as_f32();
+        let mut new_position = position + critter.velocity * delta;
+        if new_position.x < min.x || new_position.x > max.x {
+            critter.velocity.x = -critter.velocity.x;
+            new_position.x = new_position.x.clamp(min.x, max.x);
+        }
+        if new_position.y < min.y || new_position.y > max.y {
+            critter.velocity.y = -critter.velocity.y;
+            new_position.y = new_position.y.clamp(min.y, max.y);
+        }
+        transform.translation = new_position.extend(transform.translation.z);
+
+        if let Some(mut animation) = animation {
+            // Play the flee clip while fleeing, if the critter has one
+            let has_flee_clip = animation_assets
+                .get(&animation.animations)
+                .map_or(false, |x| x.clips.contains_key("flee"));
+            let clip = if critter.fleeing && has_flee_clip {
+                "flee"
+            } else {
+                "idle"
+            };
+            animation.play(clip);
+        } else if critter.velocity.x != 0. && sprite.flip_x != (critter.velocity.x < 0.) {
+            // Face the way the critter is going
+            sprite.flip_x = critter.velocity.x < 0.;
+        }
+    }
+}
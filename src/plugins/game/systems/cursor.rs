@@ -0,0 +1,90 @@
+use super::*;
+
+/// The z position of the custom cursor sprite, in front of everything else in the world
+const CURSOR_Z: f32 = 1000.;
+
+/// Marker component for the sprite drawn in place of the system cursor
+pub struct CustomCursor;
+
+/// Show, hide, and move the mouse cursor according to the game's cursor settings and the current
+/// [`GameState`]
+pub fn update_cursor(
+    mut commands: Commands,
+    mut os_cursor_visible: Local<Option<bool>>,
+    mut custom_cursors: Query<
+        (&mut Handle<Image>, &mut Transform, &mut Visible),
+        With<CustomCursor>,
+    >,
+    mut windows: ResMut<Windows>,
+    cursor_world_position: Res<CursorWorldPosition>,
+    state: Res<State<GameState>>,
+    game_info: Option<Res<GameInfo>>,
+    asset_server: Res<AssetServer>,
+) {
+    // Use the default cursor settings until the game info is loaded
+    let config = game_info.map(|x| x.cursor.clone()).unwrap_or_default();
+
+    let hidden = config.hide_in_gameplay && state.current() == &GameState::Playing;
+
+    // Hide the system cursor if it is hidden or being replaced by a custom cursor
+    let show_os_cursor = !hidden && config.image.is_none();
+    if *os_cursor_visible != Some(show_os_cursor) {
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_cursor_visibility(show_os_cursor);
+            *os_cursor_visible = Some(show_os_cursor);
+        }
+    }
+
+    let image_path = if let Some(path) = &config.image {
+        path
+    } else {
+        // Hide the custom cursor if it has been removed from the game info
+        for (_, _, mut visible) in custom_cursors.iter_mut() {
+            if visible.0 {
+                visible.0 = false;
+            }
+        }
+        return;
+    };
+    let image: Handle<Image> = asset_server.load_cached(image_path.as_str());
+
+    // Position the cursor image so that the hotspot is at the cursor position
+    let translation = cursor_world_position.0.map(|position| {
+        Vec3::new(
+            position.x - config.hotspot.0 as f32,
+            position.y - config.hotspot.1 as f32,
+            CURSOR_Z,
+        )
+    });
+
+    if let Ok((mut cursor_image, mut cursor_transform, mut cursor_visible)) =
+        custom_cursors.single_mut()
+    {
+        if *cursor_image != image {
+            *cursor_image = image;
+        }
+
+        let visible = !hidden && translation.is_some();
+        if cursor_visible.0 != visible {
+            cursor_visible.0 = visible;
+        }
+
+        if let Some(translation) = translation {
+            cursor_transform.translation = translation;
+        }
+    } else {
+        // Spawn the cursor sprite
+        commands
+            .spawn_bundle(SpriteBundle {
+                image,
+                transform: Transform::from_translation(translation.unwrap_or_default()),
+                sprite: Sprite {
+                    centered: false,
+                    ..Default::default()
+                },
+                visible: Visible(!hidden && translation.is_some()),
+                ..Default::default()
+            })
+            .insert(CustomCursor);
+    }
+}
@@ -0,0 +1,192 @@
+use super::{game_time::GameTime, *};
+
+/// The prefix of prop interactions that play a cutscene, followed by the path to the cutscene,
+/// i.e. `cutscene:cutscenes/bridge.cutscene.yml`
+const CUTSCENE_INTERACTION_PREFIX: &str = "cutscene:";
+
+/// Resource for the [`Cutscene`] being played, and the letterbox bars and camera it controls
+#[derive(Default)]
+pub struct CutscenePlayer {
+    cutscene: Option<Handle<Cutscene>>,
+    /// The index of the step being played
+    step: usize,
+    /// How long the current step has been playing, in seconds
+    step_elapsed: f32,
+    /// Whether the player's controls are locked
+    pub input_locked: bool,
+    /// Whether the letterbox bars should be shown
+    letterbox_shown: bool,
+    /// How far the letterbox bars have slid in, from `0` to `1`
+    pub letterbox: f32,
+    /// Where the camera was when the current camera step started
+    pan_start: Option<Vec2>,
+    /// Where the cutscene is holding the camera, if it is controlling it
+    camera: Option<Vec2>,
+}
+
+impl CutscenePlayer {
+    /// Start playing a cutscene, replacing the one being played
+    pub fn play(&mut self, cutscene: Handle<Cutscene>) {
+        *self = Self {
+            cutscene: Some(cutscene),
+            // Keep the player from moving while the cutscene loads
+            input_locked: true,
+            letterbox_shown: self.letterbox_shown,
+            letterbox: self.letterbox,
+            camera: self.camera,
+            ..Default::default()
+        };
+    }
+
+    /// Stop the cutscene, giving the camera and controls back to the player
+    fn finish(&mut self) {
+        self.cutscene = None;
+        self.input_locked = false;
+        self.letterbox_shown = false;
+        self.camera = None;
+    }
+}
+
+/// Play the cutscenes of the props that the player interacts with
+pub fn start_cutscenes(
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    mut cutscene_player: ResMut<CutscenePlayer>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in interaction_events.iter() {
+        if let Some(path) = event.interaction.strip_prefix(CUTSCENE_INTERACTION_PREFIX) {
+            cutscene_player.play(asset_server.load(path));
+        }
+    }
+}
+
+/// Play the steps of the current cutscene and slide the letterbox bars in and out
+#[allow(clippy::too_many_arguments)]
+pub fn play_cutscenes(
+    mut cutscene_player: ResMut<CutscenePlayer>,
+    cameras: Query<&Transform, With<Camera>>,
+    characters: Query<&Transform, With<Handle<Character>>>,
+    maps: Query<&GameMap>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+    cutscene_assets: Res<Assets<Cutscene>>,
+    game_time: Res<GameTime>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+    let delta = game_time.delta_seconds();
+    let player = &mut *cutscene_player;
+
+    // Slide the letterbox bars towards where they should be
+    let target = if player.letterbox_shown { 1. } else { 0. };
+    if (player.letterbox - target).abs() > f32::EPSILON {
+        let step = delta / game_info.cutscenes.letterbox_time.max(f32::EPSILON);
+        player.letterbox = if target > player.letterbox {
+            (player.letterbox + step).min(target)
+        } else {
+            (player.letterbox - step).max(target)
+        };
+    }
+
+    let cutscene = match &player.cutscene {
+        Some(handle) => match cutscene_assets.get(handle) {
+            Some(cutscene) => cutscene,
+            // Wait for the cutscene to load
+            None => return,
+        },
+        None => return,
+    };
+    if player.step == 0 && player.step_elapsed == 0. {
+        player.input_locked = cutscene.lock_input;
+    }
+    player.step_elapsed += delta;
+
+    let camera_position = cameras
+        .single()
+        .map(|x| x.translation.truncate())
+        .unwrap_or_default();
+    let level_position = match (maps.single(), current_level) {
+        (Ok(map), Some(level)) => map.level(&level.0).map(|x| x.position),
+        _ => None,
+    }
+    .unwrap_or_default();
+
+    // Play through the steps that finish instantly in the same frame
+    loop {
+        let step = if let Some(step) = cutscene.steps.get(player.step) {
+            step
+        } else {
+            player.finish();
+            break;
+        };
+
+        let finished = match step {
+            CutsceneStep::Letterbox(shown) => {
+                player.letterbox_shown = *shown;
+                true
+            }
+            CutsceneStep::LockInput(locked) => {
+                player.input_locked = *locked;
+                true
+            }
+            CutsceneStep::Wait(duration) => player.step_elapsed >= *duration,
+            CutsceneStep::Pan { to, duration, hold } => {
+                let start = *player
+                    .pan_start
+                    .get_or_insert(player.camera.unwrap_or(camera_position));
+                let target = level_position + Vec2::new(to.0, to.1);
+                player.camera = Some(pan(start, target, player.step_elapsed, *duration));
+                player.step_elapsed >= duration + hold
+            }
+            CutsceneStep::ReturnCamera(duration) => {
+                let start = *player
+                    .pan_start
+                    .get_or_insert(player.camera.unwrap_or(camera_position));
+                let target = characters
+                    .iter()
+                    .next()
+                    .map_or(start, |x| x.translation.truncate());
+                player.camera = Some(pan(start, target, player.step_elapsed, *duration));
+                let finished = player.step_elapsed >= *duration;
+                if finished {
+                    player.camera = None;
+                }
+                finished
+            }
+        };
+        if !finished {
+            break;
+        }
+
+        player.step += 1;
+        player.step_elapsed = 0.;
+        player.pan_start = None;
+    }
+}
+
+/// Get where the camera is after panning from `start` to `target` for `elapsed` seconds out of
+/// `duration`, easing in and out
+fn pan(start: Vec2, target: Vec2, elapsed: f32, duration: f32) -> Vec2 {
+    let t = (elapsed / duration.max(f32::EPSILON)).clamp(0., 1.);
+    start.lerp(target, t * t * (3. - 2. * t))
+}
+
+/// Hold the camera where the current cutscene has put it, after it has followed the player
+pub fn move_cutscene_camera(
+    mut cameras: Query<&mut Transform, With<Camera>>,
+    cutscene_player: Res<CutscenePlayer>,
+) {
+    let position = if let Some(position) = cutscene_player.camera {
+        position
+    } else {
+        return;
+    };
+
+    for mut transform in cameras.iter_mut() {
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
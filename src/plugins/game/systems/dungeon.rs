@@ -0,0 +1,243 @@
+use rand::Rng;
+
+use super::{
+    gameplay::Health,
+    map_loading::{MapEnemiesLoaded, MapEntrancesLoaded, MapPlotsLoaded, MapPropsLoaded},
+    transition::ScreenFade,
+    *,
+};
+
+/// The prefix of prop interactions that give the player meta-currency, followed by the amount,
+/// i.e. `currency:5`
+const CURRENCY_INTERACTION_PREFIX: &str = "currency:";
+
+/// The interaction of props that open the unlocks menu, where meta-currency is spent
+const UNLOCKS_INTERACTION: &str = "unlocks";
+
+/// Component added to maps once their dungeon rooms have been linked, with the seed of the run
+/// and the rooms they were linked for
+pub struct DungeonLayout {
+    seed: Option<u64>,
+    rooms: Vec<String>,
+}
+
+/// Resource for whether the pause menu should open on the unlocks menu, which is set when the
+/// player interacts with an unlock station
+#[derive(Default)]
+pub struct UnlockStation {
+    pub open_requested: bool,
+}
+
+/// Link the dungeon rooms of maps for the current run, re-spawning their entrances whenever a new
+/// run starts or rooms are unlocked
+pub fn generate_dungeons(
+    mut commands: Commands,
+    mut maps: Query<(Entity, &mut GameMap, Option<&DungeonLayout>)>,
+    entrances: Query<(Entity, &Entrance)>,
+    run: Res<DungeonRun>,
+    meta: Res<MetaProgress>,
+    game_info: Option<Res<GameInfo>>,
+) {
+    let config = if let Some(config) = game_info.as_ref().and_then(|x| x.dungeon.as_ref()) {
+        config
+    } else {
+        return;
+    };
+
+    for (map_ent, mut map, layout) in maps.iter_mut() {
+        if layout.is_some() && !run.is_changed() && !meta.is_changed() {
+            continue;
+        }
+        let rooms = run.rooms(config, &meta);
+        if layout.map_or(false, |x| x.seed == run.seed && x.rooms == rooms) {
+            continue;
+        }
+
+        debug!(?rooms, seed = ?run.seed, "Linking dungeon rooms");
+        map.link_dungeon_rooms(&rooms);
+
+        // Spawn the entrances again with their new links
+        for (entrance_ent, entrance) in entrances.iter() {
+            if entrance.map == map_ent {
+                commands.entity(entrance_ent).despawn();
+            }
+        }
+        commands
+            .entity(map_ent)
+            .remove::<MapEntrancesLoaded>()
+            .insert(DungeonLayout {
+                seed: run.seed,
+                rooms,
+            });
+    }
+}
+
+/// Give the player meta-currency the first time they enter each room of a run
+pub fn reward_dungeon_rooms(
+    current_level: Option<Res<CurrentLevel>>,
+    layouts: Query<&DungeonLayout>,
+    mut run: ResMut<DungeonRun>,
+    mut meta: ResMut<MetaProgress>,
+    game_info: Res<GameInfo>,
+) {
+    let (current_level, config) = match (current_level, &game_info.dungeon) {
+        (Some(current_level), Some(config)) if current_level.is_changed() => {
+            (current_level, config)
+        }
+        _ => return,
+    };
+    let level = current_level.as_str();
+    let in_run = layouts
+        .iter()
+        .any(|x| x.seed == run.seed && x.rooms.iter().any(|x| x == level));
+
+    if in_run && !run.visited.iter().any(|x| x == level) {
+        run.visited.push(level.into());
+        meta.currency += config.room_reward;
+    }
+}
+
+/// Give the player the meta-currency from props with a `currency:` interaction, removing the props
+pub fn collect_currency_from_props(
+    mut commands: Commands,
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    props: Query<&PersistentId, With<Prop>>,
+    mut level_states: ResMut<LevelStates>,
+    mut meta: ResMut<MetaProgress>,
+) {
+    for event in interaction_events.iter() {
+        let amount = if let Some(amount) = event
+            .interaction
+            .strip_prefix(CURRENCY_INTERACTION_PREFIX)
+            .and_then(|x| x.parse::<u32>().ok())
+        {
+            amount
+        } else {
+            continue;
+        };
+
+        meta.currency += amount;
+
+        // Collected currency stays collected until the next run
+        if let Ok(id) = props.get(event.prop) {
+            level_states.entity_mut(&id.level, &id.id).removed = true;
+        }
+        commands.entity(event.prop).despawn_recursive();
+    }
+}
+
+/// Pause the game and open the unlocks menu when the player interacts with an unlock station
+pub fn use_unlock_stations(
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    mut state: ResMut<State<GameState>>,
+    mut physics_time: ResMut<PhysicsTime>,
+    mut unlock_station: ResMut<UnlockStation>,
+) {
+    for event in interaction_events.iter() {
+        if event.interaction != UNLOCKS_INTERACTION {
+            continue;
+        }
+
+        // The game may already be pausing this frame
+        if state.push(GameState::Paused).is_ok() {
+            physics_time.pause();
+        }
+        unlock_station.open_requested = true;
+    }
+}
+
+/// Buy the unlock with the given ID, if the player can afford it, giving them its items
+pub fn buy_unlock(world: &mut World, id: &str) {
+    let unlock = world
+        .get_resource::<GameInfo>()
+        .unwrap()
+        .dungeon
+        .as_ref()
+        .and_then(|x| x.unlocks.iter().find(|x| x.id == id))
+        .cloned();
+    let unlock = if let Some(unlock) = unlock {
+        unlock
+    } else {
+        return;
+    };
+
+    if !world
+        .get_resource_mut::<MetaProgress>()
+        .unwrap()
+        .buy(&unlock)
+    {
+        return;
+    }
+    let mut inventory = world.get_resource_mut::<Inventory>().unwrap();
+    for item in &unlock.items {
+        inventory.add(item, 1);
+    }
+    world.get_resource_mut::<SaveRequest>().unwrap().0 = true;
+}
+
+/// When the player dies during a game with a hub, fade out and send them back to the hub with a
+/// new run, instead of ending the game
+#[allow(clippy::too_many_arguments)]
+pub fn return_to_hub(
+    mut returning: Local<bool>,
+    mut commands: Commands,
+    mut characters: Query<&mut Health, With<Handle<Character>>>,
+    map_entities: Query<Entity, (Or<(With<Enemy>, With<Prop>)>, With<PersistentId>)>,
+    maps: Query<Entity, With<GameMap>>,
+    mut screen_fade: ResMut<ScreenFade>,
+    mut run: ResMut<DungeonRun>,
+    mut level_states: ResMut<LevelStates>,
+    mut rng: ResMut<GameRng>,
+    mut load_level_events: EventWriter<LoadLevelEvent>,
+    mut save_request: ResMut<SaveRequest>,
+    game_info: Res<GameInfo>,
+) {
+    let (config, hub) = match &game_info.dungeon {
+        Some(config) => match &config.hub {
+            Some(hub) => (config, hub),
+            None => return,
+        },
+        None => return,
+    };
+
+    if !*returning {
+        if characters.iter_mut().any(|x| x.current == 0) {
+            *returning = true;
+            screen_fade.fade_out();
+        }
+        return;
+    }
+    if !screen_fade.is_black() {
+        return;
+    }
+
+    // Start a new run with fresh rooms
+    *run = DungeonRun {
+        seed: Some(rng.gen()),
+        visited: Vec::new(),
+    };
+    for room in config.all_rooms() {
+        level_states.0.remove(room);
+    }
+
+    // Spawn the map enemies and props again, so that the ones in the rooms come back
+    for ent in map_entities.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+    for map in maps.iter() {
+        commands
+            .entity(map)
+            .remove::<MapEnemiesLoaded>()
+            .remove::<MapPropsLoaded>()
+            .remove::<MapPlotsLoaded>();
+    }
+
+    for mut health in characters.iter_mut() {
+        health.current = health.max;
+    }
+    load_level_events.send(LoadLevelEvent { level: hub.clone() });
+    save_request.0 = true;
+
+    *returning = false;
+    screen_fade.fade_in();
+}
@@ -0,0 +1,106 @@
+use super::{game_time::GameTime, *};
+
+/// The prefix of prop interactions that show an emote over the prop, followed by the name of the
+/// icon from the UI theme's `emotes`, i.e. `emote:heart`
+const EMOTE_INTERACTION_PREFIX: &str = "emote:";
+
+/// Component for the [`Emote`] an entity is showing in a bubble over it, which is drawn by the HUD
+pub struct Emoting {
+    pub emote: Emote,
+    timer: Timer,
+}
+
+/// Component for the alert state of an enemy that was last shown with an emote
+pub struct EmotedAlertState(AlertState);
+
+/// Start showing the emotes sent with [`EmoteEvent`]s, and stop showing the ones that are over
+pub fn show_emotes(
+    mut commands: Commands,
+    mut emote_events: EventReader<EmoteEvent>,
+    mut emoting: Query<(Entity, &mut Emoting)>,
+    entities: Query<(), With<Transform>>,
+    game_time: Res<GameTime>,
+) {
+    for (entity, mut emoting) in emoting.iter_mut() {
+        if emoting.timer.tick(game_time.delta()).finished() {
+            commands.entity(entity).remove::<Emoting>();
+        }
+    }
+
+    for event in emote_events.iter() {
+        // The entity may have been despawned since the event was sent
+        if entities.get(event.entity).is_err() {
+            continue;
+        }
+        commands.entity(event.entity).insert(Emoting {
+            emote: event.emote.clone(),
+            timer: Timer::from_seconds(event.emote.duration, false),
+        });
+    }
+}
+
+/// Show an emote over the props that the player triggers with an emote interaction
+pub fn emote_from_props(
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    mut emote_events: EventWriter<EmoteEvent>,
+) {
+    for event in interaction_events.iter() {
+        let icon = if let Some(icon) = event.interaction.strip_prefix(EMOTE_INTERACTION_PREFIX) {
+            icon
+        } else {
+            continue;
+        };
+
+        emote_events.send(EmoteEvent {
+            entity: event.prop,
+            emote: Emote {
+                icon: Some(icon.into()),
+                text: None,
+                duration: default_emote_duration(),
+            },
+        });
+    }
+}
+
+/// Show the emotes from the game's [`EmoteConfig`] over enemies when their alert state changes
+pub fn emote_alert_states(
+    mut commands: Commands,
+    mut enemies: Query<(Entity, &Alertness, Option<&mut EmotedAlertState>), With<Enemy>>,
+    mut emote_events: EventWriter<EmoteEvent>,
+    game_info: Option<Res<GameInfo>>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+    let config = &game_info.emotes;
+
+    for (enemy_ent, alertness, emoted_state) in enemies.iter_mut() {
+        let mut emoted_state = if let Some(emoted_state) = emoted_state {
+            emoted_state
+        } else {
+            commands
+                .entity(enemy_ent)
+                .insert(EmotedAlertState(alertness.state));
+            continue;
+        };
+        if emoted_state.0 == alertness.state {
+            continue;
+        }
+        emoted_state.0 = alertness.state;
+
+        let emote = match alertness.state {
+            AlertState::Unaware => None,
+            AlertState::Suspicious => config.suspicious.as_ref(),
+            AlertState::Alert => config.alert.as_ref(),
+            AlertState::Searching => config.searching.as_ref(),
+        };
+        if let Some(emote) = emote {
+            emote_events.send(EmoteEvent {
+                entity: enemy_ent,
+                emote: emote.clone(),
+            });
+        }
+    }
+}
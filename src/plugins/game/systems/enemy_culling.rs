@@ -0,0 +1,55 @@
+use super::*;
+
+/// Freeze enemies that are far away from the player, and wake them back up with the velocity they
+/// had when the player gets close again
+///
+/// No more than the `max-active` enemies closest to the player are awake at once.
+pub fn cull_distant_enemies(
+    mut commands: Commands,
+    mut enemies: Query<(Entity, &Transform, &mut Velocity, Option<&Dormant>), With<Enemy>>,
+    characters: Query<&Transform, With<Handle<Character>>>,
+    game_info: Res<GameInfo>,
+) {
+    let character_pos = if let Ok(transform) = characters.single() {
+        transform.translation.truncate()
+    } else {
+        return;
+    };
+    let config = &game_info.enemy_culling;
+
+    // Find the enemies in range, keeping awake enemies awake until they are past the margin
+    let mut in_range = enemies
+        .iter_mut()
+        .filter_map(|(ent, transform, _, dormant)| {
+            let distance = transform.translation.truncate().distance(character_pos);
+            let radius = if dormant.is_some() {
+                config.activation_radius
+            } else {
+                config.activation_radius + config.margin
+            };
+            (distance <= radius).then(|| (ent, distance))
+        })
+        .collect::<Vec<_>>();
+    in_range.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    let active = in_range
+        .into_iter()
+        .take(config.max_active)
+        .map(|(ent, _)| ent)
+        .collect::<HashSet<_>>();
+
+    for (ent, _, mut velocity, dormant) in enemies.iter_mut() {
+        match (dormant, active.contains(&ent)) {
+            (Some(dormant), true) => {
+                *velocity = Velocity::from_linear(dormant.velocity);
+                commands.entity(ent).remove::<Dormant>();
+            }
+            (None, false) => {
+                commands.entity(ent).insert(Dormant {
+                    velocity: velocity.linear,
+                });
+                *velocity = Velocity::default();
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use super::{game_init::StartMenuMusicHandle, gameplay::HealthFeedback, perf_hud::PerfHud, *};
+use crate::EngineConfig;
+
+/// Apply the `debug` and `mute` engine options when they are changed or when the things they
+/// affect are spawned
+pub fn apply_engine_config(
+    mut applied: Local<Option<(bool, bool)>>,
+    engine_config: Res<EngineConfig>,
+    game_info: Option<ResMut<GameInfo>>,
+    mut perf_hud: ResMut<PerfHud>,
+    state: Res<State<GameState>>,
+    level_music: Option<Res<CurrentLevelMusic>>,
+    menu_music: Option<Res<StartMenuMusicHandle>>,
+    mut health_feedback: ResMut<HealthFeedback>,
+    mut sound_controller: SoundController,
+) {
+    let (was_debug, was_muted) = applied.unwrap_or_default();
+    let first_run = applied.is_none();
+    *applied = Some((engine_config.debug, engine_config.mute));
+
+    // Show the debug rendering when debug is turned on, and hide it again when it's turned off
+    if engine_config.debug != was_debug {
+        perf_hud.visible = engine_config.debug;
+    }
+    if let Some(mut game_info) = game_info {
+        if engine_config.debug != was_debug || (engine_config.debug && game_info.is_added()) {
+            game_info.debug_rendering.navmesh = engine_config.debug;
+            game_info.debug_rendering.vision_cones = engine_config.debug;
+        }
+    }
+
+    let play_music = |sound_controller: &mut SoundController, sound| {
+        sound_controller.play_sound_with_settings(
+            sound,
+            PlaySoundSettings::new().loop_start(LoopStart::Custom(0.0)),
+        );
+    };
+
+    if engine_config.mute {
+        // Stop the music whenever it starts
+        if let Some(music) = &level_music {
+            if !was_muted || music.is_changed() {
+                sound_controller.stop_sound(music.sound);
+            }
+        }
+        if let Some(music) = &menu_music {
+            if !was_muted || music.is_changed() {
+                sound_controller.stop_sound(music.0);
+            }
+        }
+        if let Some(sound) = health_feedback.heartbeat.take() {
+            sound_controller.stop_sound(sound);
+        }
+
+    // Start the music back up when the game is un-muted
+    } else if was_muted && !first_run {
+        match state.current() {
+            GameState::StartMenu => {
+                if let Some(music) = &menu_music {
+                    play_music(&mut sound_controller, music.0);
+                }
+            }
+            GameState::Playing | GameState::Paused | GameState::PhotoMode | GameState::Battle => {
+                if let Some(music) = &level_music {
+                    play_music(&mut sound_controller, music.sound);
+                }
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use super::{
+    gameplay::Health,
+    movement::{SpeedModifier, SpeedModifierSource, SpeedModifiers},
+    *,
+};
+
+/// The prefix of prop interactions that equip an item, followed by the path to the item, i.e.
+/// `equip:items/sword.item.yml`
+const EQUIP_INTERACTION_PREFIX: &str = "equip:";
+
+/// The stats of a character, from its character file plus the changes made by its equipment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub max_health: u32,
+    pub max_energy: u32,
+    /// The amount added to the character's walking speed, in pixels per second
+    pub walk_speed_bonus: f32,
+    /// Extra damage done by the character's abilities
+    pub attack: u32,
+    /// The amount that damage taken by the character is reduced by. Damage is never reduced
+    /// below 1.
+    pub defense: u32,
+}
+
+impl Stats {
+    /// Get the stats of a character without any equipment
+    pub fn base(character: &Character) -> Self {
+        Self {
+            max_health: character.max_health,
+            max_energy: character.max_energy,
+            walk_speed_bonus: 0.,
+            attack: 0,
+            defense: 0,
+        }
+    }
+
+    /// Reduce damage taken by the character by its defense
+    pub fn reduce_damage(&self, damage: u32) -> u32 {
+        damage.saturating_sub(self.defense).max(1)
+    }
+}
+
+/// The items a character is wearing
+pub struct Equipment {
+    /// The equipped items, in the order they were equipped. Items replaced by a later item in
+    /// the same slot are removed once both have loaded.
+    pub items: Vec<Handle<Item>>,
+}
+
+impl Equipment {
+    /// Get the loaded items that are being worn, in the order their sprites are drawn
+    pub fn worn<'a>(&self, item_assets: &'a Assets<Item>) -> Vec<(&Handle<Item>, &'a Item)> {
+        let mut worn: Vec<(&Handle<Item>, &Item)> = Vec::new();
+        for handle in &self.items {
+            if let Some(item) = item_assets.get(handle) {
+                // Items without a slot can't be worn
+                if item.slot.is_none() {
+                    continue;
+                }
+                worn.retain(|(_, x)| x.slot != item.slot);
+                worn.push((handle, item));
+            }
+        }
+        worn.sort_by_key(|(_, item)| item.slot);
+
+        worn
+    }
+}
+
+/// A sprite drawn over a character for an item it is wearing
+pub struct EquipmentLayer {
+    pub item: Handle<Item>,
+}
+
+/// Apply the stat changes of the items characters are wearing, and draw the items over them
+pub fn update_equipment(
+    mut commands: Commands,
+    mut characters: Query<(
+        Entity,
+        &Handle<Character>,
+        &Handle<SpriteSheet>,
+        &mut Equipment,
+        &mut Stats,
+        &mut Health,
+        &mut SpeedModifiers,
+        Option<&mut Energy>,
+        Option<&Children>,
+    )>,
+    layers: Query<&EquipmentLayer>,
+    character_assets: Res<Assets<Character>>,
+    item_assets: Res<Assets<Item>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (
+        ent,
+        character_handle,
+        sprite_sheet,
+        mut equipment,
+        mut stats,
+        mut health,
+        mut speed_modifiers,
+        energy,
+        children,
+    ) in characters.iter_mut()
+    {
+        let character = if let Some(character) = character_assets.get(character_handle) {
+            character
+        } else {
+            continue;
+        };
+        let worn = equipment.worn(&item_assets);
+
+        // Add up the stats
+        let mut new_stats = Stats::base(character);
+        let add = |stat: u32, modifier: i32| (stat as i32 + modifier).max(0) as u32;
+        for (_, item) in &worn {
+            let modifiers = &item.stats;
+            new_stats.max_health = add(new_stats.max_health, modifiers.max_health);
+            new_stats.max_energy = add(new_stats.max_energy, modifiers.max_energy);
+            new_stats.walk_speed_bonus += modifiers.walk_speed;
+            new_stats.attack = add(new_stats.attack, modifiers.attack);
+            new_stats.defense = add(new_stats.defense, modifiers.defense);
+        }
+        new_stats.max_health = new_stats.max_health.max(1);
+
+        // Update the stats only when they change, to avoid triggering change detection
+        if *stats != new_stats {
+            if health.max != new_stats.max_health {
+                health.max = new_stats.max_health;
+                health.current = health.current.min(health.max);
+            }
+            if let Some(mut energy) = energy {
+                energy.max = new_stats.max_energy as f32;
+                energy.current = energy.current.min(energy.max);
+            }
+            let walk_speed = Some(new_stats.walk_speed_bonus)
+                .filter(|x| *x != 0.)
+                .map(SpeedModifier::bonus);
+            speed_modifiers.set(SpeedModifierSource::Equipment, walk_speed);
+            *stats = new_stats;
+        }
+
+        // Replace the item sprites if the worn items have changed
+        let sprite_items = worn
+            .iter()
+            .filter(|(_, item)| item.sprite_sheet.is_some())
+            .map(|(handle, _)| (*handle).clone())
+            .collect::<Vec<_>>();
+        let layer_children = children
+            .map(|children| {
+                children
+                    .iter()
+                    .filter(|x| layers.get(**x).is_ok())
+                    .copied()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let layer_items = layer_children
+            .iter()
+            .filter_map(|x| layers.get(*x).ok())
+            .map(|x| x.item.clone())
+            .collect::<Vec<_>>();
+        if sprite_items != layer_items {
+            for layer in layer_children {
+                commands.entity(layer).despawn_recursive();
+            }
+            commands.entity(ent).with_children(|parent| {
+                for (i, (handle, item)) in worn
+                    .iter()
+                    .filter(|(_, item)| item.sprite_sheet.is_some())
+                    .enumerate()
+                {
+                    parent
+                        .spawn_bundle(SpriteBundle {
+                            image: asset_server.load(item.sprite_sheet.as_deref().unwrap()),
+                            // Draw the layers just in front of the character, in slot order
+                            transform: Transform::from_xyz(0., 0., 0.001 * (i + 1) as f32),
+                            ..Default::default()
+                        })
+                        .insert(sprite_sheet.clone())
+                        .insert(EquipmentLayer {
+                            item: (*handle).clone(),
+                        });
+                }
+            });
+        }
+
+        // Forget items that have been replaced by later items in the same slot
+        if worn.len() < equipment.items.len()
+            && equipment.items.iter().all(|x| item_assets.get(x).is_some())
+        {
+            let worn = worn.iter().map(|(x, _)| (*x).clone()).collect::<Vec<_>>();
+            equipment.items.retain(|x| worn.contains(x));
+        }
+    }
+}
+
+/// Show the same frame of the wearer's sprite sheet on its equipment layers
+pub fn animate_equipment_layers(
+    mut layers: Query<(&Parent, &mut Handle<SpriteSheet>, &mut Sprite), With<EquipmentLayer>>,
+    wearers: Query<(&Handle<SpriteSheet>, &Sprite), Without<EquipmentLayer>>,
+) {
+    for (parent, mut sprite_sheet, mut sprite) in layers.iter_mut() {
+        let (wearer_sprite_sheet, wearer_sprite) = if let Ok(wearer) = wearers.get(parent.0) {
+            wearer
+        } else {
+            continue;
+        };
+
+        // The sprite sheet asset holds the current frame, so sharing it keeps the layers in sync
+        if *sprite_sheet != *wearer_sprite_sheet {
+            *sprite_sheet = wearer_sprite_sheet.clone();
+        }
+        if sprite.flip_x != wearer_sprite.flip_x {
+            sprite.flip_x = wearer_sprite.flip_x;
+        }
+    }
+}
+
+/// Move an item from the player's inventory to their equipment, putting the item it replaces back
+/// in the inventory
+pub fn equip_from_inventory(world: &mut World, path: &str) {
+    let asset_server = world.get_resource::<AssetServer>().unwrap().clone();
+    let handle: Handle<Item> = asset_server.load(path);
+    let slot = match world.get_resource::<Assets<Item>>().unwrap().get(&handle) {
+        Some(Item {
+            slot: Some(slot), ..
+        }) => *slot,
+        _ => return,
+    };
+
+    // Find the item in the same slot
+    let mut query = world.query_filtered::<&Equipment, With<Handle<Character>>>();
+    let replaced = if let Some(equipment) = query.iter(world).next() {
+        let item_assets = world.get_resource::<Assets<Item>>().unwrap();
+        equipment
+            .worn(item_assets)
+            .into_iter()
+            .find(|(_, item)| item.slot == Some(slot))
+            .and_then(|(x, _)| asset_server.get_handle_path(x))
+            .map(|x| x.path().to_string_lossy().into_owned())
+    } else {
+        return;
+    };
+
+    let mut inventory = world.get_resource_mut::<Inventory>().unwrap();
+    if !inventory.remove(path, 1) {
+        return;
+    }
+    if let Some(replaced) = replaced {
+        inventory.add(&replaced, 1);
+    }
+
+    let mut query = world.query_filtered::<&mut Equipment, With<Handle<Character>>>();
+    if let Some(mut equipment) = query.iter_mut(world).next() {
+        equipment.items.push(handle);
+    }
+}
+
+/// Equip the items from props with an `equip:` interaction, removing the props
+pub fn equip_items_from_props(
+    mut commands: Commands,
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    mut characters: Query<&mut Equipment, With<Handle<Character>>>,
+    props: Query<&PersistentId, With<Prop>>,
+    mut level_states: ResMut<LevelStates>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in interaction_events.iter() {
+        let item = if let Some(item) = event.interaction.strip_prefix(EQUIP_INTERACTION_PREFIX) {
+            item
+        } else {
+            continue;
+        };
+        let mut equipment = if let Ok(equipment) = characters.single_mut() {
+            equipment
+        } else {
+            continue;
+        };
+
+        equipment.items.push(asset_server.load(item));
+
+        // Picked up items stay picked up
+        if let Ok(id) = props.get(event.prop) {
+            level_states.entity_mut(&id.level, &id.id).removed = true;
+        }
+        commands.entity(event.prop).despawn_recursive();
+    }
+}
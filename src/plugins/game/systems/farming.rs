@@ -0,0 +1,164 @@
+use super::*;
+
+/// The interaction of the props spawned for farming plots
+pub const PLOT_INTERACTION: &str = "plot";
+
+/// Resource holding the crops listed in the game info, by the path they are listed with
+#[derive(Default)]
+pub struct Crops(pub Vec<(String, Handle<Crop>)>);
+
+impl Crops {
+    /// Get a crop by its path
+    pub fn get<'a>(&self, crop_assets: &'a Assets<Crop>, path: &str) -> Option<&'a Crop> {
+        self.0
+            .iter()
+            .find(|(x, _)| x == path)
+            .and_then(|(_, handle)| crop_assets.get(handle))
+    }
+}
+
+/// The sprite of the crop growing in a [`Plot`], spawned as a child of the plot
+pub struct PlotCrop {
+    /// The path to the sprite image
+    pub sprite: String,
+}
+
+/// Load the crops listed in the game info
+pub fn load_crops(
+    game_info: Option<Res<GameInfo>>,
+    asset_server: Res<AssetServer>,
+    mut crops: ResMut<Crops>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+    if !game_info.is_changed() {
+        return;
+    }
+
+    crops.0 = game_info
+        .crops
+        .iter()
+        .map(|x| (x.clone(), asset_server.load(x.as_str())))
+        .collect();
+}
+
+/// Plant seeds in empty plots and harvest the crops that are ready when the player interacts with
+/// them
+///
+/// The first crop in the game info that the player has the seed for is planted.
+pub fn tend_plots(
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    plots: Query<&PersistentId, With<Plot>>,
+    crops: Res<Crops>,
+    crop_assets: Res<Assets<Crop>>,
+    clock: Res<GameClock>,
+    mut inventory: ResMut<Inventory>,
+    mut level_states: ResMut<LevelStates>,
+) {
+    for event in interaction_events.iter() {
+        if event.interaction != PLOT_INTERACTION {
+            continue;
+        }
+        let id = if let Ok(id) = plots.get(event.prop) {
+            id
+        } else {
+            continue;
+        };
+
+        let planted = level_states
+            .entity(&id.level, &id.id)
+            .and_then(|x| x.crop.clone());
+        match planted {
+            // Plant the first crop the player has a seed for
+            None => {
+                let crop = crops.0.iter().find(|(_, handle)| {
+                    crop_assets
+                        .get(handle)
+                        .map(|x| inventory.count(&x.seed) > 0)
+                        .unwrap_or(false)
+                });
+                let (path, handle) = if let Some(crop) = crop {
+                    crop
+                } else {
+                    debug!("No seeds to plant");
+                    continue;
+                };
+
+                inventory.remove(&crop_assets.get(handle).unwrap().seed, 1);
+                level_states.entity_mut(&id.level, &id.id).crop = Some(PlantedCrop {
+                    crop: path.clone(),
+                    planted_at: clock.total_hours(),
+                });
+            }
+            // Harvest the crop if it's ready
+            Some(planted) => {
+                let crop = if let Some(crop) = crops.get(&crop_assets, &planted.crop) {
+                    crop
+                } else {
+                    continue;
+                };
+                if !crop.is_ready(clock.total_hours() - planted.planted_at) {
+                    continue;
+                }
+
+                inventory.add(&crop.harvest, crop.harvest_count);
+                level_states.entity_mut(&id.level, &id.id).crop = None;
+            }
+        }
+    }
+}
+
+/// Show the growth stage of the crop planted in each plot
+pub fn update_plot_crops(
+    mut commands: Commands,
+    plots: Query<(Entity, &PersistentId, Option<&Children>), With<Plot>>,
+    plot_crops: Query<&PlotCrop>,
+    crops: Res<Crops>,
+    crop_assets: Res<Assets<Crop>>,
+    clock: Res<GameClock>,
+    level_states: Res<LevelStates>,
+    asset_server: Res<AssetServer>,
+) {
+    for (plot_ent, id, children) in plots.iter() {
+        let sprite = level_states
+            .entity(&id.level, &id.id)
+            .and_then(|x| x.crop.as_ref())
+            .and_then(|planted| {
+                let crop = crops.get(&crop_assets, &planted.crop)?;
+                let stage = crop.stage_at(clock.total_hours() - planted.planted_at);
+                Some(crop.stages[stage].sprite.clone())
+            });
+
+        // Replace the crop sprite if the stage has changed
+        let crop_children = children
+            .map(|children| {
+                children
+                    .iter()
+                    .filter_map(|x| plot_crops.get(*x).ok().map(|crop| (*x, crop)))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let current_sprite = crop_children.first().map(|(_, x)| &x.sprite);
+        if current_sprite == sprite.as_ref() {
+            continue;
+        }
+
+        for (ent, _) in crop_children {
+            commands.entity(ent).despawn_recursive();
+        }
+        if let Some(sprite) = sprite {
+            commands.entity(plot_ent).with_children(|plot| {
+                plot.spawn_bundle(SpriteBundle {
+                    image: asset_server.load(sprite.as_str()),
+                    // Draw the crop just in front of the plot
+                    transform: Transform::from_xyz(0., 0., 0.001),
+                    ..Default::default()
+                })
+                .insert(PlotCrop { sprite });
+            });
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use bevy::{
+    asset::{Asset, HandleUntyped, LoadState},
+    prelude::{
+        error, AssetServer, Commands, EventReader, EventWriter, Handle, Res, ResMut, State, World,
+    },
+};
+use bevy_retrograde::{prelude::UiTree, ui::raui::prelude::*};
+
+use crate::plugins::game::{assets::GameInfo, events::FatalErrorEvent};
+
+use super::GameState;
+
+/// Assets that the game can't run without, which show the [`fatal_error_screen`] if they fail to
+/// load
+#[derive(Default)]
+pub struct CriticalAssets(Vec<HandleUntyped>);
+
+impl CriticalAssets {
+    /// Show the error screen if the asset fails to load
+    pub fn watch<T: Asset>(&mut self, handle: &Handle<T>) {
+        let handle = handle.clone_weak_untyped();
+        if !self.0.contains(&handle) {
+            self.0.push(handle);
+        }
+    }
+}
+
+/// The error that stopped the game
+#[derive(Default, Clone, Debug)]
+pub struct FatalError {
+    /// The error message
+    pub message: String,
+    /// The path of the asset that caused the error, if any
+    pub asset: Option<String>,
+}
+
+/// Send a [`FatalErrorEvent`] for critical assets that fail to load
+pub fn check_critical_assets(
+    mut critical_assets: ResMut<CriticalAssets>,
+    asset_server: Res<AssetServer>,
+    mut error_events: EventWriter<FatalErrorEvent>,
+) {
+    // Stop watching assets once they have loaded or failed
+    critical_assets
+        .0
+        .retain(|handle| match asset_server.get_load_state(handle) {
+            LoadState::Failed => {
+                let path = asset_server
+                    .get_handle_path(handle)
+                    .map(|x| x.path().display().to_string());
+                error_events.send(FatalErrorEvent {
+                    message: "Could not load asset".into(),
+                    asset: path,
+                });
+                false
+            }
+            LoadState::Loaded => false,
+            _ => true,
+        });
+}
+
+/// Stop the game and show the error screen when a fatal error happens
+pub fn handle_fatal_errors(
+    mut error_events: EventReader<FatalErrorEvent>,
+    mut state: ResMut<State<GameState>>,
+    mut ui_tree: ResMut<UiTree>,
+    mut commands: Commands,
+    game_info: Option<Res<GameInfo>>,
+) {
+    // Only show the first error, the rest are usually caused by it
+    let event = if let Some(event) = error_events.iter().next() {
+        event
+    } else {
+        return;
+    };
+    if state.current() == &GameState::Error {
+        return;
+    }
+
+    error!(message = %event.message, asset = ?event.asset, "Fatal error");
+
+    // Without the game info we don't have a font to show the error with, so on web we let the
+    // player know in a popup instead of leaving them with a frozen screen
+    #[cfg(wasm)]
+    if game_info.is_none() {
+        if let Some(window) = web_sys::window() {
+            let message = match &event.asset {
+                Some(asset) => format!("{}: {}", event.message, asset),
+                None => event.message.clone(),
+            };
+            window.alert_with_message(&message).ok();
+        }
+    }
+    #[cfg(not(wasm))]
+    let _ = game_info;
+
+    commands.insert_resource(FatalError {
+        message: event.message.clone(),
+        asset: event.asset.clone(),
+    });
+    *ui_tree = UiTree(widget! {
+        (fatal_error_screen)
+    });
+    state
+        .overwrite_replace(GameState::Error)
+        .expect("Could not transition to error state");
+}
+
+/// The screen showing the error that stopped the game
+pub fn fatal_error_screen(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let error = world
+        .get_resource::<FatalError>()
+        .cloned()
+        .unwrap_or_default();
+    let font = world
+        .get_resource::<GameInfo>()
+        .map(|x| x.ui_theme.default_font.clone())
+        .unwrap_or_default();
+
+    let mut text = error.message;
+    if let Some(asset) = error.asset {
+        text.push_str(&format!("\n\nAsset: {}", asset));
+    }
+
+    make_widget!(content_box)
+        // Add a black background
+        .listed_slot(make_widget!(image_box).with_props(ImageBoxProps {
+            material: ImageBoxMaterial::Color(ImageBoxColor {
+                color: Color {
+                    r: 0.,
+                    g: 0.,
+                    b: 0.,
+                    a: 1.,
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        }))
+        .listed_slot(
+            make_widget!(text_box)
+                .with_props(TextBoxProps {
+                    color: Color {
+                        r: 1.,
+                        g: 0.3,
+                        b: 0.3,
+                        a: 1.,
+                    },
+                    text,
+                    font: TextBoxFont {
+                        name: font,
+                        size: 1.,
+                    },
+                    horizontal_align: TextBoxHorizontalAlign::Center,
+                    vertical_align: TextBoxVerticalAlign::Middle,
+                    ..Default::default()
+                })
+                .with_props(ContentBoxItemLayout {
+                    margin: 10.0.into(),
+                    ..Default::default()
+                }),
+        )
+        .into()
+}
@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use bevy::core::FixedTimesteps;
+
+use super::*;
+
+/// The length of a gameplay simulation step in seconds
+pub const FIXED_TIMESTEP: f64 = 1. / 60.;
+
+/// The label of the fixed timestep run criteria, used to look up its state in [`FixedTimesteps`]
+pub const FIXED_TIMESTEP_LABEL: &str = "gameplay";
+
+/// The stages added by the game
+#[derive(Clone, Debug, PartialEq, Eq, Hash, StageLabel)]
+pub enum GameStage {
+    /// Runs gameplay simulation such as character control, AI, and damage at [`FIXED_TIMESTEP`]
+    /// so that the game plays at the same speed regardless of the frame rate
+    FixedUpdate,
+}
+
+/// The real duration of a gameplay simulation step
+///
+/// Gameplay systems should use [`GameTime::fixed_delta`][super::game_time::GameTime::fixed_delta]
+/// instead, which is slowed down along with the game speed.
+pub fn fixed_delta() -> Duration {
+    Duration::from_secs_f64(FIXED_TIMESTEP)
+}
+
+/// The control events held since the last simulation step
+///
+/// Control events are sent every frame, but the fixed update stage may run any number of times
+/// per frame, so the controls are collected here for the simulation to read. The controls of
+/// frames that no simulation step ran in are kept for the next step, so that controls that are only
+/// sent for one frame, like attacking, aren't missed.
+#[derive(Default, Debug)]
+pub struct HeldControls {
+    pub controls: HashSet<ControlEvent>,
+    /// Whether a simulation step has run since the controls were collected
+    stepped: bool,
+}
+
+/// Collect this frame's control events into [`HeldControls`]
+pub fn collect_held_controls(
+    mut control_events: EventReader<ControlEvent>,
+    mut held_controls: ResMut<HeldControls>,
+) {
+    if held_controls.stepped {
+        held_controls.controls.clear();
+        held_controls.stepped = false;
+    }
+    held_controls
+        .controls
+        .extend(control_events.iter().copied());
+}
+
+/// Mark the [`HeldControls`] as read by the simulation, so that the next frame's controls replace
+/// them
+pub fn finish_held_controls(mut held_controls: ResMut<HeldControls>) {
+    held_controls.stepped = true;
+}
+
+/// Smooths the rendering of an entity that is moved by the fixed update stage or the physics by
+/// interpolating between its positions at the end of the last two simulation steps
+///
+/// Only the x and y position are interpolated, the z position is left to depth sorting such as
+/// [`YSort`]. The entity is put back at its real position before the next frame runs, so that the
+/// simulation and the physics never see the interpolated position.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct FixedStepInterpolation {
+    /// The simulated translation from the step before last
+    pub previous: Vec3,
+    /// The simulated translation from the last step
+    pub current: Vec3,
+    /// The real translation of the entity, from before it was moved for rendering. Physics bodies
+    /// keep moving between simulation steps, so this can be ahead of `current`.
+    pub simulated: Vec3,
+}
+
+impl FixedStepInterpolation {
+    /// Create an interpolation that starts at the given translation
+    pub fn new(translation: Vec3) -> Self {
+        Self {
+            previous: translation,
+            current: translation,
+            simulated: translation,
+        }
+    }
+}
+
+/// Put interpolated entities back at their real position before the simulation and the physics
+/// run
+pub fn restore_fixed_step_transforms(
+    mut interpolated: Query<(&FixedStepInterpolation, &mut Transform)>,
+) {
+    for (interpolation, mut transform) in interpolated.iter_mut() {
+        transform.translation.x = interpolation.simulated.x;
+        transform.translation.y = interpolation.simulated.y;
+    }
+}
+
+/// Record the simulated position of interpolated entities at the end of a simulation step
+pub fn record_fixed_step_transforms(
+    mut interpolated: Query<(&mut FixedStepInterpolation, &Transform)>,
+) {
+    for (mut interpolation, transform) in interpolated.iter_mut() {
+        interpolation.previous = interpolation.current;
+        interpolation.current = transform.translation;
+    }
+}
+
+/// Move interpolated entities between their last two simulated positions for rendering, based on
+/// how far we are into the next simulation step
+///
+/// Entities that have moved farther than the configured snap distance since the last step, i.e.
+/// when they are teleported, jump straight to their new position instead.
+pub fn interpolate_fixed_step_transforms(
+    mut interpolated: Query<(&mut FixedStepInterpolation, &mut Transform)>,
+    fixed_timesteps: Res<FixedTimesteps>,
+    state: Res<State<GameState>>,
+    game_info: Option<Res<GameInfo>>,
+) {
+    let t = fixed_timesteps
+        .get(FIXED_TIMESTEP_LABEL)
+        .map(|x| x.overstep_percentage() as f32)
+        .unwrap_or(1.)
+        .clamp(0., 1.);
+    let snap_distance = game_info
+        .map(|x| x.physics_smoothing.snap_distance)
+        .unwrap_or_default();
+
+    // The simulation doesn't run while the game is paused, so hold entities still where the last
+    // step left them
+    let playing = state.current() == &GameState::Playing;
+
+    for (mut interpolation, mut transform) in interpolated.iter_mut() {
+        let interpolation = &mut *interpolation;
+        interpolation.simulated = transform.translation;
+
+        if interpolation
+            .simulated
+            .truncate()
+            .distance(interpolation.current.truncate())
+            > snap_distance
+        {
+            interpolation.previous = interpolation.simulated;
+            interpolation.current = interpolation.simulated;
+            continue;
+        }
+        if !playing {
+            interpolation.previous = interpolation.current;
+        }
+
+        let translation = interpolation.previous.lerp(interpolation.current, t);
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+    }
+}
@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use bevy::utils::HashMap;
+
+use super::{game_time::GameTime, *};
+
+/// Component for a footprint left on the ground, which is put back in its [`EntityPool`] when its
+/// timer finishes
+pub struct Footprint {
+    timer: Timer,
+}
+
+/// Component for where a character or enemy last left a footprint
+pub struct FootprintTrail {
+    last: Vec2,
+    /// Whether the last footprint was flipped, so that the footprints alternate between the left
+    /// and right foot
+    flipped: bool,
+}
+
+/// Component for the images of the footprints left on the tiles in each level of a map
+pub struct MapFootprintTiles(HashMap<String, Vec<FootprintLayer>>);
+
+/// The tiles in a layer that footprints are left on
+struct FootprintLayer {
+    /// The position of the layer in the world
+    offset: Vec2,
+    grid_size: f32,
+    /// The footprint image of each tile, by its grid cell
+    tiles: HashMap<IVec2, String>,
+}
+
+/// Find the tiles in the map that footprints are left on
+pub fn load_footprint_tiles(
+    mut commands: Commands,
+    maps: Query<(Entity, &GameMap), Without<MapFootprintTiles>>,
+    game_info: Option<Res<GameInfo>>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+
+    for (map_ent, map) in maps.iter() {
+        let mut levels = HashMap::default();
+        for level in &map.levels {
+            let mut layers = Vec::new();
+            for layer in &level.layers {
+                let grid_size = layer.grid_size.max(1) as f32;
+                let tiles = layer
+                    .tiles
+                    .iter()
+                    .filter_map(|tile| {
+                        let footprint = map
+                            .tilesets
+                            .get(&tile.tileset)?
+                            .tile_metadata(tile.id, &game_info.tile_tags)?
+                            .footprint?;
+                        let cell = (tile.position.as_f32() / grid_size).floor().as_i32();
+                        Some((cell, footprint))
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                if !tiles.is_empty() {
+                    layers.push(FootprintLayer {
+                        offset: level.position + layer.offset,
+                        grid_size,
+                        tiles,
+                    });
+                }
+            }
+            levels.insert(level.identifier.clone(), layers);
+        }
+
+        commands.entity(map_ent).insert(MapFootprintTiles(levels));
+    }
+}
+
+/// Leave footprints behind the characters and enemies walking on footprint tiles in the current
+/// level, and remove the footprints that have been around for their lifetime
+#[allow(clippy::too_many_arguments)]
+pub fn leave_footprints(
+    mut commands: Commands,
+    mut walkers: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&YSort>,
+            Option<&mut FootprintTrail>,
+        ),
+        (Or<(With<Handle<Character>>, With<Enemy>)>, Without<Dormant>),
+    >,
+    mut footprints: Query<(Entity, &mut Footprint), Without<Pooled>>,
+    mut footprint_pool: ResMut<EntityPool<Footprint>>,
+    maps: Query<&MapFootprintTiles>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+    asset_server: Res<AssetServer>,
+    game_time: Res<GameTime>,
+) {
+    // Remove the footprints that have been around long enough
+    let mut count = 0;
+    for (footprint_ent, mut footprint) in footprints.iter_mut() {
+        if footprint.timer.tick(game_time.delta()).finished() {
+            footprint_pool.release(&mut commands, footprint_ent);
+        } else {
+            count += 1;
+        }
+    }
+
+    let (layers, config) = match (maps.single(), current_level, &game_info) {
+        (Ok(tiles), Some(level), Some(game_info)) => match tiles.0.get(&level.0) {
+            Some(layers) => (layers, &game_info.footprints),
+            None => return,
+        },
+        _ => return,
+    };
+
+    for (walker_ent, transform, y_sort, trail) in walkers.iter_mut() {
+        let position = transform.translation.truncate();
+        let mut trail = if let Some(trail) = trail {
+            trail
+        } else {
+            commands.entity(walker_ent).insert(FootprintTrail {
+                last: position,
+                flipped: false,
+            });
+            continue;
+        };
+        if position.distance(trail.last) < config.spacing {
+            continue;
+        }
+        trail.last = position;
+
+        // The top-most layer with footprints at the position wins
+        let image = if let Some(image) = layers.iter().rev().find_map(|layer| {
+            let cell = ((position - layer.offset) / layer.grid_size)
+                .floor()
+                .as_i32();
+            layer.tiles.get(&cell)
+        }) {
+            image
+        } else {
+            continue;
+        };
+        if count >= config.max_footprints {
+            continue;
+        }
+        count += 1;
+        trail.flipped = !trail.flipped;
+
+        // Draw the footprint on the ground, behind everything in the walker's layer
+        let z = y_sort.map_or(transform.translation.z, |x| x.base_z) - 0.01;
+        let footprint = footprint_pool.take(&mut commands);
+        commands
+            .entity(footprint)
+            .insert_bundle(SpriteBundle {
+                image: asset_server.load(image.as_str()),
+                transform: Transform::from_translation(position.extend(z)),
+                sprite: Sprite {
+                    flip_x: trail.flipped,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(Footprint {
+                timer: Timer::new(Duration::from_secs_f32(config.lifetime), false),
+            });
+    }
+}
@@ -0,0 +1,96 @@
+use super::*;
+use crate::EngineConfig;
+
+/// How fast the free camera moves in pixels per second
+const FREE_CAMERA_SPEED: f32 = 200.;
+
+/// How much faster the free camera moves while shift is held
+const FREE_CAMERA_FAST_MULTIPLIER: f32 = 3.;
+
+/// Resource for the debug free-fly camera, which is toggled with F4 when the `debug` engine
+/// option is on
+#[derive(Default)]
+pub struct FreeCamera {
+    /// Whether the camera is detached from the player and the player has no collision
+    pub active: bool,
+    /// The position of the free camera
+    position: Vec2,
+}
+
+/// Toggle the free camera with F4, turning off the player's collision with everything except
+/// entrances and regions while it is on so that they can walk through the level
+pub fn toggle_free_camera(
+    mut free_camera: ResMut<FreeCamera>,
+    mut characters: Query<&mut CollisionLayers, With<Handle<Character>>>,
+    cameras: Query<&Transform, With<Camera>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    engine_config: Res<EngineConfig>,
+    game_info: Res<GameInfo>,
+) {
+    let active = if !engine_config.debug {
+        false
+    } else if keyboard_input.just_pressed(KeyCode::F4) {
+        !free_camera.active
+    } else {
+        free_camera.active
+    };
+    if active == free_camera.active {
+        return;
+    }
+    debug!(active, "Toggling free camera");
+    free_camera.active = active;
+
+    // Start flying from wherever the camera is
+    if let Ok(transform) = cameras.single() {
+        free_camera.position = transform.translation.truncate();
+    }
+
+    let layers = if active {
+        game_info
+            .collision_layers
+            .collision_layers_with(&["player"], &["entrance", "region"])
+    } else {
+        game_info.collision_layers.collision_layers(&["player"])
+    };
+    for mut character_layers in characters.iter_mut() {
+        *character_layers = layers;
+    }
+}
+
+/// Move the free camera with the WASD keys, overriding the camera's position after it has followed
+/// the player
+pub fn move_free_camera(
+    mut free_camera: ResMut<FreeCamera>,
+    mut cameras: Query<&mut Transform, With<Camera>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+) {
+    if !free_camera.active {
+        return;
+    }
+
+    let mut direction = Vec2::ZERO;
+    if keyboard_input.pressed(KeyCode::A) {
+        direction.x -= 1.;
+    }
+    if keyboard_input.pressed(KeyCode::D) {
+        direction.x += 1.;
+    }
+    if keyboard_input.pressed(KeyCode::W) {
+        direction.y -= 1.;
+    }
+    if keyboard_input.pressed(KeyCode::S) {
+        direction.y += 1.;
+    }
+    let speed = if keyboard_input.pressed(KeyCode::LShift) {
+        FREE_CAMERA_SPEED * FREE_CAMERA_FAST_MULTIPLIER
+    } else {
+        FREE_CAMERA_SPEED
+    };
+    free_camera.position += direction.normalize_or_zero() * speed * time.delta_seconds();
+
+    if let Ok(mut transform) = cameras.single_mut() {
+        transform.translation.x = free_camera.position.x;
+        transform.translation.y = free_camera.position.y;
+    }
+}
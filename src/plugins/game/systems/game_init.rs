@@ -5,9 +5,11 @@ use bevy_retrograde::{
     prelude::heron::PhysicsSteps,
 };
 
-use super::*;
+use super::{transition::ScreenFade, *};
 
+mod intro_ui;
 mod start_menu_ui;
+pub use start_menu_ui::start_game;
 
 //
 // Game Loading and initialization systems
@@ -22,23 +24,25 @@ pub fn await_init(
     mut ui_tree: ResMut<UiTree>,
     #[cfg(not(wasm))] mut windows: ResMut<Windows>,
     mut physics_params: ResMut<IntegrationParameters>,
+    mut critical_assets: ResMut<CriticalAssets>,
+    asset_version: Option<Res<crate::asset_io::AssetVersion>>,
+    intro: Res<Intro>,
 ) {
     debug!("Awaiting game info load...");
     let game_info: Handle<GameInfo> = asset_server.load_cached("default.game.yaml");
+    critical_assets.watch(&game_info);
 
     // Spawn the map and camera once the game info loads
     if let Some(game_info) = game_info_assets.get(game_info) {
         debug!("Game info loaded: spawning camera and map");
 
+        // Use the game's asset version for the rest of the downloads
+        if let Some(asset_version) = asset_version {
+            *asset_version.0.write().unwrap() = game_info.asset_version.clone();
+        }
+
         // Tweak the physics parameters
-        *physics_params = IntegrationParameters {
-            // Adjust for a "16 pixels equals 1 meter" scale
-            erp: 0.1,
-            allowed_linear_error: 0.05,
-            prediction_distance: 1.,
-            max_linear_correction: 3.,
-            ..(*physics_params)
-        };
+        game_info.physics.apply(&mut physics_params);
         commands.insert_resource(PhysicsSteps::from_max_delta_time(Duration::from_secs_f64(
             1.0 / 24.,
         )));
@@ -65,11 +69,19 @@ pub fn await_init(
             .unwrap()
             .set_title(&game_info.title);
 
-        // Spawn the map
-        commands.spawn().insert_bundle(LdtkMapBundle {
-            map: asset_server.load_cached(game_info.map.as_str()),
-            ..Default::default()
-        });
+        // Spawn the map, which may be a Tiled map or world, or an LDtk map
+        if game_info.map.ends_with(".tmx") || game_info.map.ends_with(".world") {
+            let map: Handle<TiledMap> = asset_server.load_cached(game_info.map.as_str());
+            critical_assets.watch(&map);
+            commands.spawn_bundle((map, Transform::default(), GlobalTransform::default()));
+        } else {
+            let map: Handle<LdtkMap> = asset_server.load_cached(game_info.map.as_str());
+            critical_assets.watch(&map);
+            commands.spawn().insert_bundle(LdtkMapBundle {
+                map,
+                ..Default::default()
+            });
+        }
 
         // Add the game info as a resource
         commands.insert_resource(game_info.clone());
@@ -78,6 +90,15 @@ pub fn await_init(
             game_info.splash_screen.background_level.clone(),
         ));
 
+        // Play the intro the first time the game starts, while the map loads behind it
+        if !intro.finished && !game_info.intro.is_empty() {
+            *ui_tree = UiTree(widget! {
+                (intro_ui::intro)
+            });
+            state.push(GameState::Intro).unwrap();
+            return;
+        }
+
         // Set the UI tree to the start menu
         *ui_tree = UiTree(widget! {
             (start_menu_ui::start_menu)
@@ -88,20 +109,112 @@ pub fn await_init(
     }
 }
 
+/// Resource for the progress through the intro shown before the start menu
+#[derive(Default)]
+pub struct Intro {
+    /// The index of the slide being shown
+    pub slide: usize,
+    /// How long the current slide has been shown, in seconds
+    pub elapsed: f32,
+    /// Whether the intro has been played, which keeps it from playing again when the game restarts
+    pub finished: bool,
+}
+
+/// Show the intro slides one after the other, skipping the current slide when a key or button is
+/// pressed, and go to the start menu when they are done
+#[allow(clippy::too_many_arguments)]
+pub fn play_intro(
+    mut intro: ResMut<Intro>,
+    mut state: ResMut<State<GameState>>,
+    mut ui_tree: ResMut<UiTree>,
+    game_info: Res<GameInfo>,
+    asset_server: Res<AssetServer>,
+    mut sound_controller: SoundController,
+    mut sound_events: EventWriter<SoundPlayedEvent>,
+    engine_config: Res<crate::EngineConfig>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    time: Res<Time>,
+) {
+    let skip = keyboard_input.get_just_pressed().len() > 0
+        || mouse_input.get_just_pressed().len() > 0
+        || touches.iter_just_pressed().next().is_some();
+
+    let slide = if let Some(slide) = game_info.intro.get(intro.slide) {
+        slide
+    } else {
+        debug!("Intro finished: showing the start menu");
+        intro.finished = true;
+        *ui_tree = UiTree(widget! {
+            (start_menu_ui::start_menu)
+        });
+        state.replace(GameState::StartMenu).unwrap();
+        return;
+    };
+
+    // Play the slide's sound when it is first shown
+    if intro.elapsed == 0. {
+        if let (Some(sound), false) = (&slide.sound, engine_config.mute) {
+            let sound_data = asset_server.load_cached(sound.as_str());
+            let sound_handle = sound_controller.create_sound(&sound_data);
+            sound_controller.play_sound(sound_handle);
+            sound_events.send(SoundPlayedEvent {
+                sound: sound.clone(),
+            });
+        }
+    }
+
+    intro.elapsed += time.delta_seconds();
+    if intro.elapsed >= slide.duration || (skip && slide.skippable) {
+        intro.slide += 1;
+        intro.elapsed = 0.;
+    }
+}
+
+/// Replace the game info resource with the game info asset when it is modified, and rebuild the
+/// UI so that changes to the UI theme and fonts show up without restarting
+pub fn hot_reload_game_info(
+    mut game_info_events: EventReader<AssetEvent<GameInfo>>,
+    game_info_assets: Res<Assets<GameInfo>>,
+    game_info: Option<ResMut<GameInfo>>,
+    mut ui_tree: ResMut<UiTree>,
+    asset_server: Res<AssetServer>,
+) {
+    // The game info resource is added when the game info first loads
+    let mut game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+    let handle: Handle<GameInfo> = asset_server.load_cached("default.game.yaml");
+
+    let modified = game_info_events
+        .iter()
+        .any(|x| matches!(x, AssetEvent::Modified { handle: modified } if *modified == handle));
+    if let (true, Some(new_game_info)) = (modified, game_info_assets.get(&handle)) {
+        debug!("Game info modified: reloading the UI");
+        *game_info = new_game_info.clone();
+
+        // Apply the UI tree again, which renders every widget with the new theme
+        *ui_tree = UiTree(ui_tree.0.clone());
+    }
+}
+
 pub struct StartMenuMusicHandle(pub Sound);
 /// Position the camera on the start menu
 pub fn setup_start_menu(
     mut completed: Local<bool>,
     mut cameras: Query<&mut Transform, With<Camera>>,
-    mut maps_query: Query<&Handle<LdtkMap>>,
+    maps: Query<&GameMap>,
     current_level: Res<CurrentLevel>,
-    mut map_layers: Query<(&LdtkMapLayer, &mut Visible)>,
-    map_assets: Res<Assets<LdtkMap>>,
+    mut map_layers: Query<(&MapLayer, &mut Visible)>,
     game_info: Res<GameInfo>,
     asset_server: Res<AssetServer>,
     mut sound_controller: SoundController,
     mut commands: Commands,
     state: Res<State<GameState>>,
+    mut error_events: EventWriter<FatalErrorEvent>,
 ) {
     // If the game state has just changed, reset the completed flag
     if state.is_changed() && *completed {
@@ -120,24 +233,26 @@ pub fn setup_start_menu(
     } else {
         return;
     };
-    let map_handle = if let Ok(handle) = maps_query.single_mut() {
-        handle
-    } else {
-        return;
-    };
-    let map = if let Some(map) = map_assets.get(map_handle) {
+    let map = if let Ok(map) = maps.single() {
         map
     } else {
         return;
     };
 
     // Get the current level from the map
-    let level = map
-        .project
-        .levels
-        .iter()
-        .find(|x| x.identifier == current_level.as_str())
-        .unwrap();
+    let level = if let Some(level) = map.level(current_level.as_str()) {
+        level
+    } else {
+        *completed = true;
+        error_events.send(FatalErrorEvent {
+            message: format!(
+                "Start menu background level `{}` does not exist",
+                current_level.as_str()
+            ),
+            asset: Some(game_info.map.clone()),
+        });
+        return;
+    };
 
     // Hide all other map layers
     let mut hid_layers = false;
@@ -149,11 +264,7 @@ pub fn setup_start_menu(
     }
 
     // Center the camera on the map level
-    *camera_transform = Transform::from_xyz(
-        level.world_x as f32 + level.px_wid as f32 / 2.,
-        level.world_y as f32 + level.px_hei as f32 / 2.,
-        0.,
-    );
+    *camera_transform = Transform::from_translation(level.center().extend(0.));
 
     let sound_data = asset_server.load_cached(game_info.splash_screen.music.as_str());
     let sound = sound_controller.create_sound(&sound_data);
@@ -173,9 +284,9 @@ pub fn setup_start_menu(
 }
 
 pub fn spawn_player_and_setup_level(
+    mut loading_timer: Local<Option<Timer>>,
     mut commands: Commands,
-    map_query: Query<&Handle<LdtkMap>>,
-    map_assets: Res<Assets<LdtkMap>>,
+    maps: Query<&GameMap>,
     mut state: ResMut<State<GameState>>,
     asset_server: Res<AssetServer>,
     game_info: Res<GameInfo>,
@@ -183,61 +294,92 @@ pub fn spawn_player_and_setup_level(
     mut sound_controller: SoundController,
     mut ui_tree: ResMut<UiTree>,
     start_menu_music_handle: Res<StartMenuMusicHandle>,
+    mut screen_fade: ResMut<ScreenFade>,
+    mut sound_events: EventWriter<SoundPlayedEvent>,
+    mut rng: ResMut<GameRng>,
+    mut critical_assets: ResMut<CriticalAssets>,
+    mut error_events: EventWriter<FatalErrorEvent>,
+    time: Res<Time>,
 ) {
-    if let Ok(map_handle) = map_query.single() {
-        if let Some(map) = map_assets.get(map_handle) {
-            debug!("Map loaded: spawning player");
-
-            // Stop the menu music
-            sound_controller.stop_sound(start_menu_music_handle.0);
-
-            let level = &map
-                .project
-                .levels
-                .iter()
-                .find(|x| x.identifier == **current_level)
-                .unwrap();
-
-            let entities_layer = level
-                .layer_instances
-                .as_ref()
-                .unwrap()
-                .iter()
-                .find(|&x| !x.entity_instances.is_empty())
-                .unwrap();
-
-            let player_start = entities_layer
-                .entity_instances
-                .iter()
-                .find(|x| {
-                    x.__identifier == "SpawnPoint"
-                        && x.field_instances
-                            .iter()
-                            .any(|x| x.__identifier == "name" && x.__value == "PlayerStart")
-                })
-                .unwrap();
-
-            let character_handle: Handle<Character> =
-                asset_server.load_cached(game_info.player_character.as_str());
-
-            let character_image_handle =
-                asset_server.load_cached(format!("{}#atlas", game_info.player_character).as_str());
-            let character_spritesheet_handle = asset_server
-                .load_cached(format!("{}#spritesheet", game_info.player_character).as_str());
-
-            // Layers are 2 units away from each-other, so put the player at the top
-            let player_z = level.layer_instances.as_ref().unwrap().len() as f32 * 2.0;
-
-            // Spawn the player
-            commands.spawn().insert_bundle(CharacterBundle {
+    // Fade the start menu out before we start loading
+    if loading_timer.is_none() {
+        screen_fade.fade_out();
+
+        if !screen_fade.is_black() {
+            return;
+        }
+
+        // Show the loading screen for at least the configured time
+        *loading_timer = Some(Timer::from_seconds(
+            game_info.transitions.min_loading_duration,
+            false,
+        ));
+        *ui_tree = UiTree(widget! {
+            (transition::loading_screen)
+        });
+    }
+
+    if let Some(timer) = loading_timer.as_mut() {
+        timer.tick(time.delta());
+
+        if !timer.finished() {
+            return;
+        }
+    }
+
+    if let Ok(map) = maps.single() {
+        debug!("Map loaded: spawning player");
+
+        // Stop the menu music
+        sound_controller.stop_sound(start_menu_music_handle.0);
+
+        let level = if let Some(level) = map.level(&current_level) {
+            level
+        } else {
+            error_events.send(FatalErrorEvent {
+                message: format!("Level `{}` does not exist", current_level.as_str()),
+                asset: Some(game_info.map.clone()),
+            });
+            return;
+        };
+
+        let player_start =
+            if let Some(player_start) = level.find_entity("SpawnPoint", "name", "PlayerStart") {
+                player_start
+            } else {
+                error_events.send(FatalErrorEvent {
+                    message: format!(
+                        "Level `{}` does not have a `SpawnPoint` named `PlayerStart`",
+                        level.identifier
+                    ),
+                    asset: Some(game_info.map.clone()),
+                });
+                return;
+            };
+
+        let character_handle: Handle<Character> =
+            asset_server.load_cached(game_info.player_character.as_str());
+        critical_assets.watch(&character_handle);
+
+        let character_image_handle =
+            asset_server.load_cached(format!("{}#atlas", game_info.player_character).as_str());
+        let character_spritesheet_handle = asset_server
+            .load_cached(format!("{}#spritesheet", game_info.player_character).as_str());
+
+        // Put the player in the layer of the spawn point
+        let player_z = player_start.z;
+        let player_translation = level.place(player_start, Placement::Character);
+
+        // Spawn the player
+        commands
+            .spawn()
+            .insert(YSort { base_z: player_z })
+            .insert(FixedStepInterpolation::new(player_translation))
+            .insert_bundle(CharacterBundle {
                 character: character_handle,
                 sprite_bundle: SpriteBundle {
                     image: character_image_handle,
-                    transform: Transform::from_xyz(
-                        player_start.px[0] as f32 + level.world_x as f32,
-                        player_start.px[1] as f32 + level.world_y as f32,
-                        player_z,
-                    ),
+                    transform: Transform::from_translation(player_translation),
                     sprite: Sprite {
                         pixel_perfect: false,
                         ..Default::default()
@@ -248,54 +390,51 @@ pub fn spawn_player_and_setup_level(
                 ..Default::default()
             });
 
-            // Get the level background music
-            let background_music_field = level
-                .field_instances
-                .iter()
-                .find(|x| x.__identifier == "music")
-                .unwrap();
+        // Play the level background music if it is set
+        if let Some(music) = level.field::<String>("music") {
+            let music = music.as_str();
+            if music != "none" {
+                debug!("Starting level music");
+                let sound_data: Handle<SoundData> = asset_server.load_cached(music);
+                critical_assets.watch(&sound_data);
+                let sound = sound_controller.create_sound(&sound_data);
+
+                // Play music on loop
+                sound_controller.play_sound_with_settings(
+                    sound,
+                    PlaySoundSettings::new().loop_start(LoopStart::Custom(0.0)),
+                );
+
+                commands.insert_resource(CurrentLevelMusic { sound_data, sound });
+                sound_events.send(SoundPlayedEvent {
+                    sound: music.into(),
+                });
+            }
+        }
 
-            // Play the music if it is set
-            if let Some(music) = background_music_field.__value.as_str() {
+        // Pre-load all other background music for the map
+        for level in &map.levels {
+            if let Some(music) = level.field::<String>("music") {
                 if music != "none" {
-                    debug!("Starting level music");
-                    let sound_data = asset_server.load_cached(music);
-                    let sound = sound_controller.create_sound(&sound_data);
-
-                    // Play music on loop
-                    sound_controller.play_sound_with_settings(
-                        sound,
-                        PlaySoundSettings::new().loop_start(LoopStart::Custom(0.0)),
-                    );
-
-                    commands.insert_resource(CurrentLevelMusic { sound_data, sound });
+                    // Cache the music data
+                    asset_server.load_cached::<SoundData, _>(music.as_str());
                 }
             }
+        }
 
-            // Pre-load all other background music for the map
-            for level in &map.project.levels {
-                let background_music_field = level
-                    .field_instances
-                    .iter()
-                    .find(|x| x.__identifier == "music")
-                    .unwrap();
-
-                if let Some(music) = background_music_field.__value.as_str() {
-                    if music != "none" {
-                        // Cache the music data
-                        asset_server.load_cached::<SoundData, _>(music);
-                    }
-                }
-            }
+        // Remove the loading screen and fade into the game
+        *ui_tree = UiTree(widget! {
+            ()
+        });
+        *loading_timer = None;
+        screen_fade.fade_in();
 
-            // Remove the start menu
-            *ui_tree = UiTree(widget! {
-                ()
-            });
+        // Restart the RNG so that every run with the same seed plays out the same way
+        let seed = rng.seed();
+        rng.reseed(seed);
 
-            // Go to the running state
-            debug!("Going into running state");
-            state.push(GameState::Playing).unwrap();
-        }
+        // Go to the running state
+        debug!("Going into running state");
+        state.push(GameState::Playing).unwrap();
     }
 }
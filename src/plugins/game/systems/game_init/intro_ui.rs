@@ -0,0 +1,74 @@
+use bevy::prelude::World;
+use bevy_retrograde::ui::raui::prelude::*;
+
+use super::{GameInfo, Intro};
+
+/// How long intro slides take to fade in and out, in seconds
+const INTRO_FADE_TIME: f32 = 0.5;
+
+/// The UI tree used for the intro, showing the current slide's image centered on a black screen
+pub fn intro(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let intro = world.get_resource::<Intro>().unwrap();
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+
+    let background = make_widget!(image_box).with_props(ImageBoxProps {
+        material: ImageBoxMaterial::Color(ImageBoxColor {
+            color: Color {
+                r: 0.,
+                g: 0.,
+                b: 0.,
+                a: 1.,
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    let slide = if let Some(slide) = game_info.intro.get(intro.slide) {
+        slide
+    } else {
+        return make_widget!(content_box).listed_slot(background).into();
+    };
+
+    // Fade the image in at the start of the slide and out at the end
+    let fade_time = INTRO_FADE_TIME.min(slide.duration / 2.).max(f32::EPSILON);
+    let opacity = (intro.elapsed / fade_time)
+        .min((slide.duration - intro.elapsed) / fade_time)
+        .clamp(0., 1.);
+    let (width, height) = (slide.image.size.x as f32, slide.image.size.y as f32);
+
+    make_widget!(content_box)
+        .listed_slot(background)
+        .listed_slot(
+            make_widget!(image_box)
+                .with_props(ImageBoxProps {
+                    material: ImageBoxMaterial::Image(ImageBoxImage {
+                        id: slide.image.path.clone(),
+                        tint: Color {
+                            r: 1.,
+                            g: 1.,
+                            b: 1.,
+                            a: opacity,
+                        },
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .with_props(ContentBoxItemLayout {
+                    anchors: Rect {
+                        left: 0.5,
+                        right: 0.5,
+                        top: 0.5,
+                        bottom: 0.5,
+                    },
+                    margin: Rect {
+                        left: -width / 2.,
+                        right: -width / 2.,
+                        top: -height / 2.,
+                        bottom: -height / 2.,
+                    },
+                    ..Default::default()
+                }),
+        )
+        .into()
+}
@@ -1,7 +1,64 @@
 use bevy::prelude::World;
 use bevy_retrograde::ui::raui::prelude::*;
+use rand::Rng;
 
-use super::{ui_utils::get_ui_theme, CurrentLevel, GameInfo, GameState, State};
+use crate::{asset_overlay::ContentPacks, BuildInfo};
+
+use super::{
+    save_user_settings,
+    transition::screen_fade,
+    ui_utils::{
+        active_ui_theme, game_button, get_ui_theme, safe_area_box, GameButtonMessage,
+        GameButtonProps, UiLayout, UiThemeVariant,
+    },
+    Affinities, CurrentLevel, DungeonRun, GameClock, GameInfo, GameRng, GameState,
+    GlobalLeaderboard, HighScores, Inventory, LevelStates, MetaProgress, SaveGame, State,
+    UserSettings,
+};
+
+/// Start the game from the start menu
+pub fn start_game(world: &mut World) {
+    // Start in the level given in the engine config, or continue from the save game if there is
+    // one
+    let save = world.get_resource::<SaveGame>().cloned();
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+    let start_level = world
+        .get_resource::<crate::EngineConfig>()
+        .unwrap()
+        .start_level
+        .clone()
+        .or_else(|| save.as_ref().and_then(|x| x.level.clone()))
+        .unwrap_or_else(|| game_info.game_start_level.clone());
+    let clock = save
+        .as_ref()
+        .and_then(|x| x.clock.clone())
+        .unwrap_or_else(|| GameClock::starting_at(game_info.clock.start_hour));
+    let has_dungeon = game_info.dungeon.is_some();
+    *world.get_resource_mut::<GameClock>().unwrap() = clock;
+    // Continue the saved dungeon run, or start a new one if the game has a dungeon
+    let mut dungeon_run = save.as_ref().map(|x| x.dungeon.clone()).unwrap_or_default();
+    if dungeon_run.seed.is_none() && has_dungeon {
+        dungeon_run.seed = Some(world.get_resource_mut::<GameRng>().unwrap().gen());
+    }
+    *world.get_resource_mut::<DungeonRun>().unwrap() = dungeon_run;
+    if let Some(save) = save {
+        *world.get_resource_mut::<LevelStates>().unwrap() = save.level_states;
+        *world.get_resource_mut::<Inventory>().unwrap() = save.inventory;
+        *world.get_resource_mut::<Affinities>().unwrap() = save.affinities;
+        *world.get_resource_mut::<MetaProgress>().unwrap() = save.meta;
+    }
+
+    {
+        let mut current_level = world.get_resource_mut::<CurrentLevel>().unwrap();
+        *current_level = CurrentLevel(start_level);
+    }
+    {
+        let mut state = world.get_resource_mut::<State<GameState>>().unwrap();
+        if state.current() != &GameState::LoadingGame {
+            state.push(GameState::LoadingGame).unwrap();
+        }
+    }
+}
 
 fn use_start_menu(ctx: &mut WidgetContext) {
     ctx.life_cycle.change(|ctx| {
@@ -10,23 +67,10 @@ fn use_start_menu(ctx: &mut WidgetContext) {
         for msg in ctx.messenger.messages {
             if let Some(msg) = msg.as_any().downcast_ref::<GameButtonMessage>() {
                 if &msg.0 == "start" {
-                    let start_level = world
-                        .get_resource::<GameInfo>()
-                        .unwrap()
-                        .game_start_level
-                        .clone();
-
-                    {
-                        let mut current_level = world.get_resource_mut::<CurrentLevel>().unwrap();
-                        *current_level = CurrentLevel(start_level);
-                    }
-                    {
-                        let mut state = world.get_resource_mut::<State<GameState>>().unwrap();
-                        if state.current() != &GameState::LoadingGame {
-                            state.push(GameState::LoadingGame).unwrap();
-                        }
-                    }
+                    start_game(world);
                 } else if &msg.0 == "show_settings" {
+                    let previous_settings = world.get_resource::<UserSettings>().unwrap().clone();
+
                     let mut query = world.query::<&super::Camera>();
                     let camera = query.iter_mut(world).next().expect("Expected one camera");
 
@@ -39,9 +83,15 @@ fn use_start_menu(ctx: &mut WidgetContext) {
                             show_settings: true,
                             previous_crt_filter_enabled,
                             previous_pixel_aspect_4_3_enabled,
+                            previous_settings,
                         })
                         .unwrap();
                 } else if &msg.0 == "cancel_settings" {
+                    let StartMenuState {
+                        previous_settings, ..
+                    } = ctx.state.read_cloned_or_default();
+                    *world.get_resource_mut::<UserSettings>().unwrap() = previous_settings;
+
                     let mut query = world.query::<&mut super::Camera>();
                     let mut camera = query.iter_mut(world).next().expect("Expected one camera");
 
@@ -63,11 +113,27 @@ fn use_start_menu(ctx: &mut WidgetContext) {
                         })
                         .unwrap();
                 } else if &msg.0 == "save_settings" {
+                    save_user_settings(world);
+
                     ctx.state
                         .mutate_cloned(|state: &mut StartMenuState| {
                             state.show_settings = false;
                         })
                         .unwrap();
+                } else if &msg.0 == "show_content_packs" {
+                    ctx.state
+                        .mutate_cloned(|state: &mut StartMenuState| {
+                            state.show_content_packs = true;
+                        })
+                        .unwrap();
+                } else if &msg.0 == "close_content_packs" {
+                    save_user_settings(world);
+
+                    ctx.state
+                        .mutate_cloned(|state: &mut StartMenuState| {
+                            state.show_content_packs = false;
+                        })
+                        .unwrap();
                 }
             }
         }
@@ -77,8 +143,10 @@ fn use_start_menu(ctx: &mut WidgetContext) {
 #[derive(PropsData, Clone, Debug, serde::Serialize, serde::Deserialize, Default)]
 struct StartMenuState {
     show_settings: bool,
+    show_content_packs: bool,
     previous_crt_filter_enabled: bool,
     previous_pixel_aspect_4_3_enabled: bool,
+    previous_settings: UserSettings,
 }
 
 /// The UI tree used for the start menu
@@ -90,17 +158,43 @@ pub fn start_menu(mut ctx: WidgetContext) -> WidgetNode {
         ..
     } = ctx;
 
-    let StartMenuState { show_settings, .. } = ctx.state.read_cloned_or_default();
+    let StartMenuState {
+        show_settings,
+        show_content_packs,
+        ..
+    } = ctx.state.read_cloned_or_default();
 
     // Get the game info from the world
     let world: &mut World = process_context.get_mut().unwrap();
     let game_info = world.get_resource::<GameInfo>().unwrap();
+    let settings = world.get_resource::<UserSettings>().unwrap();
+    let ui_layout = world
+        .get_resource::<UiLayout>()
+        .cloned()
+        .unwrap_or_default();
+    let has_content_packs = world
+        .get_resource::<ContentPacks>()
+        .map(|x| !x.packs.is_empty())
+        .unwrap_or(false);
+    let build_info = world
+        .get_resource::<BuildInfo>()
+        .copied()
+        .unwrap_or_default();
+
+    let ui_theme_variant = world
+        .get_resource::<UiThemeVariant>()
+        .cloned()
+        .unwrap_or_default();
+    let ui_theme = active_ui_theme(game_info, settings, &ui_theme_variant, "menu");
 
-    // Create shared props containing the theme
+    // Create shared props containing the theme, with the game info's theme replaced by the active
+    // one for the settings and mods panels
+    let mut themed_game_info = game_info.clone();
+    themed_game_info.ui_theme = ui_theme.clone();
     let shared_props = Props::default()
         // Add the theme properties
-        .with(get_ui_theme(game_info))
-        .with(game_info.clone());
+        .with(get_ui_theme(ui_theme))
+        .with(themed_game_info);
 
     let vertical_box_props = VerticalBoxProps {
         separation: 0.,
@@ -157,6 +251,28 @@ pub fn start_menu(mut ctx: WidgetContext) -> WidgetNode {
         message_name: "show_settings".into(),
     });
 
+    // The mods button, which is only shown if there are mods
+    let content_packs_button = if has_content_packs {
+        let props = Props::new(FlexBoxItemLayout {
+            align: 0.5,
+            grow: 0.0,
+            margin: Rect {
+                top: 10.,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(GameButtonProps {
+            text: "Mods".into(),
+            notify_id: id.to_owned(),
+            message_name: "show_content_packs".into(),
+        });
+
+        widget! { (game_button: {props}) }
+    } else {
+        WidgetNode::None
+    };
+
     let copyright_props = Props::new(TextBoxProps {
         text: game_info.splash_screen.copyright.text.clone(),
         color: Color {
@@ -178,6 +294,61 @@ pub fn start_menu(mut ctx: WidgetContext) -> WidgetNode {
         ..Default::default()
     });
 
+    // The engine build in the corner, so players can include it in bug reports
+    let build_info_props = Props::new(TextBoxProps {
+        text: build_info.to_string(),
+        color: Color {
+            r: 0.,
+            g: 0.,
+            b: 0.,
+            a: 0.5,
+        },
+        font: TextBoxFont {
+            name: game_info.splash_screen.copyright.font.clone(),
+            size: 1.0,
+        },
+        horizontal_align: TextBoxHorizontalAlign::Right,
+        vertical_align: TextBoxVerticalAlign::Bottom,
+        ..Default::default()
+    })
+    .with(ContentBoxItemLayout {
+        margin: 5.0.into(),
+        ..Default::default()
+    });
+
+    // The high score table in the top-left corner, for games that keep score
+    let high_score_table = match (&game_info.score, world.get_resource::<HighScores>()) {
+        (Some(_), Some(high_scores)) if !high_scores.0.is_empty() => score_table(
+            "High Scores",
+            high_scores
+                .0
+                .iter()
+                .enumerate()
+                .map(|(i, score)| format!("{}. {}", i + 1, score)),
+            &ui_theme.default_font,
+            TextBoxHorizontalAlign::Left,
+        ),
+        _ => WidgetNode::None,
+    };
+
+    // The best scores of all players in the top-right corner, for games with an online leaderboard
+    let global_score_table = match (
+        &game_info.leaderboard,
+        world.get_resource::<GlobalLeaderboard>(),
+    ) {
+        (Some(_), Some(leaderboard)) if !leaderboard.entries.is_empty() => score_table(
+            "Leaderboard",
+            leaderboard
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| format!("{}. {} {}", i + 1, entry.name, entry.score)),
+            &ui_theme.default_font,
+            TextBoxHorizontalAlign::Right,
+        ),
+        _ => WidgetNode::None,
+    };
+
     let content = if show_settings {
         let props = Props::new(SettingsPanelProps {
             cancel_notify_id: ctx.id.to_owned(),
@@ -189,6 +360,15 @@ pub fn start_menu(mut ctx: WidgetContext) -> WidgetNode {
         widget! {
             (#{"settings"} settings_panel: {props})
         }
+    } else if show_content_packs {
+        let props = Props::new(ContentPacksPanelProps {
+            close_notify_id: ctx.id.to_owned(),
+            close_notify_message: "close_content_packs".into(),
+        });
+
+        widget! {
+            (#{"content_packs"} content_packs_panel: {props})
+        }
     } else {
         widget! {
             // The main content
@@ -197,128 +377,62 @@ pub fn start_menu(mut ctx: WidgetContext) -> WidgetNode {
                     (image_box: {title_image_props})
                     (game_button: {start_button_props})
                     (game_button: {settings_button_props})
+                    {content_packs_button}
                 ])
                 (text_box: {copyright_props})
+                (text_box: {build_info_props})
+                {high_score_table}
+                {global_score_table}
             ])
         }
     };
+    let content = safe_area_box(&ui_layout, content);
 
     widget! {
         (content_box | {shared_props} [
             {content}
+            (screen_fade)
         ])
     }
 }
 
-#[derive(PropsData, Debug, Clone, serde::Deserialize, serde::Serialize, Default)]
-struct GameButtonProps {
-    text: String,
-    notify_id: WidgetId,
-    message_name: String,
-}
-
-#[derive(MessageData, Debug, Clone, serde::Deserialize, serde::Serialize, Default)]
-struct GameButtonMessage(String);
-
-fn use_game_button(ctx: &mut WidgetContext) {
-    ctx.life_cycle.change(|ctx| {
-        let ButtonProps { trigger, .. } = ctx.state.read_cloned_or_default();
-        let GameButtonProps {
-            notify_id,
-            message_name: message,
-            ..
-        } = ctx.props.read_cloned_or_default();
-
-        if trigger {
-            ctx.messenger.write(notify_id, GameButtonMessage(message));
-        }
-    });
-}
-
-#[pre_hooks(
-    // This allows us to get a `ButtonProps` instance from our widget state which will keep
-    // track of whether or not we are clicked, hovered over, etc.
-    use_game_button,
-    use_button_notified_state,
-)]
-fn game_button(mut ctx: WidgetContext) -> WidgetNode {
-    let world: &mut World = ctx.process_context.get_mut().unwrap();
-    let game_info = world.get_resource::<GameInfo>().unwrap();
-
-    // Get our button state
-    let ButtonProps {
-        selected: hover,
-        trigger: clicked,
-        ..
-    } = ctx.state.read_cloned_or_default();
-
-    let GameButtonProps {
-        text: button_text, ..
-    } = ctx.props.read_cloned_or_default();
-
-    let button_props = ctx
-        .props
-        .clone()
-        .with(NavItemActive)
-        .with(ButtonNotifyProps(ctx.id.to_owned().into()));
-
-    let button_panel_props = Props::new(PaperProps {
-        frame: None,
-        variant: if clicked {
-            // TODO: Somehow pre-load the button-up image so that it doesn't flash
-            // blank for a second the first time a button is clicked
-            String::from("button-down")
-        } else {
-            String::from("button-up")
-        },
-    });
-
-    let scale = if hover { 1.1 } else { 1. };
-
-    let label_props = Props::new(TextBoxProps {
-        text: button_text,
-        width: TextBoxSizeValue::Fill,
-        height: TextBoxSizeValue::Fill,
-        horizontal_align: TextBoxHorizontalAlign::Center,
-        vertical_align: TextBoxVerticalAlign::Middle,
-        font: TextBoxFont {
-            name: game_info.ui_theme.default_font.clone(),
-            size: 1.,
-        },
-        transform: Transform {
-            translation: Vec2 {
-                x: 0.,
-                y: if clicked { 1. } else { 0. },
-            },
-            // scale: Vec2::from(1.0 / scale), // Undo button scale to make sure text stays same size
-            ..Default::default()
-        },
-        ..Default::default()
-    });
-
-    let size_box_props = Props::new(SizeBoxProps {
-        width: SizeBoxSizeValue::Exact(70.),
-        height: SizeBoxSizeValue::Exact(18.),
-        transform: Transform {
-            scale: Vec2::from(scale),
-            translation: Vec2 {
-                x: if hover { (-75. * scale + 75.) / 2. } else { 0. },
-                y: if hover { (-20. * scale + 20.) / 2. } else { 0. },
-            },
-            ..Default::default()
-        },
+/// A list of scores under a title, in the corner of the start menu
+fn score_table(
+    title: &str,
+    lines: impl Iterator<Item = String>,
+    font: &str,
+    align: TextBoxHorizontalAlign,
+) -> WidgetNode {
+    let mut list = make_widget!(vertical_box).with_props(ContentBoxItemLayout {
+        margin: 5.0.into(),
         ..Default::default()
     });
-
-    widget! {
-        (button: {button_props} {
-            content = (size_box: {size_box_props} {
-                content = (horizontal_paper: {button_panel_props} [
-                    (text_box: {label_props})
-                ])
-            })
-        })
+    for line in std::iter::once(title.to_owned()).chain(lines) {
+        list = list.listed_slot(
+            make_widget!(text_box)
+                .with_props(TextBoxProps {
+                    text: line,
+                    color: Color {
+                        r: 0.,
+                        g: 0.,
+                        b: 0.,
+                        a: 1.,
+                    },
+                    font: TextBoxFont {
+                        name: font.into(),
+                        size: 1.0,
+                    },
+                    horizontal_align: align,
+                    ..Default::default()
+                })
+                .with_props(FlexBoxItemLayout {
+                    grow: 0.,
+                    basis: Some(10.),
+                    ..Default::default()
+                }),
+        );
     }
+    list.into()
 }
 
 #[derive(PropsData, Debug, Clone, serde::Deserialize, serde::Serialize, Default)]
@@ -332,6 +446,44 @@ struct SettingsPanelProps {
 fn use_settings_panel(ctx: &mut WidgetContext) {
     ctx.life_cycle.change(|ctx| {
         let world: &mut World = ctx.process_context.get_mut().unwrap();
+
+        // Respond to user setting button messages
+        for msg in ctx.messenger.messages.iter() {
+            if let Some(msg) = msg.as_any().downcast_ref::<GameButtonMessage>() {
+                let mut settings = world.get_resource_mut::<UserSettings>().unwrap();
+                if &msg.0 == "cycle_text_speed" {
+                    settings.text_speed = settings.text_speed.next();
+                } else if &msg.0 == "cycle_text_size" {
+                    let accessibility = &mut settings.accessibility;
+                    accessibility.text_size = accessibility.text_size.next();
+                } else if &msg.0 == "cycle_game_speed" {
+                    let accessibility = &mut settings.accessibility;
+                    accessibility.game_speed = accessibility.next_game_speed();
+                }
+            } else if let Some(msg) = msg.as_any().downcast_ref::<ButtonNotifyMessage>() {
+                if !msg.trigger_start() {
+                    continue;
+                }
+
+                let mut settings = world.get_resource_mut::<UserSettings>().unwrap();
+                let settings = &mut *settings;
+                let accessibility = &mut settings.accessibility;
+                if msg.sender.ends_with("rumble") {
+                    settings.rumble = !settings.rumble;
+                } else if msg.sender.ends_with("screen_shake") {
+                    accessibility.screen_shake = !accessibility.screen_shake;
+                } else if msg.sender.ends_with("reduce_flashes") {
+                    accessibility.reduce_flashes = !accessibility.reduce_flashes;
+                } else if msg.sender.ends_with("high_contrast") {
+                    accessibility.high_contrast = !accessibility.high_contrast;
+                } else if msg.sender.ends_with("captions") {
+                    accessibility.captions = !accessibility.captions;
+                } else if msg.sender.ends_with("share_analytics") {
+                    settings.share_analytics = !settings.share_analytics;
+                }
+            }
+        }
+
         let mut query = world.query::<&mut super::Camera>();
         let mut camera = query.iter_mut(world).next().expect("Expected one camera");
 
@@ -368,6 +520,7 @@ fn settings_panel(mut ctx: WidgetContext) -> WidgetNode {
 
     // Get the camera info from the world
     let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let user_settings = world.get_resource::<UserSettings>().unwrap().clone();
     let mut query = world.query::<&super::Camera>();
     let camera = query.iter_mut(world).next().expect("Expected one camera");
     // Get the values for the checkboxes
@@ -548,7 +701,7 @@ fn settings_panel(mut ctx: WidgetContext) -> WidgetNode {
     let pixel_aspect_text_props = Props::new(TextBoxProps {
         text: "4/3 Pixel Aspect Ratio".into(),
         font: TextBoxFont {
-            name: game_info.ui_theme.default_font,
+            name: game_info.ui_theme.default_font.clone(),
             size: 1.0,
         },
         color: Color {
@@ -567,6 +720,82 @@ fn settings_panel(mut ctx: WidgetContext) -> WidgetNode {
         ..Default::default()
     });
 
+    let font = game_info.ui_theme.default_font;
+
+    // Text settings
+    let text_settings_title_props = settings_section_title_props(&font, "Text");
+    let text_speed_button_props = settings_cycle_button_props(
+        user_settings.text_speed.label(),
+        ctx.id.to_owned(),
+        "cycle_text_speed",
+    );
+    let text_speed_text_props = settings_label_props(&font, "Text Speed");
+
+    // Control settings
+    let controls_settings_title_props = settings_section_title_props(&font, "Controls");
+    let rumble_check_props = settings_check_box_props(user_settings.rumble, ctx.id.to_owned());
+    let rumble_text_props = settings_label_props(&font, "Rumble");
+
+    // Privacy settings, for games that record analytics
+    let privacy_settings = if game_info.analytics.is_some() {
+        let privacy_settings_title_props = settings_section_title_props(&font, "Privacy");
+        let share_analytics_check_props =
+            settings_check_box_props(user_settings.share_analytics, ctx.id.to_owned());
+        let share_analytics_text_props = settings_label_props(&font, "Share Play Data");
+        let wrapper_props = check_box_wrapper_props.clone();
+
+        widget! {
+            (vertical_box [
+                (text_box: {privacy_settings_title_props})
+                (horizontal_box: {wrapper_props} [
+                    (#{"share_analytics"} switch_button_paper: {share_analytics_check_props})
+                    (text_box: {share_analytics_text_props})
+                ])
+            ])
+        }
+    } else {
+        WidgetNode::None
+    };
+
+    // Accessibility settings
+    let accessibility = &user_settings.accessibility;
+    let accessibility_settings_title_props = settings_section_title_props(&font, "Accessibility");
+    let screen_shake_check_props =
+        settings_check_box_props(accessibility.screen_shake, ctx.id.to_owned());
+    let screen_shake_text_props = settings_label_props(&font, "Screen Shake");
+    let reduce_flashes_check_props =
+        settings_check_box_props(accessibility.reduce_flashes, ctx.id.to_owned());
+    let reduce_flashes_text_props = settings_label_props(&font, "Reduce Flashing");
+    let high_contrast_check_props =
+        settings_check_box_props(accessibility.high_contrast, ctx.id.to_owned());
+    let high_contrast_text_props = settings_label_props(&font, "High Contrast");
+    let captions_check_props = settings_check_box_props(accessibility.captions, ctx.id.to_owned());
+    let captions_text_props = settings_label_props(&font, "Captions");
+    let text_size_button_props = settings_cycle_button_props(
+        accessibility.text_size.label(),
+        ctx.id.to_owned(),
+        "cycle_text_size",
+    );
+    let text_size_text_props = settings_label_props(&font, "Text Size");
+    let game_speed_button_props = settings_cycle_button_props(
+        &format!("{:.0}%", accessibility.game_speed * 100.),
+        ctx.id.to_owned(),
+        "cycle_game_speed",
+    );
+    let game_speed_text_props = settings_label_props(&font, "Game Speed");
+
+    // Wrapper for button settings
+    let button_setting_wrapper_props = Props::new(FlexBoxItemLayout {
+        grow: 0.0,
+        basis: Some(20.),
+        margin: Rect {
+            top: 5.,
+            left: 10.,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
     let margin_box_props = FlexBoxItemLayout {
         margin: Rect {
             top: 10.,
@@ -582,15 +811,61 @@ fn settings_panel(mut ctx: WidgetContext) -> WidgetNode {
             (nav_vertical_paper: {panel_props} [
                 (vertical_box: {margin_box_props} [
                     (text_box: {title_props})
-                    (vertical_box [
-                        (text_box: {graphics_settings_title_props})
-                        (horizontal_box: {check_box_wrapper_props.clone()} [
-                            (#{"crt_filter"} switch_button_paper: {crt_filter_check_props})
-                            (text_box: {crt_filter_text_props})
+                    (horizontal_box [
+                        (vertical_box [
+                            (vertical_box [
+                                (text_box: {graphics_settings_title_props})
+                                (horizontal_box: {check_box_wrapper_props.clone()} [
+                                    (#{"crt_filter"} switch_button_paper: {crt_filter_check_props})
+                                    (text_box: {crt_filter_text_props})
+                                ])
+                                (horizontal_box: {check_box_wrapper_props.clone()} [
+                                    (#{"pixel_aspect"} switch_button_paper: {pixel_aspect_check_props})
+                                    (text_box: {pixel_aspect_text_props})
+                                ])
+                            ])
+                            (vertical_box [
+                                (text_box: {text_settings_title_props})
+                                (horizontal_box: {button_setting_wrapper_props.clone()} [
+                                    (game_button: {text_speed_button_props})
+                                    (text_box: {text_speed_text_props})
+                                ])
+                            ])
+                            (vertical_box [
+                                (text_box: {controls_settings_title_props})
+                                (horizontal_box: {check_box_wrapper_props.clone()} [
+                                    (#{"rumble"} switch_button_paper: {rumble_check_props})
+                                    (text_box: {rumble_text_props})
+                                ])
+                            ])
+                            {privacy_settings}
                         ])
-                        (horizontal_box: {check_box_wrapper_props} [
-                            (#{"pixel_aspect"} switch_button_paper: {pixel_aspect_check_props})
-                            (text_box: {pixel_aspect_text_props})
+                        (vertical_box [
+                            (text_box: {accessibility_settings_title_props})
+                            (horizontal_box: {check_box_wrapper_props.clone()} [
+                                (#{"screen_shake"} switch_button_paper: {screen_shake_check_props})
+                                (text_box: {screen_shake_text_props})
+                            ])
+                            (horizontal_box: {check_box_wrapper_props.clone()} [
+                                (#{"reduce_flashes"} switch_button_paper: {reduce_flashes_check_props})
+                                (text_box: {reduce_flashes_text_props})
+                            ])
+                            (horizontal_box: {check_box_wrapper_props.clone()} [
+                                (#{"high_contrast"} switch_button_paper: {high_contrast_check_props})
+                                (text_box: {high_contrast_text_props})
+                            ])
+                            (horizontal_box: {check_box_wrapper_props} [
+                                (#{"captions"} switch_button_paper: {captions_check_props})
+                                (text_box: {captions_text_props})
+                            ])
+                            (horizontal_box: {button_setting_wrapper_props.clone()} [
+                                (game_button: {text_size_button_props})
+                                (text_box: {text_size_text_props})
+                            ])
+                            (horizontal_box: {button_setting_wrapper_props} [
+                                (game_button: {game_speed_button_props})
+                                (text_box: {game_speed_text_props})
+                            ])
                         ])
                     ])
                     (flex_box: {button_box_props} [
@@ -602,3 +877,256 @@ fn settings_panel(mut ctx: WidgetContext) -> WidgetNode {
         ])
     }
 }
+
+#[derive(PropsData, Debug, Clone, serde::Deserialize, serde::Serialize, Default)]
+struct ContentPacksPanelProps {
+    close_notify_id: WidgetId,
+    close_notify_message: String,
+}
+
+fn use_content_packs_panel(ctx: &mut WidgetContext) {
+    ctx.life_cycle.change(|ctx| {
+        let world: &mut World = ctx.process_context.get_mut().unwrap();
+        let content_packs = world.get_resource::<ContentPacks>().unwrap().clone();
+
+        // Turn mods on and off when their check boxes are clicked
+        for msg in ctx.messenger.messages.iter() {
+            if let Some(msg) = msg.as_any().downcast_ref::<ButtonNotifyMessage>() {
+                if !msg.trigger_start() {
+                    continue;
+                }
+
+                let mut settings = world.get_resource_mut::<UserSettings>().unwrap();
+                for (i, pack) in content_packs.packs.iter().enumerate() {
+                    if !msg.sender.ends_with(&content_pack_key(i)) {
+                        continue;
+                    }
+
+                    let disabled = &mut settings.disabled_content_packs;
+                    if let Some(idx) = disabled.iter().position(|x| x == &pack.id) {
+                        disabled.remove(idx);
+                    } else {
+                        disabled.push(pack.id.clone());
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// The key of the check box for the mod at the given index in the [`ContentPacks`]
+fn content_pack_key(index: usize) -> String {
+    format!("content_pack_{}", index)
+}
+
+/// The page listing the mods that were found, with check boxes to turn them on and off
+#[pre_hooks(use_content_packs_panel)]
+fn content_packs_panel(mut ctx: WidgetContext) -> WidgetNode {
+    let game_info: GameInfo = ctx.shared_props.read_cloned().unwrap();
+    let ContentPacksPanelProps {
+        close_notify_id,
+        close_notify_message,
+    } = ctx.props.read_cloned_or_default();
+
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let user_settings = world.get_resource::<UserSettings>().unwrap();
+    let content_packs = world.get_resource::<ContentPacks>().unwrap();
+    let font = game_info.ui_theme.default_font;
+
+    let panel_props = Props::new(ContentBoxItemLayout {
+        margin: Rect {
+            left: 13.,
+            right: 13.,
+            top: 7.,
+            bottom: 7.,
+        },
+        ..Default::default()
+    })
+    .with(PaperProps {
+        variant: "panel".into(),
+        frame: None,
+    });
+
+    let title_props = Props::new(TextBoxProps {
+        text: "Mods".into(),
+        font: TextBoxFont {
+            name: font.clone(),
+            size: 1.0,
+        },
+        horizontal_align: TextBoxHorizontalAlign::Center,
+        color: Color {
+            r: 0.,
+            g: 0.,
+            b: 0.,
+            a: 1.,
+        },
+        ..Default::default()
+    })
+    .with(FlexBoxItemLayout {
+        grow: 0.,
+        basis: Some(16.),
+        ..Default::default()
+    });
+    let restart_note_props =
+        settings_section_title_props(&font, "Restart the game to apply changes");
+
+    // A row with a check box for each mod
+    let mut pack_list = make_widget!(vertical_box);
+    for (i, pack) in content_packs.packs.iter().enumerate() {
+        let manifest = &pack.manifest;
+        let mut label = manifest.name.clone();
+        if !manifest.version.is_empty() {
+            label = format!("{} {}", label, manifest.version);
+        }
+        if !manifest.author.is_empty() {
+            label = format!("{} by {}", label, manifest.author);
+        }
+        let enabled = !user_settings.disabled_content_packs.contains(&pack.id);
+
+        pack_list = pack_list.listed_slot(
+            make_widget!(horizontal_box)
+                .merge_props(Props::new(FlexBoxItemLayout {
+                    grow: 0.0,
+                    basis: Some(17.),
+                    margin: Rect {
+                        top: 5.,
+                        left: 10.,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }))
+                .listed_slot(
+                    make_widget!(switch_button_paper)
+                        .key(content_pack_key(i))
+                        .merge_props(settings_check_box_props(enabled, ctx.id.to_owned())),
+                )
+                .listed_slot(
+                    make_widget!(text_box).merge_props(settings_label_props(&font, &label)),
+                ),
+        );
+    }
+
+    let close_button_props = Props::new(FlexBoxItemLayout {
+        align: 0.5,
+        grow: 0.0,
+        margin: Rect {
+            top: 5.,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .with(GameButtonProps {
+        text: "Back".into(),
+        notify_id: close_notify_id,
+        message_name: close_notify_message,
+    });
+
+    let margin_box_props = FlexBoxItemLayout {
+        margin: Rect {
+            top: 10.,
+            bottom: 10.,
+            left: 15.,
+            right: 15.,
+        },
+        ..Default::default()
+    };
+
+    widget! {
+        (nav_content_box [
+            (nav_vertical_paper: {panel_props} [
+                (vertical_box: {margin_box_props} [
+                    (text_box: {title_props})
+                    (text_box: {restart_note_props})
+                    {pack_list}
+                    (game_button: {close_button_props})
+                ])
+            ])
+        ])
+    }
+}
+
+/// Props for the title of a section in the settings panel
+fn settings_section_title_props(font: &str, text: &str) -> Props {
+    Props::new(TextBoxProps {
+        text: text.into(),
+        font: TextBoxFont {
+            name: font.into(),
+            size: 1.0,
+        },
+        color: Color {
+            r: 0.,
+            g: 0.,
+            b: 0.,
+            a: 1.,
+        },
+        ..Default::default()
+    })
+    .with(FlexBoxItemLayout {
+        grow: 0.0,
+        align: 0.0,
+        basis: Some(16.),
+        margin: Rect {
+            left: 5.,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+/// Props for the label next to a setting in the settings panel
+fn settings_label_props(font: &str, text: &str) -> Props {
+    Props::new(TextBoxProps {
+        text: text.into(),
+        font: TextBoxFont {
+            name: font.into(),
+            size: 1.0,
+        },
+        color: Color {
+            r: 0.,
+            g: 0.,
+            b: 0.,
+            a: 1.,
+        },
+        ..Default::default()
+    })
+    .with(FlexBoxItemLayout {
+        margin: Rect {
+            left: 10.,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+/// Props for a settings check box that notifies the settings panel with the given id when clicked
+fn settings_check_box_props(on: bool, notify_id: WidgetId) -> Props {
+    Props::new(SwitchPaperProps {
+        on,
+        variant: "checkbox".into(),
+        size_level: 1,
+    })
+    .with(NavItemActive)
+    .with(ButtonNotifyProps(notify_id.into()))
+    .with(ThemedWidgetProps {
+        color: ThemeColor::Primary,
+        variant: ThemeVariant::ContentOnly,
+    })
+    .with(FlexBoxItemLayout {
+        grow: 0.0,
+        ..Default::default()
+    })
+}
+
+/// Props for a settings button showing the current value of a setting, that cycles through the
+/// values by sending `message_name` to the settings panel when clicked
+fn settings_cycle_button_props(value: &str, notify_id: WidgetId, message_name: &str) -> Props {
+    Props::new(FlexBoxItemLayout {
+        grow: 0.0,
+        ..Default::default()
+    })
+    .with(GameButtonProps {
+        text: value.into(),
+        notify_id,
+        message_name: message_name.into(),
+    })
+}
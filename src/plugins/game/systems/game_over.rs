@@ -8,11 +8,30 @@ use bevy_retrograde::prelude::{
 };
 
 use crate::plugins::game::{
+    affinity::Affinities,
     assets::GameInfo,
+    clock::GameClock,
     components::{CurrentLevel, CurrentLevelMusic},
+    dungeon::DungeonRun,
+    entity_pool::EntityPool,
+    inventory::Inventory,
+    level_state::LevelStates,
+    meta::MetaProgress,
+    score::Score,
 };
 
-use super::GameState;
+use super::{
+    abilities::Projectile,
+    bombs::Explosion,
+    cutscenes::CutscenePlayer,
+    footprints::Footprint,
+    gameplay::{stop_heartbeat, EnemyPathfindingDebugViz, HealthFeedback},
+    gathering::Gathering,
+    post_processing::PostProcessState,
+    results::{LevelResults, LevelStats},
+    stealth::VisionConeDebugViz,
+    GameState,
+};
 
 pub fn run_game_over_screen(
     mut has_shown_game_over: Local<bool>,
@@ -23,6 +42,7 @@ pub fn run_game_over_screen(
     mut ui_tree: ResMut<UiTree>,
     current_level_music: Option<Res<CurrentLevelMusic>>,
     mut sound_controller: SoundController,
+    mut health_feedback: ResMut<HealthFeedback>,
     keyboard_input: Res<Input<KeyCode>>,
     mouse_input: Res<Input<MouseButton>>,
     time: Res<Time>,
@@ -48,6 +68,9 @@ pub fn run_game_over_screen(
         }
         commands.remove_resource::<CurrentLevelMusic>();
 
+        // Stop the low-health heartbeat
+        stop_heartbeat(&mut health_feedback, &mut sound_controller);
+
         // Clear the current level
         commands.remove_resource::<CurrentLevel>();
 
@@ -66,36 +89,78 @@ pub fn run_game_over_screen(
             || keyboard_input.just_pressed(KeyCode::Escape)
             || mouse_input.just_pressed(MouseButton::Left)
         {
-            // Clear the game info
-            commands.remove_resource::<GameInfo>();
-
-            // Despawn all entities
-            for entity in all_entities.iter() {
-                commands.entity(entity).despawn();
-            }
-
-            // Transition to the game init state to restart the game
-            state
-                .replace(GameState::Init)
-                .expect("Could not transition to game init state");
+            restart_game(&mut commands, &all_entities, &mut state, &mut ui_tree);
 
             // Reset game over display state
             *has_shown_game_over = false;
-            *ui_tree = UiTree(widget!(()));
         }
     }
 }
 
+/// Clear the game world and transition back to the game init state to restart the game
+pub fn restart_game(
+    commands: &mut Commands,
+    all_entities: &Query<Entity>,
+    state: &mut State<GameState>,
+    ui_tree: &mut UiTree,
+) {
+    // Clear the game info and current level
+    commands.remove_resource::<GameInfo>();
+    commands.remove_resource::<CurrentLevel>();
+
+    // Forget the level shaders, the camera will be re-created
+    commands.insert_resource(PostProcessState::default());
+
+    // Reset the world state
+    commands.insert_resource(LevelStates::default());
+    commands.insert_resource(Inventory::default());
+    commands.insert_resource(GameClock::default());
+    commands.insert_resource(Affinities::default());
+    commands.insert_resource(DungeonRun::default());
+    commands.insert_resource(MetaProgress::default());
+    commands.insert_resource(Gathering::default());
+    commands.insert_resource(CutscenePlayer::default());
+    commands.insert_resource(LevelStats::default());
+    commands.insert_resource(LevelResults::default());
+    commands.insert_resource(Score::default());
+
+    // Forget pooled entities, which are about to be despawned
+    commands.insert_resource(EntityPool::<EnemyPathfindingDebugViz>::default());
+    commands.insert_resource(EntityPool::<VisionConeDebugViz>::default());
+    commands.insert_resource(EntityPool::<Footprint>::default());
+    commands.insert_resource(EntityPool::<Projectile>::default());
+    commands.insert_resource(EntityPool::<Explosion>::default());
+
+    // Despawn all entities
+    for entity in all_entities.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    // Transition to the game init state to restart the game
+    state
+        .replace(GameState::Init)
+        .expect("Could not transition to game init state");
+
+    *ui_tree = UiTree(widget!(()));
+}
+
 mod ui {
     use bevy::prelude::World;
     use bevy_retrograde::prelude::raui::prelude::*;
 
-    use crate::plugins::game::assets::GameInfo;
+    use crate::plugins::game::{
+        assets::GameInfo,
+        systems::ui_utils::{safe_area_box, UiLayout},
+    };
 
     pub fn game_over_screen(ctx: WidgetContext) -> WidgetNode {
         let world: &mut World = ctx.process_context.get_mut().unwrap();
 
         let game_info = world.get_resource::<GameInfo>().unwrap();
+        let ui_layout = world
+            .get_resource::<UiLayout>()
+            .cloned()
+            .unwrap_or_default();
 
         make_widget!(content_box)
             // Add a black background
@@ -112,22 +177,25 @@ mod ui {
                 ..Default::default()
             }))
             // The "Game Over" text centered in the screen
-            .listed_slot(make_widget!(text_box).with_props(TextBoxProps {
-                color: Color {
-                    r: 1.,
-                    g: 1.,
-                    b: 1.,
-                    a: 1.,
-                },
-                text: "Game Over".into(),
-                font: TextBoxFont {
-                    name: game_info.ui_theme.default_font.clone(),
-                    size: 1.,
-                },
-                horizontal_align: TextBoxHorizontalAlign::Center,
-                vertical_align: TextBoxVerticalAlign::Middle,
-                ..Default::default()
-            }))
+            .listed_slot(safe_area_box(
+                &ui_layout,
+                make_widget!(text_box).with_props(TextBoxProps {
+                    color: Color {
+                        r: 1.,
+                        g: 1.,
+                        b: 1.,
+                        a: 1.,
+                    },
+                    text: "Game Over".into(),
+                    font: TextBoxFont {
+                        name: game_info.ui_theme.default_font.clone(),
+                        size: 1.,
+                    },
+                    horizontal_align: TextBoxHorizontalAlign::Center,
+                    vertical_align: TextBoxVerticalAlign::Middle,
+                    ..Default::default()
+                }),
+            ))
             .into()
     }
 }
@@ -0,0 +1,101 @@
+use super::*;
+use crate::EngineConfig;
+
+/// The prefix of prop interactions that slow the game down for a while, followed by the speed and
+/// the number of seconds it lasts, i.e. `slow_motion:0.5:5`
+const SLOW_MOTION_INTERACTION_PREFIX: &str = "slow_motion:";
+
+/// Resource for how fast the game runs, which is applied to physics and sprite animations
+///
+/// The speed combines the `game_speed` engine option, the player's game speed setting, and any
+/// slow motion from a pickup.
+pub struct GameSpeed {
+    /// The slow motion from a pickup in progress, if any
+    pub slow_motion: Option<SlowMotion>,
+    /// The combined speed the game is running at
+    speed: f32,
+}
+
+impl Default for GameSpeed {
+    fn default() -> Self {
+        Self {
+            slow_motion: None,
+            speed: 1.,
+        }
+    }
+}
+
+impl GameSpeed {
+    /// Get the combined speed the game is running at, i.e. `0.5` for half speed
+    pub fn get(&self) -> f32 {
+        self.speed
+    }
+}
+
+/// A temporary change to the game speed
+#[derive(Debug, Clone)]
+pub struct SlowMotion {
+    /// The speed of the game during the slow motion
+    pub speed: f32,
+    /// The time left in seconds, counted in real time instead of game time
+    pub remaining: f32,
+}
+
+/// Start slow motion when the player interacts with a prop with a `slow_motion:` interaction,
+/// removing the prop
+pub fn collect_slow_motion_pickups(
+    mut commands: Commands,
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    mut game_speed: ResMut<GameSpeed>,
+) {
+    for event in interaction_events.iter() {
+        let (speed, duration) = if let Some(slow_motion) = event
+            .interaction
+            .strip_prefix(SLOW_MOTION_INTERACTION_PREFIX)
+            .and_then(|x| x.split_once(':'))
+            .and_then(|(speed, duration)| Some((speed.parse().ok()?, duration.parse().ok()?)))
+        {
+            slow_motion
+        } else {
+            continue;
+        };
+
+        game_speed.slow_motion = Some(SlowMotion {
+            speed,
+            remaining: duration,
+        });
+        commands.entity(event.prop).despawn_recursive();
+    }
+}
+
+/// Update the game speed and apply it to the physics time while the game is playing
+pub fn update_game_speed(
+    mut applied_speed: Local<Option<f32>>,
+    mut game_speed: ResMut<GameSpeed>,
+    mut physics_time: ResMut<PhysicsTime>,
+    engine_config: Res<EngineConfig>,
+    settings: Res<UserSettings>,
+    time: Res<Time>,
+) {
+    if let Some(slow_motion) = &mut game_speed.slow_motion {
+        slow_motion.remaining -= time.delta_seconds();
+        if slow_motion.remaining <= 0. {
+            game_speed.slow_motion = None;
+        }
+    }
+
+    let slow_motion_speed = game_speed.slow_motion.as_ref().map_or(1., |x| x.speed);
+    let speed = (engine_config.game_speed
+        * settings.accessibility.game_speed.clamp(0., 1.)
+        * slow_motion_speed)
+        .max(0.01);
+    if game_speed.speed != speed {
+        game_speed.speed = speed;
+    }
+
+    // Only set the physics time scale when it changes so that we don't interfere with pausing
+    if *applied_speed != Some(speed) {
+        *applied_speed = Some(speed);
+        physics_time.set_scale(speed);
+    }
+}
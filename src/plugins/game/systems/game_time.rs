@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use super::{fixed_step::fixed_delta, game_speed::GameSpeed, *};
+
+/// Resource for the time that has passed in the game world, to be used instead of [`Time`] by
+/// gameplay timers
+///
+/// Game time only passes while the game is [`GameState::Playing`], and it passes at the
+/// [`GameSpeed`], so timers using it stop while the game is paused and slow down in slow motion.
+#[derive(Debug, Clone)]
+pub struct GameTime {
+    /// The game time that passed during the last frame
+    delta: Duration,
+    /// The game time that has passed since the game started, in seconds
+    seconds_since_startup: f64,
+    /// How fast game time is passing compared to real time
+    speed: f32,
+}
+
+impl Default for GameTime {
+    fn default() -> Self {
+        Self {
+            delta: Duration::default(),
+            seconds_since_startup: 0.,
+            speed: 1.,
+        }
+    }
+}
+
+impl GameTime {
+    /// The game time that passed during the last frame
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// The game time that passed during the last frame, in seconds
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// The game time that has passed since the game started, in seconds
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.seconds_since_startup
+    }
+
+    /// The game time that passes during a gameplay simulation step, to be used by systems in the
+    /// [`GameStage::FixedUpdate`] stage
+    pub fn fixed_delta(&self) -> Duration {
+        fixed_delta().mul_f32(self.speed)
+    }
+}
+
+/// Advance the [`GameTime`] by the time since the last frame, if the game is playing
+pub fn update_game_time(
+    mut game_time: ResMut<GameTime>,
+    state: Res<State<GameState>>,
+    game_speed: Res<GameSpeed>,
+    time: Res<Time>,
+) {
+    let speed = if state.current() == &GameState::Playing {
+        game_speed.get()
+    } else {
+        0.
+    };
+
+    game_time.speed = speed;
+    game_time.delta = time.delta().mul_f32(speed);
+    game_time.seconds_since_startup += game_time.delta.as_secs_f64();
+}
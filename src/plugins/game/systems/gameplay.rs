@@ -1,15 +1,30 @@
 use std::time::Duration;
 
+use bevy::utils::{HashMap, HashSet};
 use bevy_retrograde::physics::heron::rapier_plugin::PhysicsWorld;
 use bevy_retrograde::prelude::{kira::parameter::tween::Tween, raui::core::make_widget};
 use itertools::Itertools;
 
 use crate::utils::{IntoBevy, IntoNav};
 
-use super::map_loading::LdtkMapLevelNavigationMeshes;
+use super::attract_mode::AttractMode;
+use super::cutscenes::CutscenePlayer;
+use super::lifting::Carrying;
+use super::map_loading::MapLevelNavigationMeshes;
+use super::perf_hud::PathfindingStats;
+use super::spatial_hash::EnemySpatialHash;
 use super::*;
-
+use super::{fixed_step::HeldControls, game_time::GameTime};
+
+mod camera_shake;
+pub use camera_shake::{apply_camera_shake, CameraShake};
+mod health_feedback;
+pub use health_feedback::{
+    pause_heartbeat, stop_heartbeat, update_health_feedback, HealthFeedback,
+};
 mod hud;
+mod mouse;
+pub use mouse::{mouse_control_input, update_cursor_world_position, CursorWorldPosition};
 
 /// The amount of health an object that can die or be destroyed has
 pub struct Health {
@@ -31,11 +46,21 @@ pub fn spawn_hud(state: Res<State<GameState>>, mut ui: ResMut<UiTree>) {
     }
 }
 
-/// Switch to game over when the player runs out of health
+/// Switch to game over when the player runs out of health, unless the game has a dungeon hub to
+/// send them back to
 pub fn check_for_game_over(
     characters: Query<&Health, With<Handle<Character>>>,
     mut state: ResMut<State<GameState>>,
+    game_info: Res<GameInfo>,
 ) {
+    if game_info
+        .dungeon
+        .as_ref()
+        .map_or(false, |x| x.hub.is_some())
+    {
+        return;
+    }
+
     for character_health in characters.iter() {
         // If player health is 0, then go to game over
         if character_health.current == 0 {
@@ -52,7 +77,14 @@ pub fn touch_control_input(
     mut touch_events: EventReader<TouchInput>,
     mut control_events: EventWriter<ControlEvent>,
     touches: Res<Touches>,
+    attract_mode: Res<AttractMode>,
+    cutscene_player: Res<CutscenePlayer>,
 ) {
+    // The attract mode replay or a cutscene is in control
+    if attract_mode.active || cutscene_player.input_locked {
+        return;
+    }
+
     for touch in touch_events.iter() {
         if let Some(&id) = tracked_touch.as_ref() {
             if touch.id == id {
@@ -100,8 +132,21 @@ pub fn keyboard_control_input(
     keyboard_input: Res<Input<KeyCode>>,
     mut state: ResMut<State<GameState>>,
     mut physics_time: ResMut<PhysicsTime>,
+    attract_mode: Res<AttractMode>,
+    mut pause_request: ResMut<PauseRequest>,
+    cutscene_player: Res<CutscenePlayer>,
 ) {
-    if keyboard_input.pressed(KeyCode::Escape) && !*pause_was_pressed {
+    // The attract mode replay is in control
+    if attract_mode.active {
+        return;
+    }
+
+    let pause_requested = pause_request.0 == Some(true);
+    if pause_requested {
+        pause_request.0 = None;
+    }
+
+    if (keyboard_input.pressed(KeyCode::Escape) && !*pause_was_pressed) || pause_requested {
         debug!("Pausing game");
         state
             .push(GameState::Paused)
@@ -112,6 +157,11 @@ pub fn keyboard_control_input(
         *pause_was_pressed = false;
     }
 
+    // The game can still be paused during cutscenes, but the player can't move
+    if cutscene_player.input_locked {
+        return;
+    }
+
     if keyboard_input.pressed(KeyCode::Left) {
         control_events.send(ControlEvent::MoveLeft);
     }
@@ -127,11 +177,24 @@ pub fn keyboard_control_input(
     if keyboard_input.pressed(KeyCode::Down) {
         control_events.send(ControlEvent::MoveDown);
     }
+
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        control_events.send(ControlEvent::Interact);
+    }
+
+    // Use the abilities in the first four slots with the number keys
+    for (slot, key) in [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4]
+        .iter()
+        .enumerate()
+    {
+        if keyboard_input.pressed(*key) {
+            control_events.send(ControlEvent::Ability(slot as u8));
+        }
+    }
 }
 
 /// Marker component for loaded characters
 pub struct CharacterLoaded;
-pub struct CharacterAnimationTimer(pub Timer);
 /// Add the sprite image and sprite sheet handles to the spawned character
 pub fn finish_spawning_character(
     mut commands: Commands,
@@ -145,6 +208,9 @@ pub fn finish_spawning_character(
         Without<CharacterLoaded>,
     >,
     character_assets: Res<Assets<Character>>,
+    game_info: Res<GameInfo>,
+    save: Option<Res<SaveGame>>,
+    asset_server: Res<AssetServer>,
 ) {
     for (ent, character_handle, mut image_handle, mut sprite_sheet_handle) in characters.iter_mut()
     {
@@ -153,6 +219,15 @@ pub fn finish_spawning_character(
             *image_handle = character.sprite_image.clone();
             *sprite_sheet_handle = character.sprite_sheet.clone();
 
+            // Wear the equipment from the save game, or the character's starting equipment
+            let equipment = match save.as_ref().and_then(|x| x.equipment.as_ref()) {
+                Some(items) => items
+                    .iter()
+                    .map(|x| asset_server.load(x.as_str()))
+                    .collect(),
+                None => character.equipment.clone(),
+            };
+
             commands
                 .entity(ent)
                 // Add the character loaded marker so we don't do this again
@@ -162,6 +237,17 @@ pub fn finish_spawning_character(
                     max: character.max_health,
                     current: character.max_health,
                 })
+                // Give them their equipment, which changes their stats once it loads
+                .insert(Equipment { items: equipment })
+                .insert(Stats::base(character))
+                .insert(SpeedModifiers::default())
+                // Give them their abilities and a full bar of energy to use them with
+                .insert(Abilities::new(character.abilities.iter().cloned()))
+                .insert(Energy {
+                    current: character.max_energy as f32,
+                    max: character.max_energy as f32,
+                    regen: character.energy_regen,
+                })
                 // Set the character's collision shape to it's tesselated collider image
                 .insert(TesselatedCollider {
                     image: character.collision_shape.clone(),
@@ -182,22 +268,18 @@ pub fn finish_spawning_character(
                 })
                 // And make it a dynamic body
                 .insert(RigidBody::Dynamic)
-                // Add a timer that will be used for calculating animation frames
-                .insert(CharacterAnimationTimer(Timer::new(
-                    Duration::from_millis(100),
-                    true,
-                )))
-                .insert(CollisionLayers::from_bits(
-                    // Put in the player group
-                    PhysicsGroup::Player.to_bits(),
-                    // Interact with all other groups
-                    PhysicsGroup::all_bits(),
-                ));
+                // Start the character's animation
+                .insert(SpriteAnimation::new(
+                    character.animations.clone(),
+                    "idle_down",
+                ))
+                // Put in the player group
+                .insert(game_info.collision_layers.collision_layers(&["player"]));
         }
     }
 }
 
-/// Move the character in response to character control events
+/// Move the character in response to the held controls
 pub fn control_character(
     mut characters: Query<
         (
@@ -205,16 +287,24 @@ pub fn control_character(
             &Transform,
             &mut CharacterState,
             &mut Velocity,
+            Option<&SpeedModifiers>,
         ),
         With<Handle<Character>>,
     >,
     character_assets: Res<Assets<Character>>,
-    mut control_events: EventReader<ControlEvent>,
-    time: Res<Time>,
+    held_controls: Res<HeldControls>,
+    game_info: Res<GameInfo>,
+    cursor_world_position: Res<CursorWorldPosition>,
+    game_time: Res<GameTime>,
 ) {
     // Loop through characters
-    for (character_handle, character_transform, mut character_state, mut character_velocity) in
-        characters.iter_mut()
+    for (
+        character_handle,
+        character_transform,
+        mut character_state,
+        mut character_velocity,
+        speed_modifiers,
+    ) in characters.iter_mut()
     {
         let character = if let Some(character) = character_assets.get(character_handle) {
             character
@@ -238,8 +328,8 @@ pub fn control_character(
             } = &mut character_state.action
             {
                 // Tick the knock-back timers
-                force_timer.tick(time.delta());
-                freeze_timer.tick(time.delta());
+                force_timer.tick(game_time.fixed_delta());
+                freeze_timer.tick(game_time.fixed_delta());
 
                 let mut skip_controls = false;
 
@@ -263,16 +353,15 @@ pub fn control_character(
         }
 
         // Determine movement direction
-        let mut directions = HashSet::default();
-        for control_event in control_events.iter() {
+        for control_event in held_controls.controls.iter() {
             let z = character_transform.translation.z;
-            if directions.insert(control_event) {
-                match control_event {
-                    ControlEvent::MoveUp => movement += Vec3::new(0., -1., z),
-                    ControlEvent::MoveDown => movement += Vec3::new(0., 1., z),
-                    ControlEvent::MoveLeft => movement += Vec3::new(-1., 0., z),
-                    ControlEvent::MoveRight => movement += Vec3::new(1., 0., z),
-                }
+            match control_event {
+                ControlEvent::MoveUp => movement += Vec3::new(0., -1., z),
+                ControlEvent::MoveDown => movement += Vec3::new(0., 1., z),
+                ControlEvent::MoveLeft => movement += Vec3::new(-1., 0., z),
+                ControlEvent::MoveRight => movement += Vec3::new(1., 0., z),
+                // Attacking, interacting, and using abilities don't move the character
+                ControlEvent::Attack | ControlEvent::Interact | ControlEvent::Ability(_) => (),
             }
         }
 
@@ -299,10 +388,15 @@ pub fn control_character(
             }
         }
 
-        // Reset character animation frame if direction or action changes
-        if new_direction != character_state.direction || new_action != character_state.action {
-            character_state.anim_frame_idx = 0;
+        // Face the mouse cursor if mouse controls are enabled
+        if game_info.mouse.enabled {
+            if let Some(direction) =
+                mouse::direction_to_cursor(character_transform.translation, &cursor_world_position)
+            {
+                new_direction = direction;
+            }
         }
+
         // Update character action
         if new_action != character_state.action {
             character_state.action = new_action;
@@ -314,7 +408,10 @@ pub fn control_character(
 
         if movement.length() > f32::EPSILON {
             // Set player speed
-            movement = movement.normalize() * character.walk_speed;
+            let walk_speed = speed_modifiers
+                .map(|x| x.apply(character.walk_speed))
+                .unwrap_or(character.walk_speed);
+            movement = movement.normalize() * walk_speed;
         }
 
         // Update player velocity
@@ -326,47 +423,56 @@ pub fn control_character(
 pub fn damage_character(
     mut characters: Query<
         (
+            Entity,
             &mut Velocity,
             &mut CharacterState,
             &mut Health,
             &GlobalTransform,
+            Option<&Stats>,
         ),
         With<Handle<Character>>,
     >,
     damage_regions: Query<(&DamageRegion, &GlobalTransform)>,
-    mut collision_events: EventReader<CollisionEvent>,
+    mut collision_events: EventReader<Collision<Handle<Character>, DamageRegion>>,
+    mut damaged_events: EventWriter<CharacterDamagedEvent>,
 ) {
-    // Check characters colliding with entrances
+    // Check characters colliding with damage regions
     for event in collision_events.iter() {
-        let (ent1, ent2) = event.collision_shape_entities();
-
         // If this is not a started event, skip it
         if !event.is_started() {
             continue;
         }
 
-        // Get the character from the collision or skip the event
-        let (mut character_velocity, mut character_state, mut character_health, character_location) =
-            if let Ok(character) = characters.get_mut(ent1) {
-                character
-            } else if let Ok(character) = characters.get_mut(ent2) {
-                character
-            } else {
-                continue;
-            };
-
-        // Get the damage region of the collision or skip the event
-        let (damage_region, damage_region_location) = if let Ok(region) = damage_regions
-            .get(ent1)
-            .or_else(|_| damage_regions.get(ent2))
-        {
-            region
+        // Get the character and damage region from the collision
+        let (
+            character_ent,
+            mut character_velocity,
+            mut character_state,
+            mut character_health,
+            character_location,
+            stats,
+        ) = if let Ok(character) = characters.get_mut(event.a) {
+            character
         } else {
             continue;
         };
+        let (damage_region, damage_region_location) =
+            if let Ok(region) = damage_regions.get(event.b) {
+                region
+            } else {
+                continue;
+            };
 
-        // Damage the player
-        character_health.current -= damage_region.damage.min(character_health.current);
+        // Damage the player, less their defense
+        let damage = stats
+            .map(|x| x.reduce_damage(damage_region.damage))
+            .unwrap_or(damage_region.damage)
+            .min(character_health.current);
+        character_health.current -= damage;
+        damaged_events.send(CharacterDamagedEvent {
+            character: character_ent,
+            damage,
+        });
 
         // Put the player into knock-back frames
         character_state.action = CharacterStateAction::DamageKnockBack {
@@ -394,65 +500,136 @@ pub fn damage_character(
     }
 }
 
-/// Play the character's sprite animation
+/// Get the name of the direction a character is facing, which is appended to its clip names
+pub fn direction_clip_suffix(direction: CharacterStateDirection) -> &'static str {
+    match direction {
+        CharacterStateDirection::Up => "up",
+        CharacterStateDirection::Down => "down",
+        CharacterStateDirection::Left => "left",
+        CharacterStateDirection::Right => "right",
+    }
+}
+
+/// Get the animation clip for a character's current action and direction
+fn character_clip(state: &CharacterState) -> String {
+    let action = match state.action {
+        CharacterStateAction::Idle | CharacterStateAction::DamageKnockBack { .. } => "idle",
+        CharacterStateAction::Walk => "walk",
+    };
+
+    format!("{}_{}", action, direction_clip_suffix(state.direction))
+}
+
+/// Play the character's animation clip for its current action and direction
 pub fn animate_sprites(
-    characters: Res<Assets<Character>>,
-    mut query: Query<(
-        &Handle<SpriteSheet>,
-        &mut Sprite,
-        &mut CharacterState,
-        &Handle<Character>,
-        &mut CharacterAnimationTimer,
-    )>,
-    mut sprite_sheet_assets: ResMut<Assets<SpriteSheet>>,
-    time: Res<Time>,
+    mut query: Query<(&CharacterState, &mut SpriteAnimation), Changed<CharacterState>>,
 ) {
-    // For every character and their sprites
-    for (sprite_sheet, mut sprite, mut state, character_handle, mut timer) in query.iter_mut() {
-        // Tick their animation timer
-        timer.0.tick(time.delta());
-
-        // If the timer has finished or if our animation state has changed
-        if timer.0.just_finished() || state.is_changed() {
-            // Reset the timer
-            timer.0.set_elapsed(Duration::from_millis(0));
-
-            // If the spritesheet info is loaded
-            if let Some(sprite_sheet) = sprite_sheet_assets.get_mut(sprite_sheet) {
-                let character = characters.get(character_handle).unwrap();
-
-                // Get the character info for our current action
-                let action = match state.action {
-                    CharacterStateAction::Idle | CharacterStateAction::DamageKnockBack { .. } => {
-                        &character.actions.idle
-                    }
-                    CharacterStateAction::Walk => &character.actions.walk,
-                };
+    for (state, mut animation) in query.iter_mut() {
+        // Let clips played once, like ability animations, finish first
+        if animation.one_shot && !animation.finished {
+            continue;
+        }
 
-                // Get the animation frames for the direction we are facing
-                let direction = match state.direction {
-                    CharacterStateDirection::Up => &action.animations.up,
-                    CharacterStateDirection::Down => &action.animations.down,
-                    CharacterStateDirection::Left => &action.animations.left,
-                    CharacterStateDirection::Right => &action.animations.right,
-                };
+        // Switch clips, which does nothing if the clip is already playing
+        animation.play(&character_clip(state));
+    }
+}
 
-                // Flip the sprite if necessary
-                if direction.flip {
-                    sprite.flip_x = true;
-                } else {
-                    sprite.flip_x = false;
-                }
+/// Go back to the character's clip for its current state when a clip played once finishes
+pub fn resume_character_animations(
+    mut finished_events: EventReader<SpriteAnimationFinishedEvent>,
+    mut query: Query<(&CharacterState, &mut SpriteAnimation)>,
+) {
+    for event in finished_events.iter() {
+        if let Ok((state, mut animation)) = query.get_mut(event.entity) {
+            if animation.one_shot {
+                animation.play(&character_clip(state));
+            }
+        }
+    }
+}
+
+/// How close the player has to be to a prop to interact with it, in pixels
+const PROP_INTERACTION_DISTANCE: f32 = 24.;
 
-                // Get the index of the current animation frame
-                let idx = direction.frames[state.anim_frame_idx as usize % direction.frames.len()];
+/// Send a [`PropInteractionEvent`] for the closest interactive prop when the player interacts
+pub fn interact_with_props(
+    mut control_events: EventReader<ControlEvent>,
+    // Interacting throws the prop the player is carrying instead
+    characters: Query<&Transform, (With<Handle<Character>>, Without<Carrying>)>,
+    props: Query<(Entity, &Prop, &Transform)>,
+    mut interaction_events: EventWriter<PropInteractionEvent>,
+) {
+    if !control_events.iter().any(|x| x == &ControlEvent::Interact) {
+        return;
+    }
+
+    let character_position = if let Some(transform) = characters.iter().next() {
+        transform.translation.truncate()
+    } else {
+        return;
+    };
+
+    let closest_prop = props
+        .iter()
+        .filter_map(|(ent, prop, transform)| {
+            let interaction = prop.interaction.as_ref()?;
+            let distance = transform
+                .translation
+                .truncate()
+                .distance(character_position);
+            (distance <= PROP_INTERACTION_DISTANCE).then(|| (ent, interaction, distance))
+        })
+        .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+
+    if let Some((prop, interaction, _)) = closest_prop {
+        interaction_events.send(PropInteractionEvent {
+            prop,
+            interaction: interaction.clone(),
+        });
+    }
+}
 
-                // Set the current tile in sprite sheet
-                sprite_sheet.tile_index = idx;
+/// Play the sound of the character's current action on its `footstep` animation events
+pub fn play_footstep_sounds(
+    mut animation_events: EventReader<AnimationEvent>,
+    characters: Query<(&Handle<Character>, &CharacterState)>,
+    character_assets: Res<Assets<Character>>,
+    asset_server: Res<AssetServer>,
+    mut sound_controller: SoundController,
+    mut sound_events: EventWriter<SoundPlayedEvent>,
+    engine_config: Res<crate::EngineConfig>,
+) {
+    for event in animation_events.iter() {
+        if event.event != "footstep" || engine_config.mute {
+            continue;
+        }
 
-                // Set
-                state.anim_frame_idx = state.anim_frame_idx.wrapping_add(1);
+        let (character_handle, state) = if let Ok(character) = characters.get(event.entity) {
+            character
+        } else {
+            continue;
+        };
+        let character = if let Some(character) = character_assets.get(character_handle) {
+            character
+        } else {
+            continue;
+        };
+
+        let action = match state.action {
+            CharacterStateAction::Idle | CharacterStateAction::DamageKnockBack { .. } => {
+                &character.actions.idle
             }
+            CharacterStateAction::Walk => &character.actions.walk,
+        };
+
+        if let Some(sound) = &action.sound {
+            let sound_data = asset_server.load_cached(sound.as_str());
+            let sound_handle = sound_controller.create_sound(&sound_data);
+            sound_controller.play_sound(sound_handle);
+            sound_events.send(SoundPlayedEvent {
+                sound: sound.clone(),
+            });
         }
     }
 }
@@ -460,14 +637,15 @@ pub fn animate_sprites(
 // Make the camera follow the character
 pub fn camera_follow_system(
     mut cameras: Query<(&Camera, &mut Transform)>,
-    characters: Query<&GlobalTransform, (With<Handle<Character>>, Without<Camera>)>,
-    mut map_layers: Query<
-        (&mut LdtkMapLayer, &mut Visible, &Handle<Image>, &Transform),
-        Without<Camera>,
-    >,
+    mut follow_position: ResMut<CameraFollowPosition>,
+    // Use the transform instead of the global transform so that we follow the smoothed position
+    characters: Query<&Transform, (With<Handle<Character>>, Without<Camera>)>,
+    mut map_layers: Query<(&MapLayer, &mut Visible, &Handle<Image>, &Transform), Without<Camera>>,
     windows: Res<Windows>,
     image_assets: Res<Assets<Image>>,
     current_level: Option<Res<CurrentLevel>>,
+    game_info: Res<GameInfo>,
+    time: Res<Time>,
 ) {
     let current_level = if let Some(level) = current_level {
         level
@@ -478,10 +656,27 @@ pub fn camera_follow_system(
     if let Ok((camera, mut camera_transform)) = cameras.single_mut() {
         let camera_pos = &mut camera_transform.translation;
 
-        // Start by making the camera stick to the player
+        // Start by making the camera follow the player
         if let Some(character_transform) = characters.iter().next() {
-            camera_pos.x = character_transform.translation.x;
-            camera_pos.y = character_transform.translation.y;
+            let target = character_transform.translation.truncate();
+            let smoothing = game_info.camera_smoothing;
+
+            // Move part of the way to the player, or jump straight there when the player changes
+            // levels or is teleported
+            let position = match follow_position.0 {
+                Some(position)
+                    if smoothing > 0.
+                        && !current_level.is_changed()
+                        && position.distance(target) <= CAMERA_SNAP_DISTANCE =>
+                {
+                    position.lerp(target, 1. - (-time.delta_seconds() / smoothing).exp())
+                }
+                _ => target,
+            };
+            follow_position.0 = Some(position);
+
+            camera_pos.x = position.x;
+            camera_pos.y = position.y;
         }
 
         // If there is a spawned map layer we can find, we want to make sure the camera doesn't show
@@ -496,7 +691,7 @@ pub fn camera_follow_system(
             if layer.level_identifier == **current_level {
                 // Make sure the layer is visible ( if it's supposed to be )
                 if !**layer_visible {
-                    **layer_visible = layer.layer_instance.visible;
+                    **layer_visible = layer.visible;
                 }
 
                 if !has_constrained_camera {
@@ -558,6 +753,65 @@ pub fn camera_follow_system(
     }
 }
 
+/// How close in pixels an enemy following a pack leader has to be to its place in the formation
+/// to stop moving
+const FORMATION_TOLERANCE: f32 = 2.;
+
+/// The leader of a [`Pack`] of enemies
+#[derive(Clone, Copy)]
+struct PackLeader {
+    entity: Entity,
+    order: u32,
+    position: Vec3,
+    offset: Vec2,
+}
+
+/// The path cost at which enemy paths are colored fully red when the `path-costs` debug rendering
+/// is enabled, which is the cost of a path of this many pixels over tiles without a navigation cost
+const PATH_COST_RED: f32 = 320.;
+
+/// How far apart the navigation cost of a path is sampled along it, in pixels
+const PATH_COST_SAMPLE_DISTANCE: f32 = 4.;
+
+/// Get the cost of following a path from the given start, which is its length weighted by the
+/// navigation cost of the navmesh triangles it crosses
+fn path_cost(mesh: &navmesh::NavMesh, start: Vec3, path: &[navmesh::NavVec3]) -> f32 {
+    let area_cost = |point: Vec3| {
+        mesh.find_closest_triangle(point.into_nav(), navmesh::NavQuery::Accuracy)
+            .and_then(|triangle| mesh.areas().get(triangle))
+            .map_or(1., |area| area.cost)
+    };
+
+    std::iter::once(start)
+        .chain(path.iter().map(|x| x.into_bevy()))
+        .tuple_windows()
+        .map(|(v1, v2)| {
+            // Sample the cost at the middle of evenly spaced steps along the segment
+            let length = v1.distance(v2);
+            let steps = (length / PATH_COST_SAMPLE_DISTANCE).ceil().max(1.);
+            let cost = (0..steps as usize)
+                .map(|i| area_cost(v1.lerp(v2, (i as f32 + 0.5) / steps)))
+                .sum::<f32>();
+            cost * length / steps
+        })
+        .sum()
+}
+
+/// Get the color to draw an enemy's path with, from green for cheap paths to red for costly ones
+fn path_cost_color(cost: f32) -> epaint::Color32 {
+    let cost = (cost / PATH_COST_RED).clamp(0., 1.);
+    epaint::Color32::from_rgb((255. * cost) as u8, (255. * (1. - cost)) as u8, 0)
+}
+
+/// The distance in pixels from the player past which the camera jumps to the player instead of
+/// smoothly catching up, i.e. when the player is teleported
+const CAMERA_SNAP_DISTANCE: f32 = 160.;
+
+/// Resource for the position that the camera follows the player from, before it is kept inside the
+/// level, which lags behind the player when the game's `camera-smoothing` is set
+#[derive(Default)]
+pub struct CameraFollowPosition(Option<Vec2>);
+
 /// Enumerates different states the entrance transition logic can be in
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum EntranceStatus {
@@ -580,42 +834,34 @@ pub fn change_level(
     mut status: Local<EntranceStatus>,
     mut commands: Commands,
     mut cameras: Query<&mut Camera>,
-    maps: Query<&Handle<LdtkMap>>,
-    map_assets: Res<Assets<LdtkMap>>,
+    maps: Query<&GameMap>,
     mut current_level: ResMut<CurrentLevel>,
     mut current_level_music: Option<ResMut<CurrentLevelMusic>>,
     mut sound_controller: SoundController,
     asset_server: Res<AssetServer>,
     entrances: Query<&Entrance>,
-    mut characters: Query<&mut Transform, With<Handle<Character>>>,
-    mut collision_events: EventReader<CollisionEvent>,
+    mut characters: Query<(Entity, &mut Transform, &mut YSort), With<Handle<Character>>>,
+    mut collision_events: EventReader<Collision<Handle<Character>, Entrance>>,
+    mut load_level_events: EventReader<LoadLevelEvent>,
+    mut sound_events: EventWriter<SoundPlayedEvent>,
+    mut critical_assets: ResMut<CriticalAssets>,
+    mut error_events: EventWriter<FatalErrorEvent>,
 ) {
     // Get the map
     let map = if let Ok(map) = maps.single() {
-        if let Some(map) = map_assets.get(map) {
-            map
-        } else {
-            return;
-        }
+        map
     } else {
         return;
     };
 
+    // The characters to teleport, with the level and the entrance to teleport them to. Characters
+    // without an entrance are teleported to the level's `PlayerStart` spawn point.
+    let mut teleports = Vec::new();
+
     // Check characters colliding with entrances
     for event in collision_events.iter() {
-        let (ent1, ent2) = event.collision_shape_entities();
-
-        // Skip non-character collisions
-        let mut character_transform = if let Ok(character) = characters.get_mut(ent1) {
-            character
-        } else if let Ok(character) = characters.get_mut(ent2) {
-            character
-        } else {
-            continue;
-        };
-
-        // Get the entrance of the collision or skip this event
-        let entrance = if let Ok(entrance) = entrances.get(ent1).or_else(|_| entrances.get(ent2)) {
+        // Get the entrance from the collision
+        let entrance = if let Ok(entrance) = entrances.get(event.b) {
             entrance
         } else {
             continue;
@@ -636,8 +882,8 @@ pub fn change_level(
                     *status = EntranceStatus::Outside;
                 }
 
-                // And skip all tasks below
-                return;
+                // And skip the rest of the collisions
+                break;
             }
 
             // We are outside of an entrance and walking into it for the first time
@@ -652,49 +898,68 @@ pub fn change_level(
             EntranceStatus::Outside => (),
         }
 
+        teleports.push((
+            event.a,
+            entrance.to_level.clone(),
+            Some(entrance.spawn_at.clone()),
+        ));
+    }
+
+    // Teleport the player to levels that are loaded from outside of the game
+    for event in load_level_events.iter() {
+        for (character, ..) in characters.iter_mut() {
+            teleports.push((character, event.level.clone(), None));
+        }
+    }
+
+    for (character, to_level_id, entrance_id) in teleports {
+        let (_, mut character_transform, mut character_y_sort) =
+            if let Ok(character) = characters.get_mut(character) {
+                character
+            } else {
+                continue;
+            };
+
         // Get the level that we will be teleporting to
-        let to_level = map
-            .project
-            .levels
-            .iter()
-            .find(|x| x.identifier == entrance.to_level)
-            .unwrap_or_else(|| {
-                panic!(
+        let to_level = if let Some(level) = map.level(&to_level_id) {
+            level
+        } else {
+            error_events.send(FatalErrorEvent {
+                message: format!(
                     "Level `{}` does not exist. Could not teleport there.",
-                    entrance.to_level
-                )
+                    to_level_id
+                ),
+                asset: None,
             });
+            return;
+        };
 
         // Get the spawn point we will be teleporting to
-        let to_entrance = to_level
-            .layer_instances
-            .as_ref()
-            .expect("Teleport `to` level does not have any layers")
-            .iter()
-            .find_map(|x| {
-                x.entity_instances.iter().find(|x| {
-                    x.__identifier == "Entrance"
-                        && x.field_instances
-                            .iter()
-                            .any(|x| x.__identifier == "id" && x.__value == entrance.spawn_at)
-                })
-            })
-            .unwrap_or_else(|| {
-                panic!(
-                    "Could not find entrance `{}` in level `{}` to teleport to",
-                    entrance.spawn_at, entrance.to_level
-                )
+        let to_entrance = match &entrance_id {
+            Some(id) => to_level.find_entity("Entrance", "id", id),
+            None => to_level.find_entity("SpawnPoint", "name", "PlayerStart"),
+        };
+        let to_entrance = if let Some(to_entrance) = to_entrance {
+            to_entrance
+        } else {
+            error_events.send(FatalErrorEvent {
+                message: format!(
+                    "Could not find {} in level `{}` to teleport to",
+                    entrance_id
+                        .map(|x| format!("entrance `{}`", x))
+                        .unwrap_or_else(|| "`PlayerStart`".into()),
+                    to_level_id
+                ),
+                asset: None,
             });
+            return;
+        };
 
         // Set the current level to the new level
-        *current_level = CurrentLevel(entrance.to_level.clone());
+        *current_level = CurrentLevel(to_level_id.clone());
 
         // Play the level music
-        let music_field = to_level
-            .field_instances
-            .iter()
-            .find(|x| x.__identifier == "music")
-            .expect("Level missing field `music`");
+        let music_field = to_level.field::<String>("music");
 
         // Create helper to stop the music that is already playing
         let stop_music = |controller: &mut SoundController, sound| {
@@ -709,7 +974,7 @@ pub fn change_level(
         };
 
         // If there is a music setting for this level
-        if let Some(new_music) = music_field.__value.as_str() {
+        if let Some(new_music) = music_field.as_deref() {
             // If the new music is the special value "none"
             if new_music == "none" {
                 // Stop playing any music that might already be playing
@@ -723,7 +988,8 @@ pub fn change_level(
             // If there is new music we should play
             } else {
                 // Get the new music file data
-                let new_sound_data = asset_server.load_cached(new_music);
+                let new_sound_data: Handle<SoundData> = asset_server.load_cached(new_music);
+                critical_assets.watch(&new_sound_data);
 
                 // Create helper to play the new music
                 let play_music = |controller: &mut SoundController, new_sound_data| {
@@ -756,48 +1022,32 @@ pub fn change_level(
 
                         // And play new new music
                         **current_music = play_music(&mut sound_controller, new_sound_data);
+                        sound_events.send(SoundPlayedEvent {
+                            sound: new_music.into(),
+                        });
                     }
 
                 // If there is no music already playing, just play the new music
                 } else {
                     commands.insert_resource(play_music(&mut sound_controller, new_sound_data));
+                    sound_events.send(SoundPlayedEvent {
+                        sound: new_music.into(),
+                    });
                 }
             }
         }
 
         // Set the camera background to the level background color
-        for mut camera in cameras.iter_mut() {
-            let decoded = hex::decode(
-                to_level
-                    .bg_color
-                    .as_ref()
-                    .unwrap_or(&map.project.default_level_bg_color)
-                    .strip_prefix('#')
-                    .expect("Invalid background color"),
-            )
-            .expect("Invalid background color");
-
-            camera.background_color = Color::from_rgba8(decoded[0], decoded[1], decoded[2], 1);
+        if let Some(background_color) = to_level.background_color {
+            for mut camera in cameras.iter_mut() {
+                camera.background_color = background_color;
+            }
         }
 
         // Move the character to the other entrance
-        *character_transform = Transform::from_xyz(
-            // FIXME: We subtract 0.1 pixels to push the sprite very slightly to the left because
-            // there were issues when teleporting where we were just enough to the right that we
-            // could somehow go through the first block of doorpost.
-            //
-            // Not sure why, but this is the easiest place to fix for now.
-            to_level.world_x as f32 + to_entrance.px[0] as f32 + to_entrance.width as f32 / 2.
-                - 0.1,
-            to_level.world_y as f32 + to_entrance.px[1] as f32 + to_entrance.height as f32 / 2.
-                - 0.1,
-            to_level
-                .layer_instances
-                .as_ref()
-                .expect("Level does not have any layers")
-                .len() as f32
-                * 2.,
-        );
+        *character_transform =
+            Transform::from_translation(to_level.place(to_entrance, Placement::Character));
+        character_y_sort.base_z = to_entrance.z;
     }
 }
 
@@ -807,15 +1057,30 @@ pub struct EnemyPathfindingDebugViz {
 
 pub fn enemy_follow_player(
     mut commands: Commands,
-    mut enemies: Query<(Entity, &Transform, &mut Velocity, &Enemy)>,
+    mut enemies: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Velocity,
+            Option<&Alertness>,
+            Option<&SpeedModifiers>,
+            Option<&Pack>,
+        ),
+        (With<Enemy>, Without<Dormant>),
+    >,
     characters: Query<(Entity, &Transform), With<Handle<Character>>>,
-    maps: Query<&LdtkMapLevelNavigationMeshes, With<Handle<LdtkMap>>>,
-    enemy_pathfinding_debug_vizes: Query<Entity, With<EnemyPathfindingDebugViz>>,
+    maps: Query<&MapLevelNavigationMeshes, With<GameMap>>,
+    enemy_pathfinding_debug_vizes: Query<Entity, (With<EnemyPathfindingDebugViz>, Without<Pooled>)>,
+    mut debug_viz_pool: ResMut<EntityPool<EnemyPathfindingDebugViz>>,
+    enemy_spatial_hash: Res<EnemySpatialHash>,
     current_level: Option<Res<CurrentLevel>>,
     physics_world: PhysicsWorld,
     game_info: Res<GameInfo>,
+    timings: Res<SystemTimings>,
+    mut pathfinding_stats: ResMut<PathfindingStats>,
 ) {
-    const ENEMY_SPEED: f32 = 40.;
+    let _timer = timings.start("enemy_follow_player");
+    let debug_rendering = &game_info.debug_rendering;
 
     let current_level = if let Some(level) = current_level {
         level
@@ -844,23 +1109,89 @@ pub fn enemy_follow_player(
         return;
     };
 
-    'enemy: for (enemy_ent, enemy_transform, mut enemy_velocity, enemy) in enemies.iter_mut() {
-        let enemy_pos = enemy_transform.translation.truncate().extend(0.);
+    // Clean up navigation debug viz from previous frame
+    for entity in enemy_pathfinding_debug_vizes.iter() {
+        debug_viz_pool.release(&mut commands, entity);
+    }
 
-        // Skip the enemy if he is not from the current level
-        if enemy.level != current_level.0 {
-            continue;
+    // Find the leader of each pack in the level, who pathfinds for the rest of the pack
+    let mut pack_leaders = HashMap::<String, PackLeader>::default();
+    let mut pack_members = HashSet::<Entity>::default();
+    for (ent, position) in enemy_spatial_hash.enemies() {
+        let pack = match enemies.get(ent) {
+            Ok((.., Some(pack))) => pack,
+            _ => continue,
+        };
+        pack_members.insert(ent);
+        let candidate = PackLeader {
+            entity: ent,
+            order: pack.order,
+            position: position.extend(0.),
+            offset: pack.offset,
+        };
+        let leader = pack_leaders.entry(pack.id.clone()).or_insert(candidate);
+        if (candidate.order, candidate.entity.id()) < (leader.order, leader.entity.id()) {
+            *leader = candidate;
         }
+    }
 
-        if game_info.debug_rendering.navmesh {
-            // Clean up navigation debug viz from previous frame
-            for entity in enemy_pathfinding_debug_vizes.iter() {
-                commands.entity(entity).despawn();
+    // Only the enemies in the current level follow the player
+    'enemy: for (enemy_ent, _) in enemy_spatial_hash.enemies() {
+        let (_, enemy_transform, mut enemy_velocity, alertness, speed_modifiers, pack) =
+            if let Ok(enemy) = enemies.get_mut(enemy_ent) {
+                enemy
+            } else {
+                continue;
+            };
+        let _span = info_span!("enemy_pathfinding", enemy=?enemy_ent).entered();
+        let enemy_pos = enemy_transform.translation.truncate().extend(0.);
+
+        // Enemies following a pack leader walk to their place in the formation instead of chasing
+        // the player themselves
+        let formation_slot = pack.and_then(|pack| {
+            let leader = pack_leaders.get(&pack.id)?;
+            (leader.entity != enemy_ent)
+                .then(|| leader.position + (pack.offset - leader.offset).extend(0.))
+        });
+        if let Some(slot) = formation_slot {
+            if slot.distance(enemy_pos) < FORMATION_TOLERANCE {
+                *enemy_velocity = Velocity::default();
+                continue;
             }
         }
 
-        // Try to plot a path straight to the player
-        let straight_path = if let Some(collision) = physics_world.shape_cast_with_filter(
+        // Enemies with vision only chase the player once they are alerted, and search where the
+        // player was last seen after losing them
+        let target_pos = match (formation_slot, alertness) {
+            (Some(slot), _) => slot,
+            (None, None) => character_pos,
+            (None, Some(alertness)) => match (alertness.state, alertness.last_known) {
+                (AlertState::Alert, _) => character_pos,
+                (AlertState::Searching, Some(last_known)) => last_known.extend(0.),
+                _ => {
+                    *enemy_velocity = Velocity::default();
+                    continue;
+                }
+            },
+        };
+
+        // Try to plot a path straight to the player, or to the enemy's place in its pack's
+        // formation, ignoring the rest of the pack
+        let straight_path = if let Some(slot) = formation_slot {
+            physics_world
+                .shape_cast_with_filter(
+                    &CollisionShape::Sphere { radius: 4. },
+                    enemy_pos,
+                    Quat::default(),
+                    slot - enemy_pos,
+                    CollisionLayers::default(),
+                    |entity| entity != character_ent && !pack_members.contains(&entity),
+                )
+                .is_none()
+                .then(|| vec![slot.into_nav()])
+        } else if target_pos != character_pos {
+            None
+        } else if let Some(collision) = physics_world.shape_cast_with_filter(
             &CollisionShape::Sphere { radius: 8. },
             enemy_pos,
             Quat::default(),
@@ -869,23 +1200,6 @@ pub fn enemy_follow_player(
             |entity| entity != enemy_ent,
         ) {
             if collision.entity == character_ent {
-                // Spawn debug rendering if enabled
-                if game_info.debug_rendering.navmesh {
-                    commands
-                        .spawn_bundle(ShapeBundle {
-                            shape: Shape::line_segment(
-                                [
-                                    epaint::pos2(enemy_pos.x, enemy_pos.y),
-                                    epaint::pos2(character_pos.x, character_pos.y),
-                                ],
-                                (2., epaint::Color32::RED),
-                            ),
-                            transform: Transform::from_xyz(0., 0., 1024.),
-                            ..Default::default()
-                        })
-                        .insert(EnemyPathfindingDebugViz { enemy_ent });
-                }
-
                 Some(vec![character_pos.into_nav()])
             } else {
                 None
@@ -894,23 +1208,41 @@ pub fn enemy_follow_player(
             None
         };
 
-        // Use navigation mesh to plot a path to the character if the straight path doesn't work
+        // Use navigation mesh to plot a path to the target if the straight path doesn't work
+        let is_straight = straight_path.is_some();
         if let Some(path) = straight_path.or_else(|| {
             mesh.find_path(
                 enemy_pos.into_nav(),
-                character_pos.into_nav(),
+                target_pos.into_nav(),
                 navmesh::NavQuery::Accuracy,
                 navmesh::NavPathMode::Accuracy,
             )
         }) {
+            let length = std::iter::once(enemy_pos)
+                .chain(path.iter().map(|x| x.into_bevy()))
+                .tuple_windows()
+                .map(|(v1, v2)| v1.distance(v2))
+                .sum::<f32>();
+            pathfinding_stats.record_path(length);
+
             // Display debug visualization if enabled
-            if game_info.debug_rendering.navmesh {
-                for (v1, v2) in path.iter().tuple_windows() {
+            if debug_rendering.navmesh || debug_rendering.path_costs {
+                let color = if debug_rendering.path_costs {
+                    path_cost_color(path_cost(mesh, enemy_pos, &path))
+                } else if is_straight {
+                    epaint::Color32::RED
+                } else {
+                    epaint::Color32::GREEN
+                };
+                let start = std::iter::once(enemy_pos.into_nav()).filter(|_| is_straight);
+                for (v1, v2) in start.chain(path.iter().copied()).tuple_windows() {
+                    let viz = debug_viz_pool.take(&mut commands);
                     commands
-                        .spawn_bundle(ShapeBundle {
+                        .entity(viz)
+                        .insert_bundle(ShapeBundle {
                             shape: Shape::line_segment(
                                 [epaint::pos2(v1.x, v1.y), epaint::pos2(v2.x, v2.y)],
-                                (2., epaint::Color32::GREEN),
+                                (2., color),
                             ),
                             transform: Transform::from_xyz(0., 0., 1024.),
                             ..Default::default()
@@ -920,15 +1252,19 @@ pub fn enemy_follow_player(
             }
 
             for node in path {
-                let vel = (node.into_bevy() - enemy_pos).normalize_or_zero() * ENEMY_SPEED;
+                let speed = speed_modifiers
+                    .map(|x| x.apply(game_info.enemies.speed))
+                    .unwrap_or(game_info.enemies.speed);
+                let vel = (node.into_bevy() - enemy_pos).normalize_or_zero() * speed;
                 if vel.length() > 0.5 {
                     *enemy_velocity = vel.into();
-                    break 'enemy;
+                    continue 'enemy;
                 }
             }
 
             *enemy_velocity = Velocity::default()
         } else {
+            pathfinding_stats.record_failure();
             *enemy_velocity = Velocity::default()
         }
     }
@@ -0,0 +1,61 @@
+use super::*;
+
+/// The furthest the camera is moved from its position when shaking at full strength, in pixels
+const MAX_SHAKE_OFFSET: f32 = 6.;
+/// How much shake trauma wears off per second
+const TRAUMA_DECAY: f32 = 1.5;
+/// How fast the camera moves back and forth while shaking
+const SHAKE_FREQUENCY: f32 = 30.;
+
+/// Resource used to shake the camera, i.e. when the player is hit or a bomb goes off
+#[derive(Default)]
+pub struct CameraShake {
+    /// How much the camera is shaking, from `0.0` to `1.0`, wearing off over time
+    pub trauma: f32,
+    /// Time spent shaking, used to animate the shake
+    time: f32,
+}
+
+impl CameraShake {
+    /// Add to the camera shake, up to the maximum trauma of `1.0`
+    pub fn add_trauma(&mut self, amount: f32) {
+        if amount > 0. {
+            self.trauma = (self.trauma + amount).min(1.);
+        }
+    }
+}
+
+/// Offset the camera by the current [`CameraShake`], unless the player turned screen shake off
+pub fn apply_camera_shake(
+    mut camera_shake: ResMut<CameraShake>,
+    mut cameras: Query<&mut Transform, With<Camera>>,
+    settings: Res<UserSettings>,
+    time: Res<Time>,
+) {
+    // Avoid triggering change detection if the camera isn't shaking
+    if camera_shake.trauma <= 0. {
+        return;
+    }
+
+    let shake = &mut *camera_shake;
+    shake.time += time.delta_seconds();
+    shake.trauma = (shake.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0.);
+
+    if !settings.accessibility.screen_shake {
+        return;
+    }
+
+    // The shake is stronger than linear at high trauma so that big hits feel bigger
+    let strength = shake.trauma * shake.trauma * MAX_SHAKE_OFFSET;
+    let t = shake.time * SHAKE_FREQUENCY;
+    let offset = Vec2::new(
+        (t * 1.3).sin() + (t * 2.9).sin() * 0.5,
+        (t * 1.7).cos() + (t * 3.1).sin() * 0.5,
+    ) / 1.5
+        * strength;
+
+    for mut transform in cameras.iter_mut() {
+        transform.translation.x += offset.x;
+        transform.translation.y += offset.y;
+    }
+}
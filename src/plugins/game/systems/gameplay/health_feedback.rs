@@ -0,0 +1,151 @@
+use std::f32::consts::{PI, TAU};
+
+use super::*;
+
+/// Tracks the state of the on-screen damage vignette and the low-health warning
+#[derive(Default)]
+pub struct HealthFeedback {
+    /// Timer for the vignette flash shown right after the player is damaged
+    pub damage_flash: Option<Timer>,
+    /// Whether or not the player is at or below the low-health threshold
+    pub low_health: bool,
+    /// Seconds spent at low health, used to drive the vignette pulse
+    pub pulse_time: f32,
+    /// The heartbeat sound that is looping while the player is at low health
+    pub heartbeat: Option<Sound>,
+    /// Whether or not the heartbeat sound has been paused along with the game
+    pub heartbeat_paused: bool,
+}
+
+impl HealthFeedback {
+    /// Get the current opacity of the vignette. If `reduce_flashes` is set, the damage flash
+    /// gently fades in and out at half strength instead of appearing all at once.
+    pub fn vignette_opacity(&self, config: &HealthFeedbackConfig, reduce_flashes: bool) -> f32 {
+        let flash = self
+            .damage_flash
+            .as_ref()
+            .map(|timer| {
+                if reduce_flashes {
+                    (timer.percent() * PI).sin() * config.flash_opacity * 0.5
+                } else {
+                    timer.percent_left() * config.flash_opacity
+                }
+            })
+            .unwrap_or(0.);
+
+        let pulse = if self.low_health {
+            ((self.pulse_time * config.pulse_rate * TAU).sin() * 0.5 + 0.5) * config.pulse_opacity
+        } else {
+            0.
+        };
+
+        flash.max(pulse)
+    }
+}
+
+/// Flash the vignette when the player is damaged and pulse it and play the heartbeat sound while
+/// the player is low on health
+pub fn update_health_feedback(
+    mut feedback: ResMut<HealthFeedback>,
+    mut damaged_events: EventReader<CharacterDamagedEvent>,
+    mut rumble_events: EventWriter<RumbleEvent>,
+    mut camera_shake: ResMut<CameraShake>,
+    mut sound_events: EventWriter<SoundPlayedEvent>,
+    characters: Query<&Health, With<Handle<Character>>>,
+    game_info: Res<GameInfo>,
+    asset_server: Res<AssetServer>,
+    mut sound_controller: SoundController,
+    time: Res<Time>,
+    engine_config: Res<crate::EngineConfig>,
+) {
+    let config = &game_info.health_feedback;
+
+    // Restart the flash and rumble the controller whenever the player is hurt
+    if damaged_events.iter().any(|event| event.damage > 0) {
+        feedback.damage_flash = Some(Timer::from_seconds(config.flash_duration, false));
+        rumble_events.send(RumbleEvent {
+            intensity: config.damage_rumble_intensity,
+            duration: config.damage_rumble_duration,
+        });
+        camera_shake.add_trauma(config.damage_shake);
+    }
+
+    // Fade the flash out
+    if let Some(timer) = &mut feedback.damage_flash {
+        timer.tick(time.delta());
+
+        if timer.finished() {
+            feedback.damage_flash = None;
+        }
+    }
+
+    // Check whether or not the player is low on health
+    let low_health = characters
+        .iter()
+        .next()
+        .map(|health| health.current > 0 && health.current <= config.low_health_threshold)
+        .unwrap_or(false);
+
+    if low_health {
+        feedback.pulse_time += time.delta_seconds();
+
+        // Start the heartbeat if it isn't playing yet
+        if feedback.heartbeat.is_none() && !engine_config.mute {
+            if let Some(heartbeat_sound) = &config.heartbeat_sound {
+                let sound_data = asset_server.load_cached(heartbeat_sound.as_str());
+                let sound = sound_controller.create_sound(&sound_data);
+
+                sound_controller.play_sound_with_settings(
+                    sound,
+                    PlaySoundSettings::new().loop_start(LoopStart::Custom(0.0)),
+                );
+
+                feedback.heartbeat = Some(sound);
+                sound_events.send(SoundPlayedEvent {
+                    sound: heartbeat_sound.clone(),
+                });
+            }
+        }
+    } else {
+        feedback.pulse_time = 0.;
+
+        // Stop the heartbeat once the player has recovered
+        if let Some(sound) = feedback.heartbeat.take() {
+            sound_controller.stop_sound(sound);
+        }
+    }
+
+    feedback.low_health = low_health;
+}
+
+/// Pause the heartbeat sound while the game isn't being played, i.e. when it is paused, and resume
+/// it when the game is
+pub fn pause_heartbeat(
+    mut feedback: ResMut<HealthFeedback>,
+    state: Res<State<GameState>>,
+    mut sound_controller: SoundController,
+) {
+    let sound = if let Some(sound) = feedback.heartbeat {
+        sound
+    } else {
+        return;
+    };
+
+    let playing = state.current() == &GameState::Playing;
+    if !playing && !feedback.heartbeat_paused {
+        sound_controller.pause_sound(sound);
+        feedback.heartbeat_paused = true;
+    } else if playing && feedback.heartbeat_paused {
+        sound_controller.resume_sound(sound);
+        feedback.heartbeat_paused = false;
+    }
+}
+
+/// Stop the heartbeat sound and clear the vignette, i.e. when the game is over
+pub fn stop_heartbeat(feedback: &mut HealthFeedback, sound_controller: &mut SoundController) {
+    if let Some(sound) = feedback.heartbeat.take() {
+        sound_controller.stop_sound(sound);
+    }
+
+    *feedback = HealthFeedback::default();
+}
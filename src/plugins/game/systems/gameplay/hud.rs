@@ -1,8 +1,64 @@
-use bevy::prelude::{Handle, With, World};
+use bevy::prelude::{Assets, Handle, Transform, With, World};
 // use bevy::prelude::{debug, Handle, With, World};
-use bevy_retrograde::ui::raui::prelude::*;
+use bevy_retrograde::{prelude::Camera, ui::raui::prelude::*};
 
-use crate::plugins::game::{assets::GameInfo, components::Character, systems::gameplay::Health};
+use crate::plugins::game::{
+    assets::{Ability, GameInfo, Item, ResourceNode, SizedImage, UiImageScaling, UiTheme},
+    components::Character,
+    score::Score,
+    settings::UserSettings,
+    systems::{
+        abilities::{Abilities, Energy},
+        captions::captions,
+        crafting::CraftingAssets,
+        cutscenes::CutscenePlayer,
+        emotes::Emoting,
+        gameplay::{Health, HealthFeedback},
+        gathering::Gathering,
+        inspector::inspector,
+        perf_hud::perf_hud,
+        results::LevelResults,
+        transition::screen_fade,
+        tweak_panel::tweak_panel,
+        ui_utils::{
+            active_ui_theme, rich_text, safe_area_box, ui_font, RichTextProps, UiLayout,
+            UiThemeVariant,
+        },
+    },
+};
+
+/// The space between the hearts in the health bar, in pixels
+const HEART_SEPARATION: f32 = 2.;
+
+/// The space around the hearts in the health bar, in pixels
+const HEART_MARGIN: f32 = 1.;
+
+/// The size of the ability icons, in pixels
+const ABILITY_ICON_SIZE: f32 = 16.;
+
+/// The size of the gathering mini-game bar, in pixels
+const GATHERING_BAR_SIZE: (f32, f32) = (80., 6.);
+
+/// The number of bands the damage vignette border fades in over
+const VIGNETTE_BANDS: usize = 4;
+
+/// The width of the level results panel, in pixels
+const RESULTS_WIDTH: f32 = 140.;
+
+/// The height of each line of the level results, in pixels
+const RESULTS_LINE_HEIGHT: f32 = 12.;
+
+/// The space between the edge of the level results panel and the text, in pixels
+const RESULTS_PADDING: f32 = 8.;
+
+/// The size of the icons in emote bubbles, in pixels
+const EMOTE_ICON_SIZE: f32 = 8.;
+
+/// The width of each character of the text in emote bubbles, in pixels
+const EMOTE_CHAR_WIDTH: f32 = 6.;
+
+/// The space between the edge of an emote bubble and its content, in pixels
+const EMOTE_PADDING: f32 = 3.;
 
 pub fn hud(ctx: WidgetContext) -> WidgetNode {
     let WidgetContext {
@@ -12,27 +68,86 @@ pub fn hud(ctx: WidgetContext) -> WidgetNode {
     let world: &mut World = process_context.get_mut().unwrap();
 
     // Get the health of the player
-    let player_health = {
+    let (player_health, player_max_health) = {
         let mut q = world.query_filtered::<&Health, With<Handle<Character>>>();
         if let Some(health) = q.iter(world).next() {
-            health.current
+            (health.current, health.max)
         } else {
-            return WidgetNode::None;
+            // Keep the screen faded while the player loads
+            return make_widget!(content_box)
+                .listed_slot(make_widget!(screen_fade))
+                .into();
         }
     };
 
     // Get the game info from the world
     let game_info = world.get_resource::<GameInfo>().unwrap();
-    let health_background = &game_info.ui_theme.hud.health_background;
-    let full_heart = &game_info.ui_theme.hud.full_heart;
-    let half_heart = &game_info.ui_theme.hud.half_heart;
+    let ui_theme = hud_theme(world, game_info);
+    let health_background = &ui_theme.hud.health_background;
+    let full_heart = &ui_theme.hud.full_heart;
+    let half_heart = &ui_theme.hud.half_heart;
+    let ui_layout = world
+        .get_resource::<UiLayout>()
+        .cloned()
+        .unwrap_or_default();
+
+    // Grow the health background to fit all of the heart containers, unless it is stretched
+    let heart_containers = ((player_max_health + 1) / 2) as f32;
+    let health_background_size = match health_background.scaling {
+        UiImageScaling::Stretch => (
+            health_background.size.0 as f32,
+            health_background.size.1 as f32,
+        ),
+        _ => (
+            (heart_containers * (full_heart.size.0 as f32 + HEART_SEPARATION) - HEART_SEPARATION
+                + HEART_MARGIN * 2.)
+                .max(health_background.size.0 as f32),
+            (full_heart.size.1 as f32 + HEART_MARGIN * 2.).max(health_background.size.1 as f32),
+        ),
+    };
+
+    // Get the damage vignette opacity
+    let health_feedback = &game_info.health_feedback;
+    let reduce_flashes = world
+        .get_resource::<UserSettings>()
+        .map(|x| x.accessibility.reduce_flashes)
+        .unwrap_or(false);
+    let vignette_opacity = world
+        .get_resource::<HealthFeedback>()
+        .map(|x| x.vignette_opacity(health_feedback, reduce_flashes))
+        .unwrap_or(0.);
+    let (vignette_r, vignette_g, vignette_b) = health_feedback.vignette_color;
+    let vignette_color = Color {
+        r: vignette_r,
+        g: vignette_g,
+        b: vignette_b,
+        a: vignette_opacity,
+    };
 
     make_widget!(content_box)
-        .listed_slot(
+        // The emote bubbles over characters, which follow them around the world
+        .listed_slot(make_widget!(emote_bubbles))
+        // The damage vignette, behind the rest of the HUD
+        .listed_slot(if let Some(image) = &health_feedback.vignette_image {
+            make_widget!(image_box)
+                .with_props(ImageBoxProps {
+                    material: ImageBoxMaterial::Image(ImageBoxImage {
+                        id: image.clone(),
+                        tint: vignette_color,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .into()
+        } else {
+            vignette_border(vignette_color, health_feedback.vignette_width)
+        })
+        .listed_slot(safe_area_box(
+            &ui_layout,
             make_widget!(size_box)
                 .with_props(SizeBoxProps {
-                    width: SizeBoxSizeValue::Exact(health_background.size.0 as f32),
-                    height: SizeBoxSizeValue::Exact(health_background.size.1 as f32),
+                    width: SizeBoxSizeValue::Exact(health_background_size.0),
+                    height: SizeBoxSizeValue::Exact(health_background_size.1),
                     ..Default::default()
                 })
                 .with_props(ContentBoxItemLayout {
@@ -42,21 +157,15 @@ pub fn hud(ctx: WidgetContext) -> WidgetNode {
                 .named_slot(
                     "content",
                     make_widget!(content_box)
-                        .listed_slot(make_widget!(image_box).with_props(ImageBoxProps {
-                            material: ImageBoxMaterial::Image(ImageBoxImage {
-                                id: health_background.image.clone(),
-                                ..Default::default()
-                            }),
-                            ..Default::default()
-                        }))
+                        .listed_slot(hud_image(health_background, health_background_size))
                         .listed_slot({
                             let mut horizontal = make_widget!(horizontal_box)
                                 .with_props(HorizontalBoxProps {
-                                    separation: 2.,
+                                    separation: HEART_SEPARATION,
                                     ..Default::default()
                                 })
                                 .with_props(ContentBoxItemLayout {
-                                    margin: 1.0.into(),
+                                    margin: HEART_MARGIN.into(),
                                     ..Default::default()
                                 });
 
@@ -65,20 +174,26 @@ pub fn hud(ctx: WidgetContext) -> WidgetNode {
 
                             for _ in 0..full_hearts {
                                 horizontal = horizontal.listed_slot(
-                                    make_widget!(image_box)
-                                        .with_props(ImageBoxProps {
-                                            material: ImageBoxMaterial::Image(ImageBoxImage {
-                                                id: full_heart.image.clone(),
-                                                ..Default::default()
-                                            }),
-                                            width: ImageBoxSizeValue::Exact(
+                                    make_widget!(size_box)
+                                        .with_props(SizeBoxProps {
+                                            width: SizeBoxSizeValue::Exact(
                                                 full_heart.size.0 as f32,
                                             ),
-                                            height: ImageBoxSizeValue::Exact(
+                                            height: SizeBoxSizeValue::Exact(
                                                 full_heart.size.1 as f32,
                                             ),
                                             ..Default::default()
                                         })
+                                        .named_slot(
+                                            "content",
+                                            hud_image(
+                                                full_heart,
+                                                (
+                                                    full_heart.size.0 as f32,
+                                                    full_heart.size.1 as f32,
+                                                ),
+                                            ),
+                                        )
                                         .with_props(FlexBoxItemLayout {
                                             grow: 0.0,
                                             ..Default::default()
@@ -88,20 +203,26 @@ pub fn hud(ctx: WidgetContext) -> WidgetNode {
 
                             for _ in 0..half_hearts {
                                 horizontal = horizontal.listed_slot(
-                                    make_widget!(image_box)
-                                        .with_props(ImageBoxProps {
-                                            material: ImageBoxMaterial::Image(ImageBoxImage {
-                                                id: half_heart.image.clone(),
-                                                ..Default::default()
-                                            }),
-                                            width: ImageBoxSizeValue::Exact(
+                                    make_widget!(size_box)
+                                        .with_props(SizeBoxProps {
+                                            width: SizeBoxSizeValue::Exact(
                                                 half_heart.size.0 as f32,
                                             ),
-                                            height: ImageBoxSizeValue::Exact(
+                                            height: SizeBoxSizeValue::Exact(
                                                 half_heart.size.1 as f32,
                                             ),
                                             ..Default::default()
                                         })
+                                        .named_slot(
+                                            "content",
+                                            hud_image(
+                                                half_heart,
+                                                (
+                                                    half_heart.size.0 as f32,
+                                                    half_heart.size.1 as f32,
+                                                ),
+                                            ),
+                                        )
                                         .with_props(FlexBoxItemLayout {
                                             grow: 0.0,
                                             ..Default::default()
@@ -112,6 +233,778 @@ pub fn hud(ctx: WidgetContext) -> WidgetNode {
                             horizontal
                         }),
                 ),
+        ))
+        // The player's abilities
+        .listed_slot(safe_area_box(&ui_layout, make_widget!(ability_bar)))
+        // The player's score, if the game keeps score
+        .listed_slot(safe_area_box(&ui_layout, make_widget!(score)))
+        // The gathering mini-game
+        .listed_slot(safe_area_box(&ui_layout, make_widget!(gathering_bar)))
+        // Sound captions
+        .listed_slot(safe_area_box(&ui_layout, make_widget!(captions)))
+        // The results of the level the player just completed
+        .listed_slot(safe_area_box(&ui_layout, make_widget!(level_results)))
+        // The cutscene letterbox bars, over the rest of the HUD
+        .listed_slot(make_widget!(letterbox))
+        // The performance HUD, if enabled
+        .listed_slot(make_widget!(perf_hud))
+        // The entity inspector, if enabled
+        .listed_slot(make_widget!(inspector))
+        // The tweak panel, if enabled
+        .listed_slot(make_widget!(tweak_panel))
+        // The screen transition fade
+        .listed_slot(make_widget!(screen_fade))
+        .into()
+}
+
+/// A border around the edges of the screen that fades from `color` at the edge to clear at `width`
+/// pixels in, drawn as bands that don't overlap so that the corners aren't darker
+fn vignette_border(color: Color, width: f32) -> WidgetNode {
+    if color.a <= 0. || width <= 0. {
+        return WidgetNode::None;
+    }
+
+    let band_width = width / VIGNETTE_BANDS as f32;
+    let mut border = make_widget!(content_box);
+    for band in 0..VIGNETTE_BANDS {
+        let inset = band as f32 * band_width;
+        let color = Color {
+            a: color.a * (VIGNETTE_BANDS - band) as f32 / VIGNETTE_BANDS as f32,
+            ..color
+        };
+        let bar = |anchors: Rect, margin: Rect| {
+            make_widget!(image_box)
+                .with_props(ImageBoxProps {
+                    material: ImageBoxMaterial::Color(ImageBoxColor {
+                        color,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .with_props(ContentBoxItemLayout {
+                    anchors,
+                    margin,
+                    ..Default::default()
+                })
+        };
+
+        // The top and bottom bars span the band's full width, and the side bars fit between them
+        border = border
+            .listed_slot(bar(
+                Rect {
+                    left: 0.,
+                    right: 1.,
+                    top: 0.,
+                    bottom: 0.,
+                },
+                Rect {
+                    left: inset,
+                    right: inset,
+                    top: inset,
+                    bottom: -inset - band_width,
+                },
+            ))
+            .listed_slot(bar(
+                Rect {
+                    left: 0.,
+                    right: 1.,
+                    top: 1.,
+                    bottom: 1.,
+                },
+                Rect {
+                    left: inset,
+                    right: inset,
+                    top: -inset - band_width,
+                    bottom: inset,
+                },
+            ))
+            .listed_slot(bar(
+                Rect {
+                    left: 0.,
+                    right: 0.,
+                    top: 0.,
+                    bottom: 1.,
+                },
+                Rect {
+                    left: inset,
+                    right: -inset - band_width,
+                    top: inset + band_width,
+                    bottom: inset + band_width,
+                },
+            ))
+            .listed_slot(bar(
+                Rect {
+                    left: 1.,
+                    right: 1.,
+                    top: 0.,
+                    bottom: 1.,
+                },
+                Rect {
+                    left: -inset - band_width,
+                    right: inset,
+                    top: inset + band_width,
+                    bottom: inset + band_width,
+                },
+            ));
+    }
+
+    border.into()
+}
+
+/// An image from the HUD theme, filling the given size in pixels according to its scaling
+fn hud_image(image: &SizedImage, size: (f32, f32)) -> WidgetNode {
+    let image_box = |scaling| {
+        make_widget!(image_box).with_props(ImageBoxProps {
+            material: ImageBoxMaterial::Image(ImageBoxImage {
+                id: image.image.clone(),
+                scaling,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    };
+
+    match &image.scaling {
+        UiImageScaling::Stretch => image_box(ImageBoxImageScaling::Stretch).into(),
+        UiImageScaling::Frame {
+            border_size,
+            only_frame,
+        } => image_box(ImageBoxImageScaling::Frame(
+            (*border_size as f32, *only_frame).into(),
+        ))
+        .into(),
+        // Stretch the tiles a little so that a whole number of them fit
+        UiImageScaling::Tile => {
+            let columns = (size.0 / image.size.0.max(1) as f32).round().max(1.);
+            let rows = (size.1 / image.size.1.max(1) as f32).round().max(1.);
+            let tile_size = (size.0 / columns, size.1 / rows);
+
+            let mut tiles = make_widget!(content_box);
+            for row in 0..rows as u32 {
+                for column in 0..columns as u32 {
+                    let (x, y) = (column as f32 * tile_size.0, row as f32 * tile_size.1);
+                    tiles = tiles.listed_slot(image_box(ImageBoxImageScaling::Stretch).with_props(
+                        ContentBoxItemLayout {
+                            anchors: Rect::default(),
+                            margin: Rect {
+                                left: x,
+                                right: -(x + tile_size.0),
+                                top: y,
+                                bottom: -(y + tile_size.1),
+                            },
+                            ..Default::default()
+                        },
+                    ));
+                }
+            }
+
+            tiles.into()
+        }
+    }
+}
+
+/// The bubbles of the entities showing an [`Emoting`] emote, above the entities on screen
+fn emote_bubbles(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+
+    let camera_position = {
+        let mut query = world.query_filtered::<&Transform, With<Camera>>();
+        if let Some(transform) = query.iter(world).next() {
+            transform.translation
+        } else {
+            return WidgetNode::None;
+        }
+    };
+    let emotes = world
+        .query::<(&Emoting, &Transform)>()
+        .iter(world)
+        .map(|(emoting, transform)| (emoting.emote.clone(), transform.translation))
+        .collect::<Vec<_>>();
+    if emotes.is_empty() {
+        return WidgetNode::None;
+    }
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+    let ui_theme = hud_theme(world, game_info);
+    let bubble = ui_theme.emote_bubble.as_ref().unwrap_or(&ui_theme.panel);
+    let (offset_x, offset_y) = game_info.emotes.offset;
+    let screen_size = world
+        .get_resource::<UiLayout>()
+        .map(|x| x.size)
+        .unwrap_or_default();
+
+    let mut bubbles = make_widget!(content_box);
+    for (emote, position) in emotes {
+        let icon = emote.icon.as_ref().and_then(|x| ui_theme.emotes.get(x));
+        let text = emote.text.unwrap_or_default();
+
+        // Size the bubble to fit the icon and the text
+        let content_width =
+            icon.map_or(0., |_| EMOTE_ICON_SIZE) + text.chars().count() as f32 * EMOTE_CHAR_WIDTH;
+        if content_width <= 0. {
+            continue;
+        }
+        let width = content_width + EMOTE_PADDING * 2.;
+        let height = EMOTE_ICON_SIZE.max(EMOTE_CHAR_WIDTH) + EMOTE_PADDING * 2.;
+
+        // The UI is drawn in game pixels, so the bubble is placed by its offset from the camera,
+        // centered over the entity with its bottom at the offset
+        let x = position.x - camera_position.x + screen_size.x / 2. + offset_x - width / 2.;
+        let y = position.y - camera_position.y + screen_size.y / 2. + offset_y - height;
+
+        let mut content = make_widget!(horizontal_box).with_props(ContentBoxItemLayout {
+            margin: EMOTE_PADDING.into(),
+            ..Default::default()
+        });
+        if let Some(icon) = icon {
+            content = content.listed_slot(
+                make_widget!(image_box)
+                    .with_props(ImageBoxProps {
+                        material: ImageBoxMaterial::Image(ImageBoxImage {
+                            id: icon.clone(),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    })
+                    .with_props(FlexBoxItemLayout {
+                        grow: 0.,
+                        basis: Some(EMOTE_ICON_SIZE),
+                        ..Default::default()
+                    }),
+            );
+        }
+        if !text.is_empty() {
+            content = content.listed_slot(make_widget!(text_box).with_props(TextBoxProps {
+                text,
+                font: TextBoxFont {
+                    name: ui_theme.default_font.clone(),
+                    size: 1.,
+                },
+                horizontal_align: TextBoxHorizontalAlign::Center,
+                ..Default::default()
+            }));
+        }
+
+        bubbles = bubbles.listed_slot(
+            make_widget!(content_box)
+                .with_props(ContentBoxItemLayout {
+                    anchors: Rect::default(),
+                    margin: Rect {
+                        left: x,
+                        right: -(x + width),
+                        top: y,
+                        bottom: -(y + height),
+                    },
+                    ..Default::default()
+                })
+                .listed_slot(make_widget!(image_box).with_props(ImageBoxProps {
+                    material: ImageBoxMaterial::Image(ImageBoxImage {
+                        id: bubble.image.clone(),
+                        scaling: ImageBoxImageScaling::Frame(
+                            (bubble.border_size as f32, bubble.only_frame).into(),
+                        ),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }))
+                .listed_slot(content),
+        );
+    }
+
+    bubbles.into()
+}
+
+/// The black bars slid in at the top and bottom of the screen during cutscenes
+fn letterbox(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let letterbox = world.get_resource::<CutscenePlayer>().unwrap().letterbox;
+    if letterbox <= 0. {
+        return WidgetNode::None;
+    }
+    let height = world
+        .get_resource::<GameInfo>()
+        .unwrap()
+        .cutscenes
+        .letterbox_height
+        * letterbox;
+
+    let bar = |top: f32, margin: Rect| {
+        make_widget!(image_box)
+            .with_props(ImageBoxProps {
+                material: ImageBoxMaterial::Color(ImageBoxColor {
+                    color: Color {
+                        r: 0.,
+                        g: 0.,
+                        b: 0.,
+                        a: 1.,
+                    },
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .with_props(ContentBoxItemLayout {
+                anchors: Rect {
+                    left: 0.,
+                    right: 1.,
+                    top,
+                    bottom: top,
+                },
+                margin,
+                ..Default::default()
+            })
+    };
+
+    make_widget!(content_box)
+        .listed_slot(bar(
+            0.,
+            Rect {
+                bottom: -height,
+                ..Default::default()
+            },
+        ))
+        .listed_slot(bar(
+            1.,
+            Rect {
+                top: -height,
+                ..Default::default()
+            },
+        ))
+        .into()
+}
+
+/// The player's score in the top-right corner of the HUD, with their combo multiplier under it
+fn score(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+    let config = if let Some(config) = &game_info.score {
+        config
+    } else {
+        return WidgetNode::None;
+    };
+    let score = world.get_resource::<Score>().unwrap();
+    let font = hud_font(world, game_info);
+
+    let mut lines = vec![format!("Score: {}", score.points)];
+    if score.combo > 0 {
+        lines.push(format!("x{:.1}", score.multiplier(config)));
+    }
+
+    let mut list = make_widget!(vertical_box).with_props(ContentBoxItemLayout {
+        anchors: Rect {
+            left: 0.5,
+            right: 1.,
+            top: 0.,
+            bottom: 1.,
+        },
+        margin: 5.0.into(),
+        ..Default::default()
+    });
+    for line in lines {
+        list = list.listed_slot(
+            make_widget!(rich_text)
+                .with_props(RichTextProps { text: line })
+                .with_props(TextBoxProps {
+                    font: font.clone(),
+                    horizontal_align: TextBoxHorizontalAlign::Right,
+                    ..Default::default()
+                })
+                .with_props(FlexBoxItemLayout {
+                    grow: 0.,
+                    basis: Some(10. * font.size),
+                    ..Default::default()
+                }),
+        );
+    }
+
+    list.into()
+}
+
+/// The results panel in the middle of the screen, showing the player's stats for the level they
+/// just completed
+fn level_results(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let stats = match &world.get_resource::<LevelResults>().unwrap().0 {
+        Some((stats, _)) => stats,
+        None => return WidgetNode::None,
+    };
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+    let config = if let Some(config) = &game_info.results {
+        config
+    } else {
+        return WidgetNode::None;
+    };
+    let ui_theme = hud_theme(world, game_info);
+    let font = hud_font(world, game_info);
+
+    let mut lines = vec![config.title.clone()];
+    if config.show_time {
+        let minutes = (stats.time / 60.).floor();
+        lines.push(format!(
+            "Time: {}:{:04.1}",
+            minutes,
+            stats.time - minutes * 60.
+        ));
+    }
+    if config.show_enemies {
+        lines.push(format!("Enemies defeated: {}", stats.enemies_defeated));
+    }
+    if config.show_secrets {
+        lines.push(format!("Secrets found: {}", stats.secrets_found));
+    }
+
+    let line_height = RESULTS_LINE_HEIGHT * font.size;
+    let height = lines.len() as f32 * line_height + RESULTS_PADDING * 2.;
+    let mut list = make_widget!(vertical_box).with_props(ContentBoxItemLayout {
+        margin: RESULTS_PADDING.into(),
+        ..Default::default()
+    });
+    // The title is written by the game designer, so it can use rich text markup
+    for line in lines {
+        list = list.listed_slot(
+            make_widget!(rich_text)
+                .with_props(RichTextProps { text: line })
+                .with_props(TextBoxProps {
+                    font: font.clone(),
+                    horizontal_align: TextBoxHorizontalAlign::Center,
+                    ..Default::default()
+                })
+                .with_props(FlexBoxItemLayout {
+                    grow: 0.,
+                    basis: Some(line_height),
+                    ..Default::default()
+                }),
+        );
+    }
+
+    make_widget!(content_box)
+        .with_props(ContentBoxItemLayout {
+            anchors: Rect {
+                left: 0.5,
+                right: 0.5,
+                top: 0.5,
+                bottom: 0.5,
+            },
+            margin: Rect {
+                left: -RESULTS_WIDTH / 2.,
+                right: -RESULTS_WIDTH / 2.,
+                top: -height / 2.,
+                bottom: -height / 2.,
+            },
+            ..Default::default()
+        })
+        .listed_slot(make_widget!(image_box).with_props(ImageBoxProps {
+            material: ImageBoxMaterial::Image(ImageBoxImage {
+                id: ui_theme.panel.image.clone(),
+                scaling: ImageBoxImageScaling::Frame(
+                    (ui_theme.panel.border_size as f32, ui_theme.panel.only_frame).into(),
+                ),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }))
+        .listed_slot(list)
+        .into()
+}
+
+/// Get the UI theme for the HUD
+fn hud_theme<'a>(world: &World, game_info: &'a GameInfo) -> &'a UiTheme {
+    active_ui_theme(
+        game_info,
+        world.get_resource::<UserSettings>().unwrap(),
+        world.get_resource::<UiThemeVariant>().unwrap(),
+        "hud",
+    )
+}
+
+/// Get the font for HUD text, sized by the player's text size setting
+fn hud_font(world: &World, game_info: &GameInfo) -> TextBoxFont {
+    ui_font(
+        &hud_theme(world, game_info).default_font,
+        world.get_resource::<UserSettings>().unwrap(),
+    )
+}
+
+/// The player's abilities in the bottom-left corner of the HUD, covered up while they cool down
+fn ability_bar(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+
+    let mut query =
+        world.query_filtered::<(&Abilities, Option<&Energy>), With<Handle<Character>>>();
+    let (abilities, energy) = if let Some(player) = query.iter(world).next() {
+        player
+    } else {
+        return WidgetNode::None;
+    };
+    if abilities.slots.is_empty() {
+        return WidgetNode::None;
+    }
+    let ability_assets = world.get_resource::<Assets<Ability>>().unwrap();
+
+    let mut row = make_widget!(horizontal_box)
+        .with_props(HorizontalBoxProps {
+            separation: 2.,
+            ..Default::default()
+        })
+        .with_props(FlexBoxItemLayout {
+            grow: 0.,
+            basis: Some(ABILITY_ICON_SIZE),
+            ..Default::default()
+        });
+
+    for slot in &abilities.slots {
+        let ability = if let Some(ability) = ability_assets.get(&slot.ability) {
+            ability
+        } else {
+            continue;
+        };
+
+        // Cover the icon from the bottom up as the ability cools down, and cover all of it if
+        // there isn't enough energy to use it
+        let cooldown = if ability.cooldown > 0. {
+            (slot.cooldown / ability.cooldown).clamp(0., 1.)
+        } else {
+            0.
+        };
+        let affordable = energy
+            .map(|x| x.current >= ability.cost as f32)
+            .unwrap_or(true);
+        let cover = if affordable { cooldown } else { 1. };
+
+        let mut icon = make_widget!(content_box);
+        if let Some(image) = &ability.icon {
+            icon = icon.listed_slot(make_widget!(image_box).with_props(ImageBoxProps {
+                material: ImageBoxMaterial::Image(ImageBoxImage {
+                    id: image.clone(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }));
+        }
+        icon = icon.listed_slot(
+            make_widget!(image_box)
+                .with_props(ImageBoxProps {
+                    material: ImageBoxMaterial::Color(ImageBoxColor {
+                        color: Color {
+                            r: 0.,
+                            g: 0.,
+                            b: 0.,
+                            a: 0.6,
+                        },
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .with_props(ContentBoxItemLayout {
+                    anchors: Rect {
+                        left: 0.,
+                        right: 1.,
+                        top: 1. - cover,
+                        bottom: 1.,
+                    },
+                    ..Default::default()
+                }),
+        );
+
+        row = row.listed_slot(
+            make_widget!(size_box)
+                .with_props(SizeBoxProps {
+                    width: SizeBoxSizeValue::Exact(ABILITY_ICON_SIZE),
+                    height: SizeBoxSizeValue::Exact(ABILITY_ICON_SIZE),
+                    ..Default::default()
+                })
+                .with_props(FlexBoxItemLayout {
+                    grow: 0.,
+                    ..Default::default()
+                })
+                .named_slot("content", icon),
+        );
+    }
+
+    // Show the player's energy above the abilities, if they use it
+    let energy_bar = match energy {
+        Some(energy) if energy.max > 0. => make_widget!(content_box)
+            .with_props(FlexBoxItemLayout {
+                grow: 0.,
+                basis: Some(2.),
+                ..Default::default()
+            })
+            .listed_slot(
+                make_widget!(image_box)
+                    .with_props(ImageBoxProps {
+                        material: ImageBoxMaterial::Color(ImageBoxColor {
+                            color: Color {
+                                r: 0.3,
+                                g: 0.6,
+                                b: 1.,
+                                a: 1.,
+                            },
+                            ..Default::default()
+                        }),
+                        height: ImageBoxSizeValue::Fill,
+                        ..Default::default()
+                    })
+                    .with_props(ContentBoxItemLayout {
+                        anchors: Rect {
+                            left: 0.,
+                            right: energy.current / energy.max,
+                            top: 0.,
+                            bottom: 1.,
+                        },
+                        ..Default::default()
+                    }),
+            )
+            .into(),
+        _ => WidgetNode::None,
+    };
+
+    // Stack the bar from the bottom of the screen up, like the captions
+    make_widget!(vertical_box)
+        .with_props(VerticalBoxProps {
+            separation: 2.,
+            ..Default::default()
+        })
+        .with_props(ContentBoxItemLayout {
+            anchors: Rect {
+                left: 0.,
+                right: 0.5,
+                top: 0.,
+                bottom: 1.,
+            },
+            margin: 5.0.into(),
+            ..Default::default()
+        })
+        .listed_slot(make_widget!(space_box))
+        .listed_slot(energy_bar)
+        .listed_slot(row)
+        .into()
+}
+
+/// The bar of the gathering mini-game above the bottom of the screen, with the name of the
+/// resource node or the result above it
+fn gathering_bar(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let attempt = match &world.get_resource::<Gathering>().unwrap().0 {
+        Some(attempt) => attempt,
+        None => return WidgetNode::None,
+    };
+    let node = if let Some(node) = world
+        .get_resource::<Assets<ResourceNode>>()
+        .unwrap()
+        .get(&attempt.node)
+    {
+        node
+    } else {
+        return WidgetNode::None;
+    };
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+    let font = hud_font(world, game_info);
+    let item_name = world
+        .get_resource::<CraftingAssets>()
+        .unwrap()
+        .item_name(world.get_resource::<Assets<Item>>().unwrap(), &node.item);
+
+    let text = match attempt.result {
+        None => node.name.clone(),
+        Some(true) => format!("Got {} x{}!", item_name, node.count),
+        Some(false) => "Missed!".into(),
+    };
+    let (target_start, target_end) = attempt.target(node);
+    let color_box = |color: Color, left: f32, right: f32| {
+        make_widget!(image_box)
+            .with_props(ImageBoxProps {
+                material: ImageBoxMaterial::Color(ImageBoxColor {
+                    color,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .with_props(ContentBoxItemLayout {
+                anchors: Rect {
+                    left,
+                    right,
+                    top: 0.,
+                    bottom: 1.,
+                },
+                ..Default::default()
+            })
+    };
+
+    let bar = make_widget!(content_box)
+        .listed_slot(color_box(
+            Color {
+                r: 0.,
+                g: 0.,
+                b: 0.,
+                a: 0.6,
+            },
+            0.,
+            1.,
+        ))
+        .listed_slot(color_box(
+            Color {
+                r: 0.3,
+                g: 0.8,
+                b: 0.3,
+                a: 1.,
+            },
+            target_start,
+            target_end,
+        ))
+        // The marker, two pixels wide
+        .listed_slot(color_box(
+            Color {
+                r: 1.,
+                g: 1.,
+                b: 1.,
+                a: 1.,
+            },
+            (attempt.marker - 1. / GATHERING_BAR_SIZE.0).max(0.),
+            (attempt.marker + 1. / GATHERING_BAR_SIZE.0).min(1.),
+        ));
+
+    // Stack the bar from the bottom of the screen up, like the captions
+    make_widget!(vertical_box)
+        .with_props(ContentBoxItemLayout {
+            anchors: Rect {
+                left: 0.5,
+                right: 0.5,
+                top: 0.,
+                bottom: 1.,
+            },
+            margin: Rect {
+                left: -GATHERING_BAR_SIZE.0 / 2.,
+                right: -GATHERING_BAR_SIZE.0 / 2.,
+                top: 0.,
+                bottom: 30.,
+            },
+            ..Default::default()
+        })
+        .listed_slot(make_widget!(space_box))
+        .listed_slot(
+            make_widget!(rich_text)
+                .with_props(RichTextProps { text })
+                .with_props(TextBoxProps {
+                    font: font.clone(),
+                    horizontal_align: TextBoxHorizontalAlign::Center,
+                    ..Default::default()
+                })
+                .with_props(FlexBoxItemLayout {
+                    grow: 0.,
+                    basis: Some(12. * font.size),
+                    ..Default::default()
+                }),
+        )
+        .listed_slot(
+            make_widget!(size_box)
+                .with_props(SizeBoxProps {
+                    width: SizeBoxSizeValue::Fill,
+                    height: SizeBoxSizeValue::Exact(GATHERING_BAR_SIZE.1),
+                    ..Default::default()
+                })
+                .with_props(FlexBoxItemLayout {
+                    grow: 0.,
+                    ..Default::default()
+                })
+                .named_slot("content", bar),
         )
         .into()
 }
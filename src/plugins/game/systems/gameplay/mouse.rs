@@ -0,0 +1,89 @@
+use super::*;
+
+/// Resource containing the position of the mouse cursor in the game world, if the cursor is over
+/// the window
+#[derive(Default)]
+pub struct CursorWorldPosition(pub Option<Vec2>);
+
+/// Update the [`CursorWorldPosition`] from the window cursor and the camera
+pub fn update_cursor_world_position(
+    mut cursor_world_position: ResMut<CursorWorldPosition>,
+    cameras: Query<(&Camera, &Transform)>,
+    windows: Res<Windows>,
+) {
+    let position = (|| {
+        let window = windows.get_primary()?;
+        let cursor = window.cursor_position()?;
+        let (camera, camera_transform) = cameras.single().ok()?;
+
+        // Get the size of the camera view in game pixels and the size of the window in screen
+        // pixels
+        let camera_size = camera.get_target_sizes(window).low;
+        let camera_width = camera_size.x as f32 * camera.pixel_aspect_ratio;
+        let camera_height = camera_size.y as f32;
+        let window_size = Vec2::new(window.width(), window.height());
+        if camera_width <= 0. || camera_height <= 0. {
+            return None;
+        }
+
+        // The camera view is scaled up to fit the window and centered in it, so we find the scale
+        // to go from game pixels to screen pixels
+        let scale = (window_size.x / camera_width).min(window_size.y / camera_height);
+
+        // Get the cursor offset from the center of the window. The window's Y axis points up, but
+        // the world's Y axis points down.
+        let offset = cursor - window_size / 2.;
+
+        Some(Vec2::new(
+            camera_transform.translation.x + offset.x / (scale * camera.pixel_aspect_ratio),
+            camera_transform.translation.y - offset.y / scale,
+        ))
+    })();
+
+    // Avoid triggering change detection if the cursor hasn't moved
+    if cursor_world_position.0 != position {
+        cursor_world_position.0 = position;
+    }
+}
+
+/// Listen for mouse clicks and send attack and interact control events in response
+pub fn mouse_control_input(
+    mut control_events: EventWriter<ControlEvent>,
+    mouse_input: Res<Input<MouseButton>>,
+    game_info: Res<GameInfo>,
+    attract_mode: Res<AttractMode>,
+    cutscene_player: Res<CutscenePlayer>,
+) {
+    // Skip if mouse controls are disabled or the attract mode replay or a cutscene is in control
+    if !game_info.mouse.enabled || attract_mode.active || cutscene_player.input_locked {
+        return;
+    }
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        control_events.send(ControlEvent::Attack);
+    }
+
+    if mouse_input.just_pressed(MouseButton::Right) {
+        control_events.send(ControlEvent::Interact);
+    }
+}
+
+/// Get the direction a character at `position` should face to look at the mouse cursor
+pub fn direction_to_cursor(
+    position: Vec3,
+    cursor_world_position: &CursorWorldPosition,
+) -> Option<CharacterStateDirection> {
+    let offset = cursor_world_position.0? - position.truncate();
+
+    Some(if offset.x.abs() > offset.y.abs() {
+        if offset.x > 0. {
+            CharacterStateDirection::Right
+        } else {
+            CharacterStateDirection::Left
+        }
+    } else if offset.y > 0. {
+        CharacterStateDirection::Down
+    } else {
+        CharacterStateDirection::Up
+    })
+}
@@ -0,0 +1,170 @@
+use rand::Rng;
+
+use super::{game_time::GameTime, *};
+
+/// The prefix of prop interactions that start the gathering mini-game, followed by the path to the
+/// resource node, i.e. `gather:nodes/pond.gather.yml`
+const GATHER_INTERACTION_PREFIX: &str = "gather:";
+
+/// How far the player can walk away from a resource node before the mini-game is cancelled, in
+/// pixels
+const GATHERING_CANCEL_DISTANCE: f32 = 32.;
+
+/// How long after the mini-game starts before the player can stop the marker, so that the press
+/// that started it doesn't also stop it, in seconds
+const GATHERING_START_DELAY: f32 = 0.25;
+
+/// How long the result of the mini-game is shown for, in seconds
+const GATHERING_RESULT_DURATION: f32 = 1.;
+
+/// Resource for the gathering mini-game the player is playing, if any
+#[derive(Default)]
+pub struct Gathering(pub Option<GatheringAttempt>);
+
+/// The state of the gathering mini-game at a resource node
+pub struct GatheringAttempt {
+    pub node: Handle<ResourceNode>,
+    /// The prop the player is gathering from
+    pub prop: Entity,
+    /// The position of the marker along the bar, from 0 to 1
+    pub marker: f32,
+    /// Whether the marker is moving toward the end of the bar
+    pub forward: bool,
+    /// Where the target area is along the part of the bar it fits in, from 0 to 1
+    pub target_position: f32,
+    /// The time since the mini-game started, or since the result was decided, in seconds
+    pub elapsed: f32,
+    /// Whether the player stopped the marker in the target area, once they have stopped it
+    pub result: Option<bool>,
+}
+
+impl GatheringAttempt {
+    /// Get where the target area starts and ends along the bar, from 0 to 1
+    pub fn target(&self, node: &ResourceNode) -> (f32, f32) {
+        let start = self.target_position * (1. - node.target_size);
+        (start, start + node.target_size)
+    }
+}
+
+/// Start the gathering mini-game when the player interacts with a resource node
+pub fn start_gathering(
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    mut gathering: ResMut<Gathering>,
+    mut rng: ResMut<GameRng>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in interaction_events.iter() {
+        let node = if let Some(node) = event.interaction.strip_prefix(GATHER_INTERACTION_PREFIX) {
+            node
+        } else {
+            continue;
+        };
+        // Stopping the marker is also an interaction, which shouldn't start the game over
+        if gathering.0.is_some() {
+            continue;
+        }
+
+        gathering.0 = Some(GatheringAttempt {
+            node: asset_server.load(node),
+            prop: event.prop,
+            marker: 0.,
+            forward: true,
+            target_position: rng.gen_range(0.0..1.0),
+            elapsed: 0.,
+            result: None,
+        });
+    }
+}
+
+/// Move the marker of the gathering mini-game, and give the player the item if they stop it in the
+/// target area
+pub fn update_gathering(
+    mut commands: Commands,
+    mut control_events: EventReader<ControlEvent>,
+    mut gathering: ResMut<Gathering>,
+    props: Query<(&Transform, Option<&PersistentId>), With<Prop>>,
+    characters: Query<&Transform, With<Handle<Character>>>,
+    node_assets: Res<Assets<ResourceNode>>,
+    mut inventory: ResMut<Inventory>,
+    mut level_states: ResMut<LevelStates>,
+    game_time: Res<GameTime>,
+) {
+    let stop_pressed = control_events.iter().any(|x| x == &ControlEvent::Interact);
+    let attempt = if let Some(attempt) = &mut gathering.0 {
+        attempt
+    } else {
+        return;
+    };
+    attempt.elapsed += game_time.delta_seconds();
+
+    // Show the result for a moment before ending the game
+    if attempt.result.is_some() {
+        if attempt.elapsed >= GATHERING_RESULT_DURATION {
+            gathering.0 = None;
+        }
+        return;
+    }
+
+    // Cancel the game if the node is gone or the player walks away from it
+    let (prop_transform, persistent_id) = if let Ok(prop) = props.get(attempt.prop) {
+        prop
+    } else {
+        gathering.0 = None;
+        return;
+    };
+    let walked_away = characters
+        .single()
+        .map(|x| {
+            x.translation
+                .truncate()
+                .distance(prop_transform.translation.truncate())
+                > GATHERING_CANCEL_DISTANCE
+        })
+        .unwrap_or(true);
+    if walked_away {
+        gathering.0 = None;
+        return;
+    }
+
+    let node = if let Some(node) = node_assets.get(&attempt.node) {
+        node
+    } else {
+        return;
+    };
+
+    // Sweep the marker back and forth across the bar
+    let step = node.speed * game_time.delta_seconds();
+    if attempt.forward {
+        attempt.marker += step;
+        if attempt.marker >= 1. {
+            attempt.marker = 1.;
+            attempt.forward = false;
+        }
+    } else {
+        attempt.marker -= step;
+        if attempt.marker <= 0. {
+            attempt.marker = 0.;
+            attempt.forward = true;
+        }
+    }
+
+    if !stop_pressed || attempt.elapsed < GATHERING_START_DELAY {
+        return;
+    }
+
+    let (target_start, target_end) = attempt.target(node);
+    let success = attempt.marker >= target_start && attempt.marker <= target_end;
+    if success {
+        inventory.add(&node.item, node.count);
+
+        // Used up nodes stay used up
+        if node.depletes {
+            if let Some(id) = persistent_id {
+                level_states.entity_mut(&id.level, &id.id).removed = true;
+            }
+            commands.entity(attempt.prop).despawn_recursive();
+        }
+    }
+    attempt.result = Some(success);
+    attempt.elapsed = 0.;
+}
@@ -0,0 +1,297 @@
+use bevy::prelude::{Entity, Handle, Input, KeyCode, Transform, With, World};
+use bevy_retrograde::{physics::heron::Velocity, ui::raui::prelude::*};
+
+use crate::{
+    plugins::game::{
+        assets::GameInfo,
+        components::{Character, CurrentLevel, Enemy, Entrance, Prop},
+        systems::{gameplay::Health, perf_hud::debug_text, tweak_panel::TweakPanel},
+    },
+    EngineConfig,
+};
+
+/// How much a value changes each time it is edited
+const EDIT_STEP: f32 = 1.;
+
+/// How much a value changes each time it is edited while shift is held
+const EDIT_STEP_FAST: f32 = 8.;
+
+/// Resource for the entity inspector that is toggled with F5 when the `debug` engine option is on
+#[derive(Default)]
+pub struct Inspector {
+    /// Whether the inspector is shown
+    pub visible: bool,
+    /// The lines of text in the inspector
+    pub lines: Vec<String>,
+    /// The index of the selected entity in the current level
+    selected: usize,
+    /// The index of the selected value of the selected entity
+    field: usize,
+}
+
+/// A value of an entity that can be edited in the inspector
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum InspectorField {
+    X,
+    Y,
+    Health,
+    MaxHealth,
+    VelocityX,
+    VelocityY,
+}
+
+impl InspectorField {
+    fn label(&self) -> &'static str {
+        match self {
+            InspectorField::X => "x",
+            InspectorField::Y => "y",
+            InspectorField::Health => "health",
+            InspectorField::MaxHealth => "max health",
+            InspectorField::VelocityX => "velocity x",
+            InspectorField::VelocityY => "velocity y",
+        }
+    }
+
+    /// Get the value of the field on an entity, if it has it
+    fn get(&self, world: &World, entity: Entity) -> Option<f32> {
+        match self {
+            InspectorField::X => world.get::<Transform>(entity).map(|x| x.translation.x),
+            InspectorField::Y => world.get::<Transform>(entity).map(|x| x.translation.y),
+            InspectorField::Health => world.get::<Health>(entity).map(|x| x.current as f32),
+            InspectorField::MaxHealth => world.get::<Health>(entity).map(|x| x.max as f32),
+            InspectorField::VelocityX => world.get::<Velocity>(entity).map(|x| x.linear.x),
+            InspectorField::VelocityY => world.get::<Velocity>(entity).map(|x| x.linear.y),
+        }
+    }
+
+    /// Add an amount to the field on an entity
+    fn edit(&self, world: &mut World, entity: Entity, amount: f32) {
+        let add = |value: u32| (value as f32 + amount).max(0.) as u32;
+        match self {
+            InspectorField::X | InspectorField::Y => {
+                if let Some(mut transform) = world.get_mut::<Transform>(entity) {
+                    if *self == InspectorField::X {
+                        transform.translation.x += amount;
+                    } else {
+                        transform.translation.y += amount;
+                    }
+                }
+            }
+            InspectorField::Health | InspectorField::MaxHealth => {
+                if let Some(mut health) = world.get_mut::<Health>(entity) {
+                    if *self == InspectorField::Health {
+                        health.current = add(health.current).min(health.max);
+                    } else {
+                        health.max = add(health.max);
+                        health.current = health.current.min(health.max);
+                    }
+                }
+            }
+            InspectorField::VelocityX | InspectorField::VelocityY => {
+                if let Some(mut velocity) = world.get_mut::<Velocity>(entity) {
+                    if *self == InspectorField::VelocityX {
+                        velocity.linear.x += amount;
+                    } else {
+                        velocity.linear.y += amount;
+                    }
+                }
+            }
+        }
+    }
+}
+
+const FIELDS: [InspectorField; 6] = [
+    InspectorField::X,
+    InspectorField::Y,
+    InspectorField::Health,
+    InspectorField::MaxHealth,
+    InspectorField::VelocityX,
+    InspectorField::VelocityY,
+];
+
+/// Toggle the inspector with F5 and apply the edits made with it
+///
+/// Page Up and Page Down select an entity in the current level, Tab selects one of its values, and
+/// `-` and `=` change the value, faster while shift is held.
+pub fn update_inspector(world: &mut World) {
+    let debug = world.get_resource::<EngineConfig>().unwrap().debug;
+    let (toggle, previous, next, next_field, decrease, increase, fast) = {
+        let input = world.get_resource::<Input<KeyCode>>().unwrap();
+        (
+            input.just_pressed(KeyCode::F5),
+            input.just_pressed(KeyCode::PageUp),
+            input.just_pressed(KeyCode::PageDown),
+            input.just_pressed(KeyCode::Tab),
+            input.just_pressed(KeyCode::Minus),
+            input.just_pressed(KeyCode::Equals),
+            input.pressed(KeyCode::LShift) || input.pressed(KeyCode::RShift),
+        )
+    };
+
+    let mut inspector = world.get_resource_mut::<Inspector>().unwrap();
+    if !debug {
+        inspector.visible = false;
+    } else if toggle {
+        inspector.visible = !inspector.visible;
+    }
+    if !inspector.visible {
+        inspector.lines.clear();
+        return;
+    }
+
+    // The tweak panel uses the same keys, so only one of them can be open at a time
+    if toggle {
+        world.get_resource_mut::<TweakPanel>().unwrap().visible = false;
+    }
+
+    let entities = level_entities(world);
+    let mut inspector = world.get_resource_mut::<Inspector>().unwrap();
+    if entities.is_empty() {
+        inspector.lines = vec!["Inspector: nothing in this level".into()];
+        return;
+    }
+
+    // Select an entity, wrapping around at the ends of the list
+    if next {
+        inspector.selected += 1;
+        inspector.field = 0;
+    }
+    if previous {
+        inspector.selected = inspector
+            .selected
+            .checked_sub(1)
+            .unwrap_or(entities.len() - 1);
+        inspector.field = 0;
+    }
+    inspector.selected %= entities.len();
+    let (selected, (entity, kind)) = (inspector.selected, entities[inspector.selected]);
+
+    // Select one of the values that the entity has
+    let fields = FIELDS
+        .iter()
+        .copied()
+        .filter(|x| x.get(world, entity).is_some())
+        .collect::<Vec<_>>();
+    let mut inspector = world.get_resource_mut::<Inspector>().unwrap();
+    if next_field {
+        inspector.field += 1;
+    }
+    let field_index = inspector.field.checked_rem(fields.len()).unwrap_or(0);
+    inspector.field = field_index;
+
+    // Change the selected value
+    let step = if fast { EDIT_STEP_FAST } else { EDIT_STEP };
+    if let Some(field) = fields.get(field_index) {
+        if increase {
+            field.edit(world, entity, step);
+        }
+        if decrease {
+            field.edit(world, entity, -step);
+        }
+    }
+
+    let mut lines = vec![
+        "Inspector (F5): PgUp/PgDn select, Tab value, -/= edit".into(),
+        format!(
+            "{}/{}: {} {}v{}",
+            selected + 1,
+            entities.len(),
+            kind,
+            entity.id(),
+            entity.generation()
+        ),
+    ];
+    for (i, field) in fields.iter().enumerate() {
+        lines.push(format!(
+            "{} {}: {:.1}",
+            if i == field_index { ">" } else { " " },
+            field.label(),
+            field.get(world, entity).unwrap_or_default()
+        ));
+    }
+    if let Some(enemy) = world.get::<Enemy>(entity) {
+        lines.push(format!("  level: {}", enemy.level));
+    }
+    if let Some(entrance) = world.get::<Entrance>(entity) {
+        lines.push(format!(
+            "  entrance: {} to {} at {}",
+            entrance.id, entrance.to_level, entrance.spawn_at
+        ));
+    }
+    if let Some(interaction) = world
+        .get::<Prop>(entity)
+        .and_then(|x| x.interaction.clone())
+    {
+        lines.push(format!("  interaction: {}", interaction));
+    }
+
+    world.get_resource_mut::<Inspector>().unwrap().lines = lines;
+}
+
+/// Get the player and the enemies, entrances, and props in the current level, with the kind of
+/// each one
+fn level_entities(world: &mut World) -> Vec<(Entity, &'static str)> {
+    let current_level = if let Some(level) = world.get_resource::<CurrentLevel>() {
+        level.0.clone()
+    } else {
+        return Vec::new();
+    };
+
+    let mut entities = world
+        .query_filtered::<Entity, With<Handle<Character>>>()
+        .iter(world)
+        .map(|x| (x, "Player"))
+        .collect::<Vec<_>>();
+    let mut others = Vec::new();
+    others.extend(
+        world
+            .query::<(Entity, &Enemy)>()
+            .iter(world)
+            .filter(|(_, x)| x.level == current_level)
+            .map(|(x, _)| (x, "Enemy")),
+    );
+    others.extend(
+        world
+            .query::<(Entity, &Entrance)>()
+            .iter(world)
+            .filter(|(_, x)| x.level == current_level)
+            .map(|(x, _)| (x, "Entrance")),
+    );
+    others.extend(
+        world
+            .query::<(Entity, &Prop)>()
+            .iter(world)
+            .filter(|(_, x)| x.level == current_level)
+            .map(|(x, _)| (x, "Prop")),
+    );
+
+    // Keep the order stable from frame to frame so that the selection doesn't jump around
+    others.sort_by_key(|(x, _)| x.id());
+    entities.extend(others);
+    entities
+}
+
+/// The entity inspector in the top-right corner of the screen
+pub fn inspector(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let inspector = if let Some(inspector) = world.get_resource::<Inspector>() {
+        inspector
+    } else {
+        return WidgetNode::None;
+    };
+    if !inspector.visible || inspector.lines.is_empty() {
+        return WidgetNode::None;
+    }
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+
+    debug_text(
+        &game_info.ui_theme.default_font,
+        &inspector.lines,
+        Rect {
+            left: 0.5,
+            right: 1.,
+            top: 0.,
+            bottom: 1.,
+        },
+    )
+}
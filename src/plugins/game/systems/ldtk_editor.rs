@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use super::*;
+use crate::EngineConfig;
+
+/// Launch the LDtk editor on the game's map with F7 when the `debug` engine option is on
+///
+/// LDtk can't be told which level to show from the command line, so the current level and the
+/// player's position in it are logged to find it by. Combined with the `hot-reload` option, saving
+/// the map in the editor reloads it in the game with the player left where they were.
+pub fn open_map_in_ldtk(
+    keyboard_input: Res<Input<KeyCode>>,
+    engine_config: Res<EngineConfig>,
+    game_info: Option<Res<GameInfo>>,
+    current_level: Option<Res<CurrentLevel>>,
+    maps: Query<&GameMap>,
+    characters: Query<&Transform, With<Handle<Character>>>,
+) {
+    if !engine_config.debug || !keyboard_input.just_pressed(KeyCode::F7) {
+        return;
+    }
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+
+    if !game_info.map.ends_with(".ldtk") {
+        warn!(map=%game_info.map, "Only LDtk maps can be opened in the editor");
+        return;
+    }
+    if engine_config.asset_pack.is_some() || crate::asset_pack::embedded_assets().is_some() {
+        warn!("Can't open the map in the editor when playing from an asset pack");
+        return;
+    }
+    let project = Path::new(&engine_config.asset_path).join(&game_info.map);
+
+    info!(
+        editor=%engine_config.ldtk_editor,
+        project=%project.display(),
+        "Opening map in LDtk"
+    );
+    if let Err(e) = std::process::Command::new(&engine_config.ldtk_editor)
+        .arg(&project)
+        .spawn()
+    {
+        warn!(editor=%engine_config.ldtk_editor, "Could not launch LDtk: {}", e);
+        return;
+    }
+
+    if let Some(current_level) = current_level {
+        let level_position = maps
+            .single()
+            .ok()
+            .and_then(|x| x.level(&current_level))
+            .map(|x| x.position)
+            .unwrap_or_default();
+        let player_position = characters
+            .iter()
+            .next()
+            .map(|x| x.translation.truncate() - level_position);
+        info!(
+            level=%**current_level,
+            ?player_position,
+            "Current level to edit"
+        );
+    }
+}
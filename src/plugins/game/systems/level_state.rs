@@ -0,0 +1,73 @@
+use super::*;
+
+/// The prefix of prop interactions that set a flag on the prop, followed by the flag, i.e.
+/// `flag:opened`. The flags can be checked in rich text with `{flag:...}`.
+const FLAG_INTERACTION_PREFIX: &str = "flag:";
+
+/// Apply the [`LevelStates`] to map entities when they spawn, when the player enters their level,
+/// and when the level states are replaced, i.e. when loading a save
+pub fn apply_level_states(
+    mut commands: Commands,
+    mut entities: Query<(Entity, &PersistentId, &mut Transform)>,
+    spawned: Query<(), Added<PersistentId>>,
+    level_states: Res<LevelStates>,
+    current_level: Option<Res<CurrentLevel>>,
+) {
+    let current_level_changed = current_level.map(|x| x.is_changed()).unwrap_or(false);
+    let reapply_all = level_states.is_changed() || current_level_changed;
+
+    for (ent, id, mut transform) in entities.iter_mut() {
+        if !reapply_all && spawned.get(ent).is_err() {
+            continue;
+        }
+
+        let state = if let Some(state) = level_states.entity(&id.level, &id.id) {
+            state
+        } else {
+            continue;
+        };
+
+        if state.removed {
+            commands.entity(ent).despawn_recursive();
+            continue;
+        }
+
+        if let Some((x, y)) = state.position {
+            transform.translation.x = x;
+            transform.translation.y = y;
+        }
+    }
+}
+
+/// Set the flags of props with a `flag:` interaction in the [`LevelStates`]
+pub fn set_flags_from_props(
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    props: Query<&PersistentId, With<Prop>>,
+    mut level_states: ResMut<LevelStates>,
+) {
+    for event in interaction_events.iter() {
+        let flag = if let Some(flag) = event.interaction.strip_prefix(FLAG_INTERACTION_PREFIX) {
+            flag
+        } else {
+            continue;
+        };
+
+        let id = if let Ok(id) = props.get(event.prop) {
+            id
+        } else {
+            continue;
+        };
+
+        // Avoid triggering change detection, which re-applies the level states, if the flag is
+        // already set
+        let is_set = level_states
+            .entity(&id.level, &id.id)
+            .map_or(false, |x| x.flags.contains(flag));
+        if !is_set {
+            level_states
+                .entity_mut(&id.level, &id.id)
+                .flags
+                .insert(flag.into());
+        }
+    }
+}
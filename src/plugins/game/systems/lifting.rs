@@ -0,0 +1,158 @@
+use super::{
+    abilities::{facing_direction, Projectile},
+    fixed_step::FixedStepInterpolation,
+    *,
+};
+
+/// The interaction of props that the player can lift and throw
+pub const LIFT_INTERACTION: &str = "lift";
+
+/// How close a thrown prop has to get to an enemy to hit it, in pixels
+const THROWN_PROP_RADIUS: f32 = 6.;
+
+/// Component added to the player while they are carrying a lifted prop
+pub struct Carrying {
+    /// The prop being carried
+    pub prop: Entity,
+    /// The damage the prop does when it is thrown
+    pub damage: u32,
+}
+
+/// Pick up the [`Liftable`] props that the player interacts with, if they aren't carrying one
+/// already
+pub fn lift_props(
+    mut commands: Commands,
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    props: Query<(&Liftable, Option<&PersistentId>, Option<&Children>)>,
+    characters: Query<Entity, (With<Handle<Character>>, Without<Carrying>)>,
+    mut level_states: ResMut<LevelStates>,
+) {
+    for event in interaction_events.iter() {
+        if event.interaction != LIFT_INTERACTION {
+            continue;
+        }
+        let (liftable, id, children) = if let Ok(prop) = props.get(event.prop) {
+            prop
+        } else {
+            continue;
+        };
+        let character = if let Ok(character) = characters.single() {
+            character
+        } else {
+            continue;
+        };
+
+        // Lifted props won't be back, since they break when they are thrown
+        if let Some(id) = id {
+            level_states.entity_mut(&id.level, &id.id).removed = true;
+        }
+
+        // Remove the prop's collision and stop it from being interacted with or y-sorted while it
+        // is carried
+        for &child in children.into_iter().flat_map(|x| x.iter()) {
+            commands.entity(child).despawn_recursive();
+        }
+        commands
+            .entity(event.prop)
+            .remove::<Prop>()
+            .remove::<RigidBody>()
+            .remove::<YSort>();
+
+        commands.entity(character).insert(Carrying {
+            prop: event.prop,
+            damage: liftable.damage,
+        });
+    }
+}
+
+/// Keep carried props above the player, and throw them in the direction the player is facing when
+/// the player interacts or attacks
+pub fn carry_and_throw_props(
+    mut commands: Commands,
+    mut control_events: EventReader<ControlEvent>,
+    characters: Query<
+        (
+            Entity,
+            &Transform,
+            &Carrying,
+            Option<&CharacterState>,
+            Option<&YSort>,
+        ),
+        With<Handle<Character>>,
+    >,
+    mut props: Query<
+        (
+            &mut Transform,
+            &Handle<Image>,
+            &Sprite,
+            Option<&Handle<SpriteSheet>>,
+        ),
+        Without<Handle<Character>>,
+    >,
+    mut projectile_pool: ResMut<EntityPool<Projectile>>,
+    game_info: Option<Res<GameInfo>>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+    let config = &game_info.lifting;
+    let throw = control_events
+        .iter()
+        .any(|x| x == &ControlEvent::Interact || x == &ControlEvent::Attack);
+
+    for (character_ent, transform, carrying, state, y_sort) in characters.iter() {
+        let (mut prop_transform, image, sprite, sprite_sheet) =
+            if let Ok(prop) = props.get_mut(carrying.prop) {
+                prop
+            } else {
+                // The prop is gone, i.e. because the level was reloaded
+                commands.entity(character_ent).remove::<Carrying>();
+                continue;
+            };
+
+        // Carry the prop above the player, drawn in front of them
+        prop_transform.translation =
+            transform.translation + Vec3::new(0., -config.carry_height, 0.1);
+
+        if !throw {
+            continue;
+        }
+
+        // Throw a copy of the prop from the player's position so that it hits what is in front of
+        // them, showing the frame the prop was on if it is animated
+        let start = transform.translation;
+        commands.entity(character_ent).remove::<Carrying>();
+        commands.entity(carrying.prop).despawn_recursive();
+        let projectile = projectile_pool.take(&mut commands);
+        commands
+            .entity(projectile)
+            .insert_bundle(SpriteBundle {
+                image: image.clone(),
+                transform: Transform::from_translation(start),
+                sprite: Sprite {
+                    flip_x: sprite.flip_x,
+                    flip_y: sprite.flip_y,
+                    centered: sprite.centered,
+                    pixel_perfect: sprite.pixel_perfect,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(Projectile {
+                velocity: facing_direction(state) * config.throw_speed,
+                remaining_distance: config.throw_range,
+                radius: THROWN_PROP_RADIUS,
+                damage: carrying.damage,
+                from_enemy: false,
+                area: false,
+            })
+            .insert(y_sort.copied().unwrap_or_default())
+            .insert(FixedStepInterpolation::new(start));
+        match sprite_sheet {
+            Some(sprite_sheet) => commands.entity(projectile).insert(sprite_sheet.clone()),
+            None => commands.entity(projectile).remove::<Handle<SpriteSheet>>(),
+        };
+    }
+}
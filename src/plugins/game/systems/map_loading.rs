@@ -1,6 +1,10 @@
 use std::path::{Path, PathBuf};
 
-use bevy::{prelude::*, utils::HashMap};
+use bevy::{
+    ecs::system::EntityCommands,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use bevy_retrograde::{
     core::image::{DynamicImage, GenericImageView},
     prelude::*,
@@ -10,39 +14,487 @@ use itertools::Itertools;
 use navmesh::NavMesh;
 
 use crate::plugins::game::{
-    assets::GameInfo,
+    assets::{CollisionLayersConfig, GameInfo, LdtkExternalLevel, TiledMap},
     components::{
-        DamageRegion, DamageRegionKnockBack, Enemy, Entrance, PhysicsGroup,
-        TilesetTileCollisionMode, TilesetTileMetadata,
+        Alertness, Character, CurrentLevel, DamageRegion, DamageRegionKnockBack, Enemy, Entrance,
+        Liftable, Pack, PathFollower, PersistentId, PhysicsGroup, Plot, Prop, PropCollisionMode,
+        PropNoise, Respawns, ShaderRegion, SpriteAnimation, TilesetTileCollisionMode, Vision,
+        YSort,
+    },
+    events::FatalErrorEvent,
+    map::{
+        is_dungeon_link, GameEntity, GameLevel, GameMap, InstanceOffsetApplied, MapLayer, Placement,
     },
 };
 
+use super::{
+    dungeon::DungeonLayout,
+    farming::PLOT_INTERACTION,
+    footprints::MapFootprintTiles,
+    gameplay::Health,
+    lifting::LIFT_INTERACTION,
+    movement::{MapTerrainSpeeds, SpeedModifiers},
+    reflections::MapReflectiveTiles,
+    secret_walls::SecretWall,
+    sprite_batching::MergedLayerLevels,
+    Abilities, CriticalAssets, FixedStepInterpolation, SystemTimings,
+};
+
+/// Add the [`GameMap`] to LDtk maps once they have loaded
+pub fn build_ldtk_game_maps(
+    mut commands: Commands,
+    maps: Query<(Entity, &Handle<LdtkMap>), Without<GameMap>>,
+    map_assets: Res<Assets<LdtkMap>>,
+) {
+    for (map_ent, map_handle) in maps.iter() {
+        if let Some(map) = map_assets.get(map_handle) {
+            // Wait for the external levels to be merged into the map
+            if has_unmerged_external_levels(map) {
+                continue;
+            }
+
+            commands.entity(map_ent).insert(GameMap::from_ldtk(map));
+        }
+    }
+}
+
+/// Component holding the external level files of an LDtk map, by their path relative to the map
+pub struct LdtkExternalLevels {
+    handles: HashMap<String, Handle<LdtkExternalLevel>>,
+    /// The level files that couldn't be parsed, which aren't merged again until they are modified
+    failed: HashSet<String>,
+}
+
+/// Whether or not the map has levels that are saved in separate level files that haven't been
+/// merged into the map yet
+fn has_unmerged_external_levels(map: &LdtkMap) -> bool {
+    map.project
+        .levels
+        .iter()
+        .any(|x| x.layer_instances.is_none() && x.external_rel_path.is_some())
+}
+
+/// Load the separate level files of LDtk maps and merge them into the map
+///
+/// The levels are merged again whenever the map or one of its level files is reloaded. Level files
+/// that can't be loaded, or can't be parsed before they have been merged, stop the game with a
+/// fatal error, because the map can't be built without them.
+pub fn load_ldtk_external_levels(
+    mut commands: Commands,
+    mut maps: Query<(Entity, &Handle<LdtkMap>, Option<&mut LdtkExternalLevels>)>,
+    mut map_assets: ResMut<Assets<LdtkMap>>,
+    level_assets: Res<Assets<LdtkExternalLevel>>,
+    mut level_events: EventReader<AssetEvent<LdtkExternalLevel>>,
+    asset_server: Res<AssetServer>,
+    mut critical_assets: ResMut<CriticalAssets>,
+    mut error_events: EventWriter<FatalErrorEvent>,
+) {
+    let modified_levels = level_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (map_ent, map_handle, external_levels) in maps.iter_mut() {
+        let map = if let Some(map) = map_assets.get(map_handle) {
+            map
+        } else {
+            continue;
+        };
+
+        // Start loading the level files the first time we see the map
+        let mut external_levels = if let Some(external_levels) = external_levels {
+            external_levels
+        } else {
+            if !map.project.external_levels {
+                continue;
+            }
+
+            // Level paths are relative to the map file
+            let map_dir = asset_server
+                .get_handle_path(map_handle)
+                .and_then(|x| x.path().parent().map(Path::to_path_buf))
+                .unwrap_or_default();
+
+            let handles = map
+                .project
+                .levels
+                .iter()
+                .filter_map(|level| level.external_rel_path.clone())
+                .map(|rel_path| {
+                    let handle = asset_server.load_cached(map_dir.join(&rel_path));
+                    // We would wait for the level forever if it fails to load
+                    critical_assets.watch(&handle);
+                    (rel_path, handle)
+                })
+                .collect();
+
+            commands.entity(map_ent).insert(LdtkExternalLevels {
+                handles,
+                failed: HashSet::default(),
+            });
+            continue;
+        };
+        let LdtkExternalLevels { handles, failed } = &mut *external_levels;
+
+        // Try the level files that failed to parse again once they have been modified
+        for (rel_path, handle) in handles.iter() {
+            if modified_levels.contains(handle) {
+                failed.remove(rel_path);
+            }
+        }
+
+        // Merge levels that haven't been merged yet, or that have been modified
+        let needs_merge = map.project.levels.iter().any(|level| {
+            level
+                .external_rel_path
+                .as_ref()
+                .filter(|x| !failed.contains(*x))
+                .and_then(|x| handles.get(x))
+                .map(|handle| level.layer_instances.is_none() || modified_levels.contains(handle))
+                .unwrap_or(false)
+        });
+        if !needs_merge {
+            continue;
+        }
+
+        // Wait for all of the level files to load
+        if handles.values().any(|x| level_assets.get(x).is_none()) {
+            continue;
+        }
+
+        // Getting the map mutably will trigger a hot reload of the map
+        let map = map_assets.get_mut(map_handle).unwrap();
+        for level in &mut map.project.levels {
+            let (rel_path, handle) = if let Some(x) = level
+                .external_rel_path
+                .clone()
+                .filter(|x| !failed.contains(x))
+                .and_then(|x| handles.get(&x).map(|handle| (x, handle)))
+            {
+                x
+            } else {
+                continue;
+            };
+            let external_level = if let Some(external_level) = level_assets.get(handle) {
+                external_level
+            } else {
+                continue;
+            };
+
+            match serde_yaml::from_value(external_level.data.clone()) {
+                Ok(external_level) => {
+                    *level = external_level;
+                    // Keep the path to the level file so that we can merge it again when it changes
+                    level.external_rel_path = Some(rel_path);
+                }
+                Err(error) => {
+                    let path = asset_server
+                        .get_handle_path(handle)
+                        .map(|x| x.path().display().to_string());
+                    error!(
+                        %error,
+                        level=%level.identifier,
+                        ?path,
+                        "Could not parse external level file"
+                    );
+                    failed.insert(rel_path);
+
+                    // A level that has been merged before keeps its old data, but the map can't be
+                    // built without the others
+                    if level.layer_instances.is_none() {
+                        error_events.send(FatalErrorEvent {
+                            message: format!(
+                                "Could not parse the file for level `{}`: {}",
+                                level.identifier, error
+                            ),
+                            asset: path,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Add the [`MapLayer`] component to the layers spawned for LDtk maps
+pub fn tag_ldtk_map_layers(
+    mut commands: Commands,
+    layers: Query<(Entity, &LdtkMapLayer), Without<MapLayer>>,
+) {
+    for (layer_ent, layer) in layers.iter() {
+        commands.entity(layer_ent).insert(MapLayer {
+            level_identifier: layer.level_identifier.clone(),
+            identifier: layer.layer_instance.__identifier.clone(),
+            visible: layer.layer_instance.visible,
+        });
+    }
+}
+
+/// Move the layers of instanced levels in LDtk maps to where the level was placed in the
+/// [`GameMap`]
+pub fn position_instanced_ldtk_layers(
+    mut commands: Commands,
+    mut layers: Query<
+        (Entity, &MapLayer, &mut Transform),
+        (With<LdtkMapLayer>, Without<InstanceOffsetApplied>),
+    >,
+    maps: Query<&GameMap>,
+) {
+    let map = if let Ok(map) = maps.single() {
+        map
+    } else {
+        return;
+    };
+
+    for (layer_ent, layer, mut transform) in layers.iter_mut() {
+        if let Some(level) = map.level(&layer.level_identifier) {
+            transform.translation += level.instance_offset.extend(0.);
+        }
+        commands.entity(layer_ent).insert(InstanceOffsetApplied);
+    }
+}
+
+/// Component used to mark the sprites for the rendered layers of Tiled maps
+pub struct TiledMapLayer;
+
+/// Add the [`GameMap`] to Tiled maps once they have loaded and spawn their layer sprites
+pub fn build_tiled_game_maps(
+    mut commands: Commands,
+    maps: Query<(Entity, &Handle<TiledMap>), Without<GameMap>>,
+    map_assets: Res<Assets<TiledMap>>,
+) {
+    for (map_ent, map_handle) in maps.iter() {
+        if let Some(map) = map_assets.get(map_handle) {
+            commands
+                .entity(map_ent)
+                .insert(map.map.clone())
+                .with_children(|map_children| {
+                    for layer in &map.layers {
+                        map_children
+                            .spawn_bundle(SpriteBundle {
+                                image: layer.image.clone(),
+                                transform: Transform::from_translation(layer.position),
+                                sprite: Sprite {
+                                    centered: false,
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            })
+                            .insert_bundle((
+                                TiledMapLayer,
+                                MapLayer {
+                                    level_identifier: layer.level.clone(),
+                                    identifier: layer.layer.clone(),
+                                    visible: true,
+                                },
+                            ));
+                    }
+                });
+        }
+    }
+}
+
+/// Resource for where the player and camera were when the map was hot reloaded, so that they can be
+/// put back once the map has been re-created
+#[derive(Default)]
+pub struct MapReloadFocus {
+    /// The positions of the player and the camera, if a reload is in progress
+    pending: Option<(Option<Vec2>, Option<Vec3>)>,
+}
+
+/// Remove the [`GameMap`] from maps that have been modified so that everything spawned from them
+/// is re-created
+pub fn hot_reload_game_maps(
+    mut commands: Commands,
+    ldtk_maps: Query<(Entity, &Handle<LdtkMap>)>,
+    tiled_maps: Query<(Entity, &Handle<TiledMap>)>,
+    mut ldtk_events: EventReader<AssetEvent<LdtkMap>>,
+    mut tiled_events: EventReader<AssetEvent<TiledMap>>,
+    mut focus: ResMut<MapReloadFocus>,
+    characters: Query<&Transform, With<Handle<Character>>>,
+    cameras: Query<&Transform, With<Camera>>,
+) {
+    let mut modified_maps = Vec::new();
+    for event in ldtk_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            modified_maps.extend(
+                ldtk_maps
+                    .iter()
+                    .filter(|(_, map)| *map == handle)
+                    .map(|(ent, _)| ent),
+            );
+        }
+    }
+    for event in tiled_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            modified_maps.extend(
+                tiled_maps
+                    .iter()
+                    .filter(|(_, map)| *map == handle)
+                    .map(|(ent, _)| ent),
+            );
+        }
+    }
+
+    // Remember where the player and camera are, unless we are already waiting to put them back
+    if !modified_maps.is_empty() && focus.pending.is_none() {
+        focus.pending = Some((
+            characters.iter().next().map(|x| x.translation.truncate()),
+            cameras.single().ok().map(|x| x.translation),
+        ));
+    }
+
+    for map_ent in modified_maps {
+        commands
+            .entity(map_ent)
+            .remove::<GameMap>()
+            .remove::<MapTileCollisionsLoaded>()
+            .remove::<MapTileCollisionCache>()
+            .remove::<MapTileCollisionProgress>()
+            .remove::<MapEntrancesLoaded>()
+            .remove::<MapEnemiesLoaded>()
+            .remove::<MapPropsLoaded>()
+            .remove::<MapPlotsLoaded>()
+            .remove::<MapShaderRegionsLoaded>()
+            .remove::<MapLevelNavigationMeshes>()
+            .remove::<MapTerrainSpeeds>()
+            .remove::<MapReflectiveTiles>()
+            .remove::<MapFootprintTiles>()
+            .remove::<MergedLayerLevels>()
+            .remove::<DungeonLayout>();
+    }
+}
+
+/// Put the player and camera back where they were before the map was hot reloaded once its
+/// collisions have been spawned again, keeping the layers of other levels hidden until then
+#[allow(clippy::type_complexity)]
+pub fn restore_map_reload_focus(
+    mut focus: ResMut<MapReloadFocus>,
+    maps: Query<(), (With<GameMap>, With<MapTileCollisionsLoaded>)>,
+    mut layers: Query<(&MapLayer, &mut Visible)>,
+    mut characters: Query<(&mut Transform, Option<&mut Velocity>), With<Handle<Character>>>,
+    mut cameras: Query<&mut Transform, (With<Camera>, Without<Handle<Character>>)>,
+    current_level: Option<Res<CurrentLevel>>,
+) {
+    let (player_position, camera_position) = if let Some(pending) = focus.pending {
+        pending
+    } else {
+        return;
+    };
+
+    // Re-spawned layers start out visible, which would show every level on top of each other
+    if let Some(current_level) = &current_level {
+        for (layer, mut visible) in layers.iter_mut() {
+            let layer_visible = layer.level_identifier == ***current_level && layer.visible;
+            if **visible != layer_visible {
+                **visible = layer_visible;
+            }
+        }
+    }
+
+    if maps.single().is_err() {
+        return;
+    }
+    focus.pending = None;
+
+    if let Some(position) = player_position {
+        for (mut transform, velocity) in characters.iter_mut() {
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
+            if let Some(mut velocity) = velocity {
+                *velocity = Velocity::default();
+            }
+        }
+    }
+    if let (Some(position), Ok(mut transform)) = (camera_position, cameras.single_mut()) {
+        transform.translation = position;
+    }
+}
+
+/// Despawn the collisions, entrances, enemies, etc. of maps that no longer have a [`GameMap`],
+/// because they have been modified
+#[allow(clippy::type_complexity)]
+pub fn despawn_stale_map_entities(
+    mut commands: Commands,
+    maps: Query<(), With<GameMap>>,
+    map_children: Query<
+        (Entity, &Parent),
+        Or<(
+            With<MapTileCollisionShape>,
+            With<TiledMapLayer>,
+            With<Entrance>,
+            With<ShaderRegion>,
+        )>,
+    >,
+    enemies: Query<(Entity, &Enemy)>,
+    props: Query<(Entity, &Prop)>,
+) {
+    for (ent, parent) in map_children.iter() {
+        if maps.get(parent.0).is_err() {
+            commands.entity(ent).despawn();
+        }
+    }
+    for (ent, enemy) in enemies.iter() {
+        if maps.get(enemy.map).is_err() {
+            commands.entity(ent).despawn();
+        }
+    }
+    for (ent, prop) in props.iter() {
+        if maps.get(prop.map).is_err() {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
+
 /// Component that caches map tileset collision info
 ///
-/// Keyed by (tileset_uid, tile_id)
-pub struct LdtkMapTilesetTileCache(pub HashMap<(i32, i32), LdtkMapTilesetTileCacheItem>);
-/// An item in the [`LdtkMapTilesetTileCache`]
+/// Keyed by (tileset_id, tile_id)
+pub struct MapTileCollisionCache(pub HashMap<(i32, i32), MapTileCollisionCacheItem>);
+/// An item in the [`MapTileCollisionCache`]
 #[derive(Clone)]
-pub struct LdtkMapTilesetTileCacheItem {
+pub struct MapTileCollisionCacheItem {
     pub collision_shape: CollisionShape,
     pub damage_region: Option<DamageRegion>,
+    /// Whether the tile is a secret wall
+    pub secret: bool,
 }
 /// Component used to mark map collision shapes
-pub struct LdtkMapTileCollisionShape;
+pub struct MapTileCollisionShape;
 /// Component used to mark the map as having had its collisions loaded
-pub struct LdtkMapTileCollisionsLoaded;
+pub struct MapTileCollisionsLoaded;
+/// Component that tracks how far through the map we are with spawning tile collisions
+///
+/// Holds the index of the next level, layer, and tile to spawn a collision for.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct MapTileCollisionProgress {
+    pub level: usize,
+    pub layer: usize,
+    pub tile: usize,
+}
+
+/// The most tile collisions to spawn in one frame, so that large maps don't freeze the game while
+/// loading
+const MAX_TILE_COLLISIONS_PER_FRAME: usize = 512;
 
 /// Get any maps that have not had their tile collisions spawned yet and spawn them
 pub fn spawn_map_collisions(
     mut commands: Commands,
     maps: Query<
-        (Entity, &Handle<LdtkMap>, Option<&LdtkMapTilesetTileCache>),
-        Without<LdtkMapTileCollisionsLoaded>,
+        (
+            Entity,
+            &GameMap,
+            Option<&MapTileCollisionCache>,
+            Option<&MapTileCollisionProgress>,
+        ),
+        Without<MapTileCollisionsLoaded>,
     >,
-    map_assets: Res<Assets<LdtkMap>>,
     image_assets: Res<Assets<Image>>,
     asset_server: Res<AssetServer>,
     game_info: Option<Res<GameInfo>>,
+    timings: Res<SystemTimings>,
 ) {
     // Load game info or wait until it is loaded
     let game_info = if let Some(game_info) = game_info {
@@ -51,22 +503,17 @@ pub fn spawn_map_collisions(
         return;
     };
 
-    'map_load: for (map_ent, map_handle, tileset_tile_collisions_component) in maps.iter() {
+    let _timer = timings.start("spawn_map_collisions");
+
+    'map_load: for (map_ent, map, tileset_tile_collisions_component, progress) in maps.iter() {
         // Get map commands
         let mut map_commands = commands.entity(map_ent);
 
-        // Get the loaded map
-        let map = if let Some(map) = map_assets.get(map_handle) {
-            map
-        } else {
-            continue;
-        };
-
         // Load all tilesets and skip if any are missing
         let tileset_images = if let Some(tile_sets) = map
-            .tile_sets
+            .tilesets
             .iter()
-            .map(|(name, handle)| image_assets.get(handle).map(|image| (name, image)))
+            .map(|(&id, tileset)| image_assets.get(&tileset.image).map(|image| (id, image)))
             .collect::<Option<HashMap<_, _>>>()
         {
             tile_sets
@@ -75,49 +522,32 @@ pub fn spawn_map_collisions(
         };
 
         // Tilemap tile collisions indexed by (tileset_uid, tile_id)
-        let mut tileset_tile_cache: HashMap<(i32, i32), LdtkMapTilesetTileCacheItem> =
+        let mut tileset_tile_cache: HashMap<(i32, i32), MapTileCollisionCacheItem> =
             tileset_tile_collisions_component
                 .map(|x| x.0.clone())
                 .unwrap_or_default();
 
         // Generate collision shapes for all of the tiles in each tileset
-        for tileset_def in &map.project.defs.tilesets {
-            // For all tiles with custom data
-            for tile_data in &tileset_def.custom_data {
-                // Get tile ID and custom data
-                let tile_id = tile_data
-                    .get("tileId")
-                    .expect("Tile data missing `tileId` field")
-                    .as_i64()
-                    .expect("Tile `tileId` field not an int") as i32;
-                let data = tile_data
-                    .get("data")
-                    .expect("Tile data missing `data` field")
-                    .as_str()
-                    .expect("Tile `data` field not a string");
-
+        let shapes_span = info_span!("generate_tile_collision_shapes").entered();
+        for (&tileset_id, tileset) in &map.tilesets {
+            // For all tiles with metadata or tags
+            for &tile_id in tileset.tiles.keys().chain(tileset.tags.keys()).unique() {
                 // If we already have the collision calculated for this tile, skip it
-                if tileset_tile_cache.contains_key(&(tileset_def.uid, tile_id)) {
+                if tileset_tile_cache.contains_key(&(tileset_id, tile_id)) {
                     continue;
                 }
 
-                // Parse tile metadata as YAML
-                let tileset_tile_metadata: TilesetTileMetadata = match serde_yaml::from_str(data) {
-                    Ok(metadata) => metadata,
-                    Err(error) => {
-                        warn!(
-                            %error,
-                            %tile_id,
-                            tileset_id=%tileset_def.identifier,
-                            "Could not parse tileset tile metadata, ignoring"
-                        );
+                // Get the tile metadata, including the metadata for its tags
+                let tileset_tile_metadata =
+                    if let Some(metadata) = tileset.tile_metadata(tile_id, &game_info.tile_tags) {
+                        metadata
+                    } else {
                         continue;
-                    }
-                };
+                    };
 
                 // Get the image for this tileset
                 let tileset_image = *tileset_images
-                    .get(&tileset_def.identifier)
+                    .get(&tileset_id)
                     .expect("Tileset image not loaded");
 
                 // Helper for generating alpha-based collision shapes
@@ -125,17 +555,14 @@ pub fn spawn_map_collisions(
                     ($image:ident) => {
                         {
                             // Get the tile pixel x and y positions from the tile ID
-                            let tile_grid_y = tile_id / tileset_def.__c_wid;
-                            let tile_grid_x = tile_id - (tile_grid_y * tileset_def.__c_wid);
-                            let tile_x = tile_grid_x * tileset_def.tile_grid_size;
-                            let tile_y = tile_grid_y * tileset_def.tile_grid_size;
+                            let tile_position = tileset.tile_position(tile_id);
 
                             // Get the portion of the tilemap image for this tile
                             let tile_image = $image.view(
-                                tile_x as u32,
-                                tile_y as u32,
-                                tileset_def.tile_grid_size as u32,
-                                tileset_def.tile_grid_size as u32,
+                                tile_position.x,
+                                tile_position.y,
+                                tileset.tile_size,
+                                tileset.tile_size,
                             );
 
                             // Generate a collision shape from the tile image
@@ -151,7 +578,7 @@ pub fn spawn_map_collisions(
                             } else {
                                 warn!(
                                     %tile_id,
-                                    tileset_id=%tileset_def.identifier,
+                                    tileset_id=%tileset.identifier,
                                     "Could not create collision shape for tile"
                                 );
                                 continue;
@@ -163,12 +590,12 @@ pub fn spawn_map_collisions(
                 }
 
                 // Get the tile collision shape
-                let collision_shape = match tileset_tile_metadata.collision {
+                let collision_shape = match &tileset_tile_metadata.collision {
                     // Create a cuboid collision for this block
                     TilesetTileCollisionMode::Full => Some(CollisionShape::Cuboid {
                         half_extends: Vec3::new(
-                            tileset_def.tile_grid_size as f32 / 2.0,
-                            tileset_def.tile_grid_size as f32 / 2.0,
+                            tileset.tile_size as f32 / 2.0,
+                            tileset.tile_size as f32 / 2.0,
                             0.,
                         ),
                         border_radius: None,
@@ -202,7 +629,7 @@ pub fn spawn_map_collisions(
                         } else {
                             // Store the collisions we have currently and wait to try again next
                             // frame
-                            map_commands.insert(LdtkMapTilesetTileCache(tileset_tile_cache));
+                            map_commands.insert(MapTileCollisionCache(tileset_tile_cache));
                             continue 'map_load;
                         };
 
@@ -219,89 +646,100 @@ pub fn spawn_map_collisions(
                 // If the tile has a collision shape, add it to the cache
                 if let Some(collision_shape) = collision_shape {
                     tileset_tile_cache.insert(
-                        (tileset_def.uid, tile_id),
-                        LdtkMapTilesetTileCacheItem {
+                        (tileset_id, tile_id),
+                        MapTileCollisionCacheItem {
                             collision_shape,
                             damage_region: tileset_tile_metadata.damage_region.clone(),
+                            secret: tileset_tile_metadata.secret,
                         },
                     );
                 }
             }
         }
 
+        shapes_span.exit();
+
+        // Pick up where we left off last frame
+        let _span = info_span!("spawn_tile_collisions").entered();
+        let progress = progress.copied().unwrap_or_default();
+        let mut spawned_count = 0;
+
         // For every level in the map
-        for level in &map.project.levels {
+        for (level_index, level) in map.levels.iter().enumerate().skip(progress.level) {
             // Get the level offset
-            let level_offset = Vec3::new(level.world_x as f32, level.world_y as f32, 0.);
+            let level_offset = level.position.extend(0.);
 
             // For every layer in the level
-            for layer in level
-                .layer_instances
-                .as_ref()
-                .expect("Map level has no layers")
-                .iter()
-            {
-                // Get the layer offset
-                let layer_offset = level_offset
-                    + Vec3::new(
-                        layer.__px_total_offset_x as f32,
-                        layer.__px_total_offset_y as f32,
-                        0.,
-                    );
+            for (layer_index, layer) in level.layers.iter().enumerate() {
+                // Skip layers we've already finished
+                if (level_index, layer_index) < (progress.level, progress.layer) {
+                    continue;
+                }
 
-                // Get layer tile size
-                let tile_size = layer.__grid_size as f32;
+                // Get the layer offset
+                let layer_offset = level_offset + layer.offset.extend(0.);
 
                 // Get the NoCollision hlper layer for this layer if it exists
                 let no_collision_layer = level
-                    .layer_instances
-                    .as_ref()
-                    .expect("Level has no layers")
+                    .layers
                     .iter()
-                    .find(|x| x.__identifier == format!("{}NoCollision", layer.__identifier));
-
-                // Get the layer tileset uid, or skip the layer if it doesn't have a tileset
-                let tileset_uid = if let Some(uid) = layer.__tileset_def_uid {
-                    uid
-                } else {
-                    continue;
-                };
+                    .find(|x| x.identifier == format!("{}NoCollision", layer.identifier));
 
                 // For every tile in the layer
-                for tile in layer.grid_tiles.iter().chain(layer.auto_layer_tiles.iter()) {
+                for (tile_index, tile) in layer.tiles.iter().enumerate() {
+                    // Skip tiles we've already finished
+                    if (level_index, layer_index, tile_index)
+                        < (progress.level, progress.layer, progress.tile)
+                    {
+                        continue;
+                    }
+
+                    // Stop for this frame if we've spawned enough collisions already
+                    if spawned_count >= MAX_TILE_COLLISIONS_PER_FRAME {
+                        map_commands
+                            .insert(MapTileCollisionProgress {
+                                level: level_index,
+                                layer: layer_index,
+                                tile: tile_index,
+                            })
+                            .insert(MapTileCollisionCache(tileset_tile_cache));
+                        continue 'map_load;
+                    }
+
                     // Skip this tile if it has a representative in the NoCollision layer
                     if let Some(no_collision_layer) = no_collision_layer {
-                        let tile_index = (tile.px[0] / layer.__grid_size)
-                            + (tile.px[1] / layer.__grid_size * layer.__c_wid);
-
                         // If the NoCollision layer has a tile in a position corresponding to this
                         // tile
-                        if no_collision_layer.int_grid_csv[tile_index as usize] != 0 {
+                        if no_collision_layer.cell_at(tile.position) != 0 {
                             // Skip the tile
                             continue;
                         }
                     }
 
+                    // Get tile size
+                    let tile_size = map
+                        .tilesets
+                        .get(&tile.tileset)
+                        .map(|x| x.tile_size)
+                        .unwrap_or(layer.grid_size) as f32;
+
                     // Get the tile position
-                    let tile_pos =
-                        layer_offset + Vec3::new(tile.px[0] as f32, tile.px[1] as f32, 0.);
+                    let tile_pos = layer_offset + tile.position.as_f32().extend(0.);
 
                     // Offset the tile position to get the center of the tile
                     let half_tile_size = Vec3::new(tile_size / 2.0, tile_size / 2.0, 0.);
 
                     // Spawn a collision shape for this tile if one exists
-                    if let Some(tile_cache_item) = tileset_tile_cache.get(&(tileset_uid, tile.t)) {
+                    if let Some(tile_cache_item) = tileset_tile_cache.get(&(tile.tileset, tile.id))
+                    {
+                        spawned_count += 1;
                         map_commands.with_children(|map| {
                             // Spawn the entity with the collision shape
                             let mut entity_commands = map.spawn_bundle((
-                                LdtkMapTileCollisionShape,
+                                MapTileCollisionShape,
                                 tile_cache_item.collision_shape.clone(),
-                                CollisionLayers::from_bits(
-                                    // Put it in the landscape group
-                                    PhysicsGroup::Terrain.to_bits(),
-                                    // And allow it to collide with all other layers
-                                    PhysicsGroup::all_bits(),
-                                ),
+                                // Put it in the landscape group
+                                game_info.collision_layers.collision_layers(&["terrain"]),
                                 Transform::from_translation(tile_pos + half_tile_size),
                                 GlobalTransform::default(),
                             ));
@@ -311,6 +749,17 @@ pub fn spawn_map_collisions(
                                 // Add the damage region component as well
                                 entity_commands.insert(damage_region.clone());
                             }
+
+                            // If the tile is a secret wall, remember where to erase it from when
+                            // it breaks
+                            if tile_cache_item.secret {
+                                entity_commands.insert(SecretWall {
+                                    level: level.identifier.clone(),
+                                    layer: layer.identifier.clone(),
+                                    tile: tile.position,
+                                    size: tile_size,
+                                });
+                            }
                         });
                     }
                 }
@@ -318,51 +767,20 @@ pub fn spawn_map_collisions(
         }
 
         map_commands
+            .remove::<MapTileCollisionProgress>()
             // Mark map collsions as loaded
-            .insert(LdtkMapTileCollisionsLoaded)
+            .insert(MapTileCollisionsLoaded)
             // Make the map a static body
             .insert(RigidBody::Static);
     }
 }
 
-pub fn hot_reload_map_collisions(
-    mut commands: Commands,
-    maps: Query<(Entity, &Handle<LdtkMap>)>,
-    tile_collisions: Query<(Entity, &Parent), With<LdtkMapTileCollisionShape>>,
-    mut events: EventReader<AssetEvent<LdtkMap>>,
-) {
-    for event in events.iter() {
-        if let AssetEvent::Modified { handle } = event {
-            // For every map
-            for (map_ent, map) in maps.iter() {
-                // If this map's handle has been updated
-                if map == handle {
-                    // Unmark the map collisions as loaded and emove cached tileset collisions
-                    commands
-                        .entity(map_ent)
-                        .remove::<LdtkMapTileCollisionsLoaded>()
-                        .remove::<LdtkMapTilesetTileCache>();
-
-                    // For every tile collision
-                    for (tile_ent, parent) in tile_collisions.iter() {
-                        // If this tile is a child of the map that changed
-                        if parent.0 == map_ent {
-                            // Despawn it
-                            commands.entity(tile_ent).despawn();
-                        }
-                    }
-                }
-            }
-        }
-    }
-}
-
-/// A component containing the navigation meshes for all the levels in an LDtk map
-pub struct LdtkMapLevelNavigationMeshes(pub HashMap<String, NavMesh>);
-impl_deref!(LdtkMapLevelNavigationMeshes, HashMap<String, NavMesh>);
+/// A component containing the navigation meshes for all the levels in a map
+pub struct MapLevelNavigationMeshes(pub HashMap<String, NavMesh>);
+impl_deref!(MapLevelNavigationMeshes, HashMap<String, NavMesh>);
 
 // Component for map navmesh debug visualization
-pub struct LdtkMapLevelNavigationMeshDebugViz {
+pub struct MapLevelNavigationMeshDebugViz {
     pub level_id: String,
 }
 
@@ -370,47 +788,38 @@ pub fn generate_map_navigation_mesh(
     mut commands: Commands,
     // All of the maps that have their tile collisions loaded, but do not have nav meshes
     maps: Query<
-        (Entity, &Handle<LdtkMap>),
+        (Entity, &GameMap),
         (
-            With<LdtkMapTileCollisionsLoaded>,
-            With<LdtkMapEnemiesLoaded>,
-            With<LdtkMapEntrancesLoaded>,
-            Without<LdtkMapLevelNavigationMeshes>,
+            With<MapTileCollisionsLoaded>,
+            With<MapEnemiesLoaded>,
+            With<MapPropsLoaded>,
+            With<MapEntrancesLoaded>,
+            Without<MapLevelNavigationMeshes>,
         ),
     >,
-    map_assets: Res<Assets<LdtkMap>>,
     physics_world: bevy_retrograde::physics::heron::rapier_plugin::PhysicsWorld,
     game_info: Option<Res<GameInfo>>,
+    timings: Res<SystemTimings>,
 ) {
-    // For every map
-    for (map_ent, map_handle) in maps.iter() {
-        let map = if let Some(map) = map_assets.get(map_handle) {
-            map
-        } else {
-            continue;
-        };
+    let _timer = timings.start("generate_map_navigation_mesh");
 
+    // For every map
+    for (map_ent, map) in maps.iter() {
         let mut meshes = HashMap::<String, NavMesh>::default();
 
         // For every level in the map
-        for level in &map.project.levels {
-            // Get the grid size from the first layer of this level
-            let tile_size = level
-                .layer_instances
-                .as_ref()
-                .expect("Level has no layers")
-                .get(0)
-                .expect("Level has no layers")
-                .__grid_size as u32;
+        for level in &map.levels {
+            let _span =
+                info_span!("generate_level_navigation_mesh", level=%level.identifier).entered();
+
+            // Get the grid size of this level
+            let tile_size = level.grid_size;
 
             // The size of the level in tiles
-            let grid_size = UVec2::new(
-                level.px_wid as u32 / tile_size,
-                level.px_hei as u32 / tile_size,
-            );
+            let grid_size = level.size / tile_size;
 
             // Get the level world offset
-            let level_offset = Vec3::new(level.world_x as f32, level.world_y as f32, 0.);
+            let level_offset = level.position.extend(0.);
 
             // Create a navigation mesh, using ray-casting to do edge testing
             let starting_point = level_offset.truncate() + Vec2::splat(tile_size as f32) / 2.;
@@ -432,6 +841,16 @@ pub fn generate_map_navigation_mesh(
                     .is_none()
             };
 
+            // Find the tiles that enemies should avoid, by the grid cell of their center
+            let nav_costs = level_nav_costs(map, level, game_info.as_deref());
+            let cell_cost = |point: &delaunator::Point| {
+                let cell = ((Vec2::new(point.x as f32, point.y as f32) - level.position)
+                    / tile_size as f32)
+                    .floor()
+                    .as_i32();
+                nav_costs.get(&cell).copied()
+            };
+
             // Create a triangulation point list
             let mut points =
                 Vec::<delaunator::Point>::with_capacity((grid_size.x * grid_size.y) as usize);
@@ -473,6 +892,8 @@ pub fn generate_map_navigation_mesh(
                         *chunk.next().unwrap(),
                     ]
                 })
+                // Discard any triangles touching a tile that enemies can't path across
+                .filter(|tri| tri.iter().all(|&x| cell_cost(&points[x]) != Some(None)))
                 // Discard any triangles where one of the edges doesn't pass the edge test
                 .filter(|tri| {
                     let v1 = &points[tri[0]];
@@ -500,6 +921,16 @@ pub fn generate_map_navigation_mesh(
                 })
                 .collect::<Vec<_>>();
 
+            // Triangles cost as much as the most expensive tile they touch
+            let triangle_costs = triangles
+                .iter()
+                .map(|tri| {
+                    tri.iter()
+                        .filter_map(|&x| cell_cost(&points[x]).flatten())
+                        .fold(1., f32::max)
+                })
+                .collect::<Vec<_>>();
+
             // Convert our points to nav mesh vertices
             let vertices = points
                 .into_iter()
@@ -527,7 +958,7 @@ pub fn generate_map_navigation_mesh(
             {
                 commands
                     .spawn_bundle((
-                        LdtkMapLevelNavigationMeshDebugViz {
+                        MapLevelNavigationMeshDebugViz {
                             level_id: level.identifier.clone(),
                         },
                         Transform::default(),
@@ -546,11 +977,18 @@ pub fn generate_map_navigation_mesh(
                             });
                         }
 
-                        for triangle in &triangles {
+                        for (triangle, &cost) in triangles.iter().zip(&triangle_costs) {
                             let v1 = &vertices[triangle.first as usize];
                             let v2 = &vertices[triangle.second as usize];
                             let v3 = &vertices[triangle.third as usize];
 
+                            // Shade the triangles that enemies avoid
+                            let fill = if cost > 1. {
+                                epaint::Color32::from_rgba_unmultiplied(255, 0, 0, 48)
+                            } else {
+                                epaint::Color32::TRANSPARENT
+                            };
+
                             viz.spawn_bundle(ShapeBundle {
                                 shape: Shape::convex_polygon(
                                     vec![
@@ -558,7 +996,7 @@ pub fn generate_map_navigation_mesh(
                                         epaint::pos2(v2.x as f32, v2.y as f32),
                                         epaint::pos2(v3.x as f32, v3.y as f32),
                                     ],
-                                    epaint::Color32::TRANSPARENT,
+                                    fill,
                                     (0.5, epaint::Color32::from_rgb(35, 18, 52)),
                                 ),
                                 transform: Transform::from_xyz(0., 0., 200.),
@@ -569,7 +1007,12 @@ pub fn generate_map_navigation_mesh(
             }
 
             // Return the final navmesh
-            let nav_mesh = NavMesh::new(vertices, triangles).expect("Could not create navmesh");
+            let mut nav_mesh = NavMesh::new(vertices, triangles).expect("Could not create navmesh");
+            for (index, cost) in triangle_costs.into_iter().enumerate() {
+                if cost != 1. {
+                    nav_mesh.set_area_cost(index, cost);
+                }
+            }
 
             meshes.insert(level.identifier.clone(), nav_mesh);
         }
@@ -577,252 +1020,569 @@ pub fn generate_map_navigation_mesh(
         // Add the navigation meshes component to the map
         commands
             .entity(map_ent)
-            .insert(LdtkMapLevelNavigationMeshes(meshes));
+            .insert(MapLevelNavigationMeshes(meshes));
     }
 }
 
-pub struct LdtkMapEntrancesLoaded;
+/// Get the navigation costs of the tiles in a level that have one, by grid cell, with `None` for
+/// the tiles that enemies can't path across at all
+///
+/// When tiles in more than one layer have a cost, the highest cost wins.
+fn level_nav_costs(
+    map: &GameMap,
+    level: &GameLevel,
+    game_info: Option<&GameInfo>,
+) -> HashMap<IVec2, Option<f32>> {
+    let mut costs = HashMap::default();
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return costs;
+    };
+
+    let grid_size = level.grid_size.max(1) as f32;
+    for layer in &level.layers {
+        for tile in &layer.tiles {
+            let metadata = if let Some(metadata) = map
+                .tilesets
+                .get(&tile.tileset)
+                .and_then(|x| x.tile_metadata(tile.id, &game_info.tile_tags))
+            {
+                metadata
+            } else {
+                continue;
+            };
+            let cost = match (metadata.nav_forbidden, metadata.nav_cost) {
+                (true, _) => None,
+                (false, Some(cost)) => Some(cost),
+                (false, None) => continue,
+            };
+
+            let cell = ((layer.offset + tile.position.as_f32()) / grid_size)
+                .floor()
+                .as_i32();
+            let entry = costs.entry(cell).or_insert(cost);
+            *entry = match (*entry, cost) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                _ => None,
+            };
+        }
+    }
+
+    costs
+}
+
+pub struct MapEntrancesLoaded;
 
 /// Spawn the entrance entities from the map
 pub fn spawn_map_entrances(
     mut commands: Commands,
-    maps: Query<(Entity, &Handle<LdtkMap>), Without<LdtkMapEntrancesLoaded>>,
-    map_assets: Res<Assets<LdtkMap>>,
+    maps: Query<(Entity, &GameMap), Without<MapEntrancesLoaded>>,
+    game_info: Option<Res<GameInfo>>,
 ) {
-    // For every map
-    for (ent, map_handle) in maps.iter() {
-        // Get the map
-        let map = if let Some(map) = map_assets.get(map_handle) {
-            map
-        } else {
-            continue;
-        };
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
 
+    // For every map
+    for (ent, map) in maps.iter() {
         let mut map_commands = commands.entity(ent);
 
         // For every level in the map
-        for level in &map.project.levels {
-            // Get the level's position offest
-            let level_offset = Vec3::new(level.world_x as f32, level.world_y as f32, 0.);
+        for level in &map.levels {
+            // Spawn collision sensors for the entrances
+            for entrance in level.entities.iter().filter(|x| x.identifier == "Entrance") {
+                // Dungeon entrances that don't lead to a room in this run aren't spawned
+                if entrance.field_str("to").map_or(false, is_dungeon_link) {
+                    continue;
+                }
 
-            // For every layer in the level
-            for layer in level
-                .layer_instances
-                .as_ref()
-                .expect("Map has no layers")
+                let (id, to_level, spawn_at) = if let (Some(id), Some(to_level), Some(spawn_at)) = (
+                    entrance.field_str("id"),
+                    entrance.field_str("to"),
+                    entrance.field_str("spawn_at"),
+                ) {
+                    (id, to_level, spawn_at)
+                } else {
+                    warn!(
+                        level=%level.identifier,
+                        "Entrance missing `id`, `to` or `spawn_at` field, ignoring"
+                    );
+                    continue;
+                };
+
+                // Entrances are positioned by their top-left corner
+                let entrance_position = level.place(entrance, Placement::Center).truncate();
+
+                map_commands.with_children(|map| {
+                    map.spawn_bundle((
+                        Entrance {
+                            map: ent,
+                            level: level.identifier.clone(),
+                            id: id.into(),
+                            to_level: to_level.into(),
+                            spawn_at: spawn_at.into(),
+                            goal: entrance.field::<bool>("goal").unwrap_or(false),
+                        },
+                        CollisionShape::Cuboid {
+                            half_extends: Vec3::new(
+                                // Shrink the entrance slightly by dividing by 2.2 to prevent
+                                // the collision from being hit past walls.
+                                entrance.size.x / 2.2,
+                                entrance.size.y / 2.2,
+                                0.,
+                            ),
+                            border_radius: None,
+                        },
+                        RigidBody::Sensor,
+                        game_info.collision_layers.collision_layers(&["entrance"]),
+                        Transform::from_translation(entrance_position.extend(0.)),
+                        GlobalTransform::default(),
+                    ));
+                });
+            }
+        }
+
+        map_commands.insert(MapEntrancesLoaded);
+    }
+}
+
+pub struct MapEnemiesLoaded;
+pub fn spawn_map_enemies(
+    mut commands: Commands,
+    maps: Query<(Entity, &GameMap), Without<MapEnemiesLoaded>>,
+    asset_server: Res<AssetServer>,
+    game_info: Option<Res<GameInfo>>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+
+    // For every map
+    for (map_ent, map) in maps.iter() {
+        // For every level in the map
+        for level in &map.levels {
+            // For every enemy entity in the level
+            for (index, entity) in level
+                .entities
                 .iter()
-                .filter(|x| x.__type == "Entities")
+                .filter(|x| x.identifier == "Enemy")
+                .enumerate()
             {
-                // Get the layer offset
-                let layer_offset = Vec3::new(
-                    layer.__px_total_offset_x as f32,
-                    layer.__px_total_offset_y as f32,
-                    0.,
+                let pos = level.place(entity, Placement::Position);
+
+                // Get the enemy's optional `.animations.yml` file and the clip to play
+                let animations = entity.field_str("animations");
+                let clip = entity.field_str("animation").unwrap_or("idle");
+
+                // Get the enemy's health and the paths to its `.ability.yml` files
+                let health = entity
+                    .field::<u32>("health")
+                    .unwrap_or(DEFAULT_ENEMY_HEALTH);
+                let abilities = entity.field::<Vec<String>>("abilities").unwrap_or_default();
+
+                // Spawn an enemy
+                let mut enemy_commands = spawn_enemy(
+                    &mut commands,
+                    &asset_server,
+                    Enemy {
+                        level: level.identifier.clone(),
+                        map: map_ent,
+                    },
+                    pos,
+                    entity_collision_layers(entity, "enemy", &game_info.collision_layers),
+                    game_info.enemies.knock_back.clone(),
+                    animations.map(|x| (x, clip)),
                 );
+                enemy_commands
+                    .insert(PersistentId {
+                        level: level.identifier.clone(),
+                        id: entity.persistent_id(),
+                    })
+                    .insert(Health {
+                        current: health,
+                        max: health,
+                    });
 
-                // Spawn collision sensors for the entrances
-                for entrance in layer
-                    .entity_instances
-                    .iter()
-                    .filter(|x| x.__identifier == "Entrance")
-                {
-                    let entrance_position = Vec3::new(
-                        entrance.px[0] as f32 + layer.__grid_size as f32 / 2.,
-                        entrance.px[1] as f32 + layer.__grid_size as f32 / 2.,
-                        0.,
-                    );
+                // Enemies come back the next day unless they turn it off, i.e. for bosses
+                if entity.field::<bool>("respawns").unwrap_or(true) {
+                    enemy_commands.insert(Respawns);
+                }
 
-                    map_commands.with_children(|map| {
-                        map.spawn_bundle((
-                            Entrance {
-                                map_handle: map_handle.clone(),
-                                level: level.identifier.clone(),
-                                id: entrance
-                                    .field_instances
-                                    .iter()
-                                    .find(|x| x.__identifier == "id")
-                                    .expect("Could not find entrance `id` field")
-                                    .__value
-                                    .as_str()
-                                    .expect("Entrance `id` field is not a string")
-                                    .into(),
-                                to_level: entrance
-                                    .field_instances
-                                    .iter()
-                                    .find(|x| x.__identifier == "to")
-                                    .expect("Could not find entrance `to` field")
-                                    .__value
-                                    .as_str()
-                                    .expect("Entrance `to` field is not a string")
-                                    .into(),
-                                spawn_at: entrance
-                                    .field_instances
-                                    .iter()
-                                    .find(|x| x.__identifier == "spawn_at")
-                                    .expect("Could not find entrance `spawn_at` field")
-                                    .__value
-                                    .as_str()
-                                    .expect("Entrance `spawn_at` field is not a string")
-                                    .into(),
-                            },
-                            CollisionShape::Cuboid {
-                                half_extends: Vec3::new(
-                                    // Shrink the entrance slightly by dividing by 2.2 to prevent
-                                    // the collision from being hit past walls.
-                                    entrance.width as f32 / 2.2,
-                                    entrance.height as f32 / 2.2,
-                                    0.,
-                                ),
-                                border_radius: None,
-                            },
-                            RigidBody::Sensor,
-                            CollisionLayers::from_bits(
-                                // In the entrance group
-                                PhysicsGroup::Entrance.to_bits(),
-                                // Can interact with all other groups
-                                PhysicsGroup::all_bits(),
-                            ),
-                            Transform::from_translation(
-                                level_offset + layer_offset + entrance_position,
-                            ),
-                            GlobalTransform::default(),
-                        ));
+                // Enemies with the same `pack` move in formation, keeping their places relative
+                // to each other from the level
+                if let Some(pack) = entity.field_str("pack") {
+                    enemy_commands.insert(Pack {
+                        id: format!("{}/{}", level.identifier, pack),
+                        order: index as u32,
+                        offset: pos.truncate(),
                     });
                 }
+
+                if !abilities.is_empty() {
+                    enemy_commands.insert(Abilities::new(
+                        abilities.iter().map(|x| asset_server.load(x.as_str())),
+                    ));
+                }
+
+                // Enemies with a vision range have to see or hear the player before chasing them
+                if let Some(range) = entity.field::<f32>("vision_range") {
+                    let stealth = &game_info.stealth;
+                    enemy_commands
+                        .insert(Vision {
+                            range,
+                            angle: entity
+                                .field::<f32>("vision_angle")
+                                .unwrap_or(stealth.vision_angle),
+                            facing: Vec2::new(0., 1.),
+                        })
+                        .insert(Alertness {
+                            hearing: entity.field::<f32>("hearing").unwrap_or(1.),
+                            investigates: entity
+                                .field::<bool>("investigates_noise")
+                                .unwrap_or(stealth.investigate_noise),
+                            ..Alertness::new(
+                                entity
+                                    .field::<f32>("suspicion_time")
+                                    .unwrap_or(stealth.suspicion_time),
+                                entity
+                                    .field::<f32>("search_time")
+                                    .unwrap_or(stealth.search_time),
+                            )
+                        });
+                }
             }
         }
 
-        map_commands.insert(LdtkMapEntrancesLoaded);
+        // Mark map enemies as loaded
+        commands.entity(map_ent).insert(MapEnemiesLoaded);
+    }
+}
+
+/// The health of enemies that don't set their own `health` field
+pub const DEFAULT_ENEMY_HEALTH: u32 = 3;
+
+/// Spawn an enemy at the given position, optionally playing a clip from an `.animations.yml` file,
+/// and return its entity commands so that more components can be added
+pub fn spawn_enemy<'a, 'b>(
+    commands: &'b mut Commands<'a>,
+    asset_server: &AssetServer,
+    enemy: Enemy,
+    translation: Vec3,
+    collision_layers: CollisionLayers,
+    knock_back: DamageRegionKnockBack,
+    animations: Option<(&str, &str)>,
+) -> EntityCommands<'a, 'b> {
+    let mut enemy_commands = commands.spawn_bundle(SpriteBundle {
+        image: asset_server.load("sprites/blueRadish.png"),
+        transform: Transform::from_translation(translation),
+        sprite: Sprite {
+            pixel_perfect: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    enemy_commands
+        .insert(enemy)
+        .insert(YSort {
+            base_z: translation.z,
+        })
+        .insert(FixedStepInterpolation::new(translation))
+        .insert(PhysicMaterial {
+            density: 100000.,
+            ..Default::default()
+        })
+        .insert(DamageRegion {
+            damage: 1,
+            knock_back,
+        })
+        .insert(RigidBody::Dynamic)
+        .insert(RotationConstraints::lock())
+        .insert(CollisionShape::Sphere { radius: 4. })
+        .insert(collision_layers)
+        .insert(Velocity::default())
+        .insert(SpeedModifiers::default());
+
+    if let Some((animations, clip)) = animations {
+        enemy_commands
+            .insert(SpriteAnimation::new(asset_server.load(animations), clip))
+            .insert(Handle::<SpriteSheet>::default());
     }
+
+    enemy_commands
 }
 
-pub fn hot_reload_map_entrances(
+pub struct MapPlotsLoaded;
+
+/// Spawn the farming plots from the map
+///
+/// Plots are props with an optional `sprite` for the empty plot, which can be walked over.
+pub fn spawn_map_plots(
     mut commands: Commands,
-    maps: Query<(Entity, &Handle<LdtkMap>)>,
-    entrances: Query<(Entity, &Entrance)>,
-    mut events: EventReader<AssetEvent<LdtkMap>>,
+    maps: Query<(Entity, &GameMap), Without<MapPlotsLoaded>>,
+    asset_server: Res<AssetServer>,
 ) {
-    for event in events.iter() {
-        if let AssetEvent::Modified { handle } = event {
-            // Remove the `LdtkMapEntrancesLoaded` flag from the map
-            for (ent, map) in maps.iter() {
-                if map == handle {
-                    commands.entity(ent).remove::<LdtkMapEntrancesLoaded>();
-                }
-            }
-            // Despawn all entrances for the modified map
-            for (ent, entrance) in entrances.iter() {
-                if &entrance.map_handle == handle {
-                    commands.entity(ent).despawn();
-                }
+    for (map_ent, map) in maps.iter() {
+        for level in &map.levels {
+            for entity in level.entities.iter().filter(|x| x.identifier == "Plot") {
+                // Plots are positioned by their top-left corner, like props
+                let pos = level.place(entity, Placement::Center);
+
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        image: entity
+                            .field_str("sprite")
+                            .map(|x| asset_server.load(x))
+                            .unwrap_or_default(),
+                        transform: Transform::from_translation(pos),
+                        ..Default::default()
+                    })
+                    .insert(Prop {
+                        level: level.identifier.clone(),
+                        map: map_ent,
+                        interaction: Some(PLOT_INTERACTION.into()),
+                    })
+                    .insert(Plot)
+                    .insert(PersistentId {
+                        level: level.identifier.clone(),
+                        id: entity.persistent_id(),
+                    })
+                    .insert(YSort { base_z: entity.z });
             }
         }
+
+        commands.entity(map_ent).insert(MapPlotsLoaded);
     }
 }
 
-pub struct LdtkMapEnemiesLoaded;
-pub fn spawn_map_enemies(
+pub struct MapPropsLoaded;
+
+/// The speed that props with a `path` walk along it if they don't set their own `path_speed`, in
+/// pixels per second
+const DEFAULT_PATH_SPEED: f32 = 24.;
+
+/// A point in a map entity's `path` field, which is an LDtk `Point` array field
+#[derive(serde::Deserialize)]
+struct PathPoint {
+    /// The column of the grid cell the point is in
+    cx: u32,
+    /// The row of the grid cell the point is in
+    cy: u32,
+}
+
+/// Spawn the props from the map
+pub fn spawn_map_props(
     mut commands: Commands,
-    maps: Query<(Entity, &Handle<LdtkMap>), Without<LdtkMapEnemiesLoaded>>,
-    map_assets: Res<Assets<LdtkMap>>,
+    maps: Query<(Entity, &GameMap), Without<MapPropsLoaded>>,
     asset_server: Res<AssetServer>,
+    game_info: Option<Res<GameInfo>>,
 ) {
-    // For every map
-    for (map_ent, map_handle) in maps.iter() {
-        let map = if let Some(map) = map_assets.get(map_handle) {
-            map
-        } else {
-            continue;
-        };
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
 
+    // For every map
+    for (map_ent, map) in maps.iter() {
         // For every level in the map
-        for level in &map.project.levels {
-            let level_offset = Vec3::new(level.world_x as f32, level.world_y as f32, 0.);
-
-            // For every layer
-            for layer in level.layer_instances.as_ref().expect("Level has no layers") {
-                let layer_offset = level_offset
-                    + Vec3::new(
-                        layer.__px_total_offset_x as f32,
-                        layer.__px_total_offset_y as f32,
-                        0.,
-                    );
+        for level in &map.levels {
+            // For every prop entity in the level
+            for entity in level.entities.iter().filter(|x| x.identifier == "Prop") {
+                // Props are positioned by their top-left corner
+                let pos = level.place(entity, Placement::Center);
+
+                let sprite = if let Some(sprite) = entity.field_str("sprite") {
+                    sprite
+                } else {
+                    warn!(level=%level.identifier, "Prop missing `sprite` field, ignoring");
+                    continue;
+                };
+                let collision = entity
+                    .field::<PropCollisionMode>("collision")
+                    .unwrap_or_default();
+
+                // Spawn the prop
+                let animated =
+                    sprite.ends_with(".animations.yml") || sprite.ends_with(".animations.yaml");
+                let mut prop_commands = commands.spawn_bundle(SpriteBundle {
+                    image: if animated {
+                        Default::default()
+                    } else {
+                        asset_server.load(sprite)
+                    },
+                    transform: Transform::from_translation(pos),
+                    ..Default::default()
+                });
+                prop_commands
+                    .insert(Prop {
+                        level: level.identifier.clone(),
+                        map: map_ent,
+                        interaction: entity.field("interaction"),
+                    })
+                    .insert(PersistentId {
+                        level: level.identifier.clone(),
+                        id: entity.persistent_id(),
+                    });
 
-                // For every enemy entity in the layer
-                for entity in layer
-                    .entity_instances
-                    .iter()
-                    .filter(|x| x.__identifier == "Enemy")
-                {
-                    let pos =
-                        layer_offset + Vec3::new(entity.px[0] as f32, entity.px[1] as f32, 100.);
-
-                    // Spawn an enemy
-                    commands
-                        .spawn_bundle(SpriteBundle {
-                            image: asset_server.load("sprites/blueRadish.png"),
-                            transform: Transform::from_translation(pos),
-                            sprite: Sprite {
-                                pixel_perfect: false,
-                                ..Default::default()
-                            },
-                            ..Default::default()
-                        })
-                        .insert(Enemy {
-                            level: level.identifier.clone(),
-                            map_handle: map_handle.clone(),
-                        })
-                        .insert(PhysicMaterial {
-                            density: 100000.,
-                            ..Default::default()
-                        })
-                        .insert(DamageRegion {
-                            damage: 1,
-                            knock_back: DamageRegionKnockBack {
-                                speed: 800.,
-                                force_duration: 0.04,
-                                freeze_duration: 0.18,
-                            },
-                        })
-                        .insert(RigidBody::Dynamic)
-                        .insert(RotationConstraints::lock())
-                        .insert(CollisionShape::Sphere { radius: 4. })
-                        .insert(CollisionLayers::from_bits(
-                            // In the enemy group
-                            PhysicsGroup::Enemy.to_bits(),
-                            // Can interact with all other groups
-                            PhysicsGroup::all_bits(),
-                        ))
-                        .insert(Velocity::default());
+                // Enemies can hear props with a `noise` radius when the player interacts with them
+                if let Some(radius) = entity.field::<f32>("noise") {
+                    prop_commands.insert(PropNoise { radius });
+                }
+
+                // Props with the `lift` interaction can be picked up and thrown
+                if entity.field_str("interaction") == Some(LIFT_INTERACTION) {
+                    prop_commands.insert(Liftable {
+                        damage: entity
+                            .field("throw_damage")
+                            .unwrap_or(game_info.lifting.throw_damage),
+                    });
+                }
+
+                // Props can use an `.animations.yml` file as their sprite, playing its `idle` clip
+                if animated {
+                    prop_commands
+                        .insert(SpriteAnimation::new(asset_server.load(sprite), "idle"))
+                        .insert(Handle::<SpriteSheet>::default());
+                }
+
+                // Props are y-sorted unless they turn it off
+                if entity.field::<bool>("y_sort").unwrap_or(true) {
+                    prop_commands.insert(YSort { base_z: entity.z });
+                }
+
+                // Props with a `path` walk along it, and don't collide with anything since they
+                // move
+                if let Some(path) = entity.field::<Vec<PathPoint>>("path") {
+                    let grid_size = level.grid_size as f32;
+                    let points = std::iter::once(pos.truncate())
+                        .chain(path.iter().map(|point| {
+                            level.position
+                                + (Vec2::new(point.cx as f32, point.cy as f32) + Vec2::splat(0.5))
+                                    * grid_size
+                        }))
+                        .collect();
+                    prop_commands.insert(PathFollower {
+                        points,
+                        speed: entity.field("path_speed").unwrap_or(DEFAULT_PATH_SPEED),
+                        looping: entity.field("path_loop").unwrap_or(false),
+                        target: 1,
+                        reversed: false,
+                    });
+                    continue;
                 }
+
+                // Get the size and offset of the solid part of the prop
+                let collision_size = match collision {
+                    PropCollisionMode::None => continue,
+                    PropCollisionMode::Full => entity.size,
+                    PropCollisionMode::Base => Vec2::new(entity.size.x, entity.size.y / 3.),
+                };
+                let collision_offset = Vec2::new(0., (entity.size.y - collision_size.y) / 2.);
+
+                prop_commands
+                    .insert(RigidBody::Static)
+                    .with_children(|prop| {
+                        prop.spawn_bundle((
+                            CollisionShape::Cuboid {
+                                half_extends: (collision_size / 2.).extend(0.),
+                                border_radius: None,
+                            },
+                            // Props are solid like the terrain
+                            entity_collision_layers(entity, "terrain", &game_info.collision_layers),
+                            Transform::from_translation(collision_offset.extend(0.)),
+                            GlobalTransform::default(),
+                        ));
+                    });
             }
         }
 
-        // Mark map enemies as loaded
-        commands.entity(map_ent).insert(LdtkMapEnemiesLoaded);
+        // Mark map props as loaded
+        commands.entity(map_ent).insert(MapPropsLoaded);
     }
 }
 
-pub fn hot_reload_map_enemies(
+/// Get the collision layers for a map entity, which can set its own `collision_groups` and
+/// `collides_with` fields to override the default group and the collision matrix
+fn entity_collision_layers(
+    entity: &GameEntity,
+    default_group: &str,
+    config: &CollisionLayersConfig,
+) -> CollisionLayers {
+    let groups = entity
+        .field::<Vec<String>>("collision_groups")
+        .unwrap_or_else(|| vec![default_group.into()]);
+
+    if let Some(collides_with) = entity.field::<Vec<String>>("collides_with") {
+        config.collision_layers_with(&groups, &collides_with)
+    } else {
+        config.collision_layers(&groups)
+    }
+}
+
+pub struct MapShaderRegionsLoaded;
+
+/// Spawn the shader region sensors from the map
+pub fn spawn_map_shader_regions(
     mut commands: Commands,
-    maps: Query<(Entity, &Handle<LdtkMap>)>,
-    enemies: Query<(Entity, &Enemy)>,
-    mut events: EventReader<AssetEvent<LdtkMap>>,
+    maps: Query<(Entity, &GameMap), Without<MapShaderRegionsLoaded>>,
+    game_info: Option<Res<GameInfo>>,
 ) {
-    for event in events.iter() {
-        if let AssetEvent::Modified { handle } = event {
-            // Remove the `LdtkMapEnemiesLoaded` flag from the map
-            for (ent, map) in maps.iter() {
-                if map == handle {
-                    commands.entity(ent).remove::<LdtkMapEnemiesLoaded>();
-                }
-            }
-            // Despawn all enemies for the modified map
-            for (ent, enemy) in enemies.iter() {
-                if &enemy.map_handle == handle {
-                    commands.entity(ent).despawn();
-                }
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+
+    // For every map
+    for (ent, map) in maps.iter() {
+        let mut map_commands = commands.entity(ent);
+
+        // For every level in the map
+        for level in &map.levels {
+            // Spawn collision sensors for the shader regions
+            for region in level
+                .entities
+                .iter()
+                .filter(|x| x.identifier == "ShaderRegion")
+            {
+                let shader = if let Some(shader) = region.field_str("shader") {
+                    shader
+                } else {
+                    warn!(
+                        level=%level.identifier,
+                        "Shader region missing `shader` field, ignoring"
+                    );
+                    continue;
+                };
+
+                // Regions are positioned by their top-left corner
+                let region_position = level.place(region, Placement::Center).truncate();
+
+                map_commands.with_children(|map| {
+                    map.spawn_bundle((
+                        ShaderRegion {
+                            map: ent,
+                            level: level.identifier.clone(),
+                            shader: shader.into(),
+                        },
+                        CollisionShape::Cuboid {
+                            half_extends: (region.size / 2.).extend(0.),
+                            border_radius: None,
+                        },
+                        RigidBody::Sensor,
+                        game_info.collision_layers.collision_layers(&["region"]),
+                        Transform::from_translation(region_position.extend(0.)),
+                        GlobalTransform::default(),
+                    ));
+                });
             }
         }
+
+        map_commands.insert(MapShaderRegionsLoaded);
     }
 }
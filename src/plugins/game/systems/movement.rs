@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+
+use bevy::utils::HashMap;
+
+use super::*;
+
+/// Where a [`SpeedModifier`] comes from
+///
+/// Each source has at most one modifier at a time, which the systems for that source replace
+/// whenever it changes. New ways of changing speed, such as status effects or sprinting, should
+/// get their own source instead of changing velocities themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpeedModifierSource {
+    /// The tile the entity is walking on
+    Terrain,
+    /// The items the entity is wearing
+    Equipment,
+}
+
+/// A change to the speed of an entity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpeedModifier {
+    /// The amount added to the entity's base speed, in pixels per second
+    pub bonus: f32,
+    /// The amount the entity's speed is multiplied by, after the bonuses are added
+    pub factor: f32,
+}
+
+impl SpeedModifier {
+    /// A modifier that adds to the base speed
+    pub fn bonus(bonus: f32) -> Self {
+        Self { bonus, factor: 1. }
+    }
+
+    /// A modifier that multiplies the speed
+    pub fn factor(factor: f32) -> Self {
+        Self { bonus: 0., factor }
+    }
+}
+
+/// Component for the modifiers to an entity's movement speed, which every system that changes
+/// how fast something walks goes through, so that they combine the same way everywhere
+///
+/// The final speed is the base speed plus every modifier's bonus, times every modifier's factor.
+#[derive(Clone, Debug, Default)]
+pub struct SpeedModifiers(BTreeMap<SpeedModifierSource, SpeedModifier>);
+
+impl SpeedModifiers {
+    /// Get the modifier from a source, if it has one
+    pub fn get(&self, source: SpeedModifierSource) -> Option<SpeedModifier> {
+        self.0.get(&source).copied()
+    }
+
+    /// Replace the modifier from a source, or remove it if `None`
+    pub fn set(&mut self, source: SpeedModifierSource, modifier: Option<SpeedModifier>) {
+        match modifier {
+            Some(modifier) => {
+                self.0.insert(source, modifier);
+            }
+            None => {
+                self.0.remove(&source);
+            }
+        }
+    }
+
+    /// Get the speed of an entity with the given base speed
+    pub fn apply(&self, base: f32) -> f32 {
+        let bonus: f32 = self.0.values().map(|x| x.bonus).sum();
+        let factor: f32 = self.0.values().map(|x| x.factor).product();
+
+        (base + bonus).max(0.) * factor.max(0.)
+    }
+}
+
+/// Component for the speed of the tiles in each level of a map that change how fast things walk
+/// on them
+pub struct MapTerrainSpeeds(HashMap<String, Vec<TerrainSpeedLayer>>);
+
+/// The tiles in a layer that change how fast things walk on them
+struct TerrainSpeedLayer {
+    /// The position of the layer in the world
+    offset: Vec2,
+    grid_size: f32,
+    /// The speed factor of each tile, by its grid cell
+    tiles: HashMap<IVec2, f32>,
+}
+
+/// Find the tiles in the map that change walking speed
+pub fn load_terrain_speeds(
+    mut commands: Commands,
+    maps: Query<(Entity, &GameMap), Without<MapTerrainSpeeds>>,
+    game_info: Option<Res<GameInfo>>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+
+    for (map_ent, map) in maps.iter() {
+        let mut levels = HashMap::default();
+        for level in &map.levels {
+            let mut layers = Vec::new();
+            for layer in &level.layers {
+                let grid_size = layer.grid_size.max(1) as f32;
+                let tiles = layer
+                    .tiles
+                    .iter()
+                    .filter_map(|tile| {
+                        let speed = map
+                            .tilesets
+                            .get(&tile.tileset)?
+                            .tile_metadata(tile.id, &game_info.tile_tags)?
+                            .speed?;
+                        let cell = (tile.position.as_f32() / grid_size).floor().as_i32();
+                        Some((cell, speed))
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                if !tiles.is_empty() {
+                    layers.push(TerrainSpeedLayer {
+                        offset: level.position + layer.offset,
+                        grid_size,
+                        tiles,
+                    });
+                }
+            }
+            levels.insert(level.identifier.clone(), layers);
+        }
+
+        commands.entity(map_ent).insert(MapTerrainSpeeds(levels));
+    }
+}
+
+/// Slow down or speed up things walking on tiles with a speed in the current level
+pub fn update_terrain_speeds(
+    mut walkers: Query<(&Transform, &mut SpeedModifiers)>,
+    maps: Query<&MapTerrainSpeeds>,
+    current_level: Option<Res<CurrentLevel>>,
+) {
+    let layers = match (maps.single(), current_level) {
+        (Ok(speeds), Some(level)) => match speeds.0.get(&level.0) {
+            Some(layers) => layers,
+            None => return,
+        },
+        _ => return,
+    };
+
+    for (transform, mut modifiers) in walkers.iter_mut() {
+        let position = transform.translation.truncate();
+
+        // The top-most layer with a speed at the position wins
+        let speed = layers.iter().rev().find_map(|layer| {
+            let cell = ((position - layer.offset) / layer.grid_size)
+                .floor()
+                .as_i32();
+            layer.tiles.get(&cell).copied()
+        });
+
+        // Only update the modifiers when they change, to avoid triggering change detection
+        let modifier = speed.map(SpeedModifier::factor);
+        if modifiers.get(SpeedModifierSource::Terrain) != modifier {
+            modifiers.set(SpeedModifierSource::Terrain, modifier);
+        }
+    }
+}
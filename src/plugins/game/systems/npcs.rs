@@ -0,0 +1,134 @@
+use super::*;
+
+/// The prefix of prop interactions that start a conversation with an NPC, followed by the path to
+/// the NPC, i.e. `talk:npcs/baker.npc.yml`
+const TALK_INTERACTION_PREFIX: &str = "talk:";
+
+/// The id of what the NPC is saying in the [`ui_utils::Typewriters`]
+pub const DIALOGUE_TEXT_ID: &str = "dialogue";
+
+/// Resource for the conversation the player is having with an NPC, shown in the pause menu
+#[derive(Default)]
+pub struct Conversation {
+    /// Whether the pause menu should open on the dialogue page, which is set when the player
+    /// interacts with an NPC
+    pub open_requested: bool,
+    /// The path to the NPC's `.npc.yml` file, which their affinity is saved under
+    pub npc_path: String,
+    pub npc: Handle<Npc>,
+    /// The prop the player interacted with to talk to the NPC, which their emotes are shown over
+    pub npc_entity: Option<Entity>,
+    /// What the NPC said back to the player's last choice or gift. The NPC's dialogue choices are
+    /// only shown before the player has answered.
+    pub reply: Option<String>,
+    /// Whether the player has answered the NPC
+    pub answered: bool,
+}
+
+/// Pause the game and open the dialogue page when the player interacts with an NPC
+pub fn talk_to_npcs(
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    mut state: ResMut<State<GameState>>,
+    mut physics_time: ResMut<PhysicsTime>,
+    mut conversation: ResMut<Conversation>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in interaction_events.iter() {
+        let npc = if let Some(npc) = event.interaction.strip_prefix(TALK_INTERACTION_PREFIX) {
+            npc
+        } else {
+            continue;
+        };
+
+        // The game may already be pausing this frame
+        if state.push(GameState::Paused).is_ok() {
+            physics_time.pause();
+        }
+        *conversation = Conversation {
+            open_requested: true,
+            npc_path: npc.into(),
+            npc: asset_server.load(npc),
+            npc_entity: Some(event.prop),
+            reply: None,
+            answered: false,
+        };
+    }
+}
+
+/// Answer the NPC in the current conversation with the choice at the given index of their current
+/// dialogue branch
+pub fn choose_dialogue(world: &mut World, index: usize) {
+    let conversation = world.get_resource::<Conversation>().unwrap();
+    let affinity = world
+        .get_resource::<Affinities>()
+        .unwrap()
+        .get(&conversation.npc_path);
+    let choice = world
+        .get_resource::<Assets<Npc>>()
+        .unwrap()
+        .get(&conversation.npc)
+        .and_then(|x| x.dialogue_at(affinity))
+        .and_then(|x| x.choices.get(index))
+        .cloned();
+    let choice = if let Some(choice) = choice {
+        choice
+    } else {
+        return;
+    };
+
+    let npc_path = conversation.npc_path.clone();
+    world
+        .get_resource_mut::<Affinities>()
+        .unwrap()
+        .change(&npc_path, choice.affinity);
+
+    // Show the NPC's reaction over them
+    let npc_entity = world.get_resource::<Conversation>().unwrap().npc_entity;
+    if let (Some(emote), Some(entity)) = (choice.emote, npc_entity) {
+        world
+            .get_resource_mut::<Events<EmoteEvent>>()
+            .unwrap()
+            .send(EmoteEvent { entity, emote });
+    }
+
+    let mut conversation = world.get_resource_mut::<Conversation>().unwrap();
+    conversation.reply = choice.reply;
+    conversation.answered = true;
+}
+
+/// Give an item from the player's inventory to the NPC in the current conversation
+pub fn give_gift(world: &mut World, item: &str) {
+    let conversation = world.get_resource::<Conversation>().unwrap();
+    let npc = if let Some(npc) = world
+        .get_resource::<Assets<Npc>>()
+        .unwrap()
+        .get(&conversation.npc)
+    {
+        npc
+    } else {
+        return;
+    };
+    let affinity = npc
+        .gifts
+        .get(item)
+        .copied()
+        .unwrap_or(npc.default_gift_affinity);
+    let reply = format!("{} accepted the gift", npc.name);
+    let npc_path = conversation.npc_path.clone();
+
+    if !world
+        .get_resource_mut::<Inventory>()
+        .unwrap()
+        .remove(item, 1)
+    {
+        return;
+    }
+    world
+        .get_resource_mut::<Affinities>()
+        .unwrap()
+        .change(&npc_path, affinity);
+
+    let mut conversation = world.get_resource_mut::<Conversation>().unwrap();
+    conversation.reply = Some(reply);
+    conversation.answered = true;
+}
@@ -0,0 +1,203 @@
+use bevy::utils::HashMap;
+use bevy_retrograde::core::image::DynamicImage;
+
+use super::*;
+
+/// Resource used to recolor every level with the same palette, overriding the level `palette`
+/// field, i.e. for a dusk palette driven by the time of day
+///
+/// The override also recolors the character and enemy sprites.
+#[derive(Default)]
+pub struct PaletteOverride(pub Option<String>);
+
+/// Resource for the character and enemy sprite images that have been recolored by the
+/// [`PaletteOverride`]
+#[derive(Default)]
+pub struct SpritePalettes {
+    /// The palette applied to each sprite image, and the image before it was applied
+    images: HashMap<Handle<Image>, (Option<String>, Image)>,
+}
+
+/// Component added to map layers that have had a palette applied
+pub struct LayerPalette {
+    /// The path to the palette that is applied to the layer, if any
+    pub palette: Option<String>,
+    /// The layer image before any palette was applied
+    pub original: Image,
+}
+
+/// Recolor map layer images according to their level's `palette` field or the
+/// [`PaletteOverride`]
+pub fn apply_level_palettes(
+    mut commands: Commands,
+    mut layers: Query<(Entity, &MapLayer, &Handle<Image>, Option<&mut LayerPalette>)>,
+    maps: Query<&GameMap>,
+    palette_override: Res<PaletteOverride>,
+    asset_server: Res<AssetServer>,
+    palette_assets: Res<Assets<Palette>>,
+    mut image_assets: ResMut<Assets<Image>>,
+) {
+    let map = if let Ok(map) = maps.single() {
+        map
+    } else {
+        return;
+    };
+
+    for (layer_ent, layer, image_handle, layer_palette) in layers.iter_mut() {
+        // Get the palette this layer should have
+        let target_palette = palette_override.0.clone().or_else(|| {
+            map.level(&layer.level_identifier)
+                .and_then(|level| level.field::<String>("palette"))
+                .filter(|x| x != "none")
+        });
+
+        // Skip layers that already have the right palette
+        let current_palette = layer_palette.as_ref().and_then(|x| x.palette.clone());
+        if current_palette == target_palette {
+            continue;
+        }
+
+        // Wait for the palette to load
+        let palette = if let Some(path) = &target_palette {
+            if let Some(palette) = palette_assets.get(asset_server.load_cached(path.as_str())) {
+                Some(palette)
+            } else {
+                continue;
+            }
+        } else {
+            None
+        };
+
+        let image = if let Some(image) = image_assets.get_mut(image_handle) {
+            image
+        } else {
+            continue;
+        };
+
+        // Get the original layer image, saving it the first time we recolor the layer
+        let original = if let Some(layer_palette) = &layer_palette {
+            layer_palette.original.clone()
+        } else {
+            image.clone()
+        };
+
+        **image = recolor(&original, palette);
+
+        // Record the palette applied to the layer
+        if let Some(mut layer_palette) = layer_palette {
+            layer_palette.palette = target_palette;
+        } else {
+            commands.entity(layer_ent).insert(LayerPalette {
+                palette: target_palette,
+                original,
+            });
+        }
+    }
+}
+
+/// Recolor the character and enemy sprite images with the [`PaletteOverride`]
+///
+/// The level `palette` field doesn't apply to sprites, because their images are shared by the
+/// sprites in every level.
+pub fn apply_sprite_palettes(
+    sprites: Query<&SpriteAnimation, Or<(With<Handle<Character>>, With<Enemy>)>>,
+    palette_override: Res<PaletteOverride>,
+    mut sprite_palettes: ResMut<SpritePalettes>,
+    asset_server: Res<AssetServer>,
+    palette_assets: Res<Assets<Palette>>,
+    animation_assets: Res<Assets<SpriteAnimations>>,
+    mut image_assets: ResMut<Assets<Image>>,
+) {
+    let target_palette = &palette_override.0;
+
+    for animation in sprites.iter() {
+        let image_handle = if let Some(animations) = animation_assets.get(&animation.animations) {
+            &animations.sprite_image
+        } else {
+            continue;
+        };
+
+        // Skip images that already have the right palette
+        let current_palette = sprite_palettes
+            .images
+            .get(image_handle)
+            .and_then(|(palette, _)| palette.as_ref());
+        if current_palette == target_palette.as_ref() {
+            continue;
+        }
+
+        // Wait for the palette to load
+        let palette = if let Some(path) = target_palette {
+            if let Some(palette) = palette_assets.get(asset_server.load_cached(path.as_str())) {
+                Some(palette)
+            } else {
+                continue;
+            }
+        } else {
+            None
+        };
+
+        let image = if let Some(image) = image_assets.get_mut(image_handle) {
+            image
+        } else {
+            continue;
+        };
+
+        // Get the original sprite image, saving it the first time we recolor it
+        let original = if let Some((_, original)) = sprite_palettes.images.get(image_handle) {
+            original.clone()
+        } else {
+            image.clone()
+        };
+
+        **image = recolor(&original, palette);
+        sprite_palettes
+            .images
+            .insert(image_handle.clone(), (target_palette.clone(), original));
+    }
+}
+
+/// Set the [`PaletteOverride`] from the palettes in the game's clock settings for the time of day
+pub fn set_clock_palette(
+    clock: Res<GameClock>,
+    game_info: Res<GameInfo>,
+    mut palette_override: ResMut<PaletteOverride>,
+) {
+    let palettes = &game_info.clock.palettes;
+    if palettes.is_empty() {
+        return;
+    }
+
+    // Use the latest palette that has started today, or the last one from yesterday if none have
+    let palette = palettes
+        .iter()
+        .filter(|x| x.hour <= clock.hour)
+        .max_by(|a, b| a.hour.partial_cmp(&b.hour).unwrap())
+        .or_else(|| {
+            palettes
+                .iter()
+                .max_by(|a, b| a.hour.partial_cmp(&b.hour).unwrap())
+        })
+        .map(|x| x.palette.as_str())
+        .filter(|x| *x != "none");
+
+    // Avoid triggering change detection if the palette is the same
+    if palette_override.0.as_deref() != palette {
+        palette_override.0 = palette.map(String::from);
+    }
+}
+
+/// Swap the colors of an image with the given palette
+pub fn recolor(original: &DynamicImage, palette: Option<&Palette>) -> DynamicImage {
+    let mut recolored = original.to_rgba8();
+    if let Some(palette) = palette {
+        for pixel in recolored.pixels_mut() {
+            if let Some(to) = palette.swaps.get(&[pixel[0], pixel[1], pixel[2]]) {
+                pixel[0] = to[0];
+                pixel[1] = to[1];
+                pixel[2] = to[2];
+            }
+        }
+    }
+    DynamicImage::ImageRgba8(recolored)
+}
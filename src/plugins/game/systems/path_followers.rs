@@ -0,0 +1,60 @@
+use super::*;
+
+/// Walk props along their paths, playing their `walk` clip while they move if they are animated
+pub fn follow_paths(
+    mut followers: Query<(
+        &mut PathFollower,
+        &mut Transform,
+        &mut Sprite,
+        Option<&mut SpriteAnimation>,
+    )>,
+    time: Res<Time>,
+) {
+    for (mut follower, mut transform, mut sprite, animation) in followers.iter_mut() {
+        let follower = &mut *follower;
+        let target = if let Some(&target) = follower.points.get(follower.target) {
+            target
+        } else {
+            continue;
+        };
+
+        let position = transform.translation.truncate();
+        let to_target = target - position;
+        let step = follower.speed * time.delta_seconds();
+        let new_position = if to_target.length() <= step {
+            // Move on to the next point, turning around at the ends of the path unless it loops
+            let last = follower.points.len() - 1;
+            if follower.looping {
+                follower.target = (follower.target + 1) % follower.points.len();
+            } else {
+                if follower.target == last {
+                    follower.reversed = true;
+                } else if follower.target == 0 {
+                    follower.reversed = false;
+                }
+                follower.target = if follower.reversed {
+                    follower.target.saturating_sub(1)
+                } else {
+                    (follower.target + 1).min(last)
+                };
+            }
+            target
+        } else {
+            position + to_target.normalize() * step
+        };
+        transform.translation = new_position.extend(transform.translation.z);
+
+        if let Some(mut animation) = animation {
+            animation.play(if to_target.length_squared() > 0. {
+                "walk"
+            } else {
+                "idle"
+            });
+        }
+
+        // Face the way the prop is going
+        if to_target.x != 0. && sprite.flip_x != (to_target.x < 0.) {
+            sprite.flip_x = to_target.x < 0.;
+        }
+    }
+}
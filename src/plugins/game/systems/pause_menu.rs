@@ -4,7 +4,20 @@ use bevy_retrograde::prelude::{
     PhysicsTime, UiTree,
 };
 
-use super::GameState;
+use super::{
+    crafting::CraftingStation,
+    dungeon::UnlockStation,
+    npcs::{Conversation, DIALOGUE_TEXT_ID},
+    photo_mode::PhotoMode,
+    signs::{Sign, SIGN_TEXT_ID},
+    ui_utils::Typewriters,
+    GameState,
+};
+
+/// A request to pause ( `true` ) or resume ( `false` ) the game from outside of the game's own
+/// controls, i.e. from the page hosting the game on web
+#[derive(Default)]
+pub struct PauseRequest(pub Option<bool>);
 
 pub fn handle_pause_menu(
     mut pause_menu_visible: Local<bool>,
@@ -12,71 +25,541 @@ pub fn handle_pause_menu(
     keyboard_input: Res<Input<KeyCode>>,
     mut state: ResMut<State<GameState>>,
     mut physics_time: ResMut<PhysicsTime>,
+    mut pause_request: ResMut<PauseRequest>,
+    mut crafting_station: ResMut<CraftingStation>,
+    mut conversation: ResMut<Conversation>,
+    mut unlock_station: ResMut<UnlockStation>,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut sign: ResMut<Sign>,
+    mut typewriters: ResMut<Typewriters>,
 ) {
+    // Hide the pause menu while the player is in photo mode. It is shown again when they come
+    // back.
+    if photo_mode.requested {
+        photo_mode.requested = false;
+        debug!("Hiding pause menu for photo mode");
+        *ui = UiTree(WidgetNode::None);
+        *pause_menu_visible = false;
+        state
+            .push(GameState::PhotoMode)
+            .expect("Could not transition to photo mode state");
+        return;
+    }
+
+    let resume_requested = pause_request.0 == Some(false);
+    if resume_requested {
+        pause_request.0 = None;
+    }
+
     if !*pause_menu_visible {
         debug!("Showing pause menu");
         *pause_menu_visible = true;
-        *ui = UiTree(make_widget!(ui::pause_menu).into());
-    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+
+        // Go straight to the crafting screen if the player is using a crafting station, to the
+        // dialogue if they are talking to an NPC, to the unlocks if they are at an unlock station,
+        // or to the sign they are reading
+        let start_page = if crafting_station.open_requested {
+            crafting_station.open_requested = false;
+            ui::PauseMenuPage::Crafting
+        } else if conversation.open_requested {
+            conversation.open_requested = false;
+            ui::PauseMenuPage::Dialogue
+        } else if unlock_station.open_requested {
+            unlock_station.open_requested = false;
+            ui::PauseMenuPage::Unlocks
+        } else if sign.open_requested {
+            sign.open_requested = false;
+            ui::PauseMenuPage::Sign
+        } else {
+            ui::PauseMenuPage::Main
+        };
+        *ui = UiTree(
+            make_widget!(ui::pause_menu)
+                .with_props(ui::PauseMenuProps { start_page })
+                .into(),
+        );
+    } else if keyboard_input.just_pressed(KeyCode::Escape) || resume_requested {
         debug!("Unpausing and hiding pause menu");
         state.pop().expect("Could not transition game state");
         *ui = UiTree(WidgetNode::None);
         *pause_menu_visible = false;
+        crafting_station.station = None;
+        *conversation = Conversation::default();
+        typewriters.remove(DIALOGUE_TEXT_ID);
+        typewriters.remove(SIGN_TEXT_ID);
         physics_time.resume();
     }
 }
 
 mod ui {
-    use bevy::prelude::World;
+    use bevy::prelude::{Assets, World};
     use bevy_retrograde::ui::raui::prelude::*;
 
-    use crate::plugins::game::{assets::GameInfo, systems::ui_utils::get_ui_theme};
+    use crate::plugins::game::{
+        affinity::Affinities,
+        assets::{GameInfo, Item, Npc, Recipe},
+        inventory::Inventory,
+        meta::MetaProgress,
+        settings::UserSettings,
+        systems::{
+            crafting::{craft_recipe, CraftingAssets, CraftingStation},
+            dungeon::buy_unlock,
+            equipment::equip_from_inventory,
+            npcs::{choose_dialogue, give_gift, Conversation, DIALOGUE_TEXT_ID},
+            photo_mode::PhotoMode,
+            signs::SIGN_TEXT_ID,
+            ui_utils::{
+                active_ui_theme, game_button, get_ui_theme, menu_button, menu_label, menu_panel,
+                menu_title, menu_typewriter, ui_font, GameButtonMessage, GameButtonProps,
+                Typewriters, UiLayout, UiThemeVariant,
+            },
+        },
+    };
+
+    use super::PauseRequest;
+
+    /// The size of the item icons in the inventory
+    const ITEM_ICON_SIZE: f32 = 16.;
+
+    /// The pages of the pause menu
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    pub enum PauseMenuPage {
+        Main,
+        Inventory,
+        Crafting,
+        Dialogue,
+        Gifts,
+        Unlocks,
+        Sign,
+    }
+
+    impl Default for PauseMenuPage {
+        fn default() -> Self {
+            Self::Main
+        }
+    }
+
+    #[derive(PropsData, Clone, Debug, serde::Serialize, serde::Deserialize, Default)]
+    pub struct PauseMenuProps {
+        /// The page shown when the pause menu opens
+        pub start_page: PauseMenuPage,
+    }
+
+    #[derive(PropsData, Clone, Debug, serde::Serialize, serde::Deserialize, Default)]
+    struct PauseMenuState {
+        page: PauseMenuPage,
+    }
+
+    fn use_pause_menu(ctx: &mut WidgetContext) {
+        ctx.life_cycle.change(|ctx| {
+            let world: &mut World = ctx.process_context.get_mut().unwrap();
+            let PauseMenuProps { start_page } = ctx.props.read_cloned_or_default();
+            let PauseMenuState { mut page } = ctx
+                .state
+                .read_cloned()
+                .unwrap_or(PauseMenuState { page: start_page });
+
+            let mut handled = false;
+            for msg in ctx.messenger.messages {
+                let msg = if let Some(msg) = msg.as_any().downcast_ref::<GameButtonMessage>() {
+                    msg
+                } else {
+                    continue;
+                };
+                handled = true;
 
-    pub fn pause_menu(ctx: WidgetContext) -> WidgetNode {
-        let WidgetContext {
-            process_context, ..
-        } = ctx;
+                match msg.0.as_str() {
+                    "resume" => {
+                        world.get_resource_mut::<PauseRequest>().unwrap().0 = Some(false);
+                    }
+                    "photo_mode" => {
+                        world.get_resource_mut::<PhotoMode>().unwrap().requested = true;
+                    }
+                    "show_main" => page = PauseMenuPage::Main,
+                    "show_inventory" => page = PauseMenuPage::Inventory,
+                    "show_crafting" => page = PauseMenuPage::Crafting,
+                    "show_dialogue" => page = PauseMenuPage::Dialogue,
+                    "show_gifts" => page = PauseMenuPage::Gifts,
+                    message => {
+                        if let Some(item) = message.strip_prefix("equip:") {
+                            equip_from_inventory(world, item);
+                        } else if let Some(index) = message
+                            .strip_prefix("craft:")
+                            .and_then(|x| x.parse::<usize>().ok())
+                        {
+                            craft_recipe(world, index);
+                        } else if let Some(index) = message
+                            .strip_prefix("choose:")
+                            .and_then(|x| x.parse::<usize>().ok())
+                        {
+                            choose_dialogue(world, index);
+                        } else if let Some(item) = message.strip_prefix("gift:") {
+                            give_gift(world, item);
+                            page = PauseMenuPage::Dialogue;
+                        } else if let Some(unlock) = message.strip_prefix("unlock:") {
+                            buy_unlock(world, unlock);
+                        } else if let Some(text_id) = message.strip_prefix("skip:") {
+                            world
+                                .get_resource_mut::<Typewriters>()
+                                .unwrap()
+                                .skip(text_id);
+                        }
+                    }
+                }
+            }
+
+            // Write the state even if the page hasn't changed, so that the lists are re-rendered
+            // after crafting, equipping, and buying unlocks
+            if handled {
+                ctx.state.write(PauseMenuState { page }).unwrap();
+            }
+        });
+    }
+
+    /// The pause menu, with pages for the player's inventory, crafting, talking to NPCs, buying
+    /// unlocks, and reading signs
+    #[pre_hooks(use_pause_menu)]
+    pub fn pause_menu(mut ctx: WidgetContext) -> WidgetNode {
+        let PauseMenuProps { start_page } = ctx.props.read_cloned_or_default();
+        let PauseMenuState { page } = ctx
+            .state
+            .read_cloned()
+            .unwrap_or(PauseMenuState { page: start_page });
+        let id = ctx.id.to_owned();
 
         // Get the game info from the world
-        let world: &mut World = process_context.get_mut().unwrap();
+        let world: &mut World = ctx.process_context.get_mut().unwrap();
         let game_info = world.get_resource::<GameInfo>().unwrap();
+        let settings = world.get_resource::<UserSettings>().unwrap();
+        let ui_theme_variant = world.get_resource::<UiThemeVariant>().unwrap();
+        let ui_theme = active_ui_theme(game_info, settings, ui_theme_variant, "pause");
+        let theme = get_ui_theme(ui_theme);
+        let font = ui_font(&ui_theme.default_font, settings);
+        let ui_layout = world
+            .get_resource::<UiLayout>()
+            .cloned()
+            .unwrap_or_default();
+
+        let rows = match page {
+            PauseMenuPage::Main => {
+                let mut rows = vec![
+                    menu_title(&font, "Paused"),
+                    menu_button("Resume", &id, "resume"),
+                    menu_button("Inventory", &id, "show_inventory"),
+                ];
+                if game_info.photo_mode.enabled {
+                    rows.push(menu_button("Photo Mode", &id, "photo_mode"));
+                }
+                rows
+            }
+            PauseMenuPage::Inventory => inventory_rows(world, &font, &id),
+            PauseMenuPage::Crafting => crafting_rows(world, &font, &id),
+            PauseMenuPage::Dialogue => dialogue_rows(world, &font, &id),
+            PauseMenuPage::Gifts => gift_rows(world, &font, &id),
+            PauseMenuPage::Unlocks => unlock_rows(world, &font, &id),
+            PauseMenuPage::Sign => sign_rows(world, &font, &id),
+        };
+        menu_panel(theme, &ui_layout, rows)
+    }
+
+    /// The rows of the inventory page, listing the items the player is carrying
+    fn inventory_rows(world: &mut World, font: &TextBoxFont, id: &WidgetId) -> Vec<WidgetNode> {
+        let mut rows = vec![menu_title(font, "Inventory")];
+        rows.extend(item_rows(world, font, id, |path, item| {
+            item.filter(|x| x.slot.is_some())
+                .map(|_| ("Equip", format!("equip:{}", path)))
+        }));
+        rows.push(menu_button("Crafting", id, "show_crafting"));
+        rows.push(menu_button("Back", id, "show_main"));
+        rows
+    }
 
-        // Content box
-        make_widget!(content_box)
-            .with_shared_props(get_ui_theme(game_info))
-            .listed_slot(
-                // Size box
-                make_widget!(size_box)
-                    .with_props(SizeBoxProps {
-                        width: SizeBoxSizeValue::Exact(50.),
-                        height: SizeBoxSizeValue::Exact(20.),
+    /// A row for each item in the player's inventory, with an optional button with the text and
+    /// message returned by `action`
+    fn item_rows(
+        world: &mut World,
+        font: &TextBoxFont,
+        id: &WidgetId,
+        action: impl Fn(&str, Option<&Item>) -> Option<(&'static str, String)>,
+    ) -> Vec<WidgetNode> {
+        let inventory = world.get_resource::<Inventory>().unwrap();
+        let crafting_assets = world.get_resource::<CraftingAssets>().unwrap();
+        let item_assets = world.get_resource::<Assets<Item>>().unwrap();
+
+        let mut rows = Vec::new();
+        if inventory.0.is_empty() {
+            rows.push(menu_label(font, "Nothing here yet"));
+        }
+        for (path, count) in &inventory.0 {
+            let item = crafting_assets
+                .items
+                .get(path)
+                .and_then(|x| item_assets.get(x));
+            let name = crafting_assets.item_name(item_assets, path);
+
+            let mut row = make_widget!(horizontal_box).with_props(FlexBoxItemLayout {
+                grow: 0.,
+                basis: Some(18.),
+                margin: Rect {
+                    top: 2.,
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+            if let Some(icon) = item.and_then(|x| x.icon.as_ref()) {
+                row = row.listed_slot(
+                    make_widget!(image_box)
+                        .with_props(ImageBoxProps {
+                            material: ImageBoxMaterial::Image(ImageBoxImage {
+                                id: icon.clone(),
+                                ..Default::default()
+                            }),
+                            width: ImageBoxSizeValue::Exact(ITEM_ICON_SIZE),
+                            height: ImageBoxSizeValue::Exact(ITEM_ICON_SIZE),
+                            ..Default::default()
+                        })
+                        .with_props(FlexBoxItemLayout {
+                            grow: 0.,
+                            basis: Some(ITEM_ICON_SIZE),
+                            ..Default::default()
+                        }),
+                );
+            }
+            row = row.listed_slot(menu_label(font, &format!("{} x{}", name, count)));
+            if let Some((text, message_name)) = action(path, item) {
+                row = row.listed_slot(
+                    make_widget!(game_button)
+                        .with_props(FlexBoxItemLayout {
+                            grow: 0.,
+                            ..Default::default()
+                        })
+                        .with_props(GameButtonProps {
+                            text: text.into(),
+                            notify_id: id.to_owned(),
+                            message_name,
+                        }),
+                );
+            }
+            rows.push(row.into());
+        }
+
+        rows
+    }
+
+    /// The rows of the dialogue page, with what the NPC the player is talking to says and the
+    /// choices the player can answer with
+    fn dialogue_rows(world: &mut World, font: &TextBoxFont, id: &WidgetId) -> Vec<WidgetNode> {
+        let conversation = world.get_resource::<Conversation>().unwrap();
+        let affinity = world
+            .get_resource::<Affinities>()
+            .unwrap()
+            .get(&conversation.npc_path);
+        let npc = if let Some(npc) = world
+            .get_resource::<Assets<Npc>>()
+            .unwrap()
+            .get(&conversation.npc)
+        {
+            npc
+        } else {
+            return vec![menu_button("Goodbye", id, "resume")];
+        };
+        let branch = npc.dialogue_at(affinity);
+
+        let mut rows = vec![menu_title(font, &npc.name)];
+        let (text, choices) = match branch {
+            _ if conversation.answered => (conversation.reply.clone(), Vec::new()),
+            Some(branch) => (Some(branch.text.clone()), branch.choices.clone()),
+            None => (None, Vec::new()),
+        };
+        let text_sound = world
+            .get_resource::<GameInfo>()
+            .unwrap()
+            .ui_theme
+            .text_sound
+            .clone();
+
+        // Start revealing what the NPC says whenever it changes
+        let mut typewriters = world.get_resource_mut::<Typewriters>().unwrap();
+        if let Some(text) = text {
+            if typewriters.0.get(DIALOGUE_TEXT_ID).map(|x| &x.text) != Some(&text) {
+                typewriters.start(DIALOGUE_TEXT_ID, &text, text_sound);
+            }
+            rows.push(menu_typewriter(font, DIALOGUE_TEXT_ID));
+        }
+
+        // The player can only answer once the NPC has finished talking
+        if !typewriters.is_finished(DIALOGUE_TEXT_ID) {
+            rows.push(skip_button(id, DIALOGUE_TEXT_ID));
+            return rows;
+        }
+        for (i, choice) in choices.iter().enumerate() {
+            rows.push(menu_button(&choice.text, id, &format!("choose:{}", i)));
+        }
+        rows.push(menu_button("Give a gift", id, "show_gifts"));
+        rows.push(menu_button("Goodbye", id, "resume"));
+        rows
+    }
+
+    /// The rows of the sign page, with the text of the sign the player is reading
+    fn sign_rows(world: &mut World, font: &TextBoxFont, id: &WidgetId) -> Vec<WidgetNode> {
+        let typewriters = world.get_resource::<Typewriters>().unwrap();
+        let mut rows = vec![menu_typewriter(font, SIGN_TEXT_ID)];
+        if typewriters.is_finished(SIGN_TEXT_ID) {
+            rows.push(menu_button("OK", id, "resume"));
+        } else {
+            rows.push(skip_button(id, SIGN_TEXT_ID));
+        }
+        rows
+    }
+
+    /// A button that reveals the rest of the typewriter text with the given id
+    fn skip_button(id: &WidgetId, text_id: &str) -> WidgetNode {
+        menu_button("...", id, &format!("skip:{}", text_id))
+    }
+
+    /// The rows of the gift page, listing the items the player can give to the NPC they are
+    /// talking to
+    fn gift_rows(world: &mut World, font: &TextBoxFont, id: &WidgetId) -> Vec<WidgetNode> {
+        let mut rows = vec![menu_title(font, "Give a gift")];
+        rows.extend(item_rows(world, font, id, |path, _| {
+            Some(("Give", format!("gift:{}", path)))
+        }));
+        rows.push(menu_button("Back", id, "show_dialogue"));
+        rows
+    }
+
+    /// The rows of the crafting page, listing the recipes that can be made at the current crafting
+    /// station
+    fn crafting_rows(world: &mut World, font: &TextBoxFont, id: &WidgetId) -> Vec<WidgetNode> {
+        let inventory = world.get_resource::<Inventory>().unwrap();
+        let crafting_assets = world.get_resource::<CraftingAssets>().unwrap();
+        let crafting_station = world.get_resource::<CraftingStation>().unwrap();
+        let item_assets = world.get_resource::<Assets<Item>>().unwrap();
+        let recipe_assets = world.get_resource::<Assets<Recipe>>().unwrap();
+
+        let mut rows = vec![menu_title(font, "Crafting")];
+        for (i, handle) in crafting_assets.recipes.iter().enumerate() {
+            let recipe = if let Some(recipe) = recipe_assets.get(handle) {
+                recipe
+            } else {
+                continue;
+            };
+            // Recipes without a station can be made anywhere
+            if recipe.station.is_some() && recipe.station != crafting_station.station {
+                continue;
+            }
+
+            let name = recipe
+                .name
+                .clone()
+                .unwrap_or_else(|| crafting_assets.item_name(item_assets, &recipe.output));
+            let mut inputs = recipe
+                .inputs
+                .iter()
+                .map(|(path, count)| {
+                    format!("{} {}", count, crafting_assets.item_name(item_assets, path))
+                })
+                .collect::<Vec<_>>();
+            inputs.sort();
+            let text = if recipe.count > 1 {
+                format!("{} x{}: {}", name, recipe.count, inputs.join(", "))
+            } else {
+                format!("{}: {}", name, inputs.join(", "))
+            };
+
+            let mut row = make_widget!(horizontal_box)
+                .with_props(FlexBoxItemLayout {
+                    grow: 0.,
+                    basis: Some(18.),
+                    margin: Rect {
+                        top: 2.,
                         ..Default::default()
-                    })
-                    .with_props(ContentBoxItemLayout {
-                        align: 0.5.into(),
+                    },
+                    ..Default::default()
+                })
+                .listed_slot(menu_label(font, &text));
+            if inventory.has_inputs(recipe) {
+                row = row.listed_slot(
+                    make_widget!(game_button)
+                        .with_props(FlexBoxItemLayout {
+                            grow: 0.,
+                            ..Default::default()
+                        })
+                        .with_props(GameButtonProps {
+                            text: "Craft".into(),
+                            notify_id: id.to_owned(),
+                            message_name: format!("craft:{}", i),
+                        }),
+                );
+            }
+            rows.push(row.into());
+        }
+        if rows.len() == 1 {
+            rows.push(menu_label(font, "Nothing to craft here"));
+        }
+
+        // Crafting stations go back to the game, and the crafting page from the inventory goes back
+        // to the inventory
+        if crafting_station.station.is_some() {
+            rows.push(menu_button("Back", id, "resume"));
+        } else {
+            rows.push(menu_button("Back", id, "show_inventory"));
+        }
+        rows
+    }
+
+    /// The rows of the unlocks page, listing the unlocks the player can buy with meta-currency
+    fn unlock_rows(world: &mut World, font: &TextBoxFont, id: &WidgetId) -> Vec<WidgetNode> {
+        let meta = world.get_resource::<MetaProgress>().unwrap();
+        let config = if let Some(config) = &world.get_resource::<GameInfo>().unwrap().dungeon {
+            config
+        } else {
+            return vec![menu_button("Back", id, "resume")];
+        };
+
+        let mut rows = vec![
+            menu_title(font, "Unlocks"),
+            menu_label(
+                font,
+                &format!("{}: {}", config.currency_name, meta.currency),
+            ),
+        ];
+        for unlock in &config.unlocks {
+            let bought = meta.is_unlocked(&unlock.id);
+            let text = if bought {
+                format!("{}: Unlocked", unlock.name)
+            } else {
+                format!("{}: {} {}", unlock.name, unlock.cost, config.currency_name)
+            };
+
+            let mut row = make_widget!(horizontal_box)
+                .with_props(FlexBoxItemLayout {
+                    grow: 0.,
+                    basis: Some(18.),
+                    margin: Rect {
+                        top: 2.,
                         ..Default::default()
-                    })
-                    .named_slot(
-                        "content",
-                        // Horizontal paper
-                        make_widget!(horizontal_paper)
-                            .with_props(PaperProps {
-                                variant: "button-up".into(),
-                                ..Default::default()
-                            })
-                            // Text box
-                            .listed_slot(make_widget!(text_box).with_props(TextBoxProps {
-                                text: "Paused".into(),
-                                font: TextBoxFont {
-                                    name: game_info.ui_theme.default_font.clone(),
-                                    ..Default::default()
-                                },
-                                horizontal_align: TextBoxHorizontalAlign::Center,
-                                vertical_align: TextBoxVerticalAlign::Middle,
-                                ..Default::default()
-                            })),
-                    ),
-            )
-            .into()
+                    },
+                    ..Default::default()
+                })
+                .listed_slot(menu_label(font, &text));
+            if !bought && meta.currency >= unlock.cost {
+                row = row.listed_slot(
+                    make_widget!(game_button)
+                        .with_props(FlexBoxItemLayout {
+                            grow: 0.,
+                            ..Default::default()
+                        })
+                        .with_props(GameButtonProps {
+                            text: "Buy".into(),
+                            notify_id: id.to_owned(),
+                            message_name: format!("unlock:{}", unlock.id),
+                        }),
+                );
+            }
+            rows.push(row.into());
+        }
+        rows.push(menu_button("Back", id, "resume"));
+        rows
     }
 }
@@ -0,0 +1,319 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use bevy::{
+    prelude::{Handle, Input, KeyCode, Time, Transform, With, World},
+    utils::Instant,
+};
+use bevy_retrograde::{physics::heron::RigidBody, prelude::Camera, ui::raui::prelude::*};
+
+use crate::plugins::game::{assets::GameInfo, components::Character};
+
+use super::free_camera::FreeCamera;
+
+/// How often the performance HUD is updated in seconds
+const UPDATE_INTERVAL: f32 = 0.5;
+/// How many of the largest archetypes and slowest systems to show
+const TOP_COUNT: usize = 5;
+
+/// The time taken by the heavy systems since the performance HUD was last updated
+///
+/// Systems record their time with [`start`][Self::start]. This uses a mutex instead of requiring
+/// mutable access so that it doesn't stop the timed systems from running in parallel.
+#[derive(Default)]
+pub struct SystemTimings(Mutex<HashMap<&'static str, Duration>>);
+
+impl SystemTimings {
+    /// Start timing a system. The time is recorded when the returned timer is dropped.
+    pub fn start(&self, name: &'static str) -> SystemTimer<'_> {
+        SystemTimer {
+            timings: self,
+            name,
+            start: Instant::now(),
+        }
+    }
+
+    /// Take the recorded times, resetting them to zero
+    fn take(&self) -> HashMap<&'static str, Duration> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+/// Records the time taken by a system in [`SystemTimings`] when dropped
+pub struct SystemTimer<'a> {
+    timings: &'a SystemTimings,
+    name: &'static str,
+    start: Instant,
+}
+
+impl<'a> Drop for SystemTimer<'a> {
+    fn drop(&mut self) {
+        *self.timings.0.lock().unwrap().entry(self.name).or_default() += self.start.elapsed();
+    }
+}
+
+/// Counts of the paths found by enemies since the performance HUD was last updated
+#[derive(Default)]
+pub struct PathfindingStats {
+    /// The number of paths found
+    paths: u32,
+    /// The total length of the paths found in pixels
+    length: f32,
+    /// The number of times an enemy couldn't find a path since the game started
+    failures: u32,
+}
+
+impl PathfindingStats {
+    /// Record a path that was found, with its length in pixels
+    pub fn record_path(&mut self, length: f32) {
+        self.paths += 1;
+        self.length += length;
+    }
+
+    /// Record that an enemy couldn't find a path to its target
+    pub fn record_failure(&mut self) {
+        self.failures += 1;
+    }
+}
+
+/// Resource containing the performance HUD that is toggled with F3
+#[derive(Default)]
+pub struct PerfHud {
+    /// Whether the HUD is shown
+    pub visible: bool,
+    /// The lines of text in the HUD
+    pub lines: Vec<String>,
+    /// The number of frames since the HUD was last updated
+    frames: u32,
+    /// The time since the HUD was last updated in seconds
+    elapsed: f32,
+}
+
+/// Toggle the performance HUD with F3 and update its stats while it is shown
+pub fn update_perf_hud(world: &mut World) {
+    let toggle = world
+        .get_resource::<Input<KeyCode>>()
+        .map(|x| x.just_pressed(KeyCode::F3))
+        .unwrap_or(false);
+    let delta = world.get_resource::<Time>().unwrap().delta_seconds();
+
+    // Count the frames until it is time to update
+    let (frames, elapsed) = {
+        let mut hud = world.get_resource_mut::<PerfHud>().unwrap();
+        if toggle {
+            hud.visible = !hud.visible;
+            hud.lines.clear();
+            hud.frames = 0;
+            hud.elapsed = 0.;
+        }
+        if !hud.visible {
+            return;
+        }
+
+        hud.frames += 1;
+        hud.elapsed += delta;
+        if hud.elapsed < UPDATE_INTERVAL {
+            return;
+        }
+
+        let counts = (hud.frames, hud.elapsed);
+        hud.frames = 0;
+        hud.elapsed = 0.;
+        counts
+    };
+
+    let mut lines = vec![format!("FPS: {:.0}", frames as f32 / elapsed)];
+
+    // Show the entity count and the archetypes with the most entities
+    lines.push(format!("Entities: {}", world.entities().len()));
+    let components = world.components();
+    let mut archetypes = world
+        .archetypes()
+        .iter()
+        .filter(|x| !x.is_empty())
+        .map(|archetype| {
+            let names = archetype
+                .components()
+                .filter_map(|id| components.get_info(id))
+                .map(|info| short_type_name(info.name()))
+                .collect::<Vec<_>>();
+            (archetype.len(), names.join(", "))
+        })
+        .collect::<Vec<_>>();
+    archetypes.sort_by(|a, b| b.0.cmp(&a.0));
+    for (count, names) in archetypes.into_iter().take(TOP_COUNT) {
+        lines.push(format!("  {}: {}", count, names));
+    }
+
+    // Count the physics bodies by type
+    let (mut dynamic, mut fixed, mut sensor, mut kinematic) = (0, 0, 0, 0);
+    for body in world.query::<&RigidBody>().iter(world) {
+        match body {
+            RigidBody::Dynamic => dynamic += 1,
+            RigidBody::Static => fixed += 1,
+            RigidBody::Sensor => sensor += 1,
+            _ => kinematic += 1,
+        }
+    }
+    lines.push(format!(
+        "Bodies: {} dynamic, {} static, {} sensor, {} kinematic",
+        dynamic, fixed, sensor, kinematic
+    ));
+
+    // Show how much pathfinding the enemies are doing
+    let mut pathfinding = world.get_resource_mut::<PathfindingStats>().unwrap();
+    lines.push(format!(
+        "Paths: {:.1}/frame, {:.0}px average, {} failed",
+        pathfinding.paths as f32 / frames as f32,
+        if pathfinding.paths > 0 {
+            pathfinding.length / pathfinding.paths as f32
+        } else {
+            0.
+        },
+        pathfinding.failures
+    ));
+    pathfinding.paths = 0;
+    pathfinding.length = 0.;
+
+    // Show the slowest of the timed systems, averaged per frame
+    let mut timings = world
+        .get_resource::<SystemTimings>()
+        .unwrap()
+        .take()
+        .into_iter()
+        .collect::<Vec<_>>();
+    timings.sort_by(|a, b| b.1.cmp(&a.1));
+    lines.push("Slowest systems:".into());
+    for (name, duration) in timings.into_iter().take(TOP_COUNT) {
+        lines.push(format!(
+            "  {}: {:.2}ms",
+            name,
+            duration.as_secs_f32() * 1000. / frames as f32
+        ));
+    }
+
+    // Show where the camera and the player are, for finding spots in the level
+    let free_camera = world.get_resource::<FreeCamera>().unwrap().active;
+    let camera = world
+        .query_filtered::<&Transform, With<Camera>>()
+        .iter(world)
+        .next()
+        .map(|x| x.translation);
+    if let Some(pos) = camera {
+        lines.push(format!(
+            "Camera: {:.0}, {:.0}{}",
+            pos.x,
+            pos.y,
+            if free_camera { " (free, F4)" } else { "" }
+        ));
+    }
+    let player = world
+        .query_filtered::<&Transform, With<Handle<Character>>>()
+        .iter(world)
+        .next()
+        .map(|x| x.translation);
+    if let Some(pos) = player {
+        lines.push(format!("Player: {:.0}, {:.0}", pos.x, pos.y));
+    }
+
+    // Show where the game is loading and saving its data
+    let engine_config = world.get_resource::<crate::EngineConfig>().unwrap();
+    lines.push(format!(
+        "Assets: {}",
+        engine_config
+            .asset_pack
+            .as_ref()
+            .unwrap_or(&engine_config.asset_path)
+    ));
+    if let Some(storage) = world.get_resource::<crate::Storage>() {
+        lines.push(format!("Save data: {}", storage.location()));
+    }
+
+    world.get_resource_mut::<PerfHud>().unwrap().lines = lines;
+}
+
+/// Strip the module paths from a type name, i.e. `bevy::Handle<game::Character>` becomes
+/// `Handle<Character>`
+fn short_type_name(name: &str) -> String {
+    let mut short = String::new();
+    let mut segment = String::new();
+    for c in name.chars() {
+        match c {
+            ':' => segment.clear(),
+            '<' | '>' | ',' | ' ' | '(' | ')' | '[' | ']' | ';' | '&' => {
+                short.push_str(&segment);
+                segment.clear();
+                short.push(c);
+            }
+            _ => segment.push(c),
+        }
+    }
+    short.push_str(&segment);
+    short
+}
+
+/// The performance HUD in the top-left corner of the screen
+pub fn perf_hud(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let hud = if let Some(hud) = world.get_resource::<PerfHud>() {
+        hud
+    } else {
+        return WidgetNode::None;
+    };
+    if !hud.visible || hud.lines.is_empty() {
+        return WidgetNode::None;
+    }
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+
+    debug_text(
+        &game_info.ui_theme.default_font,
+        &hud.lines,
+        Rect {
+            left: 0.,
+            right: 0.5,
+            top: 0.,
+            bottom: 1.,
+        },
+    )
+}
+
+/// Lines of small yellow debug text, pushed to the top of the given part of the screen
+pub fn debug_text(font: &str, lines: &[String], anchors: Rect) -> WidgetNode {
+    let mut list = make_widget!(vertical_box)
+        .with_props(VerticalBoxProps {
+            separation: 1.,
+            ..Default::default()
+        })
+        .with_props(ContentBoxItemLayout {
+            anchors,
+            margin: 5.0.into(),
+            ..Default::default()
+        });
+
+    for line in lines {
+        list = list.listed_slot(
+            make_widget!(text_box)
+                .with_props(TextBoxProps {
+                    text: line.clone(),
+                    font: TextBoxFont {
+                        name: font.to_owned(),
+                        size: 1.,
+                    },
+                    color: Color {
+                        r: 1.,
+                        g: 1.,
+                        b: 0.,
+                        a: 1.,
+                    },
+                    ..Default::default()
+                })
+                .with_props(FlexBoxItemLayout {
+                    grow: 0.,
+                    basis: Some(10.),
+                    ..Default::default()
+                }),
+        );
+    }
+
+    // Push the lines to the top of the screen
+    list.listed_slot(make_widget!(space_box)).into()
+}
@@ -0,0 +1,152 @@
+use super::{post_processing::get_shader_source, *};
+
+/// How much the zoom changes per second while a zoom key is held, as a multiple of the zoom
+const ZOOM_SPEED: f32 = 1.5;
+
+/// Resource for the state of photo mode
+#[derive(Default)]
+pub struct PhotoMode {
+    /// Set from the pause menu to enter photo mode
+    pub requested: bool,
+    /// How far the camera is zoomed in
+    zoom: f32,
+    /// The index of the filter in the photo mode settings that is applied, if any
+    filter: Option<usize>,
+    /// The camera's position, size, and shader before photo mode, which are put back afterwards
+    saved_camera: Option<(Transform, CameraSize, Option<String>)>,
+}
+
+/// Detach the camera from the player, remembering where it was
+pub fn enter_photo_mode(mut photo_mode: ResMut<PhotoMode>, cameras: Query<(&Camera, &Transform)>) {
+    debug!("Entering photo mode");
+    photo_mode.zoom = 1.;
+    photo_mode.filter = None;
+    photo_mode.saved_camera = cameras.single().ok().map(|(camera, transform)| {
+        (
+            *transform,
+            camera.size.clone(),
+            camera.custom_shader.clone(),
+        )
+    });
+}
+
+/// Move and zoom the camera, and switch filters, until the player leaves photo mode
+///
+/// The arrow keys move the camera, `Z` and `X` zoom in and out, `F` cycles through the filters,
+/// and `Escape` goes back to the pause menu.
+pub fn update_photo_mode(
+    mut photo_mode: ResMut<PhotoMode>,
+    mut cameras: Query<(&mut Camera, &mut Transform)>,
+    mut state: ResMut<State<GameState>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    game_info: Res<GameInfo>,
+    asset_server: Res<AssetServer>,
+    shader_assets: Res<Assets<ShaderSource>>,
+    time: Res<Time>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        state.pop().expect("Could not transition game state");
+        return;
+    }
+
+    let (mut camera, mut camera_transform) = if let Ok(camera) = cameras.single_mut() {
+        camera
+    } else {
+        return;
+    };
+    let (saved_size, saved_shader) = match &photo_mode.saved_camera {
+        Some((_, size, shader)) => (size.clone(), shader.clone()),
+        None => return,
+    };
+    let config = &game_info.photo_mode;
+    let delta = time.delta_seconds();
+
+    // Zoom the camera by shrinking or growing the size of its view
+    let mut zoom = photo_mode.zoom;
+    if keyboard_input.pressed(KeyCode::Z) {
+        zoom *= ZOOM_SPEED.powf(delta);
+    }
+    if keyboard_input.pressed(KeyCode::X) {
+        zoom /= ZOOM_SPEED.powf(delta);
+    }
+    zoom = zoom.clamp(config.min_zoom.min(1.), config.max_zoom.max(1.));
+    if zoom != photo_mode.zoom {
+        photo_mode.zoom = zoom;
+        camera.size = zoomed_camera_size(&saved_size, zoom);
+    }
+
+    // Move the camera, more slowly when it is zoomed in so that it moves the same distance across
+    // the screen
+    let mut direction = Vec2::ZERO;
+    if keyboard_input.pressed(KeyCode::Left) {
+        direction.x -= 1.;
+    }
+    if keyboard_input.pressed(KeyCode::Right) {
+        direction.x += 1.;
+    }
+    if keyboard_input.pressed(KeyCode::Up) {
+        direction.y -= 1.;
+    }
+    if keyboard_input.pressed(KeyCode::Down) {
+        direction.y += 1.;
+    }
+    let movement = direction.normalize_or_zero() * config.pan_speed * delta / zoom;
+    camera_transform.translation += movement.extend(0.);
+
+    // Cycle through the filters, ending with no filter
+    if keyboard_input.just_pressed(KeyCode::F) && !config.filters.is_empty() {
+        photo_mode.filter = match photo_mode.filter {
+            None => Some(0),
+            Some(index) if index + 1 < config.filters.len() => Some(index + 1),
+            Some(_) => None,
+        };
+    }
+    let shader = match photo_mode.filter.and_then(|x| config.filters.get(x)) {
+        Some(filter) => {
+            // Wait for the filter to load before applying it
+            match get_shader_source(filter, &game_info, &asset_server, &shader_assets) {
+                Some(source) => Some(source),
+                None => return,
+            }
+        }
+        None => saved_shader,
+    };
+
+    // Only update the camera if the shader changed so that it doesn't get re-compiled
+    if camera.custom_shader != shader {
+        camera.custom_shader = shader;
+    }
+}
+
+/// Put the camera back the way it was before photo mode
+pub fn exit_photo_mode(
+    mut photo_mode: ResMut<PhotoMode>,
+    mut cameras: Query<(&mut Camera, &mut Transform)>,
+) {
+    debug!("Leaving photo mode");
+    let (saved_transform, saved_size, saved_shader) = match photo_mode.saved_camera.take() {
+        Some(saved) => saved,
+        None => return,
+    };
+
+    if let Ok((mut camera, mut transform)) = cameras.single_mut() {
+        *transform = saved_transform;
+        camera.size = saved_size;
+        if camera.custom_shader != saved_shader {
+            camera.custom_shader = saved_shader;
+        }
+    }
+}
+
+/// Get the size of the camera's view when it is zoomed in by the given amount
+fn zoomed_camera_size(size: &CameraSize, zoom: f32) -> CameraSize {
+    let zoomed = |x: u32| (x as f32 / zoom).round().max(1.) as u32;
+    match *size {
+        CameraSize::FixedHeight(height) => CameraSize::FixedHeight(zoomed(height)),
+        CameraSize::FixedWidth(width) => CameraSize::FixedWidth(zoomed(width)),
+        CameraSize::LetterBoxed { width, height } => CameraSize::LetterBoxed {
+            width: zoomed(width),
+            height: zoomed(height),
+        },
+    }
+}
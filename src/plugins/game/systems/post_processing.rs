@@ -0,0 +1,274 @@
+use bevy::asset::LoadState;
+
+use super::*;
+
+/// The name of the built-in CRT shader
+pub const CRT_SHADER: &str = "crt";
+
+/// Placeholder that may be put in a shader's source to have it replaced with a number from `0.0`
+/// to `1.0` indicating how strongly the shader should be applied while blending in or out
+pub const SHADER_STRENGTH_PLACEHOLDER: &str = "{{strength}}";
+
+/// The number of distinct strength steps used while blending, to avoid re-compiling the shader
+/// every frame
+const BLEND_STEPS: f32 = 8.;
+
+/// The state of the level and region post-processing shaders
+#[derive(Default)]
+pub struct PostProcessState {
+    /// The shader set on the current level
+    pub level_shader: Option<String>,
+    /// The shader regions the player is currently inside of, in the order they were entered
+    pub active_regions: Vec<(Entity, String)>,
+    /// The shader that is currently applied, or being blended to
+    pub current_shader: Option<String>,
+    /// The shader that was on the camera before any level or region shader was applied, i.e. the
+    /// CRT filter from the settings menu
+    pub base_shader: Option<String>,
+    /// The blend in progress, if any
+    pub blend: Option<ShaderBlend>,
+}
+
+impl PostProcessState {
+    /// Get the shader that should be applied right now
+    pub fn target_shader(&self) -> Option<&String> {
+        self.active_regions
+            .last()
+            .map(|(_, shader)| shader)
+            .or_else(|| self.level_shader.as_ref())
+    }
+}
+
+/// A transition from one post-processing shader to another
+pub struct ShaderBlend {
+    /// The shader we are blending out of
+    pub from: BlendShader,
+    /// The shader we are blending into
+    pub to: BlendShader,
+    /// The timer for the blend
+    pub timer: Timer,
+}
+
+/// A shader in a blend, looked up once when the blend starts
+pub enum BlendShader {
+    /// The shader that was on the camera before any level or region shader was applied
+    Base,
+    /// A built-in shader
+    Source(String),
+    /// A shader loaded from the game's assets
+    Asset {
+        name: String,
+        handle: Handle<ShaderSource>,
+    },
+}
+
+impl BlendShader {
+    /// Look up a shader by name, warning and falling back to the base shader if it doesn't exist
+    fn resolve(name: Option<&str>, game_info: &GameInfo, asset_server: &AssetServer) -> Self {
+        let name = if let Some(name) = name {
+            name
+        } else {
+            return BlendShader::Base;
+        };
+        if name == CRT_SHADER {
+            return BlendShader::Source(CrtShader::default().get_shader());
+        }
+
+        match game_info.post_processing.shaders.get(name) {
+            Some(path) => BlendShader::Asset {
+                name: name.into(),
+                handle: asset_server.load_cached(path.as_str()),
+            },
+            None => {
+                warn!(shader = name, "Unknown post-processing shader");
+                BlendShader::Base
+            }
+        }
+    }
+
+    /// Fall back to the base shader if the shader failed to load, warning about it once
+    fn check_load_state(&mut self, asset_server: &AssetServer) {
+        if let BlendShader::Asset { name, handle } = self {
+            if asset_server.get_load_state(handle.id) == LoadState::Failed {
+                warn!(shader = %name, "Could not load post-processing shader");
+                *self = BlendShader::Base;
+            }
+        }
+    }
+
+    /// Get the source of the shader, returning `None` if it hasn't loaded yet
+    fn source(
+        &self,
+        base_shader: &Option<String>,
+        shader_assets: &Assets<ShaderSource>,
+    ) -> Option<Option<String>> {
+        match self {
+            BlendShader::Base => Some(base_shader.clone()),
+            BlendShader::Source(source) => Some(Some(source.clone())),
+            BlendShader::Asset { handle, .. } => {
+                shader_assets.get(handle).map(|x| Some(x.0.clone()))
+            }
+        }
+    }
+}
+
+/// Look up the source of a shader by name, returning `None` if it doesn't exist or hasn't loaded
+/// yet
+pub fn get_shader_source(
+    name: &str,
+    game_info: &GameInfo,
+    asset_server: &AssetServer,
+    shader_assets: &Assets<ShaderSource>,
+) -> Option<String> {
+    if name == CRT_SHADER {
+        return Some(CrtShader::default().get_shader());
+    }
+
+    let path = if let Some(path) = game_info.post_processing.shaders.get(name) {
+        path
+    } else {
+        warn!(shader = name, "Unknown post-processing shader");
+        return None;
+    };
+
+    let handle: Handle<ShaderSource> = asset_server.load_cached(path.as_str());
+    shader_assets.get(handle).map(|x| x.0.clone())
+}
+
+/// Apply a strength to the shader source if it supports it
+fn with_strength(source: &str, strength: f32) -> String {
+    source.replace(
+        SHADER_STRENGTH_PLACEHOLDER,
+        &format!("{:.3}", (strength * BLEND_STEPS).round() / BLEND_STEPS),
+    )
+}
+
+/// Update the level shader when the level changes, and the region shader when the player enters
+/// or leaves a shader region
+pub fn update_level_shader(
+    mut state: ResMut<PostProcessState>,
+    current_level: Option<Res<CurrentLevel>>,
+    maps: Query<&GameMap>,
+    regions: Query<&ShaderRegion>,
+    characters: Query<Entity, With<Handle<Character>>>,
+    mut collision_events: EventReader<Collision<Handle<Character>, ShaderRegion>>,
+    contacts: Res<Contacts<Handle<Character>, ShaderRegion>>,
+) {
+    let current_level = if let Some(level) = current_level {
+        level
+    } else {
+        return;
+    };
+
+    // Update the level shader when the level changes
+    if current_level.is_changed() {
+        let level = maps
+            .single()
+            .ok()
+            .and_then(|map| map.level(current_level.as_str()));
+
+        if let Some(level) = level {
+            state.level_shader = level.field("shader");
+        }
+
+        // Forget any regions from the previous level, keeping the ones the player is still inside
+        // of
+        let character = characters.single().ok();
+        state
+            .active_regions
+            .retain(|(region, _)| character.map_or(false, |x| contacts.is_touching(x, *region)));
+    }
+
+    // Check for the player entering or leaving shader regions
+    for event in collision_events.iter() {
+        let region_ent = event.b;
+        let region = if let Ok(region) = regions.get(region_ent) {
+            region
+        } else {
+            continue;
+        };
+
+        if event.is_started() {
+            state
+                .active_regions
+                .push((region_ent, region.shader.clone()));
+        } else {
+            state.active_regions.retain(|(ent, _)| *ent != region_ent);
+        }
+    }
+}
+
+/// Start blends to the target shader and apply blends in progress to the camera
+pub fn blend_post_process_shader(
+    mut state: ResMut<PostProcessState>,
+    mut cameras: Query<&mut Camera>,
+    game_info: Res<GameInfo>,
+    asset_server: Res<AssetServer>,
+    shader_assets: Res<Assets<ShaderSource>>,
+    time: Res<Time>,
+) {
+    let mut camera = if let Ok(camera) = cameras.single_mut() {
+        camera
+    } else {
+        return;
+    };
+
+    let state = &mut *state;
+
+    // Start a new blend if the target shader changed
+    let target = state.target_shader().cloned();
+    if target != state.current_shader {
+        // Remember the settings shader before we apply our first shader
+        if state.current_shader.is_none() && state.blend.is_none() {
+            state.base_shader = camera.custom_shader.clone();
+        }
+
+        state.blend = Some(ShaderBlend {
+            from: BlendShader::resolve(state.current_shader.as_deref(), &game_info, &asset_server),
+            to: BlendShader::resolve(target.as_deref(), &game_info, &asset_server),
+            timer: Timer::from_seconds(game_info.post_processing.blend_duration, false),
+        });
+        state.current_shader = target;
+    }
+
+    let blend = if let Some(blend) = &mut state.blend {
+        blend
+    } else {
+        return;
+    };
+    blend.timer.tick(time.delta());
+    blend.from.check_load_state(&asset_server);
+    blend.to.check_load_state(&asset_server);
+
+    // Blend the old shader out over the first half, then the new shader in over the second half
+    let progress = if blend.timer.duration().as_secs_f32() > 0. {
+        blend.timer.percent()
+    } else {
+        1.
+    };
+    let shader = if progress < 0.5 {
+        blend
+            .from
+            .source(&state.base_shader, &shader_assets)
+            .flatten()
+            .map(|x| with_strength(&x, 1. - progress * 2.))
+    } else {
+        // Wait for the new shader to load before blending it in
+        let source = if let Some(source) = blend.to.source(&state.base_shader, &shader_assets) {
+            source
+        } else {
+            return;
+        };
+
+        source.map(|x| with_strength(&x, progress * 2. - 1.))
+    };
+
+    // Only update the camera if the shader changed so that it doesn't get re-compiled
+    if camera.custom_shader != shader {
+        camera.custom_shader = shader;
+    }
+
+    if blend.timer.finished() {
+        state.blend = None;
+    }
+}
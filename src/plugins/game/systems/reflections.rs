@@ -0,0 +1,223 @@
+use bevy::utils::{HashMap, HashSet};
+use bevy_retrograde::core::image::{DynamicImage, GenericImageView};
+
+use super::*;
+
+/// Component for the reflection of a character or enemy, which is a child of it
+pub struct Reflection;
+
+/// Resource holding faded copies of the images of reflected characters and enemies, by the
+/// original image
+#[derive(Default)]
+pub struct FadedImages {
+    /// The opacity the images were faded to
+    opacity: f32,
+    images: HashMap<Handle<Image>, Handle<Image>>,
+}
+
+/// Component for the reflective tiles in each level of a map
+pub struct MapReflectiveTiles(HashMap<String, Vec<ReflectiveLayer>>);
+
+/// The reflective tiles in a layer
+struct ReflectiveLayer {
+    /// The position of the layer in the world
+    offset: Vec2,
+    grid_size: f32,
+    /// The grid cells of the reflective tiles
+    tiles: HashSet<IVec2>,
+}
+
+/// Find the reflective tiles in the map
+pub fn load_reflective_tiles(
+    mut commands: Commands,
+    maps: Query<(Entity, &GameMap), Without<MapReflectiveTiles>>,
+    game_info: Option<Res<GameInfo>>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+
+    for (map_ent, map) in maps.iter() {
+        let mut levels = HashMap::default();
+        for level in &map.levels {
+            let mut layers = Vec::new();
+            for layer in &level.layers {
+                let grid_size = layer.grid_size.max(1) as f32;
+                let tiles = layer
+                    .tiles
+                    .iter()
+                    .filter(|tile| {
+                        map.tilesets
+                            .get(&tile.tileset)
+                            .and_then(|x| x.tile_metadata(tile.id, &game_info.tile_tags))
+                            .map_or(false, |x| x.reflective)
+                    })
+                    .map(|tile| (tile.position.as_f32() / grid_size).floor().as_i32())
+                    .collect::<HashSet<_>>();
+
+                if !tiles.is_empty() {
+                    layers.push(ReflectiveLayer {
+                        offset: level.position + layer.offset,
+                        grid_size,
+                        tiles,
+                    });
+                }
+            }
+            levels.insert(level.identifier.clone(), layers);
+        }
+
+        commands.entity(map_ent).insert(MapReflectiveTiles(levels));
+    }
+}
+
+/// Show flipped and faded reflections under the characters and enemies standing on reflective
+/// tiles in the current level, if the game has `reflections` settings
+#[allow(clippy::too_many_arguments)]
+pub fn update_reflections(
+    mut commands: Commands,
+    casters: Query<
+        (
+            Entity,
+            &Transform,
+            &Handle<Image>,
+            Option<&Handle<SpriteSheet>>,
+            &Sprite,
+            Option<&Children>,
+        ),
+        (
+            Or<(With<Handle<Character>>, With<Enemy>)>,
+            Without<Reflection>,
+        ),
+    >,
+    mut reflections: Query<
+        (
+            &mut Handle<Image>,
+            Option<&mut Handle<SpriteSheet>>,
+            &mut Sprite,
+            &mut Transform,
+            &mut Visible,
+        ),
+        With<Reflection>,
+    >,
+    maps: Query<&MapReflectiveTiles>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+    mut faded_images: ResMut<FadedImages>,
+    mut image_assets: ResMut<Assets<Image>>,
+    sprite_sheet_assets: Res<Assets<SpriteSheet>>,
+) {
+    let config = game_info.as_ref().and_then(|x| x.reflections.as_ref());
+    let layers = match (maps.single(), current_level, config) {
+        (Ok(tiles), Some(level), Some(_)) => tiles.0.get(&level.0),
+        _ => None,
+    };
+    let (config, layers) = if let (Some(config), Some(layers)) = (config, layers) {
+        (config, layers)
+    } else {
+        // Hide the reflections when they are turned off or there is no level
+        for (.., mut visible) in reflections.iter_mut() {
+            if visible.0 {
+                visible.0 = false;
+            }
+        }
+        return;
+    };
+
+    // Fade the images again if the opacity is changed by a hot reload
+    if (faded_images.opacity - config.opacity).abs() > f32::EPSILON {
+        faded_images.opacity = config.opacity;
+        faded_images.images.clear();
+    }
+
+    for (caster_ent, transform, image_handle, sprite_sheet, caster_sprite, children) in
+        casters.iter()
+    {
+        let reflection = children
+            .into_iter()
+            .flat_map(|x| x.iter())
+            .find(|x| reflections.get_mut(**x).is_ok());
+        let reflection = if let Some(&reflection) = reflection {
+            reflection
+        } else {
+            // Add a reflection that will be shown from the next frame
+            commands.entity(caster_ent).with_children(|caster| {
+                let mut reflection = caster.spawn_bundle(SpriteBundle {
+                    visible: Visible(false),
+                    ..Default::default()
+                });
+                reflection.insert(Reflection);
+                if let Some(sprite_sheet) = sprite_sheet {
+                    reflection.insert(sprite_sheet.clone());
+                }
+            });
+            continue;
+        };
+        let (mut image, reflection_sheet, mut sprite, mut reflection_transform, mut visible) =
+            reflections.get_mut(reflection).unwrap();
+
+        // Get the faded copy of the caster's image, making it if the image has loaded
+        let faded = if let Some(faded) = faded_images.images.get(image_handle) {
+            Some(faded.clone())
+        } else if let Some(original) = image_assets.get(image_handle) {
+            let mut pixels = original.to_rgba8();
+            for pixel in pixels.pixels_mut() {
+                pixel[3] = (pixel[3] as f32 * config.opacity.clamp(0., 1.)) as u8;
+            }
+            let mut faded = original.clone();
+            *faded = DynamicImage::ImageRgba8(pixels);
+            let faded = image_assets.add(faded);
+            faded_images
+                .images
+                .insert(image_handle.clone(), faded.clone());
+            Some(faded)
+        } else {
+            None
+        };
+
+        // Only show the reflection while the caster is on a reflective tile
+        let position = transform.translation.truncate();
+        let reflective = layers.iter().any(|layer| {
+            let cell = ((position - layer.offset) / layer.grid_size)
+                .floor()
+                .as_i32();
+            layer.tiles.contains(&cell)
+        });
+        let show = reflective && faded.is_some();
+        if visible.0 != show {
+            visible.0 = show;
+        }
+        let faded = if let (true, Some(faded)) = (show, faded) {
+            faded
+        } else {
+            continue;
+        };
+
+        // The sprite sheet asset holds the current frame, so sharing it keeps the reflection in
+        // sync with the caster's animation
+        if *image != faded {
+            *image = faded;
+        }
+        if let (Some(mut reflection_sheet), Some(sprite_sheet)) = (reflection_sheet, sprite_sheet) {
+            if *reflection_sheet != *sprite_sheet {
+                *reflection_sheet = sprite_sheet.clone();
+            }
+        }
+        if sprite.flip_x != caster_sprite.flip_x || !sprite.flip_y {
+            sprite.flip_x = caster_sprite.flip_x;
+            sprite.flip_y = true;
+        }
+
+        // Put the reflection right under the caster, so that they touch at the caster's feet
+        let height = sprite_sheet
+            .and_then(|x| sprite_sheet_assets.get(x))
+            .map(|x| x.grid_size.y as f32)
+            .or_else(|| image_assets.get(image_handle).map(|x| x.height() as f32))
+            .unwrap_or_default();
+        let translation = Vec3::new(config.offset.0, height + config.offset.1, -0.003);
+        if reflection_transform.translation != translation {
+            reflection_transform.translation = translation;
+        }
+    }
+}
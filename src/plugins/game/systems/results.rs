@@ -0,0 +1,81 @@
+use super::{game_time::GameTime, *};
+
+/// Resource for the player's stats for a level, counted from when they last entered it
+#[derive(Clone, Debug, Default)]
+pub struct LevelStats {
+    /// The level the stats are for
+    pub level: String,
+    /// How long the player has spent in the level, in seconds
+    pub time: f32,
+    pub enemies_defeated: u32,
+    pub secrets_found: u32,
+}
+
+/// Resource for the results screen being shown, with the stats of the completed level and the time
+/// left to show it
+#[derive(Default)]
+pub struct LevelResults(pub Option<(LevelStats, Timer)>);
+
+/// Count the player's stats for the current level, and complete the level when the player leaves
+/// it through a `goal` entrance
+#[allow(clippy::too_many_arguments)]
+pub fn track_level_stats(
+    mut stats: ResMut<LevelStats>,
+    mut collision_events: EventReader<Collision<Handle<Character>, Entrance>>,
+    mut defeated_events: EventReader<EnemyDefeatedEvent>,
+    mut secret_events: EventReader<SecretFoundEvent>,
+    mut completed_events: EventWriter<LevelCompletedEvent>,
+    entrances: Query<&Entrance>,
+    mut results: ResMut<LevelResults>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+    game_time: Res<GameTime>,
+) {
+    let (current_level, game_info) =
+        if let (Some(current_level), Some(game_info)) = (current_level, game_info) {
+            (current_level, game_info)
+        } else {
+            return;
+        };
+
+    stats.time += game_time.delta_seconds();
+    stats.enemies_defeated += defeated_events.iter().count() as u32;
+    stats.secrets_found += secret_events.iter().count() as u32;
+
+    // Complete the level before the stats are reset for the level the goal leads to
+    for event in collision_events.iter() {
+        let goal = entrances
+            .get(event.b)
+            .map_or(false, |x| x.goal && x.level == stats.level);
+        if !goal || !event.is_started() {
+            continue;
+        }
+
+        debug!(level = %stats.level, "Level completed");
+        completed_events.send(LevelCompletedEvent {
+            stats: stats.clone(),
+        });
+        if let Some(config) = &game_info.results {
+            results.0 = Some((stats.clone(), Timer::from_seconds(config.duration, false)));
+        }
+    }
+
+    // Start counting again when the player enters a level
+    if stats.level != current_level.0 {
+        *stats = LevelStats {
+            level: current_level.0.clone(),
+            ..Default::default()
+        };
+    }
+}
+
+/// Hide the results screen once it has been shown for long enough
+pub fn update_level_results(mut results: ResMut<LevelResults>, game_time: Res<GameTime>) {
+    let finished = match &mut results.0 {
+        Some((_, timer)) => timer.tick(game_time.delta()).finished(),
+        None => return,
+    };
+    if finished {
+        results.0 = None;
+    }
+}
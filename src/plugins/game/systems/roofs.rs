@@ -0,0 +1,143 @@
+use bevy_retrograde::core::image::DynamicImage;
+
+use super::{
+    palette::{recolor, LayerPalette},
+    *,
+};
+
+/// Component added to roof layers while they are faded out
+pub struct RoofFade {
+    /// The current opacity of the roof
+    opacity: f32,
+    /// The layer image from before the roof started fading
+    original: Image,
+    /// The palette that the layer had when the original image was taken
+    palette: Option<String>,
+}
+
+/// Fade out the roof layers in the current level that the player is standing underneath
+#[allow(clippy::too_many_arguments)]
+pub fn fade_roofs(
+    mut commands: Commands,
+    mut layers: Query<(
+        Entity,
+        &MapLayer,
+        &Handle<Image>,
+        Option<&mut RoofFade>,
+        Option<&LayerPalette>,
+    )>,
+    characters: Query<&Transform, With<Handle<Character>>>,
+    maps: Query<&GameMap>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+    asset_server: Res<AssetServer>,
+    palette_assets: Res<Assets<Palette>>,
+    mut image_assets: ResMut<Assets<Image>>,
+    time: Res<Time>,
+) {
+    let (map, current_level, game_info) = if let (Ok(map), Some(current_level), Some(game_info)) =
+        (maps.single(), current_level, game_info)
+    {
+        (map, current_level, game_info)
+    } else {
+        return;
+    };
+    let player_position = characters.single().ok().map(|x| x.translation.truncate());
+    let config = &game_info.roofs;
+
+    for (layer_ent, layer, image_handle, roof_fade, layer_palette) in layers.iter_mut() {
+        // Skip layers that aren't roofs
+        let level = if let Some(level) = map.level(&layer.level_identifier) {
+            level
+        } else {
+            continue;
+        };
+        let roof = if let Some(roof) = level
+            .layers
+            .iter()
+            .find(|x| x.roof && x.identifier == layer.identifier)
+        {
+            roof
+        } else {
+            continue;
+        };
+
+        // Fade the roof out if the player is under one of its tiles
+        let under_roof = layer.level_identifier == **current_level
+            && player_position
+                .map(|x| roof.has_tile_at(x - level.position - roof.offset))
+                .unwrap_or(false);
+        let target = if under_roof {
+            config.opacity.clamp(0., 1.)
+        } else {
+            1.
+        };
+
+        // Faded roofs have to be faded again from the new colors when their palette changes
+        let palette = layer_palette.and_then(|x| x.palette.clone());
+        let palette_changed = roof_fade.as_ref().map_or(false, |x| x.palette != palette);
+
+        let current = roof_fade.as_ref().map(|x| x.opacity).unwrap_or(1.);
+        if (current - target).abs() < f32::EPSILON && !palette_changed {
+            continue;
+        }
+
+        // Move the opacity towards the target
+        let step = if config.fade_duration > 0. {
+            time.delta_seconds() / config.fade_duration
+        } else {
+            1.
+        };
+        let opacity = if current < target {
+            (current + step).min(target)
+        } else {
+            (current - step).max(target)
+        };
+
+        let image = if let Some(image) = image_assets.get_mut(image_handle) {
+            image
+        } else {
+            continue;
+        };
+
+        // Get the roof image from before it started fading, saving it the first time we fade, and
+        // recoloring the layer's original image if its palette changed while it was faded
+        let original = match (&roof_fade, layer_palette) {
+            (Some(_), Some(layer_palette)) if palette_changed => {
+                let palette = palette
+                    .as_ref()
+                    .and_then(|path| palette_assets.get(asset_server.load_cached(path.as_str())));
+                Image(recolor(&layer_palette.original, palette))
+            }
+            (Some(roof_fade), _) => roof_fade.original.clone(),
+            (None, _) => image.clone(),
+        };
+
+        // Restore the original image when the roof is fully faded back in
+        if opacity >= 1. {
+            *image = original;
+            commands.entity(layer_ent).remove::<RoofFade>();
+            continue;
+        }
+
+        let mut faded = original.to_rgba8();
+        for pixel in faded.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * opacity) as u8;
+        }
+        **image = DynamicImage::ImageRgba8(faded);
+
+        if let Some(mut roof_fade) = roof_fade {
+            roof_fade.opacity = opacity;
+            if palette_changed {
+                roof_fade.original = original;
+                roof_fade.palette = palette;
+            }
+        } else {
+            commands.entity(layer_ent).insert(RoofFade {
+                opacity,
+                original,
+                palette,
+            });
+        }
+    }
+}
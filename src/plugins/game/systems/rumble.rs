@@ -0,0 +1,25 @@
+use super::*;
+
+/// Play the rumble requested through [`RumbleEvent`]s on the devices that support it
+///
+/// Rumble is only played on the web, where it vibrates the device for the rumble's duration if the
+/// browser supports it. The browser's vibration has no intensity. The engine doesn't read gamepads,
+/// so there is no controller to rumble on desktop, and rumble requests are ignored there.
+pub fn play_rumble(mut rumble_events: EventReader<RumbleEvent>, settings: Res<UserSettings>) {
+    // Stop the vibration immediately if rumble is turned off
+    #[cfg(wasm)]
+    {
+        if settings.is_changed() && !settings.rumble {
+            crate::wasm_utils::vibrate(0);
+        }
+    }
+
+    for event in rumble_events.iter() {
+        if !settings.rumble || event.intensity <= 0. || event.duration <= 0. {
+            continue;
+        }
+
+        #[cfg(wasm)]
+        crate::wasm_utils::vibrate((event.duration * 1000.) as u32);
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::tasks::IoTaskPool;
+
+use super::{attract_mode::AttractMode, game_time::GameTime, *};
+
+/// The prefix of prop interactions that are picked up for points, followed by the number of
+/// points, i.e. `score:50`
+const SCORE_INTERACTION_PREFIX: &str = "score:";
+
+/// Give the player points for defeating enemies, finding secrets, and picking up `score:` props,
+/// and run out their combo when they don't score for a while
+pub fn score_points(
+    mut commands: Commands,
+    mut defeated_events: EventReader<EnemyDefeatedEvent>,
+    mut secret_events: EventReader<SecretFoundEvent>,
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    mut score: ResMut<Score>,
+    game_info: Option<Res<GameInfo>>,
+    game_time: Res<GameTime>,
+) {
+    let config = if let Some(config) = game_info.as_ref().and_then(|x| x.score.as_ref()) {
+        config
+    } else {
+        return;
+    };
+
+    for _ in defeated_events.iter() {
+        score.add(config.enemy_points, config);
+    }
+    for _ in secret_events.iter() {
+        score.add(config.secret_points, config);
+    }
+    for event in interaction_events.iter() {
+        let points = if let Some(points) = event
+            .interaction
+            .strip_prefix(SCORE_INTERACTION_PREFIX)
+            .and_then(|x| x.parse().ok())
+        {
+            points
+        } else {
+            continue;
+        };
+
+        score.add(points, config);
+        commands.entity(event.prop).despawn_recursive();
+    }
+
+    // Avoid triggering change detection when there is no combo to run out
+    if score.combo > 0 {
+        score.tick(game_time.delta_seconds());
+    }
+}
+
+/// Add the player's score to the high score table when the game is over, and send it to the
+/// online leaderboard
+pub fn record_high_score(
+    score: Res<Score>,
+    mut high_scores: ResMut<HighScores>,
+    best_times: Res<BestTimes>,
+    global_leaderboard: Res<GlobalLeaderboard>,
+    game_info: Option<Res<GameInfo>>,
+    attract_mode: Res<AttractMode>,
+    task_pool: Res<IoTaskPool>,
+) {
+    // The attract mode demo doesn't get a high score
+    if attract_mode.active {
+        return;
+    }
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+
+    if let Some(config) = &game_info.score {
+        high_scores.record(score.points, config.high_scores);
+        if score.points > 0 {
+            global_leaderboard.submit(&task_pool, &game_info, score.points, &best_times);
+        }
+    }
+}
+
+/// Keep the player's fastest time for each level they complete
+pub fn record_best_times(
+    mut completed_events: EventReader<LevelCompletedEvent>,
+    mut best_times: ResMut<BestTimes>,
+    attract_mode: Res<AttractMode>,
+) {
+    for event in completed_events.iter() {
+        // The attract mode demo doesn't set best times
+        if attract_mode.active {
+            continue;
+        }
+        if best_times.record(&event.stats.level, event.stats.time) {
+            debug!(level = %event.stats.level, time = event.stats.time, "New best time");
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use bevy_retrograde::core::image::{DynamicImage, Rgba};
+
+use super::{palette::LayerPalette, *};
+
+/// Component added to the collision shapes of secret wall tiles
+pub struct SecretWall {
+    /// The identifier of the level the tile is in
+    pub level: String,
+    /// The identifier of the layer the tile is in
+    pub layer: String,
+    /// The position of the top-left corner of the tile relative to its layer
+    pub tile: IVec2,
+    /// The size of the tile in pixels
+    pub size: f32,
+}
+
+/// Break the secret walls that are hit hard enough by a [`WallImpactEvent`], erasing their tiles
+/// from the map, and play the hint sound for the ones that are hit too softly
+pub fn break_secret_walls(
+    mut commands: Commands,
+    mut impacts: EventReader<WallImpactEvent>,
+    walls: Query<(Entity, &SecretWall, &GlobalTransform)>,
+    mut layers: Query<(&MapLayer, &Handle<Image>, Option<&mut LayerPalette>)>,
+    mut image_assets: ResMut<Assets<Image>>,
+    game_info: Option<Res<GameInfo>>,
+    asset_server: Res<AssetServer>,
+    mut sound_controller: SoundController,
+    mut sound_events: EventWriter<SoundPlayedEvent>,
+    mut secret_events: EventWriter<SecretFoundEvent>,
+    engine_config: Res<crate::EngineConfig>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+    let config = &game_info.secret_walls;
+
+    let mut broken = HashSet::default();
+    let mut hinted = false;
+    for impact in impacts.iter() {
+        let already_broken = broken.len();
+        for (wall_ent, wall, transform) in walls.iter() {
+            if broken.contains(&wall_ent) {
+                continue;
+            }
+
+            // Get the distance from the impact to the closest point of the tile
+            let offset = (impact.position - transform.translation.truncate()).abs();
+            let distance = (offset - Vec2::splat(wall.size / 2.))
+                .max(Vec2::ZERO)
+                .length();
+            if distance > impact.radius {
+                continue;
+            }
+
+            if impact.damage < config.min_damage {
+                hinted = true;
+                continue;
+            }
+
+            broken.insert(wall_ent);
+            commands.entity(wall_ent).despawn_recursive();
+
+            // Erase the tile from its layer, including the layer's un-recolored image so that the
+            // tile doesn't come back when the palette changes
+            for (layer, image_handle, palette) in layers.iter_mut() {
+                if layer.level_identifier != wall.level || layer.identifier != wall.layer {
+                    continue;
+                }
+                if let Some(image) = image_assets.get_mut(image_handle) {
+                    erase_tile(image, wall);
+                }
+                if let Some(mut palette) = palette {
+                    erase_tile(&mut palette.original, wall);
+                }
+            }
+        }
+
+        // A secret wall is usually made of several tiles, so the tiles broken by one hit count as
+        // one secret
+        if broken.len() > already_broken {
+            secret_events.send(SecretFoundEvent {
+                position: impact.position,
+            });
+        }
+    }
+
+    if engine_config.mute {
+        return;
+    }
+    let sound = if !broken.is_empty() {
+        &config.break_sound
+    } else if hinted {
+        &config.hint_sound
+    } else {
+        return;
+    };
+    if let Some(sound) = sound {
+        let sound_data = asset_server.load_cached(sound.as_str());
+        let sound_handle = sound_controller.create_sound(&sound_data);
+        sound_controller.play_sound(sound_handle);
+        sound_events.send(SoundPlayedEvent {
+            sound: sound.clone(),
+        });
+    }
+}
+
+/// Make the pixels of a secret wall's tile transparent in a layer image
+fn erase_tile(image: &mut Image, wall: &SecretWall) {
+    let mut pixels = image.to_rgba8();
+    let size = wall.size as u32;
+    let (left, top) = (wall.tile.x.max(0) as u32, wall.tile.y.max(0) as u32);
+    for x in left..(left + size).min(pixels.width()) {
+        for y in top..(top + size).min(pixels.height()) {
+            pixels.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+        }
+    }
+    **image = DynamicImage::ImageRgba8(pixels);
+}
@@ -0,0 +1,59 @@
+use super::*;
+
+/// Component for the blob shadow drawn under a character or enemy, which is a child of it
+pub struct Shadow;
+
+/// Marker for characters and enemies that have had a [`Shadow`] added
+pub struct Shadowed;
+
+/// Add blob shadows under characters and enemies if the game has `shadows` settings, and remove
+/// them if the settings are taken away
+pub fn add_shadows(
+    mut commands: Commands,
+    casters: Query<
+        Entity,
+        (
+            Or<(With<Handle<Character>>, With<Enemy>)>,
+            Without<Shadowed>,
+        ),
+    >,
+    shadowed: Query<Entity, With<Shadowed>>,
+    shadows: Query<Entity, With<Shadow>>,
+    game_info: Option<Res<GameInfo>>,
+    asset_server: Res<AssetServer>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+
+    let config = if let Some(config) = &game_info.shadows {
+        config
+    } else {
+        // Remove the shadows when the game info is hot reloaded without them
+        for shadow in shadows.iter() {
+            commands.entity(shadow).despawn_recursive();
+        }
+        for caster in shadowed.iter() {
+            commands.entity(caster).remove::<Shadowed>();
+        }
+        return;
+    };
+
+    for caster in casters.iter() {
+        commands
+            .entity(caster)
+            .insert(Shadowed)
+            .with_children(|caster| {
+                caster
+                    .spawn_bundle(SpriteBundle {
+                        image: asset_server.load(config.sprite.as_str()),
+                        // Draw the shadow just behind the caster
+                        transform: Transform::from_xyz(config.offset.0, config.offset.1, -0.002),
+                        ..Default::default()
+                    })
+                    .insert(Shadow);
+            });
+    }
+}
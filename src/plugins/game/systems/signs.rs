@@ -0,0 +1,41 @@
+use super::*;
+
+/// The prefix of prop interactions that show a sign, followed by the sign's rich text, i.e.
+/// `sign:Welcome to {player-name}'s house`
+const SIGN_INTERACTION_PREFIX: &str = "sign:";
+
+/// The id of the sign text in the [`ui_utils::Typewriters`]
+pub const SIGN_TEXT_ID: &str = "sign";
+
+/// Resource for the sign the player is reading, shown in the pause menu
+#[derive(Default)]
+pub struct Sign {
+    /// Whether the pause menu should open on the sign page, which is set when the player interacts
+    /// with a sign
+    pub open_requested: bool,
+}
+
+/// Pause the game and open the sign page when the player interacts with a sign
+pub fn read_signs(
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    mut state: ResMut<State<GameState>>,
+    mut physics_time: ResMut<PhysicsTime>,
+    mut sign: ResMut<Sign>,
+    mut typewriters: ResMut<ui_utils::Typewriters>,
+    game_info: Res<GameInfo>,
+) {
+    for event in interaction_events.iter() {
+        let text = if let Some(text) = event.interaction.strip_prefix(SIGN_INTERACTION_PREFIX) {
+            text
+        } else {
+            continue;
+        };
+
+        // The game may already be pausing this frame
+        if state.push(GameState::Paused).is_ok() {
+            physics_time.pause();
+        }
+        sign.open_requested = true;
+        typewriters.start(SIGN_TEXT_ID, text, game_info.ui_theme.text_sound.clone());
+    }
+}
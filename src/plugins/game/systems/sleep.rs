@@ -0,0 +1,85 @@
+use super::{gameplay::Health, map_loading::MapEnemiesLoaded, transition::ScreenFade, *};
+
+/// The interaction of props that the player can sleep in, i.e. beds
+const SLEEP_INTERACTION: &str = "sleep";
+
+/// Resource for whether the player has gone to sleep and is waiting for the screen to fade out
+#[derive(Default)]
+pub struct Sleeping(pub bool);
+
+/// Fade the screen out when the player interacts with a bed
+pub fn use_beds(
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    mut sleeping: ResMut<Sleeping>,
+    mut screen_fade: ResMut<ScreenFade>,
+) {
+    for event in interaction_events.iter() {
+        if event.interaction == SLEEP_INTERACTION && !sleeping.0 {
+            sleeping.0 = true;
+            screen_fade.fade_out();
+        }
+    }
+}
+
+/// Once the screen has faded out, skip to the next morning, restore the player's health, save the
+/// game, and fade back in
+pub fn finish_sleeping(
+    mut sleeping: ResMut<Sleeping>,
+    mut screen_fade: ResMut<ScreenFade>,
+    mut clock: ResMut<GameClock>,
+    mut characters: Query<&mut Health, With<Handle<Character>>>,
+    mut save_request: ResMut<SaveRequest>,
+    mut day_passed_events: EventWriter<DayPassedEvent>,
+    game_info: Res<GameInfo>,
+) {
+    if !sleeping.0 || !screen_fade.is_black() {
+        return;
+    }
+
+    let day = clock.day;
+    clock.skip_to(game_info.clock.start_hour);
+    if clock.day > day {
+        day_passed_events.send(DayPassedEvent { day: clock.day });
+    }
+    for mut health in characters.iter_mut() {
+        health.current = health.max;
+    }
+    save_request.0 = true;
+
+    sleeping.0 = false;
+    screen_fade.fade_in();
+}
+
+/// Bring back killed enemies that respawn when a day passes
+///
+/// All of the map enemies are spawned again so that the ones that are still alive are back where
+/// they started too.
+pub fn respawn_enemies(
+    mut commands: Commands,
+    mut day_passed_events: EventReader<DayPassedEvent>,
+    enemies: Query<Entity, (With<Enemy>, With<PersistentId>)>,
+    maps: Query<Entity, With<GameMap>>,
+    mut level_states: ResMut<LevelStates>,
+) {
+    if day_passed_events.iter().count() == 0 {
+        return;
+    }
+
+    for state in level_states
+        .0
+        .values_mut()
+        .flat_map(|x| x.entities.values_mut())
+    {
+        if state.removed && state.respawns {
+            state.removed = false;
+            state.respawns = false;
+        }
+    }
+
+    for ent in enemies.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+    for map in maps.iter() {
+        commands.entity(map).remove::<MapEnemiesLoaded>();
+    }
+}
@@ -0,0 +1,83 @@
+use bevy::utils::HashMap;
+
+use super::*;
+
+/// The size of the cells that the [`EnemySpatialHash`] buckets enemies into, in pixels
+const CELL_SIZE: f32 = 64.;
+
+/// Resource that buckets the awake enemies in the current level by position, so that systems
+/// looking for the enemies near a point don't have to check every enemy
+#[derive(Default)]
+pub struct EnemySpatialHash {
+    cells: HashMap<IVec2, Vec<(Entity, Vec2)>>,
+}
+
+impl EnemySpatialHash {
+    /// Get the cell that a position is in
+    fn cell(position: Vec2) -> IVec2 {
+        (position / CELL_SIZE).floor().as_i32()
+    }
+
+    /// Get the enemies within the given radius of a position
+    pub fn within(&self, position: Vec2, radius: f32) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        let min = Self::cell(position - Vec2::splat(radius));
+        let max = Self::cell(position + Vec2::splat(radius));
+
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(move |cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(move |(_, pos)| pos.distance(position) <= radius)
+    }
+
+    /// Get the closest enemy to a position that is within the given radius
+    pub fn nearest(&self, position: Vec2, radius: f32) -> Option<(Entity, Vec2)> {
+        self.within(position, radius)
+            .map(|(ent, pos)| (ent, pos, pos.distance(position)))
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
+            .map(|(ent, pos, _)| (ent, pos))
+    }
+
+    /// Get every enemy in the hash
+    pub fn enemies(&self) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        self.cells.values().flatten().copied()
+    }
+}
+
+/// Re-build the [`EnemySpatialHash`] from the enemies that are awake in the current level, at the
+/// start of each simulation step
+pub fn update_enemy_spatial_hash(
+    mut spatial_hash: ResMut<EnemySpatialHash>,
+    enemies: Query<(Entity, &Transform, &Enemy), Without<Dormant>>,
+    current_level: Option<Res<CurrentLevel>>,
+) {
+    let current_level = if let Some(level) = current_level {
+        level
+    } else {
+        spatial_hash.cells.clear();
+        return;
+    };
+
+    // Keep the cells' lists around so that they don't have to be allocated again, unless the level
+    // changed and the enemies will be in different cells
+    if current_level.is_changed() {
+        spatial_hash.cells.clear();
+    } else {
+        for entities in spatial_hash.cells.values_mut() {
+            entities.clear();
+        }
+    }
+
+    for (ent, transform, enemy) in enemies.iter() {
+        if enemy.level != current_level.0 {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        spatial_hash
+            .cells
+            .entry(EnemySpatialHash::cell(position))
+            .or_default()
+            .push((ent, position));
+    }
+}
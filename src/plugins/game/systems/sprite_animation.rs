@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use super::{game_time::GameTime, *};
+
+/// The length of animation frames that don't have their own duration
+const DEFAULT_FRAME_DURATION_MS: u32 = 100;
+
+/// Play the [`SpriteAnimation`] clips on animated sprites
+pub fn update_sprite_animations(
+    mut query: Query<
+        (
+            Entity,
+            &mut SpriteAnimation,
+            &mut Handle<Image>,
+            &mut Handle<SpriteSheet>,
+            &mut Sprite,
+        ),
+        Without<Dormant>,
+    >,
+    animation_assets: Res<Assets<SpriteAnimations>>,
+    mut sprite_sheet_assets: ResMut<Assets<SpriteSheet>>,
+    mut animation_events: EventWriter<AnimationEvent>,
+    mut finished_events: EventWriter<SpriteAnimationFinishedEvent>,
+    game_time: Res<GameTime>,
+    timings: Res<SystemTimings>,
+) {
+    let _span = info_span!("update_sprite_animations").entered();
+    let _timer = timings.start("update_sprite_animations");
+
+    for (entity, mut animation, mut image_handle, mut sprite_sheet_handle, mut sprite) in
+        query.iter_mut()
+    {
+        let animations = if let Some(animations) = animation_assets.get(&animation.animations) {
+            animations
+        } else {
+            continue;
+        };
+
+        // Use the animation's sprite image, only setting it when it changes to avoid triggering
+        // change detection
+        if *image_handle != animations.sprite_image {
+            *image_handle = animations.sprite_image.clone();
+        }
+
+        // Give the entity its own copy of the animation's sprite sheet the first time it is
+        // loaded, so that showing the entity's frames doesn't change the frames of every other
+        // entity playing the same animations
+        if animation.sheet_source.as_ref() != Some(&animation.animations) {
+            let sprite_sheet =
+                if let Some(sprite_sheet) = sprite_sheet_assets.get(&animations.sprite_sheet) {
+                    SpriteSheet {
+                        grid_size: sprite_sheet.grid_size,
+                        tile_index: sprite_sheet.tile_index,
+                    }
+                } else {
+                    continue;
+                };
+            *sprite_sheet_handle = sprite_sheet_assets.add(sprite_sheet);
+            animation.sheet_source = Some(animation.animations.clone());
+            animation.frame_pending = true;
+        }
+
+        if animation.finished {
+            continue;
+        }
+
+        let clip = if let Some(clip) = animations.clips.get(&animation.clip) {
+            clip
+        } else {
+            continue;
+        };
+
+        // Move to the next frame when the current one is done
+        if !animation.frame_pending {
+            let delta = game_time.delta().mul_f32(animation.speed.max(0.));
+            animation.timer.tick(delta);
+
+            if !animation.timer.finished() {
+                continue;
+            }
+
+            if animation.frame + 1 < clip.frames.len() {
+                animation.frame += 1;
+            } else if clip.looping && !animation.one_shot {
+                animation.frame = 0;
+            } else {
+                // Stay on the last frame
+                animation.finished = true;
+                finished_events.send(SpriteAnimationFinishedEvent {
+                    entity,
+                    clip: animation.clip.clone(),
+                });
+                continue;
+            }
+        }
+        animation.frame_pending = false;
+
+        // Show the frame
+        let frame = animation.frame % clip.frames.len();
+        if let Some(sprite_sheet) = sprite_sheet_assets.get_mut(&*sprite_sheet_handle) {
+            sprite_sheet.tile_index = clip.frames[frame];
+        }
+        if sprite.flip_x != clip.flip {
+            sprite.flip_x = clip.flip;
+        }
+
+        // Show the frame for as long as the clip says to
+        let duration = clip
+            .durations
+            .get(frame)
+            .copied()
+            .unwrap_or(DEFAULT_FRAME_DURATION_MS);
+        animation
+            .timer
+            .set_duration(Duration::from_millis(duration as u64));
+        animation.timer.reset();
+
+        // Send the events for the frame
+        for event in clip.events.get(&(frame as u32)).into_iter().flatten() {
+            animation_events.send(AnimationEvent {
+                entity,
+                event: event.clone(),
+            });
+        }
+    }
+}
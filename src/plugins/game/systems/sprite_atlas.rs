@@ -0,0 +1,201 @@
+use bevy::utils::HashMap;
+use bevy_retrograde::core::image::{imageops, DynamicImage, GenericImageView, RgbaImage};
+
+use super::{cursor::CustomCursor, *};
+
+/// Component for sprites that are drawn from a sprite atlas instead of their own image
+pub struct AtlasSprite {
+    /// The atlas image the sprite is drawn from
+    atlas: Handle<Image>,
+}
+
+/// Resource for the atlases that small sprites are packed into
+///
+/// Sprites are packed into a grid with other sprites of the same size, so that they can be drawn
+/// from the atlas with a [`SpriteSheet`].
+#[derive(Default)]
+pub struct SpriteAtlases {
+    /// The atlases for each sprite size, the last of which is the one being filled
+    atlases: HashMap<(u32, u32), Vec<SpriteAtlas>>,
+    /// Where each packed image is, by its original image
+    slots: HashMap<Handle<Image>, AtlasSlot>,
+}
+
+struct SpriteAtlas {
+    image: Handle<Image>,
+    /// The number of sprites in the atlas
+    len: u32,
+}
+
+#[derive(Clone)]
+struct AtlasSlot {
+    atlas: Handle<Image>,
+    sprite_sheet: Handle<SpriteSheet>,
+    /// The position of the top-left corner of the sprite in the atlas
+    position: (u32, u32),
+    size: (u32, u32),
+}
+
+impl SpriteAtlases {
+    /// Copy a loaded image into the next free spot of the atlas for its size, starting a new atlas
+    /// if that one is full
+    fn pack(
+        &mut self,
+        image_handle: &Handle<Image>,
+        atlas_size: u32,
+        image_assets: &mut Assets<Image>,
+        sprite_sheet_assets: &mut Assets<SpriteSheet>,
+    ) -> AtlasSlot {
+        let sprite = image_assets.get(image_handle).unwrap().to_rgba8();
+        let (width, height) = sprite.dimensions();
+        let columns = atlas_size / width;
+        let capacity = columns * (atlas_size / height);
+
+        let atlases = self.atlases.entry((width, height)).or_default();
+        if atlases.last().map_or(true, |x| x.len >= capacity) {
+            atlases.push(SpriteAtlas {
+                image: image_assets.add(Image(DynamicImage::ImageRgba8(RgbaImage::new(
+                    columns * width,
+                    height,
+                )))),
+                len: 0,
+            });
+        }
+        let atlas = atlases.last_mut().unwrap();
+        let index = atlas.len;
+        atlas.len += 1;
+        let position = ((index % columns) * width, (index / columns) * height);
+
+        // Add a row to the atlas when the sprite doesn't fit
+        let atlas_image = image_assets.get_mut(&atlas.image).unwrap();
+        if position.1 + height > atlas_image.height() {
+            let mut grown = RgbaImage::new(atlas_image.width(), position.1 + height);
+            imageops::replace(&mut grown, &atlas_image.to_rgba8(), 0, 0);
+            **atlas_image = DynamicImage::ImageRgba8(grown);
+        }
+        imageops::replace(
+            atlas_image.as_mut_rgba8().unwrap(),
+            &sprite,
+            position.0,
+            position.1,
+        );
+
+        let slot = AtlasSlot {
+            atlas: atlas.image.clone(),
+            sprite_sheet: sprite_sheet_assets.add(SpriteSheet {
+                grid_size: UVec2::new(width, height),
+                tile_index: index,
+            }),
+            position,
+            size: (width, height),
+        };
+        self.slots.insert(image_handle.clone(), slot.clone());
+
+        slot
+    }
+}
+
+/// Draw small sprites that don't use a sprite sheet from a shared atlas, if enabled in the
+/// rendering settings
+///
+/// Map layers and the custom cursor are left alone, because they are large or change their image
+/// every frame.
+pub fn pack_sprite_atlases(
+    mut commands: Commands,
+    mut sprites: Query<
+        (Entity, &mut Handle<Image>),
+        (
+            Without<Handle<SpriteSheet>>,
+            Without<AtlasSprite>,
+            Without<MapLayer>,
+            Without<CustomCursor>,
+        ),
+    >,
+    mut atlases: ResMut<SpriteAtlases>,
+    mut image_assets: ResMut<Assets<Image>>,
+    mut sprite_sheet_assets: ResMut<Assets<SpriteSheet>>,
+    game_info: Option<Res<GameInfo>>,
+) {
+    let config = match &game_info {
+        Some(game_info) if game_info.rendering.pack_sprite_atlases => &game_info.rendering,
+        _ => return,
+    };
+    let max_sprite_size = config.atlas_max_sprite_size.min(config.atlas_size);
+
+    for (ent, mut image_handle) in sprites.iter_mut() {
+        let slot = match atlases.slots.get(&*image_handle) {
+            Some(slot) => slot.clone(),
+            None => {
+                // Wait for the image to load, and skip images that are too big to pack
+                match image_assets.get(&*image_handle).map(|x| x.dimensions()) {
+                    Some((width, height))
+                        if width > 0 && height > 0 && width.max(height) <= max_sprite_size => {}
+                    _ => continue,
+                }
+                atlases.pack(
+                    &image_handle,
+                    config.atlas_size,
+                    &mut image_assets,
+                    &mut sprite_sheet_assets,
+                )
+            }
+        };
+
+        *image_handle = slot.atlas.clone();
+        commands
+            .entity(ent)
+            .insert(slot.sprite_sheet)
+            .insert(AtlasSprite { atlas: slot.atlas });
+    }
+}
+
+/// Stop drawing sprites from their atlas when something else changes their image, so that the new
+/// image can be packed
+pub fn unpack_changed_sprites(
+    mut commands: Commands,
+    sprites: Query<(Entity, &Handle<Image>, &AtlasSprite), Changed<Handle<Image>>>,
+) {
+    for (ent, image_handle, atlas_sprite) in sprites.iter() {
+        if *image_handle != atlas_sprite.atlas {
+            commands
+                .entity(ent)
+                .remove::<AtlasSprite>()
+                .remove::<Handle<SpriteSheet>>();
+        }
+    }
+}
+
+/// Copy packed images into their atlases again when they are hot reloaded
+pub fn reload_atlas_sprites(
+    mut image_events: EventReader<AssetEvent<Image>>,
+    atlases: Res<SpriteAtlases>,
+    mut image_assets: ResMut<Assets<Image>>,
+) {
+    for event in image_events.iter() {
+        let (slot, handle) = match event {
+            AssetEvent::Modified { handle } => match atlases.slots.get(handle) {
+                Some(slot) => (slot, handle),
+                None => continue,
+            },
+            _ => continue,
+        };
+        let sprite = if let Some(image) = image_assets.get(handle) {
+            image.to_rgba8()
+        } else {
+            continue;
+        };
+        if sprite.dimensions() != slot.size {
+            warn!("Reloaded sprite changed size and won't be updated until the game restarts");
+            continue;
+        }
+
+        if let Some(atlas) = image_assets.get_mut(&slot.atlas) {
+            imageops::replace(
+                atlas.as_mut_rgba8().unwrap(),
+                &sprite,
+                slot.position.0,
+                slot.position.1,
+            );
+        }
+    }
+}
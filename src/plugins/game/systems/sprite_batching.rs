@@ -0,0 +1,194 @@
+use bevy::utils::HashSet;
+use bevy_retrograde::core::image::{imageops, DynamicImage, GenericImageView};
+
+use super::{palette::LayerPalette, *};
+
+/// Component added to maps with the levels that have had their static layers merged
+#[derive(Default)]
+pub struct MergedLayerLevels(HashSet<String>);
+
+/// Log how many sprites and textures the current level draws whenever it changes, if the sprite
+/// audit is enabled in the debug rendering options
+///
+/// Every sprite is its own draw call, so the sprite count is the number of draws for the level.
+pub fn audit_level_sprites(
+    sprites: Query<(
+        &Handle<Image>,
+        Option<&MapLayer>,
+        Option<&Enemy>,
+        Option<&Prop>,
+    )>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Res<GameInfo>,
+) {
+    let current_level = match current_level {
+        Some(level) if level.is_changed() && game_info.debug_rendering.sprite_audit => level,
+        _ => return,
+    };
+
+    let (mut layers, mut enemies, mut props) = (0, 0, 0);
+    let mut textures = HashSet::default();
+    for (image, layer, enemy, prop) in sprites.iter() {
+        if layer.map_or(false, |x| x.level_identifier == current_level.0) {
+            layers += 1;
+        } else if enemy.map_or(false, |x| x.level == current_level.0) {
+            enemies += 1;
+        } else if prop.map_or(false, |x| x.level == current_level.0) {
+            props += 1;
+        } else {
+            continue;
+        }
+        textures.insert(image.clone());
+    }
+
+    info!(
+        level = %current_level.0,
+        sprites = layers + enemies + props,
+        layers,
+        enemies,
+        props,
+        textures = textures.len(),
+        "Level sprite audit"
+    );
+}
+
+/// Merge the layers of the current level that nothing is drawn between into a single sprite, if
+/// enabled in the rendering settings
+///
+/// Roof layers, layers with animated tiles, recolored layers, and hidden layers are left alone,
+/// because they change after the level has loaded.
+pub fn merge_static_layers(
+    mut commands: Commands,
+    mut maps: Query<(Entity, &GameMap, Option<&mut MergedLayerLevels>)>,
+    layers: Query<(
+        Entity,
+        &MapLayer,
+        &Handle<Image>,
+        &Transform,
+        Option<&LayerPalette>,
+    )>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+    mut image_assets: ResMut<Assets<Image>>,
+) {
+    let (current_level, game_info) = match (current_level, game_info) {
+        (Some(level), Some(game_info)) if game_info.rendering.merge_static_layers => {
+            (level, game_info)
+        }
+        _ => return,
+    };
+    let (map_ent, map, merged) = if let Ok(map) = maps.single_mut() {
+        map
+    } else {
+        return;
+    };
+    if merged
+        .as_ref()
+        .map_or(false, |x| x.0.contains(&current_level.0))
+    {
+        return;
+    }
+    let level = if let Some(level) = map.level(&current_level.0) {
+        level
+    } else {
+        return;
+    };
+
+    // Wait for all of the level's layers to load
+    let mut level_layers = layers
+        .iter()
+        .filter(|(_, layer, ..)| layer.level_identifier == current_level.0)
+        .collect::<Vec<_>>();
+    if level_layers.is_empty()
+        || level_layers
+            .iter()
+            .any(|(_, _, image, ..)| image_assets.get(*image).is_none())
+    {
+        return;
+    }
+    level_layers.sort_by(|a, b| a.3.translation.z.partial_cmp(&b.3.translation.z).unwrap());
+
+    // Whether a layer can't change after it loads
+    let is_static = |(_, layer, _, _, palette): &(_, &MapLayer, _, _, Option<&LayerPalette>)| {
+        if palette.is_some() {
+            return false;
+        }
+        let game_layer = if let Some(game_layer) = level
+            .layers
+            .iter()
+            .find(|x| x.identifier == layer.identifier)
+        {
+            game_layer
+        } else {
+            return false;
+        };
+        // Animated tiles and secret walls change the layer image
+        let changes = game_layer.tiles.iter().any(|tile| {
+            map.tilesets
+                .get(&tile.tileset)
+                .and_then(|x| x.tile_metadata(tile.id, &game_info.tile_tags))
+                .map_or(false, |x| x.animation.is_some() || x.secret)
+        });
+
+        layer.visible && !game_layer.roof && !changes
+    };
+
+    // Whether anything in the level, like an enemy or a prop, is drawn between two z positions
+    let band = game_info.y_sort.band.max(0.);
+    let drawn_between = |bottom: f32, top: f32| {
+        level
+            .entities
+            .iter()
+            .any(|x| x.z < top && x.z + band >= bottom)
+    };
+
+    // Group the layers that can be drawn as one
+    let mut groups: Vec<Vec<_>> = Vec::new();
+    for layer in level_layers {
+        let mergeable = match groups.last().and_then(|x| x.last()) {
+            Some(previous) => {
+                is_static(previous)
+                    && is_static(&layer)
+                    && previous.3.translation.truncate() == layer.3.translation.truncate()
+                    && image_assets.get(previous.2).map(|x| x.dimensions())
+                        == image_assets.get(layer.2).map(|x| x.dimensions())
+                    && !drawn_between(previous.3.translation.z, layer.3.translation.z)
+            }
+            None => false,
+        };
+        if mergeable {
+            groups.last_mut().unwrap().push(layer);
+        } else {
+            groups.push(vec![layer]);
+        }
+    }
+
+    // Draw each group's layers onto its bottom layer and remove the rest
+    let mut merged_count = 0;
+    for group in groups.into_iter().filter(|x| x.len() > 1) {
+        let mut merged_image = image_assets.get(group[0].2).unwrap().to_rgba8();
+        for (layer_ent, _, image, ..) in &group[1..] {
+            imageops::overlay(
+                &mut merged_image,
+                &image_assets.get(*image).unwrap().to_rgba8(),
+                0,
+                0,
+            );
+            commands.entity(*layer_ent).despawn();
+            merged_count += 1;
+        }
+        **image_assets.get_mut(group[0].2).unwrap() = DynamicImage::ImageRgba8(merged_image);
+    }
+    debug!(level = %current_level.0, merged_count, "Merged static map layers");
+
+    match merged {
+        Some(mut merged) => {
+            merged.0.insert(current_level.0.clone());
+        }
+        None => {
+            let mut merged = MergedLayerLevels::default();
+            merged.0.insert(current_level.0.clone());
+            commands.entity(map_ent).insert(merged);
+        }
+    }
+}
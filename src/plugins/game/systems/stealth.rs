@@ -0,0 +1,264 @@
+use bevy_retrograde::physics::heron::rapier_plugin::PhysicsWorld;
+
+use super::{game_time::GameTime, *};
+
+/// How much of the way to being alerted an enemy gets each time it hears a noise
+const NOISE_SUSPICION: f32 = 0.5;
+
+/// The number of line segments used to draw the arc of a vision cone
+const VISION_CONE_SEGMENTS: usize = 8;
+
+/// Marker for the debug rendering of an enemy's vision cone
+pub struct VisionConeDebugViz;
+
+/// Send noise events when the player takes a step, uses an ability, or interacts with a prop that
+/// makes a noise
+///
+/// Footsteps are louder when the player is moving faster than they walk, i.e. while dashing or
+/// running on a fast tile.
+pub fn emit_player_noise(
+    mut animation_events: EventReader<AnimationEvent>,
+    mut control_events: EventReader<ControlEvent>,
+    mut interaction_events: EventReader<PropInteractionEvent>,
+    characters: Query<(&Transform, &Velocity, &Handle<Character>)>,
+    props: Query<(&Transform, &PropNoise)>,
+    character_assets: Res<Assets<Character>>,
+    mut noise_events: EventWriter<NoiseEvent>,
+    game_info: Res<GameInfo>,
+) {
+    let stealth = &game_info.stealth;
+
+    for event in animation_events.iter() {
+        if event.event != "footstep" {
+            continue;
+        }
+        if let Ok((transform, velocity, character)) = characters.get(event.entity) {
+            let walk_speed = character_assets.get(character).map_or(0., |x| x.walk_speed);
+            let loudness = if walk_speed > 0. {
+                (velocity.linear.truncate().length() / walk_speed).max(1.)
+            } else {
+                1.
+            };
+            noise_events.send(NoiseEvent {
+                position: transform.translation.truncate(),
+                radius: stealth.footstep_noise * loudness,
+            });
+        }
+    }
+
+    let used_ability = control_events
+        .iter()
+        .any(|x| matches!(x, ControlEvent::Ability(_) | ControlEvent::Attack));
+    if let (true, Ok((transform, ..))) = (used_ability, characters.single()) {
+        noise_events.send(NoiseEvent {
+            position: transform.translation.truncate(),
+            radius: stealth.ability_noise,
+        });
+    }
+
+    for event in interaction_events.iter() {
+        if let Ok((transform, noise)) = props.get(event.prop) {
+            noise_events.send(NoiseEvent {
+                position: transform.translation.truncate(),
+                radius: noise.radius,
+            });
+        }
+    }
+}
+
+/// Update what enemies with vision can see and hear, moving them between being unaware,
+/// suspicious, alerted, and searching for the player
+pub fn update_enemy_alertness(
+    mut enemies: Query<
+        (
+            Entity,
+            &Transform,
+            &Velocity,
+            &Enemy,
+            &mut Vision,
+            &mut Alertness,
+        ),
+        Without<Dormant>,
+    >,
+    characters: Query<(Entity, &Transform), With<Handle<Character>>>,
+    mut noise_events: EventReader<NoiseEvent>,
+    current_level: Option<Res<CurrentLevel>>,
+    physics_world: PhysicsWorld,
+    game_info: Res<GameInfo>,
+    game_time: Res<GameTime>,
+) {
+    let noises = noise_events.iter().copied().collect::<Vec<_>>();
+    let (current_level, (character_ent, character_transform)) =
+        match (current_level, characters.single()) {
+            (Some(level), Ok(character)) => (level, character),
+            _ => return,
+        };
+    let character_pos = character_transform.translation.truncate();
+    let delta = game_time.delta_seconds();
+
+    for (enemy_ent, transform, velocity, enemy, mut vision, mut alertness) in enemies.iter_mut() {
+        if enemy.level != current_level.0 {
+            continue;
+        }
+        let enemy_pos = transform.translation.truncate();
+
+        // Look where the enemy is going, or towards what it noticed while standing still
+        let moving = velocity.linear.truncate();
+        if moving.length() > 0.5 {
+            vision.facing = moving.normalize();
+        } else if let Some(last_known) = alertness.last_known {
+            let towards = (last_known - enemy_pos).normalize_or_zero();
+            if towards != Vec2::ZERO {
+                vision.facing = towards;
+            }
+        }
+
+        // The enemy sees the player if they are in its vision cone and there's no wall in between
+        let sees_player = vision.covers(character_pos - enemy_pos)
+            && physics_world
+                .shape_cast_with_filter(
+                    &CollisionShape::Sphere { radius: 1. },
+                    enemy_pos.extend(0.),
+                    Quat::default(),
+                    (character_pos - enemy_pos).extend(0.),
+                    CollisionLayers::default(),
+                    |entity| entity != enemy_ent,
+                )
+                .map_or(false, |x| x.entity == character_ent);
+        // The enemy hears noises in range, which don't travel as far through walls
+        let heard = noises
+            .iter()
+            .filter(|noise| {
+                let distance = noise.position.distance(enemy_pos);
+                let radius = noise.radius * alertness.hearing;
+                if distance > radius {
+                    return false;
+                }
+                let through_wall = physics_world
+                    .shape_cast_with_filter(
+                        &CollisionShape::Sphere { radius: 1. },
+                        enemy_pos.extend(0.),
+                        Quat::default(),
+                        (noise.position - enemy_pos).extend(0.),
+                        CollisionLayers::from_bits(
+                            PhysicsGroup::all_bits(),
+                            PhysicsGroup::Terrain.to_bits(),
+                        ),
+                        |_| true,
+                    )
+                    .is_some();
+                !through_wall || distance <= radius * game_info.stealth.wall_muffling
+            })
+            .last();
+
+        let alertness = &mut *alertness;
+        let suspicion_rate = delta / alertness.suspicion_time.max(f32::EPSILON);
+        if sees_player {
+            alertness.last_known = Some(character_pos);
+            alertness.suspicion = (alertness.suspicion + suspicion_rate).min(1.);
+        } else if let Some(noise) = heard {
+            alertness.last_known = Some(noise.position);
+            alertness.suspicion = (alertness.suspicion + NOISE_SUSPICION).min(1.);
+        }
+
+        alertness.state = match alertness.state {
+            // Enemies that already know about the player are alerted again as soon as they see
+            // them
+            AlertState::Alert | AlertState::Searching if sees_player => AlertState::Alert,
+            AlertState::Alert => {
+                alertness.search_remaining = alertness.search_time;
+                AlertState::Searching
+            }
+            AlertState::Searching => {
+                if heard.is_some() {
+                    alertness.search_remaining = alertness.search_time;
+                }
+                alertness.search_remaining -= delta;
+                if alertness.search_remaining > 0. {
+                    AlertState::Searching
+                } else {
+                    alertness.suspicion = 0.;
+                    alertness.last_known = None;
+                    AlertState::Unaware
+                }
+            }
+            AlertState::Unaware | AlertState::Suspicious => {
+                if alertness.suspicion >= 1. {
+                    AlertState::Alert
+                } else if heard.is_some() && !sees_player && alertness.investigates {
+                    // Go and look where the noise came from
+                    alertness.search_remaining = alertness.search_time;
+                    AlertState::Searching
+                } else if sees_player || heard.is_some() {
+                    AlertState::Suspicious
+                } else {
+                    // Calm down when nothing else is noticed
+                    alertness.suspicion = (alertness.suspicion - suspicion_rate).max(0.);
+                    if alertness.suspicion > 0. {
+                        AlertState::Suspicious
+                    } else {
+                        alertness.last_known = None;
+                        AlertState::Unaware
+                    }
+                }
+            }
+        };
+    }
+}
+
+/// Draw the vision cones of enemies in the current level, colored by how alert they are, if
+/// enabled in the debug rendering options
+pub fn draw_vision_cones(
+    mut commands: Commands,
+    enemies: Query<(&Transform, &Enemy, &Vision, &Alertness)>,
+    debug_vizes: Query<Entity, (With<VisionConeDebugViz>, Without<Pooled>)>,
+    mut debug_viz_pool: ResMut<EntityPool<VisionConeDebugViz>>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Res<GameInfo>,
+) {
+    // Clean up the vision cones from the previous frame
+    for entity in debug_vizes.iter() {
+        debug_viz_pool.release(&mut commands, entity);
+    }
+
+    let current_level = match current_level {
+        Some(level) if game_info.debug_rendering.vision_cones => level,
+        _ => return,
+    };
+
+    for (transform, enemy, vision, alertness) in enemies.iter() {
+        if enemy.level != current_level.0 {
+            continue;
+        }
+        let pos = transform.translation.truncate();
+
+        // Go around the arc of the cone from one edge to the other
+        let half_angle = vision.angle.to_radians() / 2.;
+        let start_angle = vision.facing.y.atan2(vision.facing.x) - half_angle;
+        let arc = (0..=VISION_CONE_SEGMENTS).map(|i| {
+            let angle =
+                start_angle + vision.angle.to_radians() * i as f32 / VISION_CONE_SEGMENTS as f32;
+            let point = pos + Vec2::new(angle.cos(), angle.sin()) * vision.range;
+            epaint::pos2(point.x, point.y)
+        });
+        let points = std::iter::once(epaint::pos2(pos.x, pos.y))
+            .chain(arc)
+            .collect::<Vec<_>>();
+
+        let color = match alertness.state {
+            AlertState::Unaware => epaint::Color32::WHITE,
+            AlertState::Suspicious | AlertState::Searching => epaint::Color32::YELLOW,
+            AlertState::Alert => epaint::Color32::RED,
+        };
+
+        let viz = debug_viz_pool.take(&mut commands);
+        commands
+            .entity(viz)
+            .insert_bundle(ShapeBundle {
+                shape: Shape::closed_line(points, (1., color)),
+                transform: Transform::from_xyz(0., 0., 1024.),
+                ..Default::default()
+            })
+            .insert(VisionConeDebugViz);
+    }
+}
@@ -0,0 +1,154 @@
+use bevy::prelude::{Res, ResMut, Time, World};
+use bevy_retrograde::ui::raui::prelude::*;
+
+use crate::{asset_io::AssetDownloadProgress, plugins::game::assets::GameInfo};
+
+/// Resource controlling the full-screen fade to and from black used when transitioning between
+/// game screens
+#[derive(Default)]
+pub struct ScreenFade {
+    /// The current opacity of the fade, from `0.0` ( transparent ) to `1.0` ( black )
+    pub opacity: f32,
+    /// The opacity we are fading towards
+    pub target: f32,
+}
+
+impl ScreenFade {
+    /// Start fading the screen to black
+    pub fn fade_out(&mut self) {
+        self.target = 1.;
+    }
+
+    /// Start fading the screen back in from black
+    pub fn fade_in(&mut self) {
+        self.target = 0.;
+    }
+
+    /// Whether or not the screen has finished fading to black
+    pub fn is_black(&self) -> bool {
+        self.opacity >= 1.
+    }
+
+    /// Whether or not a fade is in progress
+    pub fn is_fading(&self) -> bool {
+        (self.opacity - self.target).abs() > f32::EPSILON
+    }
+}
+
+/// Move the screen fade opacity towards its target
+pub fn update_screen_fade(
+    mut fade: ResMut<ScreenFade>,
+    game_info: Option<Res<GameInfo>>,
+    time: Res<Time>,
+) {
+    // Avoid triggering change detection if we aren't fading
+    if !fade.is_fading() {
+        return;
+    }
+
+    let fade_duration = game_info
+        .map(|x| x.transitions.fade_duration)
+        .unwrap_or(0.5);
+
+    let step = if fade_duration > 0. {
+        time.delta_seconds() / fade_duration
+    } else {
+        1.
+    };
+
+    let fade = &mut *fade;
+    if fade.opacity < fade.target {
+        fade.opacity = (fade.opacity + step).min(fade.target);
+    } else {
+        fade.opacity = (fade.opacity - step).max(fade.target);
+    }
+}
+
+/// A full-screen black overlay with the opacity of the [`ScreenFade`]. Add this as the last child of
+/// a screen's root `content_box` to make the screen take part in transitions.
+pub fn screen_fade(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let opacity = world
+        .get_resource::<ScreenFade>()
+        .map(|x| x.opacity)
+        .unwrap_or(0.);
+
+    // Don't render anything when the fade is fully transparent
+    if opacity <= 0. {
+        return WidgetNode::None;
+    }
+
+    make_widget!(image_box)
+        .with_props(ImageBoxProps {
+            material: ImageBoxMaterial::Color(ImageBoxColor {
+                color: Color {
+                    r: 0.,
+                    g: 0.,
+                    b: 0.,
+                    a: opacity,
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .into()
+}
+
+/// The screen shown while the game is loading
+pub fn loading_screen(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+
+    // Show the download progress on web
+    let mut text = game_info.transitions.loading_text.clone();
+    if let Some(progress) = world.get_resource::<AssetDownloadProgress>() {
+        text.push_str(&format!(
+            " {}/{}",
+            progress.finished(),
+            progress.requested()
+        ));
+        if progress.retries() > 0 {
+            text.push_str(&format!("\n( retried {} downloads )", progress.retries()));
+        }
+    }
+
+    make_widget!(content_box)
+        // Add a black background
+        .listed_slot(make_widget!(image_box).with_props(ImageBoxProps {
+            material: ImageBoxMaterial::Color(ImageBoxColor {
+                color: Color {
+                    r: 0.,
+                    g: 0.,
+                    b: 0.,
+                    a: 1.,
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        }))
+        // The loading indicator in the bottom-right corner
+        .listed_slot(
+            make_widget!(text_box)
+                .with_props(TextBoxProps {
+                    color: Color {
+                        r: 1.,
+                        g: 1.,
+                        b: 1.,
+                        a: 1.,
+                    },
+                    text,
+                    font: TextBoxFont {
+                        name: game_info.ui_theme.default_font.clone(),
+                        size: 1.,
+                    },
+                    horizontal_align: TextBoxHorizontalAlign::Right,
+                    vertical_align: TextBoxVerticalAlign::Bottom,
+                    ..Default::default()
+                })
+                .with_props(ContentBoxItemLayout {
+                    margin: 5.0.into(),
+                    ..Default::default()
+                }),
+        )
+        .into()
+}
@@ -0,0 +1,489 @@
+#[cfg(not(wasm))]
+use std::path::Path;
+
+use bevy::prelude::{Assets, Handle, Input, KeyCode, World};
+use bevy_retrograde::{
+    physics::heron::rapier_plugin::rapier2d::prelude::IntegrationParameters, ui::raui::prelude::*,
+};
+
+#[cfg(not(wasm))]
+use crate::asset_overlay::ContentPacks;
+use crate::{
+    plugins::game::{
+        assets::GameInfo,
+        components::{Character, DamageRegion, Enemy},
+        systems::{inspector::Inspector, perf_hud::debug_text},
+    },
+    EngineConfig,
+};
+
+/// Resource for the tweak panel that is toggled with F6 when the `debug` engine option is on
+#[derive(Default)]
+pub struct TweakPanel {
+    /// Whether the tweak panel is shown
+    pub visible: bool,
+    /// The lines of text in the tweak panel
+    pub lines: Vec<String>,
+    /// The index of the selected value
+    selected: usize,
+    /// The result of the last time a value was written to disk
+    status: Option<String>,
+}
+
+/// A physics or movement constant that can be changed in the tweak panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tweak {
+    Erp,
+    AllowedLinearError,
+    PredictionDistance,
+    MaxLinearCorrection,
+    WalkSpeed,
+    EnemySpeed,
+    KnockBackSpeed,
+    KnockBackForceDuration,
+    KnockBackFreezeDuration,
+    /// The distance past which smoothed movement between simulation steps jumps instead
+    PhysicsSnapDistance,
+    CameraSmoothing,
+}
+
+const TWEAKS: [Tweak; 11] = [
+    Tweak::Erp,
+    Tweak::AllowedLinearError,
+    Tweak::PredictionDistance,
+    Tweak::MaxLinearCorrection,
+    Tweak::WalkSpeed,
+    Tweak::EnemySpeed,
+    Tweak::KnockBackSpeed,
+    Tweak::KnockBackForceDuration,
+    Tweak::KnockBackFreezeDuration,
+    Tweak::PhysicsSnapDistance,
+    Tweak::CameraSmoothing,
+];
+
+impl Tweak {
+    fn label(&self) -> &'static str {
+        match self {
+            Tweak::Erp => "physics erp",
+            Tweak::AllowedLinearError => "physics allowed linear error",
+            Tweak::PredictionDistance => "physics prediction distance",
+            Tweak::MaxLinearCorrection => "physics max linear correction",
+            Tweak::WalkSpeed => "player walk speed",
+            Tweak::EnemySpeed => "enemy speed",
+            Tweak::KnockBackSpeed => "enemy knock-back speed",
+            Tweak::KnockBackForceDuration => "enemy knock-back force duration",
+            Tweak::KnockBackFreezeDuration => "enemy knock-back freeze duration",
+            Tweak::PhysicsSnapDistance => "physics smoothing snap distance",
+            Tweak::CameraSmoothing => "camera smoothing",
+        }
+    }
+
+    /// How much the value changes each time it is edited
+    fn step(&self) -> f32 {
+        match self {
+            Tweak::Erp | Tweak::AllowedLinearError | Tweak::KnockBackForceDuration => 0.01,
+            Tweak::KnockBackFreezeDuration | Tweak::CameraSmoothing => 0.02,
+            Tweak::PredictionDistance | Tweak::MaxLinearCorrection => 0.1,
+            Tweak::WalkSpeed | Tweak::EnemySpeed | Tweak::PhysicsSnapDistance => 1.,
+            Tweak::KnockBackSpeed => 10.,
+        }
+    }
+
+    /// The path of keys to the value in its YAML file
+    fn yaml_path(&self) -> &'static [&'static str] {
+        match self {
+            Tweak::Erp => &["physics", "erp"],
+            Tweak::AllowedLinearError => &["physics", "allowed-linear-error"],
+            Tweak::PredictionDistance => &["physics", "prediction-distance"],
+            Tweak::MaxLinearCorrection => &["physics", "max-linear-correction"],
+            Tweak::WalkSpeed => &["walk-speed"],
+            Tweak::EnemySpeed => &["enemies", "speed"],
+            Tweak::KnockBackSpeed => &["enemies", "knock-back", "speed"],
+            Tweak::KnockBackForceDuration => &["enemies", "knock-back", "force-duration"],
+            Tweak::KnockBackFreezeDuration => &["enemies", "knock-back", "freeze-duration"],
+            Tweak::PhysicsSnapDistance => &["physics-smoothing", "snap-distance"],
+            Tweak::CameraSmoothing => &["camera-smoothing"],
+        }
+    }
+
+    /// Get the value out of the game info, for the values that are stored there
+    fn game_info_value<'a>(&self, game_info: &'a mut GameInfo) -> Option<&'a mut f32> {
+        Some(match self {
+            Tweak::Erp => &mut game_info.physics.erp,
+            Tweak::AllowedLinearError => &mut game_info.physics.allowed_linear_error,
+            Tweak::PredictionDistance => &mut game_info.physics.prediction_distance,
+            Tweak::MaxLinearCorrection => &mut game_info.physics.max_linear_correction,
+            Tweak::WalkSpeed => return None,
+            Tweak::EnemySpeed => &mut game_info.enemies.speed,
+            Tweak::KnockBackSpeed => &mut game_info.enemies.knock_back.speed,
+            Tweak::KnockBackForceDuration => &mut game_info.enemies.knock_back.force_duration,
+            Tweak::KnockBackFreezeDuration => &mut game_info.enemies.knock_back.freeze_duration,
+            Tweak::PhysicsSnapDistance => &mut game_info.physics_smoothing.snap_distance,
+            Tweak::CameraSmoothing => &mut game_info.camera_smoothing,
+        })
+    }
+
+    /// Get the current value
+    fn get(&self, world: &mut World) -> Option<f32> {
+        if *self == Tweak::WalkSpeed {
+            let handle = player_character(world)?;
+            let characters = world.get_resource::<Assets<Character>>()?;
+            return characters.get(handle).map(|x| x.walk_speed);
+        }
+
+        let mut game_info = world.get_resource_mut::<GameInfo>()?;
+        self.game_info_value(&mut game_info).map(|x| *x)
+    }
+
+    /// Add an amount to the value and apply it to the game
+    fn edit(&self, world: &mut World, amount: f32) {
+        // Round away floating point error so that the values written to disk stay tidy
+        let change = |value: &mut f32| {
+            *value = ((*value + amount) / self.step()).round() * self.step();
+            *value = value.max(0.);
+        };
+
+        if *self == Tweak::WalkSpeed {
+            if let Some(handle) = player_character(world) {
+                let mut characters = world.get_resource_mut::<Assets<Character>>().unwrap();
+                if let Some(character) = characters.get_mut(handle) {
+                    change(&mut character.walk_speed);
+                }
+            }
+            return;
+        }
+
+        let mut game_info = world.get_resource_mut::<GameInfo>().unwrap();
+        if let Some(value) = self.game_info_value(&mut game_info) {
+            change(value);
+        }
+        let physics = game_info.physics.clone();
+        let knock_back = game_info.enemies.knock_back.clone();
+
+        // Apply the values that are copied out of the game info when the game starts or when
+        // enemies spawn
+        match self {
+            Tweak::Erp
+            | Tweak::AllowedLinearError
+            | Tweak::PredictionDistance
+            | Tweak::MaxLinearCorrection => {
+                let mut params = world.get_resource_mut::<IntegrationParameters>().unwrap();
+                physics.apply(&mut params);
+            }
+            Tweak::KnockBackSpeed
+            | Tweak::KnockBackForceDuration
+            | Tweak::KnockBackFreezeDuration => {
+                for (_, mut damage_region) in
+                    world.query::<(&Enemy, &mut DamageRegion)>().iter_mut(world)
+                {
+                    damage_region.knock_back = knock_back.clone();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Write the current value to the YAML file that it came from, which is the file in the
+    /// highest priority mod that replaces it, if any
+    #[cfg(not(wasm))]
+    fn write(&self, world: &mut World) -> Result<String, String> {
+        let value = self.get(world).ok_or("Value isn't loaded")?;
+        let file = if *self == Tweak::WalkSpeed {
+            world
+                .get_resource::<GameInfo>()
+                .unwrap()
+                .player_character
+                .clone()
+        } else {
+            "default.game.yaml".into()
+        };
+        let engine_config = world.get_resource::<EngineConfig>().unwrap();
+        let content_packs = world.get_resource::<ContentPacks>().unwrap();
+        let path = if let Some(path) = content_packs.overlay_path(Path::new(&file)) {
+            path
+        } else if engine_config.asset_pack.is_some()
+            || crate::asset_pack::embedded_assets().is_some()
+        {
+            return Err("Can't write to an asset pack".into());
+        } else {
+            Path::new(&engine_config.asset_path).join(&file)
+        };
+
+        let contents =
+            std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let contents = set_yaml_value(&contents, self.yaml_path(), &value.to_string())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        std::fs::write(&path, contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(format!("Wrote {} to {}", self.label(), path.display()))
+    }
+
+    #[cfg(wasm)]
+    fn write(&self, _world: &mut World) -> Result<String, String> {
+        Err("Can't write files on web".into())
+    }
+}
+
+/// Set a value in the text of a block style YAML file, leaving the rest of the file, including its
+/// comments and formatting, as it was
+///
+/// Sections that aren't in the file yet are added to the end of their parent section.
+#[cfg(not(wasm))]
+fn set_yaml_value(contents: &str, path: &[&str], value: &str) -> Result<String, String> {
+    let mut lines = contents.lines().map(String::from).collect::<Vec<_>>();
+
+    // The lines of the section that the next key is in, and the indentation of its keys
+    let mut start = 0;
+    let mut end = lines.len();
+    let mut indent = 0;
+    for (depth, key) in path.iter().enumerate() {
+        let last = depth == path.len() - 1;
+        let content_lines = (start..end)
+            .filter(|&i| yaml_key(&lines[i]).is_some())
+            .collect::<Vec<_>>();
+        if let Some(&first) = content_lines.first() {
+            indent = indentation(&lines[first]);
+        }
+
+        let found = content_lines.iter().copied().find(|&i| {
+            indentation(&lines[i]) == indent && yaml_key(&lines[i]).map(|x| x.0) == Some(*key)
+        });
+        let line = if let Some(line) = found {
+            line
+        } else {
+            // Add the rest of the path after the last line of the section
+            let insert_at = content_lines.last().map_or(start, |x| x + 1);
+            let new_lines = path[depth..].iter().enumerate().map(|(i, key)| {
+                let padding = " ".repeat(indent + i * 2);
+                if depth + i == path.len() - 1 {
+                    format!("{}{}: {}", padding, key, value)
+                } else {
+                    format!("{}{}:", padding, key)
+                }
+            });
+            lines.splice(insert_at..insert_at, new_lines);
+            break;
+        };
+
+        let (_, rest) = yaml_key(&lines[line]).unwrap();
+        let value_len = rest.find(" #").unwrap_or(rest.len());
+        let has_value = !rest[..value_len].trim().is_empty();
+        if last {
+            if !has_value {
+                return Err(format!("`{}` isn't a value", key));
+            }
+            let value_end = rest[..value_len].trim_end().len();
+            let prefix_len = lines[line].len() - rest.len();
+            let line_text = &lines[line];
+            lines[line] = format!(
+                "{} {}{}",
+                &line_text[..prefix_len],
+                value,
+                &line_text[prefix_len + value_end..]
+            );
+        } else {
+            if has_value {
+                return Err(format!("`{}` isn't a block style section", key));
+            }
+            // The section ends at the next line that isn't indented further than its key
+            start = line + 1;
+            end = (start..lines.len())
+                .find(|&i| {
+                    yaml_key(&lines[i]).is_some()
+                        && indentation(&lines[i]) <= indentation(&lines[line])
+                })
+                .unwrap_or(lines.len());
+            indent = indentation(&lines[line]) + 2;
+        }
+    }
+
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    Ok(contents)
+}
+
+/// Get the key of a line of YAML and the rest of the line after the colon, or `None` for blank
+/// lines and comments
+///
+/// Lines that aren't a key and value, such as list items, are given an empty key.
+#[cfg(not(wasm))]
+fn yaml_key(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return None;
+    }
+
+    let colon = trimmed.match_indices(':').map(|(i, _)| i).find(|&i| {
+        trimmed[i + 1..]
+            .chars()
+            .next()
+            .map_or(true, char::is_whitespace)
+    });
+    Some(match colon {
+        Some(colon) => (
+            trimmed[..colon].trim_matches(|x| x == '"' || x == '\''),
+            &trimmed[colon + 1..],
+        ),
+        None => ("", ""),
+    })
+}
+
+/// Get the number of spaces a line is indented by
+#[cfg(not(wasm))]
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Get the handle to the player's character
+fn player_character(world: &mut World) -> Option<Handle<Character>> {
+    world
+        .query::<&Handle<Character>>()
+        .iter(world)
+        .next()
+        .cloned()
+}
+
+/// Toggle the tweak panel with F6 and apply the edits made with it
+///
+/// Page Up and Page Down select a value, `-` and `=` change it, faster while shift is held, and
+/// Enter writes it to the YAML file it came from.
+pub fn update_tweak_panel(world: &mut World) {
+    let debug = world.get_resource::<EngineConfig>().unwrap().debug;
+    let (toggle, previous, next, decrease, increase, write, fast) = {
+        let input = world.get_resource::<Input<KeyCode>>().unwrap();
+        (
+            input.just_pressed(KeyCode::F6),
+            input.just_pressed(KeyCode::PageUp),
+            input.just_pressed(KeyCode::PageDown),
+            input.just_pressed(KeyCode::Minus),
+            input.just_pressed(KeyCode::Equals),
+            input.just_pressed(KeyCode::Return),
+            input.pressed(KeyCode::LShift) || input.pressed(KeyCode::RShift),
+        )
+    };
+
+    let mut panel = world.get_resource_mut::<TweakPanel>().unwrap();
+    if !debug {
+        panel.visible = false;
+    } else if toggle {
+        panel.visible = !panel.visible;
+        panel.status = None;
+    }
+    if !panel.visible {
+        panel.lines.clear();
+        return;
+    }
+
+    // The inspector uses the same keys, so only one of them can be open at a time
+    if toggle {
+        world.get_resource_mut::<Inspector>().unwrap().visible = false;
+    }
+
+    let mut panel = world.get_resource_mut::<TweakPanel>().unwrap();
+    if next {
+        panel.selected = (panel.selected + 1) % TWEAKS.len();
+    }
+    if previous {
+        panel.selected = panel.selected.checked_sub(1).unwrap_or(TWEAKS.len() - 1);
+    }
+    let selected = panel.selected;
+    let tweak = TWEAKS[selected];
+
+    let step = if fast {
+        tweak.step() * 10.
+    } else {
+        tweak.step()
+    };
+    if increase {
+        tweak.edit(world, step);
+    }
+    if decrease {
+        tweak.edit(world, -step);
+    }
+    if write {
+        let status = match tweak.write(world) {
+            Ok(status) => status,
+            Err(e) => format!("Couldn't write {}: {}", tweak.label(), e),
+        };
+        world.get_resource_mut::<TweakPanel>().unwrap().status = Some(status);
+    }
+
+    let mut lines = vec!["Tweaks (F6): PgUp/PgDn select, -/= edit, Enter save".to_string()];
+    for (i, tweak) in TWEAKS.iter().enumerate() {
+        let value = tweak.get(world);
+        lines.push(format!(
+            "{} {}: {}",
+            if i == selected { ">" } else { " " },
+            tweak.label(),
+            value.map(|x| x.to_string()).unwrap_or_else(|| "-".into())
+        ));
+    }
+    let mut panel = world.get_resource_mut::<TweakPanel>().unwrap();
+    lines.extend(panel.status.clone());
+    panel.lines = lines;
+}
+
+/// The tweak panel in the top-right corner of the screen
+pub fn tweak_panel(ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let panel = if let Some(panel) = world.get_resource::<TweakPanel>() {
+        panel
+    } else {
+        return WidgetNode::None;
+    };
+    if !panel.visible || panel.lines.is_empty() {
+        return WidgetNode::None;
+    }
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+
+    debug_text(
+        &game_info.ui_theme.default_font,
+        &panel.lines,
+        Rect {
+            left: 0.5,
+            right: 1.,
+            top: 0.,
+            bottom: 1.,
+        },
+    )
+}
+
+#[cfg(all(test, not(wasm)))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_set_yaml_value() {
+        let yaml = "\
+title: Game # The title
+enemies:
+  speed: 30  # Pixels per second
+
+  # How enemies are pushed back
+  knock-back:
+    speed: 100
+player-character: characters/player.yaml
+";
+
+        assert_eq!(
+            set_yaml_value(yaml, &["enemies", "knock-back", "speed"], "120").unwrap(),
+            yaml.replace("    speed: 100", "    speed: 120")
+        );
+        assert_eq!(
+            set_yaml_value(yaml, &["enemies", "speed"], "32.5").unwrap(),
+            yaml.replace("speed: 30  #", "speed: 32.5  #")
+        );
+        assert_eq!(
+            set_yaml_value(yaml, &["enemies", "knock-back", "force-duration"], "0.2").unwrap(),
+            yaml.replace(
+                "    speed: 100\n",
+                "    speed: 100\n    force-duration: 0.2\n"
+            )
+        );
+        assert_eq!(
+            set_yaml_value(yaml, &["physics", "erp"], "0.5").unwrap(),
+            format!("{}physics:\n  erp: 0.5\n", yaml)
+        );
+        assert!(set_yaml_value(yaml, &["enemies"], "1").is_err());
+        assert!(set_yaml_value(yaml, &["title", "speed"], "1").is_err());
+    }
+}
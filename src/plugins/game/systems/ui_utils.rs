@@ -0,0 +1,135 @@
+use crate::plugins::game::{
+    assets::{GameInfo, UiTheme},
+    settings::UserSettings,
+};
+use bevy_retrograde::ui::raui::prelude::*;
+
+mod rich_text;
+pub use rich_text::*;
+mod typewriter;
+pub use typewriter::*;
+mod layout;
+pub use layout::*;
+mod button;
+pub use button::*;
+mod menu;
+pub use menu::*;
+mod theme;
+pub use theme::*;
+
+/// Get the UI theme to use for a screen, taking the current [`UiThemeVariant`] and the player's
+/// accessibility settings into account
+///
+/// The screen is one of the keys of the theme's `screens`: `menu`, `pause`, `battle`, or `hud`.
+pub fn active_ui_theme<'a>(
+    game_info: &'a GameInfo,
+    settings: &UserSettings,
+    variant: &UiThemeVariant,
+    screen: &str,
+) -> &'a UiTheme {
+    let default_theme = &game_info.ui_theme;
+    let theme = variant
+        .0
+        .as_ref()
+        .or_else(|| default_theme.screens.get(screen))
+        .and_then(|x| default_theme.variants.get(x))
+        .unwrap_or(default_theme);
+
+    // Variants without their own high contrast theme use the default one
+    match theme
+        .high_contrast
+        .as_ref()
+        .or_else(|| default_theme.high_contrast.as_ref())
+    {
+        Some(high_contrast) if settings.accessibility.high_contrast => &**high_contrast,
+        _ => theme,
+    }
+}
+
+/// Get the font for UI text, sized by the player's text size setting
+///
+/// Bevy Retrograde draws fonts at their own size, so the size is applied by the [`rich_text`]
+/// widget, which scales the text and lays it out to fit.
+pub fn ui_font(name: &str, settings: &UserSettings) -> TextBoxFont {
+    TextBoxFont {
+        name: name.into(),
+        size: settings.accessibility.text_size.scale(),
+    }
+}
+
+/// Get the RAUI theme props for a UI theme, i.e. one from [`active_ui_theme`]
+pub fn get_ui_theme(ui_theme: &UiTheme) -> ThemeProps {
+    let mut theme = ThemeProps::default();
+
+    theme.content_backgrounds.insert(
+        String::from("panel"),
+        ThemedImageMaterial::Image(ImageBoxImage {
+            id: ui_theme.panel.image.clone(),
+            scaling: ImageBoxImageScaling::Frame(
+                (ui_theme.panel.border_size as f32, ui_theme.panel.only_frame).into(),
+            ),
+            ..Default::default()
+        }),
+    );
+
+    theme.content_backgrounds.insert(
+        String::from("button-up"),
+        ThemedImageMaterial::Image(ImageBoxImage {
+            id: ui_theme.button_up.image.clone(),
+            scaling: ImageBoxImageScaling::Frame(
+                (
+                    ui_theme.button_up.border_size as f32,
+                    ui_theme.button_up.only_frame,
+                )
+                    .into(),
+            ),
+            ..Default::default()
+        }),
+    );
+
+    theme.content_backgrounds.insert(
+        String::from("button-down"),
+        ThemedImageMaterial::Image(ImageBoxImage {
+            id: ui_theme.button_down.image.clone(),
+            scaling: ImageBoxImageScaling::Frame(
+                (
+                    ui_theme.button_down.border_size as f32,
+                    ui_theme.button_down.only_frame,
+                )
+                    .into(),
+            ),
+            ..Default::default()
+        }),
+    );
+
+    theme.switch_variants.insert(
+        "checkbox".to_owned(),
+        ThemedSwitchMaterial {
+            on: ThemedImageMaterial::Image(ImageBoxImage {
+                id: ui_theme.checkbox.checked.clone(),
+                ..Default::default()
+            }),
+            off: ThemedImageMaterial::Image(ImageBoxImage {
+                id: ui_theme.checkbox.unchecked.clone(),
+                ..Default::default()
+            }),
+        },
+    );
+
+    theme.text_variants.insert(
+        String::new(),
+        ThemedTextMaterial {
+            font: TextBoxFont {
+                name: ui_theme.default_font.clone(),
+                // Font's in Bevy Retrograde don't really have sizes so we can just set this to
+                // one
+                size: 1.0,
+            },
+            ..Default::default()
+        },
+    );
+
+    theme.icons_level_sizes = vec![8., 12., 16.];
+
+    theme
+}
@@ -0,0 +1,119 @@
+use bevy::prelude::World;
+use bevy_retrograde::ui::raui::prelude::*;
+
+use crate::plugins::game::assets::GameInfo;
+
+/// Props for a [`game_button`], which sends a [`GameButtonMessage`] with `message_name` to the
+/// widget with `notify_id` when it is clicked
+#[derive(PropsData, Debug, Clone, serde::Deserialize, serde::Serialize, Default)]
+pub struct GameButtonProps {
+    pub text: String,
+    pub notify_id: WidgetId,
+    pub message_name: String,
+}
+
+/// The message sent by a [`game_button`] when it is clicked
+#[derive(MessageData, Debug, Clone, serde::Deserialize, serde::Serialize, Default)]
+pub struct GameButtonMessage(pub String);
+
+fn use_game_button(ctx: &mut WidgetContext) {
+    ctx.life_cycle.change(|ctx| {
+        let ButtonProps { trigger, .. } = ctx.state.read_cloned_or_default();
+        let GameButtonProps {
+            notify_id,
+            message_name: message,
+            ..
+        } = ctx.props.read_cloned_or_default();
+
+        if trigger {
+            ctx.messenger.write(notify_id, GameButtonMessage(message));
+        }
+    });
+}
+
+#[pre_hooks(
+    // This allows us to get a `ButtonProps` instance from our widget state which will keep
+    // track of whether or not we are clicked, hovered over, etc.
+    use_game_button,
+    use_button_notified_state,
+)]
+/// A button drawn with the UI theme's button images
+pub fn game_button(mut ctx: WidgetContext) -> WidgetNode {
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+
+    // Get our button state
+    let ButtonProps {
+        selected: hover,
+        trigger: clicked,
+        ..
+    } = ctx.state.read_cloned_or_default();
+
+    let GameButtonProps {
+        text: button_text, ..
+    } = ctx.props.read_cloned_or_default();
+
+    let button_props = ctx
+        .props
+        .clone()
+        .with(NavItemActive)
+        .with(ButtonNotifyProps(ctx.id.to_owned().into()));
+
+    let button_panel_props = Props::new(PaperProps {
+        frame: None,
+        variant: if clicked {
+            // TODO: Somehow pre-load the button-up image so that it doesn't flash
+            // blank for a second the first time a button is clicked
+            String::from("button-down")
+        } else {
+            String::from("button-up")
+        },
+    });
+
+    let scale = if hover { 1.1 } else { 1. };
+
+    let label_props = Props::new(TextBoxProps {
+        text: button_text,
+        width: TextBoxSizeValue::Fill,
+        height: TextBoxSizeValue::Fill,
+        horizontal_align: TextBoxHorizontalAlign::Center,
+        vertical_align: TextBoxVerticalAlign::Middle,
+        font: TextBoxFont {
+            name: game_info.ui_theme.default_font.clone(),
+            size: 1.,
+        },
+        transform: Transform {
+            translation: Vec2 {
+                x: 0.,
+                y: if clicked { 1. } else { 0. },
+            },
+            // scale: Vec2::from(1.0 / scale), // Undo button scale to make sure text stays same size
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    let size_box_props = Props::new(SizeBoxProps {
+        width: SizeBoxSizeValue::Exact(70.),
+        height: SizeBoxSizeValue::Exact(18.),
+        transform: Transform {
+            scale: Vec2::from(scale),
+            translation: Vec2 {
+                x: if hover { (-75. * scale + 75.) / 2. } else { 0. },
+                y: if hover { (-20. * scale + 20.) / 2. } else { 0. },
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    widget! {
+        (button: {button_props} {
+            content = (size_box: {size_box_props} {
+                content = (horizontal_paper: {button_panel_props} [
+                    (text_box: {label_props})
+                ])
+            })
+        })
+    }
+}
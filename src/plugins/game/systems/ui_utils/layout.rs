@@ -0,0 +1,151 @@
+use bevy::{
+    input::touch::TouchInput,
+    prelude::{EventReader, Local, Query, Res, ResMut, Windows},
+};
+use bevy_retrograde::{
+    prelude::{Camera, UiTree},
+    ui::raui::prelude::*,
+};
+
+use crate::plugins::game::assets::{GameInfo, UiLayoutConfig};
+
+/// Resource containing the scale of the UI and the part of the screen that it should be kept
+/// inside of, updated from the game's [`UiLayoutConfig`][crate::plugins::game::UiLayoutConfig]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UiLayout {
+    /// The size of the screen in UI pixels
+    pub size: Vec2,
+    /// The amount to scale the HUD and menus by
+    pub scale: f32,
+    /// The distance in UI pixels from each edge of the screen that the UI should be kept away from
+    pub safe_area: Rect,
+}
+
+impl Default for UiLayout {
+    fn default() -> Self {
+        Self {
+            size: Vec2::default(),
+            scale: 1.,
+            safe_area: Rect::default(),
+        }
+    }
+}
+
+/// Update the [`UiLayout`] from the game info, the window size, and whether or not the player is
+/// using a touch screen
+pub fn update_ui_layout(
+    mut using_touch: Local<bool>,
+    mut touch_events: EventReader<TouchInput>,
+    mut ui_layout: ResMut<UiLayout>,
+    mut ui_tree: ResMut<UiTree>,
+    game_info: Option<Res<GameInfo>>,
+    cameras: Query<&Camera>,
+    windows: Res<Windows>,
+) {
+    if touch_events.iter().next().is_some() {
+        *using_touch = true;
+    }
+
+    let config = game_info.map(|x| x.ui_layout.clone()).unwrap_or_default();
+    let (window, camera) = match (windows.get_primary(), cameras.single()) {
+        (Some(window), Ok(camera)) => (window, camera),
+        _ => return,
+    };
+
+    // The UI is drawn in game pixels, which are scaled up to fit the window
+    let camera_size = camera.get_target_sizes(window).low;
+    let size = Vec2 {
+        x: camera_size.x as f32,
+        y: camera_size.y as f32,
+    };
+    if size.x <= 0. || size.y <= 0. {
+        return;
+    }
+
+    // Convert the safe area insets from window pixels to UI pixels
+    #[allow(unused_mut)]
+    let mut safe_area = Rect::default();
+    #[cfg(wasm)]
+    if config.safe_area {
+        let pixel_scale =
+            (window.width() / (size.x * camera.pixel_aspect_ratio)).min(window.height() / size.y);
+        let [top, right, bottom, left] = crate::wasm_utils::safe_area_insets();
+        safe_area = Rect {
+            top: top / pixel_scale,
+            right: right / (pixel_scale * camera.pixel_aspect_ratio),
+            bottom: bottom / pixel_scale,
+            left: left / (pixel_scale * camera.pixel_aspect_ratio),
+        };
+    }
+
+    let layout = UiLayout {
+        size,
+        scale: camera_scale(&config, size)
+            * if *using_touch {
+                config.touch_scale
+            } else {
+                config.scale
+            },
+        safe_area,
+    };
+
+    // Avoid triggering change detection if the layout hasn't changed
+    if *ui_layout != layout {
+        *ui_layout = layout;
+
+        // Apply the UI tree again, which lays out every widget for the new size and scale
+        *ui_tree = UiTree(ui_tree.0.clone());
+    }
+}
+
+/// Get the amount to scale the UI by so that it covers the same part of the screen at the current
+/// camera size as it does at the configured reference size
+fn camera_scale(config: &UiLayoutConfig, size: Vec2) -> f32 {
+    let reference = match config.reference_size {
+        Some(reference) if reference.width > 0 && reference.height > 0 => reference,
+        _ => return 1.,
+    };
+
+    // Fit the reference size inside of the camera, which keeps the UI on screen when letterboxing
+    // shrinks one of the sides
+    let scale = (size.x / reference.width as f32).min(size.y / reference.height as f32);
+    if config.whole_number_scale && scale >= 1. {
+        scale.floor()
+    } else {
+        scale
+    }
+}
+
+/// Wrap a screen's content so that it is scaled by the UI scale and kept inside of the safe area
+pub fn safe_area_box(layout: &UiLayout, content: impl Into<WidgetNode>) -> WidgetNode {
+    let UiLayout {
+        size,
+        scale,
+        safe_area,
+    } = layout;
+
+    // Shrink the content's area by the UI scale so that it fills the safe area once it is scaled
+    // back up
+    let width = size.x - safe_area.left - safe_area.right;
+    let height = size.y - safe_area.top - safe_area.bottom;
+    let margin = Rect {
+        right: safe_area.right + width * (1. - 1. / scale),
+        bottom: safe_area.bottom + height * (1. - 1. / scale),
+        ..*safe_area
+    };
+
+    make_widget!(content_box)
+        .with_props(ContentBoxItemLayout {
+            margin,
+            ..Default::default()
+        })
+        .with_props(ContentBoxProps {
+            transform: Transform {
+                scale: Vec2::from(*scale),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .listed_slot(content)
+        .into()
+}
@@ -0,0 +1,129 @@
+use bevy_retrograde::ui::raui::prelude::*;
+
+use super::{
+    game_button, rich_text, safe_area_box, typewriter_text, GameButtonProps, RichTextProps,
+    TypewriterTextProps, UiLayout,
+};
+
+/// A menu panel in the middle of the screen, like the pause menu, with the given rows going down
+/// it
+pub fn menu_panel(theme: ThemeProps, ui_layout: &UiLayout, rows: Vec<WidgetNode>) -> WidgetNode {
+    let mut list = make_widget!(nav_vertical_box).with_props(FlexBoxItemLayout {
+        margin: Rect {
+            top: 10.,
+            bottom: 10.,
+            left: 15.,
+            right: 15.,
+        },
+        ..Default::default()
+    });
+    for row in rows {
+        list = list.listed_slot(row);
+    }
+
+    // Content box
+    make_widget!(content_box)
+        .with_shared_props(theme)
+        .listed_slot(safe_area_box(
+            ui_layout,
+            make_widget!(nav_content_box).listed_slot(
+                make_widget!(nav_vertical_paper)
+                    .with_props(PaperProps {
+                        variant: "panel".into(),
+                        frame: None,
+                    })
+                    .with_props(ContentBoxItemLayout {
+                        margin: Rect {
+                            left: 13.,
+                            right: 13.,
+                            top: 7.,
+                            bottom: 7.,
+                        },
+                        ..Default::default()
+                    })
+                    .listed_slot(list),
+            ),
+        ))
+        .into()
+}
+
+/// The title at the top of a menu page
+pub fn menu_title(font: &TextBoxFont, text: &str) -> WidgetNode {
+    make_widget!(rich_text)
+        .with_props(RichTextProps { text: text.into() })
+        .with_props(TextBoxProps {
+            font: font.clone(),
+            horizontal_align: TextBoxHorizontalAlign::Center,
+            color: Color {
+                r: 0.,
+                g: 0.,
+                b: 0.,
+                a: 1.,
+            },
+            ..Default::default()
+        })
+        .with_props(FlexBoxItemLayout {
+            grow: 0.,
+            basis: Some(16. * font.size),
+            ..Default::default()
+        })
+        .into()
+}
+
+/// A line of text in a list
+pub fn menu_label(font: &TextBoxFont, text: &str) -> WidgetNode {
+    make_widget!(rich_text)
+        .with_props(RichTextProps { text: text.into() })
+        .merge_props(label_props(font))
+        .into()
+}
+
+/// Rich text in a list that is revealed by the typewriter with the given id in the
+/// [`Typewriters`](super::Typewriters), like what an NPC is saying
+pub fn menu_typewriter(font: &TextBoxFont, text_id: &str) -> WidgetNode {
+    make_widget!(typewriter_text)
+        .with_props(TypewriterTextProps { id: text_id.into() })
+        .merge_props(label_props(font))
+        .into()
+}
+
+/// The props shared by [`menu_label`] and [`menu_typewriter`]
+fn label_props(font: &TextBoxFont) -> Props {
+    Props::new(TextBoxProps {
+        font: font.clone(),
+        color: Color {
+            r: 0.,
+            g: 0.,
+            b: 0.,
+            a: 1.,
+        },
+        ..Default::default()
+    })
+    .with(FlexBoxItemLayout {
+        margin: Rect {
+            left: 5.,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+/// A button at the bottom of a menu page that sends `message_name` to the widget with `notify_id`
+pub fn menu_button(text: &str, notify_id: &WidgetId, message_name: &str) -> WidgetNode {
+    make_widget!(game_button)
+        .with_props(FlexBoxItemLayout {
+            align: 0.5,
+            grow: 0.0,
+            margin: Rect {
+                top: 5.,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with_props(GameButtonProps {
+            text: text.into(),
+            notify_id: notify_id.to_owned(),
+            message_name: message_name.into(),
+        })
+        .into()
+}
@@ -0,0 +1,470 @@
+use std::collections::HashMap;
+
+use bevy::prelude::{AssetServer, Assets, Handle, Query, Res, ResMut, World};
+use bevy_retrograde::ui::raui::prelude::*;
+
+use crate::plugins::game::{
+    assets::{FontMetrics, GameInfo},
+    components::Character,
+    level_state::LevelStates,
+};
+
+/// The width of a character used to lay out rich text in fonts that don't have [`FontMetrics`]
+const FALLBACK_CHARACTER_WIDTH: u32 = 6;
+/// The line height used to lay out rich text in fonts that don't have [`FontMetrics`]
+const FALLBACK_LINE_HEIGHT: u32 = 8;
+/// The width of the spacer used to force a line break, wider than any text box
+const LINE_BREAK_WIDTH: f32 = 100_000.;
+
+/// Resource containing the values that can be inserted into rich text with `{variable-name}`
+#[derive(Default)]
+pub struct TextVariables(pub HashMap<String, String>);
+
+/// Keep the built-in text variables, such as `player-name`, up-to-date
+pub fn update_text_variables(
+    mut variables: ResMut<TextVariables>,
+    characters: Query<&Handle<Character>>,
+    character_assets: Res<Assets<Character>>,
+) {
+    let player_name = characters
+        .iter()
+        .next()
+        .and_then(|x| character_assets.get(x))
+        .map(|x| x.name.clone());
+
+    // Avoid triggering change detection if the name hasn't changed
+    if let Some(player_name) = player_name {
+        if variables.0.get("player-name") != Some(&player_name) {
+            variables.0.insert("player-name".into(), player_name);
+        }
+    }
+}
+
+/// The values that rich text markup is resolved against
+pub struct RichTextSources<'a> {
+    /// The [`TextVariables`]
+    pub variables: &'a HashMap<String, String>,
+    /// The UI theme's item icons
+    pub icons: &'a HashMap<String, String>,
+    /// The game's localization table
+    pub localization: &'a HashMap<String, String>,
+    /// The state of the world, which has the flags set on map entities
+    pub level_states: &'a LevelStates,
+}
+
+/// A piece of rich text
+#[derive(Debug, Clone, PartialEq)]
+pub enum RichTextSpan {
+    /// Text with an optional color, overriding the text box color
+    Text { text: String, color: Option<Color> },
+    /// An inline icon image
+    Icon(String),
+}
+
+/// A single markup token at the start of some rich text source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarkupToken {
+    /// The number of characters in the token
+    pub chars: usize,
+    /// Whether or not the token only changes formatting, and so doesn't show anything itself
+    pub formatting: bool,
+}
+
+/// Get the first markup token in some rich text source, i.e. a single character or a whole
+/// `{tag}`, so that the typewriter effect doesn't reveal tags one character at a time
+pub fn next_markup_token(text: &str) -> Option<MarkupToken> {
+    if text.starts_with("{{") {
+        return Some(MarkupToken {
+            chars: 2,
+            formatting: false,
+        });
+    }
+
+    if let Some(after) = text.strip_prefix('{') {
+        if let Some(end) = after.find('}') {
+            let tag = &after[..end];
+            return Some(MarkupToken {
+                chars: tag.chars().count() + 2,
+                formatting: tag.starts_with("color=") || tag == "/color",
+            });
+        }
+    }
+
+    text.chars().next().map(|_| MarkupToken {
+        chars: 1,
+        formatting: false,
+    })
+}
+
+/// Parse rich text markup into spans
+///
+/// The supported markup is:
+///
+/// - `{color=red}` or `{color=#ff0000}`: Change the text color until the next `{/color}`
+/// - `{item-icon:key}`: Show the icon set for `key` in the UI theme's `item-icons`
+/// - `{variable-name}`: Insert the value of a [`TextVariables`] entry, such as `{player-name}`
+/// - `{text:key}`: Insert the entry for `key` in the game's localization table
+/// - `{flag:level/entity/flag|if set|if not set}`: Insert the first text if the entity with the
+///   persistent ID `entity` in `level` has the flag set in the [`LevelStates`], or the second text
+///   if it doesn't. The second text can be left out.
+/// - `{{`: Insert a literal `{`
+///
+/// Tags that can't be resolved are shown as-is.
+pub fn parse_rich_text(text: &str, sources: &RichTextSources) -> Vec<RichTextSpan> {
+    let mut spans = Vec::new();
+    let mut color = None;
+    let mut current = String::new();
+
+    // Add the text collected so far as a span
+    fn flush(spans: &mut Vec<RichTextSpan>, current: &mut String, color: Option<Color>) {
+        if !current.is_empty() {
+            spans.push(RichTextSpan::Text {
+                text: std::mem::take(current),
+                color,
+            });
+        }
+    }
+
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        current.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        // Escaped brace
+        if let Some(after) = after.strip_prefix('{') {
+            current.push('{');
+            rest = after;
+            continue;
+        }
+
+        // Unclosed tag
+        let end = if let Some(end) = after.find('}') {
+            end
+        } else {
+            current.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+
+        let tag = &after[..end];
+        rest = &after[end + 1..];
+
+        if let Some(new_color) = tag.strip_prefix("color=").and_then(parse_color) {
+            flush(&mut spans, &mut current, color);
+            color = Some(new_color);
+        } else if tag == "/color" {
+            flush(&mut spans, &mut current, color);
+            color = None;
+        } else if let Some(icon) = tag
+            .strip_prefix("item-icon:")
+            .and_then(|key| sources.icons.get(key))
+        {
+            flush(&mut spans, &mut current, color);
+            spans.push(RichTextSpan::Icon(icon.clone()));
+        } else if let Some(value) = tag
+            .strip_prefix("text:")
+            .and_then(|key| sources.localization.get(key))
+        {
+            current.push_str(value);
+        } else if let Some(value) = tag
+            .strip_prefix("flag:")
+            .and_then(|x| resolve_flag(x, sources.level_states))
+        {
+            current.push_str(value);
+        } else if let Some(value) = sources.variables.get(tag) {
+            current.push_str(value);
+        } else {
+            current.push('{');
+            current.push_str(tag);
+            current.push('}');
+        }
+    }
+    current.push_str(rest);
+    flush(&mut spans, &mut current, color);
+
+    spans
+}
+
+/// Get the text to insert for a `{flag:level/entity/flag|if set|if not set}` tag, given the part
+/// after `flag:`
+fn resolve_flag<'a>(tag: &'a str, level_states: &LevelStates) -> Option<&'a str> {
+    let mut parts = tag.splitn(3, '|');
+    let mut path = parts.next()?.splitn(3, '/');
+    let (level, entity, flag) = (path.next()?, path.next()?, path.next()?);
+    let if_set = parts.next()?;
+    let if_not_set = parts.next().unwrap_or("");
+
+    let is_set = level_states
+        .entity(level, entity)
+        .map_or(false, |x| x.flags.contains(flag));
+    Some(if is_set { if_set } else { if_not_set })
+}
+
+/// Parse a named color or a hex color in the `#rrggbb` format
+fn parse_color(color: &str) -> Option<Color> {
+    let rgb = |r, g, b| Color { r, g, b, a: 1. };
+
+    Some(match color {
+        "white" => rgb(1., 1., 1.),
+        "black" => rgb(0., 0., 0.),
+        "gray" | "grey" => rgb(0.5, 0.5, 0.5),
+        "red" => rgb(0.85, 0.15, 0.15),
+        "green" => rgb(0.2, 0.7, 0.2),
+        "blue" => rgb(0.2, 0.35, 0.9),
+        "yellow" => rgb(0.95, 0.85, 0.2),
+        hex => {
+            let decoded = hex::decode(hex.strip_prefix('#')?).ok()?;
+            if decoded.len() != 3 {
+                return None;
+            }
+            rgb(
+                decoded[0] as f32 / 255.,
+                decoded[1] as f32 / 255.,
+                decoded[2] as f32 / 255.,
+            )
+        }
+    })
+}
+
+/// The props for a [`rich_text`] widget
+#[derive(PropsData, Debug, Clone, serde::Deserialize, serde::Serialize, Default)]
+pub struct RichTextProps {
+    /// The rich text markup to render
+    pub text: String,
+}
+
+/// A text box that renders the rich text markup in its [`RichTextProps`]. The font and default
+/// color are taken from the [`TextBoxProps`] in the widget props, and the text is scaled by the
+/// font size, i.e. the player's text size setting for a font from [`ui_font`](super::ui_font).
+/// The horizontal alignment applies to the lines started with `\n`, but not to the lines the text
+/// wraps onto.
+pub fn rich_text(ctx: WidgetContext) -> WidgetNode {
+    let RichTextProps { text } = ctx.props.read_cloned_or_default();
+    let text_box_props: TextBoxProps = ctx.props.read_cloned_or_default();
+
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let game_info = world.get_resource::<GameInfo>().unwrap();
+    let empty = HashMap::default();
+    let variables = world
+        .get_resource::<TextVariables>()
+        .map(|x| &x.0)
+        .unwrap_or(&empty);
+    let level_states = world.get_resource::<LevelStates>().unwrap();
+    let spans = parse_rich_text(
+        &text,
+        &RichTextSources {
+            variables,
+            icons: &game_info.ui_theme.item_icons,
+            localization: &game_info.localization,
+            level_states,
+        },
+    );
+    let scale = text_box_props.font.size;
+
+    // Get the font metrics if the font has them
+    let metrics = world
+        .get_resource::<AssetServer>()
+        .and_then(|asset_server| {
+            let handle: Handle<FontMetrics> = asset_server
+                .get_handle(format!("{}#FontMetrics", text_box_props.font.name).as_str());
+            world
+                .get_resource::<Assets<FontMetrics>>()
+                .and_then(|x| x.get(handle))
+        });
+    let line_height = metrics
+        .map(|x| x.line_height)
+        .unwrap_or(FALLBACK_LINE_HEIGHT) as f32;
+    let text_width = |text: &str| {
+        metrics
+            .map(|x| x.text_width(text))
+            .unwrap_or(text.chars().count() as u32 * FALLBACK_CHARACTER_WIDTH) as f32
+    };
+
+    // Center or right-align each line by adding spacers that grow to fill the rest of the line
+    let align_space = || -> WidgetNode {
+        make_widget!(space_box)
+            .with_props(FlexBoxItemLayout {
+                basis: Some(0.),
+                grow: 1.,
+                shrink: 0.,
+                ..Default::default()
+            })
+            .into()
+    };
+    let (space_before, space_after) = match text_box_props.horizontal_align {
+        TextBoxHorizontalAlign::Left => (false, false),
+        TextBoxHorizontalAlign::Center => (true, true),
+        TextBoxHorizontalAlign::Right => (true, false),
+    };
+
+    // Split the spans into words so that the flex box can wrap them
+    let mut items = Vec::new();
+    if space_before {
+        items.push(align_space());
+    }
+    for span in spans {
+        match span {
+            RichTextSpan::Text { text, color } => {
+                for (i, line) in text.split('\n').enumerate() {
+                    // Start a new line by adding a spacer too wide to share a line with anything
+                    if i > 0 {
+                        if space_after {
+                            items.push(align_space());
+                        }
+                        items.push(
+                            make_widget!(space_box)
+                                .with_props(FlexBoxItemLayout {
+                                    basis: Some(LINE_BREAK_WIDTH),
+                                    grow: 0.,
+                                    shrink: 0.,
+                                    ..Default::default()
+                                })
+                                .into(),
+                        );
+                        if space_before {
+                            items.push(align_space());
+                        }
+                    }
+
+                    for word in line.split_inclusive(' ') {
+                        let width = text_width(word);
+                        // Reserve room for the scaled text
+                        items.push(
+                            make_widget!(size_box)
+                                .with_props(SizeBoxProps {
+                                    width: SizeBoxSizeValue::Exact(width * scale),
+                                    height: SizeBoxSizeValue::Exact(line_height * scale),
+                                    ..Default::default()
+                                })
+                                .with_props(FlexBoxItemLayout {
+                                    basis: Some(width * scale),
+                                    grow: 0.,
+                                    shrink: 0.,
+                                    ..Default::default()
+                                })
+                                .named_slot(
+                                    "content",
+                                    make_widget!(text_box).with_props(TextBoxProps {
+                                        text: word.into(),
+                                        width: TextBoxSizeValue::Exact(width),
+                                        height: TextBoxSizeValue::Exact(line_height),
+                                        color: color.unwrap_or(text_box_props.color),
+                                        font: TextBoxFont {
+                                            name: text_box_props.font.name.clone(),
+                                            size: 1.,
+                                        },
+                                        // Scale the text up from its top-left corner
+                                        transform: Transform {
+                                            scale: Vec2::from(scale),
+                                            ..Default::default()
+                                        },
+                                        ..Default::default()
+                                    }),
+                                )
+                                .into(),
+                        );
+                    }
+                }
+            }
+            RichTextSpan::Icon(image) => items.push(
+                make_widget!(image_box)
+                    .with_props(ImageBoxProps {
+                        material: ImageBoxMaterial::Image(ImageBoxImage {
+                            id: image,
+                            ..Default::default()
+                        }),
+                        width: ImageBoxSizeValue::Exact(line_height * scale),
+                        height: ImageBoxSizeValue::Exact(line_height * scale),
+                        ..Default::default()
+                    })
+                    .with_props(FlexBoxItemLayout {
+                        basis: Some(line_height * scale),
+                        grow: 0.,
+                        shrink: 0.,
+                        ..Default::default()
+                    })
+                    .into(),
+            ),
+        }
+    }
+
+    if space_after {
+        items.push(align_space());
+    }
+
+    make_widget!(flex_box)
+        .merge_props(ctx.props.clone())
+        .with_props(FlexBoxProps {
+            direction: FlexBoxDirection::HorizontalLeftToRight,
+            wrap: true,
+            ..Default::default()
+        })
+        .listed_slots(items)
+        .into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_rich_text() {
+        let mut variables = HashMap::default();
+        variables.insert("player-name".to_string(), "Zoe".to_string());
+        let mut icons = HashMap::default();
+        icons.insert("key".to_string(), "icons/key.png".to_string());
+        let mut localization = HashMap::default();
+        localization.insert("greeting".to_string(), "Hello".to_string());
+        let mut level_states = LevelStates::default();
+        level_states
+            .entity_mut("Level_0", "chest")
+            .flags
+            .insert("opened".into());
+        let sources = RichTextSources {
+            variables: &variables,
+            icons: &icons,
+            localization: &localization,
+            level_states: &level_states,
+        };
+
+        assert_eq!(
+            parse_rich_text(
+                "Hi {player-name}, take {color=red}this{/color} {item-icon:key}",
+                &sources
+            ),
+            vec![
+                RichTextSpan::Text {
+                    text: "Hi Zoe, take ".into(),
+                    color: None
+                },
+                RichTextSpan::Text {
+                    text: "this".into(),
+                    color: parse_color("red")
+                },
+                RichTextSpan::Text {
+                    text: " ".into(),
+                    color: None
+                },
+                RichTextSpan::Icon("icons/key.png".into()),
+            ]
+        );
+        assert_eq!(
+            parse_rich_text(
+                "{text:greeting}! {flag:Level_0/chest/opened|Empty|Full} \
+                {flag:Level_0/door/opened|Open|Shut}{flag:Level_1/chest/opened|Gone}",
+                &sources
+            ),
+            vec![RichTextSpan::Text {
+                text: "Hello! Empty Shut".into(),
+                color: None
+            }]
+        );
+        assert_eq!(
+            parse_rich_text("{{unknown} {nope", &sources),
+            vec![RichTextSpan::Text {
+                text: "{unknown} {nope".into(),
+                color: None
+            }]
+        );
+    }
+}
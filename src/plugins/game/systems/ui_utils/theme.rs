@@ -0,0 +1,45 @@
+use bevy::prelude::{Query, Res, ResMut};
+use bevy_retrograde::prelude::UiTree;
+
+use crate::plugins::game::{assets::GameInfo, components::CurrentLevel, map::GameMap};
+
+/// Resource for the UI theme variant used for every screen, set from the current level's
+/// `ui_theme` field
+///
+/// When there isn't one, each screen uses the variant set for it in the theme's `screens`.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct UiThemeVariant(pub Option<String>);
+
+/// Switch the UI theme variant when the player enters a level with a different `ui_theme` field
+pub fn update_ui_theme_variant(
+    mut variant: ResMut<UiThemeVariant>,
+    mut ui_tree: ResMut<UiTree>,
+    maps: Query<&GameMap>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+) {
+    let (current_level, game_info) = match (current_level, game_info) {
+        (Some(level), Some(game_info)) if level.is_changed() => (level, game_info),
+        _ => return,
+    };
+    let level = if let Some(level) = maps.single().ok().and_then(|x| x.level(&current_level.0)) {
+        level
+    } else {
+        return;
+    };
+
+    // Ignore variants that the theme doesn't have
+    let new_variant = UiThemeVariant(
+        level
+            .field::<String>("ui_theme")
+            .filter(|x| game_info.ui_theme.variants.contains_key(x)),
+    );
+
+    // Avoid triggering change detection if the variant hasn't changed
+    if *variant != new_variant {
+        *variant = new_variant;
+
+        // Apply the UI tree again, which renders every widget with the new theme
+        *ui_tree = UiTree(ui_tree.0.clone());
+    }
+}
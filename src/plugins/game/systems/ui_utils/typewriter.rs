@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use bevy::prelude::{AssetServer, Res, ResMut, Time, World};
+use bevy_retrograde::{audio::SoundController, ui::raui::prelude::*};
+
+use super::{next_markup_token, rich_text, RichTextProps};
+use crate::{plugins::game::settings::UserSettings, EngineConfig};
+
+/// A block of rich text that is revealed one character at a time
+#[derive(Debug, Clone)]
+pub struct Typewriter {
+    /// The full rich text markup
+    pub text: String,
+    /// The number of characters of the markup that have been revealed
+    pub revealed: usize,
+    /// The fraction of the next character that has been revealed
+    pub progress: f32,
+    /// The sound to play for every character revealed
+    pub tick_sound: Option<String>,
+}
+
+impl Typewriter {
+    /// Whether or not all of the text has been revealed
+    pub fn is_finished(&self) -> bool {
+        self.revealed >= self.text.chars().count()
+    }
+
+    /// The part of the markup that has been revealed
+    pub fn revealed_text(&self) -> &str {
+        match self.text.char_indices().nth(self.revealed) {
+            Some((idx, _)) => &self.text[..idx],
+            None => &self.text,
+        }
+    }
+}
+
+/// Resource containing the typewriter text currently being revealed, by the id used to display it
+/// with a [`typewriter_text`] widget
+#[derive(Default)]
+pub struct Typewriters(pub HashMap<String, Typewriter>);
+
+impl Typewriters {
+    /// Start revealing text for the given id, replacing any text that was already there
+    pub fn start(&mut self, id: &str, text: &str, tick_sound: Option<String>) {
+        self.0.insert(
+            id.into(),
+            Typewriter {
+                text: text.into(),
+                revealed: 0,
+                progress: 0.,
+                tick_sound,
+            },
+        );
+    }
+
+    /// Reveal the rest of the text for the given id immediately, i.e. when the player presses a
+    /// button to skip ahead
+    pub fn skip(&mut self, id: &str) {
+        if let Some(typewriter) = self.0.get_mut(id) {
+            typewriter.revealed = typewriter.text.chars().count();
+        }
+    }
+
+    /// Whether or not the text for the given id has been fully revealed. Returns `true` if there
+    /// is no text for the id.
+    pub fn is_finished(&self, id: &str) -> bool {
+        self.0.get(id).map(|x| x.is_finished()).unwrap_or(true)
+    }
+
+    /// Stop showing the text for the given id
+    pub fn remove(&mut self, id: &str) {
+        self.0.remove(id);
+    }
+}
+
+/// Reveal typewriter text at the speed set in the [`UserSettings`]
+pub fn update_typewriters(
+    mut typewriters: ResMut<Typewriters>,
+    settings: Res<UserSettings>,
+    asset_server: Res<AssetServer>,
+    mut sound_controller: SoundController,
+    time: Res<Time>,
+    engine_config: Res<EngineConfig>,
+) {
+    // Avoid triggering change detection if there is nothing to reveal
+    if typewriters.0.values().all(|x| x.is_finished()) {
+        return;
+    }
+
+    for typewriter in typewriters.0.values_mut() {
+        if typewriter.is_finished() {
+            continue;
+        }
+
+        let characters_per_second = match settings.text_speed.characters_per_second() {
+            Some(speed) => speed,
+            None => {
+                // Show the whole text at once
+                typewriter.revealed = typewriter.text.chars().count();
+                continue;
+            }
+        };
+
+        typewriter.progress += time.delta_seconds() * characters_per_second;
+
+        // Reveal the characters we have made it past, revealing markup tags all at once
+        let mut ticked = false;
+        loop {
+            let rest = &typewriter.text[typewriter.revealed_text().len()..];
+            let token = if let Some(token) = next_markup_token(rest) {
+                token
+            } else {
+                break;
+            };
+
+            // Formatting tags are revealed for free
+            if !token.formatting {
+                if typewriter.progress < 1. {
+                    break;
+                }
+                typewriter.progress -= 1.;
+                ticked |= !rest.starts_with(char::is_whitespace);
+            }
+
+            typewriter.revealed += token.chars;
+        }
+
+        // Play at most one tick per frame so that fast text doesn't pile up sounds
+        if ticked && !engine_config.mute {
+            if let Some(tick_sound) = &typewriter.tick_sound {
+                let sound_data = asset_server.load_cached(tick_sound.as_str());
+                let sound = sound_controller.create_sound(&sound_data);
+                sound_controller.play_sound(sound);
+            }
+        }
+    }
+}
+
+/// The props for a [`typewriter_text`] widget
+#[derive(PropsData, Debug, Clone, serde::Deserialize, serde::Serialize, Default)]
+pub struct TypewriterTextProps {
+    /// The id of the text in the [`Typewriters`] resource
+    pub id: String,
+}
+
+/// A [`rich_text`] widget that shows the revealed part of the typewriter text with the id in its
+/// [`TypewriterTextProps`]. The text is styled with the [`TextBoxProps`] in the widget props.
+pub fn typewriter_text(ctx: WidgetContext) -> WidgetNode {
+    let TypewriterTextProps { id } = ctx.props.read_cloned_or_default();
+
+    let world: &mut World = ctx.process_context.get_mut().unwrap();
+    let text = world
+        .get_resource::<Typewriters>()
+        .and_then(|x| x.0.get(&id))
+        .map(|x| x.revealed_text().to_owned())
+        .unwrap_or_default();
+
+    make_widget!(rich_text)
+        .merge_props(ctx.props.clone())
+        .with_props(RichTextProps { text })
+        .into()
+}
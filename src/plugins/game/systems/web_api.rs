@@ -0,0 +1,154 @@
+use super::{game_init::start_game, *};
+use crate::web_api::{emit_game_event, take_commands, WebCommand};
+
+/// A level that the page hosting the game asked to load, waiting for the game to be somewhere it
+/// can be loaded from
+#[derive(Default)]
+pub struct PendingLevelLoad(Option<String>);
+
+/// Handle the commands sent from the page hosting the game
+pub fn handle_web_commands(world: &mut World) {
+    let state = world
+        .get_resource::<State<GameState>>()
+        .unwrap()
+        .current()
+        .clone();
+
+    for command in take_commands() {
+        match command {
+            WebCommand::Pause => {
+                if state == GameState::Playing {
+                    world.get_resource_mut::<PauseRequest>().unwrap().0 = Some(true);
+                }
+            }
+            WebCommand::Resume => {
+                if state == GameState::Paused {
+                    world.get_resource_mut::<PauseRequest>().unwrap().0 = Some(false);
+                }
+            }
+            WebCommand::LoadLevel(level) => {
+                world.get_resource_mut::<PendingLevelLoad>().unwrap().0 = Some(level);
+            }
+            WebCommand::SetEngineOption { name, value } => {
+                let mut engine_config = world.get_resource_mut::<crate::EngineConfig>().unwrap();
+                if let Err(e) = engine_config.set_option(&name, &value) {
+                    warn!(%name, %value, %e, "Could not set engine option");
+                }
+            }
+            WebCommand::ImportLeaderboard(json) => import_leaderboard(world, &json),
+        }
+    }
+
+    // Start the game in the level from the start menu, or move the player to it during gameplay
+    match state {
+        GameState::StartMenu | GameState::Playing => (),
+        _ => return,
+    }
+    let level = if let Some(level) = world
+        .get_resource_mut::<PendingLevelLoad>()
+        .unwrap()
+        .0
+        .take()
+    {
+        level
+    } else {
+        return;
+    };
+    if state == GameState::StartMenu {
+        world
+            .get_resource_mut::<crate::EngineConfig>()
+            .unwrap()
+            .start_level = Some(level);
+        start_game(world);
+    } else {
+        world
+            .get_resource_mut::<Events<LoadLevelEvent>>()
+            .unwrap()
+            .send(LoadLevelEvent { level });
+    }
+}
+
+/// Send game state and level changes to the page hosting the game
+pub fn send_web_game_events(
+    mut last_state: Local<Option<GameState>>,
+    mut last_level: Local<Option<String>>,
+    state: Res<State<GameState>>,
+    current_level: Option<Res<CurrentLevel>>,
+) {
+    let current_state = state.current();
+    if last_state.as_ref() != Some(current_state) {
+        *last_state = Some(current_state.clone());
+        emit_game_event("state-changed", &[("state", state_name(current_state))]);
+
+        // Send the level again when the game is started over
+        if current_state == &GameState::StartMenu {
+            *last_level = None;
+        }
+    }
+
+    // Only send the level once the player is in it
+    if current_state != &GameState::Playing {
+        return;
+    }
+    if let Some(level) = current_level {
+        if last_level.as_deref() != Some(level.as_str()) {
+            *last_level = Some(level.as_str().into());
+            emit_game_event("level-changed", &[("level", level.as_str())]);
+        }
+    }
+}
+
+/// Add the scores and best times of an exported leaderboard to the player's
+fn import_leaderboard(world: &mut World, json: &str) {
+    let leaderboard = match serde_json::from_str::<LocalLeaderboard>(json) {
+        Ok(leaderboard) => leaderboard,
+        Err(e) => {
+            warn!(%e, "Could not import leaderboard");
+            return;
+        }
+    };
+    let max_scores = world
+        .get_resource::<GameInfo>()
+        .and_then(|x| x.score.as_ref())
+        .map_or(ScoreConfig::default().high_scores, |x| x.high_scores);
+
+    let world = world.cell();
+    leaderboard.merge_into(
+        &mut world.get_resource_mut::<HighScores>().unwrap(),
+        &mut world.get_resource_mut::<BestTimes>().unwrap(),
+        max_scores,
+    );
+}
+
+/// Send the player's high scores and best times to the page hosting the game when they change, so
+/// that it can keep them
+pub fn send_web_leaderboard_events(high_scores: Res<HighScores>, best_times: Res<BestTimes>) {
+    if !(high_scores.is_changed() || best_times.is_changed()) {
+        return;
+    }
+
+    let leaderboard = LocalLeaderboard {
+        high_scores: high_scores.clone(),
+        best_times: best_times.clone(),
+    };
+    match serde_json::to_string(&leaderboard) {
+        Ok(json) => emit_game_event("leaderboard-changed", &[("leaderboard", &json)]),
+        Err(e) => warn!(%e, "Could not export leaderboard"),
+    }
+}
+
+/// The name of a game state used in game events
+fn state_name(state: &GameState) -> &'static str {
+    match state {
+        GameState::Init => "init",
+        GameState::Intro => "intro",
+        GameState::StartMenu => "start-menu",
+        GameState::LoadingGame => "loading-game",
+        GameState::Playing => "playing",
+        GameState::Paused => "paused",
+        GameState::PhotoMode => "photo-mode",
+        GameState::Battle => "battle",
+        GameState::GameOver => "game-over",
+        GameState::Error => "error",
+    }
+}
@@ -0,0 +1,80 @@
+use super::*;
+
+/// Set the window icon on desktop, or the page favicon on web, to the game's `window-icon` once it
+/// has loaded
+pub fn update_window_icon(
+    mut applied_icon: Local<Option<String>>,
+    game_info: Option<Res<GameInfo>>,
+    asset_server: Res<AssetServer>,
+    image_assets: Res<Assets<Image>>,
+    #[cfg(not(wasm))] windows: Res<Windows>,
+    #[cfg(not(wasm))] winit_windows: NonSend<bevy::winit::WinitWindows>,
+) {
+    let icon_path = if let Some(path) = game_info.as_ref().and_then(|x| x.window_icon.as_ref()) {
+        path
+    } else {
+        return;
+    };
+
+    // Only set the icon again if it has been changed in the game info
+    if applied_icon.as_ref() == Some(icon_path) {
+        return;
+    }
+
+    let image_handle: Handle<Image> = asset_server.load_cached(icon_path.as_str());
+    let image = if let Some(image) = image_assets.get(image_handle) {
+        image
+    } else {
+        return;
+    };
+
+    #[cfg(not(wasm))]
+    {
+        let window = if let Some(window) = windows
+            .get_primary()
+            .and_then(|x| winit_windows.get_window(x.id()))
+        {
+            window
+        } else {
+            return;
+        };
+
+        let rgba = image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        match winit::window::Icon::from_rgba(rgba.into_raw(), width, height) {
+            Ok(icon) => window.set_window_icon(Some(icon)),
+            Err(e) => warn!(%e, %icon_path, "Could not set window icon"),
+        }
+    }
+
+    #[cfg(wasm)]
+    set_favicon(image);
+
+    *applied_icon = Some(icon_path.clone());
+}
+
+/// Set the page favicon to the given image, encoded as a data URL so that it works no matter
+/// where the assets were loaded from
+#[cfg(wasm)]
+fn set_favicon(image: &Image) {
+    let mut png = Vec::new();
+    if let Err(e) = image.write_to(
+        &mut png,
+        bevy_retrograde::core::image::ImageOutputFormat::Png,
+    ) {
+        warn!(%e, "Could not encode favicon");
+        return;
+    }
+    let url = format!("data:image/png;base64,{}", base64::encode(&png));
+
+    let document = web_sys::window().unwrap().document().unwrap();
+    let link = if let Some(link) = document.query_selector("link[rel~='icon']").unwrap() {
+        link
+    } else {
+        let link = document.create_element("link").unwrap();
+        link.set_attribute("rel", "icon").unwrap();
+        document.head().unwrap().append_child(&link).unwrap();
+        link
+    };
+    link.set_attribute("href", &url).unwrap();
+}
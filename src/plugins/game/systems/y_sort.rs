@@ -0,0 +1,37 @@
+use super::*;
+
+/// Set the z position of [`YSort`] entities from their y position in the current level
+pub fn y_sort(
+    mut sorted: Query<(&YSort, &mut Transform)>,
+    maps: Query<&GameMap>,
+    current_level: Option<Res<CurrentLevel>>,
+    game_info: Option<Res<GameInfo>>,
+) {
+    let game_info = if let Some(game_info) = game_info {
+        game_info
+    } else {
+        return;
+    };
+    let level = if let Some(level) = current_level.and_then(|current_level| {
+        maps.single()
+            .ok()
+            .and_then(|map| map.level(current_level.as_str()))
+    }) {
+        level
+    } else {
+        return;
+    };
+
+    // Keep the entities just inside the band so that they never reach the top of it
+    let band = game_info.y_sort.band.max(0.) * 0.99;
+
+    for (y_sort, mut transform) in sorted.iter_mut() {
+        let y = (transform.translation.y - level.position.y) / level.size.y.max(1) as f32;
+        let z = y_sort.base_z + y.clamp(0., 1.) * band;
+
+        // Only set the z when it changes to avoid triggering change detection
+        if transform.translation.z != z {
+            transform.translation.z = z;
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Scaffolding for new games, used by the `new` command
+
+use std::path::Path;
+
+use bevy_retrograde::core::image::{Rgba, RgbaImage};
+
+/// The text files in the new game template, by their path in the asset directory
+const TEMPLATE_FILES: &[(&str, &str)] = &[
+    (
+        "default.game.yaml",
+        include_str!("scaffold/default.game.yaml"),
+    ),
+    (
+        "player/player.character.yaml",
+        include_str!("scaffold/player/player.character.yaml"),
+    ),
+    ("maps/start.tmx", include_str!("scaffold/maps/start.tmx")),
+    (
+        "fonts/default.font.yaml",
+        include_str!("scaffold/fonts/default.font.yaml"),
+    ),
+];
+
+/// The placeholder images in the new game template, by their path in the asset directory, with
+/// their size and color
+const TEMPLATE_IMAGES: &[(&str, (u32, u32), [u8; 4])] = &[
+    ("player/player.png", (16, 16), [0x3b, 0x5d, 0xc9, 0xff]),
+    ("player/collision.png", (16, 16), [0xff, 0xff, 0xff, 0xff]),
+    ("ui/splash.png", (128, 32), [0xf4, 0xf4, 0xf4, 0xff]),
+    ("ui/panel.png", (16, 16), [0x33, 0x3c, 0x57, 0xff]),
+    ("ui/button-up.png", (16, 16), [0x56, 0x6c, 0x86, 0xff]),
+    ("ui/button-down.png", (16, 16), [0x29, 0x36, 0x6f, 0xff]),
+    ("ui/checked.png", (8, 8), [0x38, 0xb7, 0x64, 0xff]),
+    ("ui/unchecked.png", (8, 8), [0x94, 0xb0, 0xc2, 0xff]),
+    (
+        "ui/health-background.png",
+        (64, 16),
+        [0x1a, 0x1c, 0x2c, 0xff],
+    ),
+    ("ui/full-heart.png", (8, 8), [0xb1, 0x3e, 0x53, 0xff]),
+    ("ui/half-heart.png", (8, 8), [0xef, 0x7d, 0x57, 0xff]),
+];
+
+/// Create a new game asset directory with a template game that runs as-is, so that there is
+/// something to start editing from
+pub fn new_game(asset_dir: &Path) -> anyhow::Result<()> {
+    if asset_dir.exists() && asset_dir.read_dir()?.next().is_some() {
+        anyhow::bail!("`{}` already exists and is not empty", asset_dir.display());
+    }
+
+    for (path, contents) in TEMPLATE_FILES {
+        let path = asset_dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, contents)?;
+    }
+
+    for (path, (width, height), color) in TEMPLATE_IMAGES {
+        let path = asset_dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        RgbaImage::from_pixel(*width, *height, Rgba(*color)).save(path)?;
+    }
+
+    // Draw a box for every character in the font so that text shows up until a real font is
+    // drawn
+    let mut glyph_sheet = RgbaImage::new(16 * 6, 6 * 8);
+    for i in 1..95 {
+        let (cell_x, cell_y) = ((i % 16) * 6, (i / 16) * 8);
+        for x in 0..5 {
+            for y in 1..7 {
+                if x == 0 || x == 4 || y == 1 || y == 6 {
+                    glyph_sheet.put_pixel(cell_x + x, cell_y + y, Rgba([0xff; 4]));
+                }
+            }
+        }
+    }
+    glyph_sheet.save(asset_dir.join("fonts/default.png"))?;
+
+    // Use a second of silence for the menu music
+    let path = asset_dir.join("music/silence.wav");
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(path, silent_wav(44100))?;
+
+    Ok(())
+}
+
+/// Encode a mono, 16 bit WAV file with the given number of silent samples
+fn silent_wav(samples: u32) -> Vec<u8> {
+    let sample_rate: u32 = 44100;
+    let data_size = samples * 2;
+
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM format with one channel
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    // The byte rate, block alignment and bits per sample
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+    wav.resize(44 + data_size as usize, 0);
+
+    wav
+}
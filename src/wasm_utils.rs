@@ -19,6 +19,140 @@ pub fn get_log_config() -> LogSettings {
     }
 }
 
+/// Vibrate the device, if supported by the browser, for the given number of milliseconds. A
+/// duration of `0` stops the current vibration.
+#[cfg(wasm)]
+pub fn vibrate(duration_ms: u32) {
+    if let Some(window) = web_sys::window() {
+        window.navigator().vibrate_with_duration(duration_ms);
+    }
+}
+
+#[cfg(wasm)]
+thread_local! {
+    /// Whether the page was visible after each of its visibility changes since they were last taken
+    static PAGE_VISIBILITY_CHANGES: std::rc::Rc<std::cell::RefCell<Vec<bool>>> = {
+        use wasm_bindgen::{closure::Closure, JsCast};
+
+        let changes = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let document = web_sys::window().unwrap().document().unwrap();
+        let listener = {
+            let changes = changes.clone();
+            let document = document.clone();
+            Closure::wrap(Box::new(move || changes.borrow_mut().push(!document.hidden()))
+                as Box<dyn FnMut()>)
+        };
+        document
+            .add_event_listener_with_callback("visibilitychange", listener.as_ref().unchecked_ref())
+            .unwrap();
+        // The listener lasts for the rest of the page's life
+        listener.forget();
+
+        changes
+    };
+}
+
+/// Take the page visibility changes since this was last called, with `true` for each time the
+/// page was shown and `false` for each time it was hidden, i.e. by switching tabs
+///
+/// Changes are only recorded after the first time this is called.
+#[cfg(wasm)]
+pub fn take_page_visibility_changes() -> Vec<bool> {
+    PAGE_VISIBILITY_CHANGES.with(|changes| changes.borrow_mut().drain(..).collect())
+}
+
+/// Get the safe area insets of the screen in window pixels, as `[top, right, bottom, left]`, so
+/// that the UI can avoid notches and rounded corners
+///
+/// These are read from the `--safe-area-inset-*` CSS variables, which the page sets from the
+/// browser's `env(safe-area-inset-*)` values.
+#[cfg(wasm)]
+pub fn safe_area_insets() -> [f32; 4] {
+    let style = (|| {
+        let window = web_sys::window()?;
+        let root = window.document()?.document_element()?;
+        window.get_computed_style(&root).ok()?
+    })();
+
+    let mut insets = [0.; 4];
+    if let Some(style) = style {
+        for (inset, side) in insets
+            .iter_mut()
+            .zip(["top", "right", "bottom", "left"].iter())
+        {
+            let value = style
+                .get_property_value(&format!("--safe-area-inset-{}", side))
+                .unwrap_or_default();
+            *inset = value.trim().trim_end_matches("px").parse().unwrap_or(0.);
+        }
+    }
+
+    insets
+}
+
+/// Persistent storage for save data and settings, kept in the browser's local storage so that it
+/// lasts across sessions
+///
+/// Values are stored base64 encoded under keys prefixed with the storage's namespace, so that
+/// games hosted on the same site don't overwrite each-other's data.
+#[cfg(wasm)]
+#[derive(Clone, Debug)]
+pub struct Storage {
+    namespace: String,
+}
+
+#[cfg(wasm)]
+impl Storage {
+    /// Open the storage for the given namespace, usually the game title
+    pub fn new(namespace: &str) -> Self {
+        Self {
+            namespace: namespace.into(),
+        }
+    }
+
+    /// Describe where the storage is kept, i.e. for showing in the performance HUD
+    pub fn location(&self) -> String {
+        format!("local storage ( {} )", self.namespace)
+    }
+
+    /// Get the value stored under a key, if there is one
+    pub async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let value = local_storage()?
+            .get_item(&self.storage_key(key))
+            .map_err(|e| anyhow::format_err!("Could not read local storage: {:?}", e))?;
+
+        Ok(match value {
+            Some(value) => Some(base64::decode(value)?),
+            None => None,
+        })
+    }
+
+    /// Store a value under a key, replacing any existing value
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> anyhow::Result<()> {
+        local_storage()?
+            .set_item(&self.storage_key(key), &base64::encode(data))
+            .map_err(|e| anyhow::format_err!("Could not write local storage: {:?}", e))
+    }
+
+    /// Remove the value stored under a key, if there is one
+    pub async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        local_storage()?
+            .remove_item(&self.storage_key(key))
+            .map_err(|e| anyhow::format_err!("Could not remove from local storage: {:?}", e))
+    }
+
+    fn storage_key(&self, key: &str) -> String {
+        format!("{}/{}", self.namespace, key)
+    }
+}
+
+#[cfg(wasm)]
+fn local_storage() -> anyhow::Result<web_sys::Storage> {
+    web_sys::window()
+        .and_then(|x| x.local_storage().ok().flatten())
+        .ok_or_else(|| anyhow::format_err!("Local storage is not available"))
+}
+
 #[cfg(any(target_arch = "wasm32", test))]
 /// Parse the query string as returned by `web_sys::window()?.location().search()?` and get a
 /// specific key out of it.
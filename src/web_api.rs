@@ -0,0 +1,113 @@
+//! The JavaScript API for pages that embed the game
+//!
+//! These functions are exported from the game's JavaScript module, and the page that hosts the game
+//! can use them to control it, i.e. pausing the game when the tab is hidden, or to listen for game
+//! events, i.e. to record when a level is completed.
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+
+/// A command sent to the game from the hosting page
+#[derive(Debug, Clone)]
+pub enum WebCommand {
+    /// Pause the game, if the player is playing
+    Pause,
+    /// Resume the game, if it is paused
+    Resume,
+    /// Move the player to the given level, starting the game if it is on the start menu
+    LoadLevel(String),
+    /// Change an engine option
+    SetEngineOption { name: String, value: String },
+    /// Add the scores and best times of an exported leaderboard to the player's
+    ImportLeaderboard(String),
+}
+
+thread_local! {
+    /// Commands sent from JavaScript that haven't been handled yet
+    static COMMANDS: RefCell<Vec<WebCommand>> = Default::default();
+    /// The functions registered with `onGameEvent`
+    static EVENT_LISTENERS: RefCell<Vec<js_sys::Function>> = Default::default();
+}
+
+fn send(command: WebCommand) {
+    COMMANDS.with(|commands| commands.borrow_mut().push(command));
+}
+
+/// Pause the game
+#[wasm_bindgen]
+pub fn pause() {
+    send(WebCommand::Pause);
+}
+
+/// Resume the game after it has been paused
+#[wasm_bindgen]
+pub fn resume() {
+    send(WebCommand::Resume);
+}
+
+/// Mute or un-mute the game's sound
+#[wasm_bindgen(js_name = setMuted)]
+pub fn set_muted(muted: bool) {
+    send(WebCommand::SetEngineOption {
+        name: "mute".into(),
+        value: muted.to_string(),
+    });
+}
+
+/// Move the player to the `PlayerStart` spawn point of a level, i.e. `loadLevel("Level 2")`
+#[wasm_bindgen(js_name = loadLevel)]
+pub fn load_level(level: String) {
+    send(WebCommand::LoadLevel(level));
+}
+
+/// Change an engine option while the game is running, i.e. `setEngineOption("mute", "true")`
+///
+/// The options that can be changed are listed in
+/// [`EngineConfig::set_option`][crate::EngineConfig::set_option].
+#[wasm_bindgen(js_name = setEngineOption)]
+pub fn set_engine_option(name: String, value: String) {
+    send(WebCommand::SetEngineOption { name, value });
+}
+
+/// Add the high scores and best times from a leaderboard exported with the `leaderboard-changed`
+/// event to the player's, i.e. when moving them from another site
+#[wasm_bindgen(js_name = importLeaderboard)]
+pub fn import_leaderboard(json: String) {
+    send(WebCommand::ImportLeaderboard(json));
+}
+
+/// Register a function to be called with the name and details of every game event, i.e.
+/// `onGameEvent((name, detail) => console.log(name, detail))`
+///
+/// The events are:
+///
+/// - `state-changed`: the game state changed, with the new `state`, i.e. `"paused"`
+/// - `level-changed`: the player entered a `level`
+/// - `leaderboard-changed`: the player's high scores or best times changed, with the
+///   `leaderboard` as JSON, which can be given to `importLeaderboard`
+#[wasm_bindgen(js_name = onGameEvent)]
+pub fn on_game_event(callback: js_sys::Function) {
+    EVENT_LISTENERS.with(|listeners| listeners.borrow_mut().push(callback));
+}
+
+/// Take the commands sent from JavaScript since this was last called
+pub fn take_commands() -> Vec<WebCommand> {
+    COMMANDS.with(|commands| commands.borrow_mut().drain(..).collect())
+}
+
+/// Call the functions registered with `onGameEvent` with an event and its details
+pub fn emit_game_event(name: &str, detail: &[(&str, &str)]) {
+    let detail_object = js_sys::Object::new();
+    for (key, value) in detail {
+        js_sys::Reflect::set(&detail_object, &(*key).into(), &(*value).into()).unwrap();
+    }
+
+    EVENT_LISTENERS.with(|listeners| {
+        for listener in listeners.borrow().iter() {
+            if let Err(e) = listener.call2(&JsValue::NULL, &name.into(), &detail_object) {
+                bevy::log::warn!(?e, %name, "Game event listener threw an error");
+            }
+        }
+    });
+}